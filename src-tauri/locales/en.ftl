@@ -0,0 +1,32 @@
+# Errors, details are untranslated messages of the failing tool
+error-network = Network error: { $details }
+error-permission = Permission denied: { $details }
+error-child-process-failed = Child process failed with exit code { $code }
+error-cancelled = Operation cancelled
+error-timeout = Timed out: { $details }
+error-validation = Invalid input: { $details }
+error-not-found = Not found: { $details }
+error-io = I/O error: { $details }
+error-other = { $details }
+
+# Progress stages of toolchain installation
+stage-download = Downloading
+stage-extract = Extracting
+stage-skip = Already installed
+stage-done = Done
+
+# Install path findings
+path-not-absolute = Path is not absolute
+path-has-spaces = Path contains spaces, ESP-IDF and some build scripts do not quote it
+path-non-ascii = Path contains non-ASCII characters, Python and CMake tools may fail to open it
+path-too-long = Path has { $length } characters, installed files would exceed { $max } characters. Enable long paths or use shorter root
+
+# PATH conflicts
+conflict-shadowed = { $tool } from { $dir } shadows the one installed by rustup in { $expected }
+conflict-bundled = { $tool } is used from { $source } ({ $dir })
+conflict-copies = { $count } copies of { $tool } in PATH, { $path } is used
+
+# Fixes offered for known failures
+fix-missing-libssl = OpenSSL development files are missing, install them with the package manager
+fix-certificate-error = Certificate authorities are missing or outdated, install the CA certificate bundle
+fix-missing-linker = C compiler and linker are missing, install build tools with the package manager
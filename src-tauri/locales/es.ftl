@@ -0,0 +1,32 @@
+# Errores, los detalles son mensajes sin traducir de la herramienta que falló
+error-network = Error de red: { $details }
+error-permission = Permiso denegado: { $details }
+error-child-process-failed = El proceso hijo falló con el código de salida { $code }
+error-cancelled = Operación cancelada
+error-timeout = Tiempo agotado: { $details }
+error-validation = Entrada no válida: { $details }
+error-not-found = No encontrado: { $details }
+error-io = Error de E/S: { $details }
+error-other = { $details }
+
+# Etapas de la instalación de herramientas
+stage-download = Descargando
+stage-extract = Extrayendo
+stage-skip = Ya instalado
+stage-done = Terminado
+
+# Problemas de la ruta de instalación
+path-not-absolute = La ruta no es absoluta
+path-has-spaces = La ruta contiene espacios, ESP-IDF y algunos scripts de compilación no la entrecomillan
+path-non-ascii = La ruta contiene caracteres no ASCII, las herramientas de Python y CMake pueden no abrirla
+path-too-long = La ruta tiene { $length } caracteres, los archivos instalados superarían { $max } caracteres. Habilite las rutas largas o use una raíz más corta
+
+# Conflictos en PATH
+conflict-shadowed = { $tool } de { $dir } oculta el instalado por rustup en { $expected }
+conflict-bundled = { $tool } se usa desde { $source } ({ $dir })
+conflict-copies = { $count } copias de { $tool } en PATH, se usa { $path }
+
+# Soluciones para fallos conocidos
+fix-missing-libssl = Faltan los archivos de desarrollo de OpenSSL, instálalos con el gestor de paquetes
+fix-certificate-error = Faltan las autoridades de certificación o están desactualizadas, instala el paquete de certificados CA
+fix-missing-linker = Faltan el compilador de C y el enlazador, instala las herramientas de compilación con el gestor de paquetes
@@ -0,0 +1,162 @@
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use log::info;
+use tauri::State;
+
+use crate::app_state::AppState;
+use crate::error::{HelmError, HelmResult};
+use crate::esp_clang::export_file_path;
+use crate::inventory::rustup_home;
+use crate::rust::get_tool_version_xtensa;
+use crate::settings::save_settings;
+
+// Installation which was not made by esp-helm, but is managed by it after adoption
+#[derive(Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ExistingInstallation {
+    pub kind: String,
+    pub path: String,
+    pub version: Option<String>,
+    pub targets: Vec<String>,
+}
+
+// Map toolchain directory created by espup to supported chips
+fn targets_from_toolchain_dir(name: &str) -> Vec<&'static str> {
+    match name {
+        "xtensa-esp-elf" => vec!["esp32", "esp32s2", "esp32s3"],
+        "xtensa-esp32-elf" => vec!["esp32"],
+        "xtensa-esp32s2-elf" => vec!["esp32s2"],
+        "xtensa-esp32s3-elf" => vec!["esp32s3"],
+        "riscv32-esp-elf" => vec!["esp32c2", "esp32c3", "esp32c6", "esp32h2"],
+        _ => vec![],
+    }
+}
+
+fn detect_espup() -> Option<ExistingInstallation> {
+    let toolchain = rustup_home()?.join("toolchains").join("esp");
+    if !toolchain.exists() {
+        return None;
+    }
+
+    let mut targets: Vec<String> = vec![];
+    for entry in std::fs::read_dir(&toolchain).ok()?.filter_map(|e| e.ok()) {
+        let name = entry.file_name().to_string_lossy().to_string();
+        for target in targets_from_toolchain_dir(&name) {
+            if !targets.iter().any(|t| t == target) {
+                targets.push(target.to_string());
+            }
+        }
+    }
+
+    // Export file lists GCC binaries also for RISC-V targets
+    if let Some(export_file) = export_file_path() {
+        if let Ok(content) = std::fs::read_to_string(export_file) {
+            if content.contains("riscv32-esp-elf") && !targets.iter().any(|t| t == "esp32c3") {
+                targets.extend(
+                    targets_from_toolchain_dir("riscv32-esp-elf")
+                        .into_iter()
+                        .map(|t| t.to_string()),
+                );
+            }
+        }
+    }
+
+    Some(ExistingInstallation {
+        kind: "espup".into(),
+        path: toolchain.to_string_lossy().to_string(),
+        version: get_tool_version_xtensa("rustc", &["+esp", "--version"], Some("rustc")),
+        targets,
+    })
+}
+
+// Read version from tools/cmake/version.cmake, e.g. "set(IDF_VERSION_MAJOR 5)"
+fn esp_idf_version(path: &Path) -> Option<String> {
+    let content = std::fs::read_to_string(path.join("tools/cmake/version.cmake")).ok()?;
+    let part = |name: &str| -> Option<String> {
+        content
+            .lines()
+            .find(|line| line.contains(name))
+            .and_then(|line| line.split_whitespace().nth(1))
+            .map(|value| value.trim_end_matches(')').to_string())
+    };
+    Some(format!(
+        "v{}.{}.{}",
+        part("IDF_VERSION_MAJOR")?,
+        part("IDF_VERSION_MINOR")?,
+        part("IDF_VERSION_PATCH")?
+    ))
+}
+
+pub fn esp_idf_candidates() -> Vec<PathBuf> {
+    let mut candidates = vec![];
+    if let Some(idf_path) = std::env::var_os("IDF_PATH") {
+        candidates.push(PathBuf::from(idf_path));
+    }
+    if let Some(home) = dirs::home_dir() {
+        candidates.push(home.join("esp").join("esp-idf"));
+    }
+    #[cfg(windows)]
+    candidates.push(PathBuf::from("C:\\Espressif\\frameworks\\esp-idf"));
+    candidates
+}
+
+fn detect_esp_idf() -> Vec<ExistingInstallation> {
+    esp_idf_candidates()
+        .into_iter()
+        .filter(|path| path.join("tools").join("idf.py").exists())
+        .map(|path| ExistingInstallation {
+            kind: "esp-idf".into(),
+            version: esp_idf_version(&path),
+            path: path.to_string_lossy().to_string(),
+            targets: vec![],
+        })
+        .collect()
+}
+
+pub fn detect_existing() -> Vec<ExistingInstallation> {
+    let mut installations = vec![];
+    installations.extend(detect_espup());
+    installations.extend(detect_esp_idf());
+    installations
+}
+
+// Command to list installations made outside of esp-helm
+#[tauri::command]
+pub async fn detect_existing_installations(
+    state_mutex: State<'_, Mutex<AppState>>,
+) -> HelmResult<Vec<ExistingInstallation>> {
+    let adopted = {
+        let state = state_mutex.lock().unwrap();
+        state.settings.adopted_installations.clone()
+    };
+    let installations = tokio::task::spawn_blocking(detect_existing)
+        .await
+        .map_err(|e| HelmError::Other(format!("Detection failed: {}", e)))?;
+    Ok(installations
+        .into_iter()
+        .filter(|installation| !adopted.iter().any(|a| a.path == installation.path))
+        .collect())
+}
+
+// Command to register existing installation, so it's listed in inventory and not reinstalled
+#[tauri::command]
+pub async fn adopt_existing_installation(
+    state_mutex: State<'_, Mutex<AppState>>,
+    path: String,
+) -> HelmResult<ExistingInstallation> {
+    let installation = tokio::task::spawn_blocking(detect_existing)
+        .await
+        .map_err(|e| HelmError::Other(format!("Detection failed: {}", e)))?
+        .into_iter()
+        .find(|installation| installation.path == path)
+        .ok_or(HelmError::NotFound(format!("Installation at {}", path)))?;
+
+    info!("Adopting {} installation at {}", installation.kind, path);
+    let mut state = state_mutex.lock().unwrap();
+    let mut settings = state.settings.clone();
+    settings.adopted_installations.retain(|a| a.path != path);
+    settings.adopted_installations.push(installation.clone());
+    save_settings(&settings)?;
+    state.settings = settings;
+    Ok(installation)
+}
@@ -0,0 +1,82 @@
+// Remove ANSI escape sequences (colors, cursor movement, window titles) from text
+pub fn strip_ansi(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            result.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            // CSI: ESC [ parameters, terminated by byte in range 0x40..=0x7e
+            Some('[') => {
+                for c in chars.by_ref() {
+                    if ('\x40'..='\x7e').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            // OSC: ESC ] text, terminated by BEL or ESC \
+            Some(']') => {
+                while let Some(c) = chars.next() {
+                    if c == '\x07' {
+                        break;
+                    }
+                    if c == '\x1b' && chars.peek() == Some(&'\\') {
+                        chars.next();
+                        break;
+                    }
+                }
+            }
+            // Two character sequence like ESC 7
+            _ => {}
+        }
+    }
+
+    result
+}
+
+// Progress bars redraw the line with carriage return, keep only the final state
+pub fn last_line_state(line: &str) -> &str {
+    let line = line.trim_end_matches(['\r', '\n']);
+    match line.rfind('\r') {
+        Some(position) => &line[position + 1..],
+        None => line,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_sgr_colors() {
+        assert_eq!(
+            strip_ansi("\x1b[0;32mI (123) wifi: connected\x1b[0m"),
+            "I (123) wifi: connected"
+        );
+        assert_eq!(strip_ansi("\x1b[1;31;40mERROR\x1b[m done"), "ERROR done");
+    }
+
+    #[test]
+    fn strips_osc_sequences() {
+        assert_eq!(strip_ansi("\x1b]0;cargo build\x07Compiling"), "Compiling");
+        assert_eq!(strip_ansi("\x1b]0;cargo build\x1b\\Compiling"), "Compiling");
+    }
+
+    #[test]
+    fn truncated_escape_is_dropped() {
+        assert_eq!(strip_ansi("done\x1b"), "done");
+        assert_eq!(strip_ansi("done\x1b["), "done");
+        assert_eq!(strip_ansi("done\x1b]0;title"), "done");
+    }
+
+    #[test]
+    fn keeps_last_state_of_progress_line() {
+        assert_eq!(last_line_state(" 10%\r 50%\r100%\r\n"), "100%");
+        assert_eq!(last_line_state("plain line\n"), "plain line");
+        assert_eq!(last_line_state("\r"), "");
+    }
+}
@@ -1,20 +1,184 @@
-#[derive(Clone)]
-pub enum BuilderState {
-    Idle,
+use std::collections::{HashMap, HashSet};
+
+use crate::debug_session::DebugSession;
+use crate::deploy::LastDeploy;
+use crate::download::DownloadQueue;
+use crate::error::HelmError;
+use crate::events::{EnvironmentDrift, UpdatesAvailable};
+use crate::monitor::MonitorState;
+use crate::settings::{load_settings, Settings};
+
+pub type JobId = u64;
+
+// Finished jobs kept for list_jobs and wait_job, older ones are dropped
+const FINISHED_JOBS_KEPT: usize = 100;
+
+#[derive(Clone, PartialEq, serde::Serialize, schemars::JsonSchema)]
+#[serde(tag = "state", content = "message")]
+pub enum JobStatus {
+    Queued,
     Running,
-    Abort,
-    Done,
+    Done(String),
+    Failed(HelmError),
+    Cancelled,
+}
+
+#[derive(Clone, serde::Serialize, schemars::JsonSchema)]
+pub struct JobInfo {
+    pub id: JobId,
+    pub name: String,
+    pub depends_on: Vec<JobId>,
+    pub status: JobStatus,
+}
+
+// Registry of queued and recently finished jobs, jobs are executed by crate::jobs
+#[derive(Default)]
+pub struct Scheduler {
+    next_id: JobId,
+    pub jobs: HashMap<JobId, JobInfo>,
+    pub handles: HashMap<JobId, tokio::task::AbortHandle>,
+}
+
+impl Scheduler {
+    pub fn add(&mut self, name: &str, depends_on: Vec<JobId>) -> JobId {
+        self.next_id += 1;
+        let id = self.next_id;
+        self.jobs.insert(
+            id,
+            JobInfo {
+                id,
+                name: name.to_string(),
+                depends_on,
+                status: JobStatus::Queued,
+            },
+        );
+        id
+    }
+
+    pub fn status(&self, id: JobId) -> Option<JobStatus> {
+        self.jobs.get(&id).map(|job| job.status.clone())
+    }
+
+    pub fn set_status(&mut self, id: JobId, status: JobStatus) -> Option<JobInfo> {
+        let job = self.jobs.get_mut(&id)?;
+        // Cancelled job must not be overwritten by result of aborted task
+        if job.status == JobStatus::Cancelled {
+            return None;
+        }
+        job.status = status;
+        let job = job.clone();
+        if !is_active(&job.status) {
+            self.handles.remove(&id);
+            self.prune();
+        }
+        Some(job)
+    }
+
+    pub fn cancel(&mut self, id: JobId) -> Option<JobInfo> {
+        let job = self.jobs.get_mut(&id)?;
+        if !is_active(&job.status) {
+            return None;
+        }
+        job.status = JobStatus::Cancelled;
+        let job = job.clone();
+        if let Some(handle) = self.handles.remove(&id) {
+            handle.abort();
+        }
+        self.prune();
+        Some(job)
+    }
+
+    // Drop the oldest finished jobs over the limit. Jobs which queued or running jobs depend
+    // on are kept, so their dependents still find the result.
+    fn prune(&mut self) {
+        let needed: HashSet<JobId> = self
+            .jobs
+            .values()
+            .filter(|job| is_active(&job.status))
+            .flat_map(|job| job.depends_on.iter().copied())
+            .collect();
+        let mut finished: Vec<JobId> = self
+            .jobs
+            .values()
+            .filter(|job| !is_active(&job.status) && !needed.contains(&job.id))
+            .map(|job| job.id)
+            .collect();
+        if finished.len() <= FINISHED_JOBS_KEPT {
+            return;
+        }
+        finished.sort_unstable();
+        for id in &finished[..finished.len() - FINISHED_JOBS_KEPT] {
+            self.jobs.remove(id);
+        }
+    }
 }
 
-#[derive(Clone)]
+fn is_active(status: &JobStatus) -> bool {
+    matches!(status, JobStatus::Queued | JobStatus::Running)
+}
+
+// Run state of long operations is owned by the task in crate::operations, so polling workers
+// do not contend for this lock. The rest stays behind the mutex, it is locked only for short
+// updates and never across await.
 pub struct AppState {
-    pub builder: BuilderState,
+    pub settings: Settings,
+    pub scheduler: Scheduler,
+    // Questions of interactive commands waiting for answer from the frontend
+    pub prompts: HashMap<u64, tokio::sync::oneshot::Sender<String>>,
+    pub next_prompt_id: u64,
+    pub monitor: MonitorState,
+    // ELF last flashed to each port, used to decode backtraces in monitor
+    pub flashed_elfs: HashMap<String, String>,
+    pub debug_session: Option<DebugSession>,
+    pub downloads: DownloadQueue,
+    pub last_deploy: Option<LastDeploy>,
+    // Changes found at startup until user acknowledges them
+    pub environment_drift: Option<EnvironmentDrift>,
+    // Result of the last background update check
+    pub available_updates: Option<UpdatesAvailable>,
 }
 
 impl Default for AppState {
     fn default() -> Self {
         Self {
-            builder: BuilderState::Idle,
+            settings: load_settings(),
+            scheduler: Scheduler::default(),
+            prompts: HashMap::new(),
+            next_prompt_id: 0,
+            monitor: MonitorState::default(),
+            flashed_elfs: HashMap::new(),
+            debug_session: None,
+            downloads: DownloadQueue::default(),
+            last_deploy: None,
+            environment_drift: None,
+            available_updates: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_bounded_history_of_finished_jobs() {
+        let mut scheduler = Scheduler::default();
+        let first = scheduler.add("first", vec![]);
+        let waiting = scheduler.add("waiting", vec![first]);
+        scheduler.set_status(first, JobStatus::Done("ok".into()));
+        for index in 0..FINISHED_JOBS_KEPT + 10 {
+            let id = scheduler.add(&format!("job {}", index), vec![]);
+            scheduler.set_status(id, JobStatus::Done("ok".into()));
         }
+
+        // Dependency of queued job survives, oldest other jobs are dropped
+        assert_eq!(scheduler.jobs.len(), FINISHED_JOBS_KEPT + 2);
+        assert!(scheduler.status(first).is_some());
+        assert!(scheduler.status(waiting + 1).is_none());
+        assert!(scheduler.status(waiting + 11).is_some());
+
+        scheduler.cancel(waiting);
+        assert!(scheduler.status(first).is_none());
+        assert_eq!(scheduler.jobs.len(), FINISHED_JOBS_KEPT);
     }
 }
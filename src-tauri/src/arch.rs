@@ -0,0 +1,370 @@
+use std::process::Command;
+use std::sync::Mutex;
+
+use log::info;
+use tauri::{Manager, State};
+
+use crate::app_state::AppState;
+use crate::error::{HelmError, HelmResult};
+
+#[derive(serde::Serialize)]
+pub struct HostArchitecture {
+    // Architecture of the machine, e.g. "aarch64" on Apple Silicon
+    hardware: String,
+    // Architecture of esp-helm process
+    process: String,
+    rosetta: bool,
+    // None when host is not supported at all
+    triple: Option<HostTriple>,
+    warnings: Vec<String>,
+}
+
+fn sysctl(name: &str) -> Option<String> {
+    let output = Command::new("sysctl").args(["-n", name]).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+// Process translated by Rosetta reports x86_64, so ask the kernel for real hardware.
+// Emulated x64 process on arm64 Windows sees real architecture in PROCESSOR_ARCHITEW6432.
+pub fn hardware_arch() -> String {
+    if cfg!(target_os = "macos") && sysctl("hw.optional.arm64").as_deref() == Some("1") {
+        return "aarch64".into();
+    }
+    if cfg!(windows) {
+        let native = std::env::var("PROCESSOR_ARCHITEW6432")
+            .or_else(|_| std::env::var("PROCESSOR_ARCHITECTURE"))
+            .unwrap_or_default();
+        match native.to_uppercase().as_str() {
+            "ARM64" => return "aarch64".into(),
+            "AMD64" => return "x86_64".into(),
+            "X86" => return "x86".into(),
+            _ => {}
+        }
+    }
+    std::env::consts::ARCH.into()
+}
+
+#[derive(Clone, serde::Serialize)]
+pub struct HostTriple {
+    // Native triple of the machine, e.g. "aarch64-pc-windows-msvc"
+    pub host: String,
+    // rustup-init is available for every supported host
+    pub rustup_host: String,
+    // Host of esp toolchain builds, None when there are none and Xtensa is unsupported
+    pub espup_host: Option<String>,
+    // espup_host runs under emulation, e.g. x64 toolchain on arm64 Windows
+    pub emulated: bool,
+    // espup_host is glibc build on musl system, it runs only with gcompat installed
+    pub needs_gcompat: bool,
+}
+
+#[derive(serde::Serialize)]
+pub struct LibcStatus {
+    musl: bool,
+    // glibc compatibility layer of Alpine, None outside of musl systems
+    gcompat: Option<bool>,
+    notes: Vec<String>,
+}
+
+// Alpine and other musl distributions ship only musl dynamic loader
+pub fn is_musl() -> bool {
+    if cfg!(target_env = "musl") {
+        return true;
+    }
+    if !cfg!(target_os = "linux") {
+        return false;
+    }
+    std::fs::read_dir("/lib")
+        .map(|entries| {
+            entries
+                .flatten()
+                .any(|entry| entry.file_name().to_string_lossy().starts_with("ld-musl-"))
+        })
+        .unwrap_or(false)
+}
+
+// gcompat provides glibc loader, so glibc binaries of esp-rs can be started
+pub fn is_gcompat_installed() -> bool {
+    let loader = match hardware_arch().as_str() {
+        "aarch64" => "/lib/ld-linux-aarch64.so.1",
+        _ => "/lib64/ld-linux-x86-64.so.2",
+    };
+    std::path::Path::new(loader).exists() || std::path::Path::new("/lib/libgcompat.so.0").exists()
+}
+
+// Explains which parts of toolchain work on musl system
+pub fn libc_status() -> LibcStatus {
+    if !is_musl() {
+        return LibcStatus {
+            musl: false,
+            gcompat: None,
+            notes: vec![],
+        };
+    }
+    let gcompat = is_gcompat_installed();
+    let mut notes = vec![
+        "rustup and cargo tools with musl builds are installed natively".to_string(),
+        "esp-rs publishes Xtensa toolchain and espup for glibc only".to_string(),
+    ];
+    if gcompat {
+        notes.push("glibc binaries run via gcompat, report issues with exact error".into());
+    } else {
+        notes.push(
+            "Install gcompat to use Xtensa chips, cargo tools without musl builds are \
+             compiled from source"
+                .into(),
+        );
+    }
+    LibcStatus {
+        musl: true,
+        gcompat: Some(gcompat),
+        notes,
+    }
+}
+
+// Maps machine to artifacts of rustup and espup, unknown combinations fail early
+// instead of downloading binaries which cannot run
+pub fn detect_host(force_x86_64: bool) -> HelmResult<HostTriple> {
+    host_triple(std::env::consts::OS, &install_arch(force_x86_64), is_musl())
+}
+
+fn host_triple(os: &str, arch: &str, musl: bool) -> HelmResult<HostTriple> {
+    let host = match (os, arch) {
+        ("linux", "x86_64") if musl => "x86_64-unknown-linux-musl",
+        ("linux", "aarch64") if musl => "aarch64-unknown-linux-musl",
+        ("linux", "x86_64") => "x86_64-unknown-linux-gnu",
+        ("linux", "aarch64") => "aarch64-unknown-linux-gnu",
+        ("linux", "arm") => "armv7-unknown-linux-gnueabihf",
+        ("linux", "x86") => "i686-unknown-linux-gnu",
+        ("freebsd", "x86_64") => "x86_64-unknown-freebsd",
+        ("freebsd", "aarch64") => "aarch64-unknown-freebsd",
+        ("macos", "x86_64") => "x86_64-apple-darwin",
+        ("macos", "aarch64") => "aarch64-apple-darwin",
+        ("windows", "x86_64") => "x86_64-pc-windows-msvc",
+        ("windows", "aarch64") => "aarch64-pc-windows-msvc",
+        ("windows", "x86") => "i686-pc-windows-msvc",
+        _ => {
+            return Err(HelmError::Validation(format!(
+                "Unsupported host: {} on {}",
+                os, arch
+            )))
+        }
+    };
+    // esp-rs publishes Xtensa toolchains for these hosts only, arm64 Windows runs x64 build
+    let (espup_host, emulated) = match host {
+        "x86_64-unknown-linux-gnu"
+        | "aarch64-unknown-linux-gnu"
+        | "x86_64-apple-darwin"
+        | "aarch64-apple-darwin"
+        | "x86_64-pc-windows-msvc" => (Some(host), false),
+        "aarch64-pc-windows-msvc" => (Some("x86_64-pc-windows-msvc"), true),
+        // There are no musl builds, glibc ones are used with gcompat
+        "x86_64-unknown-linux-musl" => (Some("x86_64-unknown-linux-gnu"), false),
+        "aarch64-unknown-linux-musl" => (Some("aarch64-unknown-linux-gnu"), false),
+        _ => (None, false),
+    };
+    Ok(HostTriple {
+        host: host.to_string(),
+        rustup_host: host.to_string(),
+        espup_host: espup_host.map(str::to_string),
+        emulated,
+        needs_gcompat: musl && espup_host.is_some(),
+    })
+}
+
+pub fn is_rosetta() -> bool {
+    cfg!(target_os = "macos") && sysctl("sysctl.proc_translated").as_deref() == Some("1")
+}
+
+pub fn is_x86_64_forced(app: &tauri::AppHandle) -> bool {
+    let state_mutex = app.state::<Mutex<AppState>>();
+    let state = state_mutex.lock().unwrap();
+    state.settings.force_x86_64
+}
+
+// Architecture of binaries which should be installed.
+// On macOS x86_64 can be forced for legacy projects, it runs via Rosetta.
+pub fn install_arch(force_x86_64: bool) -> String {
+    if cfg!(target_os = "macos") && force_x86_64 {
+        return "x86_64".into();
+    }
+    hardware_arch()
+}
+
+fn python_arch() -> Option<String> {
+    let output = Command::new("python3")
+        .args(["-c", "import platform; print(platform.machine())"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    match String::from_utf8_lossy(&output.stdout).trim() {
+        "arm64" => Some("aarch64".into()),
+        machine => Some(machine.to_string()),
+    }
+}
+
+fn homebrew_arch() -> Option<String> {
+    let output = Command::new("brew").arg("--prefix").output().ok()?;
+    match String::from_utf8_lossy(&output.stdout).trim() {
+        "/opt/homebrew" => Some("aarch64".into()),
+        "/usr/local" => Some("x86_64".into()),
+        _ => None,
+    }
+}
+
+fn architecture_warnings(hardware: &str, rosetta: bool) -> Vec<String> {
+    let mut warnings = vec![];
+    if hardware != "aarch64" {
+        return warnings;
+    }
+
+    if rosetta {
+        warnings.push("esp-helm runs under Rosetta, install the native arm64 build".into());
+    }
+    if let Some(arch) = homebrew_arch() {
+        if arch != hardware {
+            warnings.push(format!(
+                "Homebrew is installed for {}, native Homebrew lives in /opt/homebrew",
+                arch
+            ));
+        }
+    }
+    if let Some(arch) = python_arch() {
+        if arch != hardware {
+            warnings.push(format!("Python is {} binary running under Rosetta", arch));
+        }
+    }
+    warnings
+}
+
+// Command to get architecture of the host and warnings about mismatched tools
+#[tauri::command]
+pub async fn get_host_architecture(
+    state_mutex: State<'_, Mutex<AppState>>,
+) -> HelmResult<HostArchitecture> {
+    let hardware = hardware_arch();
+    let rosetta = is_rosetta();
+    let mut warnings = architecture_warnings(&hardware, rosetta);
+
+    let force_x86_64 = {
+        let state = state_mutex.lock().unwrap();
+        state.settings.force_x86_64
+    };
+    if force_x86_64 && cfg!(target_os = "macos") {
+        warnings.push("x86_64 toolchain is forced, it will run via Rosetta".into());
+    }
+    let triple = match detect_host(force_x86_64) {
+        Ok(triple) => {
+            if triple.espup_host.is_none() {
+                warnings.push(format!(
+                    "There is no Xtensa toolchain for {}, only RISC-V chips are supported",
+                    triple.host
+                ));
+            } else if triple.emulated {
+                warnings.push("Xtensa toolchain is x64 build running under emulation".into());
+            } else if triple.needs_gcompat && !is_gcompat_installed() {
+                warnings.push("Xtensa toolchain is glibc build, it needs gcompat on musl".into());
+            }
+            Some(triple)
+        }
+        Err(e) => {
+            warnings.push(e.to_string());
+            None
+        }
+    };
+
+    info!("Host architecture: {} (rosetta: {})", hardware, rosetta);
+    Ok(HostArchitecture {
+        hardware,
+        process: std::env::consts::ARCH.into(),
+        rosetta,
+        triple,
+        warnings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn triple(os: &str, arch: &str, musl: bool) -> (String, Option<String>, bool, bool) {
+        let triple = host_triple(os, arch, musl).unwrap();
+        (
+            triple.host,
+            triple.espup_host,
+            triple.emulated,
+            triple.needs_gcompat,
+        )
+    }
+
+    #[test]
+    fn maps_native_hosts() {
+        assert_eq!(
+            triple("linux", "x86_64", false),
+            (
+                "x86_64-unknown-linux-gnu".into(),
+                Some("x86_64-unknown-linux-gnu".into()),
+                false,
+                false
+            )
+        );
+        assert_eq!(
+            triple("macos", "aarch64", false),
+            (
+                "aarch64-apple-darwin".into(),
+                Some("aarch64-apple-darwin".into()),
+                false,
+                false
+            )
+        );
+    }
+
+    #[test]
+    fn arm64_windows_uses_emulated_toolchain() {
+        assert_eq!(
+            triple("windows", "aarch64", false),
+            (
+                "aarch64-pc-windows-msvc".into(),
+                Some("x86_64-pc-windows-msvc".into()),
+                true,
+                false
+            )
+        );
+    }
+
+    #[test]
+    fn musl_uses_glibc_toolchain() {
+        assert_eq!(
+            triple("linux", "x86_64", true),
+            (
+                "x86_64-unknown-linux-musl".into(),
+                Some("x86_64-unknown-linux-gnu".into()),
+                false,
+                true
+            )
+        );
+    }
+
+    #[test]
+    fn hosts_without_xtensa_toolchain() {
+        for (os, arch, host) in [
+            ("freebsd", "x86_64", "x86_64-unknown-freebsd"),
+            ("freebsd", "aarch64", "aarch64-unknown-freebsd"),
+            ("windows", "x86", "i686-pc-windows-msvc"),
+            ("linux", "arm", "armv7-unknown-linux-gnueabihf"),
+        ] {
+            assert_eq!(triple(os, arch, false), (host.into(), None, false, false));
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_host() {
+        assert!(host_triple("netbsd", "x86_64", false).is_err());
+        assert!(host_triple("macos", "x86", false).is_err());
+    }
+}
@@ -0,0 +1,120 @@
+use std::path::{Path, PathBuf};
+
+use log::info;
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+
+use crate::audit::{audit_path, AuditAction};
+use crate::error::{HelmError, HelmResult};
+#[cfg(unix)]
+use crate::external_command::set_exec_permission;
+use crate::long_path::long_path;
+
+// Partially written file lives next to the destination, so rename stays on the same filesystem
+pub fn part_path(path: &Path) -> PathBuf {
+    let path = long_path(path);
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".part");
+    path.with_file_name(name)
+}
+
+// Flush temporary file through its write handle and move it over the destination.
+// Windows refuses to flush read-only handle, so file is not reopened for sync.
+pub async fn persist(file: fs::File, part: &Path, path: &Path) -> HelmResult<()> {
+    file.sync_all().await?;
+    drop(file);
+    fs::rename(part, long_path(path)).await?;
+    audit_path(AuditAction::WriteFile, path);
+    Ok(())
+}
+
+pub async fn write_atomic(path: &Path, bytes: &[u8]) -> HelmResult<()> {
+    let part = part_path(path);
+    let mut file = fs::File::create(&part).await?;
+    file.write_all(bytes).await?;
+    persist(file, &part, path).await
+}
+
+// How downloaded executable is checked before it replaces the destination
+pub enum Probe {
+    // Tool prints its version, e.g. rustup-init
+    Version,
+    // Installers and scripts must not be started only to check them, file header is compared
+    Header(&'static [u8]),
+    // PowerShell scripts have no header, text must not be error page of proxy or CDN
+    Script,
+}
+
+// Header of Windows executables and installers
+pub const PE_HEADER: &[u8] = b"MZ";
+// Shebang of shell scripts
+pub const SCRIPT_HEADER: &[u8] = b"#!";
+
+// Write binary and check it before it replaces the destination,
+// so crash or corrupted download never leaves broken tool in ~/.cargo/bin
+pub async fn write_executable(path: &Path, bytes: &[u8], probe: Probe) -> HelmResult<()> {
+    let valid = match probe {
+        Probe::Version => true,
+        Probe::Header(header) => bytes.starts_with(header),
+        Probe::Script => {
+            std::str::from_utf8(bytes).is_ok_and(|text| !text.trim_start().starts_with('<'))
+        }
+    };
+    if !valid {
+        return Err(HelmError::Validation(format!(
+            "{} is not a valid executable",
+            path.display()
+        )));
+    }
+
+    let part = part_path(path);
+    let mut file = fs::File::create(&part).await?;
+    file.write_all(bytes).await?;
+    file.sync_all().await?;
+    drop(file);
+
+    #[cfg(unix)]
+    set_exec_permission(&part)?;
+
+    // Windows runs only files with .exe extension
+    #[cfg(windows)]
+    let probe_path = {
+        let probe_path = part.with_extension("part.exe");
+        fs::rename(&part, &probe_path).await?;
+        probe_path
+    };
+    #[cfg(unix)]
+    let probe_path = part;
+
+    if let Probe::Version = probe {
+        let output = tokio::process::Command::new(&probe_path)
+            .arg("--version")
+            .output()
+            .await;
+        match output {
+            Ok(output) if output.status.success() => info!(
+                "{} verified: {}",
+                path.display(),
+                String::from_utf8_lossy(&output.stdout).trim()
+            ),
+            Ok(output) => {
+                let _ = fs::remove_file(&probe_path).await;
+                let mut text = String::from_utf8_lossy(&output.stdout).to_string();
+                text.push_str(&String::from_utf8_lossy(&output.stderr));
+                return Err(HelmError::child_process_failed(output.status.code(), &text));
+            }
+            Err(e) => {
+                let _ = fs::remove_file(&probe_path).await;
+                return Err(HelmError::Validation(format!(
+                    "{} is not a valid executable: {}",
+                    path.display(),
+                    e
+                )));
+            }
+        }
+    }
+
+    fs::rename(&probe_path, long_path(path)).await?;
+    audit_path(AuditAction::WriteFile, path);
+    Ok(())
+}
@@ -0,0 +1,107 @@
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use log::info;
+
+use crate::error::HelmResult;
+use crate::portable::app_data_dir;
+
+const DEFAULT_LIMIT: usize = 500;
+
+// Entries from concurrent jobs must not interleave within a line
+static AUDIT_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AuditAction {
+    WriteFile,
+    CreateDir,
+    Remove,
+    // PATH, shell profile or environment variable change
+    ModifyEnvironment,
+    RunCommand,
+    RunElevated,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct AuditEntry {
+    // Milliseconds since Unix epoch
+    timestamp: u128,
+    action: AuditAction,
+    // Path or command line
+    target: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    detail: Option<String>,
+}
+
+pub fn audit_log_path() -> Option<PathBuf> {
+    app_data_dir().map(|dir| dir.join("audit.log"))
+}
+
+// Appends JSON line to audit log, failure to write is logged and never stops the operation
+pub fn audit(action: AuditAction, target: &str, detail: Option<String>) {
+    let entry = AuditEntry {
+        timestamp: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or(0),
+        action,
+        target: target.to_string(),
+        detail,
+    };
+    let Some(path) = audit_log_path() else {
+        return;
+    };
+    let _guard = AUDIT_LOCK.lock().unwrap();
+    let result = (|| -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)?;
+        let line = serde_json::to_string(&entry)?;
+        writeln!(file, "{}", line)
+    })();
+    if let Err(e) = result {
+        info!("Unable to write audit log: {}", e);
+    }
+}
+
+pub fn audit_path(action: AuditAction, path: &Path) {
+    audit(action, &path.to_string_lossy(), None);
+}
+
+pub fn audit_command(program: &str, args: &[&str]) {
+    let line = std::iter::once(program)
+        .chain(args.iter().copied())
+        .collect::<Vec<&str>>()
+        .join(" ");
+    audit(AuditAction::RunCommand, &line, None);
+}
+
+// Command to read audit log, newest entries last. Unreadable lines are skipped.
+#[tauri::command]
+pub async fn get_audit_log(
+    action: Option<AuditAction>,
+    since: Option<u128>,
+    limit: Option<usize>,
+) -> HelmResult<Vec<AuditEntry>> {
+    let Some(path) = audit_log_path().filter(|path| path.exists()) else {
+        return Ok(vec![]);
+    };
+    let file = std::fs::File::open(path)?;
+    let entries: Vec<AuditEntry> = BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| serde_json::from_str::<AuditEntry>(&line).ok())
+        .filter(|entry| action.is_none() || action == Some(entry.action))
+        .filter(|entry| entry.timestamp >= since.unwrap_or(0))
+        .collect();
+    let limit = limit.unwrap_or(DEFAULT_LIMIT);
+    let skip = entries.len().saturating_sub(limit);
+    Ok(entries.into_iter().skip(skip).collect())
+}
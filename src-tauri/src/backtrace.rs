@@ -0,0 +1,110 @@
+use std::collections::HashSet;
+use std::sync::OnceLock;
+
+use addr2line::gimli::{EndianRcSlice, RunTimeEndian};
+use addr2line::Context;
+use regex::Regex;
+
+use crate::error::{HelmError, HelmResult};
+
+// Hex numbers of any width, addresses are not always printed zero padded
+static ADDRESS: OnceLock<Regex> = OnceLock::new();
+
+// Code addresses in monitor line in order of appearance, without duplicates
+fn addresses(line: &str) -> Vec<u64> {
+    let address = ADDRESS.get_or_init(|| Regex::new(r"\b0x[0-9a-fA-F]+\b").unwrap());
+    let mut seen = HashSet::new();
+    address
+        .find_iter(line)
+        .filter_map(|found| u64::from_str_radix(&found.as_str()[2..], 16).ok())
+        .filter(|address| seen.insert(*address))
+        .collect()
+}
+
+// Debug information of the flashed application, used to decode panic backtraces
+pub struct Symbols {
+    context: Context<EndianRcSlice<RunTimeEndian>>,
+}
+
+impl Symbols {
+    pub fn load(elf: &str) -> HelmResult<Self> {
+        let data = std::fs::read(elf)?;
+        let file = addr2line::object::File::parse(data.as_slice())
+            .map_err(|e| HelmError::Validation(format!("Invalid ELF {}: {}", elf, e)))?;
+        let context = Context::new(&file)
+            .map_err(|e| HelmError::Validation(format!("Invalid debug info in {}: {}", elf, e)))?;
+        Ok(Symbols { context })
+    }
+
+    // Function and source location of code address, inlined functions are listed first
+    fn resolve(&self, address: u64) -> Vec<String> {
+        let mut frames = vec![];
+        let Ok(mut iter) = self.context.find_frames(address).skip_all_loads() else {
+            return frames;
+        };
+        while let Ok(Some(frame)) = iter.next() {
+            let Some(function) = frame
+                .function
+                .as_ref()
+                .and_then(|function| function.demangle().ok())
+            else {
+                continue;
+            };
+            let location = frame
+                .location
+                .as_ref()
+                .and_then(|location| Some((location.file?, location.line?)));
+            frames.push(match location {
+                Some((file, line)) => {
+                    format!("0x{:08x}: {} at {}:{}", address, function, file, line)
+                }
+                None => format!("0x{:08x}: {}", address, function),
+            });
+        }
+        frames
+    }
+
+    // Decoded frames for code addresses found in monitor line, e.g. in
+    // "Backtrace: 0x400d1234:0x3ffb1230" or "MEPC    : 0x42001234"
+    pub fn decode_line(&self, line: &str) -> Vec<String> {
+        addresses(line)
+            .into_iter()
+            .flat_map(|address| self.resolve(address))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xtensa_backtrace() {
+        assert_eq!(
+            addresses("Backtrace: 0x400d1234:0x3ffb1230 0x400d5678:0x3ffb1250 |<-CORRUPTED"),
+            vec![0x400d1234, 0x3ffb1230, 0x400d5678, 0x3ffb1250]
+        );
+    }
+
+    #[test]
+    fn riscv_register_dump() {
+        assert_eq!(
+            addresses("MEPC    : 0x42001234  RA      : 0x42001234  SP      : 0x3fc8e7a0"),
+            vec![0x42001234, 0x3fc8e7a0]
+        );
+    }
+
+    #[test]
+    fn addresses_without_padding() {
+        assert_eq!(
+            addresses("0x1234: panic at src/main.rs, 0xABCDEF"),
+            vec![0x1234, 0xabcdef]
+        );
+    }
+
+    #[test]
+    fn no_addresses() {
+        assert!(addresses("I (312) cpu_start: Starting scheduler, 0xzz").is_empty());
+        assert!(addresses("").is_empty());
+    }
+}
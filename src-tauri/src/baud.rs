@@ -0,0 +1,237 @@
+use std::io::{ErrorKind, Read};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use espflash::interface::Interface;
+use log::info;
+use tauri::State;
+
+use crate::app_state::AppState;
+use crate::error::{HelmError, HelmResult};
+use crate::flasher::{connect_at, get_serial_port_info};
+use crate::settings::{load_settings, save_settings};
+
+// Tried from the fastest, USB-serial bridges like CP2102 stop at 921600
+const FLASH_BAUD_RATES: [u32; 5] = [2_000_000, 1_500_000, 921_600, 460_800, 230_400];
+const MONITOR_BAUD_RATES: [u32; 6] = [115_200, 74_880, 921_600, 460_800, 230_400, 9_600];
+pub const DEFAULT_BAUD: u32 = 115_200;
+// Output read at each rate when detecting monitor baud
+const MONITOR_SAMPLE_TIME: Duration = Duration::from_millis(1500);
+
+#[derive(serde::Serialize)]
+pub struct BaudDetection {
+    board: String,
+    baud: u32,
+    saved: bool,
+}
+
+// Boards are identified by USB descriptor, so override follows board to other port
+pub fn board_key(port: &str) -> HelmResult<String> {
+    let info = get_serial_port_info(port)?;
+    Ok(match info.port_type {
+        serialport::SerialPortType::UsbPort(usb) => format!(
+            "{:04x}:{:04x}:{}",
+            usb.vid,
+            usb.pid,
+            usb.serial_number.unwrap_or_default()
+        ),
+        _ => port.to_string(),
+    })
+}
+
+pub fn flash_baud(port: &str) -> Option<u32> {
+    let board = board_key(port).ok()?;
+    load_settings().flash_baud.get(&board).copied()
+}
+
+pub fn monitor_baud(port: &str) -> u32 {
+    board_key(port)
+        .ok()
+        .and_then(|board| load_settings().monitor_baud.get(&board).copied())
+        .unwrap_or(DEFAULT_BAUD)
+}
+
+// Connection at higher rate is verified by reading device info, which fails when unstable
+fn try_flash_baud(port: &str, baud: u32) -> bool {
+    match connect_at(port, Some(1), Some(0), Some(baud)) {
+        Ok(mut flasher) => flasher.device_info().is_ok(),
+        Err(e) => {
+            info!("{} baud failed on {}: {}", baud, port, e);
+            false
+        }
+    }
+}
+
+// Share of printable characters in output, wrong baud produces mostly garbage
+fn text_score(data: &[u8]) -> f32 {
+    if data.is_empty() {
+        return 0.0;
+    }
+    let printable = data
+        .iter()
+        .filter(|byte| byte.is_ascii_graphic() || b" \r\n\t".contains(*byte))
+        .count();
+    printable as f32 / data.len() as f32
+}
+
+// Earlier rate wins a tie, output below 90% printable is not readable at all
+fn most_readable(scores: &[(u32, f32)]) -> Option<u32> {
+    let mut best: Option<(u32, f32)> = None;
+    for (baud, score) in scores {
+        if *score > best.map(|(_, best)| best).unwrap_or(0.9) {
+            best = Some((*baud, *score));
+        }
+    }
+    best.map(|(baud, _)| baud)
+}
+
+// Board is reset, so boot messages are received at each rate
+fn sample_output(port: &str, baud: u32) -> HelmResult<Vec<u8>> {
+    let port_info = get_serial_port_info(port)?;
+    let mut serial = Interface::new(&port_info, Some(1), Some(0))
+        .map_err(|e| HelmError::Io(format!("Failed to open {}: {}", port, e)))?;
+    let serial = serial.serial_port_mut();
+    serial.set_baud_rate(baud)?;
+    serial.set_timeout(Duration::from_millis(50))?;
+    serial.write_request_to_send(true)?;
+    std::thread::sleep(Duration::from_millis(100));
+    serial.write_request_to_send(false)?;
+
+    let started = Instant::now();
+    let mut data = vec![];
+    let mut buffer = [0; 1024];
+    while started.elapsed() < MONITOR_SAMPLE_TIME {
+        match serial.read(&mut buffer) {
+            Ok(count) => data.extend_from_slice(&buffer[..count]),
+            Err(e) if e.kind() == ErrorKind::TimedOut => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(data)
+}
+
+fn save_override(
+    state_mutex: &State<'_, Mutex<AppState>>,
+    monitor: bool,
+    board: &str,
+    baud: Option<u32>,
+) -> HelmResult<()> {
+    let mut state = state_mutex.lock().unwrap();
+    let mut settings = state.settings.clone();
+    let overrides = if monitor {
+        &mut settings.monitor_baud
+    } else {
+        &mut settings.flash_baud
+    };
+    match baud {
+        Some(baud) => overrides.insert(board.to_string(), baud),
+        None => overrides.remove(board),
+    };
+    save_settings(&settings)?;
+    state.settings = settings;
+    Ok(())
+}
+
+// Command to find the fastest baud at which the board can be flashed
+#[tauri::command]
+pub async fn detect_flash_baud(
+    state_mutex: State<'_, Mutex<AppState>>,
+    port: String,
+    save: bool,
+) -> HelmResult<BaudDetection> {
+    let board = board_key(&port)?;
+    let probe_port = port.clone();
+    let baud = tokio::task::spawn_blocking(move || {
+        FLASH_BAUD_RATES
+            .into_iter()
+            .find(|baud| try_flash_baud(&probe_port, *baud))
+            .unwrap_or(DEFAULT_BAUD)
+    })
+    .await
+    .map_err(|_| HelmError::Other("Baud detection panicked".into()))?;
+    info!("Flash baud of {} is {}", board, baud);
+
+    if save {
+        save_override(&state_mutex, false, &board, Some(baud))?;
+    }
+    Ok(BaudDetection {
+        board,
+        baud,
+        saved: save,
+    })
+}
+
+// Command to find baud of firmware output from the most readable boot messages
+#[tauri::command]
+pub async fn detect_monitor_baud(
+    state_mutex: State<'_, Mutex<AppState>>,
+    port: String,
+    save: bool,
+) -> HelmResult<BaudDetection> {
+    let board = board_key(&port)?;
+    let probe_port = port.clone();
+    let baud = tokio::task::spawn_blocking(move || -> HelmResult<Option<u32>> {
+        let mut scores = vec![];
+        for baud in MONITOR_BAUD_RATES {
+            scores.push((baud, text_score(&sample_output(&probe_port, baud)?)));
+        }
+        Ok(most_readable(&scores))
+    })
+    .await
+    .map_err(|_| HelmError::Other("Baud detection panicked".into()))??
+    .ok_or(HelmError::NotFound(format!("Readable output on {}", port)))?;
+    info!("Monitor baud of {} is {}", board, baud);
+
+    if save {
+        save_override(&state_mutex, true, &board, Some(baud))?;
+    }
+    Ok(BaudDetection {
+        board,
+        baud,
+        saved: save,
+    })
+}
+
+// Command to set or with None remove baud override of the board connected to port
+#[tauri::command]
+pub async fn set_baud_override(
+    state_mutex: State<'_, Mutex<AppState>>,
+    port: String,
+    monitor: bool,
+    baud: Option<u32>,
+) -> HelmResult<String> {
+    let board = board_key(&port)?;
+    save_override(&state_mutex, monitor, &board, baud)?;
+    Ok(format!("Baud override of {} updated", board))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scores_printable_output() {
+        assert_eq!(text_score(b""), 0.0);
+        assert_eq!(text_score(b"rst:0x1 (POWERON_RESET)\r\n"), 1.0);
+        assert_eq!(text_score(&[b'o', b'k', 0xF8, 0x00]), 0.5);
+    }
+
+    #[test]
+    fn picks_most_readable_baud() {
+        assert_eq!(
+            most_readable(&[(115_200, 0.4), (74_880, 0.97), (921_600, 0.99)]),
+            Some(921_600)
+        );
+        assert_eq!(
+            most_readable(&[(115_200, 1.0), (74_880, 1.0)]),
+            Some(115_200)
+        );
+        assert_eq!(most_readable(&[(115_200, 0.9), (9_600, 0.5)]), None);
+    }
+
+    #[test]
+    fn flash_rates_are_tried_fastest_first() {
+        assert!(FLASH_BAUD_RATES.windows(2).all(|pair| pair[0] > pair[1]));
+        assert!(MONITOR_BAUD_RATES.contains(&DEFAULT_BAUD));
+    }
+}
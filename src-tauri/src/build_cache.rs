@@ -0,0 +1,136 @@
+use log::info;
+use serde_json::Value;
+use tauri::{AppHandle, Window};
+
+use crate::cargo_tools::{cargo_bin, install_cargo_tool};
+use crate::error::HelmResult;
+use crate::esp_clang::set_export_variable;
+use crate::external_command::run_external_command_output;
+use crate::package_manager::{find_in_path, install_packages};
+
+// ESP-IDF build by idf.py uses IDF_CCACHE_ENABLE, esp-idf-sys runs CMake directly
+const CCACHE_VARIABLES: [&str; 3] = [
+    "IDF_CCACHE_ENABLE",
+    "CMAKE_C_COMPILER_LAUNCHER",
+    "CMAKE_CXX_COMPILER_LAUNCHER",
+];
+
+#[derive(Default, serde::Serialize)]
+pub struct CacheStats {
+    installed: bool,
+    hits: u64,
+    misses: u64,
+    // Size of cache directory in bytes
+    size: Option<u64>,
+}
+
+#[derive(serde::Serialize)]
+pub struct BuildCacheStats {
+    sccache: CacheStats,
+    ccache: CacheStats,
+}
+
+fn sccache_path() -> Option<String> {
+    cargo_bin("sccache")
+        .ok()
+        .filter(|path| path.exists())
+        .or_else(|| find_in_path("sccache"))
+        .map(|path| path.to_string_lossy().to_string())
+}
+
+// Command to install sccache and/or ccache and export variables which enable them,
+// disabled cache has its variables removed from export file
+#[tauri::command]
+pub async fn setup_build_cache(
+    window: Window,
+    app: AppHandle,
+    sccache: bool,
+    ccache: bool,
+) -> HelmResult<String> {
+    if sccache {
+        if sccache_path().is_none() {
+            install_cargo_tool(window.clone(), app.clone(), "sccache".into(), None).await?;
+        }
+        set_export_variable("RUSTC_WRAPPER", sccache_path().as_deref())?;
+    } else {
+        set_export_variable("RUSTC_WRAPPER", None)?;
+    }
+
+    if ccache {
+        install_packages(window, app, &["ccache".to_string()]).await?;
+        set_export_variable("IDF_CCACHE_ENABLE", Some("1"))?;
+        set_export_variable("CMAKE_C_COMPILER_LAUNCHER", Some("ccache"))?;
+        set_export_variable("CMAKE_CXX_COMPILER_LAUNCHER", Some("ccache"))?;
+    } else {
+        for variable in CCACHE_VARIABLES {
+            set_export_variable(variable, None)?;
+        }
+    }
+
+    info!(
+        "Build cache configured, sccache: {}, ccache: {}",
+        sccache, ccache
+    );
+    Ok("Build cache configured, restart terminal to apply".into())
+}
+
+// Hits and misses are counted per language, e.g. {"counts": {"Rust": 10, "C/C++": 2}}
+fn sum_counts(value: &Value) -> u64 {
+    value["counts"]
+        .as_object()
+        .map(|counts| counts.values().filter_map(Value::as_u64).sum())
+        .unwrap_or(0)
+}
+
+async fn sccache_stats() -> CacheStats {
+    let Some(sccache) = sccache_path() else {
+        return CacheStats::default();
+    };
+    let stats = run_external_command_output(&sccache, &["--show-stats", "--stats-format", "json"])
+        .await
+        .ok()
+        .and_then(|output| serde_json::from_str::<Value>(&output).ok())
+        .unwrap_or_default();
+    CacheStats {
+        installed: true,
+        hits: sum_counts(&stats["stats"]["cache_hits"]),
+        misses: sum_counts(&stats["stats"]["cache_misses"]),
+        size: stats["cache_size"].as_u64(),
+    }
+}
+
+// ccache 4 prints machine readable statistics as "key<TAB>value" lines
+async fn ccache_stats() -> CacheStats {
+    if find_in_path("ccache").is_none() {
+        return CacheStats::default();
+    }
+    let output = run_external_command_output("ccache", &["--print-stats"])
+        .await
+        .unwrap_or_default();
+    let mut stats = CacheStats {
+        installed: true,
+        ..Default::default()
+    };
+    for line in output.lines() {
+        let Some((key, value)) = line.split_once('\t') else {
+            continue;
+        };
+        let value: u64 = value.trim().parse().unwrap_or(0);
+        match key {
+            "direct_cache_hit" | "preprocessed_cache_hit" => stats.hits += value,
+            "cache_miss" => stats.misses += value,
+            // Reported in KiB
+            "cache_size_kibibyte" => stats.size = Some(value * 1024),
+            _ => {}
+        }
+    }
+    stats
+}
+
+#[tauri::command]
+pub async fn build_cache_stats() -> HelmResult<BuildCacheStats> {
+    Ok(BuildCacheStats {
+        sccache: sccache_stats().await,
+        ccache: ccache_stats().await,
+    })
+}
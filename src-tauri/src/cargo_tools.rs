@@ -0,0 +1,258 @@
+use std::path::PathBuf;
+
+use log::info;
+use tauri::{AppHandle, Window};
+
+#[cfg(unix)]
+use crate::atomic_file::SCRIPT_HEADER;
+use crate::atomic_file::{write_executable, Probe};
+use crate::error::{HelmError, HelmResult};
+use crate::external_command::run_external_command_with_progress;
+use crate::inventory::cargo_home;
+use crate::services::services;
+
+// Script of a release tag, main branch could change what is executed without notice
+#[cfg(unix)]
+const BINSTALL_SCRIPT_URL: &str =
+    "https://raw.githubusercontent.com/cargo-bins/cargo-binstall/v1.10.17/install-from-binstall-release.sh";
+#[cfg(windows)]
+const BINSTALL_SCRIPT_URL: &str =
+    "https://raw.githubusercontent.com/cargo-bins/cargo-binstall/v1.10.17/install-from-binstall-release.ps1";
+
+pub fn cargo_bin(name: &str) -> HelmResult<PathBuf> {
+    Ok(cargo_home()
+        .ok_or(HelmError::NotFound("cargo home".into()))?
+        .join("bin")
+        .join(format!("{}{}", name, std::env::consts::EXE_SUFFIX)))
+}
+
+// Names of crates.io: ASCII letter first, then letters, digits, "-" and "_"
+fn validate_crate_name(name: &str) -> HelmResult<()> {
+    let valid = name.len() <= 64
+        && name.starts_with(|c: char| c.is_ascii_alphabetic())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(HelmError::Validation(format!(
+            "Invalid crate name {}",
+            name
+        )));
+    }
+    Ok(())
+}
+
+// Exact semver version, e.g. "3.1.0" or "0.2.0-rc.1+build.5"
+fn validate_version(version: &str) -> HelmResult<()> {
+    let (release, build) = match version.split_once('+') {
+        Some((release, build)) => (release, Some(build)),
+        None => (version, None),
+    };
+    let (core, pre) = match release.split_once('-') {
+        Some((core, pre)) => (core, Some(pre)),
+        None => (release, None),
+    };
+    let numbers: Vec<&str> = core.split('.').collect();
+    let number = |part: &&str| {
+        !part.is_empty()
+            && part.chars().all(|c| c.is_ascii_digit())
+            && (part.len() == 1 || !part.starts_with('0'))
+    };
+    let identifiers = |part: &str| {
+        part.split('.')
+            .all(|id| !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-'))
+    };
+    let valid = numbers.len() == 3
+        && numbers.iter().all(number)
+        && pre.into_iter().chain(build).all(identifiers);
+    if !valid {
+        return Err(HelmError::Validation(format!(
+            "Version must be semver like 1.2.3, got {}",
+            version
+        )));
+    }
+    Ok(())
+}
+
+// cargo-binstall itself is installed by its release script, which downloads prebuilt binary
+async fn ensure_binstall(window: Window, app: AppHandle) -> HelmResult<()> {
+    if cargo_bin("cargo-binstall")?.exists() {
+        return Ok(());
+    }
+
+    info!("Installing cargo-binstall...");
+    let script = services(&app)
+        .http
+        .get(BINSTALL_SCRIPT_URL)
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+
+    #[cfg(unix)]
+    let script_path = std::env::temp_dir().join("install-cargo-binstall.sh");
+    #[cfg(windows)]
+    let script_path = std::env::temp_dir().join("install-cargo-binstall.ps1");
+    #[cfg(unix)]
+    let probe = Probe::Header(SCRIPT_HEADER);
+    #[cfg(windows)]
+    let probe = Probe::Script;
+    write_executable(&script_path, &script, probe).await?;
+    let script_path = script_path.to_string_lossy().to_string();
+
+    #[cfg(unix)]
+    let (cmd, args) = ("bash", vec![script_path.as_str()]);
+    #[cfg(windows)]
+    let (cmd, args) = (
+        "powershell",
+        vec![
+            "-NoProfile",
+            "-ExecutionPolicy",
+            "Bypass",
+            "-File",
+            script_path.as_str(),
+        ],
+    );
+
+    run_external_command_with_progress(window, app, cmd, &args, "PROGRESS_EVENT").await?;
+    Ok(())
+}
+
+// Download prebuilt binary from GitHub release or quickinstall, compilation is left to fallback
+async fn install_prebuilt(
+    window: Window,
+    app: AppHandle,
+    name: &str,
+    version: Option<&str>,
+) -> HelmResult<()> {
+    ensure_binstall(window.clone(), app.clone()).await?;
+
+    let cargo = cargo_bin("cargo")?.to_string_lossy().to_string();
+    let spec = match version {
+        Some(version) => format!("{}@{}", name, version),
+        None => name.to_string(),
+    };
+    run_external_command_with_progress(
+        window,
+        app,
+        &cargo,
+        &[
+            "binstall",
+            "--no-confirm",
+            "--disable-strategies",
+            "compile",
+            "--",
+            &spec,
+        ],
+        "PROGRESS_EVENT",
+    )
+    .await?;
+    Ok(())
+}
+
+async fn install_from_source(
+    window: Window,
+    app: AppHandle,
+    name: &str,
+    version: Option<&str>,
+) -> HelmResult<()> {
+    let cargo = cargo_bin("cargo")?.to_string_lossy().to_string();
+    let mut args = vec!["install", "--locked"];
+    if let Some(version) = version {
+        args.push("--version");
+        args.push(version);
+    }
+    args.push("--");
+    args.push(name);
+    run_external_command_with_progress(window, app, &cargo, &args, "PROGRESS_EVENT").await?;
+    Ok(())
+}
+
+// Command to install cargo tool like cargo-espflash, ldproxy or cargo-generate.
+// Prebuilt binary is preferred, because compilation takes very long on laptops.
+#[tauri::command]
+pub async fn install_cargo_tool(
+    window: Window,
+    app: AppHandle,
+    name: String,
+    version: Option<String>,
+) -> HelmResult<String> {
+    validate_crate_name(&name)?;
+    if let Some(version) = &version {
+        validate_version(version)?;
+    }
+    info!("Installing {}...", name);
+    // esp-rs does not publish FreeBSD binaries, binstall would only waste time searching
+    if cfg!(target_os = "freebsd") {
+        install_from_source(window, app, &name, version.as_deref()).await?;
+        return Ok(format!("{} built from source", name));
+    }
+    match install_prebuilt(window.clone(), app.clone(), &name, version.as_deref()).await {
+        Ok(()) => return Ok(format!("{} installed from prebuilt binary", name)),
+        Err(HelmError::Cancelled) => return Err(HelmError::Cancelled),
+        Err(e) => info!(
+            "Prebuilt {} not available, building from source: {}",
+            name, e
+        ),
+    }
+
+    install_from_source(window, app, &name, version.as_deref()).await?;
+    Ok(format!("{} built from source", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_crate_names() {
+        for name in ["espup", "cargo-espflash", "probe-rs-tools", "esp_idf_sys"] {
+            assert!(validate_crate_name(name).is_ok(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn rejects_option_like_crate_names() {
+        for name in [
+            "",
+            "--git",
+            "-espup",
+            "1tool",
+            "espup@1.0",
+            "esp up",
+            "../espup",
+        ] {
+            assert!(validate_crate_name(name).is_err(), "{}", name);
+        }
+        assert!(validate_crate_name(&"a".repeat(65)).is_err());
+    }
+
+    #[test]
+    fn accepts_semver_versions() {
+        for version in [
+            "0.1.0",
+            "3.1.0",
+            "1.0.0-rc.1",
+            "0.2.0-beta+build.5",
+            "10.20.30",
+        ] {
+            assert!(validate_version(version).is_ok(), "{}", version);
+        }
+    }
+
+    #[test]
+    fn rejects_other_versions() {
+        for version in [
+            "",
+            "1",
+            "1.2",
+            "^1.2.3",
+            "01.2.3",
+            "1.2.3-",
+            "1.2.3 --git",
+            "--1.2.3",
+        ] {
+            assert!(validate_version(version).is_err(), "{}", version);
+        }
+    }
+}
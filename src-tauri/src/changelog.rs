@@ -0,0 +1,153 @@
+use std::cmp::Ordering;
+
+use serde_json::Value;
+use tauri::AppHandle;
+
+use crate::cleanup::compare_versions;
+use crate::error::{HelmError, HelmResult};
+use crate::github::github_api;
+use crate::release_metadata::{RELEASES_TTL, RELEASE_SOURCES};
+
+const RELEASES_PER_PAGE: usize = 100;
+// ESP-IDF has hundreds of releases, older notes are linked instead of fetched
+const MAX_PAGES: usize = 3;
+
+#[derive(serde::Serialize)]
+pub struct ChangelogEntry {
+    tag: String,
+    name: Option<String>,
+    published_at: Option<String>,
+    html_url: Option<String>,
+    // Release notes in GitHub flavored Markdown
+    notes: String,
+}
+
+#[derive(serde::Serialize)]
+pub struct ComponentChangelog {
+    name: String,
+    repo: String,
+    from: String,
+    to: Option<String>,
+    // Newest first, releases after "from" up to and including "to"
+    entries: Vec<ChangelogEntry>,
+    // All notes concatenated with release headings, ready to render
+    markdown: String,
+    // False when "from" is older than fetched pages, oldest notes are missing
+    complete: bool,
+    // Notes come from cache of the last online session
+    stale: bool,
+}
+
+fn string_field(value: &Value, key: &str) -> Option<String> {
+    value.get(key).and_then(Value::as_str).map(str::to_string)
+}
+
+// Pre-releases are skipped unless one of them is the offered version
+fn in_range(tag: &str, from: &str, to: Option<&str>) -> bool {
+    if compare_versions(tag, from) != Ordering::Greater {
+        return false;
+    }
+    match to {
+        Some(to) => {
+            tag == to || (!tag.contains('-') && compare_versions(tag, to) == Ordering::Less)
+        }
+        None => !tag.contains('-'),
+    }
+}
+
+fn render_markdown(entries: &[ChangelogEntry]) -> String {
+    entries
+        .iter()
+        .map(|entry| {
+            let title = entry.name.as_deref().unwrap_or(&entry.tag);
+            let date = entry
+                .published_at
+                .as_deref()
+                .and_then(|date| date.get(..10))
+                .map(|date| format!(" ({})", date))
+                .unwrap_or_default();
+            format!("## {}{}\n\n{}\n", title, date, entry.notes.trim())
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+// Command to get release notes of component "name" between installed version "from" and
+// offered version "to", latest release when "to" is not set. Names are those of
+// list_release_versions, e.g. "espup", "espflash", "esp-toolchain" or "esp-idf".
+#[tauri::command]
+pub async fn get_component_changelog(
+    app: AppHandle,
+    name: String,
+    from: String,
+    to: Option<String>,
+) -> HelmResult<ComponentChangelog> {
+    let Some((_, repo)) = RELEASE_SOURCES.iter().find(|(tool, _)| *tool == name) else {
+        return Err(HelmError::NotFound(format!("Component {}", name)));
+    };
+    if let Some(to) = &to {
+        if compare_versions(to, &from) != Ordering::Greater {
+            return Err(HelmError::Validation(format!(
+                "Version {} is not newer than {}",
+                to, from
+            )));
+        }
+    }
+
+    let mut entries = vec![];
+    let mut complete = false;
+    let mut stale = false;
+    for page in 1..=MAX_PAGES {
+        let path = format!(
+            "repos/{}/releases?per_page={}&page={}",
+            repo, RELEASES_PER_PAGE, page
+        );
+        let response = github_api(&app, &path, RELEASES_TTL).await?;
+        stale |= response.stale;
+        let releases = response.body.as_array().cloned().unwrap_or_default();
+        for release in &releases {
+            if release
+                .get("draft")
+                .and_then(Value::as_bool)
+                .unwrap_or(false)
+            {
+                continue;
+            }
+            let Some(tag) = string_field(release, "tag_name") else {
+                continue;
+            };
+            // Older branches get releases later, only "from" itself ends the search
+            if compare_versions(&tag, &from) == Ordering::Equal {
+                complete = true;
+            }
+            if !in_range(&tag, &from, to.as_deref()) {
+                continue;
+            }
+            entries.push(ChangelogEntry {
+                name: string_field(release, "name").filter(|name| !name.is_empty()),
+                published_at: string_field(release, "published_at"),
+                html_url: string_field(release, "html_url"),
+                notes: string_field(release, "body").unwrap_or_default(),
+                tag,
+            });
+        }
+        if complete || releases.len() < RELEASES_PER_PAGE {
+            // Last page reached, there is nothing older to fetch
+            complete |= releases.len() < RELEASES_PER_PAGE;
+            break;
+        }
+    }
+    // Maintenance releases of older branches are interleaved by publish date
+    entries.sort_by(|a, b| compare_versions(&b.tag, &a.tag));
+
+    Ok(ComponentChangelog {
+        name,
+        repo: repo.to_string(),
+        markdown: render_markdown(&entries),
+        from,
+        to,
+        entries,
+        complete,
+        stale,
+    })
+}
@@ -0,0 +1,289 @@
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use log::info;
+
+use crate::adopt::esp_idf_candidates;
+use crate::error::{HelmError, HelmResult};
+use crate::inventory::{dir_size, espressif_home, remove_path, rustup_home};
+
+#[derive(Clone, serde::Serialize)]
+pub struct StaleComponent {
+    path: String,
+    reason: String,
+    size: u64,
+}
+
+#[derive(serde::Serialize)]
+pub struct CleanupReport {
+    components: Vec<StaleComponent>,
+    reclaimable: u64,
+}
+
+// Compare version strings like "esp-16.0.0-20230516" by their numeric parts
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    let numbers = |s: &str| -> Vec<u64> {
+        s.split(|c: char| !c.is_ascii_digit())
+            .filter_map(|part| part.parse().ok())
+            .collect()
+    };
+    numbers(a).cmp(&numbers(b))
+}
+
+fn sorted_dirs(path: &Path) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = match std::fs::read_dir(path) {
+        Ok(entries) => entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.is_dir())
+            .collect(),
+        Err(_) => vec![],
+    };
+    dirs.sort_by(|a, b| {
+        compare_versions(
+            &a.file_name().unwrap_or_default().to_string_lossy(),
+            &b.file_name().unwrap_or_default().to_string_lossy(),
+        )
+    });
+    dirs
+}
+
+// Every version except the newest one is superseded
+fn superseded_versions(component_dir: &Path, reason: &str, stale: &mut Vec<StaleComponent>) {
+    let mut versions = sorted_dirs(component_dir);
+    versions.pop();
+    for version in versions {
+        stale.push(StaleComponent {
+            path: version.to_string_lossy().to_string(),
+            reason: reason.to_string(),
+            size: dir_size(&version),
+        });
+    }
+}
+
+// Tool versions listed in tools.json of installed ESP-IDF versions, and archive names of
+// their downloads
+#[derive(Default)]
+struct IdfToolReferences {
+    versions: HashSet<(String, String)>,
+    archives: HashMap<String, (String, String)>,
+}
+
+impl IdfToolReferences {
+    fn add(&mut self, tools_json: &serde_json::Value) {
+        let list = |value: &serde_json::Value| value.as_array().cloned().unwrap_or_default();
+        for tool in list(&tools_json["tools"]) {
+            let Some(name) = tool["name"].as_str() else {
+                continue;
+            };
+            for version in list(&tool["versions"]) {
+                let Some(version_name) = version["name"].as_str() else {
+                    continue;
+                };
+                let key = (name.to_string(), version_name.to_string());
+                // Download of each platform is an object with url
+                if let Some(platforms) = version.as_object() {
+                    for url in platforms.values().filter_map(|p| p["url"].as_str()) {
+                        let archive = url.rsplit('/').next().unwrap_or(url);
+                        self.archives.insert(archive.to_string(), key.clone());
+                    }
+                }
+                self.versions.insert(key);
+            }
+        }
+    }
+
+    fn contains(&self, tool: &str, version: &str) -> bool {
+        self.versions
+            .contains(&(tool.to_string(), version.to_string()))
+    }
+}
+
+// ESP-IDF checkouts, downloaded by esp-helm or found where adopt looks for them
+fn idf_tools_jsons(espressif_dir: &Path) -> Vec<PathBuf> {
+    let mut idf_dirs = esp_idf_candidates();
+    idf_dirs.extend(sorted_dirs(&espressif_dir.join("esp-idf")));
+    idf_dirs.extend(sorted_dirs(&espressif_dir.join("idf-tools")));
+    idf_dirs
+        .into_iter()
+        .map(|dir| dir.join("tools").join("tools.json"))
+        .filter(|path| path.exists())
+        .collect()
+}
+
+fn idf_tool_references(espressif_dir: &Path) -> Option<IdfToolReferences> {
+    let jsons = idf_tools_jsons(espressif_dir);
+    if jsons.is_empty() {
+        return None;
+    }
+    let mut references = IdfToolReferences::default();
+    for path in jsons {
+        match std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|text| serde_json::from_str(&text).ok())
+        {
+            Some(json) => references.add(&json),
+            // Unreadable tools.json could reference anything, nothing is offered then
+            None => {
+                info!("Can not read {}", path.display());
+                return None;
+            }
+        }
+    }
+    Some(references)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string()
+}
+
+// Tool versions are shared by ESP-IDF versions, only those no installed ESP-IDF uses are
+// stale. Without any tools.json nothing is known to be unused.
+fn stale_idf_tools(espressif_dir: &Path, stale: &mut Vec<StaleComponent>) {
+    let Some(references) = idf_tool_references(espressif_dir) else {
+        return;
+    };
+    let tools_dir = espressif_dir.join("tools");
+    for tool in sorted_dirs(&tools_dir) {
+        let tool_name = file_name(&tool);
+        for version in sorted_dirs(&tool) {
+            if !references.contains(&tool_name, &file_name(&version)) {
+                stale.push(StaleComponent {
+                    path: version.to_string_lossy().to_string(),
+                    reason: "ESP-IDF tool not used by installed ESP-IDF".into(),
+                    size: dir_size(&version),
+                });
+            }
+        }
+    }
+
+    // Archives are kept by idf_tools.py after extraction, archive of a tool which is not
+    // installed yet may be used by next install
+    let Ok(entries) = std::fs::read_dir(espressif_dir.join("dist")) else {
+        return;
+    };
+    for path in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+        let reason = match references.archives.get(&file_name(&path)) {
+            None => "Orphaned download",
+            Some((tool, version)) if tools_dir.join(tool).join(version).exists() => {
+                "Download of installed ESP-IDF tool"
+            }
+            Some(_) => continue,
+        };
+        stale.push(StaleComponent {
+            path: path.to_string_lossy().to_string(),
+            reason: reason.into(),
+            size: dir_size(&path),
+        });
+    }
+}
+
+pub fn find_stale() -> Vec<StaleComponent> {
+    let mut stale = vec![];
+
+    // Xtensa GCC and LLVM installed by espup
+    if let Some(esp_toolchain) = rustup_home().map(|home| home.join("toolchains").join("esp")) {
+        for component in sorted_dirs(&esp_toolchain) {
+            let name = component
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string();
+            if name.contains("clang") {
+                superseded_versions(&component, "Old LLVM version", &mut stale);
+            } else if name.starts_with("xtensa-esp") || name.starts_with("riscv32-esp") {
+                superseded_versions(&component, "Superseded toolchain", &mut stale);
+            }
+        }
+    }
+
+    if let Some(espressif_dir) = espressif_home() {
+        stale_idf_tools(&espressif_dir, &mut stale);
+    }
+
+    stale
+}
+
+// Command to report components which could be removed
+#[tauri::command]
+pub async fn find_stale_components() -> HelmResult<CleanupReport> {
+    info!("Looking for stale components...");
+    let components = tokio::task::spawn_blocking(find_stale)
+        .await
+        .map_err(|e| HelmError::Other(format!("Stale component scan failed: {}", e)))?;
+    let reclaimable: u64 = components.iter().map(|c| c.size).sum();
+    info!("Reclaimable space: {} MB", reclaimable / 1_000_000);
+    Ok(CleanupReport {
+        components,
+        reclaimable,
+    })
+}
+
+// Command to delete stale components confirmed by user.
+// Paths which are not reported as stale are rejected.
+#[tauri::command]
+pub async fn cleanup_stale_components(paths: Vec<String>) -> HelmResult<u64> {
+    let stale = tokio::task::spawn_blocking(find_stale)
+        .await
+        .map_err(|e| HelmError::Other(format!("Stale component scan failed: {}", e)))?;
+
+    let mut reclaimed = 0;
+    for path in paths {
+        let Some(component) = stale.iter().find(|c| c.path == path) else {
+            return Err(HelmError::Validation(format!(
+                "{} is not a stale component",
+                path
+            )));
+        };
+        remove_path(Path::new(&component.path))?;
+        reclaimed += component.size;
+    }
+
+    info!("Reclaimed {} MB", reclaimed / 1_000_000);
+    Ok(reclaimed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn references_all_versions_of_each_esp_idf() {
+        let v5_0 = serde_json::json!({"tools": [{
+            "name": "xtensa-esp-elf",
+            "versions": [{
+                "name": "esp-12.2.0_20230208",
+                "status": "recommended",
+                "linux-amd64": {"url": "https://example.com/xtensa-esp-elf-12.2.0-linux.tar.xz"}
+            }]
+        }]});
+        let v5_1 = serde_json::json!({"tools": [{
+            "name": "xtensa-esp-elf",
+            "versions": [{
+                "name": "esp-13.2.0_20230928",
+                "status": "recommended",
+                "linux-amd64": {"url": "https://example.com/xtensa-esp-elf-13.2.0-linux.tar.xz"}
+            }]
+        }]});
+        let mut references = IdfToolReferences::default();
+        references.add(&v5_0);
+        references.add(&v5_1);
+
+        assert!(references.contains("xtensa-esp-elf", "esp-12.2.0_20230208"));
+        assert!(references.contains("xtensa-esp-elf", "esp-13.2.0_20230928"));
+        assert!(!references.contains("xtensa-esp-elf", "esp-11.2.0_20220912"));
+        assert_eq!(
+            references
+                .archives
+                .get("xtensa-esp-elf-12.2.0-linux.tar.xz"),
+            Some(&(
+                "xtensa-esp-elf".to_string(),
+                "esp-12.2.0_20230208".to_string()
+            ))
+        );
+    }
+}
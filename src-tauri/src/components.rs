@@ -0,0 +1,492 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use log::info;
+use serde_json::Value;
+use tauri::{AppHandle, Window};
+
+use crate::atomic_file::write_atomic;
+use crate::audit::{audit_path, AuditAction};
+use crate::error::{HelmError, HelmResult};
+use crate::external_command::run_external_command_with_progress;
+use crate::package_manager::find_in_path;
+
+const REGISTRY_API: &str = "https://components.espressif.com/api";
+// Registry responses are reused for this long, stale cache is used when offline
+const CACHE_TTL: Duration = Duration::from_secs(60 * 60);
+
+#[derive(serde::Serialize)]
+pub struct ComponentSummary {
+    namespace: String,
+    name: String,
+    description: Option<String>,
+    latest_version: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+pub struct ComponentVersion {
+    version: String,
+    docs: Option<String>,
+    license: Option<String>,
+    url: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+pub struct ComponentDetails {
+    namespace: String,
+    name: String,
+    description: Option<String>,
+    repository: Option<String>,
+    versions: Vec<ComponentVersion>,
+}
+
+#[derive(serde::Serialize)]
+pub struct ComponentChange {
+    manifest: String,
+    // dependencies.lock was regenerated by idf.py
+    lock_updated: bool,
+}
+
+fn cache_file(url: &str) -> Option<PathBuf> {
+    let mut hasher = DefaultHasher::new();
+    url.hash(&mut hasher);
+    dirs::cache_dir().map(|dir| {
+        dir.join("esp-helm")
+            .join("http")
+            .join(format!("{:x}.json", hasher.finish()))
+    })
+}
+
+fn read_cache(path: &Path, max_age: Option<Duration>) -> Option<Value> {
+    let modified = std::fs::metadata(path).ok()?.modified().ok()?;
+    if let Some(max_age) = max_age {
+        if modified.elapsed().ok()? > max_age {
+            return None;
+        }
+    }
+    serde_json::from_str(&std::fs::read_to_string(path).ok()?).ok()
+}
+
+// Cached GET request returning JSON
+pub async fn fetch_json(url: &str) -> HelmResult<Value> {
+    let cache = cache_file(url);
+    if let Some(value) = cache
+        .as_deref()
+        .and_then(|path| read_cache(path, Some(CACHE_TTL)))
+    {
+        return Ok(value);
+    }
+
+    // crates.io rejects requests without user agent
+    let response: Result<String, reqwest::Error> = async {
+        reqwest::Client::new()
+            .get(url)
+            .header(reqwest::header::USER_AGENT, "esp-helm")
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await
+    }
+    .await;
+    match response {
+        Ok(text) => {
+            let value: Value = serde_json::from_str(&text)
+                .map_err(|e| HelmError::Other(format!("Invalid registry response: {}", e)))?;
+            if let Some(path) = &cache {
+                if let Some(parent) = path.parent() {
+                    tokio::fs::create_dir_all(parent).await?;
+                }
+                write_atomic(path, text.as_bytes()).await?;
+            }
+            Ok(value)
+        }
+        Err(e) => {
+            info!("Registry request failed, using cache: {}", e);
+            cache
+                .as_deref()
+                .and_then(|path| read_cache(path, None))
+                .ok_or(e.into())
+        }
+    }
+}
+
+fn string_field(value: &Value, key: &str) -> Option<String> {
+    value.get(key).and_then(Value::as_str).map(str::to_string)
+}
+
+fn parse_summary(value: &Value) -> Option<ComponentSummary> {
+    Some(ComponentSummary {
+        namespace: string_field(value, "namespace")?,
+        name: string_field(value, "name")?,
+        description: string_field(value, "description"),
+        latest_version: value
+            .get("versions")
+            .and_then(Value::as_array)
+            .and_then(|versions| versions.first())
+            .and_then(|version| string_field(version, "version")),
+    })
+}
+
+// Command to search the ESP Component Registry
+#[tauri::command]
+pub async fn search_components(query: String) -> HelmResult<Vec<ComponentSummary>> {
+    let url = reqwest::Url::parse_with_params(
+        &format!("{}/components", REGISTRY_API),
+        &[("search", query.as_str())],
+    )
+    .map_err(|e| HelmError::Validation(e.to_string()))?;
+    let value = fetch_json(url.as_str()).await?;
+    let results = value
+        .as_array()
+        .or_else(|| value.get("results").and_then(Value::as_array))
+        .cloned()
+        .unwrap_or_default();
+    Ok(results.iter().filter_map(parse_summary).collect())
+}
+
+// Command to get versions and documentation links of component "namespace/name"
+#[tauri::command]
+pub async fn get_component_details(component: String) -> HelmResult<ComponentDetails> {
+    let (namespace, name) = split_component(&component)?;
+    let value = fetch_json(&format!(
+        "{}/components/{}/{}",
+        REGISTRY_API, namespace, name
+    ))
+    .await?;
+
+    let versions = value
+        .get("versions")
+        .and_then(Value::as_array)
+        .map(|versions| {
+            versions
+                .iter()
+                .filter_map(|version| {
+                    Some(ComponentVersion {
+                        version: string_field(version, "version")?,
+                        docs: version
+                            .get("docs")
+                            .and_then(|docs| string_field(docs, "readme"))
+                            .or_else(|| string_field(version, "documentation")),
+                        license: version
+                            .get("license")
+                            .and_then(|license| string_field(license, "name")),
+                        url: string_field(version, "url"),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Ok(ComponentDetails {
+        namespace: namespace.to_string(),
+        name: name.to_string(),
+        description: string_field(&value, "description"),
+        repository: value
+            .get("metadata")
+            .and_then(|metadata| string_field(metadata, "repository")),
+        versions,
+    })
+}
+
+fn split_component(component: &str) -> HelmResult<(&str, &str)> {
+    component
+        .split_once('/')
+        .filter(|(namespace, name)| !namespace.is_empty() && !name.is_empty())
+        .ok_or(HelmError::Validation(format!(
+            "Component must have form namespace/name, got {}",
+            component
+        )))
+}
+
+// Key of indented "  key: value" line
+fn yaml_key(line: &str) -> Option<&str> {
+    let trimmed = line.trim_start();
+    if trimmed.len() == line.len() {
+        return None;
+    }
+    let (key, _) = trimmed.split_once(':')?;
+    Some(key.trim().trim_matches(['"', '\'']))
+}
+
+// Remove dependency from manifest together with its nested lines
+fn remove_yaml_dependency(lines: &mut Vec<String>, component: &str) -> bool {
+    let Some(start) = lines
+        .iter()
+        .position(|line| yaml_key(line) == Some(component))
+    else {
+        return false;
+    };
+
+    let indent = line_indent(&lines[start]);
+    let end = lines[start + 1..]
+        .iter()
+        .position(|line| !line.trim().is_empty() && line_indent(line) <= indent)
+        .map(|offset| start + 1 + offset)
+        .unwrap_or(lines.len());
+    lines.drain(start..end);
+    true
+}
+
+fn line_indent(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+// Manifest with dependency replaced, added or removed. Comments and other dependencies are
+// kept as they are, None when dependency to remove is not there.
+fn edit_idf_content(content: &str, component: &str, version: Option<&str>) -> Option<String> {
+    let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+    let removed = remove_yaml_dependency(&mut lines, component);
+
+    match version {
+        Some(version) => {
+            let section = lines.iter().position(|line| {
+                let without_comment = line.split('#').next().unwrap_or_default();
+                without_comment.trim_end() == "dependencies:"
+            });
+            let section = match section {
+                Some(section) => section,
+                None => {
+                    lines.push("dependencies:".into());
+                    lines.len() - 1
+                }
+            };
+            // Entries of one mapping must have the same indentation
+            let indent = lines[section + 1..]
+                .iter()
+                .find(|line| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+                .map(|line| line_indent(line))
+                .filter(|indent| *indent > 0)
+                .unwrap_or(2);
+            lines.insert(
+                section + 1,
+                format!("{}{}: \"{}\"", " ".repeat(indent), component, version),
+            );
+        }
+        None if !removed => return None,
+        None => {}
+    }
+    Some(lines.join("\n") + "\n")
+}
+
+fn edit_idf_manifest(path: &Path, component: &str, version: Option<&str>) -> HelmResult<()> {
+    let content = std::fs::read_to_string(path).unwrap_or_default();
+    let content = edit_idf_content(&content, component, version)
+        .ok_or_else(|| HelmError::NotFound(format!("{} in {}", component, path.display())))?;
+    std::fs::write(path, content)?;
+    audit_path(AuditAction::WriteFile, path);
+    Ok(())
+}
+
+// esp-idf-sys reads components from Cargo.toml:
+// [[package.metadata.esp-idf-sys.extra_components]]
+// remote_component = { name = "espressif/mdns", version = "1.2" }
+fn edit_cargo_manifest(path: &Path, component: &str, version: Option<&str>) -> HelmResult<()> {
+    const HEADER: &str = "[[package.metadata.esp-idf-sys.extra_components]]";
+    let content = std::fs::read_to_string(path)?;
+    let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+
+    let name_field = format!("name = \"{}\"", component);
+    let existing = lines.iter().enumerate().position(|(index, line)| {
+        line.trim() == HEADER
+            && lines[index + 1..]
+                .iter()
+                .take_while(|line| !line.trim_start().starts_with('['))
+                .any(|line| line.contains(&name_field))
+    });
+    if let Some(start) = existing {
+        let end = lines[start + 1..]
+            .iter()
+            .position(|line| line.trim_start().starts_with('[') || line.trim().is_empty())
+            .map(|offset| start + 1 + offset)
+            .unwrap_or(lines.len());
+        lines.drain(start..end);
+    }
+
+    match version {
+        Some(version) => {
+            if lines
+                .last()
+                .map(|line| !line.trim().is_empty())
+                .unwrap_or(false)
+            {
+                lines.push(String::new());
+            }
+            lines.push(HEADER.into());
+            lines.push(format!(
+                "remote_component = {{ name = \"{}\", version = \"{}\" }}",
+                component, version
+            ));
+        }
+        None if existing.is_none() => {
+            return Err(HelmError::NotFound(format!(
+                "{} in {}",
+                component,
+                path.display()
+            )))
+        }
+        None => {}
+    }
+    std::fs::write(path, lines.join("\n") + "\n")?;
+    audit_path(AuditAction::WriteFile, path);
+    Ok(())
+}
+
+// Manifest which lists dependencies of the project
+fn project_manifest(project: &Path) -> PathBuf {
+    let cargo_toml = project.join("Cargo.toml");
+    if cargo_toml.exists() {
+        return cargo_toml;
+    }
+    let main_manifest = project.join("main").join("idf_component.yml");
+    if main_manifest.exists() || project.join("main").is_dir() {
+        return main_manifest;
+    }
+    project.join("idf_component.yml")
+}
+
+// Regenerate dependencies.lock, possible only when ESP-IDF environment is exported
+async fn update_lock(window: Window, app: AppHandle, project: &Path) -> bool {
+    if !project.join("CMakeLists.txt").exists() || find_in_path("idf.py").is_none() {
+        return false;
+    }
+    let project = project.to_string_lossy().to_string();
+    run_external_command_with_progress(
+        window,
+        app,
+        "idf.py",
+        &["-C", &project, "update-dependencies"],
+        "PROGRESS_EVENT",
+    )
+    .await
+    .is_ok()
+}
+
+async fn change_component(
+    window: Window,
+    app: AppHandle,
+    project_path: &str,
+    component: &str,
+    version: Option<&str>,
+) -> HelmResult<ComponentChange> {
+    split_component(component)?;
+    let project = Path::new(project_path);
+    let manifest = project_manifest(project);
+    if manifest
+        .extension()
+        .map(|ext| ext == "toml")
+        .unwrap_or(false)
+    {
+        edit_cargo_manifest(&manifest, component, version)?;
+    } else {
+        edit_idf_manifest(&manifest, component, version)?;
+    }
+    info!("{} updated with {}", manifest.display(), component);
+
+    Ok(ComponentChange {
+        manifest: manifest.to_string_lossy().to_string(),
+        lock_updated: update_lock(window, app, project).await,
+    })
+}
+
+// Command to add component to idf_component.yml or esp-idf-sys metadata
+#[tauri::command]
+pub async fn add_component(
+    window: Window,
+    app: AppHandle,
+    project_path: String,
+    component: String,
+    version: String,
+) -> HelmResult<ComponentChange> {
+    change_component(window, app, &project_path, &component, Some(&version)).await
+}
+
+#[tauri::command]
+pub async fn remove_component(
+    window: Window,
+    app: AppHandle,
+    project_path: String,
+    component: String,
+) -> HelmResult<ComponentChange> {
+    change_component(window, app, &project_path, &component, None).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST: &str = "\
+## IDF Component Manager Manifest File
+dependencies:
+  # Required IDF version
+  idf:
+    version: \">=5.0.0\"
+  espressif/mdns:
+    version: \"^1.2.0\"
+    # Pinned until 1.3 is tested
+    public: true
+  espressif/led_strip: \"^2.4.1\"
+description: Blinky example
+";
+
+    #[test]
+    fn updates_existing_dependency() {
+        let content = edit_idf_content(MANIFEST, "espressif/mdns", Some("^1.3.0")).unwrap();
+        assert_eq!(
+            content,
+            "\
+## IDF Component Manager Manifest File
+dependencies:
+  espressif/mdns: \"^1.3.0\"
+  # Required IDF version
+  idf:
+    version: \">=5.0.0\"
+  espressif/led_strip: \"^2.4.1\"
+description: Blinky example
+"
+        );
+    }
+
+    #[test]
+    fn removes_dependency_with_nested_lines() {
+        let content = edit_idf_content(MANIFEST, "espressif/mdns", None).unwrap();
+        assert_eq!(
+            content,
+            "\
+## IDF Component Manager Manifest File
+dependencies:
+  # Required IDF version
+  idf:
+    version: \">=5.0.0\"
+  espressif/led_strip: \"^2.4.1\"
+description: Blinky example
+"
+        );
+        assert!(edit_idf_content(&content, "espressif/mdns", None).is_none());
+    }
+
+    #[test]
+    fn round_trips_manifest() {
+        let added = edit_idf_content(MANIFEST, "espressif/esp_tinyusb", Some("^1.4.0")).unwrap();
+        let removed = edit_idf_content(&added, "espressif/esp_tinyusb", None).unwrap();
+        assert_eq!(removed, MANIFEST);
+    }
+
+    #[test]
+    fn keeps_indentation_of_section() {
+        let manifest = "dependencies:  # managed by esp-helm\n    idf: \">=5.0\"\n";
+        let content = edit_idf_content(manifest, "espressif/mdns", Some("^1.2.0")).unwrap();
+        assert_eq!(
+            content,
+            "dependencies:  # managed by esp-helm\n    espressif/mdns: \"^1.2.0\"\n    idf: \">=5.0\"\n"
+        );
+    }
+
+    #[test]
+    fn creates_dependencies_section() {
+        let content = edit_idf_content("", "espressif/mdns", Some("^1.2.0")).unwrap();
+        assert_eq!(content, "dependencies:\n  espressif/mdns: \"^1.2.0\"\n");
+    }
+}
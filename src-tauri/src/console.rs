@@ -1,11 +1,9 @@
-use log::{Log, Metadata, Record};
+use log::{Level, Log, Metadata, Record};
 use tauri::Manager;
 use tauri::Window;
 
-#[derive(Clone, serde::Serialize)]
-struct ConsoleEvent {
-    message: String,
-}
+use crate::espup_progress::{is_espup_record, log_record};
+use crate::events::{emit_event, ConsoleLine};
 
 pub struct TauriLogger {
     window: Window,
@@ -18,16 +16,20 @@ impl TauriLogger {
 }
 
 impl Log for TauriLogger {
-    fn enabled(&self, _metadata: &Metadata) -> bool {
-        true
+    // Debug records are enabled only for espup progress
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= Level::Info || is_espup_record(metadata.target())
     }
 
     fn log(&self, record: &Record) {
-        if self.enabled(record.metadata()) {
-            let event = ConsoleEvent {
+        if is_espup_record(record.target()) {
+            log_record(record);
+        }
+        if record.level() <= Level::Info {
+            let event = ConsoleLine {
                 message: format!("{}", record.args()),
             };
-            self.window.emit("rust-console", &event).unwrap();
+            emit_event(&self.window, &event);
             println!("{}", record.args()); // Also log to stdout
         }
     }
@@ -0,0 +1,123 @@
+use serde_json::Value;
+use tauri::{AppHandle, Window};
+
+use crate::cargo_tools::cargo_bin;
+use crate::components::fetch_json;
+use crate::error::{HelmError, HelmResult};
+use crate::external_command::run_external_command_with_progress;
+
+const CRATES_API: &str = "https://crates.io/api/v1/crates";
+
+// Crates offered when search query is empty
+const CURATED_CRATES: [&str; 12] = [
+    "esp-hal",
+    "esp-wifi",
+    "esp-backtrace",
+    "esp-println",
+    "esp-alloc",
+    "esp-storage",
+    "esp-idf-svc",
+    "esp-idf-hal",
+    "embassy-executor",
+    "embassy-time",
+    "embassy-net",
+    "embedded-hal",
+];
+
+#[derive(serde::Serialize)]
+pub struct CrateSummary {
+    name: String,
+    description: Option<String>,
+    max_version: Option<String>,
+    downloads: u64,
+    documentation: Option<String>,
+    repository: Option<String>,
+}
+
+fn string_field(value: &Value, key: &str) -> Option<String> {
+    value.get(key).and_then(Value::as_str).map(str::to_string)
+}
+
+fn parse_crate(value: &Value) -> Option<CrateSummary> {
+    Some(CrateSummary {
+        name: string_field(value, "name")?,
+        description: string_field(value, "description"),
+        max_version: string_field(value, "max_stable_version")
+            .or_else(|| string_field(value, "max_version")),
+        downloads: value.get("downloads").and_then(Value::as_u64).unwrap_or(0),
+        documentation: string_field(value, "documentation"),
+        repository: string_field(value, "repository"),
+    })
+}
+
+// Crates from esp-rs and embassy organizations, or named after them
+fn is_esp_ecosystem(summary: &CrateSummary) -> bool {
+    let name = summary.name.as_str();
+    CURATED_CRATES.contains(&name)
+        || name.starts_with("esp-")
+        || name.starts_with("esp32")
+        || name.starts_with("embassy-")
+        || summary
+            .repository
+            .as_deref()
+            .map(|repo| {
+                repo.contains("github.com/esp-rs/") || repo.contains("github.com/embassy-rs/")
+            })
+            .unwrap_or(false)
+}
+
+// Command to search crates.io, results are limited to esp-rs ecosystem
+#[tauri::command]
+pub async fn search_esp_crates(query: String) -> HelmResult<Vec<CrateSummary>> {
+    let query = query.trim();
+    let mut params: Vec<(&str, &str)> = vec![("per_page", "100")];
+    if query.is_empty() {
+        params.extend(CURATED_CRATES.iter().map(|name| ("ids[]", *name)));
+    } else {
+        params.push(("q", query));
+    }
+    let url = reqwest::Url::parse_with_params(CRATES_API, &params)
+        .map_err(|e| HelmError::Validation(e.to_string()))?;
+
+    let value = fetch_json(url.as_str()).await?;
+    let mut crates: Vec<CrateSummary> = value
+        .get("crates")
+        .and_then(Value::as_array)
+        .map(|crates| crates.iter().filter_map(parse_crate).collect())
+        .unwrap_or_default();
+    crates.retain(is_esp_ecosystem);
+    crates.sort_by(|a, b| b.downloads.cmp(&a.downloads));
+    Ok(crates)
+}
+
+// Command to add dependency to project Cargo.toml via cargo add
+#[tauri::command]
+pub async fn add_dependency(
+    window: Window,
+    app: AppHandle,
+    project: String,
+    crate_name: String,
+    version: Option<String>,
+    features: Vec<String>,
+) -> HelmResult<String> {
+    let manifest = std::path::Path::new(&project).join("Cargo.toml");
+    if !manifest.exists() {
+        return Err(HelmError::NotFound(manifest.display().to_string()));
+    }
+
+    let cargo = cargo_bin("cargo")?.to_string_lossy().to_string();
+    let manifest = manifest.to_string_lossy().to_string();
+    let spec = match &version {
+        Some(version) => format!("{}@{}", crate_name, version),
+        None => crate_name.clone(),
+    };
+    let features = features.join(",");
+    let mut args = vec!["add", spec.as_str(), "--manifest-path", manifest.as_str()];
+    if !features.is_empty() {
+        args.push("--features");
+        args.push(&features);
+    }
+
+    run_external_command_with_progress(window, app, &cargo, &args, "PROGRESS_EVENT").await?;
+    Ok(format!("{} added to {}", crate_name, project))
+}
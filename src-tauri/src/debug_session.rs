@@ -0,0 +1,527 @@
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use log::info;
+use tauri::{AppHandle, Manager, State, Window};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+use tokio::process::{Child, Command};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+use crate::app_state::AppState;
+use crate::cargo_tools::cargo_bin;
+use crate::error::{HelmError, HelmResult};
+use crate::events::{emit_event, HelmEvent};
+use crate::package_manager::find_in_path;
+
+// Default GDB port of both OpenOCD and probe-rs
+const GDB_PORT: u16 = 3333;
+// Time for GDB server to open the port before GDB connects
+const SERVER_STARTUP: Duration = Duration::from_secs(2);
+
+// Debug part of AppState, GDB commands are forwarded to the task owning the processes
+pub struct DebugSession {
+    elf: String,
+    commands: UnboundedSender<String>,
+}
+
+#[derive(serde::Serialize)]
+pub struct DebugSessionInfo {
+    elf: String,
+}
+
+#[derive(Clone, serde::Serialize, schemars::JsonSchema)]
+pub struct StopEvent {
+    reason: String,
+    address: Option<String>,
+    function: Option<String>,
+    file: Option<String>,
+    line: Option<u32>,
+    breakpoint: Option<u32>,
+}
+
+impl HelmEvent for StopEvent {
+    const NAMES: &'static [&'static str] = &["debug-stopped"];
+}
+
+#[derive(Clone, serde::Serialize, schemars::JsonSchema)]
+pub struct BreakpointEvent {
+    number: u32,
+    address: Option<String>,
+    file: Option<String>,
+    line: Option<u32>,
+}
+
+impl HelmEvent for BreakpointEvent {
+    const NAMES: &'static [&'static str] = &["debug-breakpoint"];
+}
+
+// GDB console output and output of GDB server
+#[derive(Clone, serde::Serialize, schemars::JsonSchema)]
+pub struct DebugOutput {
+    text: String,
+}
+
+impl HelmEvent for DebugOutput {
+    const NAMES: &'static [&'static str] = &["debug-output"];
+}
+
+// Target resumed, or the whole session ended
+#[derive(Clone, serde::Serialize, schemars::JsonSchema)]
+pub struct DebugStateChange {
+    ended: bool,
+}
+
+impl HelmEvent for DebugStateChange {
+    const NAMES: &'static [&'static str] = &["debug-running", "debug-ended"];
+
+    fn name(&self) -> &'static str {
+        if self.ended {
+            Self::NAMES[1]
+        } else {
+            Self::NAMES[0]
+        }
+    }
+}
+
+fn emit_output(window: &Window, text: String) {
+    emit_event(window, &DebugOutput { text });
+}
+
+fn gdb_name(chip: &str) -> Option<&'static str> {
+    match chip {
+        "esp32" => Some("xtensa-esp32-elf-gdb"),
+        "esp32s2" => Some("xtensa-esp32s2-elf-gdb"),
+        "esp32s3" => Some("xtensa-esp32s3-elf-gdb"),
+        "esp32c2" | "esp32c3" | "esp32c6" | "esp32h2" => Some("riscv32-esp-elf-gdb"),
+        _ => None,
+    }
+}
+
+// Chips with built-in USB-Serial-JTAG need only USB cable, ESP32 and ESP32-S2 need
+// external adapter like ESP-Prog
+fn openocd_board(chip: &str) -> String {
+    match chip {
+        "esp32" => "board/esp32-wrover-kit-3.3v.cfg".into(),
+        "esp32s2" => "board/esp32s2-kaluga-1.cfg".into(),
+        chip => format!("board/{}-builtin.cfg", chip),
+    }
+}
+
+fn server_command(
+    backend: &str,
+    chip: &str,
+    openocd_config: Option<String>,
+    probe: Option<String>,
+) -> HelmResult<(String, Vec<String>)> {
+    match backend {
+        "openocd" => {
+            // Espressif fork is installed as openocd-esp32 by ESP-IDF tools
+            let openocd = find_in_path("openocd")
+                .ok_or(HelmError::NotFound("openocd".into()))?
+                .to_string_lossy()
+                .to_string();
+            let config = openocd_config.unwrap_or_else(|| openocd_board(chip));
+            Ok((openocd, vec!["-f".into(), config]))
+        }
+        "probe-rs" => {
+            let probe_rs = cargo_bin("probe-rs")?.to_string_lossy().to_string();
+            let mut args = vec![
+                "gdb".into(),
+                "--chip".into(),
+                chip.into(),
+                "--gdb-connection-string".into(),
+                format!("127.0.0.1:{}", GDB_PORT),
+            ];
+            if let Some(probe) = probe {
+                args.push("--probe".into());
+                args.push(probe);
+            }
+            Ok((probe_rs, args))
+        }
+        _ => Err(HelmError::Validation(format!(
+            "Unknown debug backend {}",
+            backend
+        ))),
+    }
+}
+
+fn spawn(cmd_name: &str, args: &[String]) -> HelmResult<Child> {
+    info!("Command: {} {}", cmd_name, args.join(" "));
+    Command::new(cmd_name)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => HelmError::NotFound(format!("{}: {}", cmd_name, e)),
+            _ => HelmError::from(e),
+        })
+}
+
+// Value of key="value" in GDB/MI record, nested tuples are not parsed
+fn mi_field(record: &str, key: &str) -> Option<String> {
+    let start = record.find(&format!("{}=\"", key))? + key.len() + 2;
+    let mut value = String::new();
+    let mut escaped = false;
+    for c in record[start..].chars() {
+        match (escaped, c) {
+            (false, '\\') => escaped = true,
+            (false, '"') => return Some(value),
+            (true, 'n') => {
+                value.push('\n');
+                escaped = false;
+            }
+            (_, c) => {
+                value.push(c);
+                escaped = false;
+            }
+        }
+    }
+    None
+}
+
+// Event of GDB/MI record which is forwarded to the frontend
+enum MiEvent {
+    Stopped(StopEvent),
+    Running,
+    Breakpoint(BreakpointEvent),
+    Output(String),
+}
+
+// *stopped,reason="breakpoint-hit",bkptno="1",frame={addr="0x42000123",func="main",file="src/main.rs",line="12"}
+fn parse_mi_record(record: &str) -> Option<MiEvent> {
+    if let Some(stopped) = record.strip_prefix("*stopped") {
+        Some(MiEvent::Stopped(StopEvent {
+            reason: mi_field(stopped, "reason").unwrap_or_else(|| "signal-received".into()),
+            address: mi_field(stopped, "addr"),
+            function: mi_field(stopped, "func"),
+            file: mi_field(stopped, "fullname").or(mi_field(stopped, "file")),
+            line: mi_field(stopped, "line").and_then(|line| line.parse().ok()),
+            breakpoint: mi_field(stopped, "bkptno").and_then(|number| number.parse().ok()),
+        }))
+    } else if record.starts_with("*running") {
+        Some(MiEvent::Running)
+    } else if let Some(bkpt) = record.strip_prefix("^done,bkpt=") {
+        let number = mi_field(bkpt, "number")?.parse().ok()?;
+        Some(MiEvent::Breakpoint(BreakpointEvent {
+            number,
+            address: mi_field(bkpt, "addr"),
+            file: mi_field(bkpt, "fullname").or(mi_field(bkpt, "file")),
+            line: mi_field(bkpt, "line").and_then(|line| line.parse().ok()),
+        }))
+    } else if let Some(stream) = record
+        .strip_prefix('~')
+        .or(record.strip_prefix('@'))
+        .or(record.strip_prefix('&'))
+    {
+        let text = mi_field(&format!("s={}", stream), "s").unwrap_or_default();
+        Some(MiEvent::Output(text))
+    } else if record.starts_with("^error") {
+        let message = mi_field(record, "msg").unwrap_or_else(|| record.to_string());
+        Some(MiEvent::Output(message))
+    } else {
+        None
+    }
+}
+
+fn handle_mi_record(window: &Window, record: &str) {
+    match parse_mi_record(record) {
+        Some(MiEvent::Stopped(event)) => emit_event(window, &event),
+        Some(MiEvent::Running) => emit_event(window, &DebugStateChange { ended: false }),
+        Some(MiEvent::Breakpoint(event)) => emit_event(window, &event),
+        Some(MiEvent::Output(text)) => emit_output(window, text),
+        None => {}
+    }
+}
+
+// Location as GDB/MI c-string, so spaces and quotes can not add options or commands
+fn mi_quote(value: &str) -> HelmResult<String> {
+    if value.is_empty() || value.chars().any(char::is_control) {
+        return Err(HelmError::Validation(format!(
+            "Invalid breakpoint location {:?}",
+            value
+        )));
+    }
+    Ok(format!(
+        "\"{}\"",
+        value.replace('\\', "\\\\").replace('"', "\\\"")
+    ))
+}
+
+// Owns GDB server and GDB, ends when GDB exits or the session is stopped
+async fn run_session(
+    window: Window,
+    app: AppHandle,
+    mut server: Child,
+    mut gdb: Child,
+    mut commands: UnboundedReceiver<String>,
+) {
+    let mut gdb_stdin = gdb.stdin.take().unwrap();
+    let mut gdb_stdout = tokio::io::BufReader::new(gdb.stdout.take().unwrap()).lines();
+    let mut server_stdout = tokio::io::BufReader::new(server.stdout.take().unwrap()).lines();
+    let mut server_stderr = tokio::io::BufReader::new(server.stderr.take().unwrap()).lines();
+    let mut server_stdout_open = true;
+
+    loop {
+        tokio::select! {
+            line = gdb_stdout.next_line() => match line {
+                Ok(Some(line)) => handle_mi_record(&window, &line),
+                _ => break,
+            },
+            line = server_stdout.next_line(), if server_stdout_open => match line {
+                Ok(Some(line)) => emit_output(&window, line),
+                _ => server_stdout_open = false,
+            },
+            line = server_stderr.next_line() => match line {
+                Ok(Some(line)) => emit_output(&window, line),
+                _ => {
+                    info!("GDB server exited");
+                    break;
+                }
+            },
+            command = commands.recv() => match command {
+                Some(command) => {
+                    if gdb_stdin.write_all(format!("{}\n", command).as_bytes()).await.is_err() {
+                        break;
+                    }
+                }
+                None => {
+                    let _ = gdb_stdin.write_all(b"-gdb-exit\n").await;
+                    break;
+                }
+            },
+        }
+    }
+
+    drop(commands);
+    let _ = gdb.kill().await;
+    let _ = server.kill().await;
+    {
+        // Session may already be replaced by a new one
+        let state_mutex = app.state::<Mutex<AppState>>();
+        let mut state = state_mutex.lock().unwrap();
+        if matches!(&state.debug_session, Some(session) if session.commands.is_closed()) {
+            state.debug_session = None;
+        }
+    }
+    emit_event(&window, &DebugStateChange { ended: true });
+}
+
+fn send(state_mutex: &State<'_, Mutex<AppState>>, command: String) -> HelmResult<String> {
+    let state = state_mutex.lock().unwrap();
+    let session = state
+        .debug_session
+        .as_ref()
+        .ok_or(HelmError::NotFound("Debug session".into()))?;
+    session
+        .commands
+        .send(command.clone())
+        .map_err(|_| HelmError::Other("Debug session ended".into()))?;
+    Ok(command)
+}
+
+// Command to start GDB server (OpenOCD or probe-rs) and GDB for ELF. The target is
+// halted after reset, breakpoints are set before debug_continue.
+#[tauri::command]
+pub async fn start_debug_session(
+    window: Window,
+    app: AppHandle,
+    elf: String,
+    chip: String,
+    backend: String,
+    openocd_config: Option<String>,
+    probe: Option<String>,
+) -> HelmResult<DebugSessionInfo> {
+    // Session is reserved under the same lock as the check, so two sessions can not start
+    // at the same time. Commands sent before GDB runs wait in the channel.
+    let (sender, receiver) = unbounded_channel();
+    {
+        let state_mutex = app.state::<Mutex<AppState>>();
+        let mut state = state_mutex.lock().unwrap();
+        if state.debug_session.is_some() {
+            return Err(HelmError::Validation(
+                "Debug session is already running".into(),
+            ));
+        }
+        state.debug_session = Some(DebugSession {
+            elf: elf.clone(),
+            commands: sender.clone(),
+        });
+    }
+    let mut init = vec![format!("-target-select extended-remote :{}", GDB_PORT)];
+    if backend == "openocd" {
+        init.push("-interpreter-exec console \"monitor reset halt\"".into());
+        init.push("-interpreter-exec console \"maintenance flush register-cache\"".into());
+    }
+    for command in init {
+        // Receiver is alive until run_session is spawned
+        let _ = sender.send(command);
+    }
+    let started = spawn_processes(&elf, &chip, &backend, openocd_config, probe).await;
+    let (server, gdb) = match started {
+        Ok(processes) => processes,
+        Err(e) => {
+            let state_mutex = app.state::<Mutex<AppState>>();
+            let mut state = state_mutex.lock().unwrap();
+            let reserved = state
+                .debug_session
+                .as_ref()
+                .is_some_and(|session| session.commands.same_channel(&sender));
+            if reserved {
+                state.debug_session = None;
+            }
+            return Err(e);
+        }
+    };
+
+    tokio::spawn(run_session(window, app, server, gdb, receiver));
+    Ok(DebugSessionInfo { elf })
+}
+
+async fn spawn_processes(
+    elf: &str,
+    chip: &str,
+    backend: &str,
+    openocd_config: Option<String>,
+    probe: Option<String>,
+) -> HelmResult<(Child, Child)> {
+    if !PathBuf::from(elf).exists() {
+        return Err(HelmError::NotFound(elf.to_string()));
+    }
+    let gdb_name = gdb_name(chip).ok_or(HelmError::Validation(format!("Unknown chip {}", chip)))?;
+    let gdb_path = find_in_path(gdb_name)
+        .ok_or(HelmError::NotFound(gdb_name.into()))?
+        .to_string_lossy()
+        .to_string();
+
+    let (server_cmd, server_args) = server_command(backend, chip, openocd_config, probe)?;
+    let server = spawn(&server_cmd, &server_args)?;
+    tokio::time::sleep(SERVER_STARTUP).await;
+
+    let gdb = spawn(
+        &gdb_path,
+        &[
+            "--interpreter=mi2".into(),
+            "--quiet".into(),
+            elf.to_string(),
+        ],
+    )?;
+    Ok((server, gdb))
+}
+
+// Command to stop debug session, GDB server and GDB are terminated
+#[tauri::command]
+pub async fn stop_debug_session(state_mutex: State<'_, Mutex<AppState>>) -> HelmResult<String> {
+    let mut state = state_mutex.lock().unwrap();
+    match state.debug_session.take() {
+        Some(session) => Ok(format!("Debug session of {} stopped", session.elf)),
+        None => Err(HelmError::NotFound("Debug session".into())),
+    }
+}
+
+// Command to set breakpoint at function, file:line or *address
+#[tauri::command]
+pub async fn debug_set_breakpoint(
+    state_mutex: State<'_, Mutex<AppState>>,
+    location: String,
+) -> HelmResult<String> {
+    // Hardware breakpoints, code runs from flash where software breakpoints need flash writes
+    send(
+        &state_mutex,
+        format!("-break-insert -h {}", mi_quote(&location)?),
+    )
+}
+
+// Command to remove breakpoint by number from debug-breakpoint event
+#[tauri::command]
+pub async fn debug_remove_breakpoint(
+    state_mutex: State<'_, Mutex<AppState>>,
+    number: u32,
+) -> HelmResult<String> {
+    send(&state_mutex, format!("-break-delete {}", number))
+}
+
+// Command to continue execution until breakpoint or debug_halt
+#[tauri::command]
+pub async fn debug_continue(state_mutex: State<'_, Mutex<AppState>>) -> HelmResult<String> {
+    send(&state_mutex, "-exec-continue".into())
+}
+
+// Command to halt running target
+#[tauri::command]
+pub async fn debug_halt(state_mutex: State<'_, Mutex<AppState>>) -> HelmResult<String> {
+    send(&state_mutex, "-exec-interrupt".into())
+}
+
+// Command to step one source line, into calls unless over is set
+#[tauri::command]
+pub async fn debug_step(state_mutex: State<'_, Mutex<AppState>>, over: bool) -> HelmResult<String> {
+    let command = if over { "-exec-next" } else { "-exec-step" };
+    send(&state_mutex, command.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_escaped_mi_fields() {
+        let record = r#"reason="breakpoint-hit",msg="say \"hi\"\n",file="src\\main.rs""#;
+        assert_eq!(
+            mi_field(record, "reason").as_deref(),
+            Some("breakpoint-hit")
+        );
+        assert_eq!(mi_field(record, "msg").as_deref(), Some("say \"hi\"\n"));
+        assert_eq!(mi_field(record, "file").as_deref(), Some("src\\main.rs"));
+        assert_eq!(mi_field(record, "line"), None);
+        assert_eq!(mi_field(r#"msg="unterminated"#, "msg"), None);
+    }
+
+    #[test]
+    fn parses_stop_and_breakpoint_records() {
+        let record = r#"*stopped,reason="breakpoint-hit",disp="keep",bkptno="1",frame={addr="0x42000123",func="main",args=[],file="src/main.rs",fullname="/project/src/main.rs",line="12"}"#;
+        let Some(MiEvent::Stopped(stop)) = parse_mi_record(record) else {
+            panic!("stop event expected");
+        };
+        assert_eq!(stop.reason, "breakpoint-hit");
+        assert_eq!(stop.address.as_deref(), Some("0x42000123"));
+        assert_eq!(stop.function.as_deref(), Some("main"));
+        assert_eq!(stop.file.as_deref(), Some("/project/src/main.rs"));
+        assert_eq!(stop.line, Some(12));
+        assert_eq!(stop.breakpoint, Some(1));
+
+        let record = r#"^done,bkpt={number="2",type="hw breakpoint",addr="0x42000200",file="src/main.rs",line="20"}"#;
+        let Some(MiEvent::Breakpoint(breakpoint)) = parse_mi_record(record) else {
+            panic!("breakpoint event expected");
+        };
+        assert_eq!(breakpoint.number, 2);
+        assert_eq!(breakpoint.file.as_deref(), Some("src/main.rs"));
+        assert_eq!(breakpoint.line, Some(20));
+
+        assert!(matches!(
+            parse_mi_record("*running,thread-id=\"all\""),
+            Some(MiEvent::Running)
+        ));
+        assert!(matches!(
+            parse_mi_record(r#"~"Breakpoint 1, main ()\n""#),
+            Some(MiEvent::Output(text)) if text == "Breakpoint 1, main ()\n"
+        ));
+        assert!(matches!(
+            parse_mi_record(r#"^error,msg="No symbol table is loaded.""#),
+            Some(MiEvent::Output(text)) if text == "No symbol table is loaded."
+        ));
+        assert!(parse_mi_record("(gdb) ").is_none());
+    }
+
+    #[test]
+    fn breakpoint_location_is_quoted() {
+        assert_eq!(mi_quote("src/main.rs:12").unwrap(), "\"src/main.rs:12\"");
+        assert_eq!(mi_quote(r#"main" -c "1"#).unwrap(), r#""main\" -c \"1""#);
+        assert!(mi_quote("main\n-gdb-exit").is_err());
+        assert!(mi_quote("").is_err());
+    }
+}
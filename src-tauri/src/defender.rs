@@ -0,0 +1,197 @@
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use log::info;
+
+use crate::error::{HelmError, HelmResult};
+use crate::inventory::{cargo_home, espressif_home, rustup_home};
+use crate::system_install::run_elevated;
+
+#[cfg(windows)]
+use std::os::windows::process::CommandExt;
+#[cfg(windows)]
+const CREATE_NO_WINDOW: u32 = 0x08000000; // Windows specific constant to hide console window
+
+// Files written by the benchmark, extraction of toolchains writes tens of thousands
+const BENCHMARK_FILES: usize = 200;
+const BENCHMARK_FILE_SIZE: usize = 16 * 1024;
+// Unscanned NTFS writes small files well below this, real-time scanning is far above
+const SLOW_FILE_MICROS: u64 = 1000;
+
+#[derive(serde::Serialize)]
+pub struct DirectoryBenchmark {
+    path: String,
+    excluded: Option<bool>,
+    // Average time to create, write and read back one small file
+    micros_per_file: u64,
+    slow: bool,
+}
+
+#[derive(Default, serde::Serialize)]
+pub struct DefenderStatus {
+    // Microsoft Defender is present, always false outside of Windows
+    available: bool,
+    realtime_enabled: Option<bool>,
+    // None when exclusions can not be read, newer Windows requires administrator for it
+    exclusions: Option<Vec<String>>,
+    directories: Vec<DirectoryBenchmark>,
+}
+
+// Toolchain directories which are offered for exclusion
+pub fn exclusion_candidates() -> Vec<PathBuf> {
+    [rustup_home(), cargo_home(), espressif_home()]
+        .into_iter()
+        .flatten()
+        .filter(|path| path.exists())
+        .collect()
+}
+
+#[cfg(windows)]
+fn powershell(script: &str) -> Option<String> {
+    let output = std::process::Command::new("powershell")
+        .args(["-NoProfile", "-Command", script])
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[cfg(unix)]
+fn powershell(_script: &str) -> Option<String> {
+    None
+}
+
+fn realtime_enabled() -> Option<bool> {
+    let output = powershell("(Get-MpComputerStatus).RealTimeProtectionEnabled")?;
+    match output.as_str() {
+        "True" => Some(true),
+        "False" => Some(false),
+        _ => None,
+    }
+}
+
+fn current_exclusions() -> Option<Vec<String>> {
+    let output = powershell("(Get-MpPreference).ExclusionPath")?;
+    if output.starts_with("N/A") {
+        return None;
+    }
+    Some(
+        output
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect(),
+    )
+}
+
+fn is_excluded(path: &Path, exclusions: &[String]) -> bool {
+    let path = path.to_string_lossy().to_lowercase();
+    exclusions.iter().any(|exclusion| {
+        let exclusion = exclusion.trim_end_matches('\\').to_lowercase();
+        path == exclusion || path.starts_with(&format!("{}\\", exclusion))
+    })
+}
+
+// Small files are where scanning costs most, e.g. rustlib sources and ESP-IDF components
+fn benchmark_directory(dir: &Path) -> HelmResult<u64> {
+    let bench = dir.join(".esp-helm-scan-benchmark");
+    std::fs::create_dir_all(&bench)?;
+    let content = vec![0x5a_u8; BENCHMARK_FILE_SIZE];
+    let start = Instant::now();
+    let result = (|| -> HelmResult<()> {
+        for index in 0..BENCHMARK_FILES {
+            let file = bench.join(format!("file{}.rs", index));
+            std::fs::write(&file, &content)?;
+            std::fs::read(&file)?;
+        }
+        Ok(())
+    })();
+    let elapsed = start.elapsed();
+    let _ = std::fs::remove_dir_all(&bench);
+    result?;
+    Ok(elapsed.as_micros() as u64 / BENCHMARK_FILES as u64)
+}
+
+pub fn defender_status() -> DefenderStatus {
+    let realtime_enabled = realtime_enabled();
+    let mut status = DefenderStatus {
+        available: realtime_enabled.is_some(),
+        realtime_enabled,
+        exclusions: current_exclusions(),
+        directories: vec![],
+    };
+    if !status.available {
+        return status;
+    }
+    for dir in exclusion_candidates() {
+        match benchmark_directory(&dir) {
+            Ok(micros_per_file) => status.directories.push(DirectoryBenchmark {
+                path: dir.to_string_lossy().to_string(),
+                excluded: status
+                    .exclusions
+                    .as_ref()
+                    .map(|exclusions| is_excluded(&dir, exclusions)),
+                micros_per_file,
+                slow: micros_per_file > SLOW_FILE_MICROS,
+            }),
+            Err(e) => info!("Unable to benchmark {}: {}", dir.display(), e),
+        }
+    }
+    status
+}
+
+// Command to measure how much real-time scanning slows file operations in toolchain directories
+#[tauri::command]
+pub async fn get_defender_status() -> HelmResult<DefenderStatus> {
+    Ok(defender_status())
+}
+
+// Command to list directories add_defender_exclusions would exclude, shown before confirmation
+#[tauri::command]
+pub async fn plan_defender_exclusions() -> HelmResult<Vec<String>> {
+    Ok(exclusion_candidates()
+        .iter()
+        .map(|path| path.to_string_lossy().to_string())
+        .collect())
+}
+
+// Command to exclude confirmed toolchain directories from scanning, asks for administrator rights.
+// Only directories from plan_defender_exclusions are accepted.
+#[tauri::command]
+pub async fn add_defender_exclusions(paths: Vec<String>) -> HelmResult<String> {
+    if !cfg!(windows) {
+        return Err(HelmError::Validation(
+            "Defender exclusions are only available on Windows".into(),
+        ));
+    }
+    let candidates = exclusion_candidates();
+    for path in &paths {
+        if !candidates
+            .iter()
+            .any(|candidate| candidate == Path::new(path))
+        {
+            return Err(HelmError::Validation(format!(
+                "{} is not a toolchain directory",
+                path
+            )));
+        }
+    }
+    if paths.is_empty() {
+        return Ok("No directories selected".into());
+    }
+
+    let quoted: Vec<String> = paths
+        .iter()
+        .map(|path| format!("'{}'", path.replace('\'', "''")))
+        .collect();
+    let script = format!("Add-MpPreference -ExclusionPath {}", quoted.join(","));
+    run_elevated("powershell", &["-NoProfile", "-Command", &script])?;
+    for path in &paths {
+        info!("Excluded {} from Defender scanning", path);
+    }
+    Ok(format!("{} directories excluded", paths.len()))
+}
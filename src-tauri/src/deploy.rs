@@ -0,0 +1,253 @@
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use log::info;
+use tauri::{AppHandle, Manager, Window};
+
+use crate::app_state::{AppState, JobId};
+use crate::cargo_tools::cargo_bin;
+use crate::error::{HelmError, HelmResult};
+use crate::events::{emit_event, HelmEvent};
+use crate::external_command::run_external_command_in_dir;
+use crate::flasher::flash_elf;
+use crate::jobs::{spawn_job, wait_job};
+use crate::monitor::monitor_session;
+use crate::operations::OperationKind;
+
+#[derive(Clone, serde::Deserialize)]
+pub struct DeployOptions {
+    #[serde(default = "default_true")]
+    release: bool,
+    #[serde(default)]
+    features: Vec<String>,
+    // Attach monitor after flashing, same as cargo espflash flash --monitor
+    #[serde(default = "default_true")]
+    monitor: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+// Last deploy command, repeated by tray quick action
+#[derive(Clone)]
+pub struct LastDeploy {
+    pub project: String,
+    pub port: String,
+    options: DeployOptions,
+}
+
+#[derive(Clone, serde::Serialize, schemars::JsonSchema)]
+pub struct DeployStage {
+    stage: String,
+    job: JobId,
+}
+
+impl HelmEvent for DeployStage {
+    const NAMES: &'static [&'static str] = &["deploy-stage"];
+}
+
+#[derive(serde::Deserialize)]
+struct CargoManifest {
+    package: CargoPackage,
+}
+
+#[derive(serde::Deserialize)]
+struct CargoPackage {
+    name: String,
+}
+
+#[derive(Default, serde::Deserialize)]
+struct CargoConfig {
+    #[serde(default)]
+    build: CargoBuildConfig,
+}
+
+#[derive(Default, serde::Deserialize)]
+struct CargoBuildConfig {
+    target: Option<String>,
+    #[serde(rename = "target-dir")]
+    target_dir: Option<String>,
+}
+
+fn read_toml<T: serde::de::DeserializeOwned>(path: &Path) -> HelmResult<T> {
+    let content = std::fs::read_to_string(path)?;
+    toml::from_str(&content)
+        .map_err(|e| HelmError::Validation(format!("{}: {}", path.display(), e)))
+}
+
+// Location of the application ELF produced by cargo build
+pub fn elf_path(project: &Path, release: bool) -> HelmResult<PathBuf> {
+    let manifest: CargoManifest = read_toml(&project.join("Cargo.toml"))?;
+    let config_path = project.join(".cargo").join("config.toml");
+    let config: CargoConfig = if config_path.exists() {
+        read_toml(&config_path)?
+    } else {
+        CargoConfig::default()
+    };
+
+    let mut path = match config.build.target_dir {
+        Some(dir) => project.join(dir),
+        None => project.join("target"),
+    };
+    if let Some(target) = config.build.target {
+        path.push(target);
+    }
+    path.push(if release { "release" } else { "debug" });
+    path.push(manifest.package.name);
+    Ok(path)
+}
+
+// Chip selected by esp-hal/esp-idf feature in Cargo.toml, e.g. features = ["esp32c3"]
+pub fn project_chip(project: &Path) -> Option<String> {
+    const CHIPS: [&str; 7] = [
+        "esp32c2", "esp32c3", "esp32c6", "esp32h2", "esp32s2", "esp32s3", "esp32",
+    ];
+    let manifest = std::fs::read_to_string(project.join("Cargo.toml")).ok()?;
+    CHIPS
+        .iter()
+        .find(|chip| manifest.contains(&format!("\"{}\"", chip)))
+        .map(|chip| chip.to_string())
+}
+
+async fn build_project(
+    window: Window,
+    app: AppHandle,
+    project: PathBuf,
+    options: DeployOptions,
+) -> HelmResult<String> {
+    let cargo = cargo_bin("cargo")?.to_string_lossy().to_string();
+    let features = options.features.join(",");
+    let mut args = vec!["build"];
+    if options.release {
+        args.push("--release");
+    }
+    if !features.is_empty() {
+        args.push("--features");
+        args.push(&features);
+    }
+    run_external_command_in_dir(window, app, Some(&project), &cargo, &args, "PROGRESS_EVENT")
+        .await?;
+
+    let elf = elf_path(&project, options.release)?;
+    if !elf.exists() {
+        return Err(HelmError::NotFound(elf.display().to_string()));
+    }
+    Ok(elf.to_string_lossy().to_string())
+}
+
+async fn flash_project(
+    window: Window,
+    app: AppHandle,
+    build_job: JobId,
+    port: String,
+) -> HelmResult<String> {
+    let elf = wait_job(&app, build_job).await?;
+    info!("Flashing {} to {}", elf, port);
+    flash_elf(window, app.clone(), port.clone(), elf.clone()).await?;
+    {
+        let state_mutex = app.state::<Mutex<AppState>>();
+        let mut state = state_mutex.lock().unwrap();
+        state.flashed_elfs.insert(port, elf.clone());
+    }
+    Ok(elf)
+}
+
+async fn attach_monitor(
+    window: Window,
+    app: AppHandle,
+    flash_job: JobId,
+    port: String,
+    project: PathBuf,
+) -> HelmResult<String> {
+    let elf = wait_job(&app, flash_job).await?;
+    monitor_session(window, app.clone(), port, Some(elf), Some(project)).await?;
+    Ok("Monitoring finished successfully".into())
+}
+
+fn emit_stage(window: &Window, stage: &str, job: JobId) {
+    let payload = DeployStage {
+        stage: stage.to_string(),
+        job,
+    };
+    emit_event(window, &payload);
+}
+
+// Command to build, flash and monitor project, each stage is a separate job
+#[tauri::command]
+pub async fn deploy(
+    window: Window,
+    app: AppHandle,
+    project_path: String,
+    port: String,
+    options: DeployOptions,
+) -> HelmResult<String> {
+    {
+        let state_mutex = app.state::<Mutex<AppState>>();
+        let mut state = state_mutex.lock().unwrap();
+        state.last_deploy = Some(LastDeploy {
+            project: project_path.clone(),
+            port: port.clone(),
+            options: options.clone(),
+        });
+    }
+    let project = PathBuf::from(&project_path);
+    if !project.join("Cargo.toml").exists() {
+        return Err(HelmError::NotFound(format!(
+            "Cargo.toml in {}",
+            project_path
+        )));
+    }
+
+    let build_job = spawn_job(
+        &app,
+        OperationKind::Build,
+        "Build",
+        vec![],
+        build_project(
+            window.clone(),
+            app.clone(),
+            project.clone(),
+            options.clone(),
+        ),
+    );
+    emit_stage(&window, "build", build_job);
+
+    let flash_job = spawn_job(
+        &app,
+        OperationKind::Flash,
+        "Flash",
+        vec![build_job],
+        flash_project(window.clone(), app.clone(), build_job, port.clone()),
+    );
+    emit_stage(&window, "flash", flash_job);
+
+    if !options.monitor {
+        return wait_job(&app, flash_job).await;
+    }
+
+    let monitor_job = spawn_job(
+        &app,
+        OperationKind::Monitor,
+        "Monitor",
+        vec![flash_job],
+        attach_monitor(window.clone(), app.clone(), flash_job, port, project),
+    );
+    emit_stage(&window, "monitor", monitor_job);
+    wait_job(&app, monitor_job).await
+}
+
+// Build and flash last deployed project again, without monitor
+pub async fn flash_last_project(window: Window, app: AppHandle) -> HelmResult<String> {
+    let last = {
+        let state_mutex = app.state::<Mutex<AppState>>();
+        let state = state_mutex.lock().unwrap();
+        state.last_deploy.clone()
+    }
+    .ok_or(HelmError::NotFound("previously deployed project".into()))?;
+    let options = DeployOptions {
+        monitor: false,
+        ..last.options
+    };
+    deploy(window, app, last.project, last.port, options).await
+}
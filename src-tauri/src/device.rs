@@ -0,0 +1,106 @@
+use espflash::command::{Command, CommandType};
+use espflash::flasher::{DeviceInfo, Flasher};
+use log::info;
+
+use crate::error::{HelmError, HelmResult};
+use crate::flasher::{connect, connect_stub};
+use crate::operations::{current_token, with_token};
+
+// Device operations over espflash, they work without Python. Commands of esptool fall back
+// to them when esptool is missing or broken.
+
+fn espflash_error(action: &'static str) -> impl Fn(espflash::error::Error) -> HelmError {
+    move |e| HelmError::Other(format!("{} failed: {:?}", action, e))
+}
+
+// Serial protocol is blocking, so it runs on worker thread
+async fn with_flasher<T: Send + 'static>(
+    port: &str,
+    use_stub: bool,
+    work: impl FnOnce(&mut Flasher) -> HelmResult<T> + Send + 'static,
+) -> HelmResult<T> {
+    let port = port.to_string();
+    let token = current_token();
+    tokio::task::spawn_blocking(move || {
+        with_token(token, || {
+            let mut flasher = if use_stub {
+                connect_stub(&port)?
+            } else {
+                connect(&port, Some(1), Some(0))?
+            };
+            work(&mut flasher)
+        })
+    })
+    .await
+    .map_err(|_| HelmError::Other("Device task panicked".into()))?
+}
+
+// Chip, revision, features, MAC and flash size
+pub async fn device_info(port: &str) -> HelmResult<DeviceInfo> {
+    with_flasher(port, false, |flasher| {
+        flasher
+            .device_info()
+            .map_err(espflash_error("Reading device info"))
+    })
+    .await
+}
+
+// Erased region must be aligned to flash sectors
+const SECTOR_SIZE: u32 = 0x1000;
+
+// Command to erase whole flash, or region when both offset and size are given
+#[tauri::command]
+pub async fn erase_flash(
+    port: String,
+    offset: Option<u32>,
+    size: Option<u32>,
+) -> HelmResult<String> {
+    match (offset, size) {
+        (Some(offset), Some(size)) => {
+            if offset % SECTOR_SIZE != 0 || size % SECTOR_SIZE != 0 {
+                return Err(HelmError::Validation(format!(
+                    "Offset and size must be multiples of 0x{:x}",
+                    SECTOR_SIZE
+                )));
+            }
+            info!("Erasing 0x{:x} bytes at 0x{:x} on {}", size, offset, port);
+            with_flasher(&port, true, move |flasher| {
+                flasher
+                    .erase_region(offset, size)
+                    .map_err(espflash_error("Erasing region"))
+            })
+            .await?;
+            Ok(format!("Erased 0x{:x} bytes at 0x{:x}", size, offset))
+        }
+        (None, None) => {
+            info!("Erasing flash on {}", port);
+            with_flasher(&port, true, |flasher| {
+                let connection = flasher.connection();
+                connection
+                    .with_timeout(CommandType::EraseFlash.timeout(), |connection| {
+                        connection.command(Command::EraseFlash)
+                    })
+                    .and_then(|_| connection.flush())
+                    .map_err(espflash_error("Erasing flash"))
+            })
+            .await?;
+            Ok("Flash erased".into())
+        }
+        _ => Err(HelmError::Validation(
+            "Offset and size must be given together".into(),
+        )),
+    }
+}
+
+// Command to reset the chip, so it leaves download mode and runs the application
+#[tauri::command]
+pub async fn reset_device(port: String) -> HelmResult<String> {
+    with_flasher(&port, false, |flasher| {
+        flasher
+            .connection()
+            .reset()
+            .map_err(espflash_error("Reset"))
+    })
+    .await?;
+    Ok(format!("{} reset", port))
+}
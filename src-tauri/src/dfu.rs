@@ -0,0 +1,121 @@
+use std::path::PathBuf;
+
+use log::info;
+use regex::Regex;
+use tauri::{AppHandle, Window};
+
+use crate::error::{HelmError, HelmResult};
+use crate::events::{emit_event, HelmEvent};
+use crate::external_command::{run_external_command_lines, run_external_command_output};
+use crate::package_manager::find_in_path;
+
+// ROM bootloader of ESP32-S2 and ESP32-S3 enumerates with Espressif VID and this PID
+const ESPRESSIF_DFU_ID: &str = "303a:0002";
+
+#[derive(serde::Serialize)]
+pub struct DfuDevice {
+    // "vid:pid" as accepted by dfu-util -d
+    id: String,
+    path: String,
+    alt: u8,
+    name: String,
+    serial: Option<String>,
+    espressif: bool,
+}
+
+#[derive(Clone, serde::Serialize, schemars::JsonSchema)]
+pub struct DfuProgress {
+    percent: u8,
+}
+
+impl HelmEvent for DfuProgress {
+    const NAMES: &'static [&'static str] = &["dfu-progress"];
+}
+
+fn dfu_util() -> HelmResult<String> {
+    find_in_path("dfu-util")
+        .map(|path| path.to_string_lossy().to_string())
+        .ok_or(HelmError::NotFound(
+            "dfu-util, install it with host dependencies".into(),
+        ))
+}
+
+// Found DFU: [303a:0002] ver=0723, devnum=8, cfg=1, intf=0, path="1-1", alt=0, name="...", serial="..."
+fn parse_devices(output: &str) -> Vec<DfuDevice> {
+    let regex = Regex::new(r"^Found DFU: \[([0-9a-f]{4}:[0-9a-f]{4})\]").unwrap();
+    let field = |line: &str, key: &str| {
+        line.split_once(&format!("{}=\"", key))
+            .and_then(|(_, rest)| rest.split_once('"'))
+            .map(|(value, _)| value.to_string())
+    };
+    output
+        .lines()
+        .filter_map(|line| {
+            let id = regex.captures(line)?.get(1)?.as_str().to_string();
+            let alt = line
+                .split_once("alt=")
+                .and_then(|(_, rest)| rest.split(',').next())
+                .and_then(|alt| alt.trim().parse().ok())
+                .unwrap_or(0);
+            Some(DfuDevice {
+                espressif: id == ESPRESSIF_DFU_ID,
+                id,
+                path: field(line, "path").unwrap_or_default(),
+                alt,
+                name: field(line, "name").unwrap_or_default(),
+                serial: field(line, "serial").filter(|serial| serial != "UNKNOWN"),
+            })
+        })
+        .collect()
+}
+
+// Command to list devices in DFU mode, ESP32-S2/S3 enter it when BOOT is held during reset
+#[tauri::command]
+pub async fn list_dfu_devices() -> HelmResult<Vec<DfuDevice>> {
+    let output = run_external_command_output(&dfu_util()?, &["-l"]).await?;
+    Ok(parse_devices(&output))
+}
+
+// Command to write DFU image, e.g. build/dfu.bin created by "idf.py dfu".
+// Without path the first Espressif device is used.
+#[tauri::command]
+pub async fn flash_dfu(
+    window: Window,
+    app: AppHandle,
+    image: String,
+    path: Option<String>,
+) -> HelmResult<String> {
+    let image = PathBuf::from(image);
+    if !image.exists() {
+        return Err(HelmError::NotFound(image.display().to_string()));
+    }
+    let dfu_util = dfu_util()?;
+    let mut args: Vec<String> = vec!["-d".into(), ESPRESSIF_DFU_ID.into()];
+    if let Some(path) = path {
+        args.push("-p".into());
+        args.push(path);
+    }
+    args.push("-D".into());
+    args.push(image.to_string_lossy().to_string());
+    // Board restarts to application after download
+    args.push("-R".into());
+
+    info!("Flashing {} over DFU", image.display());
+    let percent = Regex::new(r"(\d{1,3})%").unwrap();
+    let dir = image.parent().map(PathBuf::from).unwrap_or_default();
+    let success = run_external_command_lines(&app, &dir, &dfu_util, &args, |line| {
+        if let Some(value) = percent
+            .captures_iter(line)
+            .last()
+            .and_then(|captures| captures[1].parse().ok())
+        {
+            emit_event(&window, &DfuProgress { percent: value });
+        }
+        false
+    })
+    .await?;
+    if !success {
+        return Err(HelmError::Other("dfu-util failed".into()));
+    }
+    Ok("DFU flashing finished".into())
+}
@@ -0,0 +1,58 @@
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+
+use log::info;
+use tauri::State;
+use zip::write::FileOptions;
+
+use crate::app_state::AppState;
+use crate::audit::audit_log_path;
+use crate::doctor::doctor_report;
+use crate::error::{HelmError, HelmResult};
+use crate::services::Services;
+
+fn zip_error(error: zip::result::ZipError) -> HelmError {
+    HelmError::Other(format!("Failed to write diagnostics bundle: {}", error))
+}
+
+fn json<T: serde::Serialize>(value: &T) -> HelmResult<String> {
+    serde_json::to_string_pretty(value)
+        .map_err(|e| HelmError::Other(format!("Failed to serialize diagnostics: {}", e)))
+}
+
+// Command to write zip with doctor report, settings and audit log for bug reports.
+// GitHub token is removed from settings.
+#[tauri::command]
+pub async fn export_diagnostics(
+    state_mutex: State<'_, Mutex<AppState>>,
+    services: State<'_, Services>,
+    destination: String,
+) -> HelmResult<String> {
+    let settings = state_mutex.lock().unwrap().settings.redacted();
+    let report = doctor_report(&services, settings.nightly_pin.clone()).await?;
+    let system = serde_json::json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "os": std::env::consts::OS,
+        "arch": std::env::consts::ARCH,
+    });
+
+    let file = std::fs::File::create(Path::new(&destination))?;
+    let mut zip = zip::ZipWriter::new(file);
+    let options = FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    let mut add = |name: &str, content: &[u8]| -> HelmResult<()> {
+        zip.start_file(name, options).map_err(zip_error)?;
+        zip.write_all(content)?;
+        Ok(())
+    };
+    add("system.json", json(&system)?.as_bytes())?;
+    add("doctor.json", json(&report)?.as_bytes())?;
+    add("settings.json", json(&settings)?.as_bytes())?;
+    if let Some(audit_log) = audit_log_path().filter(|path| path.exists()) {
+        add("audit.log", &std::fs::read(audit_log)?)?;
+    }
+    zip.finish().map_err(zip_error)?;
+
+    info!("Diagnostics written to {}", destination);
+    Ok(destination)
+}
@@ -0,0 +1,198 @@
+use serde_json::Value;
+use tauri::AppHandle;
+
+use crate::download::probe_size;
+use crate::error::HelmResult;
+use crate::github::github_api;
+use crate::install_plan::host_triple;
+use crate::release_metadata::RELEASES_TTL;
+use crate::rust::RustInstallOptions;
+
+// Unpacked size compared to archive, measured on esp toolchain releases
+const XZ_EXPANSION: u64 = 4;
+const ZIP_EXPANSION: u64 = 3;
+
+#[derive(serde::Serialize)]
+pub struct ComponentDisclosure {
+    name: String,
+    repo: String,
+    version: Option<String>,
+    url: Option<String>,
+    download_size: Option<u64>,
+    // Estimated from archive type when component is not installed yet
+    installed_size: Option<u64>,
+    // SPDX identifier from repository metadata, e.g. "Apache-2.0"
+    license: Option<String>,
+    license_url: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+pub struct Disclosure {
+    components: Vec<ComponentDisclosure>,
+    download_size: u64,
+    installed_size: u64,
+    // Components whose size could not be determined, totals are lower bounds then
+    unknown_sizes: usize,
+}
+
+struct ComponentSource {
+    name: &'static str,
+    repo: &'static str,
+    // Asset name prefix, host triple is matched separately
+    prefix: &'static str,
+    host_specific: bool,
+}
+
+const RUST: ComponentSource = ComponentSource {
+    name: "Rust for Xtensa",
+    repo: "esp-rs/rust-build",
+    prefix: "rust-1",
+    host_specific: true,
+};
+const RUST_SRC: ComponentSource = ComponentSource {
+    name: "Rust standard library sources",
+    repo: "esp-rs/rust-build",
+    prefix: "rust-src-",
+    host_specific: false,
+};
+const LLVM: ComponentSource = ComponentSource {
+    name: "LLVM for Xtensa",
+    repo: "espressif/llvm-project",
+    prefix: "libs-clang-",
+    host_specific: true,
+};
+const XTENSA_GCC: ComponentSource = ComponentSource {
+    name: "GCC for Xtensa",
+    repo: "espressif/crosstool-NG",
+    prefix: "xtensa-esp-elf-",
+    host_specific: true,
+};
+const RISCV_GCC: ComponentSource = ComponentSource {
+    name: "GCC for RISC-V",
+    repo: "espressif/crosstool-NG",
+    prefix: "riscv32-esp-elf-",
+    host_specific: true,
+};
+
+// Espressif builds of LLVM and GCC name hosts by GNU triples, e.g. "x86_64-w64-mingw32"
+fn espressif_host(rust_host: &str) -> String {
+    let arch = rust_host.split('-').next().unwrap_or_default();
+    if rust_host.contains("windows") {
+        format!("{}-w64-mingw32", arch)
+    } else if rust_host.contains("apple") {
+        format!("{}-apple-darwin", arch)
+    } else {
+        format!("{}-linux-gnu", arch)
+    }
+}
+
+fn string_field(value: &Value, key: &str) -> Option<String> {
+    value.get(key).and_then(Value::as_str).map(str::to_string)
+}
+
+fn estimate_installed(name: &str, download_size: u64) -> u64 {
+    if name.ends_with(".xz") {
+        download_size * XZ_EXPANSION
+    } else {
+        download_size * ZIP_EXPANSION
+    }
+}
+
+async fn license(app: &AppHandle, repo: &str) -> (Option<String>, Option<String>) {
+    let Ok(response) = github_api(app, &format!("repos/{}/license", repo), RELEASES_TTL).await
+    else {
+        return (None, None);
+    };
+    let spdx = response
+        .body
+        .get("license")
+        .and_then(|license| string_field(license, "spdx_id"))
+        // GitHub reports NOASSERTION for licenses it does not recognize
+        .filter(|spdx| spdx != "NOASSERTION");
+    (spdx, string_field(&response.body, "html_url"))
+}
+
+// Version and asset come from the latest release. espup pins LLVM and GCC versions, so
+// their sizes describe the latest build, which is close to the pinned one.
+async fn disclose(app: &AppHandle, source: &ComponentSource, host: &str) -> ComponentDisclosure {
+    let (license, license_url) = license(app, source.repo).await;
+    let mut disclosure = ComponentDisclosure {
+        name: source.name.to_string(),
+        repo: source.repo.to_string(),
+        version: None,
+        url: None,
+        download_size: None,
+        installed_size: None,
+        license,
+        license_url,
+    };
+    let path = format!("repos/{}/releases/latest", source.repo);
+    let Ok(release) = github_api(app, &path, RELEASES_TTL).await else {
+        return disclosure;
+    };
+    disclosure.version = string_field(&release.body, "tag_name");
+    let assets = release
+        .body
+        .get("assets")
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default();
+    let asset = assets.iter().find(|asset| {
+        let name = string_field(asset, "name").unwrap_or_default();
+        name.starts_with(source.prefix)
+            && (!source.host_specific || name.contains(host))
+            && (name.ends_with(".tar.xz") || name.ends_with(".zip"))
+    });
+    let Some(asset) = asset else {
+        return disclosure;
+    };
+    let name = string_field(asset, "name").unwrap_or_default();
+    let url = string_field(asset, "browser_download_url");
+    let size = match asset.get("size").and_then(Value::as_u64) {
+        Some(size) => Some(size),
+        None => match &url {
+            Some(url) => probe_size(app, url).await,
+            None => None,
+        },
+    };
+    disclosure.installed_size = size.map(|size| estimate_installed(&name, size));
+    disclosure.download_size = size;
+    disclosure.url = url;
+    disclosure
+}
+
+// Command to list components of installation with download size, installed size and
+// license, so user can agree before multi-GB downloads start
+#[tauri::command]
+pub async fn get_component_disclosure(
+    app: AppHandle,
+    options: RustInstallOptions,
+) -> HelmResult<Disclosure> {
+    let rust_host = host_triple(&app, &options);
+    let gnu_host = espressif_host(&rust_host);
+    let mut sources = vec![
+        (&RUST, rust_host.as_str()),
+        (&RUST_SRC, ""),
+        (&LLVM, &gnu_host),
+    ];
+    if options.gcc.install_gcc {
+        sources.push((&XTENSA_GCC, &gnu_host));
+        if options.gcc.esp_riscv_gcc {
+            sources.push((&RISCV_GCC, &gnu_host));
+        }
+    }
+    let requests = sources
+        .into_iter()
+        .map(|(source, host)| disclose(&app, source, host));
+    let components = futures::future::join_all(requests).await;
+
+    Ok(Disclosure {
+        download_size: components.iter().filter_map(|c| c.download_size).sum(),
+        installed_size: components.iter().filter_map(|c| c.installed_size).sum(),
+        unknown_sizes: components
+            .iter()
+            .filter(|c| c.download_size.is_none())
+            .count(),
+        components,
+    })
+}
@@ -0,0 +1,54 @@
+use std::sync::Mutex;
+
+use log::info;
+use tauri::State;
+
+use crate::app_state::AppState;
+use crate::arch::{libc_status, LibcStatus};
+use crate::defender::{defender_status, DefenderStatus};
+use crate::error::HelmResult;
+use crate::esp_clang::{get_esp_clang_status, EspClangStatus};
+use crate::git::{check_git_support, GitSupportResponse};
+use crate::path_conflicts::{analyze_path, PathAnalysis};
+use crate::rust::{rust_support, RustSupportResponse};
+use crate::services::Services;
+use crate::wokwi::{get_wokwi_status, WokwiStatus};
+
+#[derive(serde::Serialize)]
+pub struct DoctorReport {
+    rust: RustSupportResponse,
+    git: GitSupportResponse,
+    esp_clang: EspClangStatus,
+    wokwi: WokwiStatus,
+    // Real-time scanning of toolchain directories, Windows only
+    defender: DefenderStatus,
+    path: PathAnalysis,
+    // Limitations of musl systems like Alpine, glibc toolchains need gcompat
+    libc: LibcStatus,
+}
+
+pub async fn doctor_report(
+    services: &Services,
+    nightly_pin: Option<String>,
+) -> HelmResult<DoctorReport> {
+    Ok(DoctorReport {
+        rust: rust_support(services, nightly_pin, None).await,
+        git: check_git_support()?,
+        esp_clang: get_esp_clang_status(),
+        wokwi: get_wokwi_status(),
+        defender: defender_status(),
+        path: analyze_path(),
+        libc: libc_status(),
+    })
+}
+
+// Command to check all prerequisites of development environment at once
+#[tauri::command]
+pub async fn run_doctor(
+    state_mutex: State<'_, Mutex<AppState>>,
+    services: State<'_, Services>,
+) -> HelmResult<DoctorReport> {
+    info!("Checking development environment...");
+    let nightly_pin = state_mutex.lock().unwrap().settings.nightly_pin.clone();
+    doctor_report(&services, nightly_pin).await
+}
@@ -1,59 +1,828 @@
+use std::collections::BTreeMap;
 use std::path::Path;
+use std::time::{Duration, Instant};
 use tokio::fs::OpenOptions;
-use tokio::io::AsyncWriteExt; // Add this line
+use tokio::io::AsyncWriteExt;
 
-use tauri::{Manager, Window};
+use tauri::{Manager, State, Window};
 
-use crate::app_state::{AppState, BuilderState};
+use crate::app_state::AppState;
+use crate::atomic_file::{part_path, persist};
+use crate::error::{HelmError, HelmResult};
+use crate::events::{emit_event, DownloadProgress, QueueProgress};
+use crate::operations::{is_aborted, is_paused, within_operation, OperationKind};
+use crate::services::{services, HttpClient};
+use futures::StreamExt;
 use log::info;
 use std::sync::Mutex;
 
-const PROGRESS_EVENT: &str = "progress";
+// Downloads running at once when not configured in settings
+const DEFAULT_CONCURRENT_DOWNLOADS: usize = 3;
+// Finished downloads kept for the download list, older ones are dropped
+const FINISHED_KEPT: usize = 50;
+
+pub type DownloadId = u64;
+
+#[derive(Clone, Copy, PartialEq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DownloadStatus {
+    Queued,
+    Running,
+    Done,
+    Failed,
+    Cancelled,
+}
 
 #[derive(Clone, serde::Serialize)]
-struct Payload {
-    pct: String,
+pub struct DownloadInfo {
+    id: DownloadId,
+    url: String,
+    dest: String,
+    // Higher priority starts first, equal priorities start in order of queueing
+    priority: i32,
+    status: DownloadStatus,
+    downloaded: u64,
+    total: Option<u64>,
+    speed: u64,
+}
+
+// Download part of AppState
+#[derive(Default)]
+pub struct DownloadQueue {
+    next_id: DownloadId,
+    downloads: BTreeMap<DownloadId, DownloadInfo>,
+}
+
+impl DownloadQueue {
+    fn add(&mut self, url: &str, dest: &Path, priority: i32) -> DownloadId {
+        self.next_id += 1;
+        let id = self.next_id;
+        self.downloads.insert(
+            id,
+            DownloadInfo {
+                id,
+                url: url.to_string(),
+                dest: dest.to_string_lossy().to_string(),
+                priority,
+                status: DownloadStatus::Queued,
+                downloaded: 0,
+                total: None,
+                speed: 0,
+            },
+        );
+        id
+    }
+
+    fn count(&self, status: DownloadStatus) -> usize {
+        self.downloads
+            .values()
+            .filter(|download| download.status == status)
+            .count()
+    }
+
+    // Download starts when there is free slot and no queued download goes before it
+    fn can_start(&self, id: DownloadId, limit: usize) -> bool {
+        let Some(download) = self.downloads.get(&id) else {
+            return false;
+        };
+        self.count(DownloadStatus::Running) < limit
+            && !self.downloads.values().any(|other| {
+                other.status == DownloadStatus::Queued
+                    && (other.priority > download.priority
+                        || (other.priority == download.priority && other.id < id))
+            })
+    }
+
+    fn status(&self, id: DownloadId) -> Option<DownloadStatus> {
+        self.downloads.get(&id).map(|download| download.status)
+    }
+
+    fn set_status(&mut self, id: DownloadId, status: DownloadStatus) {
+        if let Some(download) = self.downloads.get_mut(&id) {
+            // Cancelled download must not be overwritten by result of its task
+            if download.status != DownloadStatus::Cancelled {
+                download.status = status;
+            }
+        }
+        self.prune();
+    }
+
+    fn is_finished(status: DownloadStatus) -> bool {
+        matches!(
+            status,
+            DownloadStatus::Done | DownloadStatus::Failed | DownloadStatus::Cancelled
+        )
+    }
+
+    // Drops oldest finished downloads, so the queue does not grow for the whole session
+    fn prune(&mut self) {
+        let finished: Vec<DownloadId> = self
+            .downloads
+            .values()
+            .filter(|download| Self::is_finished(download.status))
+            .map(|download| download.id)
+            .collect();
+        let excess = finished.len().saturating_sub(FINISHED_KEPT);
+        for id in &finished[..excess] {
+            self.downloads.remove(id);
+        }
+    }
+
+    fn progress(&self) -> QueueProgress {
+        let running = self
+            .downloads
+            .values()
+            .filter(|download| download.status == DownloadStatus::Running);
+        let mut progress = QueueProgress {
+            downloaded: 0,
+            total: 0,
+            speed: 0,
+            running: 0,
+            queued: self.count(DownloadStatus::Queued),
+        };
+        for download in running {
+            progress.downloaded += download.downloaded;
+            progress.total += download.total.unwrap_or(0);
+            progress.speed += download.speed;
+            progress.running += 1;
+        }
+        progress
+    }
 }
 
-fn is_abort_state(app: tauri::AppHandle) -> bool {
+// Percentage when size is known, otherwise downloaded amount
+fn describe_progress(downloaded: u64, total: Option<u64>) -> String {
+    match total {
+        Some(total) => format!("{:.2}%", downloaded as f64 / total as f64 * 100.0),
+        None => format!("{:.1} MiB", downloaded as f64 / (1024.0 * 1024.0)),
+    }
+}
+
+fn concurrent_downloads(app: &tauri::AppHandle) -> usize {
+    let state_mutex = app.state::<Mutex<AppState>>();
+    let state = state_mutex.lock().unwrap();
+    state
+        .settings
+        .max_concurrent_downloads
+        .unwrap_or(DEFAULT_CONCURRENT_DOWNLOADS)
+        .max(1)
+}
+
+fn update_download<F>(app: &tauri::AppHandle, id: DownloadId, update: F) -> Option<QueueProgress>
+where
+    F: FnOnce(&mut DownloadQueue),
+{
+    let state_mutex = app.state::<Mutex<AppState>>();
+    let mut state = state_mutex.lock().unwrap();
+    update(&mut state.downloads);
+    state
+        .downloads
+        .downloads
+        .contains_key(&id)
+        .then(|| state.downloads.progress())
+}
+
+fn get_download_limit(app: tauri::AppHandle) -> Option<u64> {
     let state_mutex = app.state::<Mutex<AppState>>();
     let state = state_mutex.lock().unwrap();
-    matches!(state.builder, BuilderState::Abort)
+    state.settings.download_limit_kib.map(|kib| kib * 1024)
+}
+
+// Token bucket, tokens are bytes which are refilled with configured rate
+struct RateLimiter {
+    rate: u64,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    fn new(rate: u64) -> Self {
+        Self {
+            rate,
+            tokens: rate as f64,
+            last_refill: Instant::now(),
+        }
+    }
+
+    async fn acquire(&mut self, amount: u64) {
+        let elapsed = self.last_refill.elapsed().as_secs_f64();
+        self.last_refill = Instant::now();
+        // Capacity of one second worth of data allows short bursts
+        self.tokens = (self.tokens + elapsed * self.rate as f64).min(self.rate as f64);
+        self.tokens -= amount as f64;
+
+        if self.tokens < 0.0 {
+            let wait = Duration::from_secs_f64(-self.tokens / self.rate as f64);
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+// Measure speed over the last second of the transfer
+struct SpeedMeter {
+    window_start: Instant,
+    window_bytes: u64,
+    speed: u64,
+}
+
+impl SpeedMeter {
+    fn new() -> Self {
+        Self {
+            window_start: Instant::now(),
+            window_bytes: 0,
+            speed: 0,
+        }
+    }
+
+    fn update(&mut self, amount: u64) -> u64 {
+        self.window_bytes += amount;
+        let elapsed = self.window_start.elapsed();
+        if elapsed >= Duration::from_secs(1) {
+            self.speed = (self.window_bytes as f64 / elapsed.as_secs_f64()) as u64;
+            self.window_bytes = 0;
+            self.window_start = Instant::now();
+        }
+        self.speed
+    }
+}
+
+// Size of remote file without downloading it. Some servers do not send Content-Length
+// for HEAD, then one byte range request reveals the size in Content-Range.
+pub async fn probe_size(app: &tauri::AppHandle, url: &str) -> Option<u64> {
+    probe_size_with(&*services(app).http, url).await
 }
 
+pub async fn probe_size_with(http: &dyn HttpClient, url: &str) -> Option<u64> {
+    let head = http
+        .request("HEAD", url, &[])
+        .await
+        .ok()
+        .and_then(|response| response.error_for_status().ok())
+        .and_then(|response| response.content_length)
+        .filter(|size| *size > 0);
+    if head.is_some() {
+        return head;
+    }
+    let response = http
+        .request("GET", url, &[("range", "bytes=0-0")])
+        .await
+        .ok()?
+        .error_for_status()
+        .ok()?;
+    // "bytes 0-0/12345"
+    response
+        .header("content-range")?
+        .rsplit('/')
+        .next()?
+        .parse()
+        .ok()
+}
+
+// Download through the queue, waits until global concurrency limit allows it to start
 pub async fn download_file(
-    _window: Window,
+    window: Window,
     app: tauri::AppHandle,
     url: &str,
     dest_path: &Path,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let total_size = {
-        let resp = reqwest::get(url).await?;
-        resp.content_length()
-            .ok_or("unable to get content length")?
+) -> HelmResult<()> {
+    queue_download(window, app, url, dest_path, 0).await
+}
+
+pub async fn queue_download(
+    window: Window,
+    app: tauri::AppHandle,
+    url: &str,
+    dest_path: &Path,
+    priority: i32,
+) -> HelmResult<()> {
+    let work = run_download(window, app.clone(), url, dest_path, priority);
+    within_operation(
+        &app,
+        OperationKind::Install,
+        &format!("Download {}", url),
+        work,
+    )
+    .await
+}
+
+async fn run_download(
+    window: Window,
+    app: tauri::AppHandle,
+    url: &str,
+    dest_path: &Path,
+    priority: i32,
+) -> HelmResult<()> {
+    let id = enqueue(&app, url, dest_path, priority).await?;
+    let result = transfer(&window, &app, id, url, dest_path).await;
+    finish_download(&window, &app, id, result.is_ok());
+    result
+}
+
+// Adds download to the queue and waits until it may start
+pub async fn enqueue(
+    app: &tauri::AppHandle,
+    url: &str,
+    dest_path: &Path,
+    priority: i32,
+) -> HelmResult<DownloadId> {
+    let id = {
+        let state_mutex = app.state::<Mutex<AppState>>();
+        let mut state = state_mutex.lock().unwrap();
+        state.downloads.add(url, dest_path, priority)
     };
+    info!("Download {} queued: {}", id, url);
+
+    loop {
+        let limit = concurrent_downloads(app);
+        let started = {
+            let state_mutex = app.state::<Mutex<AppState>>();
+            let mut state = state_mutex.lock().unwrap();
+            match state.downloads.status(id) {
+                Some(DownloadStatus::Queued) if state.downloads.can_start(id, limit) => {
+                    state.downloads.set_status(id, DownloadStatus::Running);
+                    true
+                }
+                Some(DownloadStatus::Queued) => false,
+                _ => {
+                    info!("Download {} cancelled", id);
+                    return Err(HelmError::Cancelled);
+                }
+            }
+        };
+        if started {
+            return Ok(id);
+        }
+        if is_aborted() {
+            update_download(app, id, |queue| {
+                queue.set_status(id, DownloadStatus::Cancelled)
+            });
+            info!("Download {} aborted", id);
+            return Err(HelmError::Cancelled);
+        }
+        tokio::time::sleep(Duration::from_millis(200)).await;
+    }
+}
+
+// Frees slot of started download, so next queued download can start
+pub fn finish_download(window: &Window, app: &tauri::AppHandle, id: DownloadId, success: bool) {
+    let status = if success {
+        DownloadStatus::Done
+    } else {
+        DownloadStatus::Failed
+    };
+    if let Some(progress) = update_download(app, id, |queue| queue.set_status(id, status)) {
+        emit_event(window, &progress);
+    }
+}
+
+// Queue, events and operation state seen by a running transfer
+pub trait TransferControl {
+    // Records progress, error stops the transfer, e.g. when download was cancelled
+    fn progress(&mut self, downloaded: u64, total: Option<u64>, speed: u64) -> HelmResult<()>;
+    fn is_paused(&self) -> bool;
+    fn is_aborted(&self) -> bool;
+    // Download alone was cancelled, other transfers of the operation continue
+    fn is_cancelled(&self) -> bool;
+    // Bytes per second, None when unlimited
+    fn rate_limit(&self) -> Option<u64>;
+}
+
+// Transfer of a download from the queue, progress is shown in queue and download events
+pub struct QueuedTransfer<'a> {
+    pub window: &'a Window,
+    pub app: &'a tauri::AppHandle,
+    pub id: DownloadId,
+}
+
+impl TransferControl for QueuedTransfer<'_> {
+    fn progress(&mut self, downloaded: u64, total: Option<u64>, speed: u64) -> HelmResult<()> {
+        let id = self.id;
+        let progress = update_download(self.app, id, |queue| {
+            if let Some(download) = queue.downloads.get_mut(&id) {
+                download.downloaded = downloaded;
+                download.total = total;
+                download.speed = speed;
+            }
+        });
+        if let Some(progress) = progress {
+            emit_event(self.window, &progress);
+        }
+        if self.is_cancelled() {
+            info!("Download {} cancelled", id);
+            return Err(HelmError::Cancelled);
+        }
+        let payload = DownloadProgress {
+            pct: total
+                .map(|total| format!("{:.2}", downloaded as f64 / total as f64 * 100.0))
+                .unwrap_or_default(),
+            speed,
+            downloaded,
+            total,
+            indeterminate: total.is_none(),
+        };
+        emit_event(self.window, &payload);
+        Ok(())
+    }
+
+    fn is_paused(&self) -> bool {
+        is_paused()
+    }
+
+    fn is_aborted(&self) -> bool {
+        is_aborted()
+    }
+
+    fn is_cancelled(&self) -> bool {
+        // Cancelled download can already be pruned from the queue
+        let state_mutex = self.app.state::<Mutex<AppState>>();
+        let state = state_mutex.lock().unwrap();
+        state.downloads.status(self.id) != Some(DownloadStatus::Running)
+    }
+
+    fn rate_limit(&self) -> Option<u64> {
+        get_download_limit(self.app.clone())
+    }
+}
+
+// Paces chunks of one transfer: reports progress, stops on cancel or abort, waits while
+// paused and keeps the bandwidth limit. Shared by downloads to disk and streamed extraction.
+pub struct Pacer<C> {
+    control: C,
+    total: Option<u64>,
+    downloaded: u64,
+    speed_meter: SpeedMeter,
+    rate_limiter: Option<RateLimiter>,
+}
 
-    let request = reqwest::get(url);
-    let mut response = request.await?;
+impl<C: TransferControl> Pacer<C> {
+    pub fn new(control: C, total: Option<u64>) -> Self {
+        Self {
+            control,
+            total,
+            downloaded: 0,
+            speed_meter: SpeedMeter::new(),
+            rate_limiter: None,
+        }
+    }
+
+    pub fn downloaded(&self) -> u64 {
+        self.downloaded
+    }
 
+    // Called after each chunk is written
+    pub async fn chunk_done(&mut self, len: usize) -> HelmResult<()> {
+        self.downloaded += len as u64;
+        let progress_text = describe_progress(self.downloaded, self.total);
+        let speed = self.speed_meter.update(len as u64);
+        info!("Download progress: {}", progress_text);
+        self.control.progress(self.downloaded, self.total, speed)?;
+
+        if self.control.is_aborted() {
+            info!("Download aborted at: {}", progress_text);
+            return Err(HelmError::Cancelled);
+        }
+
+        // Stop reading chunks while paused, server keeps the connection open for a while
+        if self.control.is_paused() {
+            info!("Download paused at: {}", progress_text);
+            while self.control.is_paused() {
+                // Paused download can still be aborted or cancelled
+                if self.control.is_aborted() || self.control.is_cancelled() {
+                    info!("Download stopped while paused at: {}", progress_text);
+                    return Err(HelmError::Cancelled);
+                }
+                tokio::time::sleep(Duration::from_millis(200)).await;
+            }
+            if self.control.is_aborted() {
+                info!("Download aborted at: {}", progress_text);
+                return Err(HelmError::Cancelled);
+            }
+            info!("Download resumed");
+        }
+
+        // Limit can be changed in settings while the download is running
+        match self.control.rate_limit() {
+            Some(limit) => {
+                let limiter = self
+                    .rate_limiter
+                    .get_or_insert_with(|| RateLimiter::new(limit));
+                limiter.rate = limit;
+                limiter.acquire(len as u64).await;
+            }
+            None => self.rate_limiter = None,
+        }
+        Ok(())
+    }
+}
+
+async fn transfer(
+    window: &Window,
+    app: &tauri::AppHandle,
+    id: DownloadId,
+    url: &str,
+    dest_path: &Path,
+) -> HelmResult<()> {
+    let control = QueuedTransfer { window, app, id };
+    transfer_with(&*services(app).http, control, url, dest_path).await
+}
+
+// Removes partially written file unless it was persisted
+struct PartGuard<'a> {
+    part: &'a Path,
+    persisted: bool,
+}
+
+impl Drop for PartGuard<'_> {
+    fn drop(&mut self) {
+        if !self.persisted {
+            let _ = std::fs::remove_file(self.part);
+        }
+    }
+}
+
+async fn transfer_with(
+    http: &dyn HttpClient,
+    control: impl TransferControl,
+    url: &str,
+    dest_path: &Path,
+) -> HelmResult<()> {
+    let mut response = http.get(url).await?.error_for_status()?;
+    // Missing with chunked transfer encoding and on some mirrors and redirect chains
+    let total_size = response.content_length.filter(|size| *size > 0);
+    if total_size.is_none() {
+        info!("Size of {} is unknown", url);
+    }
+
+    // Destination appears only when download is complete
+    let part = part_path(dest_path);
+    let mut guard = PartGuard {
+        part: &part,
+        persisted: false,
+    };
     let mut dest = OpenOptions::new()
         .create(true)
-        .append(true)
-        .open(&dest_path)
+        .write(true)
+        .truncate(true)
+        .open(&part)
         .await?;
 
-    let mut downloaded: u64 = 0;
-
-    while let Some(chunk) = response.chunk().await? {
+    let mut pacer = Pacer::new(control, total_size);
+    while let Some(chunk) = response.body.next().await {
+        let chunk = chunk?;
         dest.write_all(&chunk).await?;
-        downloaded += chunk.len() as u64;
-        let percentage = downloaded as f64 / total_size as f64 * 100.0;
-        info!("Download progress: {:.2}%", percentage);
-        if is_abort_state(app.clone()) {
-            info!("Download aborted at: {:.2}%", percentage);
-            break;
-        }
+        pacer.chunk_done(chunk.len()).await?;
     }
 
+    dest.flush().await?;
+    // Connection closed early, chunked responses are complete only when stream ends properly
+    let downloaded = pacer.downloaded();
+    if let Some(total) = total_size.filter(|total| downloaded < *total) {
+        return Err(HelmError::Network(format!(
+            "Download incomplete, {} of {} bytes received",
+            downloaded, total
+        )));
+    }
+    persist(dest, &part, dest_path).await?;
+    guard.persisted = true;
     Ok(())
 }
+
+// Command to list queued, running and finished downloads
+#[tauri::command]
+pub async fn list_downloads(
+    state_mutex: State<'_, Mutex<AppState>>,
+) -> HelmResult<Vec<DownloadInfo>> {
+    let state = state_mutex.lock().unwrap();
+    Ok(state.downloads.downloads.values().cloned().collect())
+}
+
+// Command to cancel queued or running download, other downloads continue
+#[tauri::command]
+pub async fn cancel_download(
+    state_mutex: State<'_, Mutex<AppState>>,
+    id: DownloadId,
+) -> HelmResult<String> {
+    let mut state = state_mutex.lock().unwrap();
+    match state.downloads.status(id) {
+        Some(DownloadStatus::Queued | DownloadStatus::Running) => {
+            state.downloads.set_status(id, DownloadStatus::Cancelled);
+            Ok(format!("Download {} cancelled", id))
+        }
+        Some(_) => Err(HelmError::Validation(format!(
+            "Download {} already finished",
+            id
+        ))),
+        None => Err(HelmError::NotFound(format!("Download {}", id))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::mock::{MockHttp, MockResponse};
+    use tempfile::TempDir;
+
+    const URL: &str = "https://example.com/esp.tar.xz";
+
+    fn response(status: u16, headers: &[(&str, &str)]) -> MockResponse {
+        MockResponse {
+            status,
+            headers: headers
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            body: vec![],
+        }
+    }
+
+    #[test]
+    fn size_from_head() {
+        let http =
+            MockHttp::default().with("HEAD", URL, response(200, &[("content-length", "1234")]));
+        assert_eq!(
+            futures::executor::block_on(probe_size_with(&http, URL)),
+            Some(1234)
+        );
+    }
+
+    #[test]
+    fn size_from_range_when_head_fails() {
+        let http = MockHttp::default()
+            .with("HEAD", URL, response(405, &[]))
+            .with(
+                "GET",
+                URL,
+                response(206, &[("content-range", "bytes 0-0/5678")]),
+            );
+        assert_eq!(
+            futures::executor::block_on(probe_size_with(&http, URL)),
+            Some(5678)
+        );
+    }
+
+    #[test]
+    fn size_unknown() {
+        let http = MockHttp::default().with("HEAD", URL, response(200, &[]));
+        assert_eq!(
+            futures::executor::block_on(probe_size_with(&http, URL)),
+            None
+        );
+    }
+
+    // Transfer outside of the app, never paused and not limited
+    struct Unattended;
+
+    impl TransferControl for Unattended {
+        fn progress(&mut self, _: u64, _: Option<u64>, _: u64) -> HelmResult<()> {
+            Ok(())
+        }
+
+        fn is_paused(&self) -> bool {
+            false
+        }
+
+        fn is_aborted(&self) -> bool {
+            false
+        }
+
+        fn is_cancelled(&self) -> bool {
+            false
+        }
+
+        fn rate_limit(&self) -> Option<u64> {
+            None
+        }
+    }
+
+    // Destination inside of a directory removed when the returned TempDir is dropped
+    fn destination() -> (TempDir, std::path::PathBuf) {
+        let dir = TempDir::new().unwrap();
+        let dest = dir.path().join("esp.tar.xz");
+        (dir, dest)
+    }
+
+    #[test]
+    fn finished_downloads_are_pruned() {
+        let mut queue = DownloadQueue::default();
+        let dest = Path::new("esp.tar.xz");
+        let running = queue.add(URL, dest, 0);
+        queue.set_status(running, DownloadStatus::Running);
+        for _ in 0..FINISHED_KEPT + 5 {
+            let id = queue.add(URL, dest, 0);
+            queue.set_status(id, DownloadStatus::Done);
+        }
+
+        assert_eq!(queue.count(DownloadStatus::Done), FINISHED_KEPT);
+        assert_eq!(queue.status(running), Some(DownloadStatus::Running));
+        // Oldest finished downloads go first
+        assert_eq!(queue.status(running + 1), None);
+        assert_eq!(
+            queue.status(running + FINISHED_KEPT as DownloadId + 5),
+            Some(DownloadStatus::Done)
+        );
+    }
+
+    #[tokio::test]
+    async fn paused_download_can_be_cancelled() {
+        struct PausedCancelled;
+
+        impl TransferControl for PausedCancelled {
+            fn progress(&mut self, _: u64, _: Option<u64>, _: u64) -> HelmResult<()> {
+                Ok(())
+            }
+
+            fn is_paused(&self) -> bool {
+                true
+            }
+
+            fn is_aborted(&self) -> bool {
+                false
+            }
+
+            fn is_cancelled(&self) -> bool {
+                true
+            }
+
+            fn rate_limit(&self) -> Option<u64> {
+                None
+            }
+        }
+
+        let mut pacer = Pacer::new(PausedCancelled, None);
+        let result = tokio::time::timeout(Duration::from_secs(5), pacer.chunk_done(7)).await;
+        assert_eq!(result.unwrap(), Err(HelmError::Cancelled));
+    }
+
+    #[test]
+    fn progress_without_size() {
+        assert_eq!(describe_progress(512, Some(1024)), "50.00%");
+        assert_eq!(describe_progress(3 * 1024 * 1024, None), "3.0 MiB");
+    }
+
+    #[tokio::test]
+    async fn chunked_body_is_persisted() {
+        let (_dir, dest) = destination();
+        let mut body = response(200, &[("transfer-encoding", "chunked")]);
+        body.body = b"archive".to_vec();
+        let http = MockHttp::default().with("GET", URL, body);
+
+        transfer_with(&http, Unattended, URL, &dest).await.unwrap();
+        assert_eq!(std::fs::read(&dest).unwrap(), b"archive");
+        assert!(!part_path(&dest).exists());
+    }
+
+    #[tokio::test]
+    async fn short_body_is_incomplete() {
+        let (_dir, dest) = destination();
+        let mut body = response(200, &[("content-length", "100")]);
+        body.body = b"archive".to_vec();
+        let http = MockHttp::default().with("GET", URL, body);
+
+        let error = transfer_with(&http, Unattended, URL, &dest)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error,
+            HelmError::Network("Download incomplete, 7 of 100 bytes received".into())
+        );
+        assert!(!dest.exists());
+        assert!(!part_path(&dest).exists());
+    }
+
+    #[tokio::test]
+    async fn aborted_transfer_removes_part() {
+        struct Aborted;
+
+        impl TransferControl for Aborted {
+            fn progress(&mut self, _: u64, _: Option<u64>, _: u64) -> HelmResult<()> {
+                Ok(())
+            }
+
+            fn is_paused(&self) -> bool {
+                false
+            }
+
+            fn is_aborted(&self) -> bool {
+                true
+            }
+
+            fn is_cancelled(&self) -> bool {
+                false
+            }
+
+            fn rate_limit(&self) -> Option<u64> {
+                None
+            }
+        }
+
+        let (_dir, dest) = destination();
+        let mut body = response(200, &[]);
+        body.body = b"archive".to_vec();
+        let http = MockHttp::default().with("GET", URL, body);
+
+        let error = transfer_with(&http, Aborted, URL, &dest).await.unwrap_err();
+        assert_eq!(error, HelmError::Cancelled);
+        assert!(!dest.exists());
+        assert!(!part_path(&dest).exists());
+    }
+}
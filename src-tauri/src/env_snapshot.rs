@@ -0,0 +1,216 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use log::info;
+use tauri::{AppHandle, Manager, State};
+
+use crate::app_state::AppState;
+use crate::error::{HelmError, HelmResult};
+use crate::events::{emit_event_all, EnvironmentDrift};
+use crate::inventory::collect_inventory;
+use crate::package_manager::find_in_path;
+use crate::portable::app_data_dir;
+use crate::rust::get_tool_version;
+
+// Tools found in PATH, version flag is --version for all of them
+const SNAPSHOT_TOOLS: [&str; 8] = [
+    "rustup", "cargo", "rustc", "espup", "espflash", "python3", "git", "cmake",
+];
+
+#[derive(Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct SnapshotEntry {
+    version: Option<String>,
+    path: Option<String>,
+}
+
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+pub struct EnvironmentSnapshot {
+    taken_at: u64,
+    // Keyed by "tool:<name>" for PATH tools and "<kind>:<name>" for inventory items
+    entries: BTreeMap<String, SnapshotEntry>,
+}
+
+#[derive(Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum DriftKind {
+    Added,
+    Removed,
+    VersionChanged,
+    PathChanged,
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct DriftItem {
+    key: String,
+    kind: DriftKind,
+    before: Option<String>,
+    after: Option<String>,
+    // Suggested reconciliation, e.g. reinstall removed tool
+    action: Option<String>,
+}
+
+fn snapshot_path() -> Option<PathBuf> {
+    app_data_dir().map(|dir| dir.join("environment.json"))
+}
+
+pub fn take_snapshot(app: &AppHandle) -> EnvironmentSnapshot {
+    let adopted = {
+        let state_mutex = app.state::<Mutex<AppState>>();
+        let state = state_mutex.lock().unwrap();
+        state.settings.adopted_installations.clone()
+    };
+    let mut entries = BTreeMap::new();
+    for tool in SNAPSHOT_TOOLS {
+        let Some(path) = find_in_path(tool) else {
+            continue;
+        };
+        entries.insert(
+            format!("tool:{}", tool),
+            SnapshotEntry {
+                version: get_tool_version(tool, &["--version"], None),
+                path: Some(path.to_string_lossy().to_string()),
+            },
+        );
+    }
+    for item in collect_inventory(&adopted) {
+        entries.insert(
+            format!("{}:{}", item.kind, item.name),
+            SnapshotEntry {
+                version: item.version,
+                path: Some(item.path),
+            },
+        );
+    }
+    EnvironmentSnapshot {
+        taken_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+        entries,
+    }
+}
+
+fn load_snapshot() -> Option<EnvironmentSnapshot> {
+    serde_json::from_str(&std::fs::read_to_string(snapshot_path()?).ok()?).ok()
+}
+
+fn save_snapshot(snapshot: &EnvironmentSnapshot) -> HelmResult<()> {
+    let path = snapshot_path().ok_or(HelmError::NotFound("data directory".into()))?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let content = serde_json::to_string_pretty(snapshot)
+        .map_err(|e| HelmError::Other(format!("Failed to serialize snapshot: {}", e)))?;
+    std::fs::write(path, content)?;
+    Ok(())
+}
+
+fn suggested_action(key: &str, kind: DriftKind) -> Option<String> {
+    let action = match kind {
+        DriftKind::Removed if key.starts_with("tool:") || key.contains("toolchain") => {
+            "Reinstall it from the installer or remove it from inventory"
+        }
+        DriftKind::Removed => return None,
+        DriftKind::Added => "Adopt it to let esp-helm manage it",
+        DriftKind::VersionChanged => "Run verification to check projects still build",
+        DriftKind::PathChanged => "Check PATH conflicts in doctor",
+    };
+    Some(action.to_string())
+}
+
+pub fn diff_snapshots(before: &EnvironmentSnapshot, after: &EnvironmentSnapshot) -> Vec<DriftItem> {
+    let mut items = vec![];
+    let mut push = |key: &str, kind, before: Option<String>, after: Option<String>| {
+        items.push(DriftItem {
+            key: key.to_string(),
+            kind,
+            before,
+            after,
+            action: suggested_action(key, kind),
+        })
+    };
+    for (key, old) in &before.entries {
+        match after.entries.get(key) {
+            None => push(key, DriftKind::Removed, old.version.clone(), None),
+            Some(new) if new.version != old.version => push(
+                key,
+                DriftKind::VersionChanged,
+                old.version.clone(),
+                new.version.clone(),
+            ),
+            Some(new) if new.path != old.path => push(
+                key,
+                DriftKind::PathChanged,
+                old.path.clone(),
+                new.path.clone(),
+            ),
+            Some(_) => {}
+        }
+    }
+    for (key, new) in &after.entries {
+        if !before.entries.contains_key(key) {
+            push(key, DriftKind::Added, None, new.version.clone());
+        }
+    }
+    items
+}
+
+// Snapshot after esp-helm finished its own changes, so they are not reported as drift
+pub fn refresh_snapshot(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        if let Err(e) = save_snapshot(&take_snapshot(&app)) {
+            info!("Unable to save environment snapshot: {}", e);
+        }
+    });
+}
+
+// Compares environment with snapshot of previous run, first run only stores the snapshot
+pub fn check_drift_on_startup(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let current = take_snapshot(&app);
+        let changes = load_snapshot()
+            .map(|previous| diff_snapshots(&previous, &current))
+            .unwrap_or_default();
+        if let Err(e) = save_snapshot(&current) {
+            info!("Unable to save environment snapshot: {}", e);
+        }
+        if changes.is_empty() {
+            return;
+        }
+        info!(
+            "Environment changed outside esp-helm: {} changes",
+            changes.len()
+        );
+        let event = EnvironmentDrift { changes };
+        {
+            let state_mutex = app.state::<Mutex<AppState>>();
+            let mut state = state_mutex.lock().unwrap();
+            state.environment_drift = Some(event.clone());
+        }
+        emit_event_all(&app, &event);
+    });
+}
+
+// Command to get changes found at startup, None when environment is unchanged
+#[tauri::command]
+pub async fn get_environment_drift(
+    state_mutex: State<'_, Mutex<AppState>>,
+) -> HelmResult<Option<EnvironmentDrift>> {
+    let state = state_mutex.lock().unwrap();
+    Ok(state.environment_drift.clone())
+}
+
+// Command to accept current environment, drift is not reported again
+#[tauri::command]
+pub async fn acknowledge_environment_drift(
+    app: AppHandle,
+    state_mutex: State<'_, Mutex<AppState>>,
+) -> HelmResult<String> {
+    state_mutex.lock().unwrap().environment_drift = None;
+    refresh_snapshot(&app);
+    Ok("Environment drift acknowledged".into())
+}
@@ -0,0 +1,258 @@
+use crate::i18n::tr;
+use crate::remediation::{diagnose, Remediation};
+
+// Error type returned by all Tauri commands.
+// Serialized as {"kind": "...", "details": ..., "message": "...", "fix": {...}} so the frontend
+// can branch on kind, show message in selected language and offer fix when cause is known.
+#[derive(Clone, Debug, PartialEq, thiserror::Error)]
+pub enum HelmError {
+    #[error("Network error: {0}")]
+    Network(String),
+    #[error("Permission denied: {0}")]
+    Permission(String),
+    #[error("Child process failed with exit code {code:?}")]
+    ChildProcessFailed {
+        code: Option<i32>,
+        // Last lines of output, shown when no known cause was found
+        output: String,
+        // Known cause found in output of the process
+        fix: Option<Remediation>,
+    },
+    #[error("Operation cancelled")]
+    Cancelled,
+    #[error("Timed out: {0}")]
+    Timeout(String),
+    #[error("Invalid input: {0}")]
+    Validation(String),
+    #[error("Not found: {0}")]
+    NotFound(String),
+    #[error("I/O error: {0}")]
+    Io(String),
+    #[error("{0}")]
+    Other(String),
+}
+
+pub type HelmResult<T> = Result<T, HelmError>;
+
+// Output kept in ChildProcessFailed, errors are sent to the frontend and stored in jobs
+const OUTPUT_TAIL_LINES: usize = 20;
+const OUTPUT_TAIL_BYTES: usize = 4096;
+
+// Last lines of output, cut at line or char boundary to fit OUTPUT_TAIL_BYTES
+fn output_tail(output: &str) -> String {
+    let lines: Vec<&str> = output.trim_end().lines().collect();
+    let tail = lines[lines.len().saturating_sub(OUTPUT_TAIL_LINES)..].join("\n");
+    if tail.len() <= OUTPUT_TAIL_BYTES {
+        return tail;
+    }
+    let mut start = tail.len() - OUTPUT_TAIL_BYTES;
+    while !tail.is_char_boundary(start) {
+        start += 1;
+    }
+    tail[start..].to_string()
+}
+
+impl HelmError {
+    pub fn child_process_failed(code: Option<i32>, output: &str) -> Self {
+        HelmError::ChildProcessFailed {
+            code,
+            output: output_tail(output),
+            fix: diagnose(output.lines()),
+        }
+    }
+
+    // Same name as "kind" in serialized error
+    pub fn kind(&self) -> &'static str {
+        match self {
+            HelmError::Network(_) => "Network",
+            HelmError::Permission(_) => "Permission",
+            HelmError::ChildProcessFailed { .. } => "ChildProcessFailed",
+            HelmError::Cancelled => "Cancelled",
+            HelmError::Timeout(_) => "Timeout",
+            HelmError::Validation(_) => "Validation",
+            HelmError::NotFound(_) => "NotFound",
+            HelmError::Io(_) => "Io",
+            HelmError::Other(_) => "Other",
+        }
+    }
+
+    fn details(&self) -> Option<&str> {
+        match self {
+            HelmError::Network(details)
+            | HelmError::Permission(details)
+            | HelmError::Timeout(details)
+            | HelmError::Validation(details)
+            | HelmError::NotFound(details)
+            | HelmError::Io(details)
+            | HelmError::Other(details) => Some(details),
+            HelmError::ChildProcessFailed { .. } | HelmError::Cancelled => None,
+        }
+    }
+
+    // Suggestion of the UI to fix the failure, errors of in-process tools like espup are
+    // recognized by their message
+    pub fn fix(&self) -> Option<Remediation> {
+        match self {
+            HelmError::ChildProcessFailed { fix, .. } => fix.clone(),
+            HelmError::Cancelled => None,
+            _ => diagnose(self.details()?.lines()),
+        }
+    }
+
+    // Display text translated by i18n catalogs, details stay as reported by the tool
+    pub fn localized(&self) -> String {
+        let key = match self {
+            HelmError::Network(_) => "error-network",
+            HelmError::Permission(_) => "error-permission",
+            HelmError::ChildProcessFailed { .. } => "error-child-process-failed",
+            HelmError::Cancelled => "error-cancelled",
+            HelmError::Timeout(_) => "error-timeout",
+            HelmError::Validation(_) => "error-validation",
+            HelmError::NotFound(_) => "error-not-found",
+            HelmError::Io(_) => "error-io",
+            HelmError::Other(_) => "error-other",
+        };
+        let mut args = vec![];
+        if let Some(details) = self.details() {
+            args.push(("details", details.to_string()));
+        }
+        if let HelmError::ChildProcessFailed { code, .. } = self {
+            let code = code.map(|code| code.to_string()).unwrap_or("?".into());
+            args.push(("code", code));
+        }
+        tr(key, &args)
+    }
+}
+
+impl serde::Serialize for HelmError {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("kind", self.kind())?;
+        match self {
+            HelmError::ChildProcessFailed { code, output, .. } => map.serialize_entry(
+                "details",
+                &serde_json::json!({ "code": code, "output": output }),
+            )?,
+            _ => {
+                if let Some(details) = self.details() {
+                    map.serialize_entry("details", details)?;
+                }
+            }
+        }
+        map.serialize_entry("message", &self.localized())?;
+        if let Some(fix) = self.fix() {
+            map.serialize_entry("fix", &fix)?;
+        }
+        map.end()
+    }
+}
+
+// Shape written by Serialize above, HelmError is part of job and verification events
+#[allow(dead_code)]
+#[derive(schemars::JsonSchema)]
+struct SerializedError {
+    kind: String,
+    details: Option<serde_json::Value>,
+    message: String,
+    fix: Option<Remediation>,
+}
+
+impl schemars::JsonSchema for HelmError {
+    fn schema_name() -> String {
+        "HelmError".into()
+    }
+
+    fn json_schema(gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        SerializedError::json_schema(gen)
+    }
+}
+
+impl From<std::io::Error> for HelmError {
+    fn from(error: std::io::Error) -> Self {
+        match error.kind() {
+            std::io::ErrorKind::PermissionDenied => HelmError::Permission(error.to_string()),
+            std::io::ErrorKind::NotFound => HelmError::NotFound(error.to_string()),
+            _ => HelmError::Io(error.to_string()),
+        }
+    }
+}
+
+impl From<reqwest::Error> for HelmError {
+    fn from(error: reqwest::Error) -> Self {
+        HelmError::Network(error.to_string())
+    }
+}
+
+impl From<zip::result::ZipError> for HelmError {
+    fn from(error: zip::result::ZipError) -> Self {
+        match error {
+            zip::result::ZipError::Io(error) => error.into(),
+            _ => HelmError::Validation(error.to_string()),
+        }
+    }
+}
+
+impl From<serialport::Error> for HelmError {
+    fn from(error: serialport::Error) -> Self {
+        match error.kind() {
+            serialport::ErrorKind::NoDevice => HelmError::NotFound(error.to_string()),
+            _ => HelmError::Io(error.to_string()),
+        }
+    }
+}
+
+impl From<espup::error::Error> for HelmError {
+    fn from(error: espup::error::Error) -> Self {
+        match error {
+            espup::error::Error::IoError(error) => error.into(),
+            espup::error::Error::HttpError(message) => HelmError::Network(message),
+            espup::error::Error::UnsupportedHost(_) | espup::error::Error::UnsupportedTarget(_) => {
+                HelmError::Validation(error.to_string())
+            }
+            _ => HelmError::Other(error.to_string()),
+        }
+    }
+}
+
+impl From<String> for HelmError {
+    fn from(message: String) -> Self {
+        HelmError::Other(message)
+    }
+}
+
+impl From<&str> for HelmError {
+    fn from(message: &str) -> Self {
+        HelmError::Other(message.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn output_tail_keeps_last_lines() {
+        let output: Vec<String> = (0..30).map(|i| format!("line {}", i)).collect();
+        let tail = output_tail(&output.join("\n"));
+        assert_eq!(tail.lines().count(), OUTPUT_TAIL_LINES);
+        assert!(tail.starts_with("line 10\n"));
+        assert!(tail.ends_with("line 29"));
+    }
+
+    #[test]
+    fn output_tail_is_bounded() {
+        let tail = output_tail(&"é".repeat(OUTPUT_TAIL_BYTES));
+        assert!(tail.len() <= OUTPUT_TAIL_BYTES);
+        assert!(tail.chars().all(|c| c == 'é'));
+    }
+
+    #[test]
+    fn child_process_failed_details_contain_output() {
+        let error = HelmError::child_process_failed(Some(128), "fatal: not a git repository\n");
+        let value = serde_json::to_value(&error).unwrap();
+        assert_eq!(value["details"]["code"], 128);
+        assert_eq!(value["details"]["output"], "fatal: not a git repository");
+    }
+}
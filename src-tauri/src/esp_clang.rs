@@ -0,0 +1,208 @@
+use std::path::PathBuf;
+
+use log::info;
+use tauri::Window;
+
+use crate::audit::{audit, AuditAction};
+use crate::cleanup::compare_versions;
+use crate::error::{HelmError, HelmResult};
+use crate::inventory::rustup_home;
+use crate::portable::{portable_root, write_launcher};
+use crate::rust::{espup_install, EspupOptions, GccOptions};
+use crate::services::{Fs, SystemFs};
+
+#[cfg(unix)]
+pub const ESPUP_EXPORT_FILE: &str = "export-esp.sh";
+#[cfg(windows)]
+pub const ESPUP_EXPORT_FILE: &str = "export-esp.ps1";
+
+#[derive(serde::Serialize)]
+pub struct EspClangStatus {
+    version: Option<String>,
+    // Directory with libclang installed by espup
+    libclang_path: Option<String>,
+    // LIBCLANG_PATH from espup export file
+    exported_path: Option<String>,
+    mismatch: bool,
+}
+
+// Portable installation keeps export file next to executable
+pub fn export_file_path() -> Option<PathBuf> {
+    portable_root()
+        .or_else(dirs::home_dir)
+        .map(|dir| dir.join(ESPUP_EXPORT_FILE))
+}
+
+// espup installs clang to ~/.rustup/toolchains/esp/xtensa-esp32-elf-clang/esp-<version>/esp-clang
+fn find_esp_clang() -> Option<PathBuf> {
+    find_esp_clang_with(&SystemFs)
+}
+
+fn find_esp_clang_with(fs: &dyn Fs) -> Option<PathBuf> {
+    let clang_dir = rustup_home()?
+        .join("toolchains")
+        .join("esp")
+        .join("xtensa-esp32-elf-clang");
+    let mut versions: Vec<PathBuf> = fs
+        .list_dir(&clang_dir)
+        .into_iter()
+        .filter(|p| fs.exists(&p.join("esp-clang")))
+        .collect();
+    versions.sort_by(|a, b| {
+        compare_versions(
+            &a.file_name().unwrap_or_default().to_string_lossy(),
+            &b.file_name().unwrap_or_default().to_string_lossy(),
+        )
+    });
+    versions.pop().map(|version| version.join("esp-clang"))
+}
+
+pub fn is_esp_clang_installed(fs: &dyn Fs) -> bool {
+    find_esp_clang_with(fs).is_some()
+}
+
+fn libclang_dir(esp_clang: &std::path::Path) -> PathBuf {
+    if cfg!(windows) {
+        esp_clang.join("bin")
+    } else {
+        esp_clang.join("lib")
+    }
+}
+
+// Version directory has form "esp-16.0.4-20231113"
+fn esp_clang_version(esp_clang: &std::path::Path) -> Option<String> {
+    let name = esp_clang
+        .parent()?
+        .file_name()?
+        .to_string_lossy()
+        .to_string();
+    name.strip_prefix("esp-").map(|version| version.to_string())
+}
+
+fn read_exported_libclang_path() -> Option<String> {
+    let content = std::fs::read_to_string(export_file_path()?).ok()?;
+    content.lines().find_map(|line| {
+        let line = line.trim();
+        // export LIBCLANG_PATH="..." or $Env:LIBCLANG_PATH = "..."
+        let value = line
+            .strip_prefix("export LIBCLANG_PATH=")
+            .or_else(|| line.strip_prefix("$Env:LIBCLANG_PATH = "))?;
+        Some(value.trim_matches('"').to_string())
+    })
+}
+
+pub fn get_esp_clang_status() -> EspClangStatus {
+    let esp_clang = find_esp_clang();
+    let libclang_path = esp_clang
+        .as_ref()
+        .map(|path| libclang_dir(path).to_string_lossy().to_string());
+    let exported_path =
+        read_exported_libclang_path().or_else(|| std::env::var("LIBCLANG_PATH").ok());
+    let mismatch = match (&libclang_path, &exported_path) {
+        (Some(installed), Some(exported)) => installed != exported,
+        _ => false,
+    };
+
+    EspClangStatus {
+        version: esp_clang.as_deref().and_then(esp_clang_version),
+        libclang_path,
+        exported_path,
+        mismatch,
+    }
+}
+
+#[tauri::command]
+pub async fn check_esp_clang() -> HelmResult<EspClangStatus> {
+    Ok(get_esp_clang_status())
+}
+
+// Command to install esp-clang via espup, extended LLVM contains clang binaries
+#[tauri::command]
+pub async fn install_esp_clang(window: Window) -> HelmResult<String> {
+    info!("Installing esp-clang via espup...");
+    let options = EspupOptions {
+        default_host: None,
+        targets: vec![],
+        gcc: GccOptions {
+            install_gcc: false,
+            esp_riscv_gcc: false,
+        },
+        extended_llvm: true,
+    };
+    espup_install(window, options).await?;
+    update_libclang_export()?;
+    Ok("esp-clang installed successfully!".into())
+}
+
+// Rewrite espup export file keeping lines which are not removed by keep
+fn update_export_file(
+    description: String,
+    keep: impl Fn(&str) -> bool,
+    line: Option<String>,
+) -> HelmResult<()> {
+    let export_file = export_file_path().ok_or(HelmError::NotFound("home directory".into()))?;
+    let content = std::fs::read_to_string(&export_file).unwrap_or_default();
+    let mut lines: Vec<String> = content
+        .lines()
+        .filter(|line| keep(line.trim()))
+        .map(|line| line.to_string())
+        .collect();
+    lines.extend(line);
+    std::fs::write(&export_file, lines.join("\n") + "\n")?;
+    audit(
+        AuditAction::ModifyEnvironment,
+        &export_file.to_string_lossy(),
+        Some(description),
+    );
+    write_launcher()?;
+    Ok(())
+}
+
+// Set or with None remove variable in espup export file, other lines are kept
+pub fn set_export_variable(name: &str, value: Option<&str>) -> HelmResult<()> {
+    #[cfg(unix)]
+    let prefix = format!("export {}=", name);
+    #[cfg(windows)]
+    let prefix = format!("$Env:{} = ", name);
+
+    update_export_file(
+        format!("Set {}", name),
+        |line| !line.starts_with(&prefix),
+        value.map(|value| format!("{}\"{}\"", prefix, value)),
+    )
+}
+
+// Prepend directories to PATH in export file, PATH line of espup stays. Marker identifies
+// the line, so the next call replaces it.
+pub fn set_export_path(marker: &str, dirs: &[PathBuf]) -> HelmResult<()> {
+    let comment = format!("# esp-helm: {}", marker);
+    let dirs: Vec<String> = dirs
+        .iter()
+        .map(|dir| dir.to_string_lossy().to_string())
+        .collect();
+    #[cfg(unix)]
+    let line = format!("export PATH=\"{}:$PATH\" {}", dirs.join(":"), comment);
+    #[cfg(windows)]
+    let line = format!("$Env:PATH = \"{};$Env:PATH\" {}", dirs.join(";"), comment);
+
+    update_export_file(
+        format!("Add {} to PATH", marker),
+        |line| !line.ends_with(&comment),
+        (!dirs.is_empty()).then_some(line),
+    )
+}
+
+// Point LIBCLANG_PATH in espup export file to the newest installed esp-clang
+pub fn update_libclang_export() -> HelmResult<String> {
+    let esp_clang = find_esp_clang().ok_or(HelmError::NotFound("esp-clang".into()))?;
+    let libclang_path = libclang_dir(&esp_clang).to_string_lossy().to_string();
+    set_export_variable("LIBCLANG_PATH", Some(&libclang_path))?;
+
+    info!("LIBCLANG_PATH set to {}", libclang_path);
+    Ok(libclang_path)
+}
+
+#[tauri::command]
+pub async fn fix_libclang_path() -> HelmResult<String> {
+    update_libclang_export()
+}
@@ -1,10 +1,16 @@
 use log::info;
 
 use crate::download::download_file;
-use std::path::Path;
-use tauri::Window;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Window};
 
+use crate::error::{HelmError, HelmResult};
+use crate::esp_clang::set_export_path;
+use crate::esptool::find_python;
 use crate::external_command::run_external_command_with_progress;
+use crate::inventory::espressif_home;
+use crate::long_path::long_path;
+use crate::operations::{begin_operation, OperationKind};
 
 #[derive(Clone, serde::Serialize)]
 struct Payload {
@@ -19,11 +25,11 @@ const INSTALL_SCRIPT_NAME: &str = "install.sh";
 #[cfg(windows)]
 const INSTALL_SCRIPT_NAME: &str = "install.bat";
 
-pub fn run_install_script(
+pub async fn run_install_script(
     window: Window,
     app: tauri::AppHandle,
     esp_idf_path: String,
-) -> Result<String, ()> {
+) -> HelmResult<String> {
     let file_path = Path::new(&esp_idf_path).join(INSTALL_SCRIPT_NAME);
     info!("Running install script: {:?}", file_path);
 
@@ -36,7 +42,8 @@ pub fn run_install_script(
             "bash",
             &args,
             PROGRESS_EVENT,
-        );
+        )
+        .await?;
     }
 
     #[cfg(windows)]
@@ -48,7 +55,8 @@ pub fn run_install_script(
             "cmd",
             &args,
             PROGRESS_EVENT,
-        );
+        )
+        .await?;
     }
 
     Ok("Success".to_string())
@@ -59,7 +67,7 @@ pub async fn download_esp_idf(
     app: tauri::AppHandle,
     version: String,
     dest_path: String,
-) -> Result<(), ()> {
+) -> HelmResult<()> {
     let url = format!(
         "https://github.com/espressif/esp-idf/releases/download/{}/esp-idf-{}.zip",
         version, version
@@ -83,13 +91,13 @@ pub async fn download_esp_idf(
         };
 
         if is_file_corrupted {
-            tokio::fs::remove_file(&dest_path).await.unwrap();
+            tokio::fs::remove_file(&dest_path).await?;
         }
     }
 
     // Ensure parent directory exists
     if let Some(parent_path) = dest_path.parent() {
-        tokio::fs::create_dir_all(parent_path).await.unwrap();
+        tokio::fs::create_dir_all(long_path(parent_path)).await?;
     }
 
     match download_file(window, app, &url, dest_path).await {
@@ -99,7 +107,7 @@ pub async fn download_esp_idf(
         }
         Err(err) => {
             info!("Failed to download ESP-IDF: {}", err);
-            Err(())
+            Err(err)
         }
     }
 }
@@ -114,3 +122,136 @@ fn check_zip(path: &Path) -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+// Tools of tools-only install, GCC for both architectures and OpenOCD for JTAG flashing
+const DEFAULT_IDF_TOOLS: [&str; 4] = [
+    "xtensa-esp-elf",
+    "riscv32-esp-elf",
+    "openocd-esp32",
+    "esp-rom-elfs",
+];
+
+#[derive(serde::Serialize)]
+pub struct IdfToolsInstall {
+    version: String,
+    tools: Vec<String>,
+    bin_dirs: Vec<String>,
+}
+
+// idf_tools.py and tools.json are all what is needed from ESP-IDF, they are stored in
+// tools/ of this directory which is passed as IDF path
+fn idf_tools_dir(version: &str) -> HelmResult<PathBuf> {
+    if version.is_empty() || version.contains(['/', '\\']) || version.contains("..") {
+        return Err(HelmError::Validation(format!(
+            "Invalid ESP-IDF version {}",
+            version
+        )));
+    }
+    espressif_home()
+        .map(|home| home.join("idf-tools").join(version))
+        .ok_or(HelmError::NotFound("home directory".into()))
+}
+
+// Directories which idf_tools.py export would add to PATH, from recommended version of each
+// tool. Export itself fails when any tool of the platform is missing.
+fn tool_bin_dirs(tools_json: &Path, tools: &[String]) -> HelmResult<Vec<PathBuf>> {
+    let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(tools_json)?)
+        .map_err(|e| HelmError::Validation(format!("tools.json: {}", e)))?;
+    let tools_home = espressif_home()
+        .ok_or(HelmError::NotFound("home directory".into()))?
+        .join("tools");
+    let list = |value: &serde_json::Value| value.as_array().cloned().unwrap_or_default();
+
+    let mut dirs = vec![];
+    for name in tools {
+        let tool = list(&json["tools"])
+            .into_iter()
+            .find(|tool| tool["name"] == name.as_str())
+            .ok_or_else(|| HelmError::NotFound(format!("{} in tools.json", name)))?;
+        let version = list(&tool["versions"])
+            .into_iter()
+            .find(|version| version["status"] == "recommended")
+            .and_then(|version| version["name"].as_str().map(str::to_string))
+            .ok_or_else(|| HelmError::NotFound(format!("recommended version of {}", name)))?;
+        for export_path in list(&tool["export_paths"]) {
+            let dir = list(&export_path)
+                .iter()
+                .filter_map(|part| part.as_str())
+                .fold(tools_home.join(name).join(&version), |dir, part| {
+                    dir.join(part)
+                });
+            if dir.exists() {
+                dirs.push(dir);
+            }
+        }
+    }
+    Ok(dirs)
+}
+
+async fn install_idf_tools(
+    window: Window,
+    app: AppHandle,
+    version: String,
+    tools: Vec<String>,
+) -> HelmResult<IdfToolsInstall> {
+    let idf_dir = idf_tools_dir(&version)?;
+    let tools_dir = idf_dir.join("tools");
+    tokio::fs::create_dir_all(long_path(&tools_dir)).await?;
+    for file in ["idf_tools.py", "tools.json"] {
+        let url = format!(
+            "https://raw.githubusercontent.com/espressif/esp-idf/{}/tools/{}",
+            version, file
+        );
+        download_file(window.clone(), app.clone(), &url, &tools_dir.join(file)).await?;
+    }
+
+    let python = find_python()?.to_string_lossy().to_string();
+    let script = tools_dir.join("idf_tools.py").to_string_lossy().to_string();
+    let tools_json = tools_dir.join("tools.json");
+    let tools_json_path = tools_json.to_string_lossy().to_string();
+    let idf_path = idf_dir.to_string_lossy().to_string();
+    // Tools go to IDF_TOOLS_PATH, set for child processes by toolchain_env
+    let mut args = vec![
+        script.as_str(),
+        "--idf-path",
+        &idf_path,
+        "--tools-json",
+        &tools_json_path,
+        "install",
+    ];
+    args.extend(tools.iter().map(String::as_str));
+    run_external_command_with_progress(window, app, &python, &args, PROGRESS_EVENT).await?;
+
+    let bin_dirs = tool_bin_dirs(&tools_json, &tools)?;
+    set_export_path("esp-idf tools", &bin_dirs)?;
+    info!("ESP-IDF {} tools installed: {}", version, tools.join(", "));
+    Ok(IdfToolsInstall {
+        version,
+        tools,
+        bin_dirs: bin_dirs
+            .iter()
+            .map(|dir| dir.to_string_lossy().to_string())
+            .collect(),
+    })
+}
+
+// Command to install tools of ESP-IDF version without downloading ESP-IDF itself, for users
+// who need only GCC and flashing tools. Bin directories are added to the export file.
+#[tauri::command]
+pub async fn install_esp_idf_tools(
+    window: Window,
+    app: AppHandle,
+    version: String,
+    tools: Option<Vec<String>>,
+) -> HelmResult<IdfToolsInstall> {
+    let tools = tools.filter(|tools| !tools.is_empty()).unwrap_or_else(|| {
+        DEFAULT_IDF_TOOLS
+            .iter()
+            .map(|tool| tool.to_string())
+            .collect()
+    });
+    let operation = begin_operation(&app, OperationKind::Install, "ESP-IDF tools");
+    operation
+        .scope(install_idf_tools(window, app.clone(), version, tools))
+        .await
+}
@@ -0,0 +1,221 @@
+use std::path::PathBuf;
+
+use log::info;
+use tauri::{AppHandle, Window};
+
+use crate::device::device_info;
+use crate::error::{HelmError, HelmResult};
+use crate::external_command::{run_external_command_output, run_external_command_with_progress};
+use crate::package_manager::find_in_path;
+use crate::portable::app_data_dir;
+
+// esptool, espsecure and espefuse are installed to own venv, so system Python stays untouched
+fn venv_dir() -> Option<PathBuf> {
+    app_data_dir().map(|dir| dir.join("esptool-venv"))
+}
+
+fn venv_bin(name: &str) -> Option<PathBuf> {
+    let venv = venv_dir()?;
+    #[cfg(windows)]
+    let path = venv.join("Scripts").join(format!("{}.exe", name));
+    #[cfg(not(windows))]
+    let path = venv.join("bin").join(name);
+    Some(path).filter(|path| path.exists())
+}
+
+// Python of managed venv, used to run modules without console script like NVS generator
+pub fn venv_python() -> HelmResult<String> {
+    venv_bin("python")
+        .or_else(|| venv_bin("python3"))
+        .map(|path| path.to_string_lossy().to_string())
+        .ok_or(HelmError::NotFound(
+            "Python venv (install esptool first)".into(),
+        ))
+}
+
+// Tool from managed venv, falls back to one installed by user with pip
+pub fn espressif_tool(name: &str) -> HelmResult<String> {
+    venv_bin(name)
+        .or_else(|| find_in_path(&format!("{}.py", name)))
+        .or_else(|| find_in_path(name))
+        .map(|path| path.to_string_lossy().to_string())
+        .ok_or(HelmError::NotFound(format!(
+            "{} (install esptool first)",
+            name
+        )))
+}
+
+pub fn find_python() -> HelmResult<PathBuf> {
+    find_in_path("python3")
+        .or_else(|| find_in_path("python"))
+        .ok_or(HelmError::NotFound("Python 3".into()))
+}
+
+// Command to create venv with esptool and NVS partition generator, running it again upgrades them
+#[tauri::command]
+pub async fn install_esptool(window: Window, app: AppHandle) -> HelmResult<String> {
+    let venv = venv_dir().ok_or(HelmError::NotFound("Local data directory".into()))?;
+    let venv_path = venv.to_string_lossy().to_string();
+
+    if venv_bin("python").is_none() && venv_bin("python3").is_none() {
+        let python = find_python()?.to_string_lossy().to_string();
+        info!("Creating venv in {}", venv_path);
+        run_external_command_with_progress(
+            window.clone(),
+            app.clone(),
+            &python,
+            &["-m", "venv", &venv_path],
+            "PROGRESS_EVENT",
+        )
+        .await?;
+    }
+
+    let pip = venv_bin("pip").ok_or(HelmError::NotFound(format!("pip in {}", venv_path)))?;
+    run_external_command_with_progress(
+        window,
+        app,
+        &pip.to_string_lossy(),
+        &[
+            "install",
+            "--upgrade",
+            "esptool",
+            "esp-idf-nvs-partition-gen",
+        ],
+        "PROGRESS_EVENT",
+    )
+    .await?;
+
+    let esptool = espressif_tool("esptool")?;
+    let version = run_external_command_output(&esptool, &["version"]).await?;
+    Ok(version
+        .lines()
+        .last()
+        .unwrap_or_default()
+        .trim()
+        .to_string())
+}
+
+#[derive(serde::Serialize)]
+pub struct ChipInfo {
+    chip: Option<String>,
+    pub revision: Option<String>,
+    features: Vec<String>,
+    mac: Option<String>,
+    crystal: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+pub struct FlashId {
+    manufacturer: Option<String>,
+    device: Option<String>,
+    size: Option<String>,
+}
+
+// Value of "Key: value" line in esptool output
+fn field<'a>(output: &'a str, key: &str) -> Option<&'a str> {
+    output.lines().find_map(|line| {
+        line.trim()
+            .strip_prefix(key)
+            .and_then(|rest| rest.strip_prefix(':'))
+            .map(str::trim)
+    })
+}
+
+// "Chip is ESP32-C3 (QFN32) (revision v0.4)"
+fn parse_chip_info(output: &str) -> ChipInfo {
+    let chip_line = output
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("Chip is "));
+    ChipInfo {
+        chip: chip_line.map(|line| {
+            line.split_whitespace()
+                .next()
+                .unwrap_or_default()
+                .to_lowercase()
+                .replace('-', "")
+        }),
+        revision: chip_line
+            .and_then(|line| line.split_once("(revision "))
+            .map(|(_, revision)| revision.trim_end_matches(')').to_string()),
+        features: field(output, "Features")
+            .map(|features| features.split(", ").map(str::to_string).collect())
+            .unwrap_or_default(),
+        mac: field(output, "MAC").map(str::to_string),
+        crystal: output
+            .lines()
+            .find_map(|line| line.trim().strip_prefix("Crystal is "))
+            .map(str::to_string),
+    }
+}
+
+async fn run_esptool(port: &str, command: &str) -> HelmResult<String> {
+    let esptool = espressif_tool("esptool")?;
+    run_external_command_output(&esptool, &["--port", port, command]).await
+}
+
+// esptool is missing, or it or its Python fails, so espflash is tried instead. espflash
+// reports the same except SPI flash manufacturer and device.
+fn use_espflash(result: &HelmResult<String>) -> bool {
+    match result {
+        Err(e @ (HelmError::NotFound(_) | HelmError::ChildProcessFailed { .. })) => {
+            info!("esptool is not usable, using espflash: {}", e);
+            true
+        }
+        _ => false,
+    }
+}
+
+// Command to read chip type, revision and MAC
+#[tauri::command]
+pub async fn esptool_chip_info(port: String) -> HelmResult<ChipInfo> {
+    let output = run_esptool(&port, "read_mac").await;
+    if use_espflash(&output) {
+        let info = device_info(&port).await?;
+        return Ok(ChipInfo {
+            chip: Some(info.chip.to_string()),
+            revision: info
+                .revision
+                .map(|(major, minor)| format!("v{}.{}", major, minor)),
+            features: info.features,
+            mac: Some(info.mac_address),
+            crystal: Some(format!("{}MHz", info.crystal_frequency)),
+        });
+    }
+    Ok(parse_chip_info(&output?))
+}
+
+// Command to read SPI flash manufacturer, device and size
+#[tauri::command]
+pub async fn esptool_flash_id(port: String) -> HelmResult<FlashId> {
+    let output = run_esptool(&port, "flash_id").await;
+    if use_espflash(&output) {
+        let info = device_info(&port).await?;
+        return Ok(FlashId {
+            manufacturer: None,
+            device: None,
+            size: Some(format!("{}MB", info.flash_size.size() / (1024 * 1024))),
+        });
+    }
+    let output = output?;
+    Ok(FlashId {
+        manufacturer: field(&output, "Manufacturer").map(str::to_string),
+        device: field(&output, "Device").map(str::to_string),
+        size: field(&output, "Detected flash size").map(str::to_string),
+    })
+}
+
+// Command to read all eFuses, returns espefuse JSON summary keyed by eFuse name
+#[tauri::command]
+pub async fn esptool_efuse_summary(port: String) -> HelmResult<serde_json::Value> {
+    let espefuse = espressif_tool("espefuse")?;
+    let output =
+        run_external_command_output(&espefuse, &["--port", &port, "summary", "--format", "json"])
+            .await?;
+    // Connection log is printed before the JSON
+    let json = output
+        .find('{')
+        .map(|start| &output[start..])
+        .ok_or(HelmError::Other("espefuse returned no summary".into()))?;
+    serde_json::from_str(json)
+        .map_err(|e| HelmError::Other(format!("Invalid espefuse output: {}", e)))
+}
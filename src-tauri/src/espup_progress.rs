@@ -0,0 +1,199 @@
+use std::sync::Mutex;
+
+use log::{info, Record};
+use tauri::Window;
+
+use crate::events::{emit_event, EspupProgress as EspupProgressEvent};
+use crate::i18n::tr;
+
+// Share of artifact progress reached when its download finished and extraction started
+const DOWNLOADED_WEIGHT: f64 = 0.8;
+
+// Progress of running espup installation, fed by log records of espup crate
+static ACTIVE: Mutex<Option<EspupProgress>> = Mutex::new(None);
+
+// espup downloads each artifact to memory without reporting bytes, so progress is
+// computed from artifacts it started downloading and extracting, logged at debug level:
+// Downloading 'https://github.com/.../rust-1.77.0.0-x86_64-unknown-linux-gnu.tar.xz' to '/tmp/...'
+// Extracting tar.xz file to '/home/user/.rustup/toolchains/esp'
+struct ArtifactCounter {
+    expected: usize,
+    downloading: usize,
+    extracting: usize,
+    skipped: usize,
+}
+
+impl ArtifactCounter {
+    // Rust, rust-src and LLVM are installed for Xtensa, GCC only for std projects
+    fn new(targets: &[String], std: bool, esp_riscv_gcc: bool) -> Self {
+        let xtensa = targets.is_empty()
+            || targets
+                .iter()
+                .any(|target| matches!(target.as_str(), "esp32" | "esp32s2" | "esp32s3"));
+        let riscv = targets.is_empty()
+            || targets
+                .iter()
+                .any(|target| target.contains("esp32c") || target == "esp32h2");
+        let mut expected = 0;
+        if xtensa {
+            expected += 3;
+            if std {
+                expected += 1;
+            }
+        }
+        if riscv && std && esp_riscv_gcc {
+            expected += 1;
+        }
+        Self {
+            expected: expected.max(1),
+            downloading: 0,
+            extracting: 0,
+            skipped: 0,
+        }
+    }
+
+    // 100 is reported only after espup finished
+    fn pct(&self) -> f64 {
+        let done = self.skipped as f64
+            + self.extracting as f64 * DOWNLOADED_WEIGHT
+            + (self.downloading - self.extracting.min(self.downloading)) as f64 * 0.1;
+        (done / self.expected as f64 * 100.0).min(99.0)
+    }
+
+    // Artifact and stage of recognized message, other messages are ignored
+    fn message(&mut self, message: &str) -> Option<(Option<String>, &'static str)> {
+        if let Some(rest) = message.strip_prefix("Downloading '") {
+            let url = rest.split('\'').next().unwrap_or_default();
+            let artifact = url.rsplit('/').next().map(str::to_string);
+            info!("espup downloads {}", url);
+            self.downloading += 1;
+            Some((artifact, "download"))
+        } else if message.starts_with("Extracting") || message.starts_with("Uncompressing") {
+            self.extracting += 1;
+            Some((None, "extract"))
+        } else if message.contains("already installed")
+            || message.starts_with("Previous installation")
+        {
+            self.skipped += 1;
+            Some((None, "skip"))
+        } else {
+            None
+        }
+    }
+}
+
+pub struct EspupProgress {
+    window: Window,
+    counter: ArtifactCounter,
+}
+
+impl EspupProgress {
+    pub fn new(window: Window, targets: &[String], std: bool, esp_riscv_gcc: bool) -> Self {
+        Self {
+            window,
+            counter: ArtifactCounter::new(targets, std, esp_riscv_gcc),
+        }
+    }
+
+    fn message(&mut self, message: &str) {
+        let Some((artifact, stage)) = self.counter.message(message) else {
+            return;
+        };
+        let payload = EspupProgressEvent {
+            pct: format!("{:.0}", self.counter.pct()),
+            artifact,
+            stage: stage.to_string(),
+            stage_label: tr(&format!("stage-{}", stage), &[]),
+        };
+        emit_event(&self.window, &payload);
+    }
+
+    fn finish(&self) {
+        let payload = EspupProgressEvent {
+            pct: "100".into(),
+            artifact: None,
+            stage: "done".into(),
+            stage_label: tr("stage-done", &[]),
+        };
+        emit_event(&self.window, &payload);
+    }
+}
+
+// Records of espup crate are passed to active progress, debug level is needed for downloads
+pub fn is_espup_record(target: &str) -> bool {
+    target.starts_with("espup")
+}
+
+pub fn start(progress: EspupProgress) {
+    *ACTIVE.lock().unwrap() = Some(progress);
+    log::set_max_level(log::LevelFilter::Debug);
+}
+
+pub fn log_record(record: &Record) {
+    if let Some(progress) = ACTIVE.lock().unwrap().as_mut() {
+        progress.message(&record.args().to_string());
+    }
+}
+
+pub fn finish(success: bool) {
+    log::set_max_level(log::LevelFilter::Info);
+    if let Some(progress) = ACTIVE.lock().unwrap().take() {
+        if success {
+            progress.finish();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_expected_artifacts() {
+        assert_eq!(ArtifactCounter::new(&[], false, false).expected, 3);
+        assert_eq!(ArtifactCounter::new(&[], true, true).expected, 5);
+        let riscv = vec!["esp32c3".to_string()];
+        assert_eq!(ArtifactCounter::new(&riscv, true, true).expected, 1);
+        assert_eq!(ArtifactCounter::new(&riscv, false, false).expected, 1);
+    }
+
+    #[test]
+    fn parses_espup_messages() {
+        let mut counter = ArtifactCounter::new(&[], false, false);
+        assert_eq!(
+            counter.message(
+                "Downloading 'https://github.com/esp-rs/rust-build/releases/download/v1.77.0.0/\
+                 rust-1.77.0.0-x86_64-unknown-linux-gnu.tar.xz' to '/tmp/.tmpAbc/rust.tar.xz'"
+            ),
+            Some((
+                Some("rust-1.77.0.0-x86_64-unknown-linux-gnu.tar.xz".into()),
+                "download"
+            ))
+        );
+        assert_eq!(
+            counter.message("Extracting tar.xz file to '/home/user/.rustup/toolchains/esp'"),
+            Some((None, "extract"))
+        );
+        assert_eq!(
+            counter.message("Previous installation of LLVM exists in: '/home/user/.rustup'"),
+            Some((None, "skip"))
+        );
+        assert_eq!(counter.message("Installing Xtensa Rust toolchain"), None);
+        assert_eq!(
+            (counter.downloading, counter.extracting, counter.skipped),
+            (1, 1, 1)
+        );
+    }
+
+    #[test]
+    fn progress_stays_below_100() {
+        let mut counter = ArtifactCounter::new(&[], false, false);
+        assert_eq!(counter.pct(), 0.0);
+        counter.message("Downloading 'https://example.com/rust-src.tar.xz' to '/tmp/x'");
+        assert!((counter.pct() - 10.0 / 3.0).abs() < 1e-9);
+        for _ in 0..5 {
+            counter.message("Previous installation of rust-src exists");
+        }
+        assert_eq!(counter.pct(), 99.0);
+    }
+}
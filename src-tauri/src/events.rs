@@ -0,0 +1,340 @@
+use std::collections::BTreeSet;
+use std::time::Duration;
+
+use schemars::{schema_for, JsonSchema};
+use serde_json::Value;
+use tauri::{AppHandle, Manager, Window};
+
+use crate::app_state::JobInfo;
+use crate::debug_session::{BreakpointEvent, DebugOutput, DebugStateChange, StopEvent};
+use crate::deploy::DeployStage;
+use crate::dfu::DfuProgress;
+use crate::env_snapshot::DriftItem;
+use crate::error::HelmResult;
+use crate::factory::StagePayload;
+use crate::flasher::{DeviceStatus, FlashError, FlashMessage, FlashProgressEvent};
+use crate::git::CloneProgress;
+use crate::lint::Diagnostic;
+use crate::monitor::{MonitorLine, MonitorMessage};
+use crate::ota::{OtaProgress, OtaStatus};
+use crate::plot::PlotSample;
+use crate::provision::{HostOutput, HostStatus};
+use crate::test_runner::{TestOutput, TestResult};
+use crate::update_checks::AvailableUpdate;
+use crate::verify::VerificationReport;
+
+// Increased when a field is removed or changes meaning, new optional fields keep the version
+pub const EVENT_SCHEMA_VERSION: u32 = 1;
+// Serial ports are polled, there is no portable notification about USB changes
+const HOTPLUG_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+// Payload of event with stable contract, listed in get_event_schema
+pub trait HelmEvent: serde::Serialize + JsonSchema + Clone {
+    const NAMES: &'static [&'static str];
+
+    fn name(&self) -> &'static str {
+        Self::NAMES[0]
+    }
+}
+
+// Payload fields stay at top level, so consumers of version 1 only gain "version"
+#[derive(Clone, serde::Serialize)]
+struct Versioned<'a, E> {
+    version: u32,
+    #[serde(flatten)]
+    payload: &'a E,
+}
+
+pub fn emit_event<E: HelmEvent>(window: &Window, event: &E) {
+    let versioned = Versioned {
+        version: EVENT_SCHEMA_VERSION,
+        payload: event,
+    };
+    window.emit(event.name(), versioned).unwrap();
+}
+
+// Hotplug is not tied to a window, every open window gets it
+pub fn emit_event_all<E: HelmEvent>(app: &AppHandle, event: &E) {
+    let versioned = Versioned {
+        version: EVENT_SCHEMA_VERSION,
+        payload: event,
+    };
+    app.emit_all(event.name(), versioned).unwrap();
+}
+
+// Events of one job or session are also emitted on "<name>/<scope>", e.g. "job-update/3"
+pub fn emit_event_scoped<E: HelmEvent>(window: &Window, event: &E, scope: impl std::fmt::Display) {
+    let versioned = Versioned {
+        version: EVENT_SCHEMA_VERSION,
+        payload: event,
+    };
+    window
+        .emit(&format!("{}/{}", event.name(), scope), &versioned)
+        .unwrap();
+    window.emit(event.name(), versioned).unwrap();
+}
+
+pub fn emit_event_all_scoped<E: HelmEvent>(
+    app: &AppHandle,
+    event: &E,
+    scope: impl std::fmt::Display,
+) {
+    let versioned = Versioned {
+        version: EVENT_SCHEMA_VERSION,
+        payload: event,
+    };
+    app.emit_all(&format!("{}/{}", event.name(), scope), &versioned)
+        .unwrap();
+    app.emit_all(event.name(), versioned).unwrap();
+}
+
+// Log record shown in console panel
+#[derive(Clone, serde::Serialize, JsonSchema)]
+pub struct ConsoleLine {
+    pub message: String,
+}
+
+impl HelmEvent for ConsoleLine {
+    const NAMES: &'static [&'static str] = &["rust-console"];
+}
+
+// Line of external command output, stderr has own event so it can be highlighted
+#[derive(Clone, serde::Serialize, JsonSchema)]
+pub struct CommandOutputLine {
+    pub command: String,
+    // "stdout" or "stderr"
+    pub source: String,
+    pub line: String,
+    // Milliseconds since Unix epoch
+    pub timestamp: u128,
+}
+
+impl HelmEvent for CommandOutputLine {
+    const NAMES: &'static [&'static str] = &["command-stdout", "command-stderr"];
+
+    fn name(&self) -> &'static str {
+        if self.source == "stderr" {
+            Self::NAMES[1]
+        } else {
+            Self::NAMES[0]
+        }
+    }
+}
+
+// External command produced no output for a long time
+#[derive(Clone, serde::Serialize, JsonSchema)]
+pub struct CommandWarning {
+    pub command: String,
+    pub message: String,
+    pub silent_secs: u64,
+}
+
+impl HelmEvent for CommandWarning {
+    const NAMES: &'static [&'static str] = &["command-warning"];
+}
+
+// Prompt which waits for answer_prompt command with the same id
+#[derive(Clone, serde::Serialize, JsonSchema)]
+pub struct CommandQuestion {
+    pub id: u64,
+    pub command: String,
+    pub prompt: String,
+}
+
+impl HelmEvent for CommandQuestion {
+    const NAMES: &'static [&'static str] = &["command-question"];
+}
+
+#[derive(Clone, serde::Serialize, JsonSchema)]
+pub struct DownloadProgress {
+    // Percentage with two decimals, empty when size is unknown
+    pub pct: String,
+    // Current download speed in bytes per second
+    pub speed: u64,
+    pub downloaded: u64,
+    pub total: Option<u64>,
+    // Server did not send Content-Length, e.g. chunked transfer encoding, UI shows spinner
+    pub indeterminate: bool,
+}
+
+impl HelmEvent for DownloadProgress {
+    const NAMES: &'static [&'static str] = &["progress"];
+}
+
+// Sum of all running downloads
+#[derive(Clone, serde::Serialize, JsonSchema)]
+pub struct QueueProgress {
+    pub downloaded: u64,
+    // Sum of known sizes, downloads without Content-Length are not included
+    pub total: u64,
+    pub speed: u64,
+    pub running: usize,
+    pub queued: usize,
+}
+
+impl HelmEvent for QueueProgress {
+    const NAMES: &'static [&'static str] = &["download-queue-progress"];
+}
+
+#[derive(Clone, serde::Serialize, JsonSchema)]
+pub struct ExtractProgress {
+    pub archive: String,
+    pub entry: String,
+    pub entries: u64,
+    // Based on compressed bytes read for tar archives and on entry count for zip,
+    // empty when streamed archive has unknown size
+    pub pct: String,
+}
+
+impl HelmEvent for ExtractProgress {
+    const NAMES: &'static [&'static str] = &["extract-progress"];
+}
+
+#[derive(Clone, serde::Serialize, JsonSchema)]
+pub struct EspupProgress {
+    pub pct: String,
+    // File name of the artifact espup works on, e.g. rust-1.77.0.0-x86_64-unknown-linux-gnu.tar.xz
+    pub artifact: Option<String>,
+    // "download", "extract", "skip" or "done"
+    pub stage: String,
+    // Stage name in selected language
+    pub stage_label: String,
+}
+
+impl HelmEvent for EspupProgress {
+    const NAMES: &'static [&'static str] = &["espup-progress"];
+}
+
+// Serial ports which appeared or disappeared since previous event
+#[derive(Clone, serde::Serialize, JsonSchema)]
+pub struct DeviceHotplug {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub ports: Vec<String>,
+}
+
+impl HelmEvent for DeviceHotplug {
+    const NAMES: &'static [&'static str] = &["device-hotplug"];
+}
+
+// Tools and components changed outside esp-helm since its previous run
+#[derive(Clone, serde::Serialize, JsonSchema)]
+pub struct EnvironmentDrift {
+    pub changes: Vec<DriftItem>,
+}
+
+impl HelmEvent for EnvironmentDrift {
+    const NAMES: &'static [&'static str] = &["environment-drift"];
+}
+
+// Newer releases of installed tools found by background check, nothing is installed
+#[derive(Clone, serde::Serialize, JsonSchema)]
+pub struct UpdatesAvailable {
+    pub updates: Vec<AvailableUpdate>,
+    // Unix time of the check
+    pub checked_at: u64,
+}
+
+impl HelmEvent for UpdatesAvailable {
+    const NAMES: &'static [&'static str] = &["updates-available"];
+}
+
+#[derive(serde::Serialize)]
+pub struct EventDescription {
+    name: String,
+    schema: Value,
+}
+
+#[derive(serde::Serialize)]
+pub struct EventSchema {
+    version: u32,
+    events: Vec<EventDescription>,
+}
+
+fn describe<E: HelmEvent>(events: &mut Vec<EventDescription>) {
+    let schema = serde_json::to_value(schema_for!(E)).unwrap_or(Value::Null);
+    for name in E::NAMES {
+        events.push(EventDescription {
+            name: name.to_string(),
+            schema: schema.clone(),
+        });
+    }
+}
+
+pub fn event_schema() -> EventSchema {
+    let mut events = vec![];
+    describe::<ConsoleLine>(&mut events);
+    describe::<CommandOutputLine>(&mut events);
+    describe::<CommandWarning>(&mut events);
+    describe::<CommandQuestion>(&mut events);
+    describe::<DownloadProgress>(&mut events);
+    describe::<QueueProgress>(&mut events);
+    describe::<ExtractProgress>(&mut events);
+    describe::<EspupProgress>(&mut events);
+    describe::<DeviceHotplug>(&mut events);
+    describe::<EnvironmentDrift>(&mut events);
+    describe::<UpdatesAvailable>(&mut events);
+    describe::<JobInfo>(&mut events);
+    describe::<CloneProgress>(&mut events);
+    describe::<FlashMessage>(&mut events);
+    describe::<FlashError>(&mut events);
+    describe::<FlashProgressEvent>(&mut events);
+    describe::<DeviceStatus>(&mut events);
+    describe::<MonitorLine>(&mut events);
+    describe::<MonitorMessage>(&mut events);
+    describe::<PlotSample>(&mut events);
+    describe::<DeployStage>(&mut events);
+    describe::<VerificationReport>(&mut events);
+    describe::<Diagnostic>(&mut events);
+    describe::<TestOutput>(&mut events);
+    describe::<TestResult>(&mut events);
+    describe::<StopEvent>(&mut events);
+    describe::<BreakpointEvent>(&mut events);
+    describe::<DebugOutput>(&mut events);
+    describe::<DebugStateChange>(&mut events);
+    describe::<OtaProgress>(&mut events);
+    describe::<OtaStatus>(&mut events);
+    describe::<HostOutput>(&mut events);
+    describe::<HostStatus>(&mut events);
+    describe::<StagePayload>(&mut events);
+    describe::<DfuProgress>(&mut events);
+    EventSchema {
+        version: EVENT_SCHEMA_VERSION,
+        events,
+    }
+}
+
+fn serial_ports() -> BTreeSet<String> {
+    serialport::available_ports()
+        .map(|ports| ports.into_iter().map(|port| port.port_name).collect())
+        .unwrap_or_default()
+}
+
+// Emits device-hotplug whenever list of serial ports changes, runs for the whole session
+pub fn start_hotplug_watch(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut known = serial_ports();
+        loop {
+            tokio::time::sleep(HOTPLUG_POLL_INTERVAL).await;
+            let ports = tokio::task::spawn_blocking(serial_ports)
+                .await
+                .unwrap_or_default();
+            if ports == known {
+                continue;
+            }
+            let event = DeviceHotplug {
+                added: ports.difference(&known).cloned().collect(),
+                removed: known.difference(&ports).cloned().collect(),
+                ports: ports.iter().cloned().collect(),
+            };
+            emit_event_all(&app, &event);
+            known = ports;
+        }
+    });
+}
+
+// Command to get JSON Schema of every versioned event, for frontend and third-party UIs
+#[tauri::command]
+pub async fn get_event_schema() -> HelmResult<EventSchema> {
+    Ok(event_schema())
+}
@@ -1,89 +1,733 @@
+use std::collections::VecDeque;
 use std::process::Stdio;
 use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
-use crate::app_state::{AppState, BuilderState};
+use crate::ansi::{last_line_state, strip_ansi};
+use crate::app_state::AppState;
+use crate::audit::audit_command;
+use crate::error::{HelmError, HelmResult};
+use crate::events::{emit_event, CommandOutputLine, CommandQuestion, CommandWarning};
+use crate::operations::{is_aborted, is_paused, within_operation, OperationKind};
+use crate::process_control::{new_process_group, ProcessTree};
+use crate::toolchain_env::toolchain_env;
 use tauri::Manager;
 use tauri::Window;
 
 use log::info;
 
-fn is_abort_state(app: tauri::AppHandle) -> bool {
+#[cfg(windows)]
+const CREATE_NO_WINDOW: u32 = 0x08000000; // Windows specific constant to hide console window
+
+// Warn user when command is silent for this long
+const INACTIVITY_WARNING: Duration = Duration::from_secs(120);
+// Lines of output searched for known causes when command fails
+const TAIL_LINES: usize = 100;
+
+#[derive(Default)]
+struct OutputTail(VecDeque<String>);
+
+impl OutputTail {
+    fn push(&mut self, line: &str) {
+        if self.0.len() == TAIL_LINES {
+            self.0.pop_front();
+        }
+        self.0.push_back(line.to_string());
+    }
+
+    fn failure(&self, code: Option<i32>) -> HelmError {
+        let output: Vec<&str> = self.0.iter().map(String::as_str).collect();
+        HelmError::child_process_failed(code, &output.join("\n"))
+    }
+}
+
+// Each stream has own event, so the frontend can highlight stderr
+fn emit_output_line(window: &Window, command: &str, source: &str, raw_line: &str) {
+    let line = strip_ansi(last_line_state(raw_line));
+    if line.trim().is_empty() {
+        return;
+    }
+
+    info!("[{}] {}", source, line);
+
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default();
+    let payload = CommandOutputLine {
+        command: command.to_string(),
+        source: source.to_string(),
+        line,
+        timestamp,
+    };
+    emit_event(window, &payload);
+}
+
+// Log level selected in monitor is used by builds started from esp-helm
+fn get_esp_log(app: tauri::AppHandle) -> Option<String> {
     let state_mutex = app.state::<Mutex<AppState>>();
     let state = state_mutex.lock().unwrap();
-    matches!(state.builder, BuilderState::Abort)
+    state.settings.esp_log.clone()
+}
+
+// Timeouts configured in settings, None means no limit
+fn get_timeouts(app: tauri::AppHandle) -> (Option<Duration>, Option<Duration>) {
+    let state_mutex = app.state::<Mutex<AppState>>();
+    let state = state_mutex.lock().unwrap();
+    (
+        state
+            .settings
+            .command_inactivity_timeout_secs
+            .map(Duration::from_secs),
+        state
+            .settings
+            .command_total_timeout_secs
+            .map(Duration::from_secs),
+    )
 }
 
 use tokio::io::AsyncBufReadExt;
 use tokio::process::Command;
 
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+// Abort, pause and timeouts of running child. Checks run on ticks of one interval which
+// lives for the whole command, so frequent output can not starve them.
+struct ChildControl {
+    app: tauri::AppHandle,
+    command: String,
+    interval: tokio::time::Interval,
+    inactivity_timeout: Option<Duration>,
+    total_timeout: Option<Duration>,
+    started: Instant,
+    paused_total: Duration,
+    paused_at: Option<Instant>,
+    last_output: Instant,
+    last_warning: Instant,
+    // Paused and killed as a whole, None when pid of the child is not known
+    tree: Option<ProcessTree>,
+}
+
+impl ChildControl {
+    fn new(app: &tauri::AppHandle, command: &str, pid: Option<u32>) -> Self {
+        let mut interval = tokio::time::interval(POLL_INTERVAL);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        let (inactivity_timeout, total_timeout) = get_timeouts(app.clone());
+        let now = Instant::now();
+        ChildControl {
+            app: app.clone(),
+            command: command.to_string(),
+            interval,
+            inactivity_timeout,
+            total_timeout,
+            started: now,
+            paused_total: Duration::ZERO,
+            paused_at: None,
+            last_output: now,
+            last_warning: now,
+            tree: pid.map(ProcessTree::attach),
+        }
+    }
+
+    async fn tick(&mut self) {
+        self.interval.tick().await;
+    }
+
+    fn output(&mut self) {
+        self.last_output = Instant::now();
+    }
+
+    fn finish(&mut self) {
+        if let Some(tree) = &mut self.tree {
+            tree.finish();
+        }
+    }
+
+    async fn kill(&mut self, child: &mut tokio::process::Child) {
+        kill_tree(self.tree.as_ref(), child).await;
+    }
+
+    fn set_paused(&mut self, paused: bool) {
+        let Some(tree) = &self.tree else {
+            return;
+        };
+        let result = if paused {
+            info!("Pausing command.");
+            tree.suspend()
+        } else {
+            info!("Resuming command.");
+            tree.resume()
+        };
+        if let Err(err) = result {
+            info!("Failed to change process state: {:?}", err);
+            return;
+        }
+        if paused {
+            self.paused_at = Some(Instant::now());
+        } else if let Some(paused_at) = self.paused_at.take() {
+            // Time spent in pause does not count to timeouts
+            let paused_for = paused_at.elapsed();
+            self.paused_total += paused_for;
+            self.last_output += paused_for;
+            self.last_warning += paused_for;
+        }
+    }
+
+    // Error means the child has to be killed
+    fn check(&mut self, window: &Window) -> HelmResult<()> {
+        if is_aborted() {
+            info!("Aborting command due to external signal.");
+            return Err(HelmError::Cancelled);
+        }
+
+        let paused = is_paused();
+        if paused != self.paused_at.is_some() {
+            self.set_paused(paused);
+        }
+        if self.paused_at.is_some() {
+            return Ok(());
+        }
+
+        let silent_for = self.last_output.elapsed();
+        if let Some(timeout) = self.inactivity_timeout {
+            if silent_for >= timeout {
+                info!(
+                    "No output for {} seconds, killing command.",
+                    silent_for.as_secs()
+                );
+                return Err(HelmError::Timeout(format!(
+                    "{} produced no output for {} seconds",
+                    self.command,
+                    silent_for.as_secs()
+                )));
+            }
+        }
+        if let Some(timeout) = self.total_timeout {
+            let active_time = self.started.elapsed().saturating_sub(self.paused_total);
+            if active_time >= timeout {
+                info!(
+                    "Command exceeded {} seconds, killing it.",
+                    timeout.as_secs()
+                );
+                return Err(HelmError::Timeout(format!(
+                    "{} did not finish in {} seconds",
+                    self.command,
+                    timeout.as_secs()
+                )));
+            }
+        }
+
+        if silent_for >= INACTIVITY_WARNING && self.last_warning.elapsed() >= INACTIVITY_WARNING {
+            self.last_warning = Instant::now();
+            let message = format!("No output for {} minutes", silent_for.as_secs() / 60);
+            info!("{}", message);
+            let warning = CommandWarning {
+                command: self.command.clone(),
+                message,
+                silent_secs: silent_for.as_secs(),
+            };
+            emit_event(window, &warning);
+        }
+        Ok(())
+    }
+}
+
 pub async fn run_external_command_with_progress(
-    _window: Window,
+    window: Window,
+    app: tauri::AppHandle,
+    cmd_name: &str,
+    cmd_args: &[&str],
+    progress_event: &str,
+) -> HelmResult<String> {
+    run_external_command_in_dir(window, app, None, cmd_name, cmd_args, progress_event).await
+}
+
+// Same as run_external_command_with_progress, cargo needs to run in the project directory
+// to pick up its .cargo/config.toml
+pub async fn run_external_command_in_dir(
+    window: Window,
     app: tauri::AppHandle,
+    current_dir: Option<&std::path::Path>,
     cmd_name: &str,
     cmd_args: &[&str],
     _progress_event: &str,
-) -> Result<String, ()> {
+) -> HelmResult<String> {
+    let work = run_in_dir(window, app.clone(), current_dir, cmd_name, cmd_args);
+    within_operation(&app, OperationKind::Other, cmd_name, work).await
+}
+
+async fn run_in_dir(
+    window: Window,
+    app: tauri::AppHandle,
+    current_dir: Option<&std::path::Path>,
+    cmd_name: &str,
+    cmd_args: &[&str],
+) -> HelmResult<String> {
     let cmd_name_owned = cmd_name.to_string();
     let cmd_args_owned: Vec<String> = cmd_args.iter().map(|&s| s.to_string()).collect();
 
     info!("Command: {} {}", cmd_name_owned, cmd_args_owned.join(" "));
+    audit_command(cmd_name, cmd_args);
 
-    let mut child = Command::new(&cmd_name_owned)
+    let mut command = Command::new(&cmd_name_owned);
+    command.envs(toolchain_env());
+    if let Some(esp_log) = get_esp_log(app.clone()) {
+        command.env("ESP_LOG", esp_log);
+    }
+    if let Some(dir) = current_dir {
+        command.current_dir(dir);
+    }
+    new_process_group(&mut command);
+    let mut child = command
         .args(&cmd_args_owned)
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
+        // Cancelled jobs drop the future, child process must not outlive it
+        .kill_on_drop(true)
         .spawn()
-        .expect("Failed to launch command");
-
-    let mut stdout = tokio::io::BufReader::new(child.stdout.take().unwrap());
-    let mut stderr = tokio::io::BufReader::new(child.stderr.take().unwrap());
-
-    let mut stdout_buf = String::new();
-    let mut stderr_buf = String::new();
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => {
+                HelmError::NotFound(format!("{}: {}", cmd_name_owned, e))
+            }
+            _ => HelmError::from(e),
+        })?;
 
-    let poll_interval = tokio::time::Duration::from_millis(100); // adjust as necessary
+    let mut stdout = tokio::io::BufReader::new(child.stdout.take().unwrap()).lines();
+    let mut stderr = tokio::io::BufReader::new(child.stderr.take().unwrap()).lines();
+    let mut stdout_open = true;
+    let mut stderr_open = true;
+    let mut tail = OutputTail::default();
+    let mut control = ChildControl::new(&app, &cmd_name_owned, child.id());
 
+    // Both streams are read to the end before exit status, so last lines are not lost
     loop {
-        tokio::select! {
-            _ = stdout.read_line(&mut stdout_buf) => {
-                if !stdout_buf.is_empty() {
-                    info!("{}", stdout_buf);
-                    stdout_buf.clear();
-                }
+        let (source, line) = tokio::select! {
+            line = stdout.next_line(), if stdout_open => {
+                let line = line?;
+                stdout_open = line.is_some();
+                ("stdout", line)
             },
-            _ = stderr.read_line(&mut stderr_buf) => {
-                if !stderr_buf.is_empty() {
-                    info!("{}", stderr_buf);
-                    stderr_buf.clear();
-                }
+            line = stderr.next_line(), if stderr_open => {
+                let line = line?;
+                stderr_open = line.is_some();
+                ("stderr", line)
             },
-            status = child.wait() => {
+            status = child.wait(), if !stdout_open && !stderr_open => {
+                control.finish();
                 match status {
                     Ok(status) if status.success() => {
                         info!("Done");
                         return Ok("Child process completed successfully".to_string());
                     },
-                    Ok(_) => {
+                    Ok(status) => {
                         info!("Child process exited with an error");
-                        return Err(());
+                        return Err(tail.failure(status.code()));
                     },
                     Err(err) => {
                         info!("Child process encountered an error: {:?}", err);
-                        return Err(());
+                        return Err(err.into());
                     },
                 }
             },
-            _ = tokio::time::sleep(poll_interval) => {
-                if is_abort_state(app.clone()) {
+            _ = control.tick() => {
+                if let Err(e) = control.check(&window) {
+                    control.kill(&mut child).await;
+                    return Err(e);
+                }
+                continue;
+            }
+        };
+        if let Some(line) = line {
+            control.output();
+            emit_output_line(&window, &cmd_name_owned, source, &line);
+            tail.push(&line);
+        }
+    }
+}
+
+// Child leads process group or job, killing the child alone would leave the processes it
+// started running with pipes inherited from it
+async fn kill_tree(tree: Option<&ProcessTree>, child: &mut tokio::process::Child) {
+    if let Some(tree) = tree {
+        if let Err(err) = tree.kill() {
+            info!("Failed to kill process tree: {:?}", err);
+        }
+    }
+    let _ = child.kill().await;
+}
+
+// Partial line without newline is treated as prompt after this delay
+const PROMPT_DELAY: Duration = Duration::from_millis(500);
+
+// Choices offered by confirmation prompts, e.g. "Continue? [Y/n]"
+const CONFIRM_CHOICES: [&str; 6] = [
+    "[y/n]",
+    "(y/n)",
+    "[yes/no]",
+    "(yes/no)",
+    "(yes/no/[fingerprint])",
+    "[default: y]",
+];
+// Credential prompts end with colon, e.g. "Password for 'https://github.com':"
+const CREDENTIAL_WORDS: [&str; 3] = ["password", "passphrase", "username"];
+
+fn looks_like_prompt(line: &str) -> bool {
+    let line = line.trim();
+    let lower = line.to_lowercase();
+    let choices = lower.trim_end_matches([':', '?', ' ']);
+    // rustup-init asks for menu choice with bare ">"
+    line == ">"
+        || CONFIRM_CHOICES
+            .iter()
+            .any(|choice| choices.ends_with(choice))
+        || (lower.ends_with(':') && CREDENTIAL_WORDS.iter().any(|word| lower.contains(word)))
+}
+
+// Forward question to the frontend, answer_prompt command sends the answer to returned receiver
+fn post_question(
+    window: &Window,
+    app: &tauri::AppHandle,
+    command: &str,
+    prompt: &str,
+) -> (u64, tokio::sync::oneshot::Receiver<String>) {
+    let (sender, receiver) = tokio::sync::oneshot::channel();
+    let id = {
+        let state_mutex = app.state::<Mutex<AppState>>();
+        let mut state = state_mutex.lock().unwrap();
+        state.next_prompt_id += 1;
+        let id = state.next_prompt_id;
+        state.prompts.insert(id, sender);
+        id
+    };
+
+    info!("Command {} asks: {}", command, prompt);
+    let question = CommandQuestion {
+        id,
+        command: command.to_string(),
+        prompt: prompt.to_string(),
+    };
+    emit_event(window, &question);
+    (id, receiver)
+}
+
+fn forget_question(app: &tauri::AppHandle, id: u64) {
+    let state_mutex = app.state::<Mutex<AppState>>();
+    state_mutex.lock().unwrap().prompts.remove(&id);
+}
+
+// Forward question to the frontend and wait for the answer from answer_prompt command
+pub async fn ask_question(
+    window: &Window,
+    app: tauri::AppHandle,
+    command: &str,
+    prompt: &str,
+) -> HelmResult<String> {
+    let (id, mut receiver) = post_question(window, &app, command, prompt);
+    let mut ticks = tokio::time::interval(POLL_INTERVAL);
+    loop {
+        tokio::select! {
+            answer = &mut receiver => {
+                return answer.map_err(|_| HelmError::Cancelled);
+            },
+            _ = ticks.tick() => {
+                if is_aborted() {
+                    forget_question(&app, id);
+                    return Err(HelmError::Cancelled);
+                }
+            }
+        }
+    }
+}
+
+// Run command without streaming and return its stdout, used by wrappers parsing tool output
+pub async fn run_external_command_output(cmd_name: &str, cmd_args: &[&str]) -> HelmResult<String> {
+    info!("Command: {} {}", cmd_name, cmd_args.join(" "));
+    audit_command(cmd_name, cmd_args);
+
+    let mut command = Command::new(cmd_name);
+    command
+        .args(cmd_args)
+        .envs(toolchain_env())
+        .kill_on_drop(true);
+    #[cfg(windows)]
+    command.creation_flags(CREATE_NO_WINDOW);
+
+    let output = command.output().await.map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => HelmError::NotFound(format!("{}: {}", cmd_name, e)),
+        _ => HelmError::from(e),
+    })?;
+    if !output.status.success() {
+        info!(
+            "Command failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+        return Err(HelmError::child_process_failed(
+            output.status.code(),
+            &String::from_utf8_lossy(&output.stderr),
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+// Run command in directory and pass each output line to on_line, returning true from it
+// stops the command. Returns whether the command succeeded.
+pub async fn run_external_command_lines<F>(
+    app: &tauri::AppHandle,
+    dir: &std::path::Path,
+    cmd_name: &str,
+    cmd_args: &[String],
+    on_line: F,
+) -> HelmResult<bool>
+where
+    F: FnMut(&str) -> bool,
+{
+    let work = run_lines(app, dir, cmd_name, cmd_args, on_line);
+    within_operation(app, OperationKind::Other, cmd_name, work).await
+}
+
+async fn run_lines<F>(
+    app: &tauri::AppHandle,
+    dir: &std::path::Path,
+    cmd_name: &str,
+    cmd_args: &[String],
+    mut on_line: F,
+) -> HelmResult<bool>
+where
+    F: FnMut(&str) -> bool,
+{
+    info!("Command: {} {}", cmd_name, cmd_args.join(" "));
+    let args: Vec<&str> = cmd_args.iter().map(String::as_str).collect();
+    audit_command(cmd_name, &args);
+    let mut command = Command::new(cmd_name);
+    command
+        .args(cmd_args)
+        .envs(toolchain_env())
+        .current_dir(dir)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    new_process_group(&mut command);
+    let mut child = command.spawn().map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => HelmError::NotFound(format!("{}: {}", cmd_name, e)),
+        _ => HelmError::from(e),
+    })?;
+    let mut tree = child.id().map(ProcessTree::attach);
+
+    let mut stdout = tokio::io::BufReader::new(child.stdout.take().unwrap()).lines();
+    let mut stderr = tokio::io::BufReader::new(child.stderr.take().unwrap()).lines();
+    let mut stdout_open = true;
+    let mut stderr_open = true;
+    let mut ticks = tokio::time::interval(POLL_INTERVAL);
+
+    loop {
+        let line = tokio::select! {
+            line = stdout.next_line(), if stdout_open => {
+                let line = line?;
+                stdout_open = line.is_some();
+                line
+            },
+            line = stderr.next_line(), if stderr_open => {
+                let line = line?;
+                stderr_open = line.is_some();
+                line
+            },
+            status = child.wait(), if !stdout_open && !stderr_open => {
+                if let Some(tree) = &mut tree {
+                    tree.finish();
+                }
+                return Ok(status?.success());
+            },
+            _ = ticks.tick() => {
+                if is_aborted() {
                     info!("Aborting command due to external signal.");
-                    let _ = child.kill();
-                    return Err(());
+                    kill_tree(tree.as_ref(), &mut child).await;
+                    return Err(HelmError::Cancelled);
                 }
+                None
             }
+        };
+        if let Some(line) = line {
+            if on_line(&line) {
+                kill_tree(tree.as_ref(), &mut child).await;
+                return Ok(true);
+            }
+        }
+    }
+}
+
+// Command to answer question of interactive command
+#[tauri::command]
+pub async fn answer_prompt(app: tauri::AppHandle, id: u64, answer: String) -> HelmResult<String> {
+    let sender = {
+        let state_mutex = app.state::<Mutex<AppState>>();
+        let mut state = state_mutex.lock().unwrap();
+        state.prompts.remove(&id)
+    };
+
+    match sender {
+        Some(sender) => {
+            let _ = sender.send(answer);
+            Ok("ok".to_string())
         }
+        None => Err(HelmError::NotFound(format!("Prompt {}", id))),
     }
 }
 
+fn pty_error<E: std::fmt::Display>(error: E) -> HelmError {
+    HelmError::Other(format!("PTY error: {}", error))
+}
+
+// PTY child is not killed when its handle is dropped, cancelled job would leave it running
+struct PtyChild(Box<dyn portable_pty::Child + Send + Sync>);
+
+impl Drop for PtyChild {
+    fn drop(&mut self) {
+        if let Ok(None) = self.0.try_wait() {
+            let _ = self.0.kill();
+        }
+    }
+}
+
+// Run command in pseudo terminal, so it behaves like in interactive shell.
+// Prompts detected in the output are forwarded to the frontend as questions.
+pub async fn run_external_command_interactive(
+    window: Window,
+    app: tauri::AppHandle,
+    cmd_name: &str,
+    cmd_args: &[&str],
+) -> HelmResult<String> {
+    let work = run_interactive(window, app.clone(), cmd_name, cmd_args);
+    within_operation(&app, OperationKind::Other, cmd_name, work).await
+}
+
+async fn run_interactive(
+    window: Window,
+    app: tauri::AppHandle,
+    cmd_name: &str,
+    cmd_args: &[&str],
+) -> HelmResult<String> {
+    use portable_pty::{native_pty_system, CommandBuilder, PtySize};
+    use std::io::{Read, Write};
+
+    info!("Interactive command: {} {}", cmd_name, cmd_args.join(" "));
+    audit_command(cmd_name, cmd_args);
+
+    let pair = native_pty_system()
+        .openpty(PtySize {
+            rows: 24,
+            cols: 120,
+            pixel_width: 0,
+            pixel_height: 0,
+        })
+        .map_err(pty_error)?;
+
+    let mut cmd = CommandBuilder::new(cmd_name);
+    cmd.args(cmd_args);
+    for (name, value) in toolchain_env() {
+        cmd.env(name, value);
+    }
+    if let Some(esp_log) = get_esp_log(app.clone()) {
+        cmd.env("ESP_LOG", esp_log);
+    }
+    let mut child = PtyChild(pair.slave.spawn_command(cmd).map_err(pty_error)?);
+    // Slave end belongs to the child now, keeping it open would prevent EOF
+    drop(pair.slave);
+
+    let mut reader = pair.master.try_clone_reader().map_err(pty_error)?;
+    let mut writer = pair.master.take_writer().map_err(pty_error)?;
+
+    // PTY reader is blocking, read it in separate thread
+    let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel::<Vec<u8>>();
+    std::thread::spawn(move || {
+        let mut buffer = [0u8; 1024];
+        while let Ok(count) = reader.read(&mut buffer) {
+            if count == 0 || sender.send(buffer[..count].to_vec()).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut partial = String::new();
+    let mut tail = OutputTail::default();
+    let mut control = ChildControl::new(&app, cmd_name, child.0.process_id());
+    let mut last_output = Instant::now();
+    let mut prompted = false;
+    let mut eof = false;
+    // Question waiting for answer, output and abort are still handled meanwhile
+    let mut question: Option<(u64, tokio::sync::oneshot::Receiver<String>)> = None;
+
+    let result = loop {
+        tokio::select! {
+            data = receiver.recv(), if !eof => {
+                match data {
+                    Some(data) => {
+                        control.output();
+                        last_output = Instant::now();
+                        prompted = false;
+                        for c in String::from_utf8_lossy(&data).chars() {
+                            partial.push(c);
+                            if c == '\n' {
+                                emit_output_line(&window, cmd_name, "stdout", &partial);
+                                tail.push(&partial);
+                                partial.clear();
+                            }
+                        }
+                    }
+                    None => eof = true,
+                }
+            },
+            answer = async { (&mut question.as_mut().unwrap().1).await }, if question.is_some() => {
+                question = None;
+                let Ok(answer) = answer else {
+                    break Err(HelmError::Cancelled);
+                };
+                partial.clear();
+                // Enter key in terminal sends carriage return
+                if let Err(e) = writer
+                    .write_all(format!("{}\r", answer).as_bytes())
+                    .and_then(|_| writer.flush())
+                {
+                    break Err(e.into());
+                }
+            },
+            _ = control.tick() => {
+                if let Err(e) = control.check(&window) {
+                    break Err(e);
+                }
+
+                let status = match child.0.try_wait() {
+                    Ok(status) => status,
+                    Err(e) => break Err(e.into()),
+                };
+                if let Some(status) = status {
+                    emit_output_line(&window, cmd_name, "stdout", &partial);
+                    tail.push(&partial);
+                    if status.success() {
+                        info!("Done");
+                        break Ok("Child process completed successfully".to_string());
+                    }
+                    info!("Child process exited with an error");
+                    break Err(tail.failure(Some(status.exit_code() as i32)));
+                }
+
+                let prompt = strip_ansi(last_line_state(&partial));
+                if question.is_none()
+                    && !prompted
+                    && last_output.elapsed() >= PROMPT_DELAY
+                    && looks_like_prompt(&prompt)
+                {
+                    prompted = true;
+                    question = Some(post_question(&window, &app, cmd_name, &prompt));
+                }
+            }
+        }
+    };
+    if let Some((id, _)) = question {
+        forget_question(&app, id);
+    }
+    result
+}
+
 #[cfg(unix)]
 use std::os::unix::fs::PermissionsExt;
 
@@ -96,7 +740,20 @@ pub fn set_exec_permission(path: &std::path::Path) -> std::io::Result<()> {
     fs::set_permissions(path, perms)
 }
 
-#[cfg(windows)]
-pub fn set_exec_permission(path: &std::path::Path) -> std::io::Result<()> {
-    todo!()
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_known_prompts() {
+        assert!(looks_like_prompt(">"));
+        assert!(looks_like_prompt("Proceed with installation? [Y/n] "));
+        assert!(looks_like_prompt(
+            "Are you sure you want to continue connecting (yes/no/[fingerprint])? "
+        ));
+        assert!(looks_like_prompt("Password for 'https://github.com': "));
+        assert!(!looks_like_prompt("info: downloading component 'rustc':"));
+        assert!(!looks_like_prompt("warning: what is this?"));
+        assert!(!looks_like_prompt("<stdin> -> <stdout>"));
+    }
 }
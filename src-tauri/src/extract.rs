@@ -0,0 +1,695 @@
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use futures::StreamExt;
+use log::info;
+use tauri::{AppHandle, Window};
+
+use crate::audit::{audit, AuditAction};
+use crate::download::{download_file, enqueue, finish_download, DownloadId, Pacer, QueuedTransfer};
+use crate::error::{HelmError, HelmResult};
+use crate::events::{emit_event, ExtractProgress};
+use crate::operations::{current_token, is_aborted, with_token, within_operation, OperationKind};
+use crate::services::services;
+
+// Chunks buffered between network and extraction, bounds memory when disk is slower
+const STREAM_BUFFER_CHUNKS: usize = 64;
+
+#[derive(Clone, Copy, PartialEq)]
+enum ArchiveKind {
+    Zip,
+    Tar,
+    TarGz,
+    TarXz,
+    TarZst,
+}
+
+fn archive_kind(archive: &Path) -> HelmResult<ArchiveKind> {
+    let name = archive
+        .file_name()
+        .map(|name| name.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let kinds = [
+        (".zip", ArchiveKind::Zip),
+        (".tar.gz", ArchiveKind::TarGz),
+        (".tgz", ArchiveKind::TarGz),
+        (".tar.xz", ArchiveKind::TarXz),
+        (".txz", ArchiveKind::TarXz),
+        (".tar.zst", ArchiveKind::TarZst),
+        (".tzst", ArchiveKind::TarZst),
+        (".tar", ArchiveKind::Tar),
+    ];
+    kinds
+        .iter()
+        .find(|(extension, _)| name.ends_with(extension))
+        .map(|(_, kind)| *kind)
+        .ok_or(HelmError::Validation(format!(
+            "Unsupported archive {}",
+            archive.display()
+        )))
+}
+
+// Entry path relative to destination, None for entries which would escape it
+// or which are removed completely by strip_components
+fn entry_target(dest: &Path, entry: &Path, strip_components: usize) -> HelmResult<Option<PathBuf>> {
+    let mut relative = PathBuf::new();
+    for component in entry.components() {
+        match component {
+            Component::Normal(part) => relative.push(part),
+            Component::CurDir => {}
+            _ => {
+                return Err(HelmError::Validation(format!(
+                    "Archive entry {} points outside of destination",
+                    entry.display()
+                )))
+            }
+        }
+    }
+    let stripped: PathBuf = relative.components().skip(strip_components).collect();
+    if stripped.as_os_str().is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(dest.join(stripped)))
+}
+
+fn outside_error(entry: &Path) -> HelmError {
+    HelmError::Validation(format!(
+        "Archive entry {} points outside of destination",
+        entry.display()
+    ))
+}
+
+// Parent of target is created and canonicalized, so links extracted earlier can not redirect
+// the entry outside of destination. Returns the path the entry is written to.
+fn prepare_target(dest: &Path, target: &Path) -> HelmResult<PathBuf> {
+    let (Some(parent), Some(name)) = (target.parent(), target.file_name()) else {
+        return Err(outside_error(target));
+    };
+    // Existing part of the path is checked before anything is created in it
+    let existing = parent
+        .ancestors()
+        .find(|ancestor| ancestor.exists())
+        .ok_or_else(|| outside_error(target))?;
+    if !existing.canonicalize()?.starts_with(dest) {
+        return Err(outside_error(target));
+    }
+    std::fs::create_dir_all(parent)?;
+    let parent = parent.canonicalize()?;
+    if !parent.starts_with(dest) {
+        return Err(outside_error(target));
+    }
+    Ok(parent.join(name))
+}
+
+// Symlink target must stay in destination. Links extracted earlier are followed while
+// resolving, so a chain of links which are inside one by one can not leave it.
+fn is_link_inside(dest: &Path, link: &Path, target: &Path) -> bool {
+    if target.is_absolute() {
+        return false;
+    }
+    let mut resolved = link.parent().map(PathBuf::from).unwrap_or_default();
+    for component in target.components() {
+        match component {
+            Component::ParentDir => {
+                if !resolved.pop() {
+                    return false;
+                }
+            }
+            Component::Normal(part) => {
+                resolved.push(part);
+                if let Ok(canonical) = resolved.canonicalize() {
+                    resolved = canonical;
+                }
+            }
+            _ => {}
+        }
+    }
+    resolved.starts_with(dest)
+}
+
+// Entry paths and link names are relative to archive root, strip_components applies to both.
+// Hard links are created here, tar crate would resolve their source against working directory.
+fn unpack_tar_entry<R: Read>(
+    dest: &Path,
+    entry: &mut tar::Entry<R>,
+    strip_components: usize,
+) -> HelmResult<()> {
+    let name = entry.path()?.to_path_buf();
+    let Some(target) = entry_target(dest, &name, strip_components)? else {
+        return Ok(());
+    };
+    let target = prepare_target(dest, &target)?;
+    let kind = entry.header().entry_type();
+    if kind.is_hard_link() || kind.is_symlink() {
+        let link = entry
+            .link_name()?
+            .ok_or_else(|| HelmError::Validation(format!("Link {} has no target", name.display())))?
+            .into_owned();
+        if kind.is_symlink() {
+            if !is_link_inside(dest, &target, &link) {
+                return Err(outside_error(&name));
+            }
+        } else {
+            let source = entry_target(dest, &link, strip_components)?
+                .ok_or_else(|| outside_error(&name))?
+                .canonicalize()?;
+            if !source.starts_with(dest) {
+                return Err(outside_error(&name));
+            }
+            if target.symlink_metadata().is_ok() {
+                std::fs::remove_file(&target)?;
+            }
+            std::fs::hard_link(&source, &target)?;
+            return Ok(());
+        }
+    }
+    entry.unpack(&target)?;
+    Ok(())
+}
+
+// Counts compressed bytes so progress of tar archives is known without listing them first
+struct CountingReader<R> {
+    inner: R,
+    count: Arc<AtomicU64>,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.count.fetch_add(read as u64, Ordering::Relaxed);
+        Ok(read)
+    }
+}
+
+// Blocking side of streamed download, chunks are received from async task
+struct ChannelReader {
+    chunks: tokio::sync::mpsc::Receiver<io::Result<Vec<u8>>>,
+    current: Vec<u8>,
+    position: usize,
+}
+
+impl Read for ChannelReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.current.len() {
+            match self.chunks.blocking_recv() {
+                Some(chunk) => {
+                    self.current = chunk?;
+                    self.position = 0;
+                }
+                None => return Ok(0),
+            }
+        }
+        let amount = buf.len().min(self.current.len() - self.position);
+        buf[..amount].copy_from_slice(&self.current[self.position..self.position + amount]);
+        self.position += amount;
+        Ok(amount)
+    }
+}
+
+struct Extraction<'a> {
+    window: &'a Window,
+    archive: String,
+    dest: &'a Path,
+    strip_components: usize,
+    entries: u64,
+}
+
+impl Extraction<'_> {
+    fn entry_done(&mut self, entry: &Path, pct: Option<f64>) -> HelmResult<()> {
+        self.entries += 1;
+        let progress = ExtractProgress {
+            archive: self.archive.clone(),
+            entry: entry.to_string_lossy().to_string(),
+            entries: self.entries,
+            pct: pct.map(|pct| format!("{:.2}", pct)).unwrap_or_default(),
+        };
+        emit_event(self.window, &progress);
+        if is_aborted() {
+            info!("Extraction of {} aborted", self.archive);
+            return Err(HelmError::Cancelled);
+        }
+        Ok(())
+    }
+
+    fn zip(&mut self, file: File) -> HelmResult<()> {
+        let mut zip = zip::ZipArchive::new(file)?;
+        let total = zip.len().max(1);
+        for index in 0..zip.len() {
+            let mut entry = zip.by_index(index)?;
+            let name = entry
+                .enclosed_name()
+                .map(PathBuf::from)
+                .ok_or(HelmError::Validation(format!(
+                    "Archive entry {} points outside of destination",
+                    entry.name()
+                )))?;
+            if let Some(target) = entry_target(self.dest, &name, self.strip_components)? {
+                let target = prepare_target(self.dest, &target)?;
+                if entry.is_dir() {
+                    std::fs::create_dir_all(&target)?;
+                } else {
+                    io::copy(&mut entry, &mut File::create(&target)?)?;
+                    #[cfg(unix)]
+                    if let Some(mode) = entry.unix_mode() {
+                        use std::os::unix::fs::PermissionsExt;
+                        std::fs::set_permissions(&target, std::fs::Permissions::from_mode(mode))?;
+                    }
+                }
+            }
+            self.entry_done(&name, Some((index + 1) as f64 / total as f64 * 100.0))?;
+        }
+        Ok(())
+    }
+
+    fn tar<R: Read>(&mut self, reader: R, read: &AtomicU64, total: Option<u64>) -> HelmResult<()> {
+        let mut archive = tar::Archive::new(reader);
+        archive.set_preserve_permissions(true);
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let name = entry.path()?.to_path_buf();
+            unpack_tar_entry(self.dest, &mut entry, self.strip_components)?;
+            let pct = total
+                .map(|total| read.load(Ordering::Relaxed) as f64 / total.max(1) as f64 * 100.0);
+            self.entry_done(&name, pct.map(|pct| pct.min(100.0)))?;
+        }
+        Ok(())
+    }
+
+    // Tar archives are read sequentially, so reader can be file or network stream
+    fn compressed_tar<R: Read>(
+        &mut self,
+        kind: ArchiveKind,
+        reader: R,
+        total: Option<u64>,
+    ) -> HelmResult<()> {
+        let read = Arc::new(AtomicU64::new(0));
+        let reader = io::BufReader::new(CountingReader {
+            inner: reader,
+            count: read.clone(),
+        });
+        match kind {
+            ArchiveKind::Tar => self.tar(reader, &read, total),
+            ArchiveKind::TarGz => self.tar(flate2::read::GzDecoder::new(reader), &read, total),
+            ArchiveKind::TarXz => self.tar(xz2::read::XzDecoder::new(reader), &read, total),
+            ArchiveKind::TarZst => {
+                self.tar(zstd::stream::read::Decoder::new(reader)?, &read, total)
+            }
+            ArchiveKind::Zip => Err(HelmError::Validation(
+                "Zip archive can not be extracted sequentially".into(),
+            )),
+        }
+    }
+}
+
+fn extract_blocking(
+    window: &Window,
+    archive: &Path,
+    dest: &Path,
+    strip_components: usize,
+) -> HelmResult<u64> {
+    let kind = archive_kind(archive)?;
+    std::fs::create_dir_all(dest)?;
+    audit(
+        AuditAction::CreateDir,
+        &dest.to_string_lossy(),
+        Some(format!("Extract {}", archive.display())),
+    );
+    let dest = dest.canonicalize()?;
+    let file = File::open(archive)?;
+    let total = file.metadata()?.len();
+    let mut extraction = Extraction {
+        window,
+        archive: archive.to_string_lossy().to_string(),
+        dest: &dest,
+        strip_components,
+        entries: 0,
+    };
+
+    if kind == ArchiveKind::Zip {
+        extraction.zip(file)?;
+        return Ok(extraction.entries);
+    }
+    extraction.compressed_tar(kind, file, Some(total))?;
+    Ok(extraction.entries)
+}
+
+// Extract zip, tar, tar.gz, tar.xz or tar.zst archive, returns number of entries.
+// Entries are streamed, so archive is never loaded to memory or listed twice.
+pub async fn extract_archive(
+    window: Window,
+    app: AppHandle,
+    archive: PathBuf,
+    dest: PathBuf,
+    strip_components: usize,
+) -> HelmResult<u64> {
+    info!("Extracting {} to {}", archive.display(), dest.display());
+    let name = format!("Extract {}", archive.display());
+    within_operation(&app, OperationKind::Install, &name, async move {
+        let token = current_token();
+        tokio::task::spawn_blocking(move || {
+            with_token(token, || {
+                extract_blocking(&window, &archive, &dest, strip_components)
+            })
+        })
+        .await
+        .map_err(|_| HelmError::Other("Extraction task panicked".into()))?
+    })
+    .await
+}
+
+// Command to extract archive, strip_components removes leading directories like tar does
+#[tauri::command]
+pub async fn extract(
+    window: Window,
+    app: AppHandle,
+    archive: String,
+    dest: String,
+    strip_components: Option<usize>,
+) -> HelmResult<String> {
+    let entries = extract_archive(
+        window,
+        app,
+        PathBuf::from(archive),
+        PathBuf::from(&dest),
+        strip_components.unwrap_or(0),
+    )
+    .await?;
+    Ok(format!("{} entries extracted to {}", entries, dest))
+}
+
+fn url_file_name(url: &str) -> String {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    path.rsplit('/').next().unwrap_or(path).to_string()
+}
+
+// Feeds response body to extraction running on blocking thread. Stream takes a slot in
+// download queue and is paused and throttled like downloads to disk. Transport errors are
+// returned as HelmError::Network, so caller can retry with download to disk.
+async fn stream_extract(
+    window: Window,
+    app: AppHandle,
+    url: &str,
+    kind: ArchiveKind,
+    dest: PathBuf,
+    strip_components: usize,
+) -> HelmResult<u64> {
+    let id = enqueue(&app, url, &dest, 0).await?;
+    let result = stream_queued(&window, &app, id, url, kind, dest, strip_components).await;
+    finish_download(&window, &app, id, result.is_ok());
+    result
+}
+
+async fn stream_queued(
+    window: &Window,
+    app: &AppHandle,
+    id: DownloadId,
+    url: &str,
+    kind: ArchiveKind,
+    dest: PathBuf,
+    strip_components: usize,
+) -> HelmResult<u64> {
+    let response = services(app).http.get(url).await?;
+    // Same response would be returned again to download to disk
+    match response.status {
+        404 => return Err(HelmError::NotFound(url.to_string())),
+        status if status >= 400 => {
+            return Err(HelmError::Other(format!(
+                "HTTP status {} for {}",
+                status, url
+            )))
+        }
+        _ => {}
+    }
+    let total = response.content_length.filter(|size| *size > 0);
+    let mut body = response.body;
+    std::fs::create_dir_all(&dest)?;
+    audit(
+        AuditAction::CreateDir,
+        &dest.to_string_lossy(),
+        Some(format!("Extract {}", url)),
+    );
+    let dest = dest.canonicalize()?;
+    let (sender, chunks) = tokio::sync::mpsc::channel(STREAM_BUFFER_CHUNKS);
+    let archive = url.to_string();
+    let token = current_token();
+    let extraction_window = window.clone();
+    let extraction = tokio::task::spawn_blocking(move || -> HelmResult<u64> {
+        with_token(token, || {
+            let mut extraction = Extraction {
+                window: &extraction_window,
+                archive,
+                dest: &dest,
+                strip_components,
+                entries: 0,
+            };
+            let reader = ChannelReader {
+                chunks,
+                current: vec![],
+                position: 0,
+            };
+            extraction.compressed_tar(kind, reader, total)?;
+            Ok(extraction.entries)
+        })
+    });
+
+    let mut pacer = Pacer::new(QueuedTransfer { window, app, id }, total);
+    let mut stopped = None;
+    while let Some(chunk) = body.next().await {
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                let error = io::Error::new(io::ErrorKind::ConnectionAborted, e.clone());
+                let _ = sender.send(Err(error)).await;
+                stopped = Some(e);
+                break;
+            }
+        };
+        let len = chunk.len();
+        // Send fails when extraction stopped, its result explains why
+        if sender.send(Ok(chunk)).await.is_err() {
+            break;
+        }
+        if let Err(e) = pacer.chunk_done(len).await {
+            let error = io::Error::new(io::ErrorKind::Other, e.clone());
+            let _ = sender.send(Err(error)).await;
+            stopped = Some(e);
+            break;
+        }
+    }
+    drop(sender);
+
+    let result = extraction
+        .await
+        .map_err(|_| HelmError::Other("Extraction task panicked".into()))?;
+    match stopped {
+        Some(e) => Err(e),
+        None => result,
+    }
+}
+
+// Download archive and extract it while it is being downloaded, so archive never
+// takes disk space. Zip needs its central directory at the end of file, so it and
+// streams which fail are downloaded through the queue to disk first.
+pub async fn download_and_extract(
+    window: Window,
+    app: AppHandle,
+    url: &str,
+    dest: PathBuf,
+    strip_components: usize,
+) -> HelmResult<u64> {
+    let work = stream_or_download(window, app.clone(), url, dest, strip_components);
+    within_operation(
+        &app,
+        OperationKind::Install,
+        &format!("Extract {}", url),
+        work,
+    )
+    .await
+}
+
+async fn stream_or_download(
+    window: Window,
+    app: AppHandle,
+    url: &str,
+    dest: PathBuf,
+    strip_components: usize,
+) -> HelmResult<u64> {
+    let name = url_file_name(url);
+    let kind = archive_kind(Path::new(&name))?;
+    if kind != ArchiveKind::Zip {
+        info!("Streaming {} to {}", url, dest.display());
+        let streamed = stream_extract(
+            window.clone(),
+            app.clone(),
+            url,
+            kind,
+            dest.clone(),
+            strip_components,
+        )
+        .await;
+        match streamed {
+            Err(HelmError::Network(e)) => {
+                info!("Streaming of {} failed, downloading to disk: {}", url, e)
+            }
+            result => return result,
+        }
+    }
+
+    let archive = std::env::temp_dir().join(&name);
+    download_file(window.clone(), app.clone(), url, &archive).await?;
+    let result = extract_archive(window, app, archive.clone(), dest, strip_components).await;
+    let _ = tokio::fs::remove_file(&archive).await;
+    result
+}
+
+// Command to download archive and extract it to dest
+#[tauri::command]
+pub async fn download_and_extract_archive(
+    window: Window,
+    app: AppHandle,
+    url: String,
+    dest: String,
+    strip_components: Option<usize>,
+) -> HelmResult<String> {
+    let entries = download_and_extract(
+        window,
+        app,
+        &url,
+        PathBuf::from(&dest),
+        strip_components.unwrap_or(0),
+    )
+    .await?;
+    Ok(format!("{} entries extracted to {}", entries, dest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    enum Entry<'a> {
+        File(&'a str, &'a [u8]),
+        Symlink(&'a str, &'a str),
+        HardLink(&'a str, &'a str),
+    }
+
+    // Names are written to header directly, tar::Builder refuses the malicious ones
+    fn tar_archive(entries: &[Entry]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(vec![]);
+        for entry in entries {
+            let (kind, name, link, data): (_, _, _, &[u8]) = match entry {
+                Entry::File(name, data) => (tar::EntryType::Regular, name, None, data),
+                Entry::Symlink(name, link) => (tar::EntryType::Symlink, name, Some(link), &[]),
+                Entry::HardLink(name, link) => (tar::EntryType::Link, name, Some(link), &[]),
+            };
+            let mut header = tar::Header::new_old();
+            let old = header.as_old_mut();
+            old.name[..name.len()].copy_from_slice(name.as_bytes());
+            if let Some(link) = link {
+                old.linkname[..link.len()].copy_from_slice(link.as_bytes());
+            }
+            header.set_entry_type(kind);
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append(&header, data).unwrap();
+        }
+        builder.into_inner().unwrap()
+    }
+
+    // Returns destination inside of a fresh directory, so escapes land next to it. The
+    // directory is removed when the returned TempDir is dropped.
+    fn unpack(entries: &[Entry], strip_components: usize) -> (TempDir, PathBuf, HelmResult<()>) {
+        let root = TempDir::new().unwrap();
+        let dest = root.path().join("dest");
+        std::fs::create_dir_all(&dest).unwrap();
+        let dest = dest.canonicalize().unwrap();
+        let data = tar_archive(entries);
+        let mut archive = tar::Archive::new(data.as_slice());
+        let result = archive
+            .entries()
+            .map_err(HelmError::from)
+            .and_then(|entries| {
+                for entry in entries {
+                    unpack_tar_entry(&dest, &mut entry?, strip_components)?;
+                }
+                Ok(())
+            });
+        (root, dest, result)
+    }
+
+    fn escaped(dest: &Path, name: &str) -> bool {
+        dest.parent().unwrap().join(name).exists()
+    }
+
+    #[test]
+    fn extracts_regular_entries() {
+        let entries = [Entry::File("top/bin/tool", b"tool")];
+        let (_root, dest, result) = unpack(&entries, 1);
+        result.unwrap();
+        assert_eq!(std::fs::read(dest.join("bin/tool")).unwrap(), b"tool");
+    }
+
+    #[test]
+    fn rejects_parent_dir_entry() {
+        let (_root, dest, result) = unpack(&[Entry::File("../evil", b"x")], 0);
+        assert!(result.is_err());
+        assert!(!escaped(&dest, "evil"));
+    }
+
+    #[test]
+    fn rejects_absolute_entry() {
+        let (_root, _, result) = unpack(&[Entry::File("/tmp/esp-helm-evil", b"x")], 0);
+        assert!(result.is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn rejects_symlink_chain() {
+        let entries = [
+            Entry::Symlink("a/l", ".."),
+            Entry::Symlink("a/l/m", ".."),
+            Entry::File("a/l/m/evil", b"x"),
+        ];
+        let (_root, dest, result) = unpack(&entries, 0);
+        assert!(result.is_err());
+        assert!(!escaped(&dest, "evil"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn rejects_write_through_outside_symlink() {
+        let entries = [
+            Entry::Symlink("a/up", "../../.."),
+            Entry::File("a/up/evil", b"x"),
+        ];
+        let (_root, _, result) = unpack(&entries, 0);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn hard_link_source_is_relative_to_archive_root() {
+        let entries = [
+            Entry::File("top/bin/tool", b"tool"),
+            Entry::HardLink("top/bin/alias", "top/bin/tool"),
+        ];
+        let (_root, dest, result) = unpack(&entries, 1);
+        result.unwrap();
+        assert_eq!(std::fs::read(dest.join("bin/alias")).unwrap(), b"tool");
+    }
+
+    #[test]
+    fn rejects_hard_link_outside() {
+        let (_root, dest, _) = unpack(&[], 0);
+        std::fs::write(dest.parent().unwrap().join("secret"), b"secret").unwrap();
+        let entries = [Entry::HardLink("alias", "../secret")];
+        let data = tar_archive(&entries);
+        let mut archive = tar::Archive::new(data.as_slice());
+        let mut entry = archive.entries().unwrap().next().unwrap().unwrap();
+        assert!(unpack_tar_entry(&dest, &mut entry, 0).is_err());
+        assert!(!dest.join("alias").exists());
+    }
+}
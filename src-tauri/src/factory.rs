@@ -0,0 +1,318 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use log::info;
+use sha2::{Digest, Sha256};
+use tauri::Window;
+
+use crate::error::{HelmError, HelmResult};
+use crate::esptool::venv_python;
+use crate::events::{emit_event, HelmEvent};
+use crate::external_command::run_external_command_output;
+use crate::firmware::hex;
+use crate::flasher::{connect, flash_device, FlashProgress};
+
+const MANIFEST_HEADER: &str = "timestamp,port,mac,device_id,firmware_sha256,nvs_offset";
+// Default partition table of espflash and ESP-IDF
+const DEFAULT_NVS_OFFSET: u32 = 0x9000;
+const DEFAULT_NVS_SIZE: u32 = 0x6000;
+
+#[derive(serde::Deserialize)]
+pub struct FactoryConfig {
+    // Application ELF or merged binary
+    firmware: String,
+    // CSV file to which one row per provisioned device is appended
+    manifest: String,
+    #[serde(default = "default_namespace")]
+    nvs_namespace: String,
+    nvs_offset: Option<u32>,
+    nvs_size: Option<u32>,
+    // Device ID is prefix followed by serial number or MAC when no serial is set
+    #[serde(default)]
+    id_prefix: String,
+    serial_start: Option<u64>,
+    // Names of random 256 bit keys stored as hex strings, e.g. ["api_key"]
+    #[serde(default)]
+    keys: Vec<String>,
+    // Values which are the same for every device
+    #[serde(default)]
+    values: BTreeMap<String, String>,
+}
+
+fn default_namespace() -> String {
+    "factory".into()
+}
+
+#[derive(serde::Serialize)]
+pub struct FactoryRecord {
+    port: String,
+    mac: String,
+    device_id: String,
+    // Generated keys are returned only here, they are not written to the manifest
+    keys: BTreeMap<String, String>,
+}
+
+#[derive(Clone, serde::Serialize, schemars::JsonSchema)]
+pub struct StagePayload {
+    port: String,
+    stage: &'static str,
+}
+
+impl HelmEvent for StagePayload {
+    const NAMES: &'static [&'static str] = &["factory-stage"];
+}
+
+// Serial numbers handed out to devices which are not in manifest yet, so ports provisioned
+// in parallel never get the same number
+static NEXT_SERIAL: Mutex<BTreeMap<PathBuf, u64>> = Mutex::new(BTreeMap::new());
+
+// Serial after the highest one in manifest, used to continue serial numbers between sessions.
+// Devices which failed leave gap instead of reusing their number.
+fn manifest_next_serial(content: &str, prefix: &str, start: u64) -> u64 {
+    content
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            line.split(',')
+                .nth(3)?
+                .strip_prefix(prefix)?
+                .parse::<u64>()
+                .ok()
+        })
+        .map(|serial| serial + 1)
+        .fold(start, u64::max)
+}
+
+fn allocate_serial(manifest: &Path, prefix: &str, start: u64) -> u64 {
+    let content = std::fs::read_to_string(manifest).unwrap_or_default();
+    let mut next = NEXT_SERIAL.lock().unwrap();
+    let next = next.entry(manifest.to_path_buf()).or_insert(start);
+    let serial = (*next).max(manifest_next_serial(&content, prefix, start));
+    *next = serial + 1;
+    serial
+}
+
+fn append_manifest(manifest: &Path, row: &[String]) -> HelmResult<()> {
+    let new_file = !manifest.exists();
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(manifest)?;
+    if new_file {
+        writeln!(file, "{}", MANIFEST_HEADER)?;
+    }
+    writeln!(file, "{}", row.join(","))?;
+    Ok(())
+}
+
+// NVS keys and namespaces are limited to 15 characters by ESP-IDF
+fn validate_name(kind: &str, name: &str) -> HelmResult<()> {
+    if name.is_empty() || name.len() > 15 || name.contains([',', '"', '\n', '\r']) {
+        return Err(HelmError::Validation(format!(
+            "Invalid NVS {} {}",
+            kind, name
+        )));
+    }
+    Ok(())
+}
+
+// Input of nvs_partition_gen: key,type,encoding,value
+fn nvs_csv(namespace: &str, entries: &BTreeMap<String, String>) -> String {
+    let mut csv = format!("key,type,encoding,value\n{},namespace,,\n", namespace);
+    for (key, value) in entries {
+        csv.push_str(&format!(
+            "{},data,string,\"{}\"\n",
+            key,
+            value.replace('"', "\"\"")
+        ));
+    }
+    csv
+}
+
+async fn random_key(python: &str) -> HelmResult<String> {
+    let output = run_external_command_output(
+        python,
+        &["-c", "import secrets; print(secrets.token_hex(32))"],
+    )
+    .await?;
+    Ok(output.trim().to_string())
+}
+
+async fn generate_nvs(
+    python: &str,
+    namespace: &str,
+    entries: &BTreeMap<String, String>,
+    size: u32,
+) -> HelmResult<Vec<u8>> {
+    // CSV contains generated keys, directory is readable only by the user and it is removed
+    // on drop also when generator fails
+    let dir = tempfile::Builder::new().prefix("esp-helm-nvs").tempdir()?;
+    let mut csv = tempfile::Builder::new()
+        .suffix(".csv")
+        .tempfile_in(dir.path())?;
+    csv.write_all(nvs_csv(namespace, entries).as_bytes())?;
+    csv.flush()?;
+    let csv_path = csv.path().to_path_buf();
+    let bin_path = dir.path().join("nvs.bin");
+    run_external_command_output(
+        python,
+        &[
+            "-m",
+            "esp_idf_nvs_partition_gen",
+            "generate",
+            &csv_path.to_string_lossy(),
+            &bin_path.to_string_lossy(),
+            &format!("0x{:x}", size),
+        ],
+    )
+    .await?;
+    Ok(std::fs::read(&bin_path)?)
+}
+
+// Command to provision one device: flash firmware, read MAC, flash unique NVS partition
+// with device ID and keys, and append the device to CSV manifest
+#[tauri::command]
+pub async fn provision_device(
+    window: Window,
+    port: String,
+    config: FactoryConfig,
+) -> HelmResult<FactoryRecord> {
+    let python = venv_python()?;
+    validate_name("namespace", &config.nvs_namespace)?;
+    for key in config.keys.iter().chain(config.values.keys()) {
+        validate_name("key", key)?;
+    }
+    let manifest = PathBuf::from(&config.manifest);
+    let firmware = std::fs::read(&config.firmware)?;
+    let firmware_hash = hex(&Sha256::digest(&firmware));
+    let emit_stage = |stage| {
+        let payload = StagePayload {
+            port: port.clone(),
+            stage,
+        };
+        emit_event(&window, &payload);
+    };
+
+    emit_stage("firmware");
+    let (firmware_window, firmware_port) = (window.clone(), port.clone());
+    tokio::task::spawn_blocking(move || {
+        flash_device(&firmware_window, &firmware_port, &firmware, 0)
+    })
+    .await
+    .map_err(|_| HelmError::Other("Flashing task panicked".into()))??;
+
+    emit_stage("mac");
+    let mac_port = port.clone();
+    let mac = tokio::task::spawn_blocking(move || {
+        connect(&mac_port, Some(1), Some(0))?
+            .device_info()
+            .map(|info| info.mac_address)
+            .map_err(|e| HelmError::Other(format!("Failed to read device info: {:?}", e)))
+    })
+    .await
+    .map_err(|_| HelmError::Other("Device info task panicked".into()))??;
+
+    let device_id = match config.serial_start {
+        Some(start) => format!(
+            "{}{:06}",
+            config.id_prefix,
+            allocate_serial(&manifest, &config.id_prefix, start)
+        ),
+        None => format!("{}{}", config.id_prefix, mac.replace(':', "")),
+    };
+    let mut keys = BTreeMap::new();
+    for name in &config.keys {
+        keys.insert(name.clone(), random_key(&python).await?);
+    }
+    let mut entries = config.values.clone();
+    entries.insert("device_id".into(), device_id.clone());
+    entries.extend(keys.clone());
+
+    emit_stage("nvs");
+    let nvs_offset = config.nvs_offset.unwrap_or(DEFAULT_NVS_OFFSET);
+    let nvs = generate_nvs(
+        &python,
+        &config.nvs_namespace,
+        &entries,
+        config.nvs_size.unwrap_or(DEFAULT_NVS_SIZE),
+    )
+    .await?;
+    let (nvs_window, nvs_port) = (window.clone(), port.clone());
+    tokio::task::spawn_blocking(move || {
+        let mut progress = FlashProgress::for_port(nvs_window, &nvs_port);
+        connect(&nvs_port, Some(1), Some(0))?
+            .write_bin_to_flash(nvs_offset, &nvs, Some(&mut progress))
+            .map_err(|e| HelmError::Other(format!("Flash error: {:?}", e)))
+    })
+    .await
+    .map_err(|_| HelmError::Other("Flashing task panicked".into()))??;
+
+    emit_stage("manifest");
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    append_manifest(
+        &manifest,
+        &[
+            timestamp.to_string(),
+            port.clone(),
+            mac.clone(),
+            device_id.clone(),
+            firmware_hash,
+            format!("0x{:x}", nvs_offset),
+        ],
+    )?;
+    info!("Provisioned {} as {}", mac, device_id);
+
+    Ok(FactoryRecord {
+        port,
+        mac,
+        device_id,
+        keys,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_quotes_are_escaped() {
+        let entries = BTreeMap::from([("name".to_string(), "say \"hi\", bye".to_string())]);
+        assert_eq!(
+            nvs_csv("factory", &entries),
+            "key,type,encoding,value\nfactory,namespace,,\nname,data,string,\"say \"\"hi\"\", bye\"\n"
+        );
+    }
+
+    #[test]
+    fn namespace_is_validated() {
+        assert!(validate_name("namespace", "factory").is_ok());
+        assert!(validate_name("namespace", "").is_err());
+        assert!(validate_name("namespace", "a,b").is_err());
+        assert!(validate_name("namespace", "namespace_too_long").is_err());
+    }
+
+    #[test]
+    fn serials_continue_after_highest_in_manifest() {
+        let manifest = format!(
+            "{}\n1,COM3,aa,dev000007,hash,0x9000\n2,COM4,bb,dev000004,hash,0x9000\n",
+            MANIFEST_HEADER
+        );
+        assert_eq!(manifest_next_serial(&manifest, "dev", 0), 8);
+        assert_eq!(manifest_next_serial(&manifest, "dev", 100), 100);
+        assert_eq!(manifest_next_serial(MANIFEST_HEADER, "dev", 5), 5);
+    }
+
+    #[test]
+    fn parallel_devices_get_distinct_serials() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = dir.path().join("manifest.csv");
+        let first = allocate_serial(&manifest, "dev", 1);
+        let second = allocate_serial(&manifest, "dev", 1);
+        assert_eq!((first, second), (1, 2));
+    }
+}
@@ -0,0 +1,300 @@
+use regex::bytes::Regex;
+use sha2::{Digest, Sha256};
+
+use crate::error::{HelmError, HelmResult};
+
+const IMAGE_MAGIC: u8 = 0xE9;
+const HEADER_SIZE: usize = 24;
+const SEGMENT_HEADER_SIZE: usize = 8;
+// esp_app_desc_t at the beginning of first segment of ESP-IDF applications
+const APP_DESC_MAGIC: u32 = 0xABCD_5432;
+
+#[derive(serde::Serialize)]
+pub struct FirmwareSegment {
+    load_address: u32,
+    size: u32,
+}
+
+#[derive(serde::Serialize)]
+pub struct AppDescription {
+    project_name: String,
+    version: String,
+    idf_version: String,
+    compile_time: String,
+    compile_date: String,
+    secure_version: u32,
+}
+
+#[derive(serde::Serialize)]
+pub struct FirmwareInfo {
+    chip: Option<String>,
+    entry_point: u32,
+    flash_mode: Option<String>,
+    flash_frequency: Option<String>,
+    flash_size: Option<String>,
+    min_chip_revision: String,
+    segments: Vec<FirmwareSegment>,
+    app_description: Option<AppDescription>,
+    // Crate versions found in paths compiled into the image, e.g. esp-hal 0.23.1
+    crates: Vec<(String, String)>,
+    // SHA256 stored in the image, when the image has one
+    hash: Option<String>,
+    hash_valid: Option<bool>,
+    // SHA256 of the whole file, to compare builds
+    file_hash: String,
+}
+
+fn chip_name(chip_id: u16) -> Option<&'static str> {
+    match chip_id {
+        0 => Some("esp32"),
+        2 => Some("esp32s2"),
+        5 => Some("esp32c3"),
+        9 => Some("esp32s3"),
+        12 => Some("esp32c2"),
+        13 => Some("esp32c6"),
+        16 => Some("esp32h2"),
+        18 => Some("esp32p4"),
+        _ => None,
+    }
+}
+
+fn flash_mode(mode: u8) -> Option<&'static str> {
+    match mode {
+        0 => Some("qio"),
+        1 => Some("qout"),
+        2 => Some("dio"),
+        3 => Some("dout"),
+        _ => None,
+    }
+}
+
+fn flash_frequency(frequency: u8) -> Option<&'static str> {
+    match frequency {
+        0x0 => Some("40m"),
+        0x1 => Some("26m"),
+        0x2 => Some("20m"),
+        0xF => Some("80m"),
+        _ => None,
+    }
+}
+
+fn flash_size(size: u8) -> Option<&'static str> {
+    match size {
+        0 => Some("1MB"),
+        1 => Some("2MB"),
+        2 => Some("4MB"),
+        3 => Some("8MB"),
+        4 => Some("16MB"),
+        5 => Some("32MB"),
+        6 => Some("64MB"),
+        7 => Some("128MB"),
+        _ => None,
+    }
+}
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([data[offset], data[offset + 1]])
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+fn c_string(data: &[u8]) -> String {
+    let end = data
+        .iter()
+        .position(|byte| *byte == 0)
+        .unwrap_or(data.len());
+    String::from_utf8_lossy(&data[..end]).to_string()
+}
+
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn parse_app_description(segment: &[u8]) -> Option<AppDescription> {
+    if segment.len() < 176 || read_u32(segment, 0) != APP_DESC_MAGIC {
+        return None;
+    }
+    Some(AppDescription {
+        secure_version: read_u32(segment, 4),
+        version: c_string(&segment[16..48]),
+        project_name: c_string(&segment[48..80]),
+        compile_time: c_string(&segment[80..96]),
+        compile_date: c_string(&segment[96..112]),
+        idf_version: c_string(&segment[112..144]),
+    })
+}
+
+// Panic locations keep cargo registry paths like ".../esp-hal-0.23.1/src/..."
+fn embedded_crates(data: &[u8]) -> Vec<(String, String)> {
+    let regex = Regex::new(
+        r"(esp-hal|esp-hal-embassy|esp-wifi|esp-idf-sys|esp-idf-hal|esp-idf-svc|embassy-executor)-(\d+\.\d+\.\d+)[/\\]",
+    )
+    .unwrap();
+    let mut crates: Vec<(String, String)> = regex
+        .captures_iter(data)
+        .map(|captures| {
+            (
+                String::from_utf8_lossy(&captures[1]).to_string(),
+                String::from_utf8_lossy(&captures[2]).to_string(),
+            )
+        })
+        .collect();
+    crates.sort();
+    crates.dedup();
+    crates
+}
+
+pub fn parse_image(data: &[u8]) -> HelmResult<FirmwareInfo> {
+    if data.starts_with(b"\x7FELF") {
+        return Err(HelmError::Validation(
+            "File is an ELF, convert it with espflash save-image first".into(),
+        ));
+    }
+    if data.len() < HEADER_SIZE || data[0] != IMAGE_MAGIC {
+        return Err(HelmError::Validation(
+            "File is not an ESP application image".into(),
+        ));
+    }
+    let truncated = || HelmError::Validation("Firmware image is truncated".into());
+
+    let segment_count = data[1] as usize;
+    let chip_id = read_u16(data, 12);
+    let hash_appended = data[23] == 1;
+
+    let mut offset = HEADER_SIZE;
+    let mut segments = vec![];
+    let mut app_description = None;
+    for index in 0..segment_count {
+        if offset + SEGMENT_HEADER_SIZE > data.len() {
+            return Err(truncated());
+        }
+        let load_address = read_u32(data, offset);
+        let size = read_u32(data, offset + 4);
+        offset += SEGMENT_HEADER_SIZE;
+        let end = offset + size as usize;
+        if end > data.len() {
+            return Err(truncated());
+        }
+        if index == 0 {
+            app_description = parse_app_description(&data[offset..end]);
+        }
+        segments.push(FirmwareSegment { load_address, size });
+        offset = end;
+    }
+
+    // Checksum byte is placed at the end of 16 byte aligned block
+    let image_end = (offset + 16) & !15;
+    let (hash, hash_valid) = if hash_appended && image_end + 32 <= data.len() {
+        let stored = &data[image_end..image_end + 32];
+        let computed = Sha256::digest(&data[..image_end]);
+        (Some(hex(stored)), Some(stored == computed.as_slice()))
+    } else {
+        (None, None)
+    };
+
+    Ok(FirmwareInfo {
+        chip: chip_name(chip_id).map(str::to_string),
+        entry_point: read_u32(data, 4),
+        flash_mode: flash_mode(data[2]).map(str::to_string),
+        flash_frequency: flash_frequency(data[3] & 0x0F).map(str::to_string),
+        flash_size: flash_size(data[3] >> 4).map(str::to_string),
+        min_chip_revision: format!("v{}.{}", read_u16(data, 15) / 100, read_u16(data, 15) % 100),
+        segments,
+        app_description,
+        crates: embedded_crates(data),
+        hash,
+        hash_valid,
+        file_hash: hex(&Sha256::digest(data)),
+    })
+}
+
+// Command to read metadata of application image before flashing it
+#[tauri::command]
+pub async fn inspect_firmware(path: String) -> HelmResult<FirmwareInfo> {
+    let data = tokio::fs::read(&path).await?;
+    parse_image(&data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Image with app description in first segment and appended SHA256
+    fn image(chip_id: u16) -> Vec<u8> {
+        let mut data = vec![IMAGE_MAGIC, 2, 2, 0x2F];
+        data.extend(0x4038_0080u32.to_le_bytes());
+        data.extend([0xEE, 0, 0, 0]);
+        data.extend(chip_id.to_le_bytes());
+        data.push(1);
+        data.extend(101u16.to_le_bytes());
+        data.extend([0xFF; 6]);
+        data.push(1);
+
+        let mut description = vec![0; 256];
+        description[..4].copy_from_slice(&APP_DESC_MAGIC.to_le_bytes());
+        description[4..8].copy_from_slice(&2u32.to_le_bytes());
+        description[16..21].copy_from_slice(b"1.0.0");
+        description[48..55].copy_from_slice(b"blinky\0");
+        description[112..118].copy_from_slice(b"v5.3.1");
+        data.extend(0x3C00_0020u32.to_le_bytes());
+        data.extend((description.len() as u32).to_le_bytes());
+        data.extend(description);
+
+        let code = b"/esp-hal-0.23.1/src/lib.rs esp-wifi-0.12.0/src esp-hal-0.23.1/";
+        data.extend(0x4037_C000u32.to_le_bytes());
+        data.extend((code.len() as u32).to_le_bytes());
+        data.extend(code);
+
+        data.resize((data.len() + 16) & !15, 0);
+        let hash = Sha256::digest(&data);
+        data.extend(hash);
+        data
+    }
+
+    #[test]
+    fn parses_image_header() {
+        let info = parse_image(&image(5)).unwrap();
+        assert_eq!(info.chip.as_deref(), Some("esp32c3"));
+        assert_eq!(info.entry_point, 0x4038_0080);
+        assert_eq!(info.flash_mode.as_deref(), Some("dio"));
+        assert_eq!(info.flash_frequency.as_deref(), Some("80m"));
+        assert_eq!(info.flash_size.as_deref(), Some("4MB"));
+        assert_eq!(info.min_chip_revision, "v1.1");
+        assert_eq!(info.segments.len(), 2);
+        assert_eq!(info.segments[0].load_address, 0x3C00_0020);
+        assert_eq!(info.segments[1].size, 62);
+        assert_eq!(
+            info.crates,
+            vec![
+                ("esp-hal".to_string(), "0.23.1".to_string()),
+                ("esp-wifi".to_string(), "0.12.0".to_string()),
+            ]
+        );
+        assert_eq!(info.hash_valid, Some(true));
+
+        let description = info.app_description.unwrap();
+        assert_eq!(description.project_name, "blinky");
+        assert_eq!(description.version, "1.0.0");
+        assert_eq!(description.idf_version, "v5.3.1");
+        assert_eq!(description.secure_version, 2);
+
+        assert!(parse_image(&image(42)).unwrap().chip.is_none());
+    }
+
+    #[test]
+    fn detects_modified_image() {
+        let mut data = image(0);
+        data[HEADER_SIZE + SEGMENT_HEADER_SIZE + 50] ^= 1;
+        assert_eq!(parse_image(&data).unwrap().hash_valid, Some(false));
+    }
+
+    #[test]
+    fn rejects_invalid_images() {
+        assert!(parse_image(b"\x7FELF\x01\x01").is_err());
+        assert!(parse_image(&[0; 64]).is_err());
+        let data = image(0);
+        assert!(parse_image(&data[..HEADER_SIZE + 100]).is_err());
+    }
+}
@@ -0,0 +1,190 @@
+use std::fs::File;
+use std::io::{Read, Write};
+use std::sync::Mutex;
+
+use espflash::flasher::ProgressCallbacks;
+use log::info;
+use tauri::{AppHandle, Manager, Window};
+use zip::write::FileOptions;
+
+use crate::app_state::AppState;
+use crate::error::{HelmError, HelmResult};
+use crate::flasher::{connect, emit_flash_message, write_data, FlashProgress};
+use crate::operations::{begin_operation, is_aborted, OperationKind};
+
+const IMAGE_ENTRY: &str = "flash.bin";
+const METADATA_ENTRY: &str = "backup.json";
+// Flash is read in chunks so progress can be reported and reading aborted
+const READ_CHUNK_SIZE: u32 = 256 * 1024;
+const PARTITION_TABLE_OFFSET: usize = 0x8000;
+const PARTITION_ENTRY_SIZE: usize = 32;
+const PARTITION_MAGIC: [u8; 2] = [0xAA, 0x50];
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct PartitionEntry {
+    label: String,
+    partition_type: u8,
+    subtype: u8,
+    offset: u32,
+    size: u32,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct BackupMetadata {
+    chip: String,
+    flash_size: u32,
+    mac_address: String,
+    created: u64,
+    partitions: Vec<PartitionEntry>,
+}
+
+fn parse_partition_table(flash: &[u8]) -> Vec<PartitionEntry> {
+    let Some(table) = flash.get(PARTITION_TABLE_OFFSET..PARTITION_TABLE_OFFSET + 0xC00) else {
+        return vec![];
+    };
+    table
+        .chunks_exact(PARTITION_ENTRY_SIZE)
+        .take_while(|entry| entry[..2] == PARTITION_MAGIC)
+        .map(|entry| {
+            let label_end = entry[12..28].iter().position(|b| *b == 0).unwrap_or(16);
+            PartitionEntry {
+                label: String::from_utf8_lossy(&entry[12..12 + label_end]).to_string(),
+                partition_type: entry[2],
+                subtype: entry[3],
+                offset: u32::from_le_bytes(entry[4..8].try_into().unwrap()),
+                size: u32::from_le_bytes(entry[8..12].try_into().unwrap()),
+            }
+        })
+        .collect()
+}
+
+fn read_device(window: &Window, port: &str) -> HelmResult<(Vec<u8>, BackupMetadata)> {
+    let mut flasher = connect(port, Some(1), Some(0))?;
+    let info = flasher
+        .device_info()
+        .map_err(|e| HelmError::Other(format!("Failed to read device info: {:?}", e)))?;
+    let flash_size = info.flash_size.size();
+    info!("Reading {} bytes of flash from {}", flash_size, port);
+
+    // espflash writes every chunk to file, path is unique and file is removed on drop also
+    // when reading fails or is aborted
+    let chunk_file = tempfile::Builder::new()
+        .prefix("esp-helm-flash-chunk")
+        .suffix(".bin")
+        .tempfile()?
+        .into_temp_path();
+    let mut progress = FlashProgress::new(window.clone());
+    progress.init(0, flash_size as usize);
+    let mut flash = Vec::with_capacity(flash_size as usize);
+    let mut offset = 0;
+    while offset < flash_size {
+        if is_aborted() {
+            return Err(HelmError::Cancelled);
+        }
+        let size = READ_CHUNK_SIZE.min(flash_size - offset);
+        flasher
+            .read_flash(offset, size, 0x1000, 64, chunk_file.to_path_buf())
+            .map_err(|e| HelmError::Other(format!("Flash read error: {:?}", e)))?;
+        flash.extend(std::fs::read(&chunk_file)?);
+        offset += size;
+        progress.update(offset as usize);
+    }
+    progress.finish();
+
+    let metadata = BackupMetadata {
+        chip: info.chip.to_string(),
+        flash_size,
+        mac_address: info.mac_address,
+        created: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+        partitions: parse_partition_table(&flash),
+    };
+    Ok((flash, metadata))
+}
+
+// Command to read whole flash to zip archive with flash image and partition metadata
+#[tauri::command]
+pub async fn backup_device(
+    window: Window,
+    app: AppHandle,
+    port: String,
+    out_path: String,
+) -> HelmResult<BackupMetadata> {
+    let (read_window, read_port) = (window.clone(), port.clone());
+    let (flash, metadata) =
+        begin_operation(&app, OperationKind::Flash, &format!("Backup {}", port))
+            .blocking(move || read_device(&read_window, &read_port))
+            .await??;
+
+    let mut zip = zip::ZipWriter::new(File::create(&out_path)?);
+    let options = FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    zip.start_file(METADATA_ENTRY, options)?;
+    zip.write_all(&serde_json::to_vec_pretty(&metadata).unwrap())?;
+    zip.start_file(IMAGE_ENTRY, options)?;
+    zip.write_all(&flash)?;
+    zip.finish()?;
+
+    info!("Backup of {} stored in {}", port, out_path);
+    Ok(metadata)
+}
+
+fn read_backup(image: &str) -> HelmResult<(Vec<u8>, BackupMetadata)> {
+    let mut archive = zip::ZipArchive::new(File::open(image)?)?;
+    let metadata: BackupMetadata = serde_json::from_reader(archive.by_name(METADATA_ENTRY)?)
+        .map_err(|e| HelmError::Validation(format!("Invalid backup metadata: {}", e)))?;
+    let mut flash = vec![];
+    archive.by_name(IMAGE_ENTRY)?.read_to_end(&mut flash)?;
+    Ok((flash, metadata))
+}
+
+fn write_device(
+    window: &Window,
+    port: &str,
+    flash: Vec<u8>,
+    metadata: &BackupMetadata,
+) -> HelmResult<()> {
+    let mut flasher = connect(port, Some(1), Some(0))?;
+    let info = flasher
+        .device_info()
+        .map_err(|e| HelmError::Other(format!("Failed to read device info: {:?}", e)))?;
+    if info.chip.to_string() != metadata.chip {
+        return Err(HelmError::Validation(format!(
+            "Backup was made from {}, connected chip is {}",
+            metadata.chip, info.chip
+        )));
+    }
+    if flash.len() as u32 > info.flash_size.size() {
+        return Err(HelmError::Validation(format!(
+            "Backup has {} bytes, device flash has only {}",
+            flash.len(),
+            info.flash_size.size()
+        )));
+    }
+    write_data(&mut flasher, window, 0, flash)
+}
+
+// Command to write backup created by backup_device back to device
+#[tauri::command]
+pub async fn restore_device(
+    window: Window,
+    app: AppHandle,
+    port: String,
+    image: String,
+) -> HelmResult<String> {
+    let (flash, metadata) = read_backup(&image)?;
+    let (write_window, write_port) = (window.clone(), port.clone());
+    begin_operation(&app, OperationKind::Flash, &format!("Restore {}", port))
+        .blocking(move || write_device(&write_window, &write_port, flash, &metadata))
+        .await??;
+
+    // Application in flash is no longer the one built by esp-helm
+    {
+        let state_mutex = app.state::<Mutex<AppState>>();
+        let mut state = state_mutex.lock().unwrap();
+        state.flashed_elfs.remove(&port);
+    }
+    emit_flash_message(&window, "Flash Done");
+    Ok(format!("Restored {} to {}", image, port))
+}
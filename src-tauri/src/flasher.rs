@@ -1,29 +1,96 @@
 use espflash::flasher::Flasher;
 use espflash::flasher::ProgressCallbacks;
 use espflash::interface::Interface;
+use log::info;
 use serialport::available_ports;
 use serialport::SerialPortInfo;
 use std::fs::read;
 use std::io;
 use std::path::PathBuf;
-use tauri::AppHandle;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager, State};
+
+use crate::app_state::AppState;
+use crate::baud::{board_key, flash_baud};
+use crate::error::{HelmError, HelmResult};
+use crate::events::{emit_event, HelmEvent};
+use crate::external_command::ask_question;
+use crate::jobs::{spawn_job, wait_job};
+use crate::operations::{is_aborted, OperationKind};
+use crate::settings::save_settings;
 use tauri::Window;
 
-#[derive(Clone, serde::Serialize)]
-struct Payload {
+// Text status of flashing, "pct" is kept for the frontend
+#[derive(Clone, serde::Serialize, schemars::JsonSchema)]
+pub struct FlashMessage {
     pct: String,
 }
 
-#[derive(Clone, serde::Serialize)]
-struct FlashProgressEvent {
+impl HelmEvent for FlashMessage {
+    const NAMES: &'static [&'static str] = &["flash-event"];
+}
+
+#[derive(Clone, serde::Serialize, schemars::JsonSchema)]
+pub struct FlashError {
+    pct: String,
+}
+
+impl HelmEvent for FlashError {
+    const NAMES: &'static [&'static str] = &["error"];
+}
+
+#[derive(Clone, serde::Serialize, schemars::JsonSchema)]
+pub struct FlashProgressEvent {
     count: usize,
     total: usize,
+    // Set when several devices are flashed at once
+    port: Option<String>,
+    finished: bool,
+}
+
+impl HelmEvent for FlashProgressEvent {
+    const NAMES: &'static [&'static str] = &["flash-update", "flash-finish"];
+
+    fn name(&self) -> &'static str {
+        if self.finished {
+            Self::NAMES[1]
+        } else {
+            Self::NAMES[0]
+        }
+    }
+}
+
+pub fn emit_flash_message(window: &Window, message: &str) {
+    let payload = FlashMessage {
+        pct: message.to_string(),
+    };
+    emit_event(window, &payload);
 }
 
-struct FlashProgress {
+pub struct FlashProgress {
     window: Window,
     current: usize,
     total: usize,
+    port: Option<String>,
+}
+
+impl FlashProgress {
+    pub fn new(window: Window) -> Self {
+        FlashProgress {
+            window,
+            current: 0,
+            total: 0,
+            port: None,
+        }
+    }
+
+    pub fn for_port(window: Window, port: &str) -> Self {
+        FlashProgress {
+            port: Some(port.to_string()),
+            ..FlashProgress::new(window)
+        }
+    }
 }
 
 impl ProgressCallbacks for FlashProgress {
@@ -34,8 +101,10 @@ impl ProgressCallbacks for FlashProgress {
         let flash_payload = FlashProgressEvent {
             count: self.current,
             total,
+            port: self.port.clone(),
+            finished: false,
         };
-        self.window.emit("flash-update", flash_payload).unwrap();
+        emit_event(&self.window, &flash_payload);
     }
 
     fn update(&mut self, current: usize) {
@@ -44,8 +113,10 @@ impl ProgressCallbacks for FlashProgress {
         let flash_payload = FlashProgressEvent {
             count: current,
             total: self.total,
+            port: self.port.clone(),
+            finished: false,
         };
-        self.window.emit("flash-update", flash_payload).unwrap();
+        emit_event(&self.window, &flash_payload);
     }
 
     fn finish(&mut self) {
@@ -53,11 +124,120 @@ impl ProgressCallbacks for FlashProgress {
         let flash_payload = FlashProgressEvent {
             count: self.total,
             total: self.total,
+            port: self.port.clone(),
+            finished: true,
         };
-        self.window.emit("flash-finish", flash_payload).unwrap();
+        emit_event(&self.window, &flash_payload);
     }
 }
 
+// How chip is put to download mode before flashing
+#[derive(Clone, Copy, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ResetStrategy {
+    // Detected from USB IDs, manual flow is offered when automatic reset fails
+    #[default]
+    Auto,
+    // DTR/RTS connected to EN/IO0 through auto-reset circuit of USB-UART bridge
+    Classic,
+    // Built-in USB-Serial-JTAG of ESP32-C3/S3/C6/H2
+    UsbJtagSerial,
+    // User holds BOOT and presses RESET, for boards without auto-reset circuit
+    Manual,
+}
+
+// Espressif VID and PID of USB-Serial-JTAG peripheral
+const USB_JTAG_SERIAL_ID: (u16, u16) = (0x303a, 0x1001);
+const MANUAL_BOOT_PROMPT: &str =
+    "Hold BOOT, press and release RESET, then release BOOT. Is the board in download mode?";
+
+fn detect_reset_strategy(port: &str) -> HelmResult<ResetStrategy> {
+    let info = get_serial_port_info(port)?;
+    Ok(match info.port_type {
+        serialport::SerialPortType::UsbPort(usb) if (usb.vid, usb.pid) == USB_JTAG_SERIAL_ID => {
+            ResetStrategy::UsbJtagSerial
+        }
+        _ => ResetStrategy::Classic,
+    })
+}
+
+fn configured_reset_strategy(app: &AppHandle, port: &str) -> ResetStrategy {
+    let Ok(board) = board_key(port) else {
+        return ResetStrategy::Auto;
+    };
+    let state_mutex = app.state::<Mutex<AppState>>();
+    let state = state_mutex.lock().unwrap();
+    state
+        .settings
+        .reset_strategy
+        .get(&board)
+        .copied()
+        .unwrap_or_default()
+}
+
+// espflash selects DTR/RTS or USB-Serial-JTAG sequence by USB PID. When it fails, user is
+// asked to enter download mode by hand instead of failing with connection timeout.
+pub async fn connect_with_reset(
+    window: &Window,
+    app: &AppHandle,
+    port: &str,
+    dtr: Option<u8>,
+    rts: Option<u8>,
+) -> HelmResult<Flasher> {
+    let strategy = configured_reset_strategy(app, port);
+    if strategy != ResetStrategy::Manual {
+        match connect(port, dtr, rts) {
+            Ok(flasher) => return Ok(flasher),
+            Err(e) if strategy == ResetStrategy::Auto => {
+                info!("Automatic reset of {} failed: {}", port, e);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    let answer = ask_question(window, app.clone(), "flasher", MANUAL_BOOT_PROMPT).await?;
+    if answer.trim().to_lowercase().starts_with('n') {
+        return Err(HelmError::Cancelled);
+    }
+    connect(port, dtr, rts)
+        .map_err(|e| HelmError::Other(format!("Board on {} is not in download mode: {}", port, e)))
+}
+
+#[derive(serde::Serialize)]
+pub struct ResetStrategyInfo {
+    configured: ResetStrategy,
+    detected: ResetStrategy,
+}
+
+// Command to get reset strategy of the board and the one detected from its USB IDs
+#[tauri::command]
+pub async fn get_reset_strategy(app: AppHandle, port: String) -> HelmResult<ResetStrategyInfo> {
+    Ok(ResetStrategyInfo {
+        configured: configured_reset_strategy(&app, &port),
+        detected: detect_reset_strategy(&port)?,
+    })
+}
+
+// Command to remember reset strategy of the board connected to port
+#[tauri::command]
+pub async fn set_reset_strategy(
+    state_mutex: State<'_, Mutex<AppState>>,
+    port: String,
+    strategy: ResetStrategy,
+) -> HelmResult<String> {
+    let board = board_key(&port)?;
+    let mut state = state_mutex.lock().unwrap();
+    let mut settings = state.settings.clone();
+    if strategy == ResetStrategy::Auto {
+        settings.reset_strategy.remove(&board);
+    } else {
+        settings.reset_strategy.insert(board.clone(), strategy);
+    }
+    save_settings(&settings)?;
+    state.settings = settings;
+    Ok(format!("Reset strategy of {} updated", board))
+}
+
 pub fn get_serial_port_info(port_name: &str) -> io::Result<SerialPortInfo> {
     let ports = available_ports()?;
     for p in ports {
@@ -69,19 +249,58 @@ pub fn get_serial_port_info(port_name: &str) -> io::Result<SerialPortInfo> {
 }
 
 pub fn emit_error(window: &Window, error: &str) {
-    let error_payload = Payload {
+    let error_payload = FlashError {
         pct: format!("Error: {}", error),
     };
-    window.emit("error", error_payload).unwrap();
+    emit_event(window, &error_payload);
+}
+
+// Connect at baud detected or set for the board, 115200 when there is none
+pub fn connect(port: &str, dtr: Option<u8>, rts: Option<u8>) -> HelmResult<Flasher> {
+    connect_at(port, dtr, rts, flash_baud(port))
+}
+
+pub fn connect_at(
+    port: &str,
+    dtr: Option<u8>,
+    rts: Option<u8>,
+    baud: Option<u32>,
+) -> HelmResult<Flasher> {
+    open_flasher(port, dtr, rts, baud, false)
+}
+
+// Erase commands are implemented only by flasher stub, ROM loader does not know them
+pub fn connect_stub(port: &str) -> HelmResult<Flasher> {
+    open_flasher(port, Some(1), Some(0), flash_baud(port), true)
+}
+
+fn open_flasher(
+    port: &str,
+    dtr: Option<u8>,
+    rts: Option<u8>,
+    baud: Option<u32>,
+    use_stub: bool,
+) -> HelmResult<Flasher> {
+    let serial_port_info = get_serial_port_info(port)?;
+    let port_info = match &serial_port_info.port_type {
+        serialport::SerialPortType::UsbPort(info) => info.clone(),
+        _ => return Err(HelmError::Validation("Port is not a USB port".to_string())),
+    };
+    let serial = Interface::new(&serial_port_info, dtr, rts)
+        .map_err(|e| HelmError::Io(format!("Failed to open {}: {}", port, e)))?;
+
+    println!("Connecting to port...");
+    Flasher::connect(serial, port_info, baud, use_stub)
+        .map_err(|e| HelmError::Other(format!("Failed to connect: {:?}", e)))
 }
 
 pub async fn flash_file(
     window: Window,
-    _: AppHandle,
+    app: AppHandle,
     port: String,
     file_path: String,
     flash_offset: u32,
-) -> Result<(), String> {
+) -> HelmResult<()> {
     // let file_metadata = std::fs::metadata(&file_path);
     // match file_metadata {
     //     Ok(metadata) => {
@@ -98,7 +317,7 @@ pub async fn flash_file(
 
     let binary_file = PathBuf::from(file_path);
 
-    let mut data = read(&binary_file).unwrap();
+    let data = read(&binary_file)?;
 
     let dtr = Some(1);
     let rts = Some(0);
@@ -106,27 +325,26 @@ pub async fn flash_file(
     // let port_info = get_serial_port_info(port.as_str()).unwrap();
 
     println!("port: {}", port);
-    let serial_port_info = get_serial_port_info(port.as_str()).unwrap();
-    let port_info = match &serial_port_info.port_type {
-        serialport::SerialPortType::UsbPort(info) => Some(info.clone()),
-        _ => return Err("Port is not a USB port".to_string()),
-    };
-    let serial = Interface::new(&serial_port_info, dtr, rts).unwrap();
-
-    println!("Connecting to port...");
-    let mut flasher = Flasher::connect(serial, port_info.unwrap(), None, false).unwrap();
+    let mut flasher = connect_with_reset(&window, &app, &port, dtr, rts).await?;
 
     // Emit the line to the frontend
-    let payload = Payload {
-        pct: "Start flashing...".to_string(),
-    };
-    window.emit("flash-event", payload).unwrap();
+    emit_flash_message(&window, "Start flashing...");
 
-    let mut progress = FlashProgress {
-        window: window.clone(),
-        total: 0,
-        current: 0,
-    };
+    write_data(&mut flasher, &window, flash_offset, data)?;
+
+    emit_flash_message(&window, "Flash Done");
+
+    Ok(())
+}
+
+// Write data in 1MB chunks, progress is reported per chunk
+pub fn write_data(
+    flasher: &mut Flasher,
+    window: &Window,
+    flash_offset: u32,
+    mut data: Vec<u8>,
+) -> HelmResult<()> {
+    let mut progress = FlashProgress::new(window.clone());
 
     let chunk_size = 1024 * 1024; // 1MB chunk size
                                   // let total_size = data.len();
@@ -144,15 +362,219 @@ pub async fn flash_file(
             .write_bin_to_flash(offset, chunk, Some(&mut progress))
             .map_err(|e| {
                 let error = format!("Flash error: {:?}", e);
-                emit_error(&window, &error);
-                error
+                emit_error(window, &error);
+                HelmError::Other(error)
             })?;
 
         offset += chunk.len() as u32;
         data = rest.to_vec();
     }
 
-    window.emit("flash-event", Some("Flash Done")).unwrap();
+    Ok(())
+}
+
+// Flash application ELF together with default bootloader and partition table
+pub async fn flash_elf(
+    window: Window,
+    app: AppHandle,
+    port: String,
+    elf_path: String,
+) -> HelmResult<()> {
+    let elf_data = read(&elf_path)?;
+    let mut flasher = connect_with_reset(&window, &app, &port, Some(1), Some(0)).await?;
+
+    emit_flash_message(&window, "Start flashing...");
+
+    let mut progress = FlashProgress::new(window.clone());
+    flasher
+        .load_elf_to_flash(
+            &elf_data,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            Some(&mut progress),
+        )
+        .map_err(|e| {
+            let error = format!("Flash error: {:?}", e);
+            emit_error(&window, &error);
+            HelmError::Other(error)
+        })?;
+
+    emit_flash_message(&window, "Flash Done");
 
     Ok(())
 }
+
+// Boards in download mode sometimes fail to sync on first attempt
+const DEFAULT_RETRIES: u32 = 2;
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+#[derive(Clone, serde::Serialize, schemars::JsonSchema)]
+pub struct DeviceStatus {
+    port: String,
+    // "flashing", "retrying", "done" or "failed"
+    state: &'static str,
+    attempt: u32,
+    error: Option<String>,
+}
+
+impl HelmEvent for DeviceStatus {
+    const NAMES: &'static [&'static str] = &["flash-device-status"];
+}
+
+#[derive(Clone, serde::Serialize)]
+pub struct DeviceFlashResult {
+    port: String,
+    success: bool,
+    attempts: u32,
+    error: Option<String>,
+    duration_ms: u64,
+}
+
+#[derive(serde::Serialize)]
+pub struct MultiFlashReport {
+    succeeded: usize,
+    failed: usize,
+    devices: Vec<DeviceFlashResult>,
+}
+
+// ELF is flashed with bootloader and partition table, other files are written at offset
+pub fn flash_device(window: &Window, port: &str, data: &[u8], flash_offset: u32) -> HelmResult<()> {
+    let mut flasher = connect(port, Some(1), Some(0))?;
+    let mut progress = FlashProgress::for_port(window.clone(), port);
+    let result = if data.starts_with(b"\x7fELF") {
+        flasher.load_elf_to_flash(
+            data,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            Some(&mut progress),
+        )
+    } else {
+        flasher.write_bin_to_flash(flash_offset, data, Some(&mut progress))
+    };
+    result.map_err(|e| HelmError::Other(format!("Flash error: {:?}", e)))
+}
+
+async fn flash_device_with_retries(
+    window: Window,
+    port: String,
+    data: Arc<Vec<u8>>,
+    flash_offset: u32,
+    retries: u32,
+) -> DeviceFlashResult {
+    let started = Instant::now();
+    let emit_status = |state, attempt, error: Option<String>| {
+        let status = DeviceStatus {
+            port: port.clone(),
+            state,
+            attempt,
+            error,
+        };
+        emit_event(&window, &status);
+    };
+
+    let mut attempt = 0;
+    let error = loop {
+        attempt += 1;
+        emit_status("flashing", attempt, None);
+        // espflash is blocking, each device gets own thread
+        let (device_window, device_port, device_data) =
+            (window.clone(), port.clone(), data.clone());
+        let result = tokio::task::spawn_blocking(move || {
+            flash_device(&device_window, &device_port, &device_data, flash_offset)
+        })
+        .await
+        .unwrap_or_else(|_| Err(HelmError::Other("Flashing task panicked".into())));
+
+        match result {
+            Ok(()) => break None,
+            Err(e) if attempt <= retries && !is_aborted() => {
+                emit_status("retrying", attempt, Some(e.to_string()));
+                tokio::time::sleep(RETRY_DELAY).await;
+            }
+            Err(e) => break Some(e.to_string()),
+        }
+    };
+
+    emit_status(
+        if error.is_none() { "done" } else { "failed" },
+        attempt,
+        error.clone(),
+    );
+    DeviceFlashResult {
+        port,
+        success: error.is_none(),
+        attempts: attempt,
+        error,
+        duration_ms: started.elapsed().as_millis() as u64,
+    }
+}
+
+// Command to flash the same file to several boards at once, one job per port.
+// Failure of one board does not stop the others, all results are in the report.
+#[tauri::command]
+pub async fn flash_devices(
+    window: Window,
+    app: AppHandle,
+    ports: Vec<String>,
+    file_path: String,
+    flash_offset: Option<u32>,
+    retries: Option<u32>,
+) -> HelmResult<MultiFlashReport> {
+    if ports.is_empty() {
+        return Err(HelmError::Validation("No port selected".into()));
+    }
+    let data = Arc::new(read(&file_path)?);
+    let results: Arc<Mutex<Vec<DeviceFlashResult>>> = Arc::new(Mutex::new(vec![]));
+
+    let jobs: Vec<_> = ports
+        .iter()
+        .map(|port| {
+            let device = flash_device_with_retries(
+                window.clone(),
+                port.clone(),
+                data.clone(),
+                flash_offset.unwrap_or(0),
+                retries.unwrap_or(DEFAULT_RETRIES),
+            );
+            let results = results.clone();
+            spawn_job(
+                &app,
+                OperationKind::Flash,
+                &format!("Flash {}", port),
+                vec![],
+                async move {
+                    let result = device.await;
+                    let outcome = match &result.error {
+                        None => Ok(format!("{} flashed", result.port)),
+                        Some(error) => Err(HelmError::Other(error.clone())),
+                    };
+                    results.lock().unwrap().push(result);
+                    outcome
+                },
+            )
+        })
+        .collect();
+    for job in jobs {
+        // Per device errors are part of the report
+        if let Err(HelmError::Cancelled) = wait_job(&app, job).await {
+            return Err(HelmError::Cancelled);
+        }
+    }
+
+    let mut devices = results.lock().unwrap().clone();
+    devices.sort_by(|a, b| a.port.cmp(&b.port));
+    let succeeded = devices.iter().filter(|device| device.success).count();
+    Ok(MultiFlashReport {
+        succeeded,
+        failed: devices.len() - succeeded,
+        devices,
+    })
+}
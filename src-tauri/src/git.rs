@@ -0,0 +1,569 @@
+use std::path::PathBuf;
+use std::process::Command;
+
+use tauri::{AppHandle, Window};
+
+use log::info;
+
+use crate::audit::{audit, AuditAction};
+use crate::error::{HelmError, HelmResult};
+use crate::events::{emit_event, HelmEvent};
+use crate::external_command::run_external_command_with_progress;
+use crate::package_manager::{find_in_path, install_packages};
+
+#[cfg(windows)]
+use crate::arch::hardware_arch;
+#[cfg(windows)]
+use crate::download::download_file;
+#[cfg(windows)]
+use crate::firmware::hex;
+#[cfg(windows)]
+use crate::github::github_api;
+#[cfg(windows)]
+use crate::package_manager::PackageManager;
+#[cfg(windows)]
+use crate::release_metadata::RELEASES_TTL;
+#[cfg(any(windows, test))]
+use serde_json::Value;
+#[cfg(windows)]
+use sha2::{Digest, Sha256};
+#[cfg(windows)]
+use std::os::windows::process::CommandExt;
+#[cfg(windows)]
+const CREATE_NO_WINDOW: u32 = 0x08000000; // Windows specific constant to hide console window
+#[cfg(windows)]
+const GIT_RELEASE_PATH: &str = "repos/git-for-windows/git/releases/latest";
+#[cfg(windows)]
+const GIT_INSTALLER_ARGS: [&str; 5] = [
+    "/VERYSILENT",
+    "/NORESTART",
+    "/NOCANCEL",
+    "/SP-",
+    "/SUPPRESSMSGBOXES",
+];
+
+// Git for Windows installed by winget or its installer, PATH of running process is not
+// refreshed until restart
+#[cfg(windows)]
+fn known_git_locations() -> Vec<PathBuf> {
+    let program_files = ["ProgramFiles", "ProgramW6432", "ProgramFiles(x86)"]
+        .iter()
+        .filter_map(|var| std::env::var_os(var))
+        .map(PathBuf::from);
+    let user = std::env::var_os("LOCALAPPDATA").map(|dir| PathBuf::from(dir).join("Programs"));
+    program_files
+        .chain(user)
+        .map(|dir| dir.join("Git").join("cmd").join("git.exe"))
+        .collect()
+}
+
+#[cfg(not(windows))]
+fn known_git_locations() -> Vec<PathBuf> {
+    vec![]
+}
+
+// Git from PATH, or from its install directory right after installation
+pub fn git_program() -> String {
+    find_in_path("git")
+        .or_else(|| {
+            known_git_locations()
+                .into_iter()
+                .find(|path| path.is_file())
+        })
+        .map(|path| path.to_string_lossy().to_string())
+        .unwrap_or_else(|| "git".into())
+}
+
+// Get version of installed Git, output has form: "git version 2.41.0"
+pub fn get_git_version() -> Option<String> {
+    let mut cmd = Command::new(git_program());
+    cmd.arg("--version");
+
+    #[cfg(windows)]
+    cmd.creation_flags(CREATE_NO_WINDOW);
+
+    let output = cmd.output().ok()?;
+
+    if !output.status.success() {
+        info!("command failed: {:?}", output);
+        return None;
+    }
+
+    parse_git_version(&String::from_utf8_lossy(&output.stdout))
+}
+
+// "git version 2.41.0.windows.1" gives "2.41.0.windows.1"
+pub fn parse_git_version(stdout: &str) -> Option<String> {
+    let line = stdout.lines().next()?;
+    if !line.starts_with("git version") {
+        return None;
+    }
+
+    line.split_whitespace().nth(2).map(|s| s.to_string())
+}
+
+#[derive(serde::Serialize)]
+pub struct GitSupportResponse {
+    git: Option<String>,
+}
+
+#[tauri::command]
+pub fn check_git_support() -> HelmResult<GitSupportResponse> {
+    let git_version = get_git_version();
+    info!("git: {:?}", git_version);
+    Ok(GitSupportResponse { git: git_version })
+}
+
+// Command to install Git using package manager available for the platform
+#[tauri::command]
+pub async fn install_git(window: Window, app: AppHandle) -> HelmResult<String> {
+    if let Some(version) = get_git_version() {
+        info!("Git {} already installed", version);
+        configure_git(None)?;
+        return Ok("Git already installed".into());
+    }
+
+    info!("Installing Git...");
+    #[cfg(windows)]
+    if PackageManager::detect().is_none() {
+        install_git_for_windows(window, app).await?;
+    } else {
+        install_packages(window, app, &["git".to_string()]).await?;
+    }
+    #[cfg(not(windows))]
+    install_packages(window, app, &["git".to_string()]).await?;
+
+    configure_git(None)?;
+
+    info!("Git installed successfully!");
+    Ok("Git installed successfully!".into())
+}
+
+// Installer of Git for Windows for the architecture, e.g. "Git-2.47.1-arm64.exe"
+#[cfg(any(windows, test))]
+fn installer_suffix(arch: &str) -> Option<&'static str> {
+    match arch {
+        "x86_64" => Some("-64-bit.exe"),
+        "aarch64" => Some("-arm64.exe"),
+        _ => None,
+    }
+}
+
+#[cfg(any(windows, test))]
+#[derive(Debug, PartialEq)]
+struct InstallerAsset {
+    name: String,
+    url: String,
+    sha256: String,
+}
+
+// Installer from release of GitHub API. SHA-256 is the digest GitHub computed for the asset,
+// releases published before digests existed list it in the table of release notes.
+#[cfg(any(windows, test))]
+fn installer_asset(release: &Value, suffix: &str) -> Option<InstallerAsset> {
+    let field = |value: &Value, key: &str| value.get(key)?.as_str().map(str::to_string);
+    let asset = release.get("assets")?.as_array()?.iter().find(|asset| {
+        field(asset, "name").is_some_and(|name| name.starts_with("Git-") && name.ends_with(suffix))
+    })?;
+    let name = field(asset, "name")?;
+    let sha256 = field(asset, "digest")
+        .and_then(|digest| Some(digest.strip_prefix("sha256:")?.to_string()))
+        .or_else(|| notes_sha256(&field(release, "body")?, &name))?;
+    Some(InstallerAsset {
+        url: field(asset, "browser_download_url")?,
+        sha256: sha256.to_lowercase(),
+        name,
+    })
+}
+
+// Row of release notes table: "Git-2.47.1-64-bit.exe | 0229d2d8..."
+#[cfg(any(windows, test))]
+fn notes_sha256(notes: &str, name: &str) -> Option<String> {
+    notes
+        .lines()
+        .find(|line| line.contains(name))?
+        .split(|c: char| !c.is_ascii_hexdigit())
+        .find(|word| word.len() == 64)
+        .map(str::to_string)
+}
+
+// Windows without winget and Chocolatey, e.g. LTSC editions
+#[cfg(windows)]
+async fn install_git_for_windows(window: Window, app: AppHandle) -> HelmResult<()> {
+    let arch = hardware_arch();
+    let suffix = installer_suffix(&arch).ok_or(HelmError::Validation(format!(
+        "Git for Windows has no installer for {}",
+        arch
+    )))?;
+    let release = github_api(&app, GIT_RELEASE_PATH, RELEASES_TTL).await?;
+    let asset = installer_asset(&release.body, suffix).ok_or(HelmError::NotFound(format!(
+        "Git for Windows installer for {}",
+        arch
+    )))?;
+
+    info!("Downloading {}...", asset.name);
+    let file_path = std::env::temp_dir().join(&asset.name);
+    download_file(window.clone(), app.clone(), &asset.url, &file_path).await?;
+    let sha256 = hex(&Sha256::digest(std::fs::read(&file_path)?));
+    if sha256 != asset.sha256 {
+        let _ = std::fs::remove_file(&file_path);
+        return Err(HelmError::Validation(format!(
+            "SHA-256 of {} is {}, expected {}",
+            asset.name, sha256, asset.sha256
+        )));
+    }
+
+    let result = run_external_command_with_progress(
+        window,
+        app,
+        &file_path.to_string_lossy(),
+        &GIT_INSTALLER_ARGS,
+        "Installing Git for Windows...",
+    )
+    .await;
+    let _ = std::fs::remove_file(&file_path);
+    result.map(|_| ())
+}
+
+// Apply Git settings required for cloning ESP-IDF.
+// Windows needs core.longpaths because of deep submodule paths.
+// Directories created by elevated installers must be marked as safe.directory.
+pub fn configure_git(safe_directory: Option<&str>) -> HelmResult<()> {
+    #[cfg(windows)]
+    git_config(&["config", "--global", "core.longpaths", "true"])?;
+
+    if let Some(directory) = safe_directory {
+        git_config(&["config", "--global", "--add", "safe.directory", directory])?;
+    }
+
+    Ok(())
+}
+
+// Changes of global configuration affect every repository of the user, so they are audited
+fn git_config(args: &[&str]) -> HelmResult<()> {
+    info!("git {}", args.join(" "));
+    let mut cmd = Command::new(git_program());
+    cmd.args(args);
+
+    #[cfg(windows)]
+    cmd.creation_flags(CREATE_NO_WINDOW);
+
+    let output = cmd.output()?;
+    if !output.status.success() {
+        info!("git failed: {}", String::from_utf8_lossy(&output.stderr));
+        return Err(HelmError::child_process_failed(
+            output.status.code(),
+            &String::from_utf8_lossy(&output.stderr),
+        ));
+    }
+    if args.contains(&"--global") {
+        audit(
+            AuditAction::ModifyEnvironment,
+            "Git global config",
+            Some(args.join(" ")),
+        );
+    }
+    Ok(())
+}
+
+// Command to configure Git for ESP-IDF directory
+#[tauri::command]
+pub async fn configure_git_for_path(path: String) -> HelmResult<String> {
+    configure_git(Some(&path))?;
+    Ok("Git configured".into())
+}
+
+const ESP_IDF_REPOSITORY: &str = "https://github.com/espressif/esp-idf.git";
+const SUBMODULE_RETRIES: u32 = 3;
+
+#[derive(Clone, serde::Serialize, schemars::JsonSchema)]
+pub struct CloneProgress {
+    stage: String,
+    current: usize,
+    total: usize,
+}
+
+impl HelmEvent for CloneProgress {
+    const NAMES: &'static [&'static str] = &["clone-progress"];
+}
+
+fn emit_clone_progress(window: &Window, stage: &str, current: usize, total: usize) {
+    let payload = CloneProgress {
+        stage: stage.to_string(),
+        current,
+        total,
+    };
+    emit_event(window, &payload);
+}
+
+// List submodule paths declared in .gitmodules of the repository
+fn list_submodules(repo_path: &str) -> Vec<String> {
+    let output = Command::new(git_program())
+        .args([
+            "-C",
+            repo_path,
+            "config",
+            "--file",
+            ".gitmodules",
+            "--get-regexp",
+            r"^submodule\..*\.path$",
+        ])
+        .output();
+
+    match output {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| line.split_whitespace().nth(1))
+            .map(|s| s.to_string())
+            .collect(),
+        _ => vec![],
+    }
+}
+
+// Output of "git submodule status --recursive", lines start with "-" when submodule is not
+// initialized, "+" when it's at other commit than recorded and "U" on merge conflicts
+fn submodules_checked_out(status: &str) -> bool {
+    let mut lines = status.lines().filter(|line| !line.is_empty()).peekable();
+    lines.peek().is_some() && lines.all(|line| line.starts_with(' '))
+}
+
+// Submodule is complete when it and all nested submodules are checked out
+fn is_submodule_initialized(git: &str, repo_path: &str, submodule: &str) -> bool {
+    let output = Command::new(git)
+        .args([
+            "-C",
+            repo_path,
+            "submodule",
+            "status",
+            "--recursive",
+            "--",
+            submodule,
+        ])
+        .output();
+    match output {
+        Ok(output) if output.status.success() => {
+            submodules_checked_out(&String::from_utf8_lossy(&output.stdout))
+        }
+        _ => false,
+    }
+}
+
+// Command to clone ESP-IDF with shallow history and submodules. Repository is initialized and
+// fetched instead of cloned, so directory left by failed attempt is reused and the command can
+// be re-run to resume.
+#[tauri::command]
+pub async fn clone_esp_idf(
+    window: Window,
+    app: AppHandle,
+    version: String,
+    target_path: String,
+) -> HelmResult<String> {
+    let git = git_program();
+    let git_dir = std::path::Path::new(&target_path).join(".git");
+
+    if git_dir.exists() {
+        info!("Resuming clone of ESP-IDF {} in {}", version, target_path);
+    } else {
+        info!("Cloning ESP-IDF {} to {}", version, target_path);
+        std::fs::create_dir_all(&target_path)?;
+        for args in [
+            vec!["-C", target_path.as_str(), "init"],
+            vec![
+                "-C",
+                target_path.as_str(),
+                "remote",
+                "add",
+                "origin",
+                ESP_IDF_REPOSITORY,
+            ],
+        ] {
+            run_external_command_with_progress(
+                window.clone(),
+                app.clone(),
+                &git,
+                &args,
+                CloneProgress::NAMES[0],
+            )
+            .await?;
+        }
+    }
+    emit_clone_progress(&window, "clone", 0, 1);
+    run_external_command_with_progress(
+        window.clone(),
+        app.clone(),
+        &git,
+        &[
+            "-C",
+            &target_path,
+            "fetch",
+            "--progress",
+            "--depth",
+            "1",
+            "origin",
+            &version,
+        ],
+        CloneProgress::NAMES[0],
+    )
+    .await?;
+    run_external_command_with_progress(
+        window.clone(),
+        app.clone(),
+        &git,
+        &["-C", &target_path, "checkout", "--force", "FETCH_HEAD"],
+        CloneProgress::NAMES[0],
+    )
+    .await?;
+    emit_clone_progress(&window, "clone", 1, 1);
+
+    configure_git(Some(&target_path))?;
+
+    let submodules = list_submodules(&target_path);
+    let total = submodules.len();
+    let mut failed = vec![];
+
+    for (index, submodule) in submodules.iter().enumerate() {
+        emit_clone_progress(&window, submodule, index, total);
+
+        if is_submodule_initialized(&git, &target_path, submodule) {
+            info!("Submodule {} already present", submodule);
+            continue;
+        }
+
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            info!(
+                "Updating submodule {} (attempt {}/{})",
+                submodule, attempt, SUBMODULE_RETRIES
+            );
+            let result = run_external_command_with_progress(
+                window.clone(),
+                app.clone(),
+                &git,
+                &[
+                    "-C",
+                    &target_path,
+                    "submodule",
+                    "update",
+                    "--init",
+                    "--recursive",
+                    "--depth",
+                    "1",
+                    "--progress",
+                    "--",
+                    submodule,
+                ],
+                CloneProgress::NAMES[0],
+            )
+            .await;
+
+            match result {
+                Ok(_) => break,
+                Err(HelmError::Cancelled) => return Err(HelmError::Cancelled),
+                Err(_) if attempt < SUBMODULE_RETRIES => continue,
+                Err(_) => {
+                    info!("Giving up on submodule {}", submodule);
+                    failed.push(submodule.clone());
+                    break;
+                }
+            }
+        }
+    }
+    emit_clone_progress(&window, "submodules", total, total);
+
+    if !failed.is_empty() {
+        return Err(HelmError::Network(format!(
+            "Failed to update submodules: {}. Run clone again to resume.",
+            failed.join(", ")
+        )));
+    }
+
+    info!("ESP-IDF cloned successfully!");
+    Ok("ESP-IDF cloned successfully!".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn submodule_status_requires_all_checked_out() {
+        let clean = " 5e1a7b8 components/bt/controller/lib_esp32 (heads/master)\n \
+                     2e2b7a1 components/bt/controller/lib_esp32/nested (v1.0)\n";
+        assert!(submodules_checked_out(clean));
+        assert!(!submodules_checked_out(
+            "-5e1a7b8 components/bt/controller/lib_esp32\n"
+        ));
+        assert!(!submodules_checked_out(
+            " 5e1a7b8 components/mbedtls/mbedtls (v3.5.0)\n+2e2b7a1 components/mbedtls/mbedtls/nested (v1.0)\n"
+        ));
+        assert!(!submodules_checked_out(""));
+    }
+
+    #[test]
+    fn parses_git_version() {
+        assert_eq!(
+            parse_git_version("git version 2.45.2.windows.1\n").as_deref(),
+            Some("2.45.2.windows.1")
+        );
+        assert_eq!(
+            parse_git_version("git version 2.39.3 (Apple Git-146)\n").as_deref(),
+            Some("2.39.3")
+        );
+        assert_eq!(parse_git_version("'git' is not recognized\n"), None);
+    }
+
+    const SHA_X64: &str = "0229d2d8a8d4d45c1b3e4f1a1b2c3d4e5f60718293a4b5c6d7e8f9a0b1c2d3e4";
+    const SHA_ARM64: &str = "9f8e7d6c5b4a39281706f5e4d3c2b1a09f8e7d6c5b4a39281706f5e4d3c2b1a0";
+
+    fn release(digest: bool) -> Value {
+        let asset = |name: &str, sha: &str| {
+            let mut asset = serde_json::json!({
+                "name": name,
+                "browser_download_url": format!("https://github.com/git-for-windows/{}", name),
+            });
+            if digest {
+                asset["digest"] = format!("sha256:{}", sha.to_uppercase()).into();
+            }
+            asset
+        };
+        serde_json::json!({
+            "body": format!(
+                "Filename | SHA-256\n-------- | -------\n\
+                 Git-2.47.1-64-bit.exe | {}\nGit-2.47.1-arm64.exe | {}\n",
+                SHA_X64, SHA_ARM64
+            ),
+            "assets": [
+                asset("PortableGit-2.47.1-64-bit.7z.exe", SHA_ARM64),
+                asset("Git-2.47.1-64-bit.exe", SHA_X64),
+                asset("Git-2.47.1-arm64.exe", SHA_ARM64),
+            ],
+        })
+    }
+
+    #[test]
+    fn installer_is_chosen_by_architecture() {
+        let arm64 = installer_asset(&release(true), installer_suffix("aarch64").unwrap());
+        assert_eq!(
+            arm64,
+            Some(InstallerAsset {
+                name: "Git-2.47.1-arm64.exe".into(),
+                url: "https://github.com/git-for-windows/Git-2.47.1-arm64.exe".into(),
+                sha256: SHA_ARM64.into(),
+            })
+        );
+        let x64 = installer_asset(&release(true), installer_suffix("x86_64").unwrap());
+        assert_eq!(x64.unwrap().name, "Git-2.47.1-64-bit.exe");
+        assert_eq!(installer_suffix("x86"), None);
+    }
+
+    #[test]
+    fn installer_hash_falls_back_to_release_notes() {
+        let x64 = installer_asset(&release(false), "-64-bit.exe").unwrap();
+        assert_eq!(x64.sha256, SHA_X64);
+
+        let mut without_hash = release(false);
+        without_hash["body"] = "".into();
+        assert_eq!(installer_asset(&without_hash, "-64-bit.exe"), None);
+    }
+}
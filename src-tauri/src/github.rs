@@ -0,0 +1,402 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use futures::StreamExt;
+use log::info;
+use serde_json::Value;
+use tauri::{AppHandle, Manager, State};
+
+use crate::app_state::AppState;
+use crate::atomic_file::write_atomic;
+use crate::error::{HelmError, HelmResult};
+use crate::services::{services, HttpClient, HttpResponse};
+use crate::settings::save_settings;
+
+const GITHUB_API: &str = "https://api.github.com";
+// Latest release is requested again after this time, cached one is used when offline
+const LATEST_RELEASE_TTL: Duration = Duration::from_secs(60 * 60);
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct CachedResponse {
+    etag: Option<String>,
+    // Unix time of the last successful request
+    #[serde(default)]
+    fetched_at: u64,
+    body: Value,
+}
+
+pub struct GithubResponse {
+    pub body: Value,
+    pub fetched_at: u64,
+    // GitHub could not be reached or rate limited us, body is from the last online session
+    pub stale: bool,
+}
+
+impl CachedResponse {
+    fn into_response(self, stale: bool) -> GithubResponse {
+        GithubResponse {
+            body: self.body,
+            fetched_at: self.fetched_at,
+            stale,
+        }
+    }
+}
+
+// Response fetched exactly max_age ago is requested again, zero max_age always requests
+pub fn is_fresh(fetched_at: u64, now: u64, max_age: Duration) -> bool {
+    now.saturating_sub(fetched_at) < max_age.as_secs()
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[derive(serde::Serialize)]
+pub struct ReleaseInfo {
+    tag: String,
+    name: Option<String>,
+    published_at: Option<String>,
+    html_url: Option<String>,
+    fetched_at: u64,
+    stale: bool,
+}
+
+impl ReleaseInfo {
+    pub fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    pub fn html_url(&self) -> Option<&str> {
+        self.html_url.as_deref()
+    }
+}
+
+#[derive(serde::Serialize)]
+pub struct RateLimit {
+    limit: u64,
+    remaining: u64,
+    // Unix time when remaining requests are reset
+    reset: u64,
+    authenticated: bool,
+}
+
+fn cache_file(path: &str) -> Option<PathBuf> {
+    let mut hasher = DefaultHasher::new();
+    path.hash(&mut hasher);
+    dirs::cache_dir().map(|dir| {
+        dir.join("esp-helm")
+            .join("github")
+            .join(format!("{:x}.json", hasher.finish()))
+    })
+}
+
+fn read_cache(path: Option<&PathBuf>) -> Option<CachedResponse> {
+    serde_json::from_str(&std::fs::read_to_string(path?).ok()?).ok()
+}
+
+async fn write_cache(path: Option<&PathBuf>, cached: &CachedResponse) -> HelmResult<()> {
+    let Some(path) = path else {
+        return Ok(());
+    };
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let content = serde_json::to_vec(cached)
+        .map_err(|e| HelmError::Other(format!("Failed to serialize cache: {}", e)))?;
+    write_atomic(path, &content).await
+}
+
+// Token from settings, GITHUB_TOKEN of environment is used otherwise
+fn github_token(app: &AppHandle) -> Option<String> {
+    let state_mutex = app.state::<Mutex<AppState>>();
+    let state = state_mutex.lock().unwrap();
+    state
+        .settings
+        .github_token
+        .clone()
+        .or_else(|| std::env::var("GITHUB_TOKEN").ok())
+        .filter(|token| !token.is_empty())
+}
+
+fn header_u64(response: &HttpResponse, name: &str) -> Option<u64> {
+    response.header(name)?.parse().ok()
+}
+
+// Primary limit sets remaining to 0, secondary limit sends retry-after
+fn is_rate_limited(response: &HttpResponse) -> bool {
+    matches!(response.status, 403 | 429)
+        && (header_u64(response, "x-ratelimit-remaining") == Some(0)
+            || response.header("retry-after").is_some())
+}
+
+async fn get(
+    http: &dyn HttpClient,
+    token: Option<&str>,
+    url: &str,
+    etag: Option<&str>,
+) -> HelmResult<HttpResponse> {
+    let authorization = token.map(|token| format!("Bearer {}", token));
+    let mut headers = vec![
+        ("user-agent", "esp-helm"),
+        ("accept", "application/vnd.github+json"),
+    ];
+    if let Some(authorization) = &authorization {
+        headers.push(("authorization", authorization.as_str()));
+    }
+    if let Some(etag) = etag {
+        headers.push(("if-none-match", etag));
+    }
+    http.request("GET", url, &headers).await
+}
+
+async fn read_json(mut response: HttpResponse) -> HelmResult<Value> {
+    let mut body = vec![];
+    while let Some(chunk) = response.body.next().await {
+        body.extend(chunk?);
+    }
+    serde_json::from_slice(&body)
+        .map_err(|e| HelmError::Network(format!("Invalid GitHub response: {}", e)))
+}
+
+// GET request to GitHub REST API returning JSON, cache younger than max_age is used without
+// request. Cached ETag makes unchanged responses free, they do not count against rate limit.
+pub async fn github_api(
+    app: &AppHandle,
+    path: &str,
+    max_age: Duration,
+) -> HelmResult<GithubResponse> {
+    let token = github_token(app);
+    let cache = cache_file(path);
+    github_api_with(
+        &*services(app).http,
+        token.as_deref(),
+        cache.as_ref(),
+        path,
+        max_age,
+    )
+    .await
+}
+
+async fn github_api_with(
+    http: &dyn HttpClient,
+    token: Option<&str>,
+    cache: Option<&PathBuf>,
+    path: &str,
+    max_age: Duration,
+) -> HelmResult<GithubResponse> {
+    let cached = read_cache(cache);
+    if let Some(cached) = cached
+        .as_ref()
+        .filter(|cached| is_fresh(cached.fetched_at, now(), max_age))
+    {
+        return Ok(GithubResponse {
+            body: cached.body.clone(),
+            fetched_at: cached.fetched_at,
+            stale: false,
+        });
+    }
+    let url = format!("{}/{}", GITHUB_API, path);
+    let etag = cached.as_ref().and_then(|cached| cached.etag.as_deref());
+
+    let response = match get(http, token, &url, etag).await {
+        Ok(response) => response,
+        Err(e) => {
+            info!("GitHub request failed, using cache: {}", e);
+            return cached.map(|cached| cached.into_response(true)).ok_or(e);
+        }
+    };
+    if response.status == 304 {
+        if let Some(mut cached) = cached {
+            cached.fetched_at = now();
+            write_cache(cache, &cached).await?;
+            return Ok(cached.into_response(false));
+        }
+    }
+    if is_rate_limited(&response) {
+        let reset = header_u64(&response, "x-ratelimit-reset").unwrap_or(0);
+        info!("GitHub rate limit reached, resets at {}", reset);
+        return cached
+            .map(|cached| cached.into_response(true))
+            .ok_or(HelmError::Network(format!(
+                "GitHub rate limit reached, configure token or retry after {}",
+                reset
+            )));
+    }
+    if response.status == 404 {
+        return Err(HelmError::NotFound(path.to_string()));
+    }
+
+    let etag = response.header("etag").map(str::to_string);
+    let body = match response.error_for_status() {
+        Ok(response) => read_json(response).await?,
+        Err(e) => return cached.map(|cached| cached.into_response(true)).ok_or(e),
+    };
+    let cached = CachedResponse {
+        etag,
+        fetched_at: now(),
+        body,
+    };
+    write_cache(cache, &cached).await?;
+    Ok(cached.into_response(false))
+}
+
+fn string_field(value: &Value, key: &str) -> Option<String> {
+    value.get(key).and_then(Value::as_str).map(str::to_string)
+}
+
+pub fn release_info(value: &Value, fetched_at: u64, stale: bool) -> Option<ReleaseInfo> {
+    Some(ReleaseInfo {
+        tag: string_field(value, "tag_name")?,
+        name: string_field(value, "name"),
+        published_at: string_field(value, "published_at"),
+        html_url: string_field(value, "html_url"),
+        fetched_at,
+        stale,
+    })
+}
+
+pub async fn latest_release(app: &AppHandle, repo: &str) -> HelmResult<ReleaseInfo> {
+    let path = format!("repos/{}/releases/latest", repo);
+    let response = github_api(app, &path, LATEST_RELEASE_TTL).await?;
+    release_info(&response.body, response.fetched_at, response.stale)
+        .ok_or(HelmError::Other(format!("Release of {} has no tag", repo)))
+}
+
+// Command to get latest release of GitHub repository "owner/name", e.g. "esp-rs/espup"
+#[tauri::command]
+pub async fn get_latest_release(app: AppHandle, repo: String) -> HelmResult<ReleaseInfo> {
+    if repo.split('/').count() != 2 {
+        return Err(HelmError::Validation(format!(
+            "Repository must have form owner/name, got {}",
+            repo
+        )));
+    }
+    latest_release(&app, &repo).await
+}
+
+// Command to get remaining GitHub API requests, this request itself is not counted
+#[tauri::command]
+pub async fn get_github_rate_limit(app: AppHandle) -> HelmResult<RateLimit> {
+    let token = github_token(&app);
+    let url = format!("{}/rate_limit", GITHUB_API);
+    let response = get(&*services(&app).http, token.as_deref(), &url, None).await?;
+    let value = read_json(response.error_for_status()?).await?;
+    let core = value
+        .get("resources")
+        .and_then(|resources| resources.get("core"))
+        .ok_or(HelmError::Other("Invalid rate limit response".into()))?;
+    let number = |key: &str| core.get(key).and_then(Value::as_u64).unwrap_or(0);
+    Ok(RateLimit {
+        limit: number("limit"),
+        remaining: number("remaining"),
+        reset: number("reset"),
+        authenticated: token.is_some(),
+    })
+}
+
+// Command to set personal access token used for GitHub API, None removes it
+#[tauri::command]
+pub async fn set_github_token(
+    state_mutex: State<'_, Mutex<AppState>>,
+    token: Option<String>,
+) -> HelmResult<String> {
+    let mut state = state_mutex.lock().unwrap();
+    let mut settings = state.settings.clone();
+    settings.github_token = token.map(|token| token.trim().to_string());
+    save_settings(&settings)?;
+    state.settings = settings;
+    Ok("GitHub token updated".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::mock::{MockHttp, MockResponse};
+    use tempfile::TempDir;
+
+    const PATH: &str = "repos/esp-rs/espup/releases/latest";
+
+    fn url() -> String {
+        format!("{}/{}", GITHUB_API, PATH)
+    }
+
+    fn response(status: u16, headers: &[(&str, &str)], body: &str) -> MockResponse {
+        MockResponse {
+            status,
+            headers: headers
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            body: body.as_bytes().to_vec(),
+        }
+    }
+
+    // Cache file with response fetched long ago, so it is always revalidated. The file is
+    // removed with the returned TempDir.
+    fn cache(cached: Option<CachedResponse>) -> (TempDir, PathBuf) {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("cache.json");
+        if let Some(cached) = cached {
+            std::fs::write(&path, serde_json::to_vec(&cached).unwrap()).unwrap();
+        }
+        (dir, path)
+    }
+
+    fn old_release() -> CachedResponse {
+        CachedResponse {
+            etag: Some("\"v0.11.0\"".into()),
+            fetched_at: 0,
+            body: serde_json::json!({"tag_name": "v0.11.0"}),
+        }
+    }
+
+    #[tokio::test]
+    async fn not_modified_reuses_cached_body() {
+        let (_dir, cache) = cache(Some(old_release()));
+        let http = MockHttp::default().with("GET", &url(), response(304, &[], ""));
+
+        let response = github_api_with(&http, None, Some(&cache), PATH, LATEST_RELEASE_TTL)
+            .await
+            .unwrap();
+        assert_eq!(response.body["tag_name"], "v0.11.0");
+        assert!(!response.stale);
+        assert!(read_cache(Some(&cache)).unwrap().fetched_at > 0);
+    }
+
+    #[tokio::test]
+    async fn rate_limit_returns_stale_cache() {
+        let (_dir, cache) = cache(Some(old_release()));
+        let limited = response(403, &[("x-ratelimit-remaining", "0")], "{}");
+        let http = MockHttp::default().with("GET", &url(), limited);
+
+        let response = github_api_with(&http, None, Some(&cache), PATH, LATEST_RELEASE_TTL)
+            .await
+            .unwrap();
+        assert_eq!(response.body["tag_name"], "v0.11.0");
+        assert!(response.stale);
+    }
+
+    #[tokio::test]
+    async fn stores_etag_of_new_response() {
+        let (_dir, cache) = cache(None);
+        let release = response(
+            200,
+            &[("etag", "\"v0.12.0\"")],
+            r#"{"tag_name": "v0.12.0"}"#,
+        );
+        let http = MockHttp::default().with("GET", &url(), release);
+
+        let response = github_api_with(&http, None, Some(&cache), PATH, LATEST_RELEASE_TTL)
+            .await
+            .unwrap();
+        assert_eq!(response.body["tag_name"], "v0.12.0");
+        let cached = read_cache(Some(&cache)).unwrap();
+        assert_eq!(cached.etag.as_deref(), Some("\"v0.12.0\""));
+    }
+}
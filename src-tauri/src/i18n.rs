@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+use fluent_bundle::concurrent::FluentBundle;
+use fluent_bundle::{FluentArgs, FluentResource, FluentValue};
+use log::info;
+use tauri::State;
+
+use crate::app_state::AppState;
+use crate::error::{HelmError, HelmResult};
+use crate::settings::save_settings;
+
+const DEFAULT_LANGUAGE: &str = "en";
+// Catalogs in src-tauri/locales, English is complete and used for missing keys
+const CATALOGS: [(&str, &str); 2] = [
+    ("en", include_str!("../locales/en.ftl")),
+    ("es", include_str!("../locales/es.ftl")),
+];
+
+type Bundle = FluentBundle<FluentResource>;
+
+static BUNDLES: OnceLock<HashMap<&'static str, Bundle>> = OnceLock::new();
+static LANGUAGE: Mutex<&'static str> = Mutex::new(DEFAULT_LANGUAGE);
+
+fn bundles() -> &'static HashMap<&'static str, Bundle> {
+    BUNDLES.get_or_init(|| {
+        CATALOGS
+            .iter()
+            .map(|(language, source)| {
+                let resource = FluentResource::try_new(source.to_string()).unwrap_or_else(
+                    |(resource, errors)| {
+                        info!("Catalog {} has errors: {:?}", language, errors);
+                        resource
+                    },
+                );
+                let mut bundle = FluentBundle::new_concurrent(vec![language.parse().unwrap()]);
+                // Unicode isolation marks are shown as boxes by some terminals and log viewers
+                bundle.set_use_isolating(false);
+                if let Err(errors) = bundle.add_resource(resource) {
+                    info!("Catalog {} has duplicate keys: {:?}", language, errors);
+                }
+                (*language, bundle)
+            })
+            .collect()
+    })
+}
+
+fn format(bundle: &Bundle, key: &str, args: Option<&FluentArgs>) -> Option<String> {
+    let pattern = bundle.get_message(key)?.value()?;
+    let mut errors = vec![];
+    let text = bundle
+        .format_pattern(pattern, args, &mut errors)
+        .to_string();
+    if !errors.is_empty() {
+        info!("Message {} formatted with errors: {:?}", key, errors);
+    }
+    Some(text)
+}
+
+// Localized message for key in selected language, English when translation is missing
+// and key itself when there is no such message at all
+pub fn tr(key: &str, args: &[(&str, String)]) -> String {
+    let mut fluent_args = FluentArgs::new();
+    for (name, value) in args {
+        fluent_args.set(*name, FluentValue::from(value.clone()));
+    }
+    let args = (!args.is_empty()).then_some(&fluent_args);
+    let language = *LANGUAGE.lock().unwrap();
+    let bundles = bundles();
+    [language, DEFAULT_LANGUAGE]
+        .iter()
+        .filter_map(|language| bundles.get(language))
+        .find_map(|bundle| format(bundle, key, args))
+        .unwrap_or_else(|| key.to_string())
+}
+
+pub fn apply_language(language: Option<&str>) {
+    let language = language
+        .and_then(|language| CATALOGS.iter().find(|(code, _)| *code == language))
+        .map(|(code, _)| *code)
+        .unwrap_or(DEFAULT_LANGUAGE);
+    *LANGUAGE.lock().unwrap() = language;
+}
+
+// Command to list languages which have catalog
+#[tauri::command]
+pub async fn get_languages() -> HelmResult<Vec<String>> {
+    Ok(CATALOGS.iter().map(|(code, _)| code.to_string()).collect())
+}
+
+// Command to select language of messages in command results and events, e.g. "es"
+#[tauri::command]
+pub async fn set_language(
+    state_mutex: State<'_, Mutex<AppState>>,
+    language: String,
+) -> HelmResult<String> {
+    if !CATALOGS.iter().any(|(code, _)| *code == language) {
+        return Err(HelmError::Validation(format!(
+            "Language {} is not supported",
+            language
+        )));
+    }
+    let mut state = state_mutex.lock().unwrap();
+    let mut settings = state.settings.clone();
+    settings.language = Some(language.clone());
+    save_settings(&settings)?;
+    state.settings = settings;
+    apply_language(Some(&language));
+    Ok(format!("Language set to {}", language))
+}
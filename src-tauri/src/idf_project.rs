@@ -0,0 +1,210 @@
+use std::path::Path;
+use std::sync::Mutex;
+
+use log::info;
+use tauri::State;
+
+use crate::adopt::{detect_existing, ExistingInstallation};
+use crate::app_state::AppState;
+use crate::error::{HelmError, HelmResult};
+
+#[derive(serde::Serialize)]
+pub struct IdfProject {
+    path: String,
+    name: Option<String>,
+    // Chip from CONFIG_IDF_TARGET in sdkconfig
+    target: Option<String>,
+    // Version constraint from idf_component.yml, e.g. ">=5.0"
+    idf_requirement: Option<String>,
+    // Exact version resolved in dependencies.lock
+    locked_idf_version: Option<String>,
+    // Installed ESP-IDF which satisfies the requirement
+    matching_installation: Option<ExistingInstallation>,
+    // Version which should be installed when no installation matches
+    suggested_install: Option<String>,
+}
+
+// Version padded to major.minor.patch, "v5.1" -> [5, 1, 0]
+fn version_numbers(version: &str) -> [u64; 3] {
+    let mut numbers = [0; 3];
+    for (slot, part) in numbers.iter_mut().zip(
+        version
+            .trim_start_matches('v')
+            .split(|c: char| !c.is_ascii_digit())
+            .filter_map(|part| part.parse().ok()),
+    ) {
+        *slot = part;
+    }
+    numbers
+}
+
+// Subset of component manager constraints: comparison operators, "~", "^" and "*",
+// clauses are separated by comma
+fn satisfies(version: &str, requirement: &str) -> bool {
+    let version = version_numbers(version);
+    requirement.split(',').map(str::trim).all(|clause| {
+        if clause.is_empty() || clause == "*" {
+            return true;
+        }
+        let (op, value) = match clause.find(|c: char| c.is_ascii_digit()) {
+            Some(index) => clause.split_at(index),
+            None => return false,
+        };
+        let required = version_numbers(value);
+        match op.trim() {
+            ">=" => version >= required,
+            ">" => version > required,
+            "<=" => version <= required,
+            "<" => version < required,
+            "~" => version >= required && version[..2] == required[..2],
+            "^" => version >= required && version[0] == required[0],
+            "" | "=" | "==" => version == required,
+            _ => false,
+        }
+    })
+}
+
+// Lowest version allowed by the requirement, used as release tag to install
+fn minimal_version(requirement: &str) -> Option<String> {
+    requirement.split(',').map(str::trim).find_map(|clause| {
+        let index = clause.find(|c: char| c.is_ascii_digit())?;
+        let (op, value) = clause.split_at(index);
+        matches!(op.trim(), ">=" | "~" | "^" | "=" | "==" | "").then(|| format!("v{}", value))
+    })
+}
+
+fn read_sdkconfig_target(path: &Path) -> Option<String> {
+    ["sdkconfig", "sdkconfig.defaults"].iter().find_map(|file| {
+        let content = std::fs::read_to_string(path.join(file)).ok()?;
+        content.lines().find_map(|line| {
+            line.trim()
+                .strip_prefix("CONFIG_IDF_TARGET=")
+                .map(|value| value.trim_matches('"').to_string())
+        })
+    })
+}
+
+// dependencies:
+//   idf: ">=5.0"
+// or
+//   idf:
+//     version: ">=5.0"
+fn read_idf_requirement(path: &Path) -> Option<String> {
+    let content = std::fs::read_to_string(path.join("main").join("idf_component.yml"))
+        .or_else(|_| std::fs::read_to_string(path.join("idf_component.yml")))
+        .ok()?;
+    let mut lines = content.lines().map(str::trim);
+    while let Some(line) = lines.next() {
+        if let Some(value) = line.strip_prefix("idf:") {
+            let value = value.trim();
+            if !value.is_empty() {
+                return Some(value.trim_matches(['"', '\'']).to_string());
+            }
+            return lines
+                .next()
+                .and_then(|line| line.strip_prefix("version:"))
+                .map(|value| value.trim().trim_matches(['"', '\'']).to_string());
+        }
+    }
+    None
+}
+
+// dependencies.lock contains resolved version:
+//   idf:
+//     component_hash: null
+//     source:
+//       type: idf
+//     version: 5.1.2
+fn read_locked_idf_version(path: &Path) -> Option<String> {
+    let content = std::fs::read_to_string(path.join("dependencies.lock")).ok()?;
+    let mut in_idf = false;
+    for line in content.lines() {
+        let indent = line.len() - line.trim_start().len();
+        let line = line.trim();
+        if line == "idf:" {
+            in_idf = true;
+        } else if in_idf && indent <= 2 {
+            in_idf = false;
+        } else if in_idf {
+            if let Some(version) = line.strip_prefix("version:") {
+                return Some(version.trim().to_string());
+            }
+        }
+    }
+    None
+}
+
+// project(name) from top level CMakeLists.txt, which includes IDF project.cmake
+fn read_cmake_project(path: &Path) -> HelmResult<Option<String>> {
+    let content = std::fs::read_to_string(path.join("CMakeLists.txt"))
+        .map_err(|_| HelmError::Validation(format!("{} has no CMakeLists.txt", path.display())))?;
+    if !content.contains("project.cmake") {
+        return Err(HelmError::Validation(format!(
+            "{} is not an ESP-IDF project",
+            path.display()
+        )));
+    }
+    Ok(content.lines().find_map(|line| {
+        line.trim()
+            .strip_prefix("project(")
+            .map(|rest| rest.trim_end_matches(')').trim().to_string())
+    }))
+}
+
+pub fn inspect_idf_project(
+    path: &Path,
+    adopted: &[ExistingInstallation],
+) -> HelmResult<IdfProject> {
+    let name = read_cmake_project(path)?;
+    let target = read_sdkconfig_target(path);
+    let idf_requirement = read_idf_requirement(path);
+    let locked_idf_version = read_locked_idf_version(path);
+
+    // Locked version is the most specific requirement
+    let requirement = locked_idf_version
+        .as_ref()
+        .map(|version| format!("=={}", version))
+        .or_else(|| idf_requirement.clone());
+
+    let mut installations: Vec<ExistingInstallation> = adopted.to_vec();
+    installations.extend(detect_existing());
+    let matching_installation = installations
+        .into_iter()
+        .filter(|installation| installation.kind == "esp-idf")
+        .find(|installation| match (&installation.version, &requirement) {
+            (Some(version), Some(requirement)) => satisfies(version, requirement),
+            (_, None) => true,
+            (None, Some(_)) => false,
+        });
+
+    let suggested_install = match (&matching_installation, &requirement) {
+        (None, Some(requirement)) => minimal_version(requirement),
+        _ => None,
+    };
+
+    Ok(IdfProject {
+        path: path.to_string_lossy().to_string(),
+        name,
+        target,
+        idf_requirement,
+        locked_idf_version,
+        matching_installation,
+        suggested_install,
+    })
+}
+
+// Command to inspect existing ESP-IDF project, suggested_install can be passed to clone_esp_idf
+#[tauri::command]
+pub async fn import_idf_project(
+    state_mutex: State<'_, Mutex<AppState>>,
+    path: String,
+) -> HelmResult<IdfProject> {
+    let adopted = {
+        let state = state_mutex.lock().unwrap();
+        state.settings.adopted_installations.clone()
+    };
+    info!("Importing ESP-IDF project {}", path);
+    tokio::task::spawn_blocking(move || inspect_idf_project(Path::new(&path), &adopted))
+        .await
+        .map_err(|e| HelmError::Other(format!("Import failed: {}", e)))?
+}
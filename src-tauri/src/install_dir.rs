@@ -0,0 +1,139 @@
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use log::info;
+use tauri::State;
+
+use crate::app_state::AppState;
+use crate::audit::{audit_path, AuditAction};
+use crate::error::{HelmError, HelmResult};
+use crate::esp_clang::set_export_variable;
+use crate::inventory::{cargo_home, espressif_home, rustup_home};
+use crate::long_path::{
+    long_path, long_paths_enabled, validate_install_path, PathSeverity, SAFE_ROOT,
+};
+use crate::portable::portable_root;
+use crate::settings::{save_settings, Settings};
+
+#[derive(serde::Serialize)]
+pub struct InstallPaths {
+    install_root: Option<String>,
+    rustup_home: Option<String>,
+    cargo_home: Option<String>,
+    idf_tools_path: Option<String>,
+}
+
+// Variables understood by rustup, espup and ESP-IDF install scripts, with subdirectory of root
+const ROOT_VARIABLES: [(&str, &str); 3] = [
+    ("RUSTUP_HOME", "rustup"),
+    ("CARGO_HOME", "cargo"),
+    ("IDF_TOOLS_PATH", "espressif"),
+];
+
+// Set environment of this process, installers and other child processes inherit it
+pub fn apply_install_root(settings: &Settings) {
+    let Some(root) = &settings.install_root else {
+        return;
+    };
+    let root = Path::new(root);
+    for (variable, dir) in ROOT_VARIABLES {
+        std::env::set_var(variable, root.join(dir));
+    }
+
+    // Tools installed to cargo bin directory are looked up in PATH
+    let cargo_bin = root.join("cargo").join("bin");
+    let path = std::env::var_os("PATH").unwrap_or_default();
+    let mut paths: Vec<PathBuf> = std::env::split_paths(&path).collect();
+    if !paths.contains(&cargo_bin) {
+        paths.insert(0, cargo_bin);
+        if let Ok(path) = std::env::join_paths(paths) {
+            std::env::set_var("PATH", path);
+        }
+    }
+    info!("Using install root {}", root.display());
+}
+
+fn current_paths(install_root: Option<String>) -> InstallPaths {
+    let display = |path: Option<PathBuf>| path.map(|path| path.to_string_lossy().to_string());
+    InstallPaths {
+        install_root,
+        rustup_home: display(rustup_home()),
+        cargo_home: display(cargo_home()),
+        idf_tools_path: display(espressif_home()),
+    }
+}
+
+// Installation fails late when the drive is read-only, so check it up front
+fn check_writable(root: &Path) -> HelmResult<()> {
+    let root = long_path(root);
+    std::fs::create_dir_all(&root)?;
+    audit_path(AuditAction::CreateDir, &root);
+    let probe = root.join(".esp-helm-write-test");
+    std::fs::write(&probe, b"")
+        .map_err(|e| HelmError::Permission(format!("{} is not writable: {}", root.display(), e)))?;
+    std::fs::remove_file(probe)?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_install_paths(
+    state_mutex: State<'_, Mutex<AppState>>,
+) -> HelmResult<InstallPaths> {
+    let install_root = state_mutex.lock().unwrap().settings.install_root.clone();
+    Ok(current_paths(install_root))
+}
+
+// Command to install toolchains under custom root, e.g. on a different drive.
+// None returns to default locations after restart of esp-helm and terminals.
+#[tauri::command]
+pub async fn set_install_root(
+    state_mutex: State<'_, Mutex<AppState>>,
+    path: Option<String>,
+) -> HelmResult<InstallPaths> {
+    if portable_root().is_some() {
+        return Err(HelmError::Validation(
+            "Install root cannot be changed in portable mode".into(),
+        ));
+    }
+    let path = path.filter(|path| !path.trim().is_empty());
+    if let Some(root) = &path {
+        let root = Path::new(root);
+        // Spaces and non-ASCII are only warnings, user may know their tools handle them
+        if let Some(error) = validate_install_path(root, long_paths_enabled())
+            .into_iter()
+            .find(|issue| issue.severity == PathSeverity::Error)
+        {
+            return Err(HelmError::Validation(format!(
+                "{}: {}",
+                root.display(),
+                error.message
+            )));
+        }
+        check_writable(root)?;
+    }
+
+    let settings = {
+        let mut state = state_mutex.lock().unwrap();
+        state.settings.install_root = path.clone();
+        state.settings.clone()
+    };
+    save_settings(&settings)?;
+    apply_install_root(&settings);
+
+    // Exported for terminals, so cargo and idf.py find the same toolchains
+    for (variable, dir) in ROOT_VARIABLES {
+        let value = path
+            .as_ref()
+            .map(|root| Path::new(root).join(dir).to_string_lossy().to_string());
+        set_export_variable(variable, value.as_deref())?;
+    }
+    Ok(current_paths(path))
+}
+
+// Command to move future installs to short root without spaces, e.g. C:\\esp
+#[tauri::command]
+pub async fn use_safe_install_root(
+    state_mutex: State<'_, Mutex<AppState>>,
+) -> HelmResult<InstallPaths> {
+    set_install_root(state_mutex, Some(SAFE_ROOT.into())).await
+}
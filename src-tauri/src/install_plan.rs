@@ -0,0 +1,360 @@
+use std::path::PathBuf;
+
+use serde_json::Value;
+use tauri::AppHandle;
+
+use crate::arch::{detect_host, is_x86_64_forced};
+use crate::download::probe_size;
+use crate::error::HelmResult;
+use crate::esp_clang::export_file_path;
+use crate::github::github_api;
+use crate::inventory::{cargo_home, rustup_home};
+use crate::portable::{portable_root, LAUNCHER_NAME};
+use crate::release_metadata::RELEASES_TTL;
+use crate::rust::{
+    get_default_host, riscv_channel, validate_nightly_pin, RustInstallOptions, RISCV_TARGETS,
+    RUSTUP_COMPONENTS, VS_BUILDTOOLS_ARGS, VS_BUILDTOOLS_URL,
+};
+use crate::rustup::{rustup_bin, rustup_init_source, selection_args, validate_options};
+use crate::system_install::is_system_scope;
+use crate::verify::{chip_target, ALL_CHIPS};
+
+#[derive(serde::Serialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum PlannedAction {
+    Download {
+        url: String,
+        // None when server does not report size or espup selects the version itself
+        size: Option<u64>,
+        destination: String,
+    },
+    Command {
+        program: String,
+        args: Vec<String>,
+    },
+    CreateDir {
+        path: String,
+    },
+    ModifyFile {
+        path: String,
+        reason: String,
+    },
+}
+
+#[derive(serde::Serialize)]
+pub struct PlanStep {
+    // Name of the job which runs this step during installation
+    job: String,
+    actions: Vec<PlannedAction>,
+}
+
+#[derive(serde::Serialize)]
+pub struct InstallPlan {
+    steps: Vec<PlanStep>,
+    // Sum of known download sizes
+    download_bytes: u64,
+    unknown_sizes: usize,
+}
+
+fn path_string(path: Option<PathBuf>) -> String {
+    path.map(|path| path.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+fn command(program: &str, args: &[&str]) -> PlannedAction {
+    PlannedAction::Command {
+        program: program.to_string(),
+        args: args.iter().map(|arg| arg.to_string()).collect(),
+    }
+}
+
+async fn download(app: &AppHandle, url: &str, destination: String) -> PlannedAction {
+    PlannedAction::Download {
+        url: url.to_string(),
+        size: probe_size(app, url).await,
+        destination,
+    }
+}
+
+pub fn host_triple(app: &AppHandle, options: &RustInstallOptions) -> String {
+    get_default_host(app, options.selected_variant.clone()).unwrap_or_else(|| {
+        detect_host(is_x86_64_forced(app))
+            .map(|host| host.espup_host.unwrap_or(host.host))
+            .unwrap_or_default()
+    })
+}
+
+fn rustup_step(app: &AppHandle, options: &RustInstallOptions) -> Vec<PlannedAction> {
+    let selection = selection_args(&options.rustup);
+    if let Some(rustup) = rustup_bin() {
+        let mut args = vec![
+            "toolchain".to_string(),
+            "install".into(),
+            options.rustup.default_toolchain.clone(),
+        ];
+        args.extend(selection);
+        return vec![PlannedAction::Command {
+            program: rustup.to_string_lossy().to_string(),
+            args,
+        }];
+    }
+
+    let (_, name) = rustup_init_source();
+    let rustup_init = std::env::temp_dir().join(name);
+    let mut args = vec![
+        "-y".to_string(),
+        "--no-modify-path".into(),
+        "--default-toolchain".into(),
+        options.rustup.default_toolchain.clone(),
+    ];
+    args.extend(selection);
+    if cfg!(windows) {
+        if let Some(variant) = &options.selected_variant {
+            args.push("--default-host".into());
+            args.push(variant.clone());
+        }
+    }
+    let program = if cfg!(unix) {
+        args.insert(0, rustup_init.to_string_lossy().to_string());
+        "sh".to_string()
+    } else {
+        rustup_init.to_string_lossy().to_string()
+    };
+
+    let mut actions = vec![
+        PlannedAction::CreateDir {
+            path: path_string(cargo_home()),
+        },
+        PlannedAction::CreateDir {
+            path: path_string(rustup_home()),
+        },
+        PlannedAction::Command { program, args },
+    ];
+    if portable_root().is_some() || is_system_scope(app) {
+        return actions;
+    }
+    let bin = path_string(cargo_home().map(|home| home.join("bin")));
+    #[cfg(unix)]
+    for profile in crate::rustup::profiles_to_update() {
+        actions.push(PlannedAction::ModifyFile {
+            path: profile.to_string_lossy().to_string(),
+            reason: format!("Add {} to PATH", bin),
+        });
+    }
+    #[cfg(windows)]
+    actions.push(PlannedAction::ModifyFile {
+        path: "HKCU\\Environment\\Path".into(),
+        reason: format!("Add {} to PATH", bin),
+    });
+    actions
+}
+
+// Rust part of esp toolchain comes from the latest rust-build release, its assets list sizes.
+// LLVM and GCC versions are pinned inside espup, so only their source is known.
+async fn toolchain_step(app: &AppHandle, options: &RustInstallOptions) -> Vec<PlannedAction> {
+    let host = host_triple(app, options);
+    let toolchain_dir = path_string(rustup_home().map(|home| home.join("toolchains").join("esp")));
+    let mut actions = vec![PlannedAction::CreateDir {
+        path: toolchain_dir.clone(),
+    }];
+
+    let assets = github_api(app, "repos/esp-rs/rust-build/releases/latest", RELEASES_TTL)
+        .await
+        .ok()
+        .and_then(|response| {
+            response
+                .body
+                .get("assets")
+                .and_then(Value::as_array)
+                .cloned()
+        })
+        .unwrap_or_default();
+    for asset in &assets {
+        let name = asset
+            .get("name")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        // rust-<version>-<host> and host independent rust-src-<version>
+        let wanted = name.starts_with("rust-")
+            && (name.starts_with("rust-src-") || name.contains(&host))
+            && (name.ends_with(".tar.xz") || name.ends_with(".zip"));
+        let Some(url) = asset.get("browser_download_url").and_then(Value::as_str) else {
+            continue;
+        };
+        if wanted {
+            actions.push(PlannedAction::Download {
+                url: url.to_string(),
+                size: asset.get("size").and_then(Value::as_u64),
+                destination: toolchain_dir.clone(),
+            });
+        }
+    }
+
+    let mut sources = vec!["https://github.com/espressif/llvm-project/releases"];
+    if options.gcc.install_gcc {
+        sources.push("https://github.com/espressif/crosstool-NG/releases");
+    }
+    for source in sources {
+        actions.push(PlannedAction::Download {
+            url: source.to_string(),
+            size: None,
+            destination: toolchain_dir.clone(),
+        });
+    }
+    actions.push(PlannedAction::ModifyFile {
+        path: path_string(export_file_path()),
+        reason: "Environment variables of esp toolchain".into(),
+    });
+    if let Some(root) = portable_root() {
+        actions.push(PlannedAction::ModifyFile {
+            path: root.join(LAUNCHER_NAME).to_string_lossy().to_string(),
+            reason: "Portable launcher".into(),
+        });
+    }
+    actions
+}
+
+fn verification_step(options: &RustInstallOptions) -> Vec<PlannedAction> {
+    let chips: Vec<String> = if options.targets.is_empty() {
+        ALL_CHIPS.iter().map(|chip| chip.to_string()).collect()
+    } else {
+        options.targets.clone()
+    };
+    let mut actions = vec![];
+    for chip in &chips {
+        let Some((toolchain, target, _)) = chip_target(chip) else {
+            continue;
+        };
+        let dir = std::env::temp_dir().join("esp-helm-verify").join(chip);
+        let manifest = dir.join("Cargo.toml").to_string_lossy().to_string();
+        let toolchain = format!("+{}", toolchain);
+        let mut args = vec![
+            toolchain.as_str(),
+            "build",
+            "--release",
+            "--manifest-path",
+            &manifest,
+            "--target",
+            target,
+        ];
+        if target.starts_with("xtensa") {
+            args.push("-Zbuild-std=core");
+        }
+        actions.push(PlannedAction::CreateDir {
+            path: dir.to_string_lossy().to_string(),
+        });
+        actions.push(command("cargo", &args));
+    }
+    actions
+}
+
+pub async fn plan(app: &AppHandle, options: &RustInstallOptions) -> HelmResult<InstallPlan> {
+    validate_options(&options.rustup)?;
+    if let Some(pin) = &options.nightly_pin {
+        validate_nightly_pin(pin)?;
+    }
+    let mut steps = vec![];
+
+    if cfg!(windows) && options.install_msvc {
+        let installer = std::env::temp_dir().join("vs_buildtools.exe");
+        let installer = installer.to_string_lossy().to_string();
+        steps.push(PlanStep {
+            job: "Visual Studio Build Tools".into(),
+            actions: vec![
+                download(app, VS_BUILDTOOLS_URL, installer.clone()).await,
+                command(&installer, &VS_BUILDTOOLS_ARGS),
+            ],
+        });
+    }
+
+    let mut rustup_actions = vec![];
+    if rustup_bin().is_none() {
+        let (url, name) = rustup_init_source();
+        let destination = std::env::temp_dir().join(name);
+        rustup_actions.push(download(app, &url, destination.to_string_lossy().to_string()).await);
+    }
+    rustup_actions.extend(rustup_step(app, options));
+    steps.push(PlanStep {
+        job: "rustup".into(),
+        actions: rustup_actions,
+    });
+
+    steps.push(PlanStep {
+        job: "Rust toolchain".into(),
+        actions: toolchain_step(app, options).await,
+    });
+
+    let rustup = path_string(cargo_home().map(|home| home.join("bin").join("rustup")));
+    if let Some(pin) = &options.nightly_pin {
+        let targets = RISCV_TARGETS.join(",");
+        steps.push(PlanStep {
+            job: "Pinned nightly".into(),
+            actions: vec![command(
+                &rustup,
+                &[
+                    "toolchain",
+                    "install",
+                    pin,
+                    "--profile",
+                    "minimal",
+                    "--component",
+                    "rust-src",
+                    "--target",
+                    &targets,
+                ],
+            )],
+        });
+    }
+    if options.install_components {
+        let channel = options
+            .nightly_pin
+            .clone()
+            .unwrap_or_else(|| riscv_channel(app));
+        let components = RUSTUP_COMPONENTS.join(",");
+        steps.push(PlanStep {
+            job: "rustup components".into(),
+            actions: vec![command(
+                &rustup,
+                &[
+                    "toolchain",
+                    "install",
+                    &channel,
+                    "--profile",
+                    "minimal",
+                    "--component",
+                    &components,
+                ],
+            )],
+        });
+    }
+    if options.verify {
+        steps.push(PlanStep {
+            job: "Verification".into(),
+            actions: verification_step(options),
+        });
+    }
+
+    let sizes: Vec<Option<u64>> = steps
+        .iter()
+        .flat_map(|step| &step.actions)
+        .filter_map(|action| match action {
+            PlannedAction::Download { size, .. } => Some(*size),
+            _ => None,
+        })
+        .collect();
+    Ok(InstallPlan {
+        download_bytes: sizes.iter().flatten().sum(),
+        unknown_sizes: sizes.iter().filter(|size| size.is_none()).count(),
+        steps,
+    })
+}
+
+// Command to preview downloads, commands and modified files of install_rust_support
+// with the same options, nothing is executed
+#[tauri::command]
+pub async fn plan_installation(
+    app: AppHandle,
+    options: RustInstallOptions,
+) -> HelmResult<InstallPlan> {
+    plan(&app, &options).await
+}
@@ -0,0 +1,402 @@
+use std::path::{Component, Path, PathBuf};
+
+use log::info;
+use tauri::State;
+use walkdir::WalkDir;
+
+use std::sync::Mutex;
+
+use crate::adopt::ExistingInstallation;
+use crate::app_state::AppState;
+use crate::audit::{audit_path, AuditAction};
+use crate::error::{HelmError, HelmResult};
+use crate::long_path::long_path;
+use crate::rust::get_tool_version;
+use crate::rustup::rustup_show;
+
+#[derive(Clone, serde::Serialize)]
+pub struct InventoryItem {
+    pub kind: String,
+    pub name: String,
+    pub version: Option<String>,
+    pub path: String,
+    pub size: u64,
+}
+
+// Tools installed to ~/.cargo/bin which are managed by esp-helm
+const CARGO_BIN_TOOLS: [&str; 7] = [
+    "espup",
+    "espflash",
+    "cargo-espflash",
+    "ldproxy",
+    "cargo-generate",
+    "probe-rs",
+    "cargo-binstall",
+];
+
+// Calculate size of directory or file in bytes
+pub fn dir_size(path: &Path) -> u64 {
+    WalkDir::new(path)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter_map(|e| e.metadata().ok())
+        .filter(|m| m.is_file())
+        .map(|m| m.len())
+        .sum()
+}
+
+pub fn rustup_home() -> Option<PathBuf> {
+    match std::env::var_os("RUSTUP_HOME") {
+        Some(path) => Some(PathBuf::from(path)),
+        None => dirs::home_dir().map(|home| home.join(".rustup")),
+    }
+}
+
+pub fn cargo_home() -> Option<PathBuf> {
+    match std::env::var_os("CARGO_HOME") {
+        Some(path) => Some(PathBuf::from(path)),
+        None => dirs::home_dir().map(|home| home.join(".cargo")),
+    }
+}
+
+pub fn espressif_home() -> Option<PathBuf> {
+    match std::env::var_os("IDF_TOOLS_PATH") {
+        Some(path) => Some(PathBuf::from(path)),
+        #[cfg(unix)]
+        None => dirs::home_dir().map(|home| home.join(".espressif")),
+        #[cfg(windows)]
+        None => Some(PathBuf::from("C:\\Espressif")),
+    }
+}
+
+fn list_dirs(path: &Path) -> Vec<PathBuf> {
+    match std::fs::read_dir(path) {
+        Ok(entries) => entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.is_dir())
+            .collect(),
+        Err(_) => vec![],
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+fn item(kind: &str, name: String, version: Option<String>, path: &Path) -> InventoryItem {
+    InventoryItem {
+        kind: kind.to_string(),
+        name,
+        version,
+        path: path.to_string_lossy().to_string(),
+        size: dir_size(path),
+    }
+}
+
+// Rust toolchains, "esp" toolchain contains Xtensa fork of Rust and LLVM
+fn scan_rustup_toolchains(items: &mut Vec<InventoryItem>) {
+    let Some(toolchains_dir) = rustup_home().map(|home| home.join("toolchains")) else {
+        return;
+    };
+
+    for toolchain in list_dirs(&toolchains_dir) {
+        let name = file_name(&toolchain);
+        if name == "esp" {
+            items.push(item("xtensa-toolchain", name, None, &toolchain));
+            for component in list_dirs(&toolchain) {
+                let component_name = file_name(&component);
+                let kind = if component_name.contains("clang") {
+                    "llvm"
+                } else if component_name.ends_with("-elf") {
+                    "gcc-toolchain"
+                } else {
+                    continue;
+                };
+                for version in list_dirs(&component) {
+                    items.push(item(
+                        kind,
+                        component_name.clone(),
+                        Some(file_name(&version)),
+                        &version,
+                    ));
+                }
+            }
+        } else {
+            let channel = name.split('-').next().map(|s| s.to_string());
+            items.push(item("rust-toolchain", name, channel, &toolchain));
+        }
+    }
+}
+
+// Standard library of each target installed for active toolchain, e.g. riscv32imc-unknown-none-elf
+fn scan_rustup_targets(items: &mut Vec<InventoryItem>) {
+    let (Some(show), Some(home)) = (rustup_show(), rustup_home()) else {
+        return;
+    };
+    let Some(active) = show.active_toolchain else {
+        return;
+    };
+    let rustlib = home
+        .join("toolchains")
+        .join(&active)
+        .join("lib")
+        .join("rustlib");
+    for target in show.targets {
+        let path = rustlib.join(&target);
+        if path.exists() {
+            items.push(item("rust-target", target, Some(active.clone()), &path));
+        }
+    }
+}
+
+// ESP-IDF installations and tools downloaded by idf_tools.py
+fn scan_espressif(items: &mut Vec<InventoryItem>) {
+    let Some(espressif_dir) = espressif_home() else {
+        return;
+    };
+
+    for esp_idf in list_dirs(&espressif_dir.join("esp-idf")) {
+        items.push(item(
+            "esp-idf",
+            "esp-idf".into(),
+            Some(file_name(&esp_idf)),
+            &esp_idf,
+        ));
+    }
+
+    for tool in list_dirs(&espressif_dir.join("tools")) {
+        let tool_name = file_name(&tool);
+        for version in list_dirs(&tool) {
+            items.push(item(
+                "esp-idf-tool",
+                tool_name.clone(),
+                Some(file_name(&version)),
+                &version,
+            ));
+        }
+    }
+
+    for env in list_dirs(&espressif_dir.join("python_env")) {
+        items.push(item("python-env", file_name(&env), None, &env));
+    }
+}
+
+fn scan_cargo_bin(items: &mut Vec<InventoryItem>) {
+    let Some(bin_dir) = cargo_home().map(|home| home.join("bin")) else {
+        return;
+    };
+
+    for tool in CARGO_BIN_TOOLS {
+        #[cfg(unix)]
+        let path = bin_dir.join(tool);
+        #[cfg(windows)]
+        let path = bin_dir.join(format!("{}.exe", tool));
+
+        if path.exists() {
+            let version = get_tool_version(&path.to_string_lossy(), &["--version"], None);
+            items.push(item("cargo-tool", tool.to_string(), version, &path));
+        }
+    }
+}
+
+// Adopted installations outside of default locations are listed separately
+fn scan_adopted(adopted: &[ExistingInstallation], items: &mut Vec<InventoryItem>) {
+    for installation in adopted {
+        if items.iter().any(|item| item.path == installation.path) {
+            continue;
+        }
+        let path = Path::new(&installation.path);
+        if path.exists() {
+            items.push(item(
+                &format!("adopted-{}", installation.kind),
+                installation.kind.clone(),
+                installation.version.clone(),
+                path,
+            ));
+        }
+    }
+}
+
+pub fn collect_inventory(adopted: &[ExistingInstallation]) -> Vec<InventoryItem> {
+    let mut items = vec![];
+    scan_rustup_toolchains(&mut items);
+    scan_rustup_targets(&mut items);
+    scan_espressif(&mut items);
+    scan_cargo_bin(&mut items);
+    scan_adopted(adopted, &mut items);
+    items
+}
+
+// Command to list all components managed by esp-helm with their location and size
+#[tauri::command]
+pub async fn inventory(state_mutex: State<'_, Mutex<AppState>>) -> HelmResult<Vec<InventoryItem>> {
+    info!("Scanning installed components...");
+    let adopted = {
+        let state = state_mutex.lock().unwrap();
+        state.settings.adopted_installations.clone()
+    };
+    let items = tokio::task::spawn_blocking(move || collect_inventory(&adopted))
+        .await
+        .map_err(|e| HelmError::Other(format!("Inventory scan failed: {}", e)))?;
+    info!("Found {} components", items.len());
+    Ok(items)
+}
+
+// Directories holding all components of one kind, they are never removed as a whole
+const CONTAINER_DIRS: [&str; 7] = [
+    "bin",
+    "toolchains",
+    "esp-idf",
+    "tools",
+    "python_env",
+    "dist",
+    "registry",
+];
+
+fn managed_roots() -> Vec<PathBuf> {
+    [rustup_home(), cargo_home(), espressif_home()]
+        .into_iter()
+        .flatten()
+        .filter_map(|root| root.canonicalize().ok())
+        .collect()
+}
+
+// Resolves parent of the path, so symlinks are removed themselves instead of their target.
+// Relative components are rejected before resolving, lexical checks could be bypassed by them.
+fn resolve_path(path: &Path) -> Option<PathBuf> {
+    if path.components().any(|c| c == Component::ParentDir) {
+        return None;
+    }
+    let name = path.file_name()?;
+    let parent = path.parent()?.canonicalize().ok()?;
+    Some(parent.join(name))
+}
+
+// Only paths inside directories managed by esp-helm can be removed.
+// Roots have to be canonical, path has to be already resolved.
+fn is_managed_in(path: &Path, roots: &[PathBuf]) -> bool {
+    roots.iter().any(|root| {
+        path.starts_with(root)
+            && path != root
+            && !CONTAINER_DIRS
+                .iter()
+                .any(|container| path == root.join(container))
+    })
+}
+
+pub fn remove_path(path: &Path) -> HelmResult<()> {
+    let not_managed =
+        || HelmError::Validation(format!("{} is not managed by esp-helm", path.display()));
+    let path = resolve_path(path).ok_or_else(not_managed)?;
+    if !is_managed_in(&path, &managed_roots()) {
+        return Err(not_managed());
+    }
+
+    info!("Removing {}", path.display());
+    audit_path(AuditAction::Remove, &path);
+    // ESP-IDF trees contain files deeper than MAX_PATH
+    let path = long_path(&path);
+    if path.is_dir() && !path.is_symlink() {
+        std::fs::remove_dir_all(&path)?;
+    } else {
+        std::fs::remove_file(&path)?;
+    }
+    Ok(())
+}
+
+// Item which is exactly at the requested path, descendants of items are not matched
+fn find_item<'a>(items: &'a [InventoryItem], path: &Path) -> Option<&'a InventoryItem> {
+    let path = resolve_path(path)?;
+    items
+        .iter()
+        .find(|item| resolve_path(Path::new(&item.path)).as_ref() == Some(&path))
+}
+
+// Command to remove selected component from inventory.
+// Paths which are not listed in inventory are rejected.
+#[tauri::command]
+pub async fn remove_inventory_item(
+    state_mutex: State<'_, Mutex<AppState>>,
+    path: String,
+) -> HelmResult<String> {
+    let adopted = {
+        let state = state_mutex.lock().unwrap();
+        state.settings.adopted_installations.clone()
+    };
+    let items = tokio::task::spawn_blocking(move || collect_inventory(&adopted))
+        .await
+        .map_err(|e| HelmError::Other(format!("Inventory scan failed: {}", e)))?;
+    let Some(item) = find_item(&items, Path::new(&path)) else {
+        return Err(HelmError::Validation(format!(
+            "{} is not an installed component",
+            path
+        )));
+    };
+    remove_path(Path::new(&item.path))?;
+    Ok("Removed".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    // Fresh managed root with one installed tool, canonical so it matches resolved paths.
+    // The root is removed when the returned TempDir is dropped.
+    fn root() -> (TempDir, PathBuf) {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let tool = root.join("tools").join("openocd-esp32").join("v0.12.0");
+        std::fs::create_dir_all(tool).unwrap();
+        std::fs::create_dir_all(root.join("bin")).unwrap();
+        (dir, root)
+    }
+
+    fn managed(path: &Path, root: &Path) -> bool {
+        resolve_path(path).map_or(false, |path| is_managed_in(&path, &[root.to_path_buf()]))
+    }
+
+    #[test]
+    fn accepts_component_inside_root() {
+        let (_dir, root) = root();
+        let tool = root.join("tools").join("openocd-esp32").join("v0.12.0");
+        assert!(managed(&tool, &root));
+        assert!(managed(&root.join("tools").join("openocd-esp32"), &root));
+    }
+
+    #[test]
+    fn rejects_parent_dir_traversal() {
+        let (_dir, root) = root();
+        assert!(!managed(&root.join("..").join("Documents"), &root));
+        assert!(!managed(&root.join("tools").join("..").join(".."), &root));
+    }
+
+    #[test]
+    fn rejects_whole_roots() {
+        let (_dir, root) = root();
+        assert!(!managed(&root, &root));
+        assert!(!managed(&root.join("bin"), &root));
+        assert!(!managed(&root.join("tools"), &root));
+        assert!(!managed(root.parent().unwrap(), &root));
+    }
+
+    #[test]
+    fn finds_only_listed_items() {
+        let (_dir, root) = root();
+        let tool = root.join("tools").join("openocd-esp32").join("v0.12.0");
+        let items = vec![item(
+            "esp-idf-tool",
+            "openocd-esp32".into(),
+            Some("v0.12.0".into()),
+            &tool,
+        )];
+
+        assert!(find_item(&items, &tool).is_some());
+        assert!(find_item(&items, &root.join("tools").join("openocd-esp32")).is_none());
+        assert!(find_item(&items, &root.join("tools")).is_none());
+        assert!(find_item(&items, &tool.join("..").join("v0.12.0")).is_none());
+    }
+}
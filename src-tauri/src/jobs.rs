@@ -0,0 +1,170 @@
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use log::info;
+use tauri::{AppHandle, Manager, State};
+
+use crate::app_state::{AppState, JobId, JobInfo, JobStatus};
+use crate::env_snapshot::refresh_snapshot;
+use crate::error::{HelmError, HelmResult};
+use crate::events::{emit_event_all_scoped, HelmEvent};
+use crate::metrics::record_step;
+use crate::notifications::{job_category, notify_job_finished, NotificationCategory};
+use crate::operations::{begin_operation, OperationKind};
+use crate::tray::update_tray_tooltip;
+
+impl HelmEvent for JobInfo {
+    const NAMES: &'static [&'static str] = &["job-update"];
+}
+
+// Each job has own event channel "job-update/<id>", all updates are also sent to "job-update"
+fn emit_job_update(app: &AppHandle, job: &JobInfo) {
+    emit_event_all_scoped(app, job, job.id);
+    update_tray_tooltip(app);
+}
+
+fn update_job(app: &AppHandle, id: JobId, status: JobStatus) -> Option<JobInfo> {
+    let job = {
+        let state_mutex = app.state::<Mutex<AppState>>();
+        let mut state = state_mutex.lock().unwrap();
+        state.scheduler.set_status(id, status)
+    };
+    if let Some(job) = &job {
+        emit_job_update(app, job);
+    }
+    job
+}
+
+fn get_job_status(app: &AppHandle, id: JobId) -> Option<JobStatus> {
+    let state_mutex = app.state::<Mutex<AppState>>();
+    let state = state_mutex.lock().unwrap();
+    state.scheduler.status(id)
+}
+
+// Jobs are still queued or running, environment is not settled yet
+fn is_busy(app: &AppHandle) -> bool {
+    let state_mutex = app.state::<Mutex<AppState>>();
+    let state = state_mutex.lock().unwrap();
+    state
+        .scheduler
+        .jobs
+        .values()
+        .any(|job| matches!(job.status, JobStatus::Queued | JobStatus::Running))
+}
+
+// Wait until all dependencies finished. Returns error when any of them did not succeed.
+async fn wait_for_dependencies(app: &AppHandle, depends_on: &[JobId]) -> HelmResult<()> {
+    for dependency in depends_on {
+        wait_job(app, *dependency).await?;
+    }
+    Ok(())
+}
+
+// Queue a job which starts as soon as all jobs in depends_on are done
+pub fn spawn_job<F>(
+    app: &AppHandle,
+    kind: OperationKind,
+    name: &str,
+    depends_on: Vec<JobId>,
+    job: F,
+) -> JobId
+where
+    F: Future<Output = HelmResult<String>> + Send + 'static,
+{
+    let id = {
+        let state_mutex = app.state::<Mutex<AppState>>();
+        let mut state = state_mutex.lock().unwrap();
+        state.scheduler.add(name, depends_on.clone())
+    };
+    info!("Job {} queued: {}", id, name);
+
+    let job_app = app.clone();
+    let job_name = name.to_string();
+    // Pause and abort reach the job through its own operation token
+    let operation = begin_operation(app, kind, name);
+    let handle = tokio::spawn(async move {
+        if let Err(e) = wait_for_dependencies(&job_app, &depends_on).await {
+            info!("Job {} skipped: {}", id, e);
+            if let Some(job) = update_job(&job_app, id, JobStatus::Failed(e)) {
+                notify_job_finished(&job_app, &job, true);
+            }
+            return;
+        }
+
+        update_job(&job_app, id, JobStatus::Running);
+        let started = Instant::now();
+        let result = operation.scope(job).await;
+        record_step(&job_app, &job_name, started.elapsed(), &result);
+        let status = match result {
+            Ok(message) => JobStatus::Done(message),
+            Err(error) => JobStatus::Failed(error),
+        };
+        info!("Job {} finished", id);
+        if let Some(job) = update_job(&job_app, id, status) {
+            notify_job_finished(&job_app, &job, false);
+            if job_category(&job.name) == Some(NotificationCategory::Install) && !is_busy(&job_app)
+            {
+                refresh_snapshot(&job_app);
+            }
+        }
+    });
+
+    let job = {
+        let state_mutex = app.state::<Mutex<AppState>>();
+        let mut state = state_mutex.lock().unwrap();
+        // Job might be already finished, in that case the handle is not needed
+        if matches!(
+            state.scheduler.status(id),
+            Some(JobStatus::Queued) | Some(JobStatus::Running)
+        ) {
+            state.scheduler.handles.insert(id, handle.abort_handle());
+        }
+        state.scheduler.jobs.get(&id).cloned()
+    };
+    if let Some(job) = job {
+        emit_job_update(app, &job);
+    }
+
+    id
+}
+
+// Wait for job to finish and return its result
+pub async fn wait_job(app: &AppHandle, id: JobId) -> HelmResult<String> {
+    let poll_interval = Duration::from_millis(100);
+    loop {
+        match get_job_status(app, id) {
+            Some(JobStatus::Done(message)) => return Ok(message),
+            Some(JobStatus::Failed(error)) => return Err(error),
+            Some(JobStatus::Cancelled) => return Err(HelmError::Cancelled),
+            Some(_) => tokio::time::sleep(poll_interval).await,
+            None => return Err(HelmError::NotFound(format!("Job {}", id))),
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn list_jobs(state_mutex: State<'_, Mutex<AppState>>) -> HelmResult<Vec<JobInfo>> {
+    let state = state_mutex.lock().unwrap();
+    let mut jobs: Vec<JobInfo> = state.scheduler.jobs.values().cloned().collect();
+    jobs.sort_by_key(|job| job.id);
+    Ok(jobs)
+}
+
+#[tauri::command]
+pub async fn cancel_job(app: AppHandle, id: JobId) -> HelmResult<String> {
+    let job = {
+        let state_mutex = app.state::<Mutex<AppState>>();
+        let mut state = state_mutex.lock().unwrap();
+        state.scheduler.cancel(id)
+    };
+
+    match job {
+        Some(job) => {
+            info!("Job {} cancelled", id);
+            emit_job_update(&app, &job);
+            Ok("Cancelled".into())
+        }
+        None => Err(HelmError::Validation(format!("Job {} is not running", id))),
+    }
+}
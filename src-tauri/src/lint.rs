@@ -0,0 +1,187 @@
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+use tauri::{AppHandle, Window};
+
+use crate::cargo_tools::cargo_bin;
+use crate::error::{HelmError, HelmResult};
+use crate::events::{emit_event, HelmEvent};
+use crate::external_command::run_external_command_lines;
+
+#[derive(Clone, serde::Serialize, schemars::JsonSchema)]
+pub struct Diagnostic {
+    file: String,
+    line: u64,
+    column: u64,
+    end_line: u64,
+    end_column: u64,
+    // "error", "warning", "note" or "help"
+    severity: String,
+    // Lint name, e.g. "clippy::needless_return"
+    code: Option<String>,
+    message: String,
+    // Message as printed by cargo, including source snippet
+    rendered: Option<String>,
+}
+
+impl HelmEvent for Diagnostic {
+    const NAMES: &'static [&'static str] = &["lint-diagnostic"];
+}
+
+#[derive(serde::Serialize)]
+pub struct LintReport {
+    errors: usize,
+    warnings: usize,
+    diagnostics: Vec<Diagnostic>,
+}
+
+#[derive(serde::Serialize)]
+pub struct FormatReport {
+    // Files which were (or in check mode would be) changed by rustfmt
+    files: Vec<String>,
+}
+
+// Diagnostic from cargo "compiler-message", paths are made absolute
+fn parse_diagnostic(project: &Path, line: &str) -> Option<Diagnostic> {
+    let message: Value = serde_json::from_str(line).ok()?;
+    if message["reason"] != "compiler-message" {
+        return None;
+    }
+    let message = &message["message"];
+    // Summary messages like "aborting due to previous error" have no span
+    let span = message["spans"]
+        .as_array()?
+        .iter()
+        .find(|span| span["is_primary"].as_bool().unwrap_or(false))?;
+    let number = |value: &Value| value.as_u64().unwrap_or(0);
+
+    Some(Diagnostic {
+        file: project
+            .join(span["file_name"].as_str()?)
+            .to_string_lossy()
+            .to_string(),
+        line: number(&span["line_start"]),
+        column: number(&span["column_start"]),
+        end_line: number(&span["line_end"]),
+        end_column: number(&span["column_end"]),
+        severity: message["level"].as_str()?.to_string(),
+        code: message["code"]["code"].as_str().map(str::to_string),
+        message: message["message"].as_str()?.to_string(),
+        rendered: message["rendered"].as_str().map(str::to_string),
+    })
+}
+
+// Command to run clippy, diagnostics are streamed as they arrive. Project directory is
+// used as working directory, so rust-toolchain.toml and .cargo/config.toml apply.
+#[tauri::command]
+pub async fn lint_project(window: Window, app: AppHandle, path: String) -> HelmResult<LintReport> {
+    let project = PathBuf::from(&path);
+    let cargo = cargo_bin("cargo")?.to_string_lossy().to_string();
+    let args: Vec<String> = vec!["clippy".into(), "--message-format=json".into()];
+
+    let mut diagnostics: Vec<Diagnostic> = vec![];
+    let success = run_external_command_lines(&app, &project, &cargo, &args, |line| {
+        if let Some(diagnostic) = parse_diagnostic(&project, line) {
+            // Same diagnostic is reported for each target which includes the file
+            if !diagnostics.iter().any(|known| {
+                known.file == diagnostic.file
+                    && known.line == diagnostic.line
+                    && known.message == diagnostic.message
+            }) {
+                emit_event(&window, &diagnostic);
+                diagnostics.push(diagnostic);
+            }
+        }
+        false
+    })
+    .await?;
+
+    let count = |severity: &str| {
+        diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == severity)
+            .count()
+    };
+    let (errors, warnings) = (count("error"), count("warning"));
+    if !success && errors == 0 {
+        return Err(HelmError::Other("cargo clippy failed".into()));
+    }
+    Ok(LintReport {
+        errors,
+        warnings,
+        diagnostics,
+    })
+}
+
+// Command to run rustfmt on whole project, in check mode files are only listed
+#[tauri::command]
+pub async fn format_project(
+    app: AppHandle,
+    path: String,
+    check: Option<bool>,
+) -> HelmResult<FormatReport> {
+    let project = PathBuf::from(&path);
+    let check = check.unwrap_or(false);
+    let cargo = cargo_bin("cargo")?.to_string_lossy().to_string();
+    let mut args: Vec<String> = vec!["fmt".into(), "--all".into(), "--".into(), "-l".into()];
+    if check {
+        args.push("--check".into());
+    }
+
+    let mut files = vec![];
+    let success = run_external_command_lines(&app, &project, &cargo, &args, |line| {
+        let file = line.trim();
+        if Path::new(file).is_absolute() || file.ends_with(".rs") {
+            files.push(project.join(file).to_string_lossy().to_string());
+        }
+        false
+    })
+    .await?;
+
+    // rustfmt --check fails when any file is not formatted
+    if !success && !(check && !files.is_empty()) {
+        return Err(HelmError::Other("cargo fmt failed".into()));
+    }
+    Ok(FormatReport { files })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Output of `cargo clippy --message-format=json`, children and expansion trimmed
+    const CLIPPY_LINE: &str = r#"{"reason":"compiler-message","package_id":"blinky 0.1.0 (path+file:///home/user/blinky)","manifest_path":"/home/user/blinky/Cargo.toml","target":{"kind":["bin"],"crate_types":["bin"],"name":"blinky","src_path":"/home/user/blinky/src/main.rs","edition":"2021","doc":true,"doctest":false,"test":true},"message":{"rendered":"warning: unneeded `return` statement\n --> src/main.rs:12:5\n","$message_type":"diagnostic","children":[],"code":{"code":"clippy::needless_return","explanation":null},"level":"warning","message":"unneeded `return` statement","spans":[{"byte_end":301,"byte_start":292,"column_end":14,"column_start":5,"expansion":null,"file_name":"src/main.rs","is_primary":true,"label":null,"line_end":12,"line_start":12,"suggested_replacement":null,"suggestion_applicability":null,"text":[]}]}}"#;
+
+    #[test]
+    fn parses_clippy_warning() {
+        let project = Path::new("/home/user/blinky");
+        let diagnostic = parse_diagnostic(project, CLIPPY_LINE).unwrap();
+        assert_eq!(
+            diagnostic.file,
+            project.join("src/main.rs").to_string_lossy()
+        );
+        assert_eq!(
+            (
+                diagnostic.line,
+                diagnostic.column,
+                diagnostic.end_line,
+                diagnostic.end_column
+            ),
+            (12, 5, 12, 14)
+        );
+        assert_eq!(diagnostic.severity, "warning");
+        assert_eq!(diagnostic.code.as_deref(), Some("clippy::needless_return"));
+        assert_eq!(diagnostic.message, "unneeded `return` statement");
+        assert!(diagnostic.rendered.unwrap().starts_with("warning:"));
+    }
+
+    #[test]
+    fn skips_other_messages() {
+        let project = Path::new("/home/user/blinky");
+        let artifact = r#"{"reason":"compiler-artifact","package_id":"blinky 0.1.0"}"#;
+        let summary = r#"{"reason":"compiler-message","message":{"level":"error","message":"aborting due to 1 previous error","spans":[],"code":null,"rendered":"error: aborting\n"}}"#;
+        assert!(parse_diagnostic(project, artifact).is_none());
+        assert!(parse_diagnostic(project, summary).is_none());
+        assert!(parse_diagnostic(project, "   Compiling blinky v0.1.0").is_none());
+    }
+}
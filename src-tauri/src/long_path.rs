@@ -0,0 +1,165 @@
+use std::path::{Path, PathBuf};
+
+use crate::error::HelmResult;
+use crate::i18n::tr;
+use crate::inventory::{cargo_home, espressif_home, rustup_home};
+
+// Short root without spaces which is offered when default locations are problematic
+#[cfg(windows)]
+pub const SAFE_ROOT: &str = "C:\\esp";
+#[cfg(unix)]
+pub const SAFE_ROOT: &str = "/opt/esp";
+
+// Classic Windows limit, tools which are not long path aware fail above it
+const MAX_PATH: usize = 260;
+// Deepest files of ESP-IDF tools and components are about this far below tools directory
+const IDF_DEPTH_BUDGET: usize = 150;
+
+#[derive(Clone, Copy, PartialEq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PathSeverity {
+    // Install will likely fail
+    Error,
+    // Some tools are known to break, e.g. ESP-IDF scripts with spaces
+    Warning,
+}
+
+#[derive(serde::Serialize)]
+pub struct PathIssue {
+    pub path: String,
+    pub severity: PathSeverity,
+    pub message: String,
+}
+
+#[derive(serde::Serialize)]
+pub struct PathReport {
+    issues: Vec<PathIssue>,
+    // Only reported on Windows, None when state can not be read
+    long_paths_enabled: Option<bool>,
+    // Root to pass to set_install_root when there are issues
+    suggested_root: String,
+}
+
+// Prefix absolute path with \\?\ so Windows file APIs accept paths above MAX_PATH.
+// Verbatim paths are not normalized by Windows, so . and .. are resolved here.
+#[cfg(windows)]
+pub fn long_path(path: &Path) -> PathBuf {
+    use std::path::{Component, Prefix};
+
+    let mut components = path.components();
+    let prefix = match components.next() {
+        Some(Component::Prefix(prefix)) => prefix,
+        _ => return path.to_path_buf(),
+    };
+    let mut result = match prefix.kind() {
+        Prefix::Disk(drive) => PathBuf::from(format!("\\\\?\\{}:\\", drive as char)),
+        Prefix::UNC(server, share) => PathBuf::from(format!(
+            "\\\\?\\UNC\\{}\\{}\\",
+            server.to_string_lossy(),
+            share.to_string_lossy()
+        )),
+        // Already verbatim or device path
+        _ => return path.to_path_buf(),
+    };
+    for component in components {
+        match component {
+            Component::Normal(part) => result.push(part),
+            Component::ParentDir => {
+                result.pop();
+            }
+            _ => {}
+        }
+    }
+    result
+}
+
+#[cfg(unix)]
+pub fn long_path(path: &Path) -> PathBuf {
+    path.to_path_buf()
+}
+
+// Registry value HKLM\SYSTEM\CurrentControlSet\Control\FileSystem\LongPathsEnabled
+#[cfg(windows)]
+pub fn long_paths_enabled() -> Option<bool> {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+    let output = std::process::Command::new("reg")
+        .args([
+            "query",
+            "HKLM\\SYSTEM\\CurrentControlSet\\Control\\FileSystem",
+            "/v",
+            "LongPathsEnabled",
+        ])
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+        .ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let line = stdout
+        .lines()
+        .find(|line| line.contains("LongPathsEnabled"))?;
+    Some(line.trim_end().ends_with("0x1"))
+}
+
+#[cfg(unix)]
+pub fn long_paths_enabled() -> Option<bool> {
+    None
+}
+
+pub fn validate_install_path(path: &Path, long_paths_enabled: Option<bool>) -> Vec<PathIssue> {
+    let mut issues = vec![];
+    let display = path.to_string_lossy().to_string();
+    let mut issue = |severity, message: String| {
+        issues.push(PathIssue {
+            path: display.clone(),
+            severity,
+            message,
+        })
+    };
+    if !path.is_absolute() {
+        issue(PathSeverity::Error, tr("path-not-absolute", &[]));
+    }
+    if display.contains(char::is_whitespace) {
+        issue(PathSeverity::Warning, tr("path-has-spaces", &[]));
+    }
+    if !display.is_ascii() {
+        issue(PathSeverity::Warning, tr("path-non-ascii", &[]));
+    }
+    if cfg!(windows) && long_paths_enabled != Some(true) {
+        let length = display.chars().count();
+        if length + IDF_DEPTH_BUDGET > MAX_PATH {
+            let args = [
+                ("length", length.to_string()),
+                ("max", MAX_PATH.to_string()),
+            ];
+            issue(PathSeverity::Error, tr("path-too-long", &args));
+        }
+    }
+    issues
+}
+
+// Install locations which are used when path is not given
+fn default_paths() -> Vec<PathBuf> {
+    [rustup_home(), cargo_home(), espressif_home()]
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+// Command to check install root before installation, without path current locations are checked
+#[tauri::command]
+pub async fn check_install_path(path: Option<String>) -> HelmResult<PathReport> {
+    let paths = match path.filter(|path| !path.trim().is_empty()) {
+        Some(path) => vec![PathBuf::from(path)],
+        None => default_paths(),
+    };
+    let long_paths_enabled = long_paths_enabled();
+    Ok(PathReport {
+        issues: paths
+            .iter()
+            .flat_map(|path| validate_install_path(path, long_paths_enabled))
+            .collect(),
+        long_paths_enabled,
+        suggested_root: SAFE_ROOT.into(),
+    })
+}
@@ -3,23 +3,189 @@
 
 use std::sync::Mutex;
 
+mod adopt;
+use adopt::{adopt_existing_installation, detect_existing_installations};
+mod ansi;
 mod app_state;
-use app_state::{AppState, BuilderState};
-
+mod arch;
+mod atomic_file;
+mod audit;
+use audit::get_audit_log;
+mod backtrace;
+use app_state::AppState;
+use arch::get_host_architecture;
+mod baud;
+use baud::{detect_flash_baud, detect_monitor_baud, set_baud_override};
+mod build_cache;
+use build_cache::{build_cache_stats, setup_build_cache};
+mod cargo_tools;
+use cargo_tools::install_cargo_tool;
+
+mod changelog;
+use changelog::get_component_changelog;
+mod debug_session;
+use debug_session::{
+    debug_continue, debug_halt, debug_remove_breakpoint, debug_set_breakpoint, debug_step,
+    start_debug_session, stop_debug_session,
+};
+mod defender;
+use defender::{add_defender_exclusions, get_defender_status, plan_defender_exclusions};
+mod dfu;
+use dfu::{flash_dfu, list_dfu_devices};
+mod device;
+use device::{erase_flash, reset_device};
+mod diagnostics;
+use diagnostics::export_diagnostics;
+mod disclosure;
+use disclosure::get_component_disclosure;
 mod download;
+use download::{cancel_download, list_downloads};
 
+mod cleanup;
+use cleanup::{cleanup_stale_components, find_stale_components};
+mod components;
+use components::{add_component, get_component_details, remove_component, search_components};
 mod console;
+mod crates_io;
 use console::setup_logging;
+use crates_io::{add_dependency, search_esp_crates};
+mod deploy;
+use deploy::deploy;
+mod doctor;
+use doctor::run_doctor;
+mod env_snapshot;
+use env_snapshot::{acknowledge_environment_drift, check_drift_on_startup, get_environment_drift};
+mod error;
+use error::{HelmError, HelmResult};
+mod esp_clang;
+use esp_clang::{check_esp_clang, fix_libclang_path, install_esp_clang};
 mod esp_idf;
-use esp_idf::run_install_script;
+mod esptool;
+use esp_idf::{install_esp_idf_tools, run_install_script};
+use esptool::{esptool_chip_info, esptool_efuse_summary, esptool_flash_id, install_esptool};
+mod espup_progress;
+mod events;
+use events::{get_event_schema, start_hotplug_watch};
 mod external_command;
+mod extract;
+use extract::{download_and_extract_archive, extract};
+mod factory;
+use factory::provision_device;
+mod firmware;
+mod flash_backup;
+use external_command::answer_prompt;
+use firmware::inspect_firmware;
+use flash_backup::{backup_device, restore_device};
 mod flasher;
+use flasher::{flash_devices, get_reset_strategy, set_reset_strategy};
+mod git;
+use git::{check_git_support, clone_esp_idf, configure_git_for_path, install_git};
+mod github;
+use github::{get_github_rate_limit, get_latest_release, set_github_token};
+mod i18n;
+use i18n::{apply_language, get_languages, set_language};
+mod idf_project;
+use idf_project::import_idf_project;
+mod install_dir;
+use install_dir::{apply_install_root, get_install_paths, set_install_root, use_safe_install_root};
+mod install_plan;
+use install_plan::plan_installation;
+mod inventory;
+use inventory::{espressif_home, inventory, remove_inventory_item};
+mod jobs;
+use jobs::{cancel_job, list_jobs};
+mod lint;
+use lint::{format_project, lint_project};
+mod long_path;
+use long_path::check_install_path;
+mod memory_map;
+use memory_map::memory_map;
+mod metrics;
+use metrics::{preview_metrics_payload, set_telemetry};
 mod monitor;
+use monitor::{
+    get_monitor_scrollback, list_monitor_sessions, pause_monitor, resume_monitor,
+    send_monitor_input, set_esp_log, set_log_channels, set_monitor_filter, stop_monitor,
+};
+mod monitor_capture;
+use monitor_capture::{export_capture, start_monitor_capture, stop_monitor_capture};
+mod monitor_recording;
+use monitor_recording::{replay_capture, start_monitor_recording, stop_monitor_recording};
+mod network_discovery;
+use network_discovery::discover_network_devices;
+mod nix_export;
+use nix_export::export_nix_flake;
+mod notifications;
+use notifications::set_notification_category;
+mod operations;
+use operations::{begin_operation, list_operations, OperationKind, Operations};
 mod os;
+mod ota;
+use ota::{discover_ota_devices, upload_ota};
+mod package_manager;
+use package_manager::{install_host_dependencies, plan_host_dependencies};
+mod path_conflicts;
+use path_conflicts::get_path_analysis;
+mod plot;
+use plot::set_monitor_plot;
+mod portable;
+use portable::{create_portable_installation, get_portable_status};
+mod process_control;
+mod project_generator;
 use os::get_platform;
+use project_generator::generate_project;
+mod project_toolchain;
+use project_toolchain::{fix_project_toolchain, get_project_toolchain, set_project_toolchain};
+mod provision;
+use provision::{load_provision_config, provision_hosts, save_provision_config};
+mod release_metadata;
+use release_metadata::list_release_versions;
+mod repair;
+use repair::repair_installation;
+mod remediation;
 mod rust;
-use rust::{check_rust_support, install_rust_support};
-
+mod rustup;
+use rustup::get_rustup_status;
+mod sdkconfig;
+mod secure_boot;
+mod selftest;
+use selftest::{selftest, selftest_requested, start_selftest};
+mod services;
+use services::Services;
+mod settings;
+mod shell_path;
+mod size_analysis;
+use rust::{check_rust_support, install_rust_support, set_nightly_pin};
+use sdkconfig::{get_sdkconfig, update_sdkconfig};
+use secure_boot::{
+    burn_flash_encryption_key, burn_secure_boot_key, generate_flash_encryption_key,
+    generate_signing_key, plan_security, sign_image,
+};
+use settings::{get_settings, update_settings};
+use shell_path::apply_login_shell_path;
+use size_analysis::analyze_binary_size;
+
+mod system_install;
+use system_install::set_install_scope;
+mod tray;
+use tray::{handle_tray_event, handle_window_event, system_tray};
+mod uf2;
+use uf2::{convert_to_uf2, flash_uf2, list_uf2_drives};
+mod update_checks;
+use update_checks::{
+    check_for_updates, get_available_updates, set_update_check_interval, start_update_scheduler,
+};
+mod wsl;
+use wsl::{attach_usb_to_wsl, install_rust_support_wsl, list_usbipd_devices, list_wsl_distros};
+mod verify;
+use verify::verify_rust_installation;
+mod test_runner;
+mod toolchain_env;
+use test_runner::run_tests;
+mod wizard;
+mod wokwi;
+use wizard::{complete_wizard_step, get_wizard_state, reset_wizard};
+use wokwi::{generate_wokwi_config, launch_wokwi};
 mod zip_archiver;
 use zip_archiver::{unzip, zip_dir};
 
@@ -28,21 +194,28 @@ use tauri::{State, Window};
 use serialport::available_ports;
 use sysinfo::{DiskExt, System, SystemExt};
 
-// Create a custom Error that we can return in Results
-#[derive(Debug, thiserror::Error)]
-enum Error {
-    // Implement std::io::Error for our Error enum
-    #[error(transparent)]
-    Io(#[from] std::io::Error),
-    // Add a PoisonError, but we implement it manually later
-    // #[error("the mutex was poisoned")]
-    // PoisonError(String),
+#[tauri::command]
+async fn abort_build(operations: State<'_, Operations>) -> HelmResult<String> {
+    // Flashing and monitors have their own stop commands
+    operations
+        .abort(&[
+            OperationKind::Install,
+            OperationKind::Build,
+            OperationKind::Other,
+        ])
+        .await?;
+    Ok("ok".to_string())
 }
 
 #[tauri::command]
-async fn abort_build(state_mutex: State<'_, Mutex<AppState>>) -> Result<String, ()> {
-    let mut state = state_mutex.lock().unwrap();
-    state.builder = BuilderState::Abort;
+async fn pause_installation(operations: State<'_, Operations>) -> HelmResult<String> {
+    operations.pause(OperationKind::Install).await?;
+    Ok("ok".to_string())
+}
+
+#[tauri::command]
+async fn resume_installation(operations: State<'_, Operations>) -> HelmResult<String> {
+    operations.resume(OperationKind::Install).await?;
     Ok("ok".to_string())
 }
 
@@ -51,33 +224,14 @@ async fn abort_build(state_mutex: State<'_, Mutex<AppState>>) -> Result<String,
 async fn compress(
     window: Window,
     app: tauri::AppHandle,
-    state_mutex: State<'_, Mutex<AppState>>,
     source_path: String,
     target_path: String,
-) -> Result<String, ()> {
+) -> HelmResult<String> {
     let method = zip::CompressionMethod::Deflated;
 
-    {
-        let mut state = state_mutex.lock().unwrap();
-        state.builder = BuilderState::Running;
-    }
-
-    let result = zip_dir(
-        window,
-        app.clone(),
-        source_path.as_str(),
-        target_path.as_str(),
-        method,
-    );
-    {
-        let mut state = state_mutex.lock().unwrap();
-        state.builder = BuilderState::Idle;
-    }
-
-    match result {
-        Ok(_) => Ok("Success".to_string()),
-        Err(_) => Err(()),
-    }
+    let operation = begin_operation(&app, OperationKind::Other, "compress");
+    operation.sync_scope(|| zip_dir(window, source_path.as_str(), target_path.as_str(), method))?;
+    Ok("Success".to_string())
 }
 
 // Command to decompress a archive file into a directory.
@@ -85,25 +239,12 @@ async fn compress(
 async fn decompress(
     window: Window,
     app: tauri::AppHandle,
-    state_mutex: State<'_, Mutex<AppState>>,
     source_path: String,
     target_path: String,
-) -> Result<String, ()> {
-    {
-        let mut state = state_mutex.lock().unwrap();
-        state.builder = BuilderState::Running;
-    }
-
-    let result = unzip(window, app.clone(), source_path, target_path);
-    {
-        let mut state = state_mutex.lock().unwrap();
-        state.builder = BuilderState::Idle;
-    }
-
-    match result {
-        Ok(_) => Ok("Success".to_string()),
-        Err(_) => Err(()),
-    }
+) -> HelmResult<String> {
+    let operation = begin_operation(&app, OperationKind::Other, "decompress");
+    operation.sync_scope(|| unzip(window, source_path, target_path))?;
+    Ok("Success".to_string())
 }
 
 // Command to run install shell script of ESP-IDF
@@ -111,24 +252,13 @@ async fn decompress(
 async fn run_esp_idf_install_script(
     window: Window,
     app: tauri::AppHandle,
-    state_mutex: State<'_, Mutex<AppState>>,
     target_path: String,
-) -> Result<String, ()> {
-    {
-        let mut state = state_mutex.lock().unwrap();
-        state.builder = BuilderState::Running;
-    }
-
-    let result = run_install_script(window, app.clone(), target_path);
-    {
-        let mut state = state_mutex.lock().unwrap();
-        state.builder = BuilderState::Idle;
-    }
-
-    match result {
-        Ok(_) => Ok("Success".to_string()),
-        Err(_) => Err(()),
-    }
+) -> HelmResult<String> {
+    let operation = begin_operation(&app, OperationKind::Install, "ESP-IDF install script");
+    operation
+        .scope(run_install_script(window, app.clone(), target_path))
+        .await?;
+    Ok("Success".to_string())
 }
 
 // Command to download ESP-IDF to ZIP file
@@ -136,46 +266,36 @@ async fn run_esp_idf_install_script(
 async fn download_esp_idf(
     window: Window,
     app: tauri::AppHandle,
-    state_mutex: State<'_, Mutex<AppState>>,
     version: String,
     target_path: String,
-) -> Result<String, ()> {
-    {
-        let mut state = state_mutex.lock().unwrap();
-        state.builder = BuilderState::Running;
-    }
-
+) -> HelmResult<String> {
+    let operation = begin_operation(&app, OperationKind::Install, "ESP-IDF download");
     let download_handle =
-        tokio::spawn(esp_idf::download_esp_idf(window, app, version, target_path));
+        tokio::spawn(operation.scope(esp_idf::download_esp_idf(window, app, version, target_path)));
 
     let result = download_handle.await;
 
-    {
-        let mut state = state_mutex.lock().unwrap();
-        state.builder = BuilderState::Idle;
-    }
-
     match result {
-        Ok(result) => match result {
-            Ok(_) => Ok("Download finished successfully".to_string()),
-            Err(_) => Ok("Download failed".to_string()),
-        },
-        Err(_) => Ok("Download task panicked".to_string().to_string()),
+        Ok(result) => {
+            result?;
+            Ok("Download finished successfully".to_string())
+        }
+        Err(_) => Err(HelmError::Other("Download task panicked".to_string())),
     }
 }
 
 // Command to get list of ESP-IDF stored in users home directory as array of strings
 #[tauri::command]
-async fn get_esp_idf_list() -> Result<Vec<String>, ()> {
+async fn get_esp_idf_list() -> HelmResult<Vec<String>> {
     let mut esp_idf_list: Vec<String> = Vec::new();
 
-    let tools_dir = get_esp_idf_tools_dir().await.unwrap();
+    let tools_dir = get_esp_idf_tools_dir().await?;
     let path = format!("{}/{}", tools_dir, "esp-idf");
-    let paths = std::fs::read_dir(path).unwrap();
+    let paths = std::fs::read_dir(path)?;
 
     for path in paths {
-        let path = path.unwrap().path();
-        let path = path.to_str().unwrap().to_string();
+        let path = path?.path();
+        let path = path.to_string_lossy().to_string();
         esp_idf_list.push(path);
     }
 
@@ -184,120 +304,80 @@ async fn get_esp_idf_list() -> Result<Vec<String>, ()> {
 
 // Comand to get the current user home
 #[tauri::command]
-async fn get_user_home() -> Result<String, ()> {
+async fn get_user_home() -> HelmResult<String> {
     match dirs::home_dir() {
-        Some(path) => Ok(path.to_str().unwrap().to_string()),
-        None => Err(()),
+        Some(path) => Ok(path.to_string_lossy().to_string()),
+        None => Err(HelmError::NotFound("home directory".into())),
     }
 }
 
 // Command to get ESP-IDF Tools directory which is specific for each operating system.
 #[tauri::command]
-async fn get_esp_idf_tools_dir() -> Result<String, ()> {
-    #[cfg(unix)]
-    match dirs::home_dir() {
-        Some(path) => Ok(format!("{}/{}", path.to_str().unwrap(), ".espressif")),
-        None => Err(()),
-    }
-
-    #[cfg(windows)]
-    Ok("C:\\Espressif".to_string())
+async fn get_esp_idf_tools_dir() -> HelmResult<String> {
+    // IDF_TOOLS_PATH is set when custom install root is configured
+    espressif_home()
+        .map(|path| path.to_string_lossy().to_string())
+        .ok_or(HelmError::NotFound("home directory".into()))
 }
 
-use crate::monitor::monitor_port;
+use crate::monitor::monitor_session;
 
+// Monitors of different ports run at the same time, each in its own session
 #[tauri::command]
 async fn start_monitor(
     window: Window,
     app: tauri::AppHandle,
-    state_mutex: State<'_, Mutex<AppState>>,
     port: String,
-) -> Result<String, ()> {
-    {
-        let mut state = state_mutex.lock().unwrap();
-        state.builder = BuilderState::Running;
-    }
-
-    let monitor_handle = tokio::spawn(monitor_port(window, app, port));
-
-    let result = monitor_handle.await;
-
-    {
-        let mut state = state_mutex.lock().unwrap();
-        state.builder = BuilderState::Idle;
-    }
-
-    match result {
-        Ok(result) => match result {
-            Ok(_) => Ok("Monitoring finished successfully".to_string()),
-            Err(_) => Ok("Monitoring failed".to_string()),
-        },
-        Err(_) => Ok("Monitoring task panicked".to_string().to_string()),
-    }
-}
-
-#[tauri::command]
-async fn stop_monitor(state_mutex: State<'_, Mutex<AppState>>) -> Result<String, ()> {
-    let mut state = state_mutex.lock().unwrap();
-    state.builder = BuilderState::Abort;
-    Ok("ok".to_string())
+    elf: Option<String>,
+    project: Option<String>,
+) -> HelmResult<String> {
+    monitor_session(
+        window,
+        app,
+        port,
+        elf,
+        project.map(std::path::PathBuf::from),
+    )
+    .await?;
+    Ok("Monitoring finished successfully".to_string())
 }
 
-// async fn monitor_port(window: Window, app: tauri::AppHandle, port: String) -> Result<(), ()> {
-//   let state_mutex = app.get_state::<Mutex<AppState>>().unwrap();
-//   let state = state_mutex.lock().await;
-
-//   // code to open the port and monitor it, dispatching events to frontend...
-//   // check state.monitor occasionally to see if we need to stop monitoring...
-// }
-
 #[tauri::command]
 async fn start_flash(
     window: Window,
     app: tauri::AppHandle,
-    state_mutex: State<'_, Mutex<AppState>>,
     port: String,
     file_path: String,
     flash_offset: u32,
-) -> Result<String, ()> {
-    {
-        let mut state = state_mutex.lock().unwrap();
-        state.builder = BuilderState::Running;
-    }
-
-    let flasher_handle = tokio::spawn(flasher::flash_file(
+) -> HelmResult<String> {
+    let operation = begin_operation(&app, OperationKind::Flash, &format!("Flash {}", port));
+    let flasher_handle = tokio::spawn(operation.scope(flasher::flash_file(
         window,
         app,
         port,
         file_path,
         flash_offset,
-    ));
+    )));
 
     let result = flasher_handle.await;
 
-    {
-        let mut state = state_mutex.lock().unwrap();
-        state.builder = BuilderState::Idle;
-    }
-
     match result {
-        Ok(result) => match result {
-            Ok(_) => Ok("Flashing finished successfully".to_string()),
-            Err(_) => Ok("flashing failed".to_string()),
-        },
-        Err(_) => Ok("Flashing task panicked".to_string().to_string()),
+        Ok(result) => {
+            result?;
+            Ok("Flashing finished successfully".to_string())
+        }
+        Err(_) => Err(HelmError::Other("Flashing task panicked".to_string())),
     }
 }
 
 #[tauri::command]
-async fn stop_flash(state_mutex: State<'_, Mutex<AppState>>) -> Result<String, ()> {
-    let mut state = state_mutex.lock().unwrap();
-    state.builder = BuilderState::Abort;
+async fn stop_flash(operations: State<'_, Operations>) -> HelmResult<String> {
+    operations.abort(&[OperationKind::Flash]).await?;
     Ok("ok".to_string())
 }
 
 #[tauri::command]
-async fn get_disk_usage() -> Result<Vec<String>, ()> {
+async fn get_disk_usage() -> HelmResult<Vec<String>> {
     let mut sys = System::new_all();
     sys.refresh_all();
 
@@ -345,8 +425,20 @@ async fn get_connected_serial_devices() -> Vec<ConnectedPort> {
 }
 
 fn main() {
+    // Install root below takes precedence over PATH of login shell
+    apply_login_shell_path();
+    let state = AppState::default();
+    // Child processes inherit toolchain locations from environment
+    apply_install_root(&state.settings);
+    apply_language(state.settings.language.as_deref());
+
     tauri::Builder::default()
-        .manage(Mutex::new(AppState::default()))
+        .manage(Mutex::new(state))
+        .manage(Operations::start())
+        .manage(Services::system())
+        .system_tray(system_tray())
+        .on_system_tray_event(handle_tray_event)
+        .on_window_event(handle_window_event)
         .invoke_handler(tauri::generate_handler![
             compress,
             decompress,
@@ -357,18 +449,178 @@ fn main() {
             get_esp_idf_list,
             get_esp_idf_tools_dir,
             abort_build,
+            pause_installation,
+            resume_installation,
             run_esp_idf_install_script,
+            install_esp_idf_tools,
             start_flash,
             stop_flash,
             start_monitor,
             stop_monitor,
             check_rust_support,
+            set_nightly_pin,
             install_rust_support,
-            get_platform
+            get_platform,
+            check_git_support,
+            install_git,
+            configure_git_for_path,
+            run_doctor,
+            clone_esp_idf,
+            inventory,
+            remove_inventory_item,
+            find_stale_components,
+            cleanup_stale_components,
+            get_settings,
+            update_settings,
+            list_jobs,
+            cancel_job,
+            answer_prompt,
+            detect_existing_installations,
+            adopt_existing_installation,
+            get_host_architecture,
+            list_wsl_distros,
+            install_rust_support_wsl,
+            list_usbipd_devices,
+            attach_usb_to_wsl,
+            plan_host_dependencies,
+            install_host_dependencies,
+            check_esp_clang,
+            install_esp_clang,
+            fix_libclang_path,
+            install_cargo_tool,
+            get_wizard_state,
+            complete_wizard_step,
+            reset_wizard,
+            preview_metrics_payload,
+            set_telemetry,
+            verify_rust_installation,
+            import_idf_project,
+            get_sdkconfig,
+            update_sdkconfig,
+            search_components,
+            get_component_details,
+            add_component,
+            remove_component,
+            search_esp_crates,
+            add_dependency,
+            generate_project,
+            get_project_toolchain,
+            set_project_toolchain,
+            fix_project_toolchain,
+            set_monitor_filter,
+            pause_monitor,
+            resume_monitor,
+            get_monitor_scrollback,
+            list_monitor_sessions,
+            set_esp_log,
+            start_monitor_capture,
+            stop_monitor_capture,
+            start_monitor_recording,
+            stop_monitor_recording,
+            replay_capture,
+            set_monitor_plot,
+            export_capture,
+            deploy,
+            inspect_firmware,
+            backup_device,
+            restore_device,
+            discover_ota_devices,
+            upload_ota,
+            discover_network_devices,
+            plan_security,
+            generate_signing_key,
+            sign_image,
+            generate_flash_encryption_key,
+            burn_secure_boot_key,
+            burn_flash_encryption_key,
+            install_esptool,
+            esptool_chip_info,
+            esptool_flash_id,
+            esptool_efuse_summary,
+            generate_wokwi_config,
+            launch_wokwi,
+            run_tests,
+            lint_project,
+            format_project,
+            analyze_binary_size,
+            memory_map,
+            setup_build_cache,
+            build_cache_stats,
+            get_install_paths,
+            set_install_root,
+            get_portable_status,
+            create_portable_installation,
+            load_provision_config,
+            save_provision_config,
+            provision_hosts,
+            flash_devices,
+            provision_device,
+            send_monitor_input,
+            detect_flash_baud,
+            detect_monitor_baud,
+            set_baud_override,
+            list_dfu_devices,
+            flash_dfu,
+            list_uf2_drives,
+            convert_to_uf2,
+            flash_uf2,
+            get_reset_strategy,
+            set_reset_strategy,
+            start_debug_session,
+            stop_debug_session,
+            debug_set_breakpoint,
+            debug_remove_breakpoint,
+            debug_continue,
+            debug_halt,
+            debug_step,
+            set_log_channels,
+            get_rustup_status,
+            get_latest_release,
+            get_github_rate_limit,
+            set_github_token,
+            list_release_versions,
+            list_downloads,
+            cancel_download,
+            extract,
+            download_and_extract_archive,
+            check_install_path,
+            use_safe_install_root,
+            set_install_scope,
+            get_defender_status,
+            plan_defender_exclusions,
+            add_defender_exclusions,
+            get_path_analysis,
+            get_languages,
+            set_language,
+            get_event_schema,
+            set_notification_category,
+            get_environment_drift,
+            acknowledge_environment_drift,
+            get_available_updates,
+            check_for_updates,
+            set_update_check_interval,
+            get_component_changelog,
+            repair_installation,
+            plan_installation,
+            get_audit_log,
+            export_diagnostics,
+            get_component_disclosure,
+            export_nix_flake,
+            list_operations,
+            selftest,
+            erase_flash,
+            reset_device
         ])
         .setup(|app| {
             // Initialize the logging system
             setup_logging(app);
+            if selftest_requested() {
+                start_selftest(app);
+                return Ok(());
+            }
+            start_hotplug_watch(app.handle());
+            check_drift_on_startup(&app.handle());
+            start_update_scheduler(app.handle());
             Ok(())
         })
         .run(tauri::generate_context!())
@@ -0,0 +1,281 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use addr2line::object::{Object, ObjectSection, ObjectSymbol, SymbolKind};
+
+use crate::deploy::{elf_path, project_chip};
+use crate::error::{HelmError, HelmResult};
+
+const TOP_SYMBOLS: usize = 10;
+const TOP_STACK_FRAMES: usize = 20;
+
+#[derive(serde::Serialize)]
+pub struct SymbolSize {
+    name: String,
+    address: u64,
+    size: u64,
+}
+
+#[derive(serde::Serialize)]
+pub struct RegionUsage {
+    name: String,
+    start: u64,
+    capacity: u64,
+    used: u64,
+    top_symbols: Vec<SymbolSize>,
+}
+
+#[derive(serde::Serialize)]
+pub struct StackUsage {
+    function: String,
+    bytes: u64,
+}
+
+#[derive(serde::Serialize)]
+pub struct MemoryMap {
+    chip: String,
+    regions: Vec<RegionUsage>,
+    // Available only when built with -Z emit-stack-sizes
+    stack_usage: Vec<StackUsage>,
+}
+
+// Name, start and end of memory regions as used by esp-hal linker scripts
+fn memory_regions(chip: &str) -> Option<&'static [(&'static str, u64, u64)]> {
+    match chip {
+        "esp32" => Some(&[
+            ("IRAM", 0x4008_0000, 0x400A_0000),
+            ("DRAM", 0x3FFB_0000, 0x4000_0000),
+            ("Flash code", 0x400D_0000, 0x4040_0000),
+            ("Flash data", 0x3F40_0000, 0x3F80_0000),
+            ("RTC fast", 0x3FF8_0000, 0x3FF8_2000),
+            ("RTC slow", 0x5000_0000, 0x5000_2000),
+        ]),
+        "esp32s2" => Some(&[
+            ("IRAM", 0x4002_0000, 0x4007_0000),
+            ("DRAM", 0x3FFB_0000, 0x4000_0000),
+            ("Flash code", 0x4008_0000, 0x4080_0000),
+            ("Flash data", 0x3F00_0000, 0x3F3F_0000),
+            ("RTC fast", 0x4007_0000, 0x4007_2000),
+            ("RTC slow", 0x5000_0000, 0x5000_2000),
+        ]),
+        "esp32s3" => Some(&[
+            ("IRAM", 0x4037_0000, 0x403E_0000),
+            ("DRAM", 0x3FC8_8000, 0x3FD0_0000),
+            ("Flash code", 0x4200_0000, 0x4400_0000),
+            ("Flash data", 0x3C00_0000, 0x3E00_0000),
+            ("RTC fast", 0x600F_E000, 0x6010_0000),
+            ("RTC slow", 0x5000_0000, 0x5000_2000),
+        ]),
+        "esp32c2" => Some(&[
+            ("IRAM", 0x4037_C000, 0x403C_0000),
+            ("DRAM", 0x3FCA_0000, 0x3FCE_0000),
+            ("Flash code", 0x4200_0000, 0x4240_0000),
+            ("Flash data", 0x3C00_0000, 0x3C40_0000),
+        ]),
+        "esp32c3" => Some(&[
+            ("IRAM", 0x4037_C000, 0x403E_0000),
+            ("DRAM", 0x3FC8_0000, 0x3FCE_0000),
+            ("Flash code", 0x4200_0000, 0x4280_0000),
+            ("Flash data", 0x3C00_0000, 0x3C80_0000),
+            ("RTC fast", 0x5000_0000, 0x5000_2000),
+        ]),
+        // Instruction and data bus share the same addresses on C6 and H2
+        "esp32c6" => Some(&[
+            ("RAM", 0x4080_0000, 0x4088_0000),
+            ("Flash", 0x4200_0000, 0x4300_0000),
+            ("LP RAM", 0x5000_0000, 0x5000_4000),
+        ]),
+        "esp32h2" => Some(&[
+            ("RAM", 0x4080_0000, 0x4085_0000),
+            ("Flash", 0x4200_0000, 0x4300_0000),
+            ("LP RAM", 0x5000_0000, 0x5000_1000),
+        ]),
+        _ => None,
+    }
+}
+
+fn read_uleb128(data: &[u8], offset: &mut usize) -> Option<u64> {
+    let mut value = 0u64;
+    let mut shift = 0;
+    loop {
+        let byte = *data.get(*offset)?;
+        *offset += 1;
+        value |= ((byte & 0x7F) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+        shift += 7;
+        if shift >= 64 {
+            return None;
+        }
+    }
+}
+
+// .stack_sizes contains 32 bit function address followed by ULEB128 stack size
+fn parse_stack_sizes(data: &[u8], functions: &HashMap<u64, String>) -> Vec<StackUsage> {
+    let mut usage = vec![];
+    let mut offset = 0;
+    while offset + 4 <= data.len() {
+        let address = u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap()) as u64;
+        offset += 4;
+        let Some(bytes) = read_uleb128(data, &mut offset) else {
+            break;
+        };
+        let function = functions
+            .get(&address)
+            .cloned()
+            .unwrap_or_else(|| format!("0x{:08x}", address));
+        usage.push(StackUsage { function, bytes });
+    }
+    usage.sort_by(|a, b| b.bytes.cmp(&a.bytes));
+    usage.truncate(TOP_STACK_FRAMES);
+    usage
+}
+
+fn build_memory_map(chip: &str, elf: &str) -> HelmResult<MemoryMap> {
+    let regions =
+        memory_regions(chip).ok_or(HelmError::Validation(format!("Unknown chip {}", chip)))?;
+    let data = std::fs::read(elf)?;
+    let file = addr2line::object::File::parse(data.as_slice())
+        .map_err(|e| HelmError::Validation(format!("Invalid ELF {}: {}", elf, e)))?;
+
+    let mut regions: Vec<RegionUsage> = regions
+        .iter()
+        .map(|(name, start, end)| RegionUsage {
+            name: name.to_string(),
+            start: *start,
+            capacity: end - start,
+            used: 0,
+            top_symbols: vec![],
+        })
+        .collect();
+    let region_of = |address: u64| {
+        regions
+            .iter()
+            .position(|region| address >= region.start && address < region.start + region.capacity)
+    };
+
+    let mut used = vec![0; regions.len()];
+    for section in file.sections() {
+        if section.address() == 0 {
+            continue;
+        }
+        if let Some(index) = region_of(section.address()) {
+            used[index] += section.size();
+        }
+    }
+
+    let mut symbols: Vec<Vec<SymbolSize>> = regions.iter().map(|_| vec![]).collect();
+    let mut functions = HashMap::new();
+    for symbol in file.symbols() {
+        let Ok(name) = symbol.name() else {
+            continue;
+        };
+        let name = format!("{:#}", rustc_demangle::demangle(name));
+        if symbol.kind() == SymbolKind::Text {
+            functions.insert(symbol.address(), name.clone());
+        }
+        if symbol.size() == 0 {
+            continue;
+        }
+        if let Some(index) = region_of(symbol.address()) {
+            symbols[index].push(SymbolSize {
+                name,
+                address: symbol.address(),
+                size: symbol.size(),
+            });
+        }
+    }
+
+    for ((region, used), mut symbols) in regions.iter_mut().zip(used).zip(symbols) {
+        symbols.sort_by(|a, b| b.size.cmp(&a.size));
+        symbols.truncate(TOP_SYMBOLS);
+        region.used = used;
+        region.top_symbols = symbols;
+    }
+
+    let stack_usage = file
+        .section_by_name(".stack_sizes")
+        .and_then(|section| section.data().ok())
+        .map(|data| parse_stack_sizes(data, &functions))
+        .unwrap_or_default();
+
+    Ok(MemoryMap {
+        chip: chip.to_string(),
+        regions,
+        stack_usage,
+    })
+}
+
+// Command to report usage of chip memory regions by the last build of project
+#[tauri::command]
+pub async fn memory_map(project: String, release: Option<bool>) -> HelmResult<MemoryMap> {
+    let project = PathBuf::from(project);
+    let chip =
+        project_chip(&project).ok_or(HelmError::NotFound("Chip feature in Cargo.toml".into()))?;
+    let elf = elf_path(&project, release.unwrap_or(true))?;
+    if !elf.exists() {
+        return Err(HelmError::NotFound(format!(
+            "{}, build the project first",
+            elf.display()
+        )));
+    }
+    build_memory_map(&chip, &elf.to_string_lossy())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_uleb128() {
+        let data = [0x02, 0xE5, 0x8E, 0x26, 0x80];
+        let mut offset = 0;
+        assert_eq!(read_uleb128(&data, &mut offset), Some(2));
+        assert_eq!(read_uleb128(&data, &mut offset), Some(624_485));
+        assert_eq!(offset, 4);
+        // Continuation bit set on last byte
+        assert_eq!(read_uleb128(&data, &mut offset), None);
+    }
+
+    #[test]
+    fn parses_stack_sizes_largest_first() {
+        let mut data = vec![];
+        data.extend(0x4200_0010u32.to_le_bytes());
+        data.push(0x20);
+        data.extend(0x4200_0100u32.to_le_bytes());
+        data.extend([0x80, 0x02]);
+        // Truncated entry is ignored
+        data.extend([0x00, 0x01]);
+        let functions = HashMap::from([(0x4200_0010, "main".to_string())]);
+
+        let usage = parse_stack_sizes(&data, &functions);
+        assert_eq!(usage.len(), 2);
+        assert_eq!(usage[0].function, "0x42000100");
+        assert_eq!(usage[0].bytes, 256);
+        assert_eq!(usage[1].function, "main");
+        assert_eq!(usage[1].bytes, 32);
+    }
+
+    #[test]
+    fn memory_regions_do_not_overlap() {
+        for chip in crate::verify::CHIPS {
+            let Some(regions) = memory_regions(chip) else {
+                continue;
+            };
+            for (index, (name, start, end)) in regions.iter().enumerate() {
+                assert!(start < end, "{} {}", chip, name);
+                for (other, other_start, other_end) in &regions[index + 1..] {
+                    assert!(
+                        end <= other_start || other_end <= start,
+                        "{} {} overlaps {}",
+                        chip,
+                        name,
+                        other
+                    );
+                }
+            }
+        }
+        assert!(memory_regions("esp8266").is_none());
+    }
+}
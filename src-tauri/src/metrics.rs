@@ -0,0 +1,111 @@
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use log::info;
+use tauri::{AppHandle, Manager, State};
+
+use crate::app_state::AppState;
+use crate::error::{HelmError, HelmResult};
+use crate::portable::app_config_dir;
+use crate::settings::save_settings;
+
+// Result of single install step. Only error kind is stored, messages might contain paths.
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct MetricRecord {
+    step: String,
+    success: bool,
+    duration_ms: u64,
+    error_kind: Option<String>,
+}
+
+// Everything which would leave the machine, user can inspect it before sharing
+#[derive(serde::Serialize)]
+pub struct MetricsPayload {
+    os: String,
+    arch: String,
+    app_version: String,
+    records: Vec<MetricRecord>,
+}
+
+fn metrics_path() -> Option<PathBuf> {
+    app_config_dir().map(|dir| dir.join("metrics.json"))
+}
+
+fn load_records() -> Vec<MetricRecord> {
+    metrics_path()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_records(records: &[MetricRecord]) -> HelmResult<()> {
+    let path = metrics_path().ok_or(HelmError::NotFound("config directory".into()))?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let content = serde_json::to_string_pretty(records)
+        .map_err(|e| HelmError::Other(format!("Failed to serialize metrics: {}", e)))?;
+    std::fs::write(&path, content)?;
+    Ok(())
+}
+
+fn is_telemetry_enabled(app: &AppHandle) -> bool {
+    let state_mutex = app.state::<Mutex<AppState>>();
+    let state = state_mutex.lock().unwrap();
+    state.settings.telemetry_enabled
+}
+
+// Store result of install step, nothing is recorded without opt-in
+pub fn record_step<T>(app: &AppHandle, step: &str, duration: Duration, result: &HelmResult<T>) {
+    if !is_telemetry_enabled(app) {
+        return;
+    }
+
+    let mut records = load_records();
+    records.push(MetricRecord {
+        step: step.to_string(),
+        success: result.is_ok(),
+        duration_ms: duration.as_millis() as u64,
+        error_kind: result.as_ref().err().map(|e| e.kind().to_string()),
+    });
+    if let Err(e) = save_records(&records) {
+        info!("Unable to record metrics: {}", e);
+    }
+}
+
+// Command to show collected metrics exactly as they would be sent
+#[tauri::command]
+pub async fn preview_metrics_payload(app: AppHandle) -> HelmResult<MetricsPayload> {
+    Ok(MetricsPayload {
+        os: std::env::consts::OS.into(),
+        arch: std::env::consts::ARCH.into(),
+        app_version: app.package_info().version.to_string(),
+        records: load_records(),
+    })
+}
+
+// Command to opt in or out, opting out also removes already collected metrics
+#[tauri::command]
+pub async fn set_telemetry(
+    state_mutex: State<'_, Mutex<AppState>>,
+    enabled: bool,
+) -> HelmResult<String> {
+    let mut state = state_mutex.lock().unwrap();
+    let mut settings = state.settings.clone();
+    settings.telemetry_enabled = enabled;
+    save_settings(&settings)?;
+    state.settings = settings;
+
+    if !enabled {
+        if let Some(path) = metrics_path().filter(|path| path.exists()) {
+            std::fs::remove_file(path)?;
+        }
+    }
+    info!("Telemetry enabled: {}", enabled);
+    Ok(format!(
+        "Telemetry {}",
+        if enabled { "enabled" } else { "disabled" }
+    ))
+}
@@ -1,40 +1,612 @@
-use tauri::{Manager, Window};
+use log::info;
+use tauri::{Manager, State, Window};
 
-use crate::app_state::{AppState, BuilderState};
+use crate::ansi::strip_ansi;
+use crate::app_state::AppState;
+use crate::backtrace::Symbols;
+use crate::baud::monitor_baud;
+use crate::cargo_tools::cargo_bin;
+use crate::deploy::project_chip;
+use crate::error::{HelmError, HelmResult};
+use crate::events::{emit_event, emit_event_scoped, HelmEvent};
+use crate::external_command::run_external_command_lines;
+use crate::monitor_capture::MonitorCapture;
+use crate::monitor_recording::Recording;
+use crate::operations::{
+    begin_operation, current_token, is_aborted, with_token, OperationId, OperationKind, Operations,
+};
+use crate::plot::{emit_sample, parse_plot_line, PlotSample};
+use crate::settings::save_settings;
 use espflash::interface::Interface;
+use regex::Regex;
 use serialport::available_ports;
 use serialport::SerialPortInfo;
+use std::collections::{BTreeMap, VecDeque};
+use std::future::Future;
 use std::io;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::sync::Mutex;
 use std::{io::ErrorKind, time::Duration};
 
-fn normalized<I>(iter: I) -> impl Iterator<Item = u8>
-where
-    I: Iterator<Item = u8>,
-{
-    iter.map(|byte| match byte {
-        0..=31 | 127 => b'?', // replace control characters with '?'
-        _ => byte,
-    })
+// Lines kept for scrollback and for replay after pause
+const SCROLLBACK_CAPACITY: usize = 5000;
+// Line without newline is flushed when it grows over this size
+const MAX_LINE_LENGTH: usize = 1024;
+
+const LOG_LEVELS: [&str; 5] = ["error", "warn", "info", "debug", "verbose"];
+
+pub type SessionId = u64;
+
+#[derive(Clone, serde::Serialize, schemars::JsonSchema)]
+pub struct MonitorLine {
+    session: SessionId,
+    id: u64,
+    timestamp: u128,
+    // Line without ANSI colors
+    text: String,
+    level: Option<String>,
+    tag: Option<String>,
+    message: String,
+}
+
+impl HelmEvent for MonitorLine {
+    const NAMES: &'static [&'static str] = &["monitor-line"];
+}
+
+// Plain text of monitor lines and status messages, "pct" is kept for the frontend
+#[derive(Clone, serde::Serialize, schemars::JsonSchema)]
+pub struct MonitorMessage {
+    pct: String,
 }
 
-fn handle_serial(buff: &[u8], window: &Window) {
-    // println!("Serial: {:?}", buff);
-    // Normalize the buffer content
-    let text: Vec<u8> = normalized(buff.iter().copied()).collect();
-    let text = String::from_utf8_lossy(&text).to_string();
+impl HelmEvent for MonitorMessage {
+    const NAMES: &'static [&'static str] = &["monitor-event"];
+}
 
-    // Emit the line to the frontend
-    let payload = Payload {
-        pct: text.to_string().to_string(),
+fn emit_message(window: &Window, message: &str) {
+    let payload = MonitorMessage {
+        pct: message.to_string(),
     };
-    window.emit("monitor-event", payload).unwrap();
+    emit_event(window, &payload);
 }
 
-fn is_abort_state(app: tauri::AppHandle) -> bool {
-    let state_mutex = app.state::<Mutex<AppState>>();
-    let state = state_mutex.lock().unwrap();
-    matches!(state.builder, BuilderState::Abort)
+// Source of firmware logs, channels of project are tried in order until one works
+#[derive(Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogChannel {
+    // Real-Time Transfer through probe-rs over USB-Serial-JTAG or JTAG probe, UART stays free
+    Rtt,
+    Uart,
+}
+
+#[derive(Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct MonitorFilter {
+    pub regex: Option<String>,
+    // Most verbose level shown, lines without level are always shown
+    pub level: Option<String>,
+    // Only lines with these ESP-IDF tags are shown, empty list shows all
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+// Monitor of one port, filtering is done in backend to keep the frontend responsive
+pub struct MonitorSession {
+    id: SessionId,
+    port: String,
+    operation: OperationId,
+    running: bool,
+    filter: MonitorFilter,
+    regex: Option<Regex>,
+    paused: bool,
+    // Id of the last line emitted before pause
+    paused_after: u64,
+    next_line_id: u64,
+    scrollback: VecDeque<MonitorLine>,
+    // Set while serial port is open, bytes waiting to be written to it
+    serial_open: bool,
+    input: VecDeque<Vec<u8>>,
+    pub recording: Option<Recording>,
+    // Numeric lines are parsed to samples on plot channel
+    plot: bool,
+}
+
+#[derive(Clone, serde::Serialize)]
+pub struct MonitorSessionInfo {
+    id: SessionId,
+    port: String,
+    event: String,
+    running: bool,
+    paused: bool,
+    plot: bool,
+    lines: usize,
+}
+
+// Monitor part of AppState. Stopped session keeps its scrollback until a new monitor is
+// started on the same port.
+#[derive(Default)]
+pub struct MonitorState {
+    // Filter given for all sessions, also used by sessions started later
+    filter: MonitorFilter,
+    regex: Option<Regex>,
+    plot: bool,
+    next_session_id: SessionId,
+    sessions: BTreeMap<String, MonitorSession>,
+    pub capture: Option<MonitorCapture>,
+}
+
+pub fn unix_millis() -> u128 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0)
+}
+
+fn level_index(level: &str) -> Option<usize> {
+    LOG_LEVELS.iter().position(|l| *l == level)
+}
+
+fn esp_idf_level(letter: &str) -> Option<&'static str> {
+    match letter {
+        "E" => Some("error"),
+        "W" => Some("warn"),
+        "I" => Some("info"),
+        "D" => Some("debug"),
+        "V" => Some("verbose"),
+        _ => None,
+    }
+}
+
+// ESP-IDF: "I (1234) wifi: message"
+// esp-println: "INFO - message"
+fn parse_log_line(text: &str) -> (Option<String>, Option<String>, String) {
+    if let Some((letter, rest)) = text.split_once(" (") {
+        if let (Some(level), Some((_, rest))) = (esp_idf_level(letter), rest.split_once(") ")) {
+            return match rest.split_once(": ") {
+                Some((tag, message)) => (
+                    Some(level.into()),
+                    Some(tag.to_string()),
+                    message.to_string(),
+                ),
+                None => (Some(level.into()), None, rest.to_string()),
+            };
+        }
+    }
+
+    if let Some((level, message)) = text.split_once(" - ") {
+        let level = level.trim().to_lowercase();
+        let level = if level == "trace" {
+            "verbose".into()
+        } else {
+            level
+        };
+        if level_index(&level).is_some() {
+            return (Some(level), None, message.to_string());
+        }
+    }
+
+    (None, None, text.to_string())
+}
+
+impl MonitorFilter {
+    fn matches(&self, line: &MonitorLine, regex: Option<&Regex>) -> bool {
+        if let (Some(max), Some(level)) = (&self.level, &line.level) {
+            if let (Some(max), Some(level)) = (level_index(max), level_index(level)) {
+                if level > max {
+                    return false;
+                }
+            }
+        }
+        if !self.tags.is_empty() {
+            match &line.tag {
+                Some(tag) if self.tags.contains(tag) => {}
+                _ => return false,
+            }
+        }
+        regex
+            .map(|regex| regex.is_match(&line.text))
+            .unwrap_or(true)
+    }
+}
+
+impl MonitorSession {
+    fn info(&self) -> MonitorSessionInfo {
+        MonitorSessionInfo {
+            id: self.id,
+            port: self.port.clone(),
+            // Lines of this session are emitted also on this channel
+            event: format!("{}/{}", MonitorLine::NAMES[0], self.id),
+            running: self.running,
+            paused: self.paused,
+            plot: self.plot,
+            lines: self.scrollback.len(),
+        }
+    }
+
+    // Store line in scrollback, raw line goes to recording
+    fn push(&mut self, raw: &str, timestamp: u128) -> MonitorLine {
+        if let Some(recording) = &mut self.recording {
+            if let Err(e) = recording.write_line(timestamp, raw) {
+                info!("Monitor recording failed: {}", e);
+                self.recording = None;
+            }
+        }
+        let text = strip_ansi(raw);
+        let (level, tag, message) = parse_log_line(&text);
+        self.next_line_id += 1;
+        let line = MonitorLine {
+            session: self.id,
+            id: self.next_line_id,
+            timestamp,
+            text,
+            level,
+            tag,
+            message,
+        };
+
+        if self.scrollback.len() >= SCROLLBACK_CAPACITY {
+            self.scrollback.pop_front();
+        }
+        self.scrollback.push_back(line.clone());
+        line
+    }
+
+    fn visible(&self, line: &MonitorLine) -> bool {
+        !self.paused && self.filter.matches(line, self.regex.as_ref())
+    }
+
+    fn filtered(&self, after: u64) -> Vec<MonitorLine> {
+        self.scrollback
+            .iter()
+            .filter(|line| line.id > after && self.filter.matches(line, self.regex.as_ref()))
+            .cloned()
+            .collect()
+    }
+
+    fn pause(&mut self) {
+        self.paused = true;
+        self.paused_after = self.next_line_id;
+    }
+
+    // Lines received during pause
+    fn resume(&mut self) -> Vec<MonitorLine> {
+        self.paused = false;
+        self.filtered(self.paused_after)
+    }
+}
+
+impl MonitorState {
+    // Monitor on port which is still running is not replaced
+    fn open_session(&mut self, port: &str, operation: OperationId) -> HelmResult<SessionId> {
+        if self
+            .sessions
+            .get(port)
+            .is_some_and(|session| session.running)
+        {
+            return Err(HelmError::Validation(format!(
+                "Monitor on {} is already running",
+                port
+            )));
+        }
+        self.next_session_id += 1;
+        let id = self.next_session_id;
+        self.sessions.insert(
+            port.to_string(),
+            MonitorSession {
+                id,
+                port: port.to_string(),
+                operation,
+                running: true,
+                filter: self.filter.clone(),
+                regex: self.regex.clone(),
+                paused: false,
+                paused_after: 0,
+                next_line_id: 0,
+                scrollback: VecDeque::new(),
+                serial_open: false,
+                input: VecDeque::new(),
+                recording: None,
+                plot: self.plot,
+            },
+        );
+        Ok(id)
+    }
+
+    fn session(&self, port: &str) -> HelmResult<&MonitorSession> {
+        self.sessions
+            .get(port)
+            .ok_or_else(|| HelmError::NotFound(format!("Monitor on {}", port)))
+    }
+
+    pub fn session_mut(&mut self, port: &str) -> HelmResult<&mut MonitorSession> {
+        self.sessions
+            .get_mut(port)
+            .ok_or_else(|| HelmError::NotFound(format!("Monitor on {}", port)))
+    }
+
+    // Sessions affected by command, all of them when port is not given
+    fn selected(&mut self, port: Option<&str>) -> HelmResult<Vec<&mut MonitorSession>> {
+        match port {
+            Some(port) => Ok(vec![self.session_mut(port)?]),
+            None => Ok(self.sessions.values_mut().collect()),
+        }
+    }
+
+    fn scrollback(&self, port: Option<&str>) -> HelmResult<Vec<MonitorLine>> {
+        match port {
+            Some(port) => Ok(self.session(port)?.filtered(0)),
+            None => {
+                let mut lines: Vec<MonitorLine> = self
+                    .sessions
+                    .values()
+                    .flat_map(|session| session.filtered(0))
+                    .collect();
+                lines.sort_by_key(|line| line.timestamp);
+                Ok(lines)
+            }
+        }
+    }
+
+    pub fn set_plot(&mut self, port: Option<&str>, enabled: bool) -> HelmResult<()> {
+        if port.is_none() {
+            self.plot = enabled;
+        }
+        for session in self.selected(port)? {
+            session.plot = enabled;
+        }
+        Ok(())
+    }
+
+    // Returns line of the session when it should be emitted right away
+    fn push(&mut self, port: &str, raw: &str, timestamp: u128) -> Option<Pushed> {
+        let several = self.sessions.len() > 1;
+        let session = self.sessions.get_mut(port)?;
+        let line = session.push(raw, timestamp);
+
+        // Capture contains everything, filter affects only the view
+        if let Some(capture) = &mut self.capture {
+            // Lines are prefixed with port when several monitors write to the same capture
+            let text = if several {
+                format!("{}: {}", port, line.text)
+            } else {
+                line.text.clone()
+            };
+            if let Err(e) = capture.write_line(line.timestamp, &text) {
+                info!("Monitor capture failed: {}", e);
+                self.capture = None;
+            }
+        }
+
+        // Plot is not affected by filter and pause of the view
+        let sample = session
+            .plot
+            .then(|| parse_plot_line(session.id, timestamp, &line.text))
+            .flatten();
+        let line = session.visible(&line).then_some(line);
+        Some(Pushed { line, sample })
+    }
+}
+
+// What a pushed line produces for the frontend
+struct Pushed {
+    line: Option<MonitorLine>,
+    sample: Option<PlotSample>,
+}
+
+// Session is marked stopped also when monitor task is aborted or panics
+struct SessionGuard {
+    app: tauri::AppHandle,
+    port: String,
+    id: SessionId,
+}
+
+impl Drop for SessionGuard {
+    fn drop(&mut self) {
+        let state_mutex = self.app.state::<Mutex<AppState>>();
+        let recording = {
+            let mut state = state_mutex.lock().unwrap();
+            match state.monitor.sessions.get_mut(&self.port) {
+                Some(session) if session.id == self.id => {
+                    session.running = false;
+                    session.serial_open = false;
+                    session.input.clear();
+                    session.recording.take()
+                }
+                _ => None,
+            }
+        };
+        if let Some(recording) = recording {
+            match recording.finish() {
+                Ok(message) => info!("{}", message),
+                Err(e) => info!("Monitor recording failed: {}", e),
+            }
+        }
+    }
+}
+
+// Runs work feeding lines of session on port as its own operation, so monitors of other
+// ports and installations keep running when it is stopped
+pub async fn run_session(
+    app: &tauri::AppHandle,
+    port: String,
+    work: impl Future<Output = HelmResult<()>> + Send + 'static,
+) -> HelmResult<()> {
+    let operation = begin_operation(app, OperationKind::Monitor, &format!("Monitor {}", port));
+    let id = {
+        let state_mutex = app.state::<Mutex<AppState>>();
+        let mut state = state_mutex.lock().unwrap();
+        state.monitor.open_session(&port, operation.id())?
+    };
+    info!("Monitor session {} started on {}", id, port);
+    let guard = SessionGuard {
+        app: app.clone(),
+        port,
+        id,
+    };
+    let handle = tokio::spawn(operation.scope(work));
+    let result = handle.await;
+    drop(guard);
+    result.map_err(|_| HelmError::Other("Monitoring task panicked".to_string()))?
+}
+
+pub async fn monitor_session(
+    window: Window,
+    app: tauri::AppHandle,
+    port: String,
+    elf: Option<String>,
+    project: Option<PathBuf>,
+) -> HelmResult<()> {
+    let work = monitor_project(window, app.clone(), port.clone(), elf, project);
+    run_session(&app, port, work).await
+}
+
+// Session channel carries lines of one port, plain channels are kept for single monitor views
+fn emit_line(window: &Window, line: &MonitorLine) {
+    emit_event_scoped(window, line, line.session);
+    // Plain text event is kept for simple consumers
+    emit_message(window, &format!("{}\n", line.text));
+}
+
+// Decoded backtrace frames are inserted right after the line containing the addresses
+fn handle_line(
+    raw: &[u8],
+    window: &Window,
+    app: &tauri::AppHandle,
+    port: &str,
+    symbols: Option<&Symbols>,
+) {
+    let raw = String::from_utf8_lossy(raw);
+    let raw = raw.trim_end_matches('\r');
+    let frames = symbols
+        .map(|symbols| symbols.decode_line(raw))
+        .unwrap_or_default();
+    let texts = std::iter::once(raw).chain(frames.iter().map(String::as_str));
+    monitor_lines(window, app, port, texts, unix_millis());
+}
+
+// Add lines to session on port and emit the visible ones
+pub fn monitor_lines<'a>(
+    window: &Window,
+    app: &tauri::AppHandle,
+    port: &str,
+    texts: impl IntoIterator<Item = &'a str>,
+    timestamp: u128,
+) {
+    let pushed: Vec<Pushed> = {
+        let state_mutex = app.state::<Mutex<AppState>>();
+        let mut state = state_mutex.lock().unwrap();
+        texts
+            .into_iter()
+            .filter_map(|text| state.monitor.push(port, text, timestamp))
+            .collect()
+    };
+    for pushed in &pushed {
+        if let Some(line) = &pushed.line {
+            emit_line(window, line);
+        }
+        if let Some(sample) = &pushed.sample {
+            emit_sample(window, sample);
+        }
+    }
+}
+
+// esp-println with defmt-espflash feature starts each frame with 0xFF 0x00,
+// rzcobs encoded frame is terminated by 0x00
+const DEFMT_FRAME_START: u8 = 0xFF;
+const DEFMT_FRAME_DELIMITER: u8 = 0x00;
+
+fn load_defmt_table(elf: &str) -> HelmResult<Option<defmt_decoder::Table>> {
+    let bytes = std::fs::read(elf)?;
+    defmt_decoder::Table::parse(&bytes)
+        .map_err(|e| HelmError::Validation(format!("Invalid defmt data in {}: {}", elf, e)))
+}
+
+// Separates defmt frames from plain text in serial stream
+struct DefmtStream<'a> {
+    decoder: Box<dyn defmt_decoder::StreamDecoder + 'a>,
+    start_pending: bool,
+    in_frame: bool,
+    frame: Vec<u8>,
+}
+
+impl<'a> DefmtStream<'a> {
+    fn new(table: &'a defmt_decoder::Table) -> Self {
+        DefmtStream {
+            decoder: table.new_stream_decoder(),
+            start_pending: false,
+            in_frame: false,
+            frame: vec![],
+        }
+    }
+
+    // Returns decoded log lines, bytes outside of frames are appended to text
+    fn process(&mut self, bytes: &[u8], text: &mut Vec<u8>) -> Vec<String> {
+        let mut lines = vec![];
+        for &byte in bytes {
+            if self.in_frame {
+                self.frame.push(byte);
+                if byte == DEFMT_FRAME_DELIMITER {
+                    self.decoder.received(&self.frame);
+                    self.frame.clear();
+                    self.in_frame = false;
+                    lines.extend(self.decode());
+                }
+            } else if self.start_pending {
+                self.start_pending = false;
+                if byte == DEFMT_FRAME_DELIMITER {
+                    self.in_frame = true;
+                } else {
+                    text.extend_from_slice(&[DEFMT_FRAME_START, byte]);
+                }
+            } else if byte == DEFMT_FRAME_START {
+                self.start_pending = true;
+            } else {
+                text.push(byte);
+            }
+        }
+        lines
+    }
+
+    fn decode(&mut self) -> Vec<String> {
+        let mut lines = vec![];
+        loop {
+            match self.decoder.decode() {
+                Ok(frame) => {
+                    let level = frame
+                        .level()
+                        .map(|level| level.as_str().to_uppercase())
+                        .unwrap_or_else(|| "INFO".into());
+                    lines.push(format!("{} - {}", level, frame.display_message()));
+                }
+                Err(defmt_decoder::DecodeError::UnexpectedEof) => break,
+                Err(defmt_decoder::DecodeError::Malformed) => {
+                    info!("Malformed defmt frame skipped");
+                    break;
+                }
+            }
+        }
+        lines
+    }
+}
+
+// Split received bytes to lines, incomplete line stays in pending buffer
+fn handle_serial(
+    buff: &[u8],
+    pending: &mut Vec<u8>,
+    window: &Window,
+    app: &tauri::AppHandle,
+    port: &str,
+    symbols: Option<&Symbols>,
+) {
+    pending.extend_from_slice(buff);
+    while let Some(position) = pending.iter().position(|byte| *byte == b'\n') {
+        let line: Vec<u8> = pending.drain(..=position).collect();
+        handle_line(&line[..line.len() - 1], window, app, port, symbols);
+    }
+    if pending.len() > MAX_LINE_LENGTH {
+        handle_line(pending, window, app, port, symbols);
+        pending.clear();
+    }
 }
 
 pub fn get_serial_port_info(port_name: &str) -> io::Result<SerialPortInfo> {
@@ -47,75 +619,513 @@ pub fn get_serial_port_info(port_name: &str) -> io::Result<SerialPortInfo> {
     Err(io::Error::new(io::ErrorKind::NotFound, "Port not found"))
 }
 
-#[derive(Clone, serde::Serialize)]
-struct Payload {
-    pct: String,
+// Without explicit ELF use the one flashed to this port
+fn resolve_elf(app: &tauri::AppHandle, port: &str, elf: Option<String>) -> Option<String> {
+    elf.or_else(|| {
+        let state_mutex = app.state::<Mutex<AppState>>();
+        let state = state_mutex.lock().unwrap();
+        state
+            .flashed_elfs
+            .get(port)
+            .filter(|elf| Path::new(elf).exists())
+            .cloned()
+    })
+}
+
+fn project_channels(app: &tauri::AppHandle, project: Option<&Path>) -> Vec<LogChannel> {
+    let state_mutex = app.state::<Mutex<AppState>>();
+    let state = state_mutex.lock().unwrap();
+    project
+        .and_then(|project| {
+            state
+                .settings
+                .log_channels
+                .get(&project.to_string_lossy().to_string())
+        })
+        .cloned()
+        .unwrap_or_else(|| vec![LogChannel::Uart])
+}
+
+// probe-rs attach reads RTT control block location from ELF and prints channels to stdout,
+// defmt channel is decoded by probe-rs. Returns false when RTT could not be attached.
+async fn monitor_rtt(
+    window: &Window,
+    app: &tauri::AppHandle,
+    port: &str,
+    chip: &str,
+    elf: &str,
+) -> HelmResult<bool> {
+    let probe_rs = cargo_bin("probe-rs")?.to_string_lossy().to_string();
+    let symbols = Symbols::load(elf).ok();
+    let args: Vec<String> = vec!["attach".into(), "--chip".into(), chip.into(), elf.into()];
+    let dir = Path::new(elf)
+        .parent()
+        .map(PathBuf::from)
+        .unwrap_or_default();
+    emit_message(window, "Starting RTT monitoring");
+    run_external_command_lines(app, &dir, &probe_rs, &args, |line| {
+        handle_line(line.as_bytes(), window, app, port, symbols.as_ref());
+        false
+    })
+    .await
 }
 
-pub async fn monitor_port(window: Window, app: tauri::AppHandle, port: String) -> Result<(), ()> {
-    // let state_mutex = app.get_state::<Mutex<AppState>>().unwrap();
+// Monitor using log channels configured for project, RTT falls back to next channel
+// when there is no probe, chip is unknown or firmware does not set up RTT
+async fn monitor_project(
+    window: Window,
+    app: tauri::AppHandle,
+    port: String,
+    elf: Option<String>,
+    project: Option<PathBuf>,
+) -> HelmResult<()> {
+    let elf = resolve_elf(&app, &port, elf);
+    let chip = project.as_deref().and_then(project_chip);
+    for channel in project_channels(&app, project.as_deref()) {
+        match channel {
+            LogChannel::Uart => return monitor_port(window, app, port, elf).await,
+            LogChannel::Rtt => {
+                let (Some(chip), Some(elf)) = (&chip, &elf) else {
+                    info!("RTT needs chip and ELF, skipping");
+                    continue;
+                };
+                match monitor_rtt(&window, &app, &port, chip, elf).await {
+                    Ok(true) => return Ok(()),
+                    Ok(false) => info!("RTT attach failed"),
+                    Err(HelmError::Cancelled) => return Ok(()),
+                    Err(e) => info!("RTT unavailable: {}", e),
+                }
+                emit_message(&window, "RTT unavailable, trying next log channel");
+            }
+        }
+    }
+    Err(HelmError::NotFound("Working log channel".into()))
+}
+
+// Command to set log channels of project in fallback order, empty list restores UART only
+#[tauri::command]
+pub async fn set_log_channels(
+    state_mutex: State<'_, Mutex<AppState>>,
+    project: String,
+    channels: Vec<LogChannel>,
+) -> HelmResult<String> {
+    let mut state = state_mutex.lock().unwrap();
+    let mut settings = state.settings.clone();
+    if channels.is_empty() {
+        settings.log_channels.remove(&project);
+    } else {
+        settings.log_channels.insert(project.clone(), channels);
+    }
+    save_settings(&settings)?;
+    state.settings = settings;
+    Ok(format!("Log channels of {} updated", project))
+}
 
-    // create necessary ConnectArgs and Config
-    // let connect_args = ConnectArgs {
-    //   port: Some(port.co),
-    //   baud: Some(115200),
-    //   no_stub: false,
-    // };
+// When elf contains defmt table, defmt frames are decoded, otherwise output is shown as text.
+// Code addresses in output are resolved to functions and source lines using the elf.
+// Serial reads block, so the port is read on worker thread.
+pub async fn monitor_port(
+    window: Window,
+    app: tauri::AppHandle,
+    port: String,
+    elf: Option<String>,
+) -> HelmResult<()> {
+    let token = current_token();
+    tokio::task::spawn_blocking(move || with_token(token, || read_port(&window, &app, &port, elf)))
+        .await
+        .map_err(|_| HelmError::Other("Monitoring task panicked".to_string()))?
+}
 
+fn read_port(
+    window: &Window,
+    app: &tauri::AppHandle,
+    port: &str,
+    elf: Option<String>,
+) -> HelmResult<()> {
     let dtr = Some(1);
     let rts = Some(0);
 
-    let port_info = get_serial_port_info(port.as_str()).unwrap();
+    let port_info = get_serial_port_info(port)?;
 
-    let mut serial = Interface::new(&port_info, dtr, rts).unwrap();
-    serial.serial_port_mut().set_baud_rate(115200).unwrap();
+    let mut serial = Interface::new(&port_info, dtr, rts)
+        .map_err(|e| HelmError::Io(format!("Failed to open {}: {}", port, e)))?;
+    serial.serial_port_mut().set_baud_rate(monitor_baud(port))?;
     serial
         .serial_port_mut()
-        .set_timeout(Duration::from_millis(5))
-        .unwrap();
-    //  let port_x = UsbPortInfo {
-    //   vid: 0,
-    //   pid: 0,
-    //   serial_number: None,
-    //   manufacturer: None,
-    //   product: None,
-    // };
-    // create Flasher object
-    // let flasher = Flasher::connect(
-    //   interface,
-    //   port_x,
-    //   Some(115200),
-    //   false,
-    // ).unwrap();
+        .set_timeout(Duration::from_millis(5))?;
 
     let mut buff = [0; 1024];
-    // let mut serial = flasher.into_interface();
+    let mut pending: Vec<u8> = vec![];
+
+    let elf = resolve_elf(app, port, elf);
+    let symbols = elf.as_deref().and_then(|elf| match Symbols::load(elf) {
+        Ok(symbols) => Some(symbols),
+        Err(e) => {
+            info!("Backtraces will not be decoded: {}", e);
+            None
+        }
+    });
 
-    let payload = Payload {
-        pct: format!("Starting monitoring").to_string(),
+    let table = match &elf {
+        Some(elf) => load_defmt_table(elf)?,
+        None => None,
     };
-    window.emit("monitor-event", payload).unwrap();
+    if elf.is_some() && table.is_none() {
+        info!("No defmt table in ELF, showing raw output");
+    }
+    let mut defmt = table.as_ref().map(DefmtStream::new);
+
+    {
+        let state_mutex = app.state::<Mutex<AppState>>();
+        let mut state = state_mutex.lock().unwrap();
+        if let Ok(session) = state.monitor.session_mut(port) {
+            session.serial_open = true;
+            session.input.clear();
+        }
+    }
+
+    emit_message(window, "Starting monitoring");
     loop {
+        let input: Vec<Vec<u8>> = {
+            let state_mutex = app.state::<Mutex<AppState>>();
+            let mut state = state_mutex.lock().unwrap();
+            state
+                .monitor
+                .session_mut(port)
+                .map(|session| session.input.drain(..).collect())
+                .unwrap_or_default()
+        };
+        for data in input {
+            serial.serial_port_mut().write_all(&data)?;
+        }
+
         let read_count = match serial.serial_port_mut().read(&mut buff) {
             Ok(count) => Ok(count),
             Err(e) if e.kind() == ErrorKind::TimedOut => Ok(0),
             Err(e) if e.kind() == ErrorKind::Interrupted => continue,
             err => err,
-        }
-        .unwrap();
+        }?;
 
         if read_count > 0 {
-            handle_serial(&buff[0..read_count], &window);
+            match &mut defmt {
+                Some(defmt) => {
+                    let mut text = vec![];
+                    for line in defmt.process(&buff[0..read_count], &mut text) {
+                        handle_line(line.as_bytes(), window, app, port, symbols.as_ref());
+                    }
+                    handle_serial(&text, &mut pending, window, app, port, symbols.as_ref());
+                }
+                None => handle_serial(
+                    &buff[0..read_count],
+                    &mut pending,
+                    window,
+                    app,
+                    port,
+                    symbols.as_ref(),
+                ),
+            }
         }
 
-        if is_abort_state(app.clone()) {
-            let payload = Payload {
-                pct: format!("Monitoring stopped").to_string(),
-            };
-            window.emit("monitor-event", payload).unwrap();
+        if is_aborted() {
+            emit_message(window, "Monitoring stopped");
             break;
         }
     }
 
+    let state_mutex = app.state::<Mutex<AppState>>();
+    if let Ok(session) = state_mutex.lock().unwrap().monitor.session_mut(port) {
+        session.serial_open = false;
+    }
     Ok(())
 }
+
+// "\n" in input is replaced by line ending expected by firmware
+fn translate_line_endings(data: &str, line_ending: &str) -> HelmResult<Vec<u8>> {
+    let ending = match line_ending {
+        "none" => "",
+        "lf" => "\n",
+        "cr" => "\r",
+        "crlf" => "\r\n",
+        _ => {
+            return Err(HelmError::Validation(format!(
+                "Unknown line ending {}",
+                line_ending
+            )))
+        }
+    };
+    Ok(data
+        .replace("\r\n", "\n")
+        .replace('\n', ending)
+        .into_bytes())
+}
+
+// Command to write to serial port of running monitor, e.g. commands for ESP-IDF console.
+// Local echo adds the input to monitor output for firmware which does not echo itself.
+#[tauri::command]
+pub async fn send_monitor_input(
+    window: Window,
+    state_mutex: State<'_, Mutex<AppState>>,
+    port: String,
+    data: String,
+    line_ending: Option<String>,
+    echo: Option<bool>,
+) -> HelmResult<String> {
+    let bytes = translate_line_endings(&data, line_ending.as_deref().unwrap_or("crlf"))?;
+    let echoed = {
+        let mut state = state_mutex.lock().unwrap();
+        let session = state.monitor.session_mut(&port)?;
+        if !session.serial_open {
+            return Err(HelmError::NotFound(format!("Monitor on {}", port)));
+        }
+        session.input.push_back(bytes);
+        if echo.unwrap_or(false) {
+            let text = format!("> {}", data.trim_end_matches(['\r', '\n']));
+            state
+                .monitor
+                .push(&port, &text, unix_millis())
+                .and_then(|pushed| pushed.line)
+        } else {
+            None
+        }
+    };
+    if let Some(line) = echoed {
+        emit_line(&window, &line);
+    }
+    Ok(format!("{} bytes queued", data.len()))
+}
+
+// Command to change filter of monitor on port, or of all monitors when port is not given.
+// Returns filtered scrollback so the view can be redrawn.
+#[tauri::command]
+pub async fn set_monitor_filter(
+    state_mutex: State<'_, Mutex<AppState>>,
+    filter: MonitorFilter,
+    port: Option<String>,
+) -> HelmResult<Vec<MonitorLine>> {
+    let regex = match &filter.regex {
+        Some(pattern) if !pattern.is_empty() => Some(
+            Regex::new(pattern)
+                .map_err(|e| HelmError::Validation(format!("Invalid regex: {}", e)))?,
+        ),
+        _ => None,
+    };
+    if let Some(level) = &filter.level {
+        if level_index(level).is_none() {
+            return Err(HelmError::Validation(format!(
+                "Unknown log level {}",
+                level
+            )));
+        }
+    }
+
+    let mut state = state_mutex.lock().unwrap();
+    if port.is_none() {
+        state.monitor.filter = filter.clone();
+        state.monitor.regex = regex.clone();
+    }
+    for session in state.monitor.selected(port.as_deref())? {
+        session.filter = filter.clone();
+        session.regex = regex.clone();
+    }
+    state.monitor.scrollback(port.as_deref())
+}
+
+// Command to stop emitting lines, they are still stored in scrollback
+#[tauri::command]
+pub async fn pause_monitor(
+    state_mutex: State<'_, Mutex<AppState>>,
+    port: Option<String>,
+) -> HelmResult<String> {
+    let mut state = state_mutex.lock().unwrap();
+    let sessions = state.monitor.selected(port.as_deref())?;
+    let count = sessions.len();
+    sessions.into_iter().for_each(MonitorSession::pause);
+    Ok(format!("{} monitors paused", count))
+}
+
+// Command to continue emitting, lines received during pause are emitted first
+#[tauri::command]
+pub async fn resume_monitor(
+    window: Window,
+    state_mutex: State<'_, Mutex<AppState>>,
+    port: Option<String>,
+) -> HelmResult<String> {
+    let lines: Vec<Vec<MonitorLine>> = {
+        let mut state = state_mutex.lock().unwrap();
+        state
+            .monitor
+            .selected(port.as_deref())?
+            .into_iter()
+            .map(|session| session.resume())
+            .collect()
+    };
+    for lines in &lines {
+        for line in lines {
+            emit_line(&window, line);
+        }
+    }
+    Ok(format!("{} monitors resumed", lines.len()))
+}
+
+// Command to get scrollback of monitor on port, without port lines of all monitors ordered by time
+#[tauri::command]
+pub async fn get_monitor_scrollback(
+    state_mutex: State<'_, Mutex<AppState>>,
+    port: Option<String>,
+) -> HelmResult<Vec<MonitorLine>> {
+    let state = state_mutex.lock().unwrap();
+    state.monitor.scrollback(port.as_deref())
+}
+
+// Command to list running monitors and stopped ones which still have scrollback
+#[tauri::command]
+pub async fn list_monitor_sessions(
+    state_mutex: State<'_, Mutex<AppState>>,
+) -> HelmResult<Vec<MonitorSessionInfo>> {
+    let state = state_mutex.lock().unwrap();
+    Ok(state
+        .monitor
+        .sessions
+        .values()
+        .map(MonitorSession::info)
+        .collect())
+}
+
+// Command to stop monitor on port, without port all monitors are stopped
+#[tauri::command]
+pub async fn stop_monitor(
+    state_mutex: State<'_, Mutex<AppState>>,
+    operations: State<'_, Operations>,
+    port: Option<String>,
+) -> HelmResult<String> {
+    let Some(port) = port else {
+        operations.abort(&[OperationKind::Monitor]).await?;
+        return Ok("ok".to_string());
+    };
+    let operation = {
+        let state = state_mutex.lock().unwrap();
+        let session = state.monitor.session(&port)?;
+        if !session.running {
+            return Err(HelmError::Validation(format!(
+                "Monitor on {} is not running",
+                port
+            )));
+        }
+        session.operation
+    };
+    operations.abort_one(operation).await?;
+    Ok(format!("Monitor on {} stopped", port))
+}
+
+// Command to set ESP_LOG passed to subsequent builds, esp-println reads it at compile time
+#[tauri::command]
+pub async fn set_esp_log(
+    state_mutex: State<'_, Mutex<AppState>>,
+    level: Option<String>,
+) -> HelmResult<String> {
+    let mut state = state_mutex.lock().unwrap();
+    let mut settings = state.settings.clone();
+    settings.esp_log = level.filter(|level| !level.is_empty());
+    save_settings(&settings)?;
+    state.settings = settings;
+    Ok("ESP_LOG updated".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(state: &mut MonitorState) -> &mut MonitorSession {
+        state.open_session("/dev/ttyUSB0", 1).unwrap();
+        state.session_mut("/dev/ttyUSB0").unwrap()
+    }
+
+    fn filter(level: Option<&str>, tags: &[&str]) -> MonitorFilter {
+        MonitorFilter {
+            regex: None,
+            level: level.map(|level| level.to_string()),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn parses_esp_idf_line() {
+        assert_eq!(
+            parse_log_line("I (123) wifi: connected"),
+            (Some("info".into()), Some("wifi".into()), "connected".into())
+        );
+        assert_eq!(
+            parse_log_line("E (4567) boot failed"),
+            (Some("error".into()), None, "boot failed".into())
+        );
+        assert_eq!(
+            parse_log_line("X (1) tag: message"),
+            (None, None, "X (1) tag: message".into())
+        );
+    }
+
+    #[test]
+    fn parses_esp_println_line() {
+        assert_eq!(
+            parse_log_line("INFO - Hello world"),
+            (Some("info".into()), None, "Hello world".into())
+        );
+        assert_eq!(
+            parse_log_line("TRACE - polling"),
+            (Some("verbose".into()), None, "polling".into())
+        );
+        assert_eq!(parse_log_line("a - b"), (None, None, "a - b".into()));
+    }
+
+    #[test]
+    fn filters_by_level_and_tag() {
+        let mut state = MonitorState::default();
+        let session = session(&mut state);
+        let error = session.push("E (1) wifi: failed", 0);
+        let debug = session.push("D (2) wifi: scanning", 0);
+        let other = session.push("I (3) http: request", 0);
+        let plain = session.push("plain output", 0);
+
+        let info = filter(Some("info"), &[]);
+        assert!(info.matches(&error, None));
+        assert!(!info.matches(&debug, None));
+        assert!(info.matches(&plain, None));
+
+        let wifi = filter(None, &["wifi"]);
+        assert!(wifi.matches(&debug, None));
+        assert!(!wifi.matches(&other, None));
+        assert!(!wifi.matches(&plain, None));
+
+        let regex = Regex::new("scan").unwrap();
+        assert!(!MonitorFilter::default().matches(&error, Some(&regex)));
+        assert!(MonitorFilter::default().matches(&debug, Some(&regex)));
+    }
+
+    #[test]
+    fn resume_replays_lines_after_pause() {
+        let mut state = MonitorState::default();
+        let session = session(&mut state);
+        session.push("before", 0);
+        session.pause();
+        let during = session.push("during", 0);
+        assert!(!session.visible(&during));
+        session.push("also during", 0);
+
+        let replayed: Vec<String> = session.resume().into_iter().map(|l| l.text).collect();
+        assert_eq!(replayed, vec!["during", "also during"]);
+        let after = session.push("after", 0);
+        assert!(session.visible(&after));
+    }
+
+    #[test]
+    fn scrollback_evicts_oldest_lines() {
+        let mut state = MonitorState::default();
+        let session = session(&mut state);
+        for i in 0..SCROLLBACK_CAPACITY + 10 {
+            session.push(&format!("line {}", i), 0);
+        }
+        assert_eq!(session.scrollback.len(), SCROLLBACK_CAPACITY);
+        assert_eq!(session.scrollback.front().unwrap().text, "line 10");
+        assert_eq!(session.scrollback.front().unwrap().id, 11);
+    }
+}
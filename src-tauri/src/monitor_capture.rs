@@ -0,0 +1,162 @@
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use log::info;
+use tauri::State;
+use zip::write::FileOptions;
+
+use crate::app_state::AppState;
+use crate::error::{HelmError, HelmResult};
+
+// Rotated files are named <path>.1 (newest) .. <path>.N (oldest)
+const MAX_ROTATED_FILES: usize = 5;
+const DEFAULT_MAX_SIZE: u64 = 10 * 1024 * 1024;
+
+// Serial output written to disk while monitor runs
+pub struct MonitorCapture {
+    path: PathBuf,
+    max_size: u64,
+    file: File,
+    written: u64,
+}
+
+fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}", index));
+    path.with_file_name(name)
+}
+
+// Format milliseconds since epoch as UTC "YYYY-MM-DD HH:MM:SS.mmm"
+fn format_timestamp(millis: u128) -> String {
+    let secs = (millis / 1000) as i64;
+    let days = secs.div_euclid(86_400);
+    let time = secs.rem_euclid(86_400);
+
+    // Civil date from days since 1970-01-01, Howard Hinnant's algorithm
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}.{:03}",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60,
+        millis % 1000
+    )
+}
+
+impl MonitorCapture {
+    fn open(path: PathBuf, max_size: u64) -> HelmResult<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let written = file.metadata()?.len();
+        Ok(MonitorCapture {
+            path,
+            max_size,
+            file,
+            written,
+        })
+    }
+
+    fn rotate(&mut self) -> HelmResult<()> {
+        for index in (1..MAX_ROTATED_FILES).rev() {
+            let from = rotated_path(&self.path, index);
+            if from.exists() {
+                std::fs::rename(&from, rotated_path(&self.path, index + 1))?;
+            }
+        }
+        std::fs::rename(&self.path, rotated_path(&self.path, 1))?;
+        self.file = File::create(&self.path)?;
+        self.written = 0;
+        Ok(())
+    }
+
+    pub fn write_line(&mut self, timestamp: u128, text: &str) -> HelmResult<()> {
+        if self.written >= self.max_size {
+            self.rotate()?;
+        }
+        let line = format!("[{}] {}\n", format_timestamp(timestamp), text);
+        self.file.write_all(line.as_bytes())?;
+        self.written += line.len() as u64;
+        Ok(())
+    }
+
+    // Current file and rotated ones, oldest first
+    fn files(&self) -> Vec<PathBuf> {
+        let mut files: Vec<PathBuf> = (1..=MAX_ROTATED_FILES)
+            .rev()
+            .map(|index| rotated_path(&self.path, index))
+            .filter(|path| path.exists())
+            .collect();
+        files.push(self.path.clone());
+        files
+    }
+}
+
+// Command to tee monitor output to file, it's rotated when it reaches max_size bytes
+#[tauri::command]
+pub async fn start_monitor_capture(
+    state_mutex: State<'_, Mutex<AppState>>,
+    path: String,
+    max_size: Option<u64>,
+) -> HelmResult<String> {
+    let capture = MonitorCapture::open(PathBuf::from(&path), max_size.unwrap_or(DEFAULT_MAX_SIZE))?;
+    let mut state = state_mutex.lock().unwrap();
+    state.monitor.capture = Some(capture);
+    info!("Capturing monitor output to {}", path);
+    Ok(format!("Capturing to {}", path))
+}
+
+#[tauri::command]
+pub async fn stop_monitor_capture(state_mutex: State<'_, Mutex<AppState>>) -> HelmResult<String> {
+    let mut state = state_mutex.lock().unwrap();
+    match state.monitor.capture.take() {
+        Some(mut capture) => {
+            capture.file.flush()?;
+            Ok("Capture stopped".into())
+        }
+        None => Err(HelmError::Validation("Capture is not running".into())),
+    }
+}
+
+// Command to pack current capture file with rotated ones to zip archive
+#[tauri::command]
+pub async fn export_capture(
+    state_mutex: State<'_, Mutex<AppState>>,
+    dest: String,
+) -> HelmResult<String> {
+    let files = {
+        let mut state = state_mutex.lock().unwrap();
+        let capture = state
+            .monitor
+            .capture
+            .as_mut()
+            .ok_or(HelmError::Validation("Capture is not running".into()))?;
+        capture.file.flush()?;
+        capture.files()
+    };
+
+    let mut zip = zip::ZipWriter::new(File::create(&dest)?);
+    let options = FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    for file in &files {
+        let name = file.file_name().unwrap_or_default().to_string_lossy();
+        zip.start_file(name, options)?;
+        zip.write_all(&std::fs::read(file)?)?;
+    }
+    zip.finish()?;
+    Ok(format!("Exported {} files to {}", files.len(), dest))
+}
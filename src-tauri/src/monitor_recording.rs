@@ -0,0 +1,252 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use log::info;
+use tauri::{AppHandle, State, Window};
+
+use crate::app_state::AppState;
+use crate::error::{HelmError, HelmResult};
+use crate::monitor::{monitor_lines, run_session, unix_millis};
+use crate::operations::is_aborted;
+
+// Gzipped text, header "esp-helm-recording 1 <start ms> <port>", then one "<delta ms> <line>"
+// per line. Lines are raw, ANSI colors are kept, "\" and newline are escaped.
+const MAGIC: &str = "esp-helm-recording";
+const VERSION: u32 = 1;
+// Longer pauses of recorded device are shortened on replay
+const MAX_REPLAY_GAP: Duration = Duration::from_secs(5);
+
+// Monitor lines of one session written to disk
+pub struct Recording {
+    path: PathBuf,
+    encoder: GzEncoder<BufWriter<File>>,
+    last: u128,
+    lines: u64,
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+fn unescape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => result.push('\n'),
+            Some(other) => result.push(other),
+            None => result.push('\\'),
+        }
+    }
+    result
+}
+
+impl Recording {
+    pub fn create(path: PathBuf, port: &str) -> HelmResult<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = BufWriter::new(File::create(&path)?);
+        let mut encoder = GzEncoder::new(file, flate2::Compression::default());
+        let start = unix_millis();
+        writeln!(encoder, "{} {} {} {}", MAGIC, VERSION, start, escape(port))?;
+        Ok(Recording {
+            path,
+            encoder,
+            last: start,
+            lines: 0,
+        })
+    }
+
+    pub fn write_line(&mut self, timestamp: u128, raw: &str) -> HelmResult<()> {
+        let delta = timestamp.saturating_sub(self.last);
+        self.last = self.last.max(timestamp);
+        writeln!(self.encoder, "{} {}", delta, escape(raw))?;
+        self.lines += 1;
+        Ok(())
+    }
+
+    // Gzip trailer is written only here, unfinished file can not be replayed completely
+    pub fn finish(self) -> HelmResult<String> {
+        self.encoder.finish()?.flush()?;
+        Ok(format!(
+            "{} lines recorded to {}",
+            self.lines,
+            self.path.display()
+        ))
+    }
+}
+
+// Port of recorded session and its lines with timestamps
+fn read_recording(path: &Path) -> HelmResult<(String, Vec<(u128, String)>)> {
+    let invalid = |what: &str| {
+        HelmError::Validation(format!(
+            "{} is not a monitor recording: {}",
+            path.display(),
+            what
+        ))
+    };
+    let reader = BufReader::new(GzDecoder::new(File::open(path)?));
+    let mut lines = reader.lines();
+    let header = lines.next().ok_or_else(|| invalid("empty file"))??;
+    // Port is the rest of the line, it may contain spaces
+    let fields: Vec<&str> = header.splitn(4, ' ').collect();
+    let [magic, version, start, port] = fields[..] else {
+        return Err(invalid("bad header"));
+    };
+    if magic != MAGIC {
+        return Err(invalid("bad header"));
+    }
+    if version.parse::<u32>().ok() != Some(VERSION) {
+        return Err(invalid(&format!("unsupported version {}", version)));
+    }
+    let mut timestamp: u128 = start.parse().map_err(|_| invalid("bad start time"))?;
+
+    let mut recorded = vec![];
+    for line in lines {
+        let line = line?;
+        let (delta, raw) = line.split_once(' ').unwrap_or((&line, ""));
+        timestamp += delta
+            .parse::<u128>()
+            .map_err(|_| invalid("bad line timestamp"))?;
+        recorded.push((timestamp, unescape(raw)));
+    }
+    Ok((unescape(port), recorded))
+}
+
+// Command to record lines of monitor on port, recording ends with stop_monitor_recording
+// or when the monitor stops
+#[tauri::command]
+pub async fn start_monitor_recording(
+    state_mutex: State<'_, Mutex<AppState>>,
+    port: String,
+    path: String,
+) -> HelmResult<String> {
+    let recording = Recording::create(PathBuf::from(&path), &port)?;
+    let mut state = state_mutex.lock().unwrap();
+    let session = state.monitor.session_mut(&port)?;
+    if session.recording.is_some() {
+        return Err(HelmError::Validation(format!(
+            "Monitor on {} is already recorded",
+            port
+        )));
+    }
+    session.recording = Some(recording);
+    info!("Recording monitor on {} to {}", port, path);
+    Ok(format!("Recording to {}", path))
+}
+
+#[tauri::command]
+pub async fn stop_monitor_recording(
+    state_mutex: State<'_, Mutex<AppState>>,
+    port: String,
+) -> HelmResult<String> {
+    let recording = {
+        let mut state = state_mutex.lock().unwrap();
+        state.monitor.session_mut(&port)?.recording.take()
+    };
+    match recording {
+        Some(recording) => recording.finish(),
+        None => Err(HelmError::Validation(format!(
+            "Monitor on {} is not recorded",
+            port
+        ))),
+    }
+}
+
+async fn replay(
+    window: Window,
+    app: AppHandle,
+    session: String,
+    lines: Vec<(u128, String)>,
+    speed: f64,
+) -> HelmResult<()> {
+    let mut previous = lines.first().map(|(timestamp, _)| *timestamp);
+    for (timestamp, raw) in &lines {
+        let gap = timestamp.saturating_sub(previous.unwrap_or(*timestamp)) as f64;
+        let wait = Duration::from_secs_f64(gap / 1000.0 / speed).min(MAX_REPLAY_GAP);
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+        previous = Some(*timestamp);
+        if is_aborted() {
+            break;
+        }
+        monitor_lines(&window, &app, &session, [raw.as_str()], *timestamp);
+    }
+    Ok(())
+}
+
+// Command to emit recorded lines again as monitor session "replay:<port>", with original
+// timing divided by speed. It's stopped by stop_monitor of that session.
+#[tauri::command]
+pub async fn replay_capture(
+    window: Window,
+    app: AppHandle,
+    path: String,
+    speed: Option<f64>,
+) -> HelmResult<String> {
+    let speed = speed.unwrap_or(1.0);
+    if !speed.is_finite() || speed <= 0.0 {
+        return Err(HelmError::Validation(format!(
+            "Replay speed must be positive, got {}",
+            speed
+        )));
+    }
+    let (port, lines) = read_recording(Path::new(&path))?;
+    let count = lines.len();
+    let session = format!("replay:{}", port);
+    info!("Replaying {} lines of {} from {}", count, port, path);
+    run_session(
+        &app,
+        session.clone(),
+        replay(window, app.clone(), session, lines, speed),
+    )
+    .await?;
+    Ok(format!("Replayed {} lines of {}", count, port))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn recording_round_trip() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("recordings").join("session.rec.gz");
+        let mut recording = Recording::create(path.clone(), "/dev/ttyUSB0").unwrap();
+        let start = recording.last;
+        let lines = [
+            "\u{1b}[0;32mI (31) boot: ESP-IDF v5.1\u{1b}[0m",
+            "> a\\b\nc",
+            "",
+        ];
+        for (index, line) in lines.iter().enumerate() {
+            recording
+                .write_line(start + index as u128 * 250, line)
+                .unwrap();
+        }
+        recording.finish().unwrap();
+
+        let (port, recorded) = read_recording(&path).unwrap();
+        assert_eq!(port, "/dev/ttyUSB0");
+        assert_eq!(
+            recorded,
+            lines
+                .iter()
+                .enumerate()
+                .map(|(index, line)| (start + index as u128 * 250, line.to_string()))
+                .collect::<Vec<_>>()
+        );
+    }
+}
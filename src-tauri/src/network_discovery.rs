@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use mdns_sd::{ServiceDaemon, ServiceEvent};
+
+use crate::error::{HelmError, HelmResult};
+
+pub const DISCOVERY_TIMEOUT: Duration = Duration::from_secs(3);
+
+// Services advertised by ESP-IDF OTA servers, ESPHome and ArduinoOTA
+const ESP_SERVICES: [&str; 3] = [
+    "_esp-ota._tcp.local.",
+    "_esphomelib._tcp.local.",
+    "_arduino._tcp.local.",
+];
+// Generic HTTP services are listed only when they look like ESP devices
+const HTTP_SERVICE: &str = "_http._tcp.local.";
+
+#[derive(Clone, serde::Serialize)]
+pub struct NetworkDevice {
+    pub name: String,
+    pub hostname: String,
+    pub addresses: Vec<String>,
+    pub port: u16,
+    pub service_type: String,
+    pub txt: HashMap<String, String>,
+}
+
+impl NetworkDevice {
+    pub fn property(&self, key: &str) -> Option<&str> {
+        self.txt.get(key).map(String::as_str)
+    }
+}
+
+// ESP-IDF default hostname is "espressif", Arduino and esp-hal examples use "esp32-..."
+fn looks_like_esp(device: &NetworkDevice) -> bool {
+    let hostname = device.hostname.to_lowercase();
+    hostname.starts_with("esp")
+        || device.txt.contains_key("chip")
+        || device
+            .property("board")
+            .map(|board| board.to_lowercase().contains("esp"))
+            .unwrap_or(false)
+}
+
+// Browse given mDNS service types until timeout, devices are deduplicated by instance name
+pub fn browse(service_types: &[&str], timeout: Duration) -> HelmResult<Vec<NetworkDevice>> {
+    let mdns = ServiceDaemon::new()
+        .map_err(|e| HelmError::Other(format!("Failed to start mDNS: {}", e)))?;
+    let receivers = service_types
+        .iter()
+        .map(|service_type| {
+            mdns.browse(service_type)
+                .map_err(|e| HelmError::Other(format!("mDNS browse failed: {}", e)))
+        })
+        .collect::<HelmResult<Vec<_>>>()?;
+
+    let mut devices: Vec<NetworkDevice> = vec![];
+    let deadline = Instant::now() + timeout;
+    let poll_interval = Duration::from_millis(50);
+    while Instant::now() < deadline {
+        for receiver in &receivers {
+            while let Ok(event) = receiver.try_recv() {
+                let ServiceEvent::ServiceResolved(info) = event else {
+                    continue;
+                };
+                let device = NetworkDevice {
+                    name: info.get_fullname().to_string(),
+                    hostname: info.get_hostname().trim_end_matches('.').to_string(),
+                    addresses: info.get_addresses().iter().map(|a| a.to_string()).collect(),
+                    port: info.get_port(),
+                    service_type: info.get_type().to_string(),
+                    txt: info
+                        .get_properties()
+                        .iter()
+                        .map(|property| {
+                            (property.key().to_string(), property.val_str().to_string())
+                        })
+                        .collect(),
+                };
+                devices.retain(|known| known.name != device.name);
+                devices.push(device);
+            }
+        }
+        std::thread::sleep(poll_interval);
+    }
+    let _ = mdns.shutdown();
+    Ok(devices)
+}
+
+// Command to list ESP devices in local network, also used by OTA uploader
+#[tauri::command]
+pub async fn discover_network_devices(timeout_ms: Option<u64>) -> HelmResult<Vec<NetworkDevice>> {
+    let timeout = timeout_ms
+        .map(Duration::from_millis)
+        .unwrap_or(DISCOVERY_TIMEOUT);
+    let mut service_types = ESP_SERVICES.to_vec();
+    service_types.push(HTTP_SERVICE);
+
+    let devices = tokio::task::spawn_blocking(move || browse(&service_types, timeout))
+        .await
+        .map_err(|e| HelmError::Other(format!("mDNS discovery failed: {}", e)))??;
+    Ok(devices
+        .into_iter()
+        .filter(|device| device.service_type != HTTP_SERVICE || looks_like_esp(device))
+        .collect())
+}
@@ -0,0 +1,211 @@
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use log::info;
+use tauri::{AppHandle, State};
+
+use crate::app_state::AppState;
+use crate::audit::{audit_path, AuditAction};
+use crate::error::{HelmError, HelmResult};
+use crate::esp_clang::export_file_path;
+use crate::portable::app_data_dir;
+use crate::project_toolchain::inspect_project_toolchain;
+use crate::rust::{tool_version, tool_version_xtensa, RISCV_TARGETS};
+use crate::services::{services, ProcessRunner};
+
+// Tools taken from nixpkgs, versions installed by esp-helm are recorded next to them
+const NIX_TOOLS: [(&str, &str); 4] = [
+    ("espflash", "espflash"),
+    ("ldproxy", "ldproxy"),
+    ("cargo-generate", "cargo-generate"),
+    ("probe-rs", "probe-rs-tools"),
+];
+
+#[derive(serde::Serialize)]
+pub struct PinnedVersions {
+    // Dated nightly for RISC-V chips, rust-overlay pins it exactly
+    riscv_channel: String,
+    // esp toolchain is not packaged in nixpkgs, shell checks installed version instead
+    xtensa: Option<String>,
+    tools: Vec<(String, Option<String>)>,
+}
+
+#[derive(serde::Serialize)]
+pub struct NixExport {
+    directory: String,
+    files: Vec<String>,
+    versions: PinnedVersions,
+    warnings: Vec<String>,
+}
+
+fn pinned_versions(
+    process: &dyn ProcessRunner,
+    nightly_pin: Option<String>,
+    project: Option<&Path>,
+) -> PinnedVersions {
+    // Project toolchain file wins, so the shell builds the project as rustup would
+    let project_channel = project
+        .and_then(|project| inspect_project_toolchain(process, project).ok())
+        .and_then(|status| status.toolchain.channel)
+        .filter(|channel| channel.starts_with("nightly"));
+    PinnedVersions {
+        riscv_channel: project_channel
+            .or(nightly_pin)
+            .unwrap_or_else(|| "nightly".into()),
+        xtensa: tool_version_xtensa(process, "rustc", &["+esp", "--version"], Some("rustc")),
+        tools: NIX_TOOLS
+            .iter()
+            .map(|(binary, _)| {
+                (
+                    binary.to_string(),
+                    tool_version(process, binary, &["--version"], None),
+                )
+            })
+            .collect(),
+    }
+}
+
+// rust-overlay expects "nightly" or dated nightly split into channel and date
+fn rust_overlay_toolchain(channel: &str) -> String {
+    match channel.strip_prefix("nightly-") {
+        Some(date) => format!("rust-bin.nightly.\"{}\".default", date),
+        None => "rust-bin.nightly.latest.default".into(),
+    }
+}
+
+fn shell_hook(versions: &PinnedVersions) -> String {
+    let mut hook = vec![];
+    if let Some(export_file) = export_file_path() {
+        hook.push(format!(
+            "[ -f \"{0}\" ] && . \"{0}\"",
+            export_file.to_string_lossy()
+        ));
+    }
+    if let Some(xtensa) = &versions.xtensa {
+        hook.push(format!(
+            "rustc +esp --version 2>/dev/null | grep -q \"{0}\" || \
+             echo \"esp toolchain {0} is expected, run espup install --toolchain-version {0}\"",
+            xtensa
+        ));
+    }
+    hook.join("\n        ")
+}
+
+fn flake_nix(versions: &PinnedVersions) -> String {
+    let tools = NIX_TOOLS
+        .iter()
+        .zip(&versions.tools)
+        .map(|((_, package), (_, version))| match version {
+            Some(version) => format!("{} # {}", package, version),
+            None => package.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n            ");
+    format!(
+        r#"# Generated by esp-helm, toolchains are pinned by flake.lock
+{{
+  inputs = {{
+    nixpkgs.url = "github:NixOS/nixpkgs/nixos-unstable";
+    rust-overlay.url = "github:oxalica/rust-overlay";
+    rust-overlay.inputs.nixpkgs.follows = "nixpkgs";
+    flake-utils.url = "github:numtide/flake-utils";
+  }};
+
+  outputs = {{ nixpkgs, rust-overlay, flake-utils, ... }}:
+    flake-utils.lib.eachDefaultSystem (system:
+      let
+        pkgs = import nixpkgs {{
+          inherit system;
+          overlays = [ (import rust-overlay) ];
+        }};
+        # RISC-V chips, channel {channel}
+        riscv = pkgs.{toolchain}.override {{
+          extensions = [ "rust-src" ];
+          targets = [ {targets} ];
+        }};
+      in {{
+        devShells.default = pkgs.mkShell {{
+          buildInputs = with pkgs; [
+            riscv
+            {tools}
+          ];
+          # esp toolchain for Xtensa chips is installed by espup outside of Nix
+          shellHook = ''
+            {hook}
+          '';
+        }};
+      }});
+}}
+"#,
+        channel = versions.riscv_channel,
+        toolchain = rust_overlay_toolchain(&versions.riscv_channel),
+        targets = RISCV_TARGETS
+            .iter()
+            .map(|target| format!("\"{}\"", target))
+            .collect::<Vec<_>>()
+            .join(" "),
+        tools = tools,
+        hook = shell_hook(versions),
+    )
+}
+
+// nix-shell users get the same shell through flake-compat
+fn shell_nix() -> String {
+    r#"# Generated by esp-helm, shell of flake.nix for nix-shell
+(import (fetchTarball "https://github.com/edolstra/flake-compat/archive/master.tar.gz") {
+  src = ./.;
+}).shellNix
+"#
+    .to_string()
+}
+
+fn export_directory(project: Option<&Path>) -> HelmResult<PathBuf> {
+    match project {
+        Some(project) => Ok(project.to_path_buf()),
+        None => app_data_dir()
+            .map(|dir| dir.join("nix"))
+            .ok_or(HelmError::NotFound("data directory".into())),
+    }
+}
+
+// Command to write flake.nix and shell.nix reproducing installed toolchain versions, into
+// the project when given, otherwise into data directory of esp-helm
+#[tauri::command]
+pub async fn export_nix_flake(
+    app: AppHandle,
+    state_mutex: State<'_, Mutex<AppState>>,
+    project_path: Option<String>,
+) -> HelmResult<NixExport> {
+    let nightly_pin = state_mutex.lock().unwrap().settings.nightly_pin.clone();
+    let project = project_path.as_deref().map(Path::new);
+    let directory = export_directory(project)?;
+    std::fs::create_dir_all(&directory)?;
+
+    let versions = pinned_versions(services(&app).process.as_ref(), nightly_pin, project);
+    let mut warnings = vec![];
+    if versions.riscv_channel == "nightly" {
+        warnings.push("Nightly is not pinned, flake.lock decides which one is used".into());
+    }
+    if versions.xtensa.is_none() {
+        warnings.push("esp toolchain is not installed, Xtensa chips are not covered".into());
+    }
+
+    let mut files = vec![];
+    for (name, content) in [
+        ("flake.nix", flake_nix(&versions)),
+        ("shell.nix", shell_nix()),
+    ] {
+        let path = directory.join(name);
+        std::fs::write(&path, content)?;
+        audit_path(AuditAction::WriteFile, &path);
+        files.push(path.to_string_lossy().to_string());
+    }
+
+    info!("Nix environment exported to {}", directory.display());
+    Ok(NixExport {
+        directory: directory.to_string_lossy().to_string(),
+        files,
+        versions,
+        warnings,
+    })
+}
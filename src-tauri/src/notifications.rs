@@ -0,0 +1,121 @@
+use std::sync::Mutex;
+
+use log::info;
+use tauri::api::notification::Notification;
+use tauri::{AppHandle, Manager, State};
+
+use crate::app_state::{AppState, JobInfo, JobStatus};
+use crate::error::HelmResult;
+use crate::settings::save_settings;
+
+#[derive(Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotificationCategory {
+    Install,
+    Build,
+    Flash,
+}
+
+// Desktop notifications per category, shown only while the window is not focused
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct NotificationSettings {
+    pub install: bool,
+    pub build: bool,
+    pub flash: bool,
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        Self {
+            install: true,
+            build: true,
+            flash: true,
+        }
+    }
+}
+
+impl NotificationSettings {
+    fn enabled(&self, category: NotificationCategory) -> bool {
+        match category {
+            NotificationCategory::Install => self.install,
+            NotificationCategory::Build => self.build,
+            NotificationCategory::Flash => self.flash,
+        }
+    }
+}
+
+// Monitor runs until user stops it, so it is never reported
+pub fn job_category(name: &str) -> Option<NotificationCategory> {
+    if name.starts_with("Monitor") {
+        None
+    } else if name.starts_with("Build") {
+        Some(NotificationCategory::Build)
+    } else if name.starts_with("Flash") || name.starts_with("OTA") {
+        Some(NotificationCategory::Flash)
+    } else {
+        Some(NotificationCategory::Install)
+    }
+}
+
+fn is_window_focused(app: &AppHandle) -> bool {
+    app.get_window("main")
+        .and_then(|window| window.is_focused().ok())
+        .unwrap_or(false)
+}
+
+// Success is reported only for the last job of a chain, e.g. verification after install.
+// Jobs skipped because their dependency failed are not reported again.
+pub fn notify_job_finished(app: &AppHandle, job: &JobInfo, skipped: bool) {
+    let Some(category) = job_category(&job.name) else {
+        return;
+    };
+    let (title, body) = match &job.status {
+        JobStatus::Done(message) => (format!("{} finished", job.name), message.clone()),
+        JobStatus::Failed(error) if !skipped => (format!("{} failed", job.name), error.localized()),
+        _ => return,
+    };
+    {
+        let state_mutex = app.state::<Mutex<AppState>>();
+        let state = state_mutex.lock().unwrap();
+        if !state.settings.notifications.enabled(category) {
+            return;
+        }
+        let has_dependents = state
+            .scheduler
+            .jobs
+            .values()
+            .any(|other| other.depends_on.contains(&job.id));
+        if matches!(job.status, JobStatus::Done(_)) && has_dependents {
+            return;
+        }
+    }
+    if is_window_focused(app) {
+        return;
+    }
+
+    let identifier = app.config().tauri.bundle.identifier.clone();
+    if let Err(e) = Notification::new(identifier).title(title).body(body).show() {
+        info!("Unable to show notification: {}", e);
+    }
+}
+
+// Command to enable or disable desktop notifications of one category
+#[tauri::command]
+pub async fn set_notification_category(
+    state_mutex: State<'_, Mutex<AppState>>,
+    category: NotificationCategory,
+    enabled: bool,
+) -> HelmResult<NotificationSettings> {
+    let mut state = state_mutex.lock().unwrap();
+    let mut settings = state.settings.clone();
+    match category {
+        NotificationCategory::Install => settings.notifications.install = enabled,
+        NotificationCategory::Build => settings.notifications.build = enabled,
+        NotificationCategory::Flash => settings.notifications.flash = enabled,
+    }
+    save_settings(&settings)?;
+    let notifications = settings.notifications.clone();
+    state.settings = settings;
+    Ok(notifications)
+}
@@ -0,0 +1,405 @@
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use log::info;
+use tauri::{AppHandle, Manager};
+use tokio::sync::{mpsc, oneshot, watch};
+
+use crate::error::{HelmError, HelmResult};
+
+pub type OperationId = u64;
+
+// Run state of long operation, workers read it on every poll without taking any lock
+#[derive(Clone, Copy, PartialEq, Debug, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Signal {
+    Running,
+    Paused,
+    Aborted,
+}
+
+// What operation does, pause and abort commands of the UI reach only operations of their
+// kind, so stopping flashing does not abort installation running next to it
+#[derive(Clone, Copy, PartialEq, Debug, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OperationKind {
+    Install,
+    Build,
+    Flash,
+    Monitor,
+    Other,
+}
+
+impl OperationKind {
+    fn describe(self) -> &'static str {
+        match self {
+            OperationKind::Install => "installation",
+            OperationKind::Build => "build",
+            OperationKind::Flash => "flashing",
+            OperationKind::Monitor => "monitor",
+            OperationKind::Other => "operation",
+        }
+    }
+}
+
+#[derive(Clone, serde::Serialize)]
+pub struct OperationInfo {
+    id: OperationId,
+    kind: OperationKind,
+    name: String,
+    signal: Signal,
+}
+
+struct Operation {
+    kind: OperationKind,
+    name: String,
+    sender: watch::Sender<Signal>,
+}
+
+// Messages handled by the task owning operations, replies are sent back on oneshot channels
+enum OperationCommand {
+    Register {
+        id: OperationId,
+        operation: Operation,
+    },
+    Finish {
+        id: OperationId,
+    },
+    Pause {
+        kind: OperationKind,
+        reply: oneshot::Sender<HelmResult<usize>>,
+    },
+    Resume {
+        kind: OperationKind,
+        reply: oneshot::Sender<HelmResult<usize>>,
+    },
+    Abort {
+        kinds: Vec<OperationKind>,
+        reply: oneshot::Sender<usize>,
+    },
+    AbortOne {
+        id: OperationId,
+        reply: oneshot::Sender<bool>,
+    },
+    List {
+        reply: oneshot::Sender<Vec<OperationInfo>>,
+    },
+}
+
+// Cancellation token of one operation, abort of other operations does not affect it
+#[derive(Clone)]
+pub struct OperationToken {
+    id: OperationId,
+    receiver: watch::Receiver<Signal>,
+}
+
+impl OperationToken {
+    pub fn signal(&self) -> Signal {
+        *self.receiver.borrow()
+    }
+}
+
+tokio::task_local! {
+    static OPERATION: OperationToken;
+}
+
+// Operation is finished when the guard is dropped, also when its task is aborted
+pub struct OperationGuard {
+    token: OperationToken,
+    sender: mpsc::UnboundedSender<OperationCommand>,
+}
+
+impl OperationGuard {
+    pub fn id(&self) -> OperationId {
+        self.token.id
+    }
+
+    // Future sees the token of this operation in is_aborted and is_paused
+    pub fn scope<F: Future>(&self, future: F) -> impl Future<Output = F::Output> {
+        OPERATION.scope(self.token.clone(), future)
+    }
+
+    // Same as scope for blocking work running on the current task
+    pub fn sync_scope<R>(&self, work: impl FnOnce() -> R) -> R {
+        OPERATION.sync_scope(self.token.clone(), work)
+    }
+
+    // Same as sync_scope, work runs on blocking thread, e.g. serial I/O of espflash
+    pub async fn blocking<R: Send + 'static>(
+        &self,
+        work: impl FnOnce() -> R + Send + 'static,
+    ) -> HelmResult<R> {
+        let token = self.token.clone();
+        tokio::task::spawn_blocking(move || OPERATION.sync_scope(token, work))
+            .await
+            .map_err(|_| HelmError::Other("Blocking task panicked".into()))
+    }
+}
+
+impl Drop for OperationGuard {
+    fn drop(&mut self) {
+        let _ = self
+            .sender
+            .send(OperationCommand::Finish { id: self.token.id });
+    }
+}
+
+// Handle of the task owning operation state, managed by tauri
+#[derive(Clone)]
+pub struct Operations {
+    sender: mpsc::UnboundedSender<OperationCommand>,
+    next_id: Arc<AtomicU64>,
+}
+
+impl Operations {
+    pub fn start() -> Self {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        tauri::async_runtime::spawn(run(receiver));
+        Self {
+            sender,
+            next_id: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    pub fn begin(&self, kind: OperationKind, name: &str) -> OperationGuard {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let (sender, receiver) = watch::channel(Signal::Running);
+        let _ = self.sender.send(OperationCommand::Register {
+            id,
+            operation: Operation {
+                kind,
+                name: name.to_string(),
+                sender,
+            },
+        });
+        OperationGuard {
+            token: OperationToken { id, receiver },
+            sender: self.sender.clone(),
+        }
+    }
+
+    async fn request<T>(
+        &self,
+        command: impl FnOnce(oneshot::Sender<T>) -> OperationCommand,
+    ) -> HelmResult<T> {
+        let (reply, response) = oneshot::channel();
+        self.sender
+            .send(command(reply))
+            .map_err(|_| HelmError::Other("Operation task is not running".into()))?;
+        response
+            .await
+            .map_err(|_| HelmError::Other("Operation task is not running".into()))
+    }
+
+    pub async fn pause(&self, kind: OperationKind) -> HelmResult<usize> {
+        self.request(|reply| OperationCommand::Pause { kind, reply })
+            .await?
+    }
+
+    pub async fn resume(&self, kind: OperationKind) -> HelmResult<usize> {
+        self.request(|reply| OperationCommand::Resume { kind, reply })
+            .await?
+    }
+
+    pub async fn abort(&self, kinds: &[OperationKind]) -> HelmResult<usize> {
+        let kinds = kinds.to_vec();
+        self.request(|reply| OperationCommand::Abort { kinds, reply })
+            .await
+    }
+
+    // Abort only this operation, e.g. one of several monitors
+    pub async fn abort_one(&self, id: OperationId) -> HelmResult<bool> {
+        self.request(|reply| OperationCommand::AbortOne { id, reply })
+            .await
+    }
+
+    pub async fn list(&self) -> HelmResult<Vec<OperationInfo>> {
+        self.request(|reply| OperationCommand::List { reply }).await
+    }
+}
+
+// Change signal of operations of given kinds in state `from`, returns how many were changed
+fn transition(
+    operations: &BTreeMap<OperationId, Operation>,
+    kinds: &[OperationKind],
+    from: &[Signal],
+    to: Signal,
+) -> usize {
+    let mut count = 0;
+    for operation in operations.values() {
+        if kinds.contains(&operation.kind) && from.contains(&*operation.sender.borrow()) {
+            operation.sender.send_replace(to);
+            count += 1;
+        }
+    }
+    count
+}
+
+// State is owned by this task only, so a signal can not be lost between check and update
+async fn run(mut receiver: mpsc::UnboundedReceiver<OperationCommand>) {
+    let mut operations: BTreeMap<OperationId, Operation> = BTreeMap::new();
+    while let Some(command) = receiver.recv().await {
+        match command {
+            OperationCommand::Register { id, operation } => {
+                info!("Operation {} started: {}", id, operation.name);
+                operations.insert(id, operation);
+            }
+            OperationCommand::Finish { id } => {
+                if let Some(operation) = operations.remove(&id) {
+                    info!("Operation {} finished: {}", id, operation.name);
+                }
+            }
+            OperationCommand::Pause { kind, reply } => {
+                let count = transition(&operations, &[kind], &[Signal::Running], Signal::Paused);
+                let result = match count {
+                    0 => Err(HelmError::Validation(format!(
+                        "No {} is running",
+                        kind.describe()
+                    ))),
+                    count => Ok(count),
+                };
+                let _ = reply.send(result);
+            }
+            OperationCommand::Resume { kind, reply } => {
+                let count = transition(&operations, &[kind], &[Signal::Paused], Signal::Running);
+                let result = match count {
+                    0 => Err(HelmError::Validation(format!(
+                        "No {} is paused",
+                        kind.describe()
+                    ))),
+                    count => Ok(count),
+                };
+                let _ = reply.send(result);
+            }
+            OperationCommand::Abort { kinds, reply } => {
+                let count = transition(
+                    &operations,
+                    &kinds,
+                    &[Signal::Running, Signal::Paused],
+                    Signal::Aborted,
+                );
+                let _ = reply.send(count);
+            }
+            OperationCommand::AbortOne { id, reply } => {
+                let aborted = operations.get(&id).is_some_and(|operation| {
+                    operation.sender.send_if_modified(|signal| {
+                        let running = *signal != Signal::Aborted;
+                        *signal = Signal::Aborted;
+                        running
+                    })
+                });
+                let _ = reply.send(aborted);
+            }
+            OperationCommand::List { reply } => {
+                let list = operations
+                    .iter()
+                    .map(|(id, operation)| OperationInfo {
+                        id: *id,
+                        kind: operation.kind,
+                        name: operation.name.clone(),
+                        signal: *operation.sender.borrow(),
+                    })
+                    .collect();
+                let _ = reply.send(list);
+            }
+        }
+    }
+}
+
+// Task locals do not cross to blocking threads, so the token is passed there explicitly
+pub fn current_token() -> Option<OperationToken> {
+    OPERATION.try_with(|token| token.clone()).ok()
+}
+
+pub fn with_token<R>(token: Option<OperationToken>, work: impl FnOnce() -> R) -> R {
+    match token {
+        Some(token) => OPERATION.sync_scope(token, work),
+        None => work(),
+    }
+}
+
+pub fn begin_operation(app: &AppHandle, kind: OperationKind, name: &str) -> OperationGuard {
+    app.state::<Operations>().begin(kind, name)
+}
+
+// Long work started outside of any operation gets its own, so abort reaches it and abort
+// requested while nothing runs does not affect work started later
+pub async fn within_operation<F: Future>(
+    app: &AppHandle,
+    kind: OperationKind,
+    name: &str,
+    work: F,
+) -> F::Output {
+    if current_token().is_some() {
+        return work.await;
+    }
+    let operation = begin_operation(app, kind, name);
+    operation.scope(work).await
+}
+
+// Work outside of any operation can not be paused or aborted
+fn current_signal() -> Signal {
+    OPERATION
+        .try_with(|token| token.signal())
+        .unwrap_or(Signal::Running)
+}
+
+pub fn is_aborted() -> bool {
+    current_signal() == Signal::Aborted
+}
+
+pub fn is_paused() -> bool {
+    current_signal() == Signal::Paused
+}
+
+// Command to list running operations with their signal
+#[tauri::command]
+pub async fn list_operations(app: AppHandle) -> HelmResult<Vec<OperationInfo>> {
+    app.state::<Operations>().list().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn operation(kind: OperationKind) -> (Operation, watch::Receiver<Signal>) {
+        let (sender, receiver) = watch::channel(Signal::Running);
+        let operation = Operation {
+            kind,
+            name: format!("{:?}", kind),
+            sender,
+        };
+        (operation, receiver)
+    }
+
+    #[test]
+    fn signals_reach_only_given_kinds() {
+        let (install, install_signal) = operation(OperationKind::Install);
+        let (flash, flash_signal) = operation(OperationKind::Flash);
+        let (monitor, monitor_signal) = operation(OperationKind::Monitor);
+        let operations = BTreeMap::from([(1, install), (2, flash), (3, monitor)]);
+
+        let paused = transition(
+            &operations,
+            &[OperationKind::Install],
+            &[Signal::Running],
+            Signal::Paused,
+        );
+        assert_eq!(paused, 1);
+        assert_eq!(*install_signal.borrow(), Signal::Paused);
+        assert_eq!(*monitor_signal.borrow(), Signal::Running);
+
+        let aborted = transition(
+            &operations,
+            &[OperationKind::Flash],
+            &[Signal::Running, Signal::Paused],
+            Signal::Aborted,
+        );
+        assert_eq!(aborted, 1);
+        assert_eq!(*flash_signal.borrow(), Signal::Aborted);
+        assert_eq!(*install_signal.borrow(), Signal::Paused);
+        assert_eq!(*monitor_signal.borrow(), Signal::Running);
+    }
+}
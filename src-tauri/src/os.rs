@@ -6,6 +6,8 @@ pub fn get_platform() -> String {
         "darwin".to_string()
     } else if cfg!(target_os = "linux") {
         "linux".to_string()
+    } else if cfg!(target_os = "freebsd") {
+        "freebsd".to_string()
     } else {
         "unknown".to_string()
     }
@@ -0,0 +1,303 @@
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+use futures::StreamExt;
+use log::info;
+use tauri::{AppHandle, Window};
+
+use crate::error::{HelmError, HelmResult};
+use crate::events::{emit_event, HelmEvent};
+use crate::firmware::parse_image;
+use crate::jobs::{spawn_job, wait_job};
+use crate::network_discovery::{browse, NetworkDevice, DISCOVERY_TIMEOUT};
+use crate::operations::OperationKind;
+
+// Devices running OTA server advertise "_esp-ota._tcp", TXT records may override
+// the upload path ("path") and the status path ("status")
+const OTA_SERVICE: &str = "_esp-ota._tcp.local.";
+const DEFAULT_UPLOAD_PATH: &str = "/ota";
+const DEFAULT_STATUS_PATH: &str = "/ota/status";
+const UPLOAD_CHUNK_SIZE: usize = 16 * 1024;
+// Device reboots into new image and must mark it valid before rollback timer expires
+const STATUS_TIMEOUT: Duration = Duration::from_secs(60);
+const STATUS_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Clone, serde::Serialize)]
+pub struct OtaDevice {
+    name: String,
+    hostname: String,
+    addresses: Vec<String>,
+    port: u16,
+    upload_url: String,
+    status_url: String,
+}
+
+#[derive(Clone, serde::Serialize, schemars::JsonSchema)]
+pub struct OtaProgress {
+    sent: usize,
+    total: usize,
+}
+
+impl HelmEvent for OtaProgress {
+    const NAMES: &'static [&'static str] = &["ota-progress"];
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct OtaStatus {
+    // ESP-IDF esp_ota_img_states_t as string, e.g. "valid", "pending_verify", "aborted"
+    state: String,
+    #[serde(default)]
+    version: Option<String>,
+}
+
+impl HelmEvent for OtaStatus {
+    const NAMES: &'static [&'static str] = &["ota-status"];
+}
+
+#[derive(serde::Deserialize)]
+pub struct OtaOptions {
+    #[serde(default)]
+    accept_invalid_certs: bool,
+    #[serde(default = "default_true")]
+    wait_for_status: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+// Host part of device URL, mDNS reports addresses in no particular order. IPv4 is preferred
+// and IPv6 is bracketed. Link-local IPv6 needs zone which URLs can not carry, so it is used
+// only when nothing else is advertised.
+fn url_host(addresses: &[String]) -> Option<String> {
+    let mut addresses: Vec<IpAddr> = addresses
+        .iter()
+        .filter_map(|address| address.parse().ok())
+        .collect();
+    addresses.sort_by_key(|address| {
+        let rank = match address {
+            IpAddr::V4(_) => 0,
+            IpAddr::V6(v6) if v6.segments()[0] & 0xffc0 == 0xfe80 => 2,
+            IpAddr::V6(_) => 1,
+        };
+        (rank, *address)
+    });
+    addresses.first().map(|address| match address {
+        IpAddr::V4(v4) => v4.to_string(),
+        IpAddr::V6(v6) => format!("[{}]", v6),
+    })
+}
+
+impl From<NetworkDevice> for OtaDevice {
+    fn from(device: NetworkDevice) -> Self {
+        let scheme = match device.property("tls") {
+            Some("1") => "https",
+            _ => "http",
+        };
+        let address = url_host(&device.addresses).unwrap_or_else(|| device.hostname.clone());
+        let url = |path: &str| format!("{}://{}:{}{}", scheme, address, device.port, path);
+        OtaDevice {
+            upload_url: url(device.property("path").unwrap_or(DEFAULT_UPLOAD_PATH)),
+            status_url: url(device.property("status").unwrap_or(DEFAULT_STATUS_PATH)),
+            name: device.name,
+            hostname: device.hostname,
+            addresses: device.addresses,
+            port: device.port,
+        }
+    }
+}
+
+// Command to list devices advertising OTA endpoint in local network
+#[tauri::command]
+pub async fn discover_ota_devices(timeout_ms: Option<u64>) -> HelmResult<Vec<OtaDevice>> {
+    let timeout = timeout_ms
+        .map(Duration::from_millis)
+        .unwrap_or(DISCOVERY_TIMEOUT);
+    let devices = tokio::task::spawn_blocking(move || browse(&[OTA_SERVICE], timeout))
+        .await
+        .map_err(|e| HelmError::Other(format!("mDNS discovery failed: {}", e)))??;
+    Ok(devices
+        .into_iter()
+        .filter(|device| !device.addresses.is_empty())
+        .map(OtaDevice::from)
+        .collect())
+}
+
+fn http_client(options: &OtaOptions) -> HelmResult<reqwest::Client> {
+    // Devices usually serve OTA with self-signed certificate
+    Ok(reqwest::Client::builder()
+        .danger_accept_invalid_certs(options.accept_invalid_certs)
+        .build()?)
+}
+
+async fn upload_image(
+    window: &Window,
+    client: &reqwest::Client,
+    upload_url: &str,
+    image: Vec<u8>,
+) -> HelmResult<()> {
+    let total = image.len();
+    let progress_window = window.clone();
+    let chunks: Vec<Vec<u8>> = image
+        .chunks(UPLOAD_CHUNK_SIZE)
+        .map(<[u8]>::to_vec)
+        .collect();
+    let mut sent = 0;
+    // Progress is reported when chunk is taken by the HTTP client
+    let body = futures::stream::iter(chunks).map(move |chunk| {
+        sent += chunk.len();
+        emit_event(&progress_window, &OtaProgress { sent, total });
+        Ok::<_, std::io::Error>(chunk)
+    });
+
+    client
+        .post(upload_url)
+        .header(reqwest::header::CONTENT_TYPE, "application/octet-stream")
+        .header(reqwest::header::CONTENT_LENGTH, total)
+        .body(reqwest::Body::wrap_stream(body))
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+async fn current_status(client: &reqwest::Client, status_url: &str) -> Option<OtaStatus> {
+    client.get(status_url).send().await.ok()?.json().await.ok()
+}
+
+// Old image reports "valid" until the device reboots, so the state counts only after
+// the device was unreachable or when it reports other version than before the upload
+fn confirms_update(status: &OtaStatus, rebooted: bool, previous: Option<&str>) -> bool {
+    status.state == "valid"
+        && (rebooted
+            || previous.is_some_and(|previous| status.version.as_deref() != Some(previous)))
+}
+
+// Poll status until new image confirms itself or rollback happens
+async fn wait_for_status(
+    window: &Window,
+    client: &reqwest::Client,
+    status_url: &str,
+    previous: Option<&str>,
+) -> HelmResult<OtaStatus> {
+    let deadline = Instant::now() + STATUS_TIMEOUT;
+    let mut rebooted = false;
+    while Instant::now() < deadline {
+        tokio::time::sleep(STATUS_POLL_INTERVAL).await;
+        // Requests fail while the device reboots
+        let status = match client.get(status_url).send().await {
+            Ok(response) => response.json::<OtaStatus>().await.ok(),
+            Err(e) => {
+                info!("OTA status not available yet: {}", e);
+                rebooted = true;
+                None
+            }
+        };
+        let Some(status) = status else {
+            continue;
+        };
+        emit_event(window, &status);
+        match status.state.as_str() {
+            "valid" if confirms_update(&status, rebooted, previous) => return Ok(status),
+            "valid" => info!("Device still runs the previous image"),
+            "invalid" | "aborted" => {
+                return Err(HelmError::Other(format!(
+                    "Device rolled back the update ({})",
+                    status.state
+                )))
+            }
+            _ => {}
+        }
+    }
+    Err(HelmError::Other(
+        "Device did not confirm the update in time".into(),
+    ))
+}
+
+async fn run_ota(
+    window: Window,
+    upload_url: String,
+    status_url: Option<String>,
+    image_path: String,
+    options: OtaOptions,
+) -> HelmResult<String> {
+    let image = tokio::fs::read(&image_path).await?;
+    // Refuse to send anything what device would reject after the transfer
+    parse_image(&image)?;
+    let client = http_client(&options)?;
+    let previous = match &status_url {
+        Some(status_url) if options.wait_for_status => current_status(&client, status_url)
+            .await
+            .and_then(|status| status.version),
+        _ => None,
+    };
+
+    info!("Uploading {} to {}", image_path, upload_url);
+    upload_image(&window, &client, &upload_url, image).await?;
+
+    match status_url {
+        Some(status_url) if options.wait_for_status => {
+            let status =
+                wait_for_status(&window, &client, &status_url, previous.as_deref()).await?;
+            Ok(format!(
+                "Update confirmed, running {}",
+                status.version.unwrap_or(status.state)
+            ))
+        }
+        _ => Ok("Image uploaded".into()),
+    }
+}
+
+// Command to push application image to device over HTTP(S), runs as cancellable job
+#[tauri::command]
+pub async fn upload_ota(
+    window: Window,
+    app: AppHandle,
+    upload_url: String,
+    status_url: Option<String>,
+    image_path: String,
+    options: OtaOptions,
+) -> HelmResult<String> {
+    let job = spawn_job(
+        &app,
+        OperationKind::Flash,
+        "OTA update",
+        vec![],
+        run_ota(window, upload_url, status_url, image_path, options),
+    );
+    wait_job(&app, job).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addresses(list: &[&str]) -> Vec<String> {
+        list.iter().map(|address| address.to_string()).collect()
+    }
+
+    #[test]
+    fn ipv4_is_preferred_and_ipv6_bracketed() {
+        assert_eq!(
+            url_host(&addresses(&["fe80::1", "2001:db8::5", "192.168.1.20"])).as_deref(),
+            Some("192.168.1.20")
+        );
+        assert_eq!(
+            url_host(&addresses(&["fe80::1", "2001:db8::5"])).as_deref(),
+            Some("[2001:db8::5]")
+        );
+        assert_eq!(url_host(&addresses(&["esp32.local"])), None);
+    }
+
+    #[test]
+    fn valid_state_counts_only_after_reboot_or_version_change() {
+        let status = |version: &str| OtaStatus {
+            state: "valid".into(),
+            version: Some(version.into()),
+        };
+        assert!(!confirms_update(&status("1.0.0"), false, Some("1.0.0")));
+        assert!(!confirms_update(&status("1.0.0"), false, None));
+        assert!(confirms_update(&status("1.0.0"), true, Some("1.0.0")));
+        assert!(confirms_update(&status("1.1.0"), false, Some("1.0.0")));
+    }
+}
@@ -0,0 +1,330 @@
+use std::path::PathBuf;
+
+use log::info;
+use tauri::{AppHandle, Window};
+
+use crate::arch::{is_gcompat_installed, is_musl};
+use crate::error::{HelmError, HelmResult};
+use crate::external_command::{
+    run_external_command_interactive, run_external_command_with_progress,
+};
+
+// Host prerequisites which can be installed by system package manager
+pub const HOST_PACKAGES: [&str; 5] = ["cmake", "ninja", "python", "git", "dfu-util"];
+
+#[derive(Clone, Copy, PartialEq, serde::Serialize)]
+pub enum PackageManager {
+    Homebrew,
+    Winget,
+    Chocolatey,
+    Apt,
+    Dnf,
+    Pacman,
+    Zypper,
+    Apk,
+    Pkg,
+}
+
+#[derive(serde::Serialize)]
+pub struct PackageStatus {
+    name: String,
+    package_id: Option<String>,
+    installed: bool,
+}
+
+// Steps which would be executed, used for dry run
+#[derive(serde::Serialize)]
+pub struct InstallPlan {
+    manager: Option<PackageManager>,
+    packages: Vec<PackageStatus>,
+    commands: Vec<String>,
+}
+
+// pkexec is part of polkit, which is not in FreeBSD base system, doas and sudo are more common
+const FREEBSD_ELEVATION: [&str; 3] = ["doas", "sudo", "pkexec"];
+
+#[cfg(unix)]
+fn is_root() -> bool {
+    unsafe { libc::geteuid() == 0 }
+}
+
+#[cfg(windows)]
+fn is_root() -> bool {
+    false
+}
+
+// Find executable in directories listed in PATH
+pub fn find_in_path(name: &str) -> Option<PathBuf> {
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path).find_map(|dir| {
+        #[cfg(windows)]
+        let candidates = [dir.join(format!("{}.exe", name)), dir.join(name)];
+        #[cfg(unix)]
+        let candidates = [dir.join(name)];
+        candidates.into_iter().find(|candidate| candidate.is_file())
+    })
+}
+
+// Name of executable provided by the package
+fn package_binary(package: &str) -> &str {
+    match package {
+        "python" if cfg!(windows) => "python",
+        "python" => "python3",
+        _ => package,
+    }
+}
+
+pub fn is_package_installed(package: &str) -> bool {
+    // gcompat is a library, there is no executable to look for
+    if package == "gcompat" {
+        return is_gcompat_installed();
+    }
+    // Headers and certificates neither, package manager skips them when installed
+    if package == "openssl" || package == "ca-certificates" {
+        return false;
+    }
+    find_in_path(package_binary(package)).is_some()
+}
+
+// Default prerequisites, glibc toolchains need gcompat on musl systems
+fn default_packages() -> Vec<String> {
+    let mut packages: Vec<String> = HOST_PACKAGES.iter().map(|p| p.to_string()).collect();
+    if is_musl() {
+        packages.push("gcompat".into());
+    }
+    packages
+}
+
+impl PackageManager {
+    pub fn detect() -> Option<Self> {
+        let candidates: &[(&str, PackageManager)] = if cfg!(target_os = "macos") {
+            &[("brew", PackageManager::Homebrew)]
+        } else if cfg!(windows) {
+            &[
+                ("winget", PackageManager::Winget),
+                ("choco", PackageManager::Chocolatey),
+            ]
+        } else if cfg!(target_os = "freebsd") {
+            &[("pkg", PackageManager::Pkg)]
+        } else {
+            &[
+                ("apt-get", PackageManager::Apt),
+                ("dnf", PackageManager::Dnf),
+                ("pacman", PackageManager::Pacman),
+                ("zypper", PackageManager::Zypper),
+                ("apk", PackageManager::Apk),
+            ]
+        };
+
+        candidates
+            .iter()
+            .find(|(binary, _)| find_in_path(binary).is_some())
+            .map(|(_, manager)| *manager)
+    }
+
+    // Package identifier in repository of the package manager, None when not available
+    pub fn package_id(&self, package: &str) -> Option<&'static str> {
+        use PackageManager::*;
+        match (self, package) {
+            (Winget, "cmake") => Some("Kitware.CMake"),
+            (Winget, "ninja") => Some("Ninja-build.Ninja"),
+            (Winget, "python") => Some("Python.Python.3.11"),
+            (Winget, "git") => Some("Git.Git"),
+            (Winget, "dfu-util") => None,
+            (Winget, "ccache") => Some("Ccache.Ccache"),
+            (Chocolatey, "dfu-util") => None,
+            (Apt | Dnf, "ninja") => Some("ninja-build"),
+            (Apt | Dnf | Zypper | Apk | Pkg, "python") => Some("python3"),
+            (Apk, "ninja") => Some("samurai"),
+            (Apk, "gcompat") => Some("gcompat"),
+            (Homebrew, "python") => Some("python@3.11"),
+            // Fixes of remediation, Windows and macOS toolchains bring their own
+            (Apt, "openssl") => Some("libssl-dev"),
+            (Dnf, "openssl") => Some("openssl-devel"),
+            (Zypper, "openssl") => Some("libopenssl-devel"),
+            (Apk, "openssl") => Some("openssl-dev"),
+            (Pacman | Pkg, "openssl") => Some("openssl"),
+            (Pkg, "ca-certificates") => Some("ca_root_nss"),
+            (Apt | Dnf | Pacman | Zypper | Apk, "ca-certificates") => Some("ca-certificates"),
+            (Apt, "cc") => Some("build-essential"),
+            (Dnf | Zypper, "cc") => Some("gcc"),
+            (Pacman, "cc") => Some("base-devel"),
+            (Apk, "cc") => Some("build-base"),
+            (_, "cmake") => Some("cmake"),
+            (_, "ninja") => Some("ninja"),
+            (_, "python") => Some("python"),
+            (_, "git") => Some("git"),
+            (_, "dfu-util") => Some("dfu-util"),
+            (_, "ccache") => Some("ccache"),
+            _ => None,
+        }
+    }
+
+    // Program running pkg as root, None when esp-helm already runs as root or when there is
+    // no way to elevate it
+    fn freebsd_elevation() -> Option<&'static str> {
+        if is_root() {
+            return None;
+        }
+        FREEBSD_ELEVATION
+            .into_iter()
+            .find(|program| find_in_path(program).is_some())
+    }
+
+    // Commands to install packages, Linux managers are elevated via pkexec
+    pub fn install_commands(&self, package_ids: &[&str]) -> Vec<(String, Vec<String>)> {
+        let owned = |args: &[&str]| -> Vec<String> {
+            args.iter()
+                .map(|arg| arg.to_string())
+                .chain(package_ids.iter().map(|id| id.to_string()))
+                .collect()
+        };
+
+        match self {
+            PackageManager::Homebrew => vec![("brew".into(), owned(&["install"]))],
+            // winget installs single package per invocation
+            PackageManager::Winget => package_ids
+                .iter()
+                .map(|id| {
+                    (
+                        "winget".into(),
+                        [
+                            "install",
+                            "--id",
+                            *id,
+                            "-e",
+                            "--silent",
+                            "--accept-package-agreements",
+                            "--accept-source-agreements",
+                        ]
+                        .iter()
+                        .map(|arg| arg.to_string())
+                        .collect(),
+                    )
+                })
+                .collect(),
+            PackageManager::Chocolatey => vec![("choco".into(), owned(&["install", "-y"]))],
+            PackageManager::Apt => vec![("pkexec".into(), owned(&["apt-get", "install", "-y"]))],
+            PackageManager::Dnf => vec![("pkexec".into(), owned(&["dnf", "install", "-y"]))],
+            PackageManager::Pacman => {
+                vec![("pkexec".into(), owned(&["pacman", "-S", "--noconfirm"]))]
+            }
+            PackageManager::Zypper => vec![(
+                "pkexec".into(),
+                owned(&["zypper", "--non-interactive", "install"]),
+            )],
+            PackageManager::Apk => vec![("pkexec".into(), owned(&["apk", "add"]))],
+            PackageManager::Pkg => match Self::freebsd_elevation() {
+                Some(program) => vec![(program.into(), owned(&["pkg", "install", "-y"]))],
+                None => vec![("pkg".into(), owned(&["install", "-y"]))],
+            },
+        }
+    }
+}
+
+pub fn plan_installation(packages: &[String]) -> InstallPlan {
+    let manager = PackageManager::detect();
+
+    let statuses: Vec<PackageStatus> = packages
+        .iter()
+        .map(|package| PackageStatus {
+            name: package.clone(),
+            package_id: manager
+                .and_then(|m| m.package_id(package))
+                .map(|id| id.to_string()),
+            installed: is_package_installed(package),
+        })
+        .collect();
+
+    let package_ids: Vec<&str> = statuses
+        .iter()
+        .filter(|status| !status.installed)
+        .filter_map(|status| status.package_id.as_deref())
+        .collect();
+
+    let commands = match manager {
+        Some(manager) if !package_ids.is_empty() => manager
+            .install_commands(&package_ids)
+            .into_iter()
+            .map(|(cmd, args)| format!("{} {}", cmd, args.join(" ")))
+            .collect(),
+        _ => vec![],
+    };
+
+    InstallPlan {
+        manager,
+        packages: statuses,
+        commands,
+    }
+}
+
+// Install packages which are missing, already installed ones are skipped
+pub async fn install_packages(
+    window: Window,
+    app: AppHandle,
+    packages: &[String],
+) -> HelmResult<String> {
+    let manager =
+        PackageManager::detect().ok_or(HelmError::NotFound("supported package manager".into()))?;
+
+    let missing: Vec<&str> = packages
+        .iter()
+        .filter(|package| !is_package_installed(package))
+        .filter_map(|package| manager.package_id(package))
+        .collect();
+    if missing.is_empty() {
+        return Ok("All packages already installed".into());
+    }
+
+    for (cmd, args) in manager.install_commands(&missing) {
+        let command = format!("{} {}", cmd, args.join(" "));
+        if cmd == "pkg" && !is_root() {
+            return Err(HelmError::Permission(format!(
+                "Neither doas nor sudo is installed, run `{}` as root",
+                command
+            )));
+        }
+        info!("Installing packages: {}", command);
+        let args: Vec<&str> = args.iter().map(|arg| arg.as_str()).collect();
+        // doas and sudo ask for password on terminal, the prompt is forwarded to the frontend
+        if cmd == "doas" || cmd == "sudo" {
+            run_external_command_interactive(window.clone(), app.clone(), &cmd, &args).await?;
+            continue;
+        }
+        run_external_command_with_progress(
+            window.clone(),
+            app.clone(),
+            &cmd,
+            &args,
+            "PROGRESS_EVENT",
+        )
+        .await?;
+    }
+
+    Ok(format!("Installed {}", missing.join(", ")))
+}
+
+// Command to list what would be installed without executing anything
+#[tauri::command]
+pub async fn plan_host_dependencies(packages: Option<Vec<String>>) -> HelmResult<InstallPlan> {
+    let packages = packages.unwrap_or_else(default_packages);
+    Ok(plan_installation(&packages))
+}
+
+// Command to install host prerequisites, with dry_run only the plan is returned
+#[tauri::command]
+pub async fn install_host_dependencies(
+    window: Window,
+    app: AppHandle,
+    packages: Option<Vec<String>>,
+    dry_run: bool,
+) -> HelmResult<InstallPlan> {
+    let packages = packages.unwrap_or_else(default_packages);
+    let plan = plan_installation(&packages);
+    if dry_run {
+        return Ok(plan);
+    }
+
+    install_packages(window, app, &packages).await?;
+    Ok(plan_installation(&packages))
+}
@@ -0,0 +1,287 @@
+use std::path::{Path, PathBuf};
+
+use crate::error::HelmResult;
+use crate::i18n::tr;
+use crate::inventory::cargo_home;
+
+// Tools which break builds when wrong copy is found first in PATH
+const CHECKED_TOOLS: [&str; 9] = [
+    "rustup",
+    "cargo",
+    "rustc",
+    "espflash",
+    "clang",
+    "python",
+    "python3",
+    "esptool.py",
+    "cmake",
+];
+// Tools which must come from cargo bin directory when rustup manages it
+const CARGO_TOOLS: [&str; 4] = ["rustup", "cargo", "rustc", "espflash"];
+// PATH entries of distributions which bundle their own compilers and Python
+const SUSPICIOUS_SOURCES: [(&str, &str); 6] = [
+    ("anaconda", "Anaconda"),
+    ("miniconda", "Miniconda"),
+    ("msys64", "MSYS2"),
+    ("mingw", "MinGW"),
+    ("cygwin", "Cygwin"),
+    // Store stub which opens Microsoft Store instead of running Python
+    ("windowsapps", "Windows Store alias"),
+];
+
+#[derive(serde::Serialize)]
+pub struct PathEntry {
+    path: String,
+    exists: bool,
+    // Same directory appears earlier in PATH
+    duplicate: bool,
+    // Distribution which provides this directory, e.g. "Anaconda"
+    source: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+pub struct PathConflict {
+    tool: String,
+    // Every copy in PATH order, first one is used
+    found: Vec<String>,
+    expected: Option<String>,
+    message: String,
+}
+
+#[derive(serde::Serialize)]
+pub struct PathAnalysis {
+    entries: Vec<PathEntry>,
+    conflicts: Vec<PathConflict>,
+    corrected_path: String,
+    // Line for shell profile which sets corrected PATH
+    export: String,
+}
+
+fn path_dirs() -> Vec<PathBuf> {
+    std::env::var_os("PATH")
+        .map(|path| std::env::split_paths(&path).collect())
+        .unwrap_or_default()
+}
+
+fn suspicious_source(dir: &Path) -> Option<&'static str> {
+    let lower = dir.to_string_lossy().to_lowercase();
+    SUSPICIOUS_SOURCES
+        .iter()
+        .find(|(pattern, _)| lower.contains(pattern))
+        .map(|(_, name)| *name)
+}
+
+fn same_dir(a: &Path, b: &Path) -> bool {
+    if cfg!(windows) {
+        a.to_string_lossy().to_lowercase().trim_end_matches('\\')
+            == b.to_string_lossy().to_lowercase().trim_end_matches('\\')
+    } else {
+        a.components().eq(b.components())
+    }
+}
+
+// All copies of the tool in PATH order, unlike find_in_path which returns the first
+fn find_all(dirs: &[PathBuf], name: &str) -> Vec<PathBuf> {
+    dirs.iter()
+        .filter_map(|dir| {
+            #[cfg(windows)]
+            let candidates = [dir.join(format!("{}.exe", name)), dir.join(name)];
+            #[cfg(unix)]
+            let candidates = [dir.join(name)];
+            candidates.into_iter().find(|candidate| candidate.is_file())
+        })
+        .collect()
+}
+
+fn check_tool(dirs: &[PathBuf], tool: &str, cargo_bin: Option<&Path>) -> Option<PathConflict> {
+    let found = find_all(dirs, tool);
+    let first = found.first()?;
+    let first_dir = first.parent()?;
+    let expected = cargo_bin.filter(|bin| {
+        CARGO_TOOLS.contains(&tool) && found.iter().any(|path| path.starts_with(bin))
+    });
+
+    let message = if let Some(bin) = expected.filter(|bin| !same_dir(first_dir, bin)) {
+        let args = [
+            ("tool", tool.to_string()),
+            ("dir", first_dir.to_string_lossy().to_string()),
+            ("expected", bin.to_string_lossy().to_string()),
+        ];
+        tr("conflict-shadowed", &args)
+    } else if let Some(source) = suspicious_source(first_dir) {
+        let args = [
+            ("tool", tool.to_string()),
+            ("source", source.to_string()),
+            ("dir", first_dir.to_string_lossy().to_string()),
+        ];
+        tr("conflict-bundled", &args)
+    } else if found.len() > 1 && !CARGO_TOOLS.contains(&tool) {
+        // Several copies are common and harmless for cargo tools found in expected place
+        let args = [
+            ("count", found.len().to_string()),
+            ("tool", tool.to_string()),
+            ("path", first.to_string_lossy().to_string()),
+        ];
+        tr("conflict-copies", &args)
+    } else {
+        return None;
+    };
+    Some(PathConflict {
+        tool: tool.to_string(),
+        found: found
+            .iter()
+            .map(|path| path.to_string_lossy().to_string())
+            .collect(),
+        expected: expected.map(|bin| bin.to_string_lossy().to_string()),
+        message,
+    })
+}
+
+// Cargo bin first, missing and duplicate entries dropped, bundled distributions last
+fn corrected_dirs(dirs: &[PathBuf], cargo_bin: Option<&Path>) -> Vec<PathBuf> {
+    let mut result: Vec<PathBuf> = vec![];
+    let mut moved: Vec<PathBuf> = vec![];
+    let ordered = cargo_bin
+        .filter(|bin| bin.exists())
+        .map(Path::to_path_buf)
+        .into_iter()
+        .chain(dirs.iter().cloned());
+    for dir in ordered {
+        if !dir.exists()
+            || result
+                .iter()
+                .chain(&moved)
+                .any(|other| same_dir(other, &dir))
+        {
+            continue;
+        }
+        if suspicious_source(&dir).is_some() {
+            moved.push(dir);
+        } else {
+            result.push(dir);
+        }
+    }
+    result.append(&mut moved);
+    result
+}
+
+fn export_line(path: &str) -> String {
+    #[cfg(unix)]
+    let prefix = "export PATH=";
+    #[cfg(windows)]
+    let prefix = "$Env:PATH = ";
+    format!("{}\"{}\"", prefix, path)
+}
+
+pub fn analyze_path() -> PathAnalysis {
+    let dirs = path_dirs();
+    let cargo_bin = cargo_home().map(|home| home.join("bin"));
+    let cargo_bin = cargo_bin.as_deref();
+
+    let entries = dirs
+        .iter()
+        .enumerate()
+        .map(|(index, dir)| PathEntry {
+            path: dir.to_string_lossy().to_string(),
+            exists: dir.exists(),
+            duplicate: dirs[..index].iter().any(|other| same_dir(other, dir)),
+            source: suspicious_source(dir).map(str::to_string),
+        })
+        .collect();
+    let conflicts = CHECKED_TOOLS
+        .iter()
+        .filter_map(|tool| check_tool(&dirs, tool, cargo_bin))
+        .collect();
+    let corrected_path = std::env::join_paths(corrected_dirs(&dirs, cargo_bin))
+        .map(|path| path.to_string_lossy().to_string())
+        .unwrap_or_default();
+    PathAnalysis {
+        entries,
+        conflicts,
+        export: export_line(&corrected_path),
+        corrected_path,
+    }
+}
+
+// Command to list PATH entries and tools shadowed by wrong copies, with corrected PATH export
+#[tauri::command]
+pub async fn get_path_analysis() -> HelmResult<PathAnalysis> {
+    Ok(analyze_path())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    // Fresh directories of PATH, each tool is an empty file. Directories are removed when the
+    // returned TempDir is dropped.
+    fn dirs(tools: &[(&str, &str)]) -> (TempDir, PathBuf) {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path().to_path_buf();
+        for dir in ["usr/bin", "cargo/bin", "anaconda3/bin"] {
+            std::fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        for (dir, tool) in tools {
+            std::fs::write(root.join(dir).join(tool), "").unwrap();
+        }
+        (temp, root)
+    }
+
+    #[test]
+    fn cargo_tool_shadowed_by_earlier_copy() {
+        let (_temp, root) = dirs(&[("usr/bin", "cargo"), ("cargo/bin", "cargo")]);
+        let (usr_bin, cargo_bin) = (root.join("usr/bin"), root.join("cargo/bin"));
+        let path = [usr_bin.clone(), cargo_bin.clone()];
+
+        let conflict = check_tool(&path, "cargo", Some(&cargo_bin)).unwrap();
+        let found: Vec<String> = [usr_bin, cargo_bin.clone()]
+            .iter()
+            .map(|dir| dir.join("cargo").to_string_lossy().to_string())
+            .collect();
+        assert_eq!(conflict.tool, "cargo");
+        assert_eq!(conflict.found, found);
+        assert_eq!(
+            conflict.expected,
+            Some(cargo_bin.to_string_lossy().to_string())
+        );
+    }
+
+    #[test]
+    fn cargo_tool_first_in_cargo_bin() {
+        let (_temp, root) = dirs(&[("usr/bin", "cargo"), ("cargo/bin", "cargo")]);
+        let cargo_bin = root.join("cargo/bin");
+        let path = [cargo_bin.clone(), root.join("usr/bin")];
+
+        assert!(check_tool(&path, "cargo", Some(&cargo_bin)).is_none());
+    }
+
+    #[test]
+    fn bundled_python_first() {
+        let (_temp, root) = dirs(&[("anaconda3/bin", "python3"), ("usr/bin", "python3")]);
+        let path = [root.join("anaconda3/bin"), root.join("usr/bin")];
+
+        let conflict = check_tool(&path, "python3", None).unwrap();
+        assert_eq!(conflict.found.len(), 2);
+        assert_eq!(conflict.expected, None);
+        assert_eq!(suspicious_source(&path[0]), Some("Anaconda"));
+    }
+
+    #[test]
+    fn corrected_path_order() {
+        let (_temp, root) = dirs(&[]);
+        let cargo_bin = root.join("cargo/bin");
+        let path = [
+            root.join("anaconda3/bin"),
+            root.join("usr/bin"),
+            root.join("missing"),
+            root.join("usr/bin"),
+            cargo_bin.clone(),
+        ];
+
+        assert_eq!(
+            corrected_dirs(&path, Some(&cargo_bin)),
+            vec![cargo_bin, root.join("usr/bin"), root.join("anaconda3/bin")]
+        );
+    }
+}
@@ -0,0 +1,156 @@
+use std::sync::Mutex;
+
+use tauri::{State, Window};
+
+use crate::app_state::AppState;
+use crate::error::HelmResult;
+use crate::events::{emit_event_scoped, HelmEvent};
+use crate::monitor::SessionId;
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize, schemars::JsonSchema)]
+pub struct PlotValue {
+    name: String,
+    value: f64,
+}
+
+// Numeric values of one monitor line
+#[derive(Clone, Debug, PartialEq, serde::Serialize, schemars::JsonSchema)]
+pub struct PlotSample {
+    session: SessionId,
+    timestamp: u128,
+    values: Vec<PlotValue>,
+}
+
+impl HelmEvent for PlotSample {
+    const NAMES: &'static [&'static str] = &["monitor-plot"];
+}
+
+// Teleplot: ">name:value" or ">name:timestamp:value", optionally followed by "§unit" and
+// "|flags"
+fn parse_teleplot(text: &str) -> Option<(Option<u128>, PlotValue)> {
+    let text = text.strip_prefix('>')?;
+    let text = text.split(['§', '|']).next().unwrap_or_default();
+    let (name, rest) = text.split_once(':')?;
+    if name.is_empty() || name.contains(char::is_whitespace) {
+        return None;
+    }
+    let (timestamp, value) = match rest.split_once(':') {
+        Some((timestamp, value)) => (Some(timestamp.trim().parse().ok()?), value),
+        None => (None, rest),
+    };
+    let value = PlotValue {
+        name: name.to_string(),
+        value: value.trim().parse::<f64>().ok().filter(|v| v.is_finite())?,
+    };
+    Some((timestamp, value))
+}
+
+// CSV or Arduino plotter: "1.5,2,3" or "temp:21.5,hum:40", separated by comma or tab.
+// Unlabeled values are named by their column.
+fn parse_csv(text: &str) -> Option<Vec<PlotValue>> {
+    text.split([',', '\t'])
+        .enumerate()
+        .map(|(column, field)| {
+            let (name, value) = match field.split_once(':') {
+                Some((name, value)) => (name.trim().to_string(), value),
+                None => (column.to_string(), field),
+            };
+            if name.is_empty() {
+                return None;
+            }
+            let value = value.trim().parse::<f64>().ok().filter(|v| v.is_finite())?;
+            Some(PlotValue { name, value })
+        })
+        .collect()
+}
+
+// Lines which are not entirely numeric data are not plotted, so log messages are skipped
+pub fn parse_plot_line(session: SessionId, timestamp: u128, text: &str) -> Option<PlotSample> {
+    let text = text.trim();
+    if text.is_empty() {
+        return None;
+    }
+    let (timestamp, values) = if text.starts_with('>') {
+        let (sent, value) = parse_teleplot(text)?;
+        (sent.unwrap_or(timestamp), vec![value])
+    } else {
+        (timestamp, parse_csv(text)?)
+    };
+    Some(PlotSample {
+        session,
+        timestamp,
+        values,
+    })
+}
+
+// Samples of one session are emitted also on "monitor-plot/<session>"
+pub fn emit_sample(window: &Window, sample: &PlotSample) {
+    emit_event_scoped(window, sample, sample.session);
+}
+
+// Command to turn plot parsing of monitor on port on or off, without port for all monitors
+// including ones started later
+#[tauri::command]
+pub async fn set_monitor_plot(
+    state_mutex: State<'_, Mutex<AppState>>,
+    enabled: bool,
+    port: Option<String>,
+) -> HelmResult<String> {
+    let mut state = state_mutex.lock().unwrap();
+    state.monitor.set_plot(port.as_deref(), enabled)?;
+    Ok(format!(
+        "Plotting {}",
+        if enabled { "enabled" } else { "disabled" }
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(text: &str) -> Option<Vec<(String, f64)>> {
+        parse_plot_line(1, 1000, text).map(|sample| {
+            sample
+                .values
+                .into_iter()
+                .map(|value| (value.name, value.value))
+                .collect()
+        })
+    }
+
+    #[test]
+    fn parses_teleplot() {
+        assert_eq!(values(">temp:21.5"), Some(vec![("temp".into(), 21.5)]));
+        assert_eq!(
+            values(">accel_x:-0.25§g|g"),
+            Some(vec![("accel_x".into(), -0.25)])
+        );
+        let sample = parse_plot_line(1, 1000, ">rpm:1234:3000").unwrap();
+        assert_eq!(sample.timestamp, 1234);
+        assert_eq!(values("> temp:21.5"), None);
+    }
+
+    #[test]
+    fn parses_csv() {
+        assert_eq!(
+            values("1.5,2\t-3"),
+            Some(vec![
+                ("0".into(), 1.5),
+                ("1".into(), 2.0),
+                ("2".into(), -3.0)
+            ])
+        );
+        assert_eq!(
+            values("temp:21.5,hum:40"),
+            Some(vec![("temp".into(), 21.5), ("hum".into(), 40.0)])
+        );
+    }
+
+    #[test]
+    fn skips_log_lines() {
+        assert_eq!(values("I (31) boot: ESP-IDF v5.1"), None);
+        assert_eq!(values("wifi: connected, rssi -40"), None);
+        assert_eq!(values("42 apples"), None);
+        assert_eq!(values(""), None);
+    }
+}
@@ -0,0 +1,141 @@
+use std::path::{Path, PathBuf};
+
+use log::info;
+
+use crate::audit::{audit_path, AuditAction};
+use crate::error::{HelmError, HelmResult};
+use crate::esp_clang::{export_file_path, ESPUP_EXPORT_FILE};
+
+// File next to esp-helm executable which switches it to portable mode
+const PORTABLE_MARKER: &str = "esp-helm-portable";
+
+#[cfg(unix)]
+pub const LAUNCHER_NAME: &str = "esp-env.sh";
+#[cfg(windows)]
+pub const LAUNCHER_NAME: &str = "esp-env.ps1";
+
+#[derive(serde::Serialize)]
+pub struct PortableStatus {
+    portable: bool,
+    root: Option<String>,
+}
+
+// Directory of esp-helm executable when it runs in portable mode
+pub fn portable_root() -> Option<PathBuf> {
+    let exe = std::env::current_exe().ok()?;
+    let dir = exe.parent()?;
+    dir.join(PORTABLE_MARKER)
+        .exists()
+        .then(|| dir.to_path_buf())
+}
+
+// Toolchains of portable installation, recomputed on every start so the directory can move
+pub fn portable_tools_dir() -> Option<PathBuf> {
+    portable_root().map(|root| root.join("tools"))
+}
+
+// Settings and other state of esp-helm, kept next to executable in portable mode
+pub fn app_config_dir() -> Option<PathBuf> {
+    match portable_root() {
+        Some(root) => Some(root.join("data")),
+        None => dirs::config_dir().map(|dir| dir.join("esp-helm")),
+    }
+}
+
+pub fn app_data_dir() -> Option<PathBuf> {
+    match portable_root() {
+        Some(root) => Some(root.join("data")),
+        None => dirs::data_local_dir().map(|dir| dir.join("esp-helm")),
+    }
+}
+
+// Export file with every absolute path below root replaced by variable of launcher
+fn relocatable_exports(root: &Path, root_variable: &str) -> String {
+    let content = export_file_path()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .unwrap_or_default();
+    content.replace(&root.to_string_lossy().to_string(), root_variable)
+}
+
+#[cfg(unix)]
+fn launcher(root: &Path) -> String {
+    format!(
+        "#!/usr/bin/env bash\n\
+         # Generated by esp-helm, source this file: . ./{launcher}\n\
+         ESP_HELM_ROOT=\"$(cd \"$(dirname \"${{BASH_SOURCE[0]:-$0}}\")\" && pwd)\"\n\
+         export RUSTUP_HOME=\"$ESP_HELM_ROOT/tools/rustup\"\n\
+         export CARGO_HOME=\"$ESP_HELM_ROOT/tools/cargo\"\n\
+         export IDF_TOOLS_PATH=\"$ESP_HELM_ROOT/tools/espressif\"\n\
+         export PATH=\"$CARGO_HOME/bin:$PATH\"\n\
+         {exports}",
+        launcher = LAUNCHER_NAME,
+        exports = relocatable_exports(root, "$ESP_HELM_ROOT"),
+    )
+}
+
+#[cfg(windows)]
+fn launcher(root: &Path) -> String {
+    format!(
+        "# Generated by esp-helm, run in PowerShell: . .\\{launcher}\r\n\
+         $EspHelmRoot = $PSScriptRoot\r\n\
+         $Env:RUSTUP_HOME = \"$EspHelmRoot\\tools\\rustup\"\r\n\
+         $Env:CARGO_HOME = \"$EspHelmRoot\\tools\\cargo\"\r\n\
+         $Env:IDF_TOOLS_PATH = \"$EspHelmRoot\\tools\\espressif\"\r\n\
+         $Env:PATH = \"$Env:CARGO_HOME\\bin;$Env:PATH\"\r\n\
+         {exports}",
+        launcher = LAUNCHER_NAME,
+        exports = relocatable_exports(root, "$EspHelmRoot"),
+    )
+}
+
+// Launcher is rewritten after each installation, so it contains variables exported by espup
+pub fn write_launcher() -> HelmResult<Option<PathBuf>> {
+    let Some(root) = portable_root() else {
+        return Ok(None);
+    };
+    let path = root.join(LAUNCHER_NAME);
+    std::fs::write(&path, launcher(&root))?;
+    audit_path(AuditAction::ModifyEnvironment, &path);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))?;
+    }
+    info!("Portable launcher written to {}", path.display());
+    Ok(Some(path))
+}
+
+#[tauri::command]
+pub async fn get_portable_status() -> HelmResult<PortableStatus> {
+    let root = portable_root();
+    Ok(PortableStatus {
+        portable: root.is_some(),
+        root: root.map(|root| root.to_string_lossy().to_string()),
+    })
+}
+
+// Command to copy esp-helm to empty directory, e.g. USB stick, and switch the copy to
+// portable mode. Tools are installed when the copy is started.
+#[tauri::command]
+pub async fn create_portable_installation(path: String) -> HelmResult<String> {
+    let root = PathBuf::from(&path);
+    std::fs::create_dir_all(&root)?;
+    if root.read_dir()?.next().is_some() {
+        return Err(HelmError::Validation(format!("{} is not empty", path)));
+    }
+
+    let exe = std::env::current_exe()?;
+    let target = root.join(exe.file_name().unwrap_or_default());
+    std::fs::copy(&exe, &target)?;
+    std::fs::write(
+        root.join(PORTABLE_MARKER),
+        "Delete this file to use settings and tools from home directory\n",
+    )?;
+    std::fs::create_dir_all(root.join("tools"))?;
+    std::fs::create_dir_all(root.join("data"))?;
+    // Export file is created by espup, launcher works without it
+    std::fs::write(root.join(ESPUP_EXPORT_FILE), "")?;
+
+    info!("Portable installation created in {}", path);
+    Ok(target.to_string_lossy().to_string())
+}
@@ -0,0 +1,275 @@
+// Suspend, resume and kill of child processes, used to pause and abort long running
+// installations. Build tools start their own processes, e.g. idf.py runs cmake and ninja,
+// so the whole tree of the child is paused or killed.
+
+use tokio::process::Command;
+
+// Child leads own process group, signals to the group reach everything it starts
+#[cfg(unix)]
+pub fn new_process_group(command: &mut Command) {
+    command.process_group(0);
+}
+
+// Child starts suspended and runs only after attach puts it to job object, so nothing it
+// starts escapes the job. Also hides console window, replaces other creation flags.
+#[cfg(windows)]
+pub fn new_process_group(command: &mut Command) {
+    use windows_sys::Win32::System::Threading::CREATE_SUSPENDED;
+    const CREATE_NO_WINDOW: u32 = 0x08000000;
+    command.creation_flags(CREATE_SUSPENDED | CREATE_NO_WINDOW);
+}
+
+// Child and processes started by it, attached right after spawn. Dropping it kills the
+// whole tree unless finish was called, kill_on_drop of tokio reaches the child alone.
+pub struct ProcessTree {
+    pid: u32,
+    finished: bool,
+    #[cfg(windows)]
+    job: windows_sys::Win32::Foundation::HANDLE,
+}
+
+#[cfg(unix)]
+impl ProcessTree {
+    pub fn attach(pid: u32) -> Self {
+        ProcessTree {
+            pid,
+            finished: false,
+        }
+    }
+
+    // Stopped processes are killed too, no need to continue them first
+    pub fn kill(&self) -> std::io::Result<()> {
+        self.signal(libc::SIGKILL)
+    }
+
+    pub fn suspend(&self) -> std::io::Result<()> {
+        self.signal(libc::SIGSTOP)
+    }
+
+    pub fn resume(&self) -> std::io::Result<()> {
+        self.signal(libc::SIGCONT)
+    }
+
+    // Group id of group leader is its pid. Child spawned without new_process_group, e.g. by
+    // other crates, gets the signal alone.
+    fn signal(&self, signal: libc::c_int) -> std::io::Result<()> {
+        if self.pid == 0 {
+            return Err(std::io::ErrorKind::InvalidInput.into());
+        }
+        let pid = self.pid as libc::pid_t;
+        send_signal(-pid, signal).or_else(|_| send_signal(pid, signal))
+    }
+}
+
+// Child exited by itself, processes it left running are not touched. Group id may be
+// reused once the group is empty, so it is not signalled after that.
+impl ProcessTree {
+    pub fn finish(&mut self) {
+        self.finished = true;
+    }
+}
+
+#[cfg(unix)]
+impl Drop for ProcessTree {
+    fn drop(&mut self) {
+        if !self.finished {
+            let _ = self.kill();
+        }
+    }
+}
+
+#[cfg(unix)]
+fn send_signal(pid: libc::pid_t, signal: libc::c_int) -> std::io::Result<()> {
+    // Safety: kill has no memory safety requirements
+    let result = unsafe { libc::kill(pid, signal) };
+    if result == 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error())
+    }
+}
+
+// Processes in job object, ids which do not fit are not paused
+#[cfg(windows)]
+const MAX_JOB_PROCESSES: usize = 512;
+
+#[cfg(windows)]
+impl ProcessTree {
+    // Processes started by the child later are added to the job automatically. Child was
+    // spawned suspended by new_process_group and is resumed once it is in the job.
+    pub fn attach(pid: u32) -> Self {
+        use windows_sys::Win32::Foundation::CloseHandle;
+        use windows_sys::Win32::System::JobObjects::{AssignProcessToJobObject, CreateJobObjectW};
+        use windows_sys::Win32::System::Threading::{
+            OpenProcess, ResumeThread, PROCESS_SET_QUOTA, PROCESS_TERMINATE,
+        };
+
+        unsafe {
+            let job = CreateJobObjectW(std::ptr::null(), std::ptr::null());
+            if job != 0 && !set_kill_on_close(job, true) {
+                log::info!(
+                    "Failed to set job limits: {:?}",
+                    std::io::Error::last_os_error()
+                );
+            }
+            let process = OpenProcess(PROCESS_SET_QUOTA | PROCESS_TERMINATE, 0, pid);
+            if job != 0 && process != 0 && AssignProcessToJobObject(job, process) == 0 {
+                log::info!(
+                    "Failed to assign process {} to job: {:?}",
+                    pid,
+                    std::io::Error::last_os_error()
+                );
+            }
+            if process != 0 {
+                CloseHandle(process);
+            }
+            if let Err(err) = for_each_thread(pid, |thread| {
+                ResumeThread(thread);
+            }) {
+                log::info!("Failed to start process {}: {:?}", pid, err);
+            }
+            ProcessTree {
+                pid,
+                finished: false,
+                job,
+            }
+        }
+    }
+
+    // Child alone when job object could not be created, caller kills it then
+    pub fn kill(&self) -> std::io::Result<()> {
+        use windows_sys::Win32::System::JobObjects::TerminateJobObject;
+
+        if self.job == 0 {
+            return Err(std::io::ErrorKind::Unsupported.into());
+        }
+        if unsafe { TerminateJobObject(self.job, 1) } == 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    pub fn suspend(&self) -> std::io::Result<()> {
+        for pid in self.pids() {
+            for_each_thread(pid, |thread| unsafe {
+                windows_sys::Win32::System::Threading::SuspendThread(thread);
+            })?;
+        }
+        Ok(())
+    }
+
+    pub fn resume(&self) -> std::io::Result<()> {
+        for pid in self.pids() {
+            for_each_thread(pid, |thread| unsafe {
+                windows_sys::Win32::System::Threading::ResumeThread(thread);
+            })?;
+        }
+        Ok(())
+    }
+
+    // Child alone when job object could not be created
+    fn pids(&self) -> Vec<u32> {
+        use windows_sys::Win32::System::JobObjects::{
+            JobObjectBasicProcessIdList, QueryInformationJobObject,
+        };
+
+        // JOBOBJECT_BASIC_PROCESS_ID_LIST with room for MAX_JOB_PROCESSES ids
+        #[repr(C)]
+        struct ProcessIdList {
+            assigned: u32,
+            listed: u32,
+            ids: [usize; MAX_JOB_PROCESSES],
+        }
+
+        if self.job == 0 {
+            return vec![self.pid];
+        }
+        unsafe {
+            let mut list: ProcessIdList = std::mem::zeroed();
+            let ok = QueryInformationJobObject(
+                self.job,
+                JobObjectBasicProcessIdList,
+                &mut list as *mut ProcessIdList as *mut std::ffi::c_void,
+                std::mem::size_of::<ProcessIdList>() as u32,
+                std::ptr::null_mut(),
+            );
+            if ok == 0 {
+                return vec![self.pid];
+            }
+            let listed = (list.listed as usize).min(MAX_JOB_PROCESSES);
+            list.ids[..listed].iter().map(|id| *id as u32).collect()
+        }
+    }
+}
+
+// Closing the handle ends the processes in the job, unless the child finished by itself
+#[cfg(windows)]
+impl Drop for ProcessTree {
+    fn drop(&mut self) {
+        if self.job != 0 {
+            unsafe {
+                if self.finished {
+                    set_kill_on_close(self.job, false);
+                }
+                windows_sys::Win32::Foundation::CloseHandle(self.job);
+            }
+        }
+    }
+}
+
+#[cfg(windows)]
+unsafe fn set_kill_on_close(job: windows_sys::Win32::Foundation::HANDLE, kill: bool) -> bool {
+    use windows_sys::Win32::System::JobObjects::{
+        JobObjectExtendedLimitInformation, SetInformationJobObject,
+        JOBOBJECT_EXTENDED_LIMIT_INFORMATION, JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE,
+    };
+
+    let mut limits: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = std::mem::zeroed();
+    if kill {
+        limits.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+    }
+    SetInformationJobObject(
+        job,
+        JobObjectExtendedLimitInformation,
+        &limits as *const JOBOBJECT_EXTENDED_LIMIT_INFORMATION as *const std::ffi::c_void,
+        std::mem::size_of::<JOBOBJECT_EXTENDED_LIMIT_INFORMATION>() as u32,
+    ) != 0
+}
+
+// Windows has no process level suspend in public API, so each thread is suspended
+#[cfg(windows)]
+fn for_each_thread<F>(pid: u32, action: F) -> std::io::Result<()>
+where
+    F: Fn(windows_sys::Win32::Foundation::HANDLE),
+{
+    use windows_sys::Win32::Foundation::{CloseHandle, INVALID_HANDLE_VALUE};
+    use windows_sys::Win32::System::Diagnostics::ToolHelp::{
+        CreateToolhelp32Snapshot, Thread32First, Thread32Next, TH32CS_SNAPTHREAD, THREADENTRY32,
+    };
+    use windows_sys::Win32::System::Threading::{OpenThread, THREAD_SUSPEND_RESUME};
+
+    unsafe {
+        let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPTHREAD, 0);
+        if snapshot == INVALID_HANDLE_VALUE {
+            return Err(std::io::Error::last_os_error());
+        }
+
+        let mut entry: THREADENTRY32 = std::mem::zeroed();
+        entry.dwSize = std::mem::size_of::<THREADENTRY32>() as u32;
+
+        let mut has_entry = Thread32First(snapshot, &mut entry) != 0;
+        while has_entry {
+            if entry.th32OwnerProcessID == pid {
+                let thread = OpenThread(THREAD_SUSPEND_RESUME, 0, entry.th32ThreadID);
+                if thread != 0 {
+                    action(thread);
+                    CloseHandle(thread);
+                }
+            }
+            has_entry = Thread32Next(snapshot, &mut entry) != 0;
+        }
+
+        CloseHandle(snapshot);
+    }
+    Ok(())
+}
@@ -0,0 +1,197 @@
+use std::path::Path;
+
+use log::info;
+
+use crate::audit::{audit_path, AuditAction};
+use crate::error::{HelmError, HelmResult};
+use crate::verify::chip_target;
+
+#[derive(Clone, Copy, serde::Deserialize)]
+pub enum ProjectTemplate {
+    // Blocking main loop with delay
+    Blocking,
+    // Async main with embassy executor and example task
+    Embassy,
+}
+
+const BLOCKING_MAIN: &str = r#"#![no_std]
+#![no_main]
+
+use esp_backtrace as _;
+use esp_hal::delay::Delay;
+use esp_hal::prelude::*;
+use log::info;
+
+#[entry]
+fn main() -> ! {
+    esp_println::logger::init_logger_from_env();
+    let _peripherals = esp_hal::init(esp_hal::Config::default());
+    let delay = Delay::new();
+
+    loop {
+        info!("Hello world!");
+        delay.delay(500.millis());
+    }
+}
+"#;
+
+const EMBASSY_MAIN: &str = r#"#![no_std]
+#![no_main]
+
+use embassy_executor::Spawner;
+use embassy_time::{Duration, Timer};
+use esp_backtrace as _;
+use esp_hal::timer::timg::TimerGroup;
+use log::info;
+
+#[embassy_executor::task]
+async fn ticker() {
+    loop {
+        info!("Tick from task");
+        Timer::after(Duration::from_millis(1000)).await;
+    }
+}
+
+#[esp_hal_embassy::main]
+async fn main(spawner: Spawner) {
+    esp_println::logger::init_logger_from_env();
+    let peripherals = esp_hal::init(esp_hal::Config::default());
+
+    // Time driver of embassy-time, TIMG0 is available on all chips
+    let timg0 = TimerGroup::new(peripherals.TIMG0);
+    esp_hal_embassy::init(timg0.timer0);
+
+    spawner.spawn(ticker()).unwrap();
+
+    loop {
+        info!("Hello from main");
+        Timer::after(Duration::from_millis(5000)).await;
+    }
+}
+"#;
+
+fn cargo_toml(name: &str, chip: &str, template: ProjectTemplate) -> String {
+    let mut dependencies = format!(
+        r#"esp-backtrace = {{ version = "0.15", features = ["{chip}", "exception-handler", "panic-handler", "println"] }}
+esp-hal = {{ version = "0.23", features = ["{chip}"] }}
+esp-println = {{ version = "0.13", features = ["{chip}", "log"] }}
+log = "0.4"
+"#
+    );
+    if let ProjectTemplate::Embassy = template {
+        dependencies.push_str(&format!(
+            r#"embassy-executor = {{ version = "0.7", features = ["task-arena-size-12288"] }}
+embassy-time = "0.4"
+esp-hal-embassy = {{ version = "0.6", features = ["{chip}"] }}
+"#
+        ));
+    }
+
+    format!(
+        r#"[package]
+name = "{name}"
+version = "0.1.0"
+edition = "2021"
+
+[dependencies]
+{dependencies}
+[profile.dev]
+# Rust debug builds are too slow for most of the peripherals
+opt-level = "s"
+
+[profile.release]
+codegen-units = 1
+debug = 2
+lto = "fat"
+opt-level = "s"
+"#
+    )
+}
+
+fn cargo_config(target: &str, xtensa: bool) -> String {
+    let mut rustflags = vec!["\"-C\", \"link-arg=-Tlinkall.x\""];
+    if xtensa {
+        rustflags.push("\"-C\", \"link-arg=-nostartfiles\"");
+    } else {
+        rustflags.push("\"-C\", \"force-frame-pointers\"");
+    }
+    let mut config = format!(
+        r#"[target.{target}]
+runner = "espflash flash --monitor"
+
+[env]
+ESP_LOG = "info"
+
+[build]
+rustflags = [{}]
+target = "{target}"
+"#,
+        rustflags.join(", ")
+    );
+    // Xtensa targets have no prebuilt core library
+    if xtensa {
+        config.push_str("\n[unstable]\nbuild-std = [\"core\"]\n");
+    }
+    config
+}
+
+fn rust_toolchain(target: &str, xtensa: bool) -> String {
+    if xtensa {
+        "[toolchain]\nchannel = \"esp\"\n".into()
+    } else {
+        format!(
+            "[toolchain]\nchannel = \"stable\"\ncomponents = [\"rust-src\"]\ntargets = [\"{}\"]\n",
+            target
+        )
+    }
+}
+
+pub fn generate(path: &Path, name: &str, chip: &str, template: ProjectTemplate) -> HelmResult<()> {
+    let (_, target, _) =
+        chip_target(chip).ok_or(HelmError::Validation(format!("Unsupported chip {}", chip)))?;
+    let xtensa = target.starts_with("xtensa");
+
+    if path
+        .read_dir()
+        .map(|mut dir| dir.next().is_some())
+        .unwrap_or(false)
+    {
+        return Err(HelmError::Validation(format!(
+            "{} is not empty",
+            path.display()
+        )));
+    }
+    std::fs::create_dir_all(path.join("src"))?;
+    std::fs::create_dir_all(path.join(".cargo"))?;
+
+    let main = match template {
+        ProjectTemplate::Blocking => BLOCKING_MAIN,
+        ProjectTemplate::Embassy => EMBASSY_MAIN,
+    };
+    std::fs::write(path.join("Cargo.toml"), cargo_toml(name, chip, template))?;
+    std::fs::write(path.join("src").join("main.rs"), main)?;
+    std::fs::write(
+        path.join(".cargo").join("config.toml"),
+        cargo_config(target, xtensa),
+    )?;
+    std::fs::write(
+        path.join("rust-toolchain.toml"),
+        rust_toolchain(target, xtensa),
+    )?;
+    std::fs::write(path.join(".gitignore"), "/target\n")?;
+    audit_path(AuditAction::CreateDir, path);
+    Ok(())
+}
+
+// Command to create no_std esp-hal project for the chip
+#[tauri::command]
+pub async fn generate_project(
+    path: String,
+    name: String,
+    chip: String,
+    template: ProjectTemplate,
+) -> HelmResult<String> {
+    info!("Generating project {} for {} in {}", name, chip, path);
+    generate(Path::new(&path), &name, &chip, template)?;
+    Ok(format!("Project {} created", name))
+}
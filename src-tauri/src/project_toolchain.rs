@@ -0,0 +1,269 @@
+use std::path::{Path, PathBuf};
+
+use log::info;
+use tauri::{AppHandle, Window};
+
+use crate::audit::{audit_path, AuditAction};
+use crate::cargo_tools::cargo_bin;
+use crate::error::{HelmError, HelmResult};
+use crate::external_command::run_external_command_with_progress;
+use crate::services::{services, ProcessRunner};
+
+#[derive(Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct ToolchainSection {
+    pub channel: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub components: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub targets: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+}
+
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+struct ToolchainFile {
+    toolchain: ToolchainSection,
+}
+
+#[derive(serde::Serialize)]
+pub struct ProjectToolchain {
+    // rust-toolchain.toml or legacy rust-toolchain, None when project has no override
+    file: Option<String>,
+    toolchain: ToolchainSection,
+    installed: bool,
+    missing_components: Vec<String>,
+    missing_targets: Vec<String>,
+}
+
+fn toolchain_file(project: &Path) -> Option<PathBuf> {
+    ["rust-toolchain.toml", "rust-toolchain"]
+        .iter()
+        .map(|name| project.join(name))
+        .find(|path| path.exists())
+}
+
+fn read_toolchain(path: &Path) -> HelmResult<ToolchainSection> {
+    let content = std::fs::read_to_string(path)?;
+    parse_toolchain(&content)
+        .map_err(|e| HelmError::Validation(format!("{}: {}", path.display(), e)))
+}
+
+// Legacy rust-toolchain might contain just the channel name
+fn parse_toolchain(content: &str) -> Result<ToolchainSection, toml::de::Error> {
+    if !content.contains("[toolchain]") {
+        return Ok(ToolchainSection {
+            channel: Some(content.trim().to_string()),
+            ..Default::default()
+        });
+    }
+    toml::from_str::<ToolchainFile>(content).map(|file| file.toolchain)
+}
+
+fn rustup_lines(process: &dyn ProcessRunner, args: &[&str]) -> Option<Vec<String>> {
+    let rustup = cargo_bin("rustup").ok()?;
+    let output = process.output(&rustup.to_string_lossy(), args).ok()?;
+    if !output.success {
+        return None;
+    }
+    Some(
+        output
+            .stdout
+            .lines()
+            .map(|line| line.trim().to_string())
+            .collect(),
+    )
+}
+
+// rustup lists "nightly-x86_64-unknown-linux-gnu (default)" or just "esp"
+pub fn is_channel_installed(process: &dyn ProcessRunner, channel: &str) -> bool {
+    rustup_lines(process, &["toolchain", "list"])
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|line| line.split_whitespace().next())
+        .any(|name| name == channel || name.starts_with(&format!("{}-", channel)))
+}
+
+// Components and targets of custom esp toolchain are not managed by rustup
+fn missing_items(
+    process: &dyn ProcessRunner,
+    channel: &str,
+    kind: &str,
+    required: &[String],
+) -> Vec<String> {
+    let args = [kind, "list", "--installed", "--toolchain", channel];
+    let Some(installed) = rustup_lines(process, &args) else {
+        return vec![];
+    };
+    missing_from(&installed, required)
+}
+
+// rustup lists components with host triple, e.g. "rust-src" as "rust-src" but "clippy" as
+// "clippy-x86_64-unknown-linux-gnu"
+fn missing_from(installed: &[String], required: &[String]) -> Vec<String> {
+    required
+        .iter()
+        .filter(|item| {
+            !installed
+                .iter()
+                .any(|line| line == *item || line.starts_with(&format!("{}-", item)))
+        })
+        .cloned()
+        .collect()
+}
+
+pub fn inspect_project_toolchain(
+    process: &dyn ProcessRunner,
+    project: &Path,
+) -> HelmResult<ProjectToolchain> {
+    let Some(file) = toolchain_file(project) else {
+        return Ok(ProjectToolchain {
+            file: None,
+            toolchain: ToolchainSection::default(),
+            installed: true,
+            missing_components: vec![],
+            missing_targets: vec![],
+        });
+    };
+
+    let toolchain = read_toolchain(&file)?;
+    let channel = toolchain.channel.clone().unwrap_or_else(|| "stable".into());
+    let installed = is_channel_installed(process, &channel);
+    let (missing_components, missing_targets) = if installed {
+        (
+            missing_items(process, &channel, "component", &toolchain.components),
+            missing_items(process, &channel, "target", &toolchain.targets),
+        )
+    } else {
+        (toolchain.components.clone(), toolchain.targets.clone())
+    };
+
+    Ok(ProjectToolchain {
+        file: Some(file.to_string_lossy().to_string()),
+        toolchain,
+        installed,
+        missing_components,
+        missing_targets,
+    })
+}
+
+// Command to read toolchain override of the project and compare it with installed toolchains
+#[tauri::command]
+pub async fn get_project_toolchain(
+    app: AppHandle,
+    project_path: String,
+) -> HelmResult<ProjectToolchain> {
+    inspect_project_toolchain(services(&app).process.as_ref(), Path::new(&project_path))
+}
+
+// Command to write rust-toolchain.toml, legacy rust-toolchain file is replaced
+#[tauri::command]
+pub async fn set_project_toolchain(
+    app: AppHandle,
+    project_path: String,
+    toolchain: ToolchainSection,
+) -> HelmResult<ProjectToolchain> {
+    let project = Path::new(&project_path);
+    let content = toml::to_string(&ToolchainFile { toolchain })
+        .map_err(|e| HelmError::Other(format!("Failed to serialize toolchain: {}", e)))?;
+    std::fs::write(project.join("rust-toolchain.toml"), content)?;
+    audit_path(AuditAction::WriteFile, &project.join("rust-toolchain.toml"));
+
+    let legacy = project.join("rust-toolchain");
+    if legacy.exists() {
+        std::fs::remove_file(legacy)?;
+    }
+    inspect_project_toolchain(services(&app).process.as_ref(), project)
+}
+
+// Command to install channel, components and targets required by the project.
+// esp channel is installed by espup, the others by rustup.
+#[tauri::command]
+pub async fn fix_project_toolchain(
+    window: Window,
+    app: AppHandle,
+    project_path: String,
+) -> HelmResult<ProjectToolchain> {
+    let process = services(&app).process;
+    let status = inspect_project_toolchain(process.as_ref(), Path::new(&project_path))?;
+    let channel = status
+        .toolchain
+        .channel
+        .clone()
+        .unwrap_or_else(|| "stable".into());
+
+    if channel == "esp" {
+        if !status.installed {
+            info!("Installing esp toolchain for {}", project_path);
+            let espup = cargo_bin("espup")?.to_string_lossy().to_string();
+            run_external_command_with_progress(window, app, &espup, &["install"], "PROGRESS_EVENT")
+                .await?;
+        }
+    } else if !status.installed
+        || !status.missing_components.is_empty()
+        || !status.missing_targets.is_empty()
+    {
+        info!("Installing {} toolchain for {}", channel, project_path);
+        let rustup = cargo_bin("rustup")?.to_string_lossy().to_string();
+        let components = status.missing_components.join(",");
+        let targets = status.missing_targets.join(",");
+        let mut args = vec!["toolchain", "install", channel.as_str()];
+        if let Some(profile) = &status.toolchain.profile {
+            args.push("--profile");
+            args.push(profile);
+        }
+        if !components.is_empty() {
+            args.push("--component");
+            args.push(&components);
+        }
+        if !targets.is_empty() {
+            args.push("--target");
+            args.push(&targets);
+        }
+        run_external_command_with_progress(window, app, &rustup, &args, "PROGRESS_EVENT").await?;
+    }
+
+    inspect_project_toolchain(process.as_ref(), Path::new(&project_path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(items: &[&str]) -> Vec<String> {
+        items.iter().map(|item| item.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_toolchain_toml() {
+        let toolchain = parse_toolchain(
+            "[toolchain]\n\
+             channel = \"nightly-2024-06-01\"\n\
+             components = [\"rust-src\", \"clippy\"]\n\
+             targets = [\"riscv32imc-unknown-none-elf\"]\n",
+        )
+        .unwrap();
+        assert_eq!(toolchain.channel.as_deref(), Some("nightly-2024-06-01"));
+        assert_eq!(toolchain.components, strings(&["rust-src", "clippy"]));
+        assert_eq!(toolchain.targets, strings(&["riscv32imc-unknown-none-elf"]));
+        assert_eq!(toolchain.profile, None);
+    }
+
+    #[test]
+    fn parses_legacy_channel_file() {
+        let toolchain = parse_toolchain("esp\n").unwrap();
+        assert_eq!(toolchain.channel.as_deref(), Some("esp"));
+        assert!(toolchain.components.is_empty());
+    }
+
+    #[test]
+    fn rejects_invalid_toml() {
+        assert!(parse_toolchain("[toolchain]\nchannel = nightly\n").is_err());
+    }
+
+    #[test]
+    fn finds_missing_components() {
+        let installed = strings(&["rust-src", "clippy-x86_64-unknown-linux-gnu"]);
+        let required = strings(&["rust-src", "clippy", "rustfmt"]);
+        assert_eq!(missing_from(&installed, &required), strings(&["rustfmt"]));
+    }
+}
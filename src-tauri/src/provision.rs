@@ -0,0 +1,333 @@
+use std::path::PathBuf;
+
+use futures::StreamExt;
+use log::info;
+use tauri::{AppHandle, Window};
+
+use crate::error::{HelmError, HelmResult};
+use crate::events::{emit_event, HelmEvent};
+use crate::external_command::run_external_command_lines;
+use crate::package_manager::find_in_path;
+use crate::portable::app_config_dir;
+
+// Hosts provisioned at the same time, each of them downloads toolchains
+const MAX_PARALLEL_HOSTS: usize = 8;
+
+// Installation which is applied to every machine
+#[derive(Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct ProvisionConfig {
+    // Chips passed to espup, empty list installs all of them
+    pub targets: Vec<String>,
+    // GCC toolchains for std projects
+    pub std: bool,
+    // Cargo tools installed by cargo-binstall, e.g. espflash or cargo-generate
+    pub cargo_tools: Vec<String>,
+    // ESP-IDF release cloned to ~/esp/esp-idf, e.g. "v5.1.2"
+    pub esp_idf_version: Option<String>,
+}
+
+// Remote machine reachable by ssh with key authentication, passwords are not supported
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct ProvisionHost {
+    host: String,
+    user: Option<String>,
+    port: Option<u16>,
+    // Private key, ssh-agent and ~/.ssh/config are used when not set
+    identity_file: Option<String>,
+}
+
+#[derive(Clone, serde::Serialize, schemars::JsonSchema)]
+pub struct HostOutput {
+    host: String,
+    line: String,
+}
+
+impl HelmEvent for HostOutput {
+    const NAMES: &'static [&'static str] = &["provision-output"];
+}
+
+#[derive(Clone, serde::Serialize, schemars::JsonSchema)]
+#[serde(rename_all = "lowercase")]
+enum HostState {
+    Running,
+    Done,
+    Failed,
+}
+
+#[derive(Clone, serde::Serialize, schemars::JsonSchema)]
+pub struct HostStatus {
+    host: String,
+    state: HostState,
+    error: Option<String>,
+}
+
+impl HelmEvent for HostStatus {
+    const NAMES: &'static [&'static str] = &["provision-status"];
+}
+
+fn config_path() -> Option<PathBuf> {
+    app_config_dir().map(|dir| dir.join("provision.json"))
+}
+
+// Values pasted into the script and ssh arguments, options are not allowed either
+fn validate_value(what: &str, value: &str) -> HelmResult<()> {
+    let allowed = |c: char| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '/' | '-');
+    if value.is_empty() || value.starts_with('-') || !value.chars().all(allowed) {
+        return Err(HelmError::Validation(format!(
+            "Invalid {} \"{}\"",
+            what, value
+        )));
+    }
+    Ok(())
+}
+
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+// Script executed by POSIX sh on Linux, macOS and FreeBSD hosts, steps already done are
+// skipped. FreeBSD does not ship bash, so the script must not depend on it.
+fn provision_script(config: &ProvisionConfig) -> HelmResult<String> {
+    for target in &config.targets {
+        validate_value("target", target)?;
+    }
+    for tool in &config.cargo_tools {
+        validate_value("cargo tool", tool)?;
+    }
+    if let Some(version) = &config.esp_idf_version {
+        validate_value("ESP-IDF version", version)?;
+    }
+
+    let mut script = vec![
+        // Non-login shell, PATH additions of the user are read from profile
+        "if [ -f \"$HOME/.profile\" ]; then . \"$HOME/.profile\"; fi".to_string(),
+        "set -e".to_string(),
+        "if ! command -v rustup >/dev/null; then \
+         curl --proto '=https' --tlsv1.2 -sSf https://sh.rustup.rs | sh -s -- -y; fi"
+            .to_string(),
+        ". \"$HOME/.cargo/env\"".to_string(),
+        // Installer of binstall is a bash script, it is built from source without bash
+        "if ! command -v cargo-binstall >/dev/null; then \
+         if command -v bash >/dev/null; then curl -L --proto '=https' --tlsv1.2 -sSf \
+         https://raw.githubusercontent.com/cargo-bins/cargo-binstall/main/install-from-binstall-release.sh | bash; \
+         else cargo install cargo-binstall; fi; fi"
+            .to_string(),
+        // There are no prebuilt espup binaries for FreeBSD
+        "if [ \"$(uname)\" = FreeBSD ]; then cargo install espup; \
+         else cargo binstall --no-confirm espup; fi"
+            .to_string(),
+    ];
+
+    let mut espup = "espup install".to_string();
+    if !config.targets.is_empty() {
+        espup.push_str(&format!(
+            " --targets {}",
+            shell_quote(&config.targets.join(","))
+        ));
+    }
+    if config.std {
+        espup.push_str(" --std");
+    }
+    script.push(espup);
+
+    if !config.cargo_tools.is_empty() {
+        let tools: Vec<String> = config.cargo_tools.iter().map(|t| shell_quote(t)).collect();
+        script.push(format!(
+            "cargo binstall --no-confirm -- {}",
+            tools.join(" ")
+        ));
+    }
+    if let Some(version) = &config.esp_idf_version {
+        script.push(format!(
+            "if [ ! -d \"$HOME/esp/esp-idf\" ]; then \
+             git clone --depth 1 --recursive --shallow-submodules -b {} \
+             https://github.com/espressif/esp-idf.git \"$HOME/esp/esp-idf\"; fi",
+            shell_quote(version)
+        ));
+        script.push("\"$HOME/esp/esp-idf/install.sh\"".to_string());
+    }
+    Ok(script.join("\n"))
+}
+
+// Destination starting with "-" would be taken as ssh option
+fn ssh_args(host: &ProvisionHost, script: &str) -> HelmResult<Vec<String>> {
+    validate_value("host", &host.host)?;
+    if let Some(user) = &host.user {
+        validate_value("user", user)?;
+    }
+    // Host key prompt or password prompt would block, so fail instead
+    let mut args: Vec<String> = vec![
+        "-o".into(),
+        "BatchMode=yes".into(),
+        "-o".into(),
+        "StrictHostKeyChecking=accept-new".into(),
+    ];
+    if let Some(port) = host.port {
+        args.push("-p".into());
+        args.push(port.to_string());
+    }
+    if let Some(identity_file) = &host.identity_file {
+        args.push("-i".into());
+        args.push(identity_file.clone());
+    }
+    args.push("--".into());
+    args.push(match &host.user {
+        Some(user) => format!("{}@{}", user, host.host),
+        None => host.host.clone(),
+    });
+    args.push("sh".into());
+    args.push("-c".into());
+    args.push(shell_quote(script));
+    Ok(args)
+}
+
+async fn provision_host(
+    window: &Window,
+    app: &AppHandle,
+    ssh: &str,
+    host: &ProvisionHost,
+    script: &str,
+) -> HostStatus {
+    let emit_status = |state: HostState, error: Option<String>| {
+        let status = HostStatus {
+            host: host.host.clone(),
+            state,
+            error,
+        };
+        emit_event(window, &status);
+        status
+    };
+    emit_status(HostState::Running, None);
+    info!("Provisioning {}", host.host);
+
+    let args = match ssh_args(host, script) {
+        Ok(args) => args,
+        Err(e) => return emit_status(HostState::Failed, Some(e.to_string())),
+    };
+    let dir = std::env::temp_dir();
+    let result = run_external_command_lines(app, &dir, ssh, &args, |line| {
+        let output = HostOutput {
+            host: host.host.clone(),
+            line: line.to_string(),
+        };
+        emit_event(window, &output);
+        false
+    })
+    .await;
+
+    match result {
+        Ok(true) => emit_status(HostState::Done, None),
+        Ok(false) => emit_status(HostState::Failed, Some("Provisioning script failed".into())),
+        Err(e) => emit_status(HostState::Failed, Some(e.to_string())),
+    }
+}
+
+#[tauri::command]
+pub async fn load_provision_config() -> HelmResult<ProvisionConfig> {
+    Ok(config_path()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default())
+}
+
+#[tauri::command]
+pub async fn save_provision_config(config: ProvisionConfig) -> HelmResult<String> {
+    let path = config_path().ok_or(HelmError::NotFound("config directory".into()))?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let content = serde_json::to_string_pretty(&config)
+        .map_err(|e| HelmError::Other(format!("Failed to serialize configuration: {}", e)))?;
+    std::fs::write(&path, content)?;
+    Ok("Provisioning configuration saved".into())
+}
+
+// Command to apply configuration to Linux and macOS machines over ssh. Hosts run in
+// parallel, one failing host does not stop the others.
+#[tauri::command]
+pub async fn provision_hosts(
+    window: Window,
+    app: AppHandle,
+    hosts: Vec<ProvisionHost>,
+    config: Option<ProvisionConfig>,
+) -> HelmResult<Vec<HostStatus>> {
+    let ssh = find_in_path("ssh")
+        .ok_or(HelmError::NotFound("OpenSSH client".into()))?
+        .to_string_lossy()
+        .to_string();
+    let config = match config {
+        Some(config) => config,
+        None => load_provision_config().await?,
+    };
+    let script = provision_script(&config)?;
+
+    let statuses = futures::stream::iter(hosts.iter())
+        .map(|host| provision_host(&window, &app, &ssh, host, &script))
+        .buffer_unordered(MAX_PARALLEL_HOSTS)
+        .collect::<Vec<_>>()
+        .await;
+    let failed = statuses
+        .iter()
+        .filter(|status| matches!(status.state, HostState::Failed))
+        .count();
+    info!("Provisioned {} hosts, {} failed", statuses.len(), failed);
+    Ok(statuses)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_values_breaking_out_of_script() {
+        for bad in [
+            "v5.1; rm -rf ~",
+            "$(id)",
+            "--git=https://example.com",
+            "a b",
+            "",
+        ] {
+            let config = ProvisionConfig {
+                esp_idf_version: Some(bad.to_string()),
+                ..Default::default()
+            };
+            assert!(provision_script(&config).is_err(), "{}", bad);
+        }
+        let config = ProvisionConfig {
+            targets: vec!["esp32".into(), "esp32c3".into()],
+            cargo_tools: vec!["espflash".into()],
+            esp_idf_version: Some("release/v5.1".into()),
+            ..Default::default()
+        };
+        let script = provision_script(&config).unwrap();
+        assert!(script.contains("--targets 'esp32,esp32c3'"));
+        assert!(script.contains("-b 'release/v5.1'"));
+    }
+
+    #[test]
+    fn rejects_host_taken_as_option() {
+        let host = ProvisionHost {
+            host: "-oProxyCommand=id".into(),
+            user: None,
+            port: None,
+            identity_file: None,
+        };
+        assert!(ssh_args(&host, "true").is_err());
+    }
+
+    #[test]
+    fn runs_script_with_posix_sh() {
+        let host = ProvisionHost {
+            host: "lab-01".into(),
+            user: Some("student".into()),
+            port: None,
+            identity_file: None,
+        };
+        let args = ssh_args(&host, "true").unwrap();
+        assert_eq!(
+            args[args.len() - 4..],
+            ["student@lab-01", "sh", "-c", "'true'"]
+        );
+    }
+}
@@ -0,0 +1,147 @@
+use std::time::Duration;
+
+use log::info;
+use serde_json::Value;
+use tauri::AppHandle;
+
+use crate::error::HelmResult;
+use crate::github::{github_api, release_info, GithubResponse, ReleaseInfo};
+
+// Release lists change rarely, so they are fetched at most this often
+pub const RELEASES_TTL: Duration = Duration::from_secs(6 * 60 * 60);
+const RELEASES_PER_TOOL: usize = 20;
+
+// Tools whose versions are offered by the installer, with their GitHub repository
+pub const RELEASE_SOURCES: [(&str, &str); 5] = [
+    ("espup", "esp-rs/espup"),
+    ("espflash", "esp-rs/espflash"),
+    ("esp-toolchain", "esp-rs/rust-build"),
+    ("esp-idf", "espressif/esp-idf"),
+    ("esp-helm", "georgik/esp-helm"),
+];
+
+#[derive(serde::Serialize)]
+pub struct ToolReleases {
+    tool: String,
+    repo: String,
+    // Newest first, drafts are skipped
+    releases: Vec<ReleaseInfo>,
+    // Unix time of the last successful request, 0 when tool was never fetched
+    fetched_at: u64,
+    // Releases come from cache of the last online session
+    stale: bool,
+    // Set when there is neither network nor cache
+    error: Option<String>,
+}
+
+impl ToolReleases {
+    pub fn tool(&self) -> &str {
+        &self.tool
+    }
+
+    pub fn releases(&self) -> &[ReleaseInfo] {
+        &self.releases
+    }
+}
+
+// Releases of GitHub release list, newest first, drafts are skipped
+fn releases(response: &GithubResponse) -> Vec<ReleaseInfo> {
+    response
+        .body
+        .as_array()
+        .map(|releases| {
+            releases
+                .iter()
+                .filter(|release| {
+                    !release
+                        .get("draft")
+                        .and_then(Value::as_bool)
+                        .unwrap_or(false)
+                })
+                .filter_map(|release| release_info(release, response.fetched_at, response.stale))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+pub async fn tool_releases(
+    app: &AppHandle,
+    tool: &str,
+    repo: &str,
+    max_age: Duration,
+) -> ToolReleases {
+    let path = format!("repos/{}/releases?per_page={}", repo, RELEASES_PER_TOOL);
+    let mut result = ToolReleases {
+        tool: tool.to_string(),
+        repo: repo.to_string(),
+        releases: vec![],
+        fetched_at: 0,
+        stale: true,
+        error: None,
+    };
+    match github_api(app, &path, max_age).await {
+        Ok(response) => {
+            result.releases = releases(&response);
+            result.fetched_at = response.fetched_at;
+            result.stale = response.stale;
+        }
+        Err(e) => {
+            info!("Releases of {} are not available: {}", repo, e);
+            result.error = Some(e.to_string());
+        }
+    }
+    result
+}
+
+// Command to list known versions of installable tools. Cached lists are returned offline
+// and marked stale, refresh ignores cache age but still falls back to cache.
+#[tauri::command]
+pub async fn list_release_versions(
+    app: AppHandle,
+    refresh: Option<bool>,
+) -> HelmResult<Vec<ToolReleases>> {
+    let max_age = if refresh.unwrap_or(false) {
+        Duration::ZERO
+    } else {
+        RELEASES_TTL
+    };
+    let requests = RELEASE_SOURCES
+        .iter()
+        .map(|(tool, repo)| tool_releases(&app, tool, repo, max_age));
+    Ok(futures::future::join_all(requests).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::github::is_fresh;
+
+    #[test]
+    fn cache_expires_at_ttl() {
+        let fetched_at = 1_700_000_000;
+        let ttl = RELEASES_TTL.as_secs();
+        assert!(is_fresh(fetched_at, fetched_at, RELEASES_TTL));
+        assert!(is_fresh(fetched_at, fetched_at + ttl - 1, RELEASES_TTL));
+        assert!(!is_fresh(fetched_at, fetched_at + ttl, RELEASES_TTL));
+        // Refresh ignores cache age
+        assert!(!is_fresh(fetched_at, fetched_at, Duration::ZERO));
+        // Clock moved back, cache is still used
+        assert!(is_fresh(fetched_at, fetched_at - 10, RELEASES_TTL));
+    }
+
+    #[test]
+    fn skips_drafts_and_keeps_order() {
+        let response = GithubResponse {
+            body: serde_json::json!([
+                {"tag_name": "v0.12.0", "draft": true},
+                {"tag_name": "v0.11.0", "name": "v0.11.0"},
+                {"name": "no tag"},
+                {"tag_name": "v0.10.0", "draft": false},
+            ]),
+            fetched_at: 42,
+            stale: true,
+        };
+        let tags: Vec<&str> = releases(&response).iter().map(|r| r.tag()).collect();
+        assert_eq!(tags, vec!["v0.11.0", "v0.10.0"]);
+    }
+}
@@ -0,0 +1,107 @@
+use crate::i18n::tr;
+use crate::package_manager::PackageManager;
+
+// Output of failed tool which has known cause, fixed by installing host package
+struct Signature {
+    id: &'static str,
+    patterns: &'static [&'static str],
+    // Generic package name of package_manager
+    package: &'static str,
+}
+
+const SIGNATURES: [Signature; 3] = [
+    Signature {
+        id: "missing-libssl",
+        patterns: &[
+            "libssl.so",
+            "openssl/ssl.h",
+            "Could not find directory of OpenSSL installation",
+            "failed to run custom build command for `openssl-sys",
+        ],
+        package: "openssl",
+    },
+    Signature {
+        id: "certificate-error",
+        patterns: &[
+            "SSL certificate problem",
+            "curl: (60)",
+            "certificate verify failed",
+            "unable to get local issuer certificate",
+            "invalid peer certificate",
+        ],
+        package: "ca-certificates",
+    },
+    Signature {
+        id: "missing-linker",
+        patterns: &["linker `cc` not found", "linker 'cc' not found"],
+        package: "cc",
+    },
+];
+
+// What the UI does on one click
+#[derive(Clone, Debug, PartialEq, serde::Serialize, schemars::JsonSchema)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum FixAction {
+    // Arguments of install_host_dependencies
+    InstallHostDependencies { packages: Vec<String> },
+}
+
+// Attached to serialized error as "fix"
+#[derive(Clone, Debug, PartialEq, serde::Serialize, schemars::JsonSchema)]
+pub struct Remediation {
+    // Stable identifier of the failure, e.g. "missing-libssl"
+    id: String,
+    summary: String,
+    action: FixAction,
+}
+
+fn matching_signature<'a>(lines: impl IntoIterator<Item = &'a str>) -> Option<&'static Signature> {
+    lines.into_iter().find_map(|line| {
+        SIGNATURES.iter().find(|signature| {
+            signature
+                .patterns
+                .iter()
+                .any(|pattern| line.contains(pattern))
+        })
+    })
+}
+
+// Fix for output of failed command, None when the cause is unknown or package manager of
+// this host does not provide the package
+pub fn diagnose<'a>(lines: impl IntoIterator<Item = &'a str>) -> Option<Remediation> {
+    let signature = matching_signature(lines)?;
+    PackageManager::detect()?.package_id(signature.package)?;
+    Some(Remediation {
+        id: signature.id.to_string(),
+        summary: tr(&format!("fix-{}", signature.id), &[]),
+        action: FixAction::InstallHostDependencies {
+            packages: vec![signature.package.to_string()],
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signature_id(output: &str) -> Option<&'static str> {
+        matching_signature(output.lines()).map(|signature| signature.id)
+    }
+
+    #[test]
+    fn matches_known_failures() {
+        assert_eq!(
+            signature_id("error while loading shared libraries: libssl.so.1.1: cannot open"),
+            Some("missing-libssl")
+        );
+        assert_eq!(
+            signature_id("curl: (60) SSL certificate problem: unable to get local issuer"),
+            Some("certificate-error")
+        );
+        assert_eq!(
+            signature_id("   Compiling espup v0.13.0\nerror: linker `cc` not found\n"),
+            Some("missing-linker")
+        );
+        assert_eq!(signature_id("error: could not compile `app`"), None);
+    }
+}
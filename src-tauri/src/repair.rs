@@ -0,0 +1,253 @@
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use log::info;
+use tauri::{AppHandle, Window};
+
+use crate::arch::{install_arch, is_x86_64_forced};
+use crate::cargo_tools::{cargo_bin, install_cargo_tool};
+use crate::error::{HelmError, HelmResult};
+use crate::external_command::run_external_command_with_progress;
+use crate::inventory::{cargo_home, remove_path, rustup_home};
+use crate::project_toolchain::is_channel_installed;
+use crate::rust::{
+    component_path, espup_install, riscv_channel, sysroot_of, EspupOptions, GccOptions,
+};
+use crate::services::{services, ProcessRunner};
+
+#[derive(Clone, Copy, PartialEq, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RepairKind {
+    DeadSymlink,
+    WrongArchitecture,
+    CorruptedXtensaToolchain,
+    MissingRustSrc,
+}
+
+#[derive(serde::Serialize)]
+pub struct RepairIssue {
+    kind: RepairKind,
+    // Path or toolchain the issue was found in
+    target: String,
+    detail: String,
+    repaired: bool,
+    error: Option<String>,
+}
+
+impl RepairIssue {
+    fn new(kind: RepairKind, target: &str, detail: String) -> Self {
+        RepairIssue {
+            kind,
+            target: target.to_string(),
+            detail,
+            repaired: false,
+            error: None,
+        }
+    }
+}
+
+// Architecture from executable header, "universal" for fat Mach-O binaries
+fn binary_arch(path: &Path) -> Option<&'static str> {
+    let mut header = vec![0u8; 4096];
+    let read = std::fs::File::open(path).ok()?.read(&mut header).ok()?;
+    header.truncate(read);
+    let u16_le = |at: usize| Some(u16::from_le_bytes([*header.get(at)?, *header.get(at + 1)?]));
+    let u32_le = |at: usize| Some(u32::from_le_bytes(header.get(at..at + 4)?.try_into().ok()?));
+
+    if header.starts_with(b"\x7fELF") {
+        // Little endian is the only one used by supported hosts
+        return match u16_le(18)? {
+            0x3e => Some("x86_64"),
+            0xb7 => Some("aarch64"),
+            0x03 => Some("x86"),
+            0x28 => Some("arm"),
+            _ => None,
+        };
+    }
+    if header.starts_with(&[0xcf, 0xfa, 0xed, 0xfe]) {
+        return match u32_le(4)? {
+            0x0100_0007 => Some("x86_64"),
+            0x0100_000c => Some("aarch64"),
+            _ => None,
+        };
+    }
+    if header.starts_with(&[0xca, 0xfe, 0xba, 0xbe]) {
+        return Some("universal");
+    }
+    if header.starts_with(b"MZ") {
+        let pe = u32_le(0x3c)? as usize;
+        if header.get(pe..pe + 4)? != b"PE\0\0" {
+            return None;
+        }
+        return match u16_le(pe + 4)? {
+            0x8664 => Some("x86_64"),
+            0xaa64 => Some("aarch64"),
+            0x014c => Some("x86"),
+            _ => None,
+        };
+    }
+    None
+}
+
+fn dead_symlinks(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return vec![];
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.symlink_metadata()
+                .map(|meta| meta.file_type().is_symlink())
+                .unwrap_or(false)
+                && !path.exists()
+        })
+        .collect()
+}
+
+// esp toolchain which exists but cannot run rustc or lost its standard library sources
+fn xtensa_problem(process: &dyn ProcessRunner) -> Option<String> {
+    let toolchain = rustup_home()?.join("toolchains").join("esp");
+    if !toolchain.exists() {
+        return None;
+    }
+    let Some(sysroot) = sysroot_of(process, "esp") else {
+        return Some("rustc +esp does not run".into());
+    };
+    ["rustc", "rust-src"]
+        .iter()
+        .find(|component| !component_path(&sysroot, component).exists())
+        .map(|component| format!("{} is missing", component))
+}
+
+pub fn detect_issues(app: &AppHandle) -> Vec<RepairIssue> {
+    let mut issues = vec![];
+
+    let dirs = [
+        cargo_home().map(|home| home.join("bin")),
+        rustup_home().map(|home| home.join("toolchains")),
+    ];
+    for dir in dirs.iter().flatten() {
+        for link in dead_symlinks(dir) {
+            let target = std::fs::read_link(&link)
+                .map(|target| target.to_string_lossy().to_string())
+                .unwrap_or_default();
+            issues.push(RepairIssue::new(
+                RepairKind::DeadSymlink,
+                &link.to_string_lossy(),
+                format!("Points to missing {}", target),
+            ));
+        }
+    }
+
+    if let Ok(espup) = cargo_bin("espup") {
+        let expected = install_arch(is_x86_64_forced(app));
+        if let Some(arch) = binary_arch(&espup) {
+            if arch != expected && arch != "universal" {
+                issues.push(RepairIssue::new(
+                    RepairKind::WrongArchitecture,
+                    &espup.to_string_lossy(),
+                    format!("espup is {} binary, {} is expected", arch, expected),
+                ));
+            }
+        }
+    }
+
+    let process = services(app).process;
+    if let Some(problem) = xtensa_problem(process.as_ref()) {
+        issues.push(RepairIssue::new(
+            RepairKind::CorruptedXtensaToolchain,
+            "esp",
+            problem,
+        ));
+    }
+
+    let channel = riscv_channel(app);
+    if is_channel_installed(process.as_ref(), &channel) {
+        let missing = sysroot_of(process.as_ref(), &channel)
+            .map(|sysroot| !component_path(&sysroot, "rust-src").exists())
+            .unwrap_or(false);
+        if missing {
+            issues.push(RepairIssue::new(
+                RepairKind::MissingRustSrc,
+                &channel,
+                "rust-src is needed to build core for RISC-V targets".into(),
+            ));
+        }
+    }
+    issues
+}
+
+async fn repair_issue(window: Window, app: AppHandle, issue: &RepairIssue) -> HelmResult<()> {
+    match issue.kind {
+        RepairKind::DeadSymlink => remove_path(Path::new(&issue.target)),
+        RepairKind::WrongArchitecture => {
+            install_cargo_tool(window, app, "espup".into(), None).await?;
+            Ok(())
+        }
+        RepairKind::CorruptedXtensaToolchain => {
+            // espup skips LLVM and GCC which are intact, only Rust part is fetched again
+            let toolchain = rustup_home()
+                .ok_or(HelmError::NotFound("rustup home".into()))?
+                .join("toolchains")
+                .join("esp");
+            for dir in ["bin", "lib"].map(|name| toolchain.join(name)) {
+                if dir.exists() {
+                    remove_path(&dir)?;
+                }
+            }
+            let options = EspupOptions {
+                default_host: None,
+                targets: vec![],
+                gcc: GccOptions {
+                    install_gcc: false,
+                    esp_riscv_gcc: false,
+                },
+                extended_llvm: false,
+            };
+            espup_install(window, options).await
+        }
+        RepairKind::MissingRustSrc => {
+            let rustup = cargo_bin("rustup")?.to_string_lossy().to_string();
+            run_external_command_with_progress(
+                window,
+                app,
+                &rustup,
+                &["component", "add", "rust-src", "--toolchain", &issue.target],
+                "PROGRESS_EVENT",
+            )
+            .await?;
+            Ok(())
+        }
+    }
+}
+
+// Command to find broken parts of installation and reinstall only them. With dry run
+// issues are only reported. Download caches of rustup and espup are reused.
+#[tauri::command]
+pub async fn repair_installation(
+    window: Window,
+    app: AppHandle,
+    dry_run: Option<bool>,
+) -> HelmResult<Vec<RepairIssue>> {
+    let detect_app = app.clone();
+    let mut issues = tauri::async_runtime::spawn_blocking(move || detect_issues(&detect_app))
+        .await
+        .map_err(|e| HelmError::Other(format!("Detection failed: {}", e)))?;
+    info!("Found {} broken parts of installation", issues.len());
+    if dry_run.unwrap_or(false) {
+        return Ok(issues);
+    }
+
+    for issue in issues.iter_mut() {
+        match repair_issue(window.clone(), app.clone(), issue).await {
+            Ok(()) => issue.repaired = true,
+            Err(HelmError::Cancelled) => return Err(HelmError::Cancelled),
+            Err(e) => {
+                info!("Repair of {} failed: {}", issue.target, e);
+                issue.error = Some(e.to_string());
+            }
+        }
+    }
+    Ok(issues)
+}
@@ -1,45 +1,52 @@
-use std::process::Command;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
-use tauri::{AppHandle, Window};
+use tauri::{AppHandle, Manager, State, Window};
 
 use external_command::run_external_command_with_progress;
 
 use log::info;
 
-use tokio::fs;
-use tokio::io::AsyncWriteExt;
-
+use crate::adopt::ExistingInstallation;
+use crate::app_state::{AppState, JobId};
+use crate::arch::{detect_host, is_gcompat_installed, is_x86_64_forced};
+#[cfg(target_os = "windows")]
+use crate::atomic_file::{write_executable, Probe, PE_HEADER};
+use crate::audit::{audit, AuditAction};
+use crate::error::{HelmError, HelmResult};
+use crate::esp_clang::{export_file_path, is_esp_clang_installed};
+use crate::espup_progress::{self, EspupProgress};
 use crate::external_command;
-#[cfg(unix)]
-use crate::external_command::set_exec_permission;
-
-#[cfg(windows)]
-use std::os::windows::process::CommandExt;
-#[cfg(windows)]
-const CREATE_NO_WINDOW: u32 = 0x08000000; // Windows specific constant to hide console window
-
-pub fn get_tool_version(command: &str, flags: &[&str], keyword: Option<&str>) -> Option<String> {
-    let mut cmd = Command::new(command);
-    for flag in flags {
-        cmd.arg(flag);
-    }
-
-    #[cfg(windows)]
-    cmd.creation_flags(CREATE_NO_WINDOW);
+use crate::git::parse_git_version;
+use crate::inventory::{cargo_home, rustup_home};
+use crate::jobs::{spawn_job, wait_job};
+use crate::operations::OperationKind;
+use crate::portable::{portable_root, write_launcher};
+use crate::project_toolchain::{inspect_project_toolchain, is_channel_installed};
+use crate::rustup::{install_rustup, RustupOptions};
+use crate::services::{services, ProcessRunner, Services, SystemProcess};
+use crate::settings::save_settings;
+use crate::verify::{chip_target, verify_installation, CHIPS};
 
-    let output = cmd.output().ok()?;
+// First line of tool output, None when it does not contain the keyword
+fn first_line(
+    process: &dyn ProcessRunner,
+    command: &str,
+    flags: &[&str],
+    keyword: Option<&str>,
+) -> Option<String> {
+    let output = process.output(command, flags).ok()?;
 
-    if !output.status.success() {
-        info!("command failed: {:?}", output);
+    if !output.success {
+        info!("command failed: {} {}", command, output.stderr.trim());
         return None;
     }
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    info!("stdout: {:?}", stdout);
+    info!("stdout: {:?}", output.stdout);
 
     // Split by newline and take the first line.
-    let binding = stdout.split('\n').collect::<Vec<&str>>();
-    let line = binding.first()?;
+    let line = output.stdout.split('\n').next()?.to_string();
 
     // If a keyword is provided, look for it in the line. If not found, return None.
     if let Some(keyword) = keyword {
@@ -47,74 +54,553 @@ pub fn get_tool_version(command: &str, flags: &[&str], keyword: Option<&str>) ->
             return None;
         }
     }
+    Some(line)
+}
 
-    // Extract just the version part for other cases.
+// "cargo 1.77.0 (...)" gives "1.77.0"
+pub fn tool_version(
+    process: &dyn ProcessRunner,
+    command: &str,
+    flags: &[&str],
+    keyword: Option<&str>,
+) -> Option<String> {
+    let line = first_line(process, command, flags, keyword)?;
     line.split_whitespace().nth(1).map(|s| s.to_string())
 }
 
+// "rustc 1.82.0-nightly (f5ee3f6a5 2024-10-10) (1.82.0.3)" gives "1.82.0.3"
+pub fn tool_version_xtensa(
+    process: &dyn ProcessRunner,
+    command: &str,
+    flags: &[&str],
+    keyword: Option<&str>,
+) -> Option<String> {
+    let line = first_line(process, command, flags, keyword)?;
+    line.split_whitespace()
+        .nth(4)
+        .map(|s| s.trim_matches(')').trim_matches('(').to_string())
+}
+
+// "git version 2.41.0" gives "2.41.0", same parsing as Git check of the installer
+fn tool_version_git(
+    process: &dyn ProcessRunner,
+    command: &str,
+    flags: &[&str],
+    _keyword: Option<&str>,
+) -> Option<String> {
+    parse_git_version(&first_line(process, command, flags, None)?)
+}
+
+pub fn get_tool_version(command: &str, flags: &[&str], keyword: Option<&str>) -> Option<String> {
+    tool_version(&SystemProcess, command, flags, keyword)
+}
+
 pub fn get_tool_version_xtensa(
     command: &str,
     flags: &[&str],
     keyword: Option<&str>,
 ) -> Option<String> {
-    let mut cmd = Command::new(command);
-    for flag in flags {
-        cmd.arg(flag);
+    tool_version_xtensa(&SystemProcess, command, flags, keyword)
+}
+
+// Components needed by IDE support, minimal profile of rustup does not contain them
+pub const RUSTUP_COMPONENTS: [&str; 4] = ["rust-src", "rust-analyzer", "clippy", "rustfmt"];
+
+#[derive(Clone, serde::Serialize)]
+pub struct ComponentStatus {
+    toolchain: String,
+    component: String,
+    installed: bool,
+}
+
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ChipComponentKind {
+    RustTarget,
+    Gcc,
+    EspClang,
+}
+
+#[derive(Clone, serde::Serialize)]
+pub struct ChipComponent {
+    kind: ChipComponentKind,
+    name: String,
+    installed: bool,
+    // GCC of RISC-V chips and esp-clang are needed only by std projects
+    required: bool,
+}
+
+#[derive(Clone, serde::Serialize)]
+pub struct ChipSupport {
+    chip: String,
+    target: String,
+    components: Vec<ChipComponent>,
+    // All required components are installed
+    buildable: bool,
+}
+
+pub const VS_BUILDTOOLS_URL: &str = "https://aka.ms/vs/17/release/vs_buildtools.exe";
+pub const VS_BUILDTOOLS_ARGS: [&str; 6] = [
+    "--passive",
+    "--wait",
+    "--add",
+    "Microsoft.VisualStudio.Component.VC.Tools.x86.x64",
+    "--add",
+    "Microsoft.VisualStudio.Component.Windows11SDK.22621",
+];
+
+// RISC-V targets of the pinned nightly, Xtensa targets come with esp toolchain
+pub const RISCV_TARGETS: [&str; 2] = [
+    "riscv32imc-unknown-none-elf",
+    "riscv32imac-unknown-none-elf",
+];
+
+#[derive(Clone, serde::Serialize)]
+pub struct RustSupportResponse {
+    xtensa: Option<String>,
+    riscv: Option<String>,
+    cargo: Option<String>,
+    components: Vec<ComponentStatus>,
+    // Dated nightly from settings, None when moving nightly is used
+    nightly_pin: Option<String>,
+    pin_installed: Option<bool>,
+    // Pinned channel is missing or project expects another nightly
+    warnings: Vec<String>,
+    espflash: Option<String>,
+    ldproxy: Option<String>,
+    git: Option<String>,
+    python: Option<String>,
+    probe_rs: Option<String>,
+    targets: Vec<ChipSupport>,
+}
+
+// "nightly-2024-05-01", plain "nightly" moves and breaks builds after upstream changes
+pub fn validate_nightly_pin(pin: &str) -> HelmResult<()> {
+    let date = pin.strip_prefix("nightly-").unwrap_or_default();
+    let parts: Vec<&str> = date.split('-').collect();
+    let valid = parts.len() == 3
+        && [4, 2, 2]
+            .iter()
+            .zip(&parts)
+            .all(|(len, part)| part.len() == *len && part.chars().all(|c| c.is_ascii_digit()));
+    if !valid {
+        return Err(HelmError::Validation(format!(
+            "Nightly pin must have form nightly-YYYY-MM-DD, got {}",
+            pin
+        )));
     }
+    Ok(())
+}
 
-    let output = cmd.output().ok()?;
+// Channel used for RISC-V targets, pinned nightly when user selected one
+pub fn riscv_channel(app: &AppHandle) -> String {
+    let state_mutex = app.state::<Mutex<AppState>>();
+    let state = state_mutex.lock().unwrap();
+    state
+        .settings
+        .nightly_pin
+        .clone()
+        .unwrap_or_else(|| "nightly".into())
+}
 
-    if !output.status.success() {
-        info!("command failed: {:?}", output);
+pub fn sysroot_of(process: &dyn ProcessRunner, toolchain: &str) -> Option<std::path::PathBuf> {
+    let output = process
+        .output("rustc", &[&format!("+{}", toolchain), "--print", "sysroot"])
+        .ok()?;
+    if !output.success {
         return None;
     }
+    Some(output.stdout.trim().into())
+}
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    info!("stdout: {:?}", stdout);
+// Custom esp toolchain is not managed by rustup, so check files in sysroot for both toolchains
+pub fn component_path(sysroot: &std::path::Path, component: &str) -> std::path::PathBuf {
+    let binary = |name: &str| {
+        sysroot
+            .join("bin")
+            .join(format!("{}{}", name, std::env::consts::EXE_SUFFIX))
+    };
+    match component {
+        "rust-src" => sysroot.join("lib").join("rustlib").join("src").join("rust"),
+        "clippy" => binary("cargo-clippy"),
+        other => binary(other),
+    }
+}
 
-    // Split by newline and take the first line.
-    let binding = stdout.split('\n').collect::<Vec<&str>>();
-    let line = binding.first()?;
+fn check_components(services: &Services, toolchain: &str) -> Vec<ComponentStatus> {
+    let sysroot = sysroot_of(services.process.as_ref(), toolchain);
+    RUSTUP_COMPONENTS
+        .iter()
+        .map(|component| ComponentStatus {
+            toolchain: toolchain.to_string(),
+            component: component.to_string(),
+            installed: sysroot
+                .as_ref()
+                .map(|sysroot| services.fs.exists(&component_path(sysroot, component)))
+                .unwrap_or(false),
+        })
+        .collect()
+}
 
-    // If a keyword is provided, look for it in the line. If not found, return None.
-    if let Some(keyword) = keyword {
-        if !line.contains(keyword) {
-            return None;
+// Probe still running after this is reported as missing, e.g. rustup installing toolchain of
+// rust-toolchain.toml on first use
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+// UI checks again when switching views, installed versions do not change that fast
+const SUPPORT_CACHE_TTL: Duration = Duration::from_secs(5);
+
+struct CachedSupport {
+    key: (Option<String>, Option<PathBuf>),
+    checked: Instant,
+    response: RustSupportResponse,
+}
+
+static SUPPORT_CACHE: Mutex<Option<CachedSupport>> = Mutex::new(None);
+
+// Called after installation, so the next check sees new versions
+pub fn invalidate_rust_support() {
+    *SUPPORT_CACHE.lock().unwrap() = None;
+}
+
+// Blocking probe runs on worker thread, so slow tool does not delay others
+async fn probe<T: Default + Send + 'static>(
+    name: &str,
+    probe: impl FnOnce() -> T + Send + 'static,
+) -> T {
+    match tokio::time::timeout(PROBE_TIMEOUT, tokio::task::spawn_blocking(probe)).await {
+        Ok(Ok(value)) => value,
+        Ok(Err(_)) => {
+            info!("Probe of {} panicked", name);
+            T::default()
+        }
+        Err(_) => {
+            info!("Probe of {} timed out", name);
+            T::default()
         }
     }
+}
 
-    // Extract just the version part for other cases.
-    line.split_whitespace()
-        .nth(4)
-        .map(|s| s.trim_matches(')').trim_matches('(').to_string())
+type VersionParser = fn(&dyn ProcessRunner, &str, &[&str], Option<&str>) -> Option<String>;
+
+async fn probe_version(
+    services: &Services,
+    parse: VersionParser,
+    command: &'static str,
+    flags: &[&str],
+    keyword: Option<&'static str>,
+) -> Option<String> {
+    let process = services.process.clone();
+    let flags: Vec<String> = flags.iter().map(|flag| flag.to_string()).collect();
+    probe(command, move || {
+        let flags: Vec<&str> = flags.iter().map(String::as_str).collect();
+        parse(process.as_ref(), command, &flags, keyword)
+    })
+    .await
 }
 
-#[derive(serde::Serialize)]
-pub struct RustSupportResponse {
-    xtensa: Option<String>,
-    riscv: Option<String>,
-    cargo: Option<String>,
+// Xtensa targets are built from rust-src of esp toolchain, RISC-V targets are installed
+// into sysroot of the nightly by rustup. espup puts GCC next to esp toolchain.
+fn chip_matrix(services: &Services, channel: &str) -> Vec<ChipSupport> {
+    let esp_sysroot = sysroot_of(services.process.as_ref(), "esp");
+    let riscv_sysroot = sysroot_of(services.process.as_ref(), channel);
+    let esp_toolchain = rustup_home().map(|home| home.join("toolchains").join("esp"));
+    let installed = |path: Option<PathBuf>| path.is_some_and(|path| services.fs.exists(&path));
+    let esp_clang = is_esp_clang_installed(services.fs.as_ref());
+
+    CHIPS
+        .iter()
+        .filter_map(|chip| {
+            let (toolchain, target, _) = chip_target(chip)?;
+            let xtensa = toolchain == "esp";
+            let rust_target = if xtensa {
+                installed(
+                    esp_sysroot
+                        .as_deref()
+                        .map(|sysroot| component_path(sysroot, "rust-src")),
+                )
+            } else {
+                installed(
+                    riscv_sysroot
+                        .as_ref()
+                        .map(|sysroot| sysroot.join("lib").join("rustlib").join(target)),
+                )
+            };
+            let gcc = if xtensa {
+                "xtensa-esp-elf"
+            } else {
+                "riscv32-esp-elf"
+            };
+            let components = vec![
+                ChipComponent {
+                    kind: ChipComponentKind::RustTarget,
+                    name: target.to_string(),
+                    installed: rust_target,
+                    required: true,
+                },
+                ChipComponent {
+                    kind: ChipComponentKind::Gcc,
+                    name: gcc.to_string(),
+                    installed: installed(esp_toolchain.as_ref().map(|dir| dir.join(gcc))),
+                    required: xtensa,
+                },
+                ChipComponent {
+                    kind: ChipComponentKind::EspClang,
+                    name: "esp-clang".to_string(),
+                    installed: esp_clang,
+                    required: false,
+                },
+            ];
+            Some(ChipSupport {
+                chip: chip.to_string(),
+                target: target.to_string(),
+                buildable: components
+                    .iter()
+                    .all(|component| component.installed || !component.required),
+                components,
+            })
+        })
+        .collect()
 }
 
-#[tauri::command]
-pub fn check_rust_support() -> Result<RustSupportResponse, String> {
-    let cargo_version = get_tool_version("cargo", &["--version"], None);
-    let riscv_version = get_tool_version("rustc", &["+nightly", "--version"], Some("rustc"));
-    let xtensa_version = get_tool_version_xtensa("rustc", &["+esp", "--version"], Some("rustc"));
+async fn probe_components(services: &Services, toolchain: &str) -> Vec<ComponentStatus> {
+    let services = services.clone();
+    let toolchain = toolchain.to_string();
+    probe("components", move || {
+        check_components(&services, &toolchain)
+    })
+    .await
+}
+
+async fn probe_matrix(services: &Services, channel: &str) -> Vec<ChipSupport> {
+    let services = services.clone();
+    let channel = channel.to_string();
+    probe("targets", move || chip_matrix(&services, &channel)).await
+}
+
+// Cached result of rust_support_with for the same pin and project
+pub async fn rust_support(
+    services: &Services,
+    nightly_pin: Option<String>,
+    project: Option<&Path>,
+) -> RustSupportResponse {
+    let key = (nightly_pin.clone(), project.map(Path::to_path_buf));
+    if let Some(cached) = SUPPORT_CACHE.lock().unwrap().as_ref() {
+        if cached.key == key && cached.checked.elapsed() < SUPPORT_CACHE_TTL {
+            return cached.response.clone();
+        }
+    }
+    let response = rust_support_with(services, nightly_pin, project).await;
+    *SUPPORT_CACHE.lock().unwrap() = Some(CachedSupport {
+        key,
+        checked: Instant::now(),
+        response: response.clone(),
+    });
+    response
+}
+
+// All probes run at once, the check takes as long as the slowest one
+pub async fn rust_support_with(
+    services: &Services,
+    nightly_pin: Option<String>,
+    project: Option<&Path>,
+) -> RustSupportResponse {
+    let channel = nightly_pin.clone().unwrap_or_else(|| "nightly".into());
+    let riscv_flags = [format!("+{}", channel), "--version".to_string()];
+    let riscv_flags: Vec<&str> = riscv_flags.iter().map(String::as_str).collect();
+    let python = if cfg!(windows) { "python" } else { "python3" };
+    let pin = nightly_pin.clone();
+    let project_path = project.map(Path::to_path_buf);
+    let pin_process = services.process.clone();
+    let project_process = services.process.clone();
+
+    let (
+        (cargo_version, riscv_version, xtensa_version),
+        (espflash, ldproxy, git, python, probe_rs),
+        (riscv_components, esp_components, targets),
+        (pin_installed, project_channel),
+    ) = futures::join!(
+        futures::future::join3(
+            probe_version(services, tool_version, "cargo", &["--version"], None),
+            probe_version(services, tool_version, "rustc", &riscv_flags, Some("rustc")),
+            probe_version(
+                services,
+                tool_version_xtensa,
+                "rustc",
+                &["+esp", "--version"],
+                Some("rustc")
+            ),
+        ),
+        futures::future::join5(
+            probe_version(services, tool_version, "espflash", &["--version"], None),
+            probe_version(services, tool_version, "ldproxy", &["--version"], None),
+            probe_version(services, tool_version_git, "git", &["--version"], None),
+            probe_version(
+                services,
+                tool_version,
+                python,
+                &["--version"],
+                Some("Python")
+            ),
+            probe_version(services, tool_version, "probe-rs", &["--version"], None),
+        ),
+        futures::future::join3(
+            probe_components(services, &channel),
+            probe_components(services, "esp"),
+            probe_matrix(services, &channel),
+        ),
+        futures::future::join(
+            probe("pinned channel", move || pin
+                .as_deref()
+                .map(|pin| is_channel_installed(pin_process.as_ref(), pin))),
+            // esp and stable channels of the project are not affected by the pin
+            probe("project toolchain", move || {
+                project_path
+                    .and_then(|project| {
+                        inspect_project_toolchain(project_process.as_ref(), &project).ok()
+                    })
+                    .and_then(|status| status.toolchain.channel)
+                    .filter(|channel| channel.starts_with("nightly"))
+            }),
+        ),
+    );
+
+    let mut components = riscv_components;
+    components.extend(esp_components);
+
+    let mut warnings = vec![];
+    if let (Some(pin), Some(false)) = (&nightly_pin, pin_installed) {
+        warnings.push(format!("Pinned channel {} is not installed", pin));
+    }
+    if let (Some(pin), Some(project_channel)) = (&nightly_pin, &project_channel) {
+        if project_channel != pin {
+            warnings.push(format!(
+                "rust-toolchain.toml of the project expects {}, but {} is pinned",
+                project_channel, pin
+            ));
+        }
+    }
 
     info!("riscv: {:?}", riscv_version);
-    Ok(RustSupportResponse {
+    RustSupportResponse {
         xtensa: xtensa_version,
         riscv: riscv_version,
         cargo: cargo_version,
-    })
+        components,
+        nightly_pin,
+        pin_installed,
+        warnings,
+        espflash,
+        ldproxy,
+        git,
+        python,
+        probe_rs,
+        targets,
+    }
+}
+
+// Command to check installed toolchains and tools, pin of RISC-V nightly is compared with
+// the toolchain file when project is given
+#[tauri::command]
+pub async fn check_rust_support(
+    state_mutex: State<'_, Mutex<AppState>>,
+    services: State<'_, Services>,
+    project_path: Option<String>,
+) -> HelmResult<RustSupportResponse> {
+    let nightly_pin = state_mutex.lock().unwrap().settings.nightly_pin.clone();
+    Ok(rust_support(
+        &services,
+        nightly_pin,
+        project_path.as_deref().map(Path::new),
+    )
+    .await)
+}
+
+// Command to pin RISC-V nightly to dated snapshot, None returns to moving nightly
+#[tauri::command]
+pub async fn set_nightly_pin(
+    state_mutex: State<'_, Mutex<AppState>>,
+    pin: Option<String>,
+) -> HelmResult<String> {
+    if let Some(pin) = &pin {
+        validate_nightly_pin(pin)?;
+    }
+    let mut state = state_mutex.lock().unwrap();
+    let mut settings = state.settings.clone();
+    settings.nightly_pin = pin.clone();
+    save_settings(&settings)?;
+    state.settings = settings;
+    match pin {
+        Some(pin) => Ok(format!("RISC-V nightly pinned to {}", pin)),
+        None => Ok("RISC-V nightly is not pinned".into()),
+    }
 }
 
 #[derive(serde::Serialize, serde::Deserialize)]
 pub struct RustInstallOptions {
-    selected_variant: Option<String>,
-    install_msvc: bool,
-    install_mingw: bool,
+    pub selected_variant: Option<String>,
+    pub install_msvc: bool,
+    pub install_mingw: bool,
+    #[serde(flatten)]
+    pub gcc: GccOptions,
+    // Profile, components and targets of default toolchain installed by rustup
+    #[serde(default)]
+    pub rustup: RustupOptions,
+    // Install rust-src, rust-analyzer, clippy and rustfmt after toolchains
+    #[serde(default)]
+    pub install_components: bool,
+    // Chips passed to espup, empty list installs all of them
+    #[serde(default)]
+    pub targets: Vec<String>,
+    // Build test project for each target after installation
+    #[serde(default = "default_true")]
+    pub verify: bool,
+    // Dated nightly for RISC-V targets, stored in settings, e.g. "nightly-2024-05-01"
+    #[serde(default)]
+    pub nightly_pin: Option<String>,
+}
+
+// GCC toolchains installed by espup, pure no_std projects do not need them
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct GccOptions {
+    #[serde(default = "default_true")]
+    pub install_gcc: bool,
+    // Use GCC bundled by Espressif for RISC-V targets instead of system one
+    #[serde(default)]
+    pub esp_riscv_gcc: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+// Adopted espup installation which still works and has GCC for every requested chip, empty
+// list requests all of them
+fn adopted_toolchain<'a>(
+    services: &Services,
+    adopted: &'a [ExistingInstallation],
+    targets: &[String],
+) -> Option<&'a ExistingInstallation> {
+    let covers = |installation: &ExistingInstallation| {
+        let has = |chip: &str| installation.targets.iter().any(|target| target == chip);
+        if targets.is_empty() {
+            CHIPS.iter().all(|chip| has(chip))
+        } else {
+            targets.iter().all(|chip| has(chip))
+        }
+    };
+    adopted
+        .iter()
+        .filter(|installation| installation.kind == "espup")
+        .filter(|installation| services.fs.exists(Path::new(&installation.path)))
+        .find(|installation| covers(installation))
+        .filter(|_| {
+            tool_version_xtensa(
+                services.process.as_ref(),
+                "rustc",
+                &["+esp", "--version"],
+                Some("rustc"),
+            )
+            .is_some()
+        })
 }
 
 #[tauri::command]
@@ -122,228 +608,506 @@ pub async fn install_rust_support(
     window: Window,
     app: AppHandle,
     install_options: RustInstallOptions,
-) -> Result<String, String> {
+) -> HelmResult<String> {
     let selected_variant = install_options.selected_variant;
-    #[cfg(target_os = "windows")]
-    {
-        if install_options.install_msvc {
-            install_vc_tools_and_sdk(window.clone(), app.clone()).await?;
-        }
+    let host = detect_host(is_x86_64_forced(&app))?;
+    // Empty target list means all chips, which includes Xtensa ones
+    let needs_xtensa = install_options.targets.is_empty()
+        || install_options
+            .targets
+            .iter()
+            .any(|chip| chip_target(chip).is_some_and(|(toolchain, _, _)| toolchain == "esp"));
+    if host.espup_host.is_none() && needs_xtensa {
+        return Err(HelmError::Validation(format!(
+            "Unsupported host {} for Xtensa chips, only RISC-V chips can be installed",
+            host.host
+        )));
     }
-
-    install_rustup(window.clone(), app.clone(), selected_variant.as_ref()).await?;
-    install_espup(window.clone(), app.clone(), selected_variant.as_ref()).await?;
-    install_rust_toolchain(window, app, selected_variant.as_ref()).await?;
-    Ok("Success".into())
-}
-
-pub async fn install_rustup(
-    window: Window,
-    app: tauri::AppHandle,
-    selected_variant: Option<&String>,
-) -> Result<String, String> {
-    // Check if rustup is already installed
-    if let Ok(output) = Command::new("rustup").arg("--version").output() {
-        if output.status.success() {
-            info!("Rustup already installed");
-            return Ok("Rustup already installed".into());
-        }
+    if host.needs_gcompat && needs_xtensa && !is_gcompat_installed() {
+        return Err(HelmError::Validation(
+            "Xtensa toolchain is built for glibc, install gcompat with host dependencies first"
+                .into(),
+        ));
+    }
+    if let Some(pin) = &install_options.nightly_pin {
+        validate_nightly_pin(pin)?;
+        let state_mutex = app.state::<Mutex<AppState>>();
+        let mut state = state_mutex.lock().unwrap();
+        let mut settings = state.settings.clone();
+        settings.nightly_pin = Some(pin.clone());
+        save_settings(&settings)?;
+        state.settings = settings;
     }
-
-    info!("Installing rustup...");
 
     #[cfg(target_os = "windows")]
-    {
-        let mut args = vec!["install", "-y"];
+    let msvc_jobs: Vec<JobId> = if install_options.install_msvc {
+        vec![spawn_job(
+            &app,
+            OperationKind::Install,
+            "Visual Studio Build Tools",
+            vec![],
+            install_vc_tools_and_sdk(window.clone(), app.clone()),
+        )]
+    } else {
+        vec![]
+    };
+    #[cfg(not(target_os = "windows"))]
+    let msvc_jobs: Vec<JobId> = vec![];
 
-        if let Some(variant) = selected_variant {
-            args.push("--default-host");
-            args.push(variant);
-        }
+    let adopted = {
+        let state_mutex = app.state::<Mutex<AppState>>();
+        let state = state_mutex.lock().unwrap();
+        state.settings.adopted_installations.clone()
+    };
+    let adopted = host
+        .espup_host
+        .as_ref()
+        .and_then(|_| adopted_toolchain(&services(&app), &adopted, &install_options.targets))
+        .cloned();
 
-        run_external_command_with_progress(
-            window.clone(),
-            app,
-            "rustup-init.exe",
-            &args,
-            "PROGRESS_EVENT",
+    // rustup-init needs the linker, espup needs rustup for nightly toolchain. Adopted
+    // toolchain already has both.
+    let rustup_jobs: Vec<JobId> = match &adopted {
+        Some(_) => vec![],
+        None => vec![spawn_job(
+            &app,
+            OperationKind::Install,
+            "rustup",
+            msvc_jobs.clone(),
+            install_rustup(
+                window.clone(),
+                app.clone(),
+                selected_variant.clone(),
+                install_options.rustup,
+            ),
+        )],
+    };
+    // Without esp toolchain builds for this host, RISC-V targets come from rustup nightly
+    let toolchain_job = if let Some(installation) = adopted {
+        info!("Using adopted toolchain at {}", installation.path);
+        spawn_job(
+            &app,
+            OperationKind::Install,
+            "Rust toolchain",
+            msvc_jobs,
+            async move { Ok(format!("Using adopted toolchain at {}", installation.path)) },
         )
-        .await;
-    }
+    } else if host.espup_host.is_some() {
+        spawn_job(
+            &app,
+            OperationKind::Install,
+            "Rust toolchain",
+            rustup_jobs,
+            install_rust_toolchain(
+                window.clone(),
+                app.clone(),
+                selected_variant,
+                install_options.gcc,
+                install_options.targets.clone(),
+            ),
+        )
+    } else {
+        let channel = install_options
+            .nightly_pin
+            .clone()
+            .unwrap_or_else(|| "nightly".into());
+        spawn_job(
+            &app,
+            OperationKind::Install,
+            "Rust toolchain",
+            rustup_jobs,
+            install_riscv_nightly(window.clone(), app.clone(), channel),
+        )
+    };
 
-    #[cfg(unix)]
+    // espup installs moving nightly, pinned snapshot is added next to it
+    let toolchain_job = match install_options
+        .nightly_pin
+        .filter(|_| host.espup_host.is_some())
     {
-        let args = vec!["-y"];
-        run_external_command_with_progress(
-            window.clone(),
-            app,
-            "./rustup-init.sh",
-            &args,
-            "PROGRESS_EVENT",
+        Some(pin) => spawn_job(
+            &app,
+            OperationKind::Install,
+            "Pinned nightly",
+            vec![toolchain_job],
+            install_riscv_nightly(window.clone(), app.clone(), pin),
+        ),
+        None => toolchain_job,
+    };
+
+    let last_job = if install_options.install_components {
+        spawn_job(
+            &app,
+            OperationKind::Install,
+            "rustup components",
+            vec![toolchain_job],
+            install_rustup_components(window.clone(), app.clone()),
         )
-        .await;
+    } else {
+        toolchain_job
+    };
+
+    if install_options.verify {
+        let verify_job = spawn_job(
+            &app,
+            OperationKind::Install,
+            "Verification",
+            vec![last_job],
+            verify_installation(window, app.clone(), install_options.targets),
+        );
+        let result = wait_job(&app, verify_job).await;
+        invalidate_rust_support();
+        return result;
     }
 
-    info!("Rustup installed or already present");
-    Ok("Rustup installed or already present".into())
+    let result = wait_job(&app, last_job).await;
+    invalidate_rust_support();
+    result?;
+    Ok("Success".into())
+}
+
+async fn install_riscv_nightly(
+    window: Window,
+    app: AppHandle,
+    channel: String,
+) -> HelmResult<String> {
+    info!("Installing {} for RISC-V targets...", channel);
+    let rustup_path = cargo_home()
+        .ok_or(HelmError::NotFound("cargo home".into()))?
+        .join("bin")
+        .join("rustup");
+    let targets = RISCV_TARGETS.join(",");
+    run_external_command_with_progress(
+        window,
+        app,
+        &rustup_path.to_string_lossy(),
+        &[
+            "toolchain",
+            "install",
+            &channel,
+            "--profile",
+            "minimal",
+            "--component",
+            "rust-src",
+            "--target",
+            &targets,
+        ],
+        "PROGRESS_EVENT",
+    )
+    .await?;
+    Ok(format!("{} installed", channel))
 }
 
-async fn install_espup(
-    _window: Window,
-    _app: AppHandle,
-    _selected_variant: Option<&String>,
-) -> Result<String, String> {
-    info!("Installing espup...");
+// rustup can add components only to nightly, esp toolchain is linked by espup and
+// ships rust-src on its own
+async fn install_rustup_components(window: Window, app: AppHandle) -> HelmResult<String> {
+    info!("Installing rustup components...");
+    let rustup_path = cargo_home()
+        .ok_or(HelmError::NotFound("cargo home".into()))?
+        .join("bin")
+        .join("rustup");
 
-    let url: &'static str;
-    #[cfg(target_os = "linux")]
-    #[cfg(target_arch = "aarch64")]
-    {
-        url = "https://github.com/esp-rs/espup/releases/latest/download/espup-aarch64-unknown-linux-gnu";
-    }
-    #[cfg(target_os = "linux")]
-    #[cfg(target_arch = "x86_64")]
-    {
-        url = "https://github.com/esp-rs/espup/releases/latest/download/espup-x86_64-unknown-linux-gnu";
-    }
-    #[cfg(target_os = "macos")]
-    #[cfg(target_arch = "aarch64")]
-    {
-        url = "https://github.com/esp-rs/espup/releases/latest/download/espup-aarch64-apple-darwin";
+    let components = RUSTUP_COMPONENTS.join(",");
+    let channel = riscv_channel(&app);
+    let services = services(&app);
+    // toolchain install only adds missing components when nightly already exists
+    run_external_command_with_progress(
+        window,
+        app,
+        &rustup_path.to_string_lossy(),
+        &[
+            "toolchain",
+            "install",
+            &channel,
+            "--profile",
+            "minimal",
+            "--component",
+            &components,
+        ],
+        "PROGRESS_EVENT",
+    )
+    .await?;
+
+    for status in check_components(&services, "esp") {
+        if !status.installed {
+            info!("{} is not available for esp toolchain", status.component);
+        }
     }
-    #[cfg(target_os = "macos")]
-    #[cfg(target_arch = "x86_64")]
-    {
-        url = "https://github.com/esp-rs/espup/releases/latest/download/espup-x86_64-apple-darwin";
+    Ok("rustup components installed".into())
+}
+
+// Host triple passed to espup. Windows uses variant selected by user or x64 build on arm64,
+// macOS uses x86_64 when it's forced for legacy projects.
+pub fn get_default_host(app: &AppHandle, selected_variant: Option<String>) -> Option<String> {
+    if cfg!(target_os = "windows") {
+        return selected_variant.or_else(|| {
+            detect_host(false)
+                .ok()
+                .filter(|host| host.emulated)
+                .and_then(|host| host.espup_host)
+        });
     }
-    #[cfg(target_os = "windows")]
-    {
-        url = "https://github.com/esp-rs/espup/releases/latest/download/espup-x86_64-pc-windows-msvc.exe";
+    if cfg!(target_os = "macos") && is_x86_64_forced(app) {
+        return Some("x86_64-apple-darwin".into());
     }
+    None
+}
 
-    // Download the binary using reqwest's async API
-    let response = reqwest::get(url)
-        .await
-        .map_err(|e| format!("Failed to download espup: {}", e))?;
-
-    #[cfg(unix)]
-    let fname = "espup";
-    #[cfg(windows)]
-    let fname = "espup.exe";
-
-    let bytes = response
-        .bytes()
-        .await
-        .map_err(|e| format!("Failed to read response bytes: {}", e))?;
+// Options of espup install, chips are parsed by espup, empty list installs all of them
+pub struct EspupOptions {
+    pub default_host: Option<String>,
+    pub targets: Vec<String>,
+    pub gcc: GccOptions,
+    pub extended_llvm: bool,
+}
 
-    let output_dir = dirs::home_dir()
-        .ok_or("Failed to get home directory")?
-        .join(".cargo/bin");
-    let output_path = output_dir.join(fname);
-    let mut dest = fs::File::create(&output_path)
-        .await
-        .map_err(|e| format!("Failed to create file: {}", e))?;
+// espup runs in-process, so installation is cancelled with its job and progress is
+// read from its log records
+pub async fn espup_install(window: Window, options: EspupOptions) -> HelmResult<()> {
+    let targets = match options.targets.join(",").as_str() {
+        "" => "all".to_string(),
+        targets => targets.to_string(),
+    };
+    let targets = espup::targets::parse_targets(&targets)
+        .map_err(|e| HelmError::Validation(e.to_string()))?;
+    // Portable installation keeps export file next to the launcher
+    let export_file = portable_root().and(export_file_path());
+    let install_opts = espup::cli::InstallOpts {
+        default_host: options.default_host,
+        // GCC is installed by espup only for std projects
+        esp_riscv_gcc: options.gcc.install_gcc && options.gcc.esp_riscv_gcc,
+        export_file,
+        extended_llvm: options.extended_llvm,
+        log_level: "debug".into(),
+        name: "esp".into(),
+        skip_version_parse: false,
+        std: options.gcc.install_gcc,
+        targets,
+        toolchain_version: None,
+    };
 
-    dest.write_all(&bytes)
+    espup_progress::start(EspupProgress::new(
+        window,
+        &options.targets,
+        options.gcc.install_gcc,
+        options.gcc.esp_riscv_gcc,
+    ));
+    let result = espup::toolchain::install(install_opts, espup::toolchain::InstallMode::Install)
         .await
-        .map_err(|e| format!("Failed to write to file: {}", e))?;
-
-    // Set execute permission for the binary on Unix-based systems
-    #[cfg(unix)]
-    set_exec_permission(&output_path)
-        .map_err(|e| format!("Failed to set execute permissions: {}", e))?;
-
-    info!("espup downloaded successfully!");
-
-    Ok("espup installed successfully!".into())
+        .map_err(HelmError::from);
+    espup_progress::finish(result.is_ok());
+    if result.is_ok() {
+        let toolchain = rustup_home().map(|home| home.join("toolchains").join("esp"));
+        audit(
+            AuditAction::CreateDir,
+            &toolchain.unwrap_or_default().to_string_lossy(),
+            Some(format!(
+                "espup install, targets: {}",
+                options.targets.join(",")
+            )),
+        );
+    }
+    result
 }
 
 async fn install_rust_toolchain(
     window: Window,
     app: AppHandle,
-    selected_variant: Option<&String>,
-) -> Result<String, String> {
+    selected_variant: Option<String>,
+    gcc: GccOptions,
+    targets: Vec<String>,
+) -> HelmResult<String> {
     info!("Installing Rust toolchain via espup... (this might take a while)");
 
-    let espup_path = dirs::home_dir()
-        .ok_or("Failed to get home directory")?
-        .join(".cargo/bin/espup")
-        .to_str()
-        .unwrap()
-        .to_string();
-
-    #[cfg(not(target_os = "windows"))]
-    let args = vec!["install"];
-    #[cfg(target_os = "windows")]
-    let mut args = vec!["install"];
-    // If there's a variant specified for Windows, pass it as a parameter
-    #[cfg(target_os = "windows")]
-    if let Some(variant) = selected_variant {
-        args.push("--default-host");
-        args.push(variant);
-    }
-
-    let result = run_external_command_with_progress(
-        window.clone(),
-        app.clone(),
-        &espup_path,
-        &args,
-        "PROGRESS_EVENT",
-    )
-    .await;
-
-    match result {
+    let options = EspupOptions {
+        default_host: get_default_host(&app, selected_variant),
+        targets,
+        gcc,
+        extended_llvm: false,
+    };
+    match espup_install(window, options).await {
         Ok(_) => {
             info!("Rust toolchain installed successfully via espup.");
+            write_launcher()?;
             Ok("Rust toolchain installed successfully!".into())
         }
-        Err(_) => {
-            info!("Failed to install Rust toolchain via espup.");
-            Err("Failed to install Rust toolchain via espup.".into())
+        Err(e) => {
+            info!("Failed to install Rust toolchain via espup: {}", e);
+            Err(e)
         }
     }
 }
 
 #[cfg(target_os = "windows")]
-async fn install_vc_tools_and_sdk(window: Window, app: tauri::AppHandle) -> Result<String, String> {
+async fn install_vc_tools_and_sdk(window: Window, app: tauri::AppHandle) -> HelmResult<String> {
     info!("Downloading Visual Studio Build Tools and Windows SDK...");
 
     // Download vs_buildtools.exe
-    let url = "https://aka.ms/vs/17/release/vs_buildtools.exe";
-    let response = reqwest::get(url)
-        .await
-        .map_err(|e| format!("Failed to download VS Build Tools: {}", e))?;
-    let bytes = response
-        .bytes()
-        .await
-        .map_err(|e| format!("Failed to read response bytes: {}", e))?;
+    let response = services(&app).http.get(VS_BUILDTOOLS_URL).await?;
+    let bytes = response.error_for_status()?.bytes().await?;
 
     // Save to a temporary location
     use std::env;
     let tmp_dir = env::temp_dir();
     let file_path = tmp_dir.join("vs_buildtools.exe");
-    fs::write(&file_path, &bytes).await;
+    write_executable(&file_path, &bytes, Probe::Header(PE_HEADER)).await?;
     info!("Starting installer at {:?}", &file_path.display());
 
     // Run the installer with the necessary components
-    let args = [
-        "--passive",
-        "--wait",
-        "--add",
-        "Microsoft.VisualStudio.Component.VC.Tools.x86.x64",
-        "--add",
-        "Microsoft.VisualStudio.Component.Windows11SDK.22621",
-    ];
     run_external_command_with_progress(
         window.clone(),
         app,
         &file_path.to_string_lossy(),
-        &args,
+        &VS_BUILDTOOLS_ARGS,
         "Installing Visual Studio Build Tools and Windows SDK...",
     )
-    .await;
+    .await?;
 
     info!("Visual Studio Build Tools and Windows SDK installed successfully!");
 
     Ok("Visual Studio Build Tools and Windows SDK installed successfully!".into())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::mock::{self, MockFs, MockHttp, MockProcess};
+
+    #[test]
+    fn parses_tool_versions() {
+        let process = MockProcess::default()
+            .with("cargo --version", "cargo 1.77.0 (3fe68eabf 2024-02-29)\n")
+            .with(
+                "rustc +esp --version",
+                "rustc 1.82.0-nightly (f5ee3f6a5 2024-10-10) (1.82.0.3)\n",
+            );
+        assert_eq!(
+            tool_version(&process, "cargo", &["--version"], None).as_deref(),
+            Some("1.77.0")
+        );
+        assert_eq!(
+            tool_version_xtensa(&process, "rustc", &["+esp", "--version"], Some("rustc"))
+                .as_deref(),
+            Some("1.82.0.3")
+        );
+        assert_eq!(
+            tool_version(&process, "espflash", &["--version"], None),
+            None
+        );
+    }
+
+    #[test]
+    fn parses_git_version_after_prefix() {
+        let process = MockProcess::default().with("git --version", "git version 2.45.2\n");
+        assert_eq!(
+            tool_version_git(&process, "git", &["--version"], None).as_deref(),
+            Some("2.45.2")
+        );
+    }
+
+    #[test]
+    fn keyword_filters_unexpected_output() {
+        let process =
+            MockProcess::default().with("rustc +nightly --version", "error: no such toolchain");
+        assert_eq!(
+            tool_version(&process, "rustc", &["+nightly", "--version"], Some("rustc")),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn components_are_checked_in_sysroot() {
+        let sysroot = Path::new("/toolchains/esp");
+        let process = MockProcess::default()
+            .with("rustc +esp --print sysroot", "/toolchains/esp\n")
+            .with(
+                "rustc +esp --version",
+                "rustc 1.82.0-nightly (f5ee3f6a5 2024-10-10) (1.82.0.3)",
+            );
+        let fs = MockFs::default()
+            .with_path(component_path(sysroot, "rust-src"))
+            .with_path(component_path(sysroot, "clippy"));
+        let services = mock::services(MockHttp::default(), process, fs);
+
+        let response = rust_support_with(&services, None, None).await;
+        assert_eq!(response.xtensa.as_deref(), Some("1.82.0.3"));
+        assert_eq!(response.riscv, None);
+        let installed: Vec<_> = response
+            .components
+            .iter()
+            .filter(|status| status.installed)
+            .map(|status| (status.toolchain.as_str(), status.component.as_str()))
+            .collect();
+        assert_eq!(installed, [("esp", "rust-src"), ("esp", "clippy")]);
+    }
+
+    #[test]
+    fn chip_matrix_finds_esp_clang_through_services() {
+        let esp = rustup_home().unwrap().join("toolchains").join("esp");
+        let esp_clang = esp
+            .join("xtensa-esp32-elf-clang")
+            .join("esp-17.0.1_20240419")
+            .join("esp-clang");
+        let fs = MockFs::default()
+            .with_path(esp_clang)
+            .with_path(esp.join("xtensa-esp-elf"));
+        let services = mock::services(MockHttp::default(), MockProcess::default(), fs);
+
+        let matrix = chip_matrix(&services, "nightly");
+        let esp32 = matrix.iter().find(|support| support.chip == "esp32");
+        let installed: Vec<bool> = esp32
+            .unwrap()
+            .components
+            .iter()
+            .map(|component| component.installed)
+            .collect();
+        // Rust target, GCC and esp-clang
+        assert_eq!(installed, [false, true, true]);
+    }
+
+    fn espup_installation(targets: &[&str]) -> ExistingInstallation {
+        ExistingInstallation {
+            kind: "espup".into(),
+            path: "/rustup/toolchains/esp".into(),
+            version: Some("1.82.0.3".into()),
+            targets: targets.iter().map(|target| target.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn adopted_toolchain_skips_install() {
+        let process = MockProcess::default().with(
+            "rustc +esp --version",
+            "rustc 1.82.0-nightly (f5ee3f6a5 2024-10-10) (1.82.0.3)",
+        );
+        let fs = MockFs::default().with_path("/rustup/toolchains/esp");
+        let services = mock::services(MockHttp::default(), process, fs);
+        let adopted = [espup_installation(&CHIPS)];
+
+        let found = adopted_toolchain(&services, &adopted, &["esp32s3".into()]);
+        assert!(found == Some(&adopted[0]));
+        assert!(adopted_toolchain(&services, &adopted, &[]) == Some(&adopted[0]));
+    }
+
+    #[test]
+    fn adopted_toolchain_must_cover_targets_and_work() {
+        let process = MockProcess::default().with(
+            "rustc +esp --version",
+            "rustc 1.82.0-nightly (f5ee3f6a5 2024-10-10) (1.82.0.3)",
+        );
+        let fs = MockFs::default().with_path("/rustup/toolchains/esp");
+        let services = mock::services(MockHttp::default(), process, fs);
+        let xtensa_only = [espup_installation(&["esp32", "esp32s2", "esp32s3"])];
+        assert!(adopted_toolchain(&services, &xtensa_only, &["esp32c3".into()]).is_none());
+        assert!(adopted_toolchain(&services, &xtensa_only, &[]).is_none());
+
+        // Toolchain directory was removed after adoption
+        let services = mock::services(
+            MockHttp::default(),
+            MockProcess::default(),
+            MockFs::default(),
+        );
+        let adopted = [espup_installation(&CHIPS)];
+        assert!(adopted_toolchain(&services, &adopted, &["esp32".into()]).is_none());
+    }
+}
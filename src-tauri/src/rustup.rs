@@ -0,0 +1,492 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use log::info;
+use tauri::{AppHandle, Window};
+
+#[cfg(unix)]
+use crate::atomic_file::SCRIPT_HEADER;
+use crate::atomic_file::{write_executable, Probe};
+use crate::audit::{audit, AuditAction};
+use crate::error::{HelmError, HelmResult};
+use crate::external_command::{
+    run_external_command_interactive, run_external_command_output,
+    run_external_command_with_progress,
+};
+use crate::inventory::cargo_home;
+use crate::portable::portable_root;
+use crate::services::services;
+use crate::system_install::is_system_scope;
+
+#[cfg(windows)]
+use std::os::windows::process::CommandExt;
+#[cfg(windows)]
+const CREATE_NO_WINDOW: u32 = 0x08000000; // Windows specific constant to hide console window
+
+// Line which marks PATH added to shell profiles by esp-helm
+#[cfg(unix)]
+const PATH_MARKER: &str = "# Added by esp-helm";
+#[cfg(unix)]
+const SHELL_PROFILES: [&str; 3] = [".profile", ".bashrc", ".zshenv"];
+
+// rustup-init options, rustup never modifies PATH itself, see add_cargo_bin_to_path
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct RustupOptions {
+    // "minimal" or "default", minimal skips docs which are never used for ESP targets
+    pub profile: String,
+    pub default_toolchain: String,
+    pub components: Vec<String>,
+    pub targets: Vec<String>,
+}
+
+impl Default for RustupOptions {
+    fn default() -> Self {
+        Self {
+            profile: "minimal".into(),
+            default_toolchain: "stable".into(),
+            components: vec![],
+            targets: vec![],
+        }
+    }
+}
+
+#[derive(Default, serde::Serialize)]
+pub struct RustupToolchain {
+    pub name: String,
+    pub default: bool,
+    pub active: bool,
+}
+
+#[derive(Default, serde::Serialize)]
+pub struct RustupShow {
+    pub default_host: Option<String>,
+    pub rustup_home: Option<String>,
+    pub toolchains: Vec<RustupToolchain>,
+    // Targets installed for active toolchain
+    pub targets: Vec<String>,
+    pub active_toolchain: Option<String>,
+}
+
+pub fn rustup_bin() -> Option<PathBuf> {
+    let path = cargo_home()?
+        .join("bin")
+        .join(format!("rustup{}", std::env::consts::EXE_SUFFIX));
+    path.exists().then_some(path)
+}
+
+pub fn validate_options(options: &RustupOptions) -> HelmResult<()> {
+    if !matches!(options.profile.as_str(), "minimal" | "default" | "complete") {
+        return Err(HelmError::Validation(format!(
+            "Unknown rustup profile {}",
+            options.profile
+        )));
+    }
+    let names = options
+        .components
+        .iter()
+        .chain(&options.targets)
+        .chain(std::iter::once(&options.default_toolchain));
+    for name in names {
+        if name.is_empty() || name.starts_with('-') || name.contains(char::is_whitespace) {
+            return Err(HelmError::Validation(format!(
+                "Invalid rustup name {}",
+                name
+            )));
+        }
+    }
+    Ok(())
+}
+
+// Arguments shared by rustup-init and rustup toolchain install
+pub fn selection_args(options: &RustupOptions) -> Vec<String> {
+    let mut args = vec!["--profile".to_string(), options.profile.clone()];
+    for component in &options.components {
+        args.push("--component".into());
+        args.push(component.clone());
+    }
+    for target in &options.targets {
+        args.push("--target".into());
+        args.push(target.clone());
+    }
+    args
+}
+
+// URL and file name of rustup-init for this host
+pub fn rustup_init_source() -> (String, &'static str) {
+    #[cfg(unix)]
+    {
+        ("https://sh.rustup.rs".to_string(), "rustup-init.sh")
+    }
+    #[cfg(windows)]
+    {
+        let host = crate::arch::detect_host(false)
+            .map(|host| host.rustup_host)
+            .unwrap_or_else(|_| format!("{}-pc-windows-msvc", crate::arch::hardware_arch()));
+        (
+            format!(
+                "https://static.rust-lang.org/rustup/dist/{}/rustup-init.exe",
+                host
+            ),
+            "rustup-init.exe",
+        )
+    }
+}
+
+// Installer is downloaded to temporary directory instead of expecting it in working directory
+async fn download_rustup_init(app: &AppHandle) -> HelmResult<PathBuf> {
+    let (url, name) = rustup_init_source();
+    let bytes = services(app)
+        .http
+        .get(&url)
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+    let path = std::env::temp_dir().join(name);
+    // Shell script downloads the real installer when started, so only its shebang is checked
+    #[cfg(unix)]
+    let probe = Probe::Header(SCRIPT_HEADER);
+    #[cfg(windows)]
+    let probe = Probe::Version;
+    write_executable(&path, &bytes, probe).await?;
+    Ok(path)
+}
+
+// Shell profiles which do not have PATH entry of esp-helm yet
+#[cfg(unix)]
+pub fn profiles_to_update() -> Vec<PathBuf> {
+    let Some(home) = dirs::home_dir() else {
+        return vec![];
+    };
+    SHELL_PROFILES
+        .iter()
+        .map(|profile| home.join(profile))
+        // Profiles are not created, a new .profile would hide .bash_profile or .bash_login
+        .filter(|path| path.exists())
+        .filter(|path| {
+            !std::fs::read_to_string(path)
+                .unwrap_or_default()
+                .contains(PATH_MARKER)
+        })
+        .collect()
+}
+
+#[cfg(unix)]
+fn add_cargo_bin_to_path(bin: &Path) -> HelmResult<Vec<String>> {
+    let block = format!("{}\nexport PATH=\"{}:$PATH\"\n", PATH_MARKER, bin.display());
+    let mut updated = vec![];
+    for path in profiles_to_update() {
+        let content = std::fs::read_to_string(&path).unwrap_or_default();
+        let separator = if content.is_empty() || content.ends_with('\n') {
+            ""
+        } else {
+            "\n"
+        };
+        std::fs::write(&path, format!("{}{}{}", content, separator, block))?;
+        audit(
+            AuditAction::ModifyEnvironment,
+            &path.to_string_lossy(),
+            Some(format!("Add {} to PATH", bin.display())),
+        );
+        updated.push(path.to_string_lossy().to_string());
+    }
+    Ok(updated)
+}
+
+// User Path in registry, setx would truncate it to 1024 characters
+#[cfg(windows)]
+fn add_cargo_bin_to_path(bin: &Path) -> HelmResult<Vec<String>> {
+    let script = format!(
+        "$bin = '{}'; $path = [Environment]::GetEnvironmentVariable('Path', 'User'); \
+         if (($path -split ';') -notcontains $bin) {{ \
+         [Environment]::SetEnvironmentVariable('Path', \"$bin;$path\", 'User'); 'updated' }}",
+        bin.display().to_string().replace('\'', "''")
+    );
+    let output = Command::new("powershell")
+        .args(["-NoProfile", "-Command", &script])
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()?;
+    if !output.status.success() {
+        return Err(HelmError::child_process_failed(
+            output.status.code(),
+            &String::from_utf8_lossy(&output.stderr),
+        ));
+    }
+    let updated = String::from_utf8_lossy(&output.stdout).contains("updated");
+    if updated {
+        audit(
+            AuditAction::ModifyEnvironment,
+            "User Path",
+            Some(format!("Add {} to PATH", bin.display())),
+        );
+    }
+    Ok(if updated {
+        vec!["user Path".into()]
+    } else {
+        vec![]
+    })
+}
+
+// Installs rustup with selected profile, components and targets. When rustup already
+// exists, the same selection is applied to the default toolchain.
+pub async fn install_rustup(
+    window: Window,
+    app: AppHandle,
+    selected_variant: Option<String>,
+    options: RustupOptions,
+) -> HelmResult<String> {
+    validate_options(&options)?;
+    let mut args = selection_args(&options);
+
+    if let Some(rustup) = rustup_bin() {
+        info!("Rustup already installed, applying component selection");
+        let rustup = rustup.to_string_lossy().to_string();
+        let mut install_args = vec![
+            "toolchain".to_string(),
+            "install".into(),
+            options.default_toolchain.clone(),
+        ];
+        install_args.append(&mut args);
+        let install_args: Vec<&str> = install_args.iter().map(String::as_str).collect();
+        run_external_command_with_progress(window, app, &rustup, &install_args, "PROGRESS_EVENT")
+            .await?;
+        return Ok("Rustup already installed".into());
+    }
+
+    info!("Installing rustup...");
+    let rustup_init = download_rustup_init(&app).await?;
+    let mut init_args = vec![
+        "-y".to_string(),
+        "--no-modify-path".into(),
+        "--default-toolchain".into(),
+        options.default_toolchain.clone(),
+    ];
+    init_args.append(&mut args);
+    if cfg!(windows) {
+        if let Some(variant) = selected_variant {
+            init_args.push("--default-host".into());
+            init_args.push(variant);
+        }
+    }
+
+    #[cfg(unix)]
+    let (command, init_args) = {
+        let mut sh_args = vec![rustup_init.to_string_lossy().to_string()];
+        sh_args.append(&mut init_args);
+        ("sh".to_string(), sh_args)
+    };
+    #[cfg(windows)]
+    let command = rustup_init.to_string_lossy().to_string();
+    let init_args: Vec<&str> = init_args.iter().map(String::as_str).collect();
+    // rustup might ask questions even with -y, e.g. about existing installation
+    let result = run_external_command_interactive(window, app, &command, &init_args).await;
+    let _ = std::fs::remove_file(&rustup_init);
+    result?;
+
+    // Portable installation is used through its launcher, system scope uses system profile
+    if portable_root().is_none() && !is_system_scope(&app) {
+        let bin = cargo_home()
+            .ok_or(HelmError::NotFound("cargo home".into()))?
+            .join("bin");
+        for profile in add_cargo_bin_to_path(&bin)? {
+            info!("Added {} to PATH in {}", bin.display(), profile);
+        }
+    }
+
+    info!("Rustup installed");
+    Ok("Rustup installed".into())
+}
+
+fn strip_markers(line: &str) -> (String, bool, bool) {
+    let (name, markers) = match line.split_once(" (") {
+        Some((name, markers)) => (name, markers.trim_end_matches(')')),
+        None => (line, ""),
+    };
+    (
+        name.trim().to_string(),
+        markers.contains("default"),
+        markers.contains("active"),
+    )
+}
+
+// Sections are headers underlined by dashes. rustup 1.28 lists "name: ..." and
+// "installed targets:" inside active toolchain section, older versions have separate sections.
+pub fn parse_rustup_show(output: &str) -> RustupShow {
+    let mut show = RustupShow::default();
+    let lines: Vec<&str> = output.lines().collect();
+    let mut section = "";
+    let mut in_targets = false;
+    for (index, line) in lines.iter().enumerate() {
+        let trimmed = line.trim();
+        if lines
+            .get(index + 1)
+            .is_some_and(|next| !next.is_empty() && next.chars().all(|c| c == '-'))
+        {
+            section = trimmed;
+            in_targets = false;
+            continue;
+        }
+        if trimmed.is_empty() || trimmed.chars().all(|c| c == '-') {
+            continue;
+        }
+        if let Some(host) = trimmed.strip_prefix("Default host:") {
+            show.default_host = Some(host.trim().to_string());
+        } else if let Some(home) = trimmed.strip_prefix("rustup home:") {
+            show.rustup_home = Some(home.trim().to_string());
+        } else if section == "installed toolchains" {
+            let (name, default, active) = strip_markers(trimmed);
+            show.toolchains.push(RustupToolchain {
+                name,
+                default,
+                active,
+            });
+        } else if section.starts_with("installed targets") {
+            show.targets.push(trimmed.to_string());
+        } else if section == "active toolchain" {
+            if let Some(name) = trimmed.strip_prefix("name:") {
+                show.active_toolchain = Some(name.trim().to_string());
+            } else if trimmed == "installed targets:" {
+                in_targets = true;
+            } else if in_targets && line.starts_with(' ') {
+                show.targets.push(trimmed.to_string());
+            } else if show.active_toolchain.is_none() && !trimmed.contains(':') {
+                show.active_toolchain = Some(strip_markers(trimmed).0);
+            }
+        }
+    }
+    if let Some(active) = &show.active_toolchain {
+        for toolchain in &mut show.toolchains {
+            toolchain.active |= &toolchain.name == active;
+        }
+    }
+    show
+}
+
+pub fn rustup_show() -> Option<RustupShow> {
+    let rustup = rustup_bin()?;
+    let mut cmd = Command::new(rustup);
+    cmd.arg("show");
+    #[cfg(windows)]
+    cmd.creation_flags(CREATE_NO_WINDOW);
+    let output = cmd.output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(parse_rustup_show(&String::from_utf8_lossy(&output.stdout)))
+}
+
+// Command to get toolchains and targets known to rustup
+#[tauri::command]
+pub async fn get_rustup_status() -> HelmResult<RustupShow> {
+    let rustup = rustup_bin().ok_or(HelmError::NotFound("rustup".into()))?;
+    let output = run_external_command_output(&rustup.to_string_lossy(), &["show"]).await?;
+    Ok(parse_rustup_show(&output))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // rustup 1.28
+    const SHOW: &str = "Default host: x86_64-unknown-linux-gnu
+rustup home:  /home/user/.rustup
+
+installed toolchains
+--------------------
+stable-x86_64-unknown-linux-gnu (active, default)
+nightly-2024-06-01-x86_64-unknown-linux-gnu
+esp
+
+active toolchain
+----------------
+name: stable-x86_64-unknown-linux-gnu
+active because: it's the default toolchain
+installed targets:
+  riscv32imc-unknown-none-elf
+  x86_64-unknown-linux-gnu
+";
+
+    // rustup 1.27 and older
+    const SHOW_LEGACY: &str = "Default host: x86_64-unknown-linux-gnu
+rustup home:  /home/user/.rustup
+
+installed toolchains
+--------------------
+
+stable-x86_64-unknown-linux-gnu
+nightly-x86_64-unknown-linux-gnu (default)
+
+installed targets for active toolchain
+--------------------------------------
+
+riscv32imc-unknown-none-elf
+x86_64-unknown-linux-gnu
+
+active toolchain
+----------------
+
+nightly-x86_64-unknown-linux-gnu (default)
+rustc 1.80.0-nightly (72fdf913c 2024-06-05)
+";
+
+    fn toolchains(show: &RustupShow) -> Vec<(&str, bool, bool)> {
+        show.toolchains
+            .iter()
+            .map(|toolchain| (toolchain.name.as_str(), toolchain.default, toolchain.active))
+            .collect()
+    }
+
+    #[test]
+    fn parses_rustup_show() {
+        let show = parse_rustup_show(SHOW);
+        assert_eq!(
+            show.default_host.as_deref(),
+            Some("x86_64-unknown-linux-gnu")
+        );
+        assert_eq!(show.rustup_home.as_deref(), Some("/home/user/.rustup"));
+        assert_eq!(
+            toolchains(&show),
+            [
+                ("stable-x86_64-unknown-linux-gnu", true, true),
+                ("nightly-2024-06-01-x86_64-unknown-linux-gnu", false, false),
+                ("esp", false, false),
+            ]
+        );
+        assert_eq!(
+            show.active_toolchain.as_deref(),
+            Some("stable-x86_64-unknown-linux-gnu")
+        );
+        assert_eq!(
+            show.targets,
+            ["riscv32imc-unknown-none-elf", "x86_64-unknown-linux-gnu"]
+        );
+    }
+
+    #[test]
+    fn parses_legacy_rustup_show() {
+        let show = parse_rustup_show(SHOW_LEGACY);
+        assert_eq!(
+            toolchains(&show),
+            [
+                ("stable-x86_64-unknown-linux-gnu", false, false),
+                ("nightly-x86_64-unknown-linux-gnu", true, true),
+            ]
+        );
+        assert_eq!(
+            show.active_toolchain.as_deref(),
+            Some("nightly-x86_64-unknown-linux-gnu")
+        );
+        assert_eq!(
+            show.targets,
+            ["riscv32imc-unknown-none-elf", "x86_64-unknown-linux-gnu"]
+        );
+    }
+
+    #[test]
+    fn empty_output_gives_nothing() {
+        let show = parse_rustup_show("");
+        assert!(show.toolchains.is_empty());
+        assert!(show.active_toolchain.is_none());
+    }
+}
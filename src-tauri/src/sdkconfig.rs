@@ -0,0 +1,1206 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use log::info;
+
+use crate::atomic_file::write_atomic;
+use crate::error::{HelmError, HelmResult};
+use crate::inventory::espressif_home;
+
+const CONFIG_PREFIX: &str = "CONFIG_";
+
+#[derive(Clone, serde::Serialize)]
+pub struct DefaultValue {
+    value: String,
+    condition: Option<String>,
+}
+
+// Node of Kconfig menu tree, kind is one of "menu", "config", "choice" and "comment"
+#[derive(Clone, Default, serde::Serialize)]
+pub struct ConfigNode {
+    kind: String,
+    name: Option<String>,
+    prompt: Option<String>,
+    value_type: Option<String>,
+    defaults: Vec<DefaultValue>,
+    // All dependencies including the ones inherited from menus and if blocks
+    depends_on: Option<String>,
+    range: Option<(String, String)>,
+    help: Option<String>,
+    // Reverse dependencies, value is name of the target symbol
+    selects: Vec<DefaultValue>,
+    implies: Vec<DefaultValue>,
+    // Condition of menu prompt, hidden menu still gives values to its options
+    visible_if: Option<String>,
+    value: Option<String>,
+    // Dependencies are met, value is written to sdkconfig
+    enabled: bool,
+    // Enabled and not hidden by menu, user can change the value
+    visible: bool,
+    // Forced to y by select of another option
+    selected: bool,
+    children: Vec<ConfigNode>,
+}
+
+impl ConfigNode {
+    fn new(kind: &str, name: Option<String>, prompt: Option<String>) -> Self {
+        ConfigNode {
+            kind: kind.into(),
+            name,
+            prompt,
+            ..Default::default()
+        }
+    }
+
+    fn add_dependency(&mut self, expr: &str) {
+        self.depends_on = Some(match self.depends_on.take() {
+            Some(existing) => format!("({}) && ({})", existing, expr),
+            None => expr.to_string(),
+        });
+    }
+}
+
+// Kconfig files of all components, the same lists are generated by ESP-IDF build
+struct KconfigSources {
+    kconfigs: Vec<PathBuf>,
+    projbuilds: Vec<PathBuf>,
+    idf_target: String,
+}
+
+struct Parser<'a> {
+    idf_path: &'a Path,
+    sources: &'a KconfigSources,
+    // Open menus and choices, root is the first one
+    stack: Vec<ConfigNode>,
+    // Conditions of open if blocks
+    conditions: Vec<String>,
+    // Attributes apply to the last child of stack top, or to the block when it was just opened
+    block_is_current: bool,
+    help_indent: Option<usize>,
+}
+
+fn indent_width(line: &str) -> usize {
+    line.chars()
+        .take_while(|c| c.is_whitespace())
+        .map(|c| if c == '\t' { 8 } else { 1 })
+        .sum()
+}
+
+// Strings of Kconfig and sdkconfig escape quote and backslash with backslash
+fn unquote(value: &str) -> String {
+    let value = value.trim();
+    let Some(inner) = value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) else {
+        return value.to_string();
+    };
+    let mut result = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => result.extend(chars.next()),
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+// Length of quoted string at the start of text including both quotes, escaped quotes
+// do not end the string
+fn quoted_len(text: &str) -> usize {
+    let mut escaped = false;
+    for (i, c) in text.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return i + 1,
+            _ => {}
+        }
+    }
+    text.len()
+}
+
+// Split "\"prompt\" if EXPR" or "VALUE if EXPR" to value and condition
+fn split_condition(rest: &str) -> (String, Option<String>) {
+    let rest = rest.trim();
+    let value_end = if rest.starts_with('"') {
+        quoted_len(rest)
+    } else {
+        rest.find(char::is_whitespace).unwrap_or(rest.len())
+    };
+    let (value, tail) = rest.split_at(value_end);
+    let condition = tail
+        .trim()
+        .strip_prefix("if ")
+        .map(|condition| condition.trim().to_string());
+    (value.to_string(), condition)
+}
+
+impl<'a> Parser<'a> {
+    fn expand_variables(&self, value: &str) -> String {
+        let mut result = value.to_string();
+        for (name, replacement) in [
+            ("IDF_PATH", self.idf_path.to_string_lossy().to_string()),
+            ("IDF_TARGET", self.sources.idf_target.clone()),
+        ] {
+            for pattern in [
+                format!("$({})", name),
+                format!("${{{}}}", name),
+                format!("${}", name),
+            ] {
+                result = result.replace(&pattern, &replacement);
+            }
+        }
+        result
+    }
+
+    fn current(&mut self) -> Option<&mut ConfigNode> {
+        let top = self.stack.last_mut()?;
+        if self.block_is_current {
+            Some(top)
+        } else {
+            top.children.last_mut()
+        }
+    }
+
+    fn push_entry(&mut self, mut node: ConfigNode) {
+        for condition in &self.conditions {
+            node.add_dependency(condition);
+        }
+        if let Some(top) = self.stack.last_mut() {
+            top.children.push(node);
+        }
+        self.block_is_current = false;
+    }
+
+    fn open_block(&mut self, mut node: ConfigNode) {
+        for condition in &self.conditions {
+            node.add_dependency(condition);
+        }
+        self.stack.push(node);
+        self.block_is_current = true;
+    }
+
+    fn close_block(&mut self) {
+        if self.stack.len() > 1 {
+            let node = self.stack.pop().unwrap();
+            self.stack.last_mut().unwrap().children.push(node);
+        }
+        self.block_is_current = false;
+    }
+
+    fn source(&mut self, keyword: &str, path: &str, current_file: &Path) {
+        let optional = keyword.starts_with('o');
+        let relative = keyword.trim_start_matches('o') == "rsource";
+        let path = unquote(path);
+
+        // Generated lists of component Kconfig files
+        let files = if path.contains("COMPONENT_KCONFIGS_PROJBUILD_SOURCE_FILE") {
+            self.sources.projbuilds.clone()
+        } else if path.contains("COMPONENT_KCONFIGS_SOURCE_FILE") {
+            self.sources.kconfigs.clone()
+        } else {
+            let expanded = self.expand_variables(&path);
+            let file = if relative {
+                current_file
+                    .parent()
+                    .unwrap_or(Path::new(""))
+                    .join(expanded)
+            } else if Path::new(&expanded).is_absolute() {
+                PathBuf::from(expanded)
+            } else {
+                self.idf_path.join(expanded)
+            };
+            vec![file]
+        };
+
+        for file in files {
+            if let Err(e) = self.parse_file(&file) {
+                if !optional {
+                    info!("Unable to read Kconfig {}: {}", file.display(), e);
+                }
+            }
+        }
+    }
+
+    fn parse_file(&mut self, path: &Path) -> HelmResult<()> {
+        let content = std::fs::read_to_string(path)?;
+        let mut lines = content.lines();
+        while let Some(raw_line) = lines.next() {
+            // Join lines ending with backslash
+            let mut line = raw_line.to_string();
+            while line.ends_with('\\') {
+                line.pop();
+                match lines.next() {
+                    Some(next) => line.push_str(next),
+                    None => break,
+                }
+            }
+
+            let indent = indent_width(&line);
+            let trimmed = line.trim();
+
+            if let Some(help_indent) = self.help_indent {
+                if trimmed.is_empty() || indent > help_indent {
+                    if let Some(node) = self.current() {
+                        let help = node.help.get_or_insert_with(String::new);
+                        if !help.is_empty() {
+                            help.push('\n');
+                        }
+                        help.push_str(trimmed);
+                    }
+                    continue;
+                }
+                self.help_indent = None;
+            }
+
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+
+            let (keyword, rest) = trimmed
+                .split_once(char::is_whitespace)
+                .map(|(keyword, rest)| (keyword, rest.trim()))
+                .unwrap_or((trimmed, ""));
+
+            match keyword {
+                "mainmenu" => {}
+                "menu" => self.open_block(ConfigNode::new("menu", None, Some(unquote(rest)))),
+                "endmenu" | "endchoice" => self.close_block(),
+                "choice" => {
+                    let name = (!rest.is_empty()).then(|| rest.to_string());
+                    self.open_block(ConfigNode::new("choice", name, None));
+                }
+                "config" | "menuconfig" => {
+                    self.push_entry(ConfigNode::new("config", Some(rest.to_string()), None))
+                }
+                "comment" => self.push_entry(ConfigNode::new("comment", None, Some(unquote(rest)))),
+                "if" => self.conditions.push(rest.to_string()),
+                "endif" => {
+                    self.conditions.pop();
+                }
+                "source" | "rsource" | "osource" | "orsource" => self.source(keyword, rest, path),
+                "bool" | "tristate" | "int" | "hex" | "string" => {
+                    let (prompt, _) = split_condition(rest);
+                    if let Some(node) = self.current() {
+                        node.value_type = Some(keyword.replace("tristate", "bool"));
+                        if !prompt.is_empty() {
+                            node.prompt = Some(unquote(&prompt));
+                        }
+                    }
+                }
+                "def_bool" | "def_tristate" | "def_int" | "def_hex" | "def_string" => {
+                    let (value, condition) = split_condition(rest);
+                    if let Some(node) = self.current() {
+                        let value_type = keyword.trim_start_matches("def_");
+                        node.value_type = Some(value_type.replace("tristate", "bool"));
+                        node.defaults.push(DefaultValue { value, condition });
+                    }
+                }
+                "prompt" => {
+                    let (prompt, _) = split_condition(rest);
+                    if let Some(node) = self.current() {
+                        node.prompt = Some(unquote(&prompt));
+                    }
+                }
+                "default" => {
+                    let (value, condition) = split_condition(rest);
+                    if let Some(node) = self.current() {
+                        node.defaults.push(DefaultValue { value, condition });
+                    }
+                }
+                "depends" => {
+                    let expr = rest.trim_start_matches("on").trim().to_string();
+                    if let Some(node) = self.current() {
+                        node.add_dependency(&expr);
+                    }
+                }
+                "range" => {
+                    let mut bounds = rest.split_whitespace();
+                    if let (Some(min), Some(max)) = (bounds.next(), bounds.next()) {
+                        let range = (min.to_string(), max.to_string());
+                        if let Some(node) = self.current() {
+                            node.range = Some(range);
+                        }
+                    }
+                }
+                "select" | "imply" => {
+                    let (target, condition) = split_condition(rest);
+                    if let Some(node) = self.current() {
+                        let reverse = DefaultValue {
+                            value: target,
+                            condition,
+                        };
+                        match keyword {
+                            "select" => node.selects.push(reverse),
+                            _ => node.implies.push(reverse),
+                        }
+                    }
+                }
+                "visible" => {
+                    let expr = rest.trim_start_matches("if").trim().to_string();
+                    if let Some(node) = self.current() {
+                        node.visible_if = Some(expr);
+                    }
+                }
+                "help" | "---help---" => self.help_indent = Some(indent),
+                // option is not needed for editing
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+// Kconfig and Kconfig.projbuild of ESP-IDF components and of project components
+fn collect_sources(idf_path: &Path, project: &Path, idf_target: String) -> KconfigSources {
+    let mut component_dirs: Vec<PathBuf> = vec![];
+    for parent in [idf_path.join("components"), project.join("components")] {
+        if let Ok(entries) = std::fs::read_dir(parent) {
+            let mut dirs: Vec<PathBuf> = entries
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|p| p.is_dir())
+                .collect();
+            dirs.sort();
+            component_dirs.extend(dirs);
+        }
+    }
+    component_dirs.push(project.join("main"));
+
+    let existing = |name: &str| -> Vec<PathBuf> {
+        component_dirs
+            .iter()
+            .map(|dir| dir.join(name))
+            .filter(|file| file.exists())
+            .collect()
+    };
+    KconfigSources {
+        kconfigs: existing("Kconfig"),
+        projbuilds: existing("Kconfig.projbuild"),
+        idf_target,
+    }
+}
+
+pub fn parse_kconfig(idf_path: &Path, project: &Path, idf_target: &str) -> HelmResult<ConfigNode> {
+    let root_kconfig = idf_path.join("Kconfig");
+    if !root_kconfig.exists() {
+        return Err(HelmError::NotFound(root_kconfig.display().to_string()));
+    }
+
+    let sources = collect_sources(idf_path, project, idf_target.to_string());
+    let mut parser = Parser {
+        idf_path,
+        sources: &sources,
+        stack: vec![ConfigNode::new("menu", None, Some("Configuration".into()))],
+        conditions: vec![],
+        block_is_current: false,
+        help_indent: None,
+    };
+    parser.parse_file(&root_kconfig)?;
+    while parser.stack.len() > 1 {
+        parser.close_block();
+    }
+    Ok(parser.stack.pop().unwrap())
+}
+
+// Tokenizer and evaluator of Kconfig expressions: symbols, literals, !, &&, ||,
+// comparisons and parentheses
+fn tokenize(expr: &str) -> Vec<String> {
+    let mut tokens = vec![];
+    let chars: Vec<char> = expr.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '"' {
+            let rest: String = chars[i..].iter().collect();
+            let quoted = &rest[..quoted_len(&rest)];
+            i += quoted.chars().count();
+            tokens.push(quoted.to_string());
+        } else if "!=<>&|".contains(c) {
+            let pair: String = chars[i..(i + 2).min(chars.len())].iter().collect();
+            if ["!=", "<=", ">=", "&&", "||"].contains(&pair.as_str()) {
+                tokens.push(pair);
+                i += 2;
+            } else {
+                tokens.push(c.to_string());
+                i += 1;
+            }
+        } else if c == '(' || c == ')' {
+            tokens.push(c.to_string());
+            i += 1;
+        } else {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            if i == start {
+                i += 1;
+            }
+            tokens.push(chars[start..i].iter().collect());
+        }
+    }
+    tokens
+}
+
+struct Evaluator<'a> {
+    tokens: Vec<String>,
+    position: usize,
+    values: &'a HashMap<String, String>,
+}
+
+impl<'a> Evaluator<'a> {
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.position).map(|t| t.as_str())
+    }
+
+    fn next(&mut self) -> Option<String> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn term(&mut self) -> String {
+        let token = self.next().unwrap_or_default();
+        if token.starts_with('"') {
+            return unquote(&token);
+        }
+        match self.values.get(&token) {
+            Some(value) => value.clone(),
+            // Unknown symbols are "n", literals like numbers stay as they are
+            None if token.starts_with(|c: char| c.is_ascii_uppercase())
+                && token
+                    .chars()
+                    .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_') =>
+            {
+                "n".into()
+            }
+            None => token,
+        }
+    }
+
+    fn primary(&mut self) -> bool {
+        if self.peek() == Some("(") {
+            self.next();
+            let value = self.or();
+            if self.peek() == Some(")") {
+                self.next();
+            }
+            return value;
+        }
+
+        let left = self.term();
+        match self.peek() {
+            Some(op @ ("=" | "!=" | "<" | ">" | "<=" | ">=")) => {
+                let op = op.to_string();
+                self.next();
+                let right = self.term();
+                let ordering = match (parse_number(&left), parse_number(&right)) {
+                    (Some(l), Some(r)) => l.cmp(&r),
+                    _ => left.cmp(&right),
+                };
+                match op.as_str() {
+                    "=" => ordering.is_eq(),
+                    "!=" => ordering.is_ne(),
+                    "<" => ordering.is_lt(),
+                    ">" => ordering.is_gt(),
+                    "<=" => ordering.is_le(),
+                    _ => ordering.is_ge(),
+                }
+            }
+            _ => left == "y" || left == "m",
+        }
+    }
+
+    fn unary(&mut self) -> bool {
+        if self.peek() == Some("!") {
+            self.next();
+            return !self.unary();
+        }
+        self.primary()
+    }
+
+    fn and(&mut self) -> bool {
+        let mut value = self.unary();
+        while self.peek() == Some("&&") {
+            self.next();
+            value &= self.unary();
+        }
+        value
+    }
+
+    fn or(&mut self) -> bool {
+        let mut value = self.and();
+        while self.peek() == Some("||") {
+            self.next();
+            value |= self.and();
+        }
+        value
+    }
+}
+
+fn parse_number(value: &str) -> Option<i64> {
+    match value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        Some(hex) => i64::from_str_radix(hex, 16).ok(),
+        None => value.parse().ok(),
+    }
+}
+
+pub fn evaluate(expr: &str, values: &HashMap<String, String>) -> bool {
+    let mut evaluator = Evaluator {
+        tokens: tokenize(expr),
+        position: 0,
+        values,
+    };
+    evaluator.or()
+}
+
+fn resolve_value(value: &str, values: &HashMap<String, String>) -> String {
+    if value.starts_with('"') {
+        return unquote(value);
+    }
+    values
+        .get(value)
+        .cloned()
+        .unwrap_or_else(|| value.to_string())
+}
+
+// Read "CONFIG_X=value" and "# CONFIG_X is not set" lines, names are stored without prefix
+pub fn read_sdkconfig(path: &Path) -> HashMap<String, String> {
+    let mut values = HashMap::new();
+    let Ok(content) = std::fs::read_to_string(path) else {
+        return values;
+    };
+    for line in content.lines().map(str::trim) {
+        if let Some(name) = line
+            .strip_prefix("# ")
+            .and_then(|line| line.strip_suffix(" is not set"))
+            .and_then(|name| name.strip_prefix(CONFIG_PREFIX))
+        {
+            values.insert(name.to_string(), "n".into());
+        } else if let Some((name, value)) = line.split_once('=') {
+            if let Some(name) = name.strip_prefix(CONFIG_PREFIX) {
+                values.insert(name.to_string(), unquote(value));
+            }
+        }
+    }
+    values
+}
+
+// Symbols selected or implied by options which are y
+#[derive(Default)]
+struct ReverseDeps {
+    selected: HashSet<String>,
+    implied: HashSet<String>,
+}
+
+impl ReverseDeps {
+    fn collect(node: &ConfigNode, values: &HashMap<String, String>, reverse: &mut Self) {
+        if node.kind == "config" && node.value.as_deref() == Some("y") {
+            let active = |entry: &&DefaultValue| {
+                entry
+                    .condition
+                    .as_ref()
+                    .map(|condition| evaluate(condition, values))
+                    .unwrap_or(true)
+            };
+            for select in node.selects.iter().filter(active) {
+                reverse.selected.insert(select.value.clone());
+            }
+            for imply in node.implies.iter().filter(active) {
+                reverse.implied.insert(imply.value.clone());
+            }
+        }
+        for child in &node.children {
+            Self::collect(child, values, reverse);
+        }
+    }
+}
+
+// Symbols might depend on symbols defined later in the tree and selects change values of
+// other symbols, so values are computed until they stop changing
+const MAX_PASSES: usize = 8;
+
+// Fill value and visibility of every node
+fn compute_values(root: &mut ConfigNode, explicit: &HashMap<String, String>) {
+    let mut values: HashMap<String, String> = HashMap::new();
+    for _ in 0..MAX_PASSES {
+        let mut reverse = ReverseDeps::default();
+        ReverseDeps::collect(root, &values, &mut reverse);
+        let previous = values.clone();
+        compute_node(root, (true, true), explicit, &reverse, &mut values);
+        if values == previous {
+            break;
+        }
+    }
+}
+
+fn default_value(node: &ConfigNode, values: &HashMap<String, String>) -> Option<String> {
+    node.defaults
+        .iter()
+        .find(|default| {
+            default
+                .condition
+                .as_ref()
+                .map(|condition| evaluate(condition, values))
+                .unwrap_or(true)
+        })
+        .map(|default| resolve_value(&default.value, values))
+}
+
+fn compute_node(
+    node: &mut ConfigNode,
+    (parent_enabled, parent_visible): (bool, bool),
+    explicit: &HashMap<String, String>,
+    reverse: &ReverseDeps,
+    values: &mut HashMap<String, String>,
+) {
+    let condition = |expr: &Option<String>| {
+        expr.as_ref()
+            .map(|expr| evaluate(expr, values))
+            .unwrap_or(true)
+    };
+    node.enabled = parent_enabled && condition(&node.depends_on);
+    node.visible = parent_visible && node.enabled && condition(&node.visible_if);
+
+    if node.kind == "config" {
+        let name = node.name.clone().unwrap_or_default();
+        let is_bool = node.value_type.as_deref() == Some("bool");
+        // Select forces the value also when dependencies of the symbol are not met
+        node.selected = is_bool && reverse.selected.contains(&name);
+        let value = if node.selected {
+            Some("y".to_string())
+        } else if !node.enabled {
+            None
+        } else if let Some(value) = explicit.get(&name) {
+            Some(value.clone())
+        } else if is_bool && reverse.implied.contains(&name) {
+            Some("y".to_string())
+        } else {
+            default_value(node, values).or_else(|| is_bool.then(|| "n".into()))
+        };
+        match &value {
+            Some(value) => values.insert(name, value.clone()),
+            None => values.remove(&name),
+        };
+        node.value = value;
+    }
+
+    let parent = (node.enabled, node.visible);
+    for child in node.children.iter_mut() {
+        compute_node(child, parent, explicit, reverse, values);
+    }
+
+    // Exactly one member of enabled choice is selected
+    if node.kind == "choice" && node.enabled {
+        let members: Vec<String> = node
+            .children
+            .iter()
+            .filter(|child| child.kind == "config" && child.enabled)
+            .filter_map(|child| child.name.clone())
+            .collect();
+        let chosen = |member: &&String| {
+            reverse.selected.contains(*member)
+                || explicit.get(*member).map(|v| v == "y").unwrap_or(false)
+        };
+        let selected = members
+            .iter()
+            .find(chosen)
+            .or_else(|| {
+                default_value(node, values)
+                    .and_then(|default| members.iter().find(|member| **member == default))
+            })
+            .or(members.first())
+            .cloned();
+        for child in node.children.iter_mut() {
+            if let Some(name) = &child.name {
+                let value = if Some(name) == selected.as_ref() {
+                    "y"
+                } else {
+                    "n"
+                };
+                child.value = Some(value.into());
+                values.insert(name.clone(), value.into());
+            }
+        }
+        node.value = selected;
+    }
+}
+
+fn find_config<'a>(node: &'a ConfigNode, name: &str) -> Option<&'a ConfigNode> {
+    if node.kind == "config" && node.name.as_deref() == Some(name) {
+        return Some(node);
+    }
+    node.children
+        .iter()
+        .find_map(|child| find_config(child, name))
+}
+
+fn find_choice_siblings(node: &ConfigNode, name: &str) -> Option<Vec<String>> {
+    if node.kind == "choice"
+        && node
+            .children
+            .iter()
+            .any(|child| child.name.as_deref() == Some(name))
+    {
+        return Some(
+            node.children
+                .iter()
+                .filter_map(|child| child.name.clone())
+                .filter(|child| child != name)
+                .collect(),
+        );
+    }
+    node.children
+        .iter()
+        .find_map(|child| find_choice_siblings(child, name))
+}
+
+fn collect_values(node: &ConfigNode, values: &mut HashMap<String, String>) {
+    if let (Some(name), Some(value)) = (&node.name, &node.value) {
+        if node.kind == "config" {
+            values.insert(name.clone(), value.clone());
+        }
+    }
+    for child in &node.children {
+        collect_values(child, values);
+    }
+}
+
+fn validate_value(
+    node: &ConfigNode,
+    value: &str,
+    values: &HashMap<String, String>,
+) -> HelmResult<()> {
+    let name = node.name.as_deref().unwrap_or_default();
+    let invalid = |reason: &str| HelmError::Validation(format!("{}: {}", name, reason));
+    match node.value_type.as_deref() {
+        Some("bool") if value != "y" && value != "n" => Err(invalid("expected y or n")),
+        Some("int") | Some("hex") => {
+            if node.value_type.as_deref() == Some("hex") && !value.starts_with("0x") {
+                return Err(invalid("hex value must start with 0x"));
+            }
+            let number = parse_number(value).ok_or_else(|| invalid("not a number"))?;
+            if let Some((min, max)) = &node.range {
+                let min = parse_number(&resolve_value(min, values));
+                let max = parse_number(&resolve_value(max, values));
+                if min.map(|min| number < min).unwrap_or(false)
+                    || max.map(|max| number > max).unwrap_or(false)
+                {
+                    return Err(invalid("value out of range"));
+                }
+            }
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+fn format_value(node: &ConfigNode, value: &str) -> String {
+    let name = node.name.as_deref().unwrap_or_default();
+    match node.value_type.as_deref() {
+        Some("bool") if value == "n" => format!("# {}{} is not set", CONFIG_PREFIX, name),
+        Some("string") => format!("{}{}={}", CONFIG_PREFIX, name, quote(value)),
+        _ => format!("{}{}={}", CONFIG_PREFIX, name, value),
+    }
+}
+
+// Full sdkconfig in the same layout as menuconfig writes it. Options of disabled and hidden
+// menus are written too when they have value, e.g. when they are selected.
+fn render_sdkconfig(node: &ConfigNode, lines: &mut Vec<String>) {
+    let header = node.kind == "menu" && node.enabled;
+    match node.kind.as_str() {
+        "menu" if header => {
+            if let Some(prompt) = &node.prompt {
+                lines.push("#".into());
+                lines.push(format!("# {}", prompt));
+                lines.push("#".into());
+            }
+        }
+        "config" => {
+            if let Some(value) = &node.value {
+                lines.push(format_value(node, value));
+            }
+        }
+        _ => {}
+    }
+    for child in &node.children {
+        render_sdkconfig(child, lines);
+    }
+    if header {
+        if let Some(prompt) = &node.prompt {
+            lines.push(format!("# end of {}", prompt));
+        }
+    }
+}
+
+// esp-idf-sys projects keep only sdkconfig.defaults, ESP-IDF projects have full sdkconfig
+fn is_esp_idf_sys_project(project: &Path) -> bool {
+    project.join("Cargo.toml").exists() && !project.join("sdkconfig").exists()
+}
+
+// ESP-IDF used for Kconfig metadata, esp-idf-sys downloads its own copy to .embuild
+pub fn find_idf_path(project: &Path, idf_path: Option<String>) -> HelmResult<PathBuf> {
+    let newest_in = |dir: PathBuf| -> Option<PathBuf> {
+        let mut dirs: Vec<PathBuf> = std::fs::read_dir(dir)
+            .ok()?
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.join("Kconfig").exists())
+            .collect();
+        dirs.sort();
+        dirs.pop()
+    };
+
+    idf_path
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("IDF_PATH").map(PathBuf::from))
+        .filter(|path| path.join("Kconfig").exists())
+        .or_else(|| newest_in(project.join(".embuild").join("espressif").join("esp-idf")))
+        .or_else(|| espressif_home().and_then(|home| newest_in(home.join("esp-idf"))))
+        .ok_or(HelmError::NotFound("ESP-IDF with Kconfig files".into()))
+}
+
+fn load_explicit_values(project: &Path) -> HashMap<String, String> {
+    let mut values = read_sdkconfig(&project.join("sdkconfig.defaults"));
+    values.extend(read_sdkconfig(&project.join("sdkconfig")));
+    values
+}
+
+fn load_tree(
+    project: &Path,
+    idf_path: Option<String>,
+    explicit: &HashMap<String, String>,
+) -> HelmResult<ConfigNode> {
+    let idf_path = find_idf_path(project, idf_path)?;
+    let target = explicit
+        .get("IDF_TARGET")
+        .cloned()
+        .unwrap_or_else(|| "esp32".into());
+    let mut root = parse_kconfig(&idf_path, project, &target)?;
+    compute_values(&mut root, explicit);
+    Ok(root)
+}
+
+// Command to get option tree of the project with current values
+#[tauri::command]
+pub async fn get_sdkconfig(
+    project_path: String,
+    idf_path: Option<String>,
+) -> HelmResult<ConfigNode> {
+    tokio::task::spawn_blocking(move || {
+        let project = Path::new(&project_path);
+        load_tree(project, idf_path, &load_explicit_values(project))
+    })
+    .await
+    .map_err(|e| HelmError::Other(format!("Kconfig parsing failed: {}", e)))?
+}
+
+// Command to change options. Edits are validated against types, ranges and dependencies
+// before sdkconfig (or sdkconfig.defaults of esp-idf-sys project) is rewritten.
+#[tauri::command]
+pub async fn update_sdkconfig(
+    project_path: String,
+    idf_path: Option<String>,
+    changes: HashMap<String, String>,
+) -> HelmResult<ConfigNode> {
+    let project = PathBuf::from(&project_path);
+    let (root, content, file) = tokio::task::spawn_blocking(move || -> HelmResult<_> {
+        let mut explicit = load_explicit_values(&project);
+        let current = load_tree(&project, idf_path.clone(), &explicit)?;
+
+        let mut values = HashMap::new();
+        collect_values(&current, &mut values);
+        for (name, value) in &changes {
+            let name = name.trim_start_matches(CONFIG_PREFIX);
+            let node = find_config(&current, name)
+                .ok_or(HelmError::NotFound(format!("Option {}", name)))?;
+            validate_value(node, value, &values)?;
+            // Selecting choice member deselects the others
+            if value == "y" {
+                for sibling in find_choice_siblings(&current, name).unwrap_or_default() {
+                    explicit.remove(&sibling);
+                }
+            }
+            explicit.insert(name.to_string(), value.clone());
+        }
+
+        let root = load_tree(&project, idf_path, &explicit)?;
+        for (name, value) in &changes {
+            let name = name.trim_start_matches(CONFIG_PREFIX);
+            let Some(node) = find_config(&root, name) else {
+                continue;
+            };
+            if node.selected && value == "n" {
+                return Err(HelmError::Validation(format!(
+                    "{} is selected by another option",
+                    name
+                )));
+            }
+            if !node.visible && !node.selected {
+                return Err(HelmError::Validation(format!(
+                    "{} depends on {}",
+                    name,
+                    node.depends_on.as_deref().unwrap_or_default()
+                )));
+            }
+        }
+
+        let (file, lines) = if is_esp_idf_sys_project(&project) {
+            let mut names: Vec<&String> = explicit.keys().collect();
+            names.sort();
+            let lines = names
+                .into_iter()
+                .map(|name| match find_config(&root, name) {
+                    Some(node) => format_value(node, &explicit[name]),
+                    None => format!("{}{}={}", CONFIG_PREFIX, name, explicit[name]),
+                })
+                .collect();
+            (project.join("sdkconfig.defaults"), lines)
+        } else {
+            let mut lines = vec![
+                "#".to_string(),
+                "# Automatically generated file. DO NOT EDIT.".to_string(),
+                "#".to_string(),
+            ];
+            render_sdkconfig(&root, &mut lines);
+            (project.join("sdkconfig"), lines)
+        };
+        Ok((root, lines.join("\n") + "\n", file))
+    })
+    .await
+    .map_err(|e| HelmError::Other(format!("Kconfig parsing failed: {}", e)))??;
+
+    write_atomic(&file, content.as_bytes()).await?;
+    info!("{} updated", file.display());
+    Ok(root)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const KCONFIG: &str = r#"
+mainmenu "Test"
+
+menu "Wi-Fi"
+    config WIFI_ENABLED
+        bool "Enable Wi-Fi"
+        default y
+
+    config WIFI_SSID
+        string "SSID"
+        depends on WIFI_ENABLED
+        default "esp"
+
+    config WIFI_RETRIES
+        int "Retries"
+        depends on WIFI_ENABLED
+        range 1 10
+        default 3
+        help
+            Connection attempts
+            before giving up.
+endmenu
+
+choice LOG_LEVEL
+    prompt "Log level"
+    default LOG_LEVEL_INFO
+
+    config LOG_LEVEL_WARN
+        bool "Warning"
+    config LOG_LEVEL_INFO
+        bool "Info"
+endchoice
+
+if LOG_LEVEL_INFO
+config LOG_BUFFER
+    hex "Log buffer"
+    default 0x400 if WIFI_SSID = "esp"
+    default 0x100
+endif
+"#;
+
+    fn explicit(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    const REVERSE_KCONFIG: &str = r#"
+config BT_ENABLED
+    bool "Bluetooth"
+    select NVS_ENCRYPTION if SECURE
+    imply BT_LOG
+
+config SECURE
+    bool "Secure"
+    default y
+
+config ADVANCED
+    bool "Advanced"
+
+menu "Storage"
+    visible if ADVANCED
+
+config NVS_ENCRYPTION
+    bool "Encrypt NVS"
+    depends on ADVANCED
+
+config BT_LOG
+    bool "Bluetooth log"
+
+config STORAGE_NAME
+    string "Storage name"
+    default "nvs"
+endmenu
+"#;
+
+    // ESP-IDF directory with only root Kconfig, computed with given explicit values
+    fn tree(explicit: &HashMap<String, String>) -> ConfigNode {
+        tree_from(KCONFIG, explicit)
+    }
+
+    fn tree_from(kconfig: &str, explicit: &HashMap<String, String>) -> ConfigNode {
+        let idf = TempDir::new().unwrap();
+        let idf_path = idf.path();
+        std::fs::write(idf_path.join("Kconfig"), kconfig).unwrap();
+        let mut root = parse_kconfig(idf_path, &idf_path.join("project"), "esp32").unwrap();
+        compute_values(&mut root, explicit);
+        root
+    }
+
+    #[test]
+    fn parses_kconfig() {
+        let root = tree(&HashMap::new());
+        let retries = find_config(&root, "WIFI_RETRIES").unwrap();
+        assert_eq!(retries.value_type.as_deref(), Some("int"));
+        assert_eq!(retries.prompt.as_deref(), Some("Retries"));
+        assert_eq!(retries.depends_on.as_deref(), Some("WIFI_ENABLED"));
+        assert_eq!(retries.range, Some(("1".into(), "10".into())));
+        assert_eq!(
+            retries.help.as_deref(),
+            Some("Connection attempts\nbefore giving up.")
+        );
+        assert_eq!(retries.value.as_deref(), Some("3"));
+        assert_eq!(
+            find_config(&root, "LOG_BUFFER").and_then(|node| node.depends_on.as_deref()),
+            Some("LOG_LEVEL_INFO")
+        );
+        assert_eq!(
+            find_choice_siblings(&root, "LOG_LEVEL_INFO"),
+            Some(vec!["LOG_LEVEL_WARN".to_string()])
+        );
+    }
+
+    #[test]
+    fn evaluates_dependencies() {
+        let values = explicit(&[("A", "y"), ("B", "n"), ("NAME", "esp"), ("SIZE", "0x400")]);
+        assert!(evaluate("A", &values));
+        assert!(!evaluate("B", &values));
+        assert!(!evaluate("UNKNOWN", &values));
+        assert!(evaluate("A && !B", &values));
+        assert!(evaluate("(B || A) && !UNKNOWN", &values));
+        assert!(evaluate("NAME = \"esp\"", &values));
+        assert!(evaluate("NAME != \"other\"", &values));
+        assert!(evaluate("SIZE >= 1024", &values));
+        assert!(!evaluate("SIZE < 0x100", &values));
+
+        // Disabled parent hides dependent options, defaults follow conditions
+        let root = tree(&explicit(&[("WIFI_ENABLED", "n")]));
+        let ssid = find_config(&root, "WIFI_SSID").unwrap();
+        assert!(!ssid.visible);
+        assert_eq!(ssid.value, None);
+        assert_eq!(
+            find_config(&root, "LOG_BUFFER").unwrap().value.as_deref(),
+            Some("0x100")
+        );
+
+        let root = tree(&explicit(&[("LOG_LEVEL_WARN", "y")]));
+        assert_eq!(
+            find_config(&root, "LOG_LEVEL_INFO")
+                .unwrap()
+                .value
+                .as_deref(),
+            Some("n")
+        );
+        assert!(!find_config(&root, "LOG_BUFFER").unwrap().visible);
+    }
+
+    #[test]
+    fn written_sdkconfig_reads_back() {
+        let explicit = explicit(&[("WIFI_SSID", "home"), ("WIFI_RETRIES", "5")]);
+        let root = tree(&explicit);
+        let mut lines = vec![];
+        render_sdkconfig(&root, &mut lines);
+        assert!(lines.contains(&"CONFIG_WIFI_SSID=\"home\"".to_string()));
+        assert!(lines.contains(&"# CONFIG_LOG_LEVEL_WARN is not set".to_string()));
+        assert!(lines.contains(&"# end of Wi-Fi".to_string()));
+
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("sdkconfig");
+        std::fs::write(&path, lines.join("\n") + "\n").unwrap();
+        let read = read_sdkconfig(&path);
+
+        let mut expected = HashMap::new();
+        collect_values(&root, &mut expected);
+        assert_eq!(read, expected);
+        assert_eq!(tree(&read).value, root.value);
+    }
+
+    #[test]
+    fn select_and_imply_set_reverse_dependencies() {
+        let root = tree_from(REVERSE_KCONFIG, &explicit(&[("BT_ENABLED", "y")]));
+        let encryption = find_config(&root, "NVS_ENCRYPTION").unwrap();
+        assert!(encryption.selected);
+        assert!(!encryption.enabled);
+        assert_eq!(encryption.value.as_deref(), Some("y"));
+        assert_eq!(
+            find_config(&root, "BT_LOG").unwrap().value.as_deref(),
+            Some("y")
+        );
+
+        // Options of hidden menu keep their values and are written
+        let name = find_config(&root, "STORAGE_NAME").unwrap();
+        assert!(name.enabled && !name.visible);
+        let mut lines = vec![];
+        render_sdkconfig(&root, &mut lines);
+        assert!(lines.contains(&"CONFIG_NVS_ENCRYPTION=y".to_string()));
+        assert!(lines.contains(&"CONFIG_STORAGE_NAME=\"nvs\"".to_string()));
+
+        // Implied value can be changed, select depends on its condition
+        let root = tree_from(
+            REVERSE_KCONFIG,
+            &explicit(&[("BT_ENABLED", "y"), ("BT_LOG", "n"), ("SECURE", "n")]),
+        );
+        assert_eq!(
+            find_config(&root, "BT_LOG").unwrap().value.as_deref(),
+            Some("n")
+        );
+        let encryption = find_config(&root, "NVS_ENCRYPTION").unwrap();
+        assert!(!encryption.selected);
+        assert_eq!(encryption.value, None);
+    }
+
+    #[test]
+    fn strings_are_escaped() {
+        let value = r#"say "hi" C:\esp"#;
+        assert_eq!(quote(value), r#""say \"hi\" C:\\esp""#);
+        assert_eq!(unquote(&quote(value)), value);
+        assert_eq!(
+            split_condition(r#""a \"b\"" if X"#),
+            (r#""a \"b\"""#.to_string(), Some("X".to_string()))
+        );
+        let values = explicit(&[("NAME", "a\"b")]);
+        assert!(evaluate(r#"NAME = "a\"b""#, &values));
+    }
+}
@@ -0,0 +1,513 @@
+use std::path::Path;
+
+use log::info;
+use tauri::{AppHandle, Window};
+
+use crate::error::{HelmError, HelmResult};
+use crate::esptool::{espressif_tool, esptool_chip_info};
+use crate::external_command::run_external_command_with_progress;
+
+#[derive(Clone, serde::Serialize)]
+pub struct EfuseChange {
+    name: String,
+    value: String,
+    description: String,
+}
+
+#[derive(Clone, Default, serde::Deserialize)]
+pub struct SecurityFeatures {
+    #[serde(default)]
+    secure_boot: bool,
+    #[serde(default)]
+    flash_encryption: bool,
+    // Release mode disables JTAG and download mode decryption, development mode keeps reflashing possible
+    #[serde(default)]
+    release_mode: bool,
+}
+
+#[derive(serde::Serialize)]
+pub struct SecurityPlan {
+    chip: String,
+    signing_scheme: Option<String>,
+    // All listed eFuses are one-time programmable
+    efuses: Vec<EfuseChange>,
+    steps: Vec<String>,
+    // Text user has to type to confirm burning, e.g. "BURN esp32c3 /dev/ttyUSB0"
+    confirmation: String,
+}
+
+fn efuse(name: &str, value: &str, description: &str) -> EfuseChange {
+    EfuseChange {
+        name: name.to_string(),
+        value: value.to_string(),
+        description: description.to_string(),
+    }
+}
+
+// Secure boot v2 signature scheme supported by chip
+fn signing_scheme(chip: &str) -> Option<&'static str> {
+    match chip {
+        "esp32c2" => Some("ecdsa256"),
+        "esp32" | "esp32s2" | "esp32s3" | "esp32c3" | "esp32c6" | "esp32h2" => Some("rsa3072"),
+        _ => None,
+    }
+}
+
+fn confirmation_text(chip: &str, port: &str) -> String {
+    format!("BURN {} {}", chip, port)
+}
+
+fn ensure_confirmed(chip: &str, port: &str, confirmation: &str) -> HelmResult<()> {
+    if confirmation != confirmation_text(chip, port) {
+        return Err(HelmError::Validation(
+            "Confirmation text does not match, eFuses were not burned".into(),
+        ));
+    }
+    Ok(())
+}
+
+// Major part of "v3.0", older esptool prints only "3"
+fn major_revision(revision: &str) -> Option<u32> {
+    revision
+        .trim_start_matches('v')
+        .split('.')
+        .next()?
+        .parse()
+        .ok()
+}
+
+// ESP32 supports secure boot v2 only from ECO3, older revisions have only v1
+fn ensure_secure_boot_v2(chip: &str, revision: Option<&str>) -> HelmResult<()> {
+    if chip != "esp32" {
+        return Ok(());
+    }
+    match revision.and_then(major_revision) {
+        Some(major) if major >= 3 => Ok(()),
+        Some(_) => Err(HelmError::Validation(format!(
+            "Secure boot v2 requires ESP32 revision v3.0 (ECO3) or newer, chip is {}",
+            revision.unwrap_or_default()
+        ))),
+        None => Err(HelmError::Validation(
+            "ESP32 revision is unknown, secure boot v2 requires v3.0 (ECO3) or newer".into(),
+        )),
+    }
+}
+
+async fn check_secure_boot_v2(chip: &str, port: &str) -> HelmResult<()> {
+    if chip != "esp32" {
+        return Ok(());
+    }
+    let info = esptool_chip_info(port.to_string()).await?;
+    ensure_secure_boot_v2(chip, info.revision.as_deref())
+}
+
+fn efuse_changes(chip: &str, features: &SecurityFeatures) -> Vec<EfuseChange> {
+    let mut efuses = vec![];
+    if chip == "esp32" {
+        if features.secure_boot {
+            efuses.push(efuse(
+                "BLOCK2",
+                "SHA-256 of signing public key",
+                "Public key digest checked by ROM on every boot",
+            ));
+            efuses.push(efuse(
+                "ABS_DONE_1",
+                "1",
+                "Enables secure boot v2, unsigned bootloaders will not boot",
+            ));
+        }
+        if features.flash_encryption {
+            efuses.push(efuse(
+                "BLOCK1",
+                "flash encryption key",
+                "AES key, read and write protected after burning",
+            ));
+            efuses.push(efuse(
+                "FLASH_CRYPT_CONFIG",
+                "0xF",
+                "Uses all key tweak bits",
+            ));
+            efuses.push(efuse(
+                "FLASH_CRYPT_CNT",
+                "odd value",
+                "Enables transparent flash decryption",
+            ));
+        }
+        if features.release_mode {
+            efuses.push(efuse("JTAG_DISABLE", "1", "Disables JTAG debugging"));
+            efuses.push(efuse(
+                "DISABLE_DL_ENCRYPT",
+                "1",
+                "Disables flash encryption in download mode",
+            ));
+            efuses.push(efuse(
+                "DISABLE_DL_DECRYPT",
+                "1",
+                "Disables flash decryption in download mode",
+            ));
+            efuses.push(efuse(
+                "DISABLE_DL_CACHE",
+                "1",
+                "Disables flash cache in download mode",
+            ));
+        }
+        return efuses;
+    }
+
+    if features.secure_boot {
+        efuses.push(efuse(
+            "BLOCK_KEY0",
+            "SHA-256 of signing public key",
+            "Public key digest checked by ROM on every boot",
+        ));
+        efuses.push(efuse(
+            "KEY_PURPOSE_0",
+            "SECURE_BOOT_DIGEST0",
+            "Marks key block as secure boot digest",
+        ));
+        efuses.push(efuse(
+            "SECURE_BOOT_EN",
+            "1",
+            "Enables secure boot v2, unsigned bootloaders will not boot",
+        ));
+    }
+    if features.flash_encryption {
+        let purpose = match chip {
+            "esp32s2" | "esp32s3" => "XTS_AES_256_KEY_1, XTS_AES_256_KEY_2",
+            _ => "XTS_AES_128_KEY",
+        };
+        let blocks = match chip {
+            "esp32s2" | "esp32s3" => "BLOCK_KEY1, BLOCK_KEY2",
+            _ => "BLOCK_KEY1",
+        };
+        efuses.push(efuse(
+            blocks,
+            "flash encryption key",
+            "XTS-AES key, read and write protected after burning",
+        ));
+        efuses.push(efuse(
+            "KEY_PURPOSE_1",
+            purpose,
+            "Marks key block as flash encryption key",
+        ));
+        efuses.push(efuse(
+            "SPI_BOOT_CRYPT_CNT",
+            "odd value",
+            "Enables transparent flash decryption",
+        ));
+    }
+    if features.release_mode {
+        efuses.push(efuse("DIS_PAD_JTAG", "1", "Disables JTAG on pins"));
+        efuses.push(efuse("DIS_USB_JTAG", "1", "Disables JTAG over USB"));
+        efuses.push(efuse(
+            "DIS_DOWNLOAD_MANUAL_ENCRYPT",
+            "1",
+            "Disables flash encryption in download mode",
+        ));
+        if features.secure_boot {
+            efuses.push(efuse(
+                "SECURE_BOOT_AGGRESSIVE_REVOKE",
+                "1",
+                "Revokes key digest after first failed verification",
+            ));
+        }
+    }
+    efuses
+}
+
+fn ensure_new_file(path: &str) -> HelmResult<()> {
+    if Path::new(path).exists() {
+        // Overwriting a key which is already burned to a device would brick updates
+        return Err(HelmError::Validation(format!(
+            "{} already exists, refusing to overwrite key",
+            path
+        )));
+    }
+    Ok(())
+}
+
+// Command to explain which eFuses will be burned, nothing is written to the device
+#[tauri::command]
+pub async fn plan_security(
+    chip: String,
+    port: String,
+    features: SecurityFeatures,
+) -> HelmResult<SecurityPlan> {
+    let scheme =
+        signing_scheme(&chip).ok_or(HelmError::Validation(format!("Unknown chip {}", chip)))?;
+    if features.secure_boot {
+        check_secure_boot_v2(&chip, &port).await?;
+    }
+
+    let mut steps = vec![];
+    if features.secure_boot {
+        steps.push(format!(
+            "Generate {} signing key and store it safely",
+            scheme
+        ));
+        steps.push("Sign bootloader and application with the key".to_string());
+    }
+    if features.flash_encryption {
+        steps.push("Generate flash encryption key".to_string());
+        steps.push("Burn flash encryption key, it cannot be read back".to_string());
+    }
+    steps.push("Burn eFuses, this cannot be undone".to_string());
+    if features.secure_boot && features.release_mode {
+        steps.push("From now on only images signed with the key can be flashed".to_string());
+    }
+
+    Ok(SecurityPlan {
+        signing_scheme: features.secure_boot.then(|| scheme.to_string()),
+        efuses: efuse_changes(&chip, &features),
+        steps,
+        confirmation: confirmation_text(&chip, &port),
+        chip,
+    })
+}
+
+// Command to generate secure boot v2 signing key
+#[tauri::command]
+pub async fn generate_signing_key(
+    window: Window,
+    app: AppHandle,
+    chip: String,
+    key_path: String,
+) -> HelmResult<String> {
+    ensure_new_file(&key_path)?;
+    let scheme =
+        signing_scheme(&chip).ok_or(HelmError::Validation(format!("Unknown chip {}", chip)))?;
+    let espsecure = espressif_tool("espsecure")?;
+    run_external_command_with_progress(
+        window,
+        app,
+        &espsecure,
+        &[
+            "generate_signing_key",
+            "--version",
+            "2",
+            "--scheme",
+            scheme,
+            &key_path,
+        ],
+        "PROGRESS_EVENT",
+    )
+    .await?;
+    Ok(key_path)
+}
+
+// Command to sign bootloader or application image with secure boot v2 key
+#[tauri::command]
+pub async fn sign_image(
+    window: Window,
+    app: AppHandle,
+    key_path: String,
+    image: String,
+    output: String,
+) -> HelmResult<String> {
+    let espsecure = espressif_tool("espsecure")?;
+    run_external_command_with_progress(
+        window,
+        app,
+        &espsecure,
+        &[
+            "sign_data",
+            "--version",
+            "2",
+            "--keyfile",
+            &key_path,
+            "--output",
+            &output,
+            &image,
+        ],
+        "PROGRESS_EVENT",
+    )
+    .await?;
+    Ok(output)
+}
+
+// Command to generate flash encryption key, XTS-AES-256 is supported only by S2 and S3
+#[tauri::command]
+pub async fn generate_flash_encryption_key(
+    window: Window,
+    app: AppHandle,
+    chip: String,
+    key_path: String,
+) -> HelmResult<String> {
+    ensure_new_file(&key_path)?;
+    let keylen = match chip.as_str() {
+        "esp32s2" | "esp32s3" => "512",
+        _ => "256",
+    };
+    let espsecure = espressif_tool("espsecure")?;
+    run_external_command_with_progress(
+        window,
+        app,
+        &espsecure,
+        &[
+            "generate_flash_encryption_key",
+            "--keylen",
+            keylen,
+            &key_path,
+        ],
+        "PROGRESS_EVENT",
+    )
+    .await?;
+    Ok(key_path)
+}
+
+// Command to burn secure boot key digest, requires confirmation text from plan_security
+#[tauri::command]
+pub async fn burn_secure_boot_key(
+    window: Window,
+    app: AppHandle,
+    chip: String,
+    port: String,
+    key_path: String,
+    confirmation: String,
+) -> HelmResult<String> {
+    ensure_confirmed(&chip, &port, &confirmation)?;
+    check_secure_boot_v2(&chip, &port).await?;
+    let espefuse = espressif_tool("espefuse")?;
+    let mut args = vec![
+        "--chip",
+        chip.as_str(),
+        "--port",
+        port.as_str(),
+        "--do-not-confirm",
+    ];
+    if chip == "esp32" {
+        args.extend(["burn_key_digest", key_path.as_str()]);
+    } else {
+        args.extend([
+            "burn_key_digest",
+            "BLOCK_KEY0",
+            key_path.as_str(),
+            "SECURE_BOOT_DIGEST0",
+        ]);
+    }
+
+    info!("Burning secure boot key digest to {} on {}", chip, port);
+    run_external_command_with_progress(window, app, &espefuse, &args, "PROGRESS_EVENT").await?;
+    Ok("Secure boot key digest burned".into())
+}
+
+// espefuse arguments burning flash encryption key, 512-bit key of S2 and S3 takes two blocks
+fn flash_encryption_key_args<'a>(chip: &str, key_path: &'a str) -> Vec<&'a str> {
+    match chip {
+        "esp32" => vec!["burn_key", "flash_encryption", key_path],
+        "esp32s2" | "esp32s3" => vec!["burn_key", "BLOCK_KEY1", key_path, "XTS_AES_256_KEY"],
+        _ => vec!["burn_key", "BLOCK_KEY1", key_path, "XTS_AES_128_KEY"],
+    }
+}
+
+// Command to burn flash encryption key, requires confirmation text from plan_security
+#[tauri::command]
+pub async fn burn_flash_encryption_key(
+    window: Window,
+    app: AppHandle,
+    chip: String,
+    port: String,
+    key_path: String,
+    confirmation: String,
+) -> HelmResult<String> {
+    ensure_confirmed(&chip, &port, &confirmation)?;
+    signing_scheme(&chip).ok_or(HelmError::Validation(format!("Unknown chip {}", chip)))?;
+    let espefuse = espressif_tool("espefuse")?;
+    let mut args = vec![
+        "--chip",
+        chip.as_str(),
+        "--port",
+        port.as_str(),
+        "--do-not-confirm",
+    ];
+    args.extend(flash_encryption_key_args(&chip, &key_path));
+
+    info!("Burning flash encryption key to {} on {}", chip, port);
+    run_external_command_with_progress(window, app, &espefuse, &args, "PROGRESS_EVENT").await?;
+    Ok("Flash encryption key burned".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(efuses: &[EfuseChange]) -> Vec<&str> {
+        efuses.iter().map(|efuse| efuse.name.as_str()).collect()
+    }
+
+    #[test]
+    fn esp32_efuses() {
+        let features = SecurityFeatures {
+            secure_boot: true,
+            flash_encryption: true,
+            release_mode: false,
+        };
+        assert_eq!(
+            names(&efuse_changes("esp32", &features)),
+            [
+                "BLOCK2",
+                "ABS_DONE_1",
+                "BLOCK1",
+                "FLASH_CRYPT_CONFIG",
+                "FLASH_CRYPT_CNT"
+            ]
+        );
+    }
+
+    #[test]
+    fn s3_flash_encryption_uses_two_blocks() {
+        let features = SecurityFeatures {
+            flash_encryption: true,
+            ..Default::default()
+        };
+        let efuses = efuse_changes("esp32s3", &features);
+        assert_eq!(
+            names(&efuses),
+            [
+                "BLOCK_KEY1, BLOCK_KEY2",
+                "KEY_PURPOSE_1",
+                "SPI_BOOT_CRYPT_CNT"
+            ]
+        );
+        assert_eq!(efuses[1].value, "XTS_AES_256_KEY_1, XTS_AES_256_KEY_2");
+        assert_eq!(
+            flash_encryption_key_args("esp32s3", "key.bin"),
+            ["burn_key", "BLOCK_KEY1", "key.bin", "XTS_AES_256_KEY"]
+        );
+    }
+
+    #[test]
+    fn release_mode_revokes_with_secure_boot_only() {
+        let release = SecurityFeatures {
+            release_mode: true,
+            ..Default::default()
+        };
+        assert!(
+            !names(&efuse_changes("esp32c3", &release)).contains(&"SECURE_BOOT_AGGRESSIVE_REVOKE")
+        );
+
+        let secure_release = SecurityFeatures {
+            secure_boot: true,
+            release_mode: true,
+            ..Default::default()
+        };
+        let efuses = efuse_changes("esp32c3", &secure_release);
+        assert!(names(&efuses).contains(&"SECURE_BOOT_EN"));
+        assert!(names(&efuses).contains(&"SECURE_BOOT_AGGRESSIVE_REVOKE"));
+    }
+
+    #[test]
+    fn no_features_burn_nothing() {
+        assert!(efuse_changes("esp32c6", &SecurityFeatures::default()).is_empty());
+    }
+
+    #[test]
+    fn secure_boot_v2_needs_eco3() {
+        assert!(ensure_secure_boot_v2("esp32", Some("v3.0")).is_ok());
+        assert!(ensure_secure_boot_v2("esp32", Some("v3.1")).is_ok());
+        assert!(ensure_secure_boot_v2("esp32", Some("3")).is_ok());
+        assert!(ensure_secure_boot_v2("esp32", Some("v1.0")).is_err());
+        assert!(ensure_secure_boot_v2("esp32", None).is_err());
+        assert!(ensure_secure_boot_v2("esp32c3", None).is_ok());
+    }
+}
@@ -0,0 +1,273 @@
+use std::future::Future;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use log::info;
+use tauri::{AppHandle, Manager, Window};
+
+use crate::arch::detect_host;
+use crate::download::download_file;
+use crate::error::{HelmError, HelmResult};
+use crate::external_command::run_external_command_lines;
+use crate::extract::{download_and_extract, extract_archive};
+use crate::os::get_platform;
+use crate::rust::get_tool_version;
+
+const FIXTURE_ENTRY: &str = "fixture/hello.txt";
+const FIXTURE_CONTENT: &[u8] = b"esp-helm selftest\n";
+const ZIP_FIXTURE: &str = "fixture.zip";
+const TAR_FIXTURE: &str = "fixture.tar.gz";
+
+#[derive(serde::Serialize)]
+pub struct SelftestStep {
+    name: String,
+    passed: bool,
+    duration_ms: u128,
+    details: String,
+}
+
+#[derive(serde::Serialize)]
+pub struct SelftestReport {
+    passed: bool,
+    steps: Vec<SelftestStep>,
+}
+
+fn tar_gz_fixture(path: &Path) -> HelmResult<()> {
+    let encoder =
+        flate2::write::GzEncoder::new(std::fs::File::create(path)?, flate2::Compression::default());
+    let mut builder = tar::Builder::new(encoder);
+    let mut header = tar::Header::new_gnu();
+    header.set_size(FIXTURE_CONTENT.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    builder.append_data(&mut header, FIXTURE_ENTRY, FIXTURE_CONTENT)?;
+    builder.into_inner()?.finish()?;
+    Ok(())
+}
+
+fn zip_fixture(path: &Path) -> HelmResult<()> {
+    let mut zip = zip::ZipWriter::new(std::fs::File::create(path)?);
+    zip.start_file(FIXTURE_ENTRY, zip::write::FileOptions::default())?;
+    zip.write_all(FIXTURE_CONTENT)?;
+    zip.finish()?;
+    Ok(())
+}
+
+// Minimal HTTP/1.1 for fixtures, the whole file is sent also for range requests
+fn serve(dir: &Path, mut stream: TcpStream) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default();
+    let name = parts.next().unwrap_or_default().trim_start_matches('/');
+    let body = (!name.contains('/') && !name.contains(".."))
+        .then(|| std::fs::read(dir.join(name)).ok())
+        .flatten();
+    match body {
+        Some(body) => {
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                body.len()
+            )?;
+            if method != "HEAD" {
+                stream.write_all(&body)?;
+            }
+        }
+        None => write!(
+            stream,
+            "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+        )?,
+    }
+    stream.flush()
+}
+
+// Fixtures are served on loopback, so downloads go through reqwest without internet access.
+// Server thread ends with the process.
+fn serve_fixtures(dir: PathBuf) -> HelmResult<String> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let address = listener.local_addr()?;
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let _ = serve(&dir, stream);
+        }
+    });
+    Ok(format!("http://{}", address))
+}
+
+fn check_extracted(dest: &Path, entries: u64) -> HelmResult<String> {
+    let content = std::fs::read(dest.join("hello.txt"))?;
+    if content != FIXTURE_CONTENT {
+        return Err(HelmError::Validation(
+            "Extracted file differs from fixture".into(),
+        ));
+    }
+    Ok(format!(
+        "{} entries extracted to {}",
+        entries,
+        dest.display()
+    ))
+}
+
+fn detection() -> HelmResult<String> {
+    let host = detect_host(false)?;
+    Ok(format!(
+        "{} on {}, esp toolchain host {}, cargo {}",
+        host.host,
+        get_platform(),
+        host.espup_host.as_deref().unwrap_or("none"),
+        get_tool_version("cargo", &["--version"], None).unwrap_or_else(|| "missing".into())
+    ))
+}
+
+async fn run_step(name: &str, step: impl Future<Output = HelmResult<String>>) -> SelftestStep {
+    let started = Instant::now();
+    let result = step.await;
+    info!("Selftest {}: {:?}", name, result);
+    let passed = result.is_ok();
+    SelftestStep {
+        name: name.to_string(),
+        passed,
+        duration_ms: started.elapsed().as_millis(),
+        details: result.unwrap_or_else(|e| e.to_string()),
+    }
+}
+
+// Detection, download, extraction and command streaming against local fixtures, the same
+// code paths as installation uses
+pub async fn run_selftest(window: Window, app: AppHandle) -> HelmResult<SelftestReport> {
+    let dir = std::env::temp_dir().join("esp-helm-selftest");
+    let _ = std::fs::remove_dir_all(&dir);
+    let fixtures = dir.join("fixtures");
+    std::fs::create_dir_all(&fixtures)?;
+    zip_fixture(&fixtures.join(ZIP_FIXTURE))?;
+    tar_gz_fixture(&fixtures.join(TAR_FIXTURE))?;
+    let base_url = serve_fixtures(fixtures.clone())?;
+    let archive = dir.join(ZIP_FIXTURE);
+
+    let mut steps = vec![run_step("detection", async { detection() }).await];
+
+    steps.push(
+        run_step("download", async {
+            let url = format!("{}/{}", base_url, ZIP_FIXTURE);
+            download_file(window.clone(), app.clone(), &url, &archive).await?;
+            if std::fs::read(&archive)? != std::fs::read(fixtures.join(ZIP_FIXTURE))? {
+                return Err(HelmError::Validation(
+                    "Downloaded file differs from fixture".into(),
+                ));
+            }
+            Ok(format!("{} downloaded", url))
+        })
+        .await,
+    );
+
+    steps.push(
+        run_step("extraction", async {
+            let dest = dir.join("zip");
+            let entries = extract_archive(
+                window.clone(),
+                app.clone(),
+                archive.clone(),
+                dest.clone(),
+                1,
+            )
+            .await?;
+            check_extracted(&dest, entries)
+        })
+        .await,
+    );
+
+    steps.push(
+        run_step("streamed extraction", async {
+            let dest = dir.join("tar");
+            let url = format!("{}/{}", base_url, TAR_FIXTURE);
+            let entries =
+                download_and_extract(window.clone(), app.clone(), &url, dest.clone(), 1).await?;
+            check_extracted(&dest, entries)
+        })
+        .await,
+    );
+
+    steps.push(
+        run_step("command streaming", async {
+            let (shell, args) = if cfg!(windows) {
+                (
+                    "cmd",
+                    vec!["/C".to_string(), "echo one&& echo two".to_string()],
+                )
+            } else {
+                (
+                    "sh",
+                    vec!["-c".to_string(), "echo one; echo two >&2".to_string()],
+                )
+            };
+            let mut lines = vec![];
+            let success = run_external_command_lines(&app, &dir, shell, &args, |line| {
+                lines.push(line.trim().to_string());
+                false
+            })
+            .await?;
+            lines.sort();
+            if !success || lines != ["one", "two"] {
+                return Err(HelmError::Validation(format!(
+                    "Unexpected output of {}: {:?}",
+                    shell, lines
+                )));
+            }
+            Ok(format!("{} lines streamed from {}", lines.len(), shell))
+        })
+        .await,
+    );
+
+    let _ = std::fs::remove_dir_all(&dir);
+    Ok(SelftestReport {
+        passed: steps.iter().all(|step| step.passed),
+        steps,
+    })
+}
+
+// Hidden command for packagers, not used by the UI
+#[tauri::command]
+pub async fn selftest(window: Window, app: AppHandle) -> HelmResult<SelftestReport> {
+    run_selftest(window, app).await
+}
+
+pub fn selftest_requested() -> bool {
+    std::env::args().skip(1).any(|arg| arg == "--selftest")
+}
+
+// `esp-helm --selftest` prints the report as JSON and exits with 1 when a step failed.
+// Webview still needs a display, on Linux CI run it under xvfb-run.
+pub fn start_selftest(app: &tauri::App) {
+    let handle = app.handle();
+    let Some(window) = app.get_window("main") else {
+        eprintln!("Selftest needs the main window");
+        handle.exit(1);
+        return;
+    };
+    let _ = window.hide();
+    tauri::async_runtime::spawn(async move {
+        let passed = match run_selftest(window, handle.clone()).await {
+            Ok(report) => {
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&report).unwrap_or_default()
+                );
+                report.passed
+            }
+            Err(e) => {
+                eprintln!("Selftest could not start: {}", e);
+                false
+            }
+        };
+        handle.exit(if passed { 0 } else { 1 });
+    });
+}
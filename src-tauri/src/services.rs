@@ -0,0 +1,311 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use futures::future::BoxFuture;
+use futures::stream::BoxStream;
+use futures::{FutureExt, StreamExt};
+use tauri::{AppHandle, Manager};
+
+use crate::error::{HelmError, HelmResult};
+use crate::toolchain_env::toolchain_env;
+
+#[cfg(windows)]
+use std::os::windows::process::CommandExt;
+#[cfg(windows)]
+const CREATE_NO_WINDOW: u32 = 0x08000000; // Windows specific constant to hide console window
+
+pub struct ProcessOutput {
+    pub success: bool,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+// Runs short-lived tools whose output is parsed, e.g. version checks
+pub trait ProcessRunner: Send + Sync {
+    fn output(&self, program: &str, args: &[&str]) -> std::io::Result<ProcessOutput>;
+}
+
+pub struct HttpResponse {
+    pub status: u16,
+    pub content_length: Option<u64>,
+    // Names are lowercase
+    pub headers: Vec<(String, String)>,
+    pub body: BoxStream<'static, HelmResult<Vec<u8>>>,
+}
+
+impl HttpResponse {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn error_for_status(self) -> HelmResult<Self> {
+        if self.status >= 400 {
+            return Err(HelmError::Network(format!("HTTP status {}", self.status)));
+        }
+        Ok(self)
+    }
+
+    // Whole body in memory, for small files like installers and scripts
+    pub async fn bytes(mut self) -> HelmResult<Vec<u8>> {
+        let mut bytes = vec![];
+        while let Some(chunk) = self.body.next().await {
+            bytes.extend(chunk?);
+        }
+        Ok(bytes)
+    }
+}
+
+pub trait HttpClient: Send + Sync {
+    fn request(
+        &self,
+        method: &str,
+        url: &str,
+        headers: &[(&str, &str)],
+    ) -> BoxFuture<'static, HelmResult<HttpResponse>>;
+
+    fn get(&self, url: &str) -> BoxFuture<'static, HelmResult<HttpResponse>> {
+        self.request("GET", url, &[])
+    }
+}
+
+// Checks of installed files, e.g. components in toolchain sysroot
+pub trait Fs: Send + Sync {
+    fn exists(&self, path: &Path) -> bool;
+    // Entries of directory, empty when it can not be read
+    fn list_dir(&self, path: &Path) -> Vec<PathBuf>;
+}
+
+// Same environment as commands started by external_command
+pub struct SystemProcess;
+
+impl ProcessRunner for SystemProcess {
+    fn output(&self, program: &str, args: &[&str]) -> std::io::Result<ProcessOutput> {
+        let mut cmd = std::process::Command::new(program);
+        cmd.args(args).envs(toolchain_env());
+        #[cfg(windows)]
+        cmd.creation_flags(CREATE_NO_WINDOW);
+        let output = cmd.output()?;
+        Ok(ProcessOutput {
+            success: output.status.success(),
+            stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+        })
+    }
+}
+
+pub struct SystemHttp {
+    client: reqwest::Client,
+}
+
+impl HttpClient for SystemHttp {
+    fn request(
+        &self,
+        method: &str,
+        url: &str,
+        headers: &[(&str, &str)],
+    ) -> BoxFuture<'static, HelmResult<HttpResponse>> {
+        let method = reqwest::Method::from_bytes(method.as_bytes()).unwrap_or(reqwest::Method::GET);
+        let mut request = self.client.request(method, url);
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        async move {
+            let response = request.send().await?;
+            let headers = response
+                .headers()
+                .iter()
+                .filter_map(|(name, value)| {
+                    Some((name.as_str().to_string(), value.to_str().ok()?.to_string()))
+                })
+                .collect();
+            Ok(HttpResponse {
+                status: response.status().as_u16(),
+                content_length: response.content_length(),
+                headers,
+                body: response
+                    .bytes_stream()
+                    .map(|chunk| chunk.map(|bytes| bytes.to_vec()).map_err(HelmError::from))
+                    .boxed(),
+            })
+        }
+        .boxed()
+    }
+}
+
+pub struct SystemFs;
+
+impl Fs for SystemFs {
+    fn exists(&self, path: &Path) -> bool {
+        path.exists()
+    }
+
+    fn list_dir(&self, path: &Path) -> Vec<PathBuf> {
+        std::fs::read_dir(path)
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| Some(entry.ok()?.path()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+// Side effects of installation logic, managed by tauri so tests can swap them for mocks
+#[derive(Clone)]
+pub struct Services {
+    pub http: Arc<dyn HttpClient>,
+    pub process: Arc<dyn ProcessRunner>,
+    pub fs: Arc<dyn Fs>,
+}
+
+impl Services {
+    pub fn system() -> Self {
+        Self {
+            http: Arc::new(SystemHttp {
+                client: reqwest::Client::new(),
+            }),
+            process: Arc::new(SystemProcess),
+            fs: Arc::new(SystemFs),
+        }
+    }
+}
+
+pub fn services(app: &AppHandle) -> Services {
+    app.state::<Services>().inner().clone()
+}
+
+#[cfg(test)]
+pub mod mock {
+    use std::collections::{HashMap, HashSet};
+    use std::path::{Path, PathBuf};
+    use std::sync::Arc;
+
+    use futures::future::BoxFuture;
+    use futures::{FutureExt, StreamExt};
+
+    use super::{Fs, HttpClient, HttpResponse, ProcessOutput, ProcessRunner, Services};
+    use crate::error::{HelmError, HelmResult};
+
+    // Outputs keyed by program and arguments joined with spaces, unknown commands are missing
+    #[derive(Default)]
+    pub struct MockProcess {
+        pub outputs: HashMap<String, String>,
+    }
+
+    impl MockProcess {
+        pub fn with(mut self, command: &str, stdout: &str) -> Self {
+            self.outputs.insert(command.to_string(), stdout.to_string());
+            self
+        }
+    }
+
+    impl ProcessRunner for MockProcess {
+        fn output(&self, program: &str, args: &[&str]) -> std::io::Result<ProcessOutput> {
+            let key = std::iter::once(program)
+                .chain(args.iter().copied())
+                .collect::<Vec<_>>()
+                .join(" ");
+            match self.outputs.get(&key) {
+                Some(stdout) => Ok(ProcessOutput {
+                    success: true,
+                    stdout: stdout.clone(),
+                    stderr: String::new(),
+                }),
+                None => Err(std::io::ErrorKind::NotFound.into()),
+            }
+        }
+    }
+
+    #[derive(Clone)]
+    pub struct MockResponse {
+        pub status: u16,
+        pub headers: Vec<(String, String)>,
+        pub body: Vec<u8>,
+    }
+
+    // Responses keyed by method and URL, e.g. "HEAD https://..."
+    #[derive(Default)]
+    pub struct MockHttp {
+        pub responses: HashMap<String, MockResponse>,
+    }
+
+    impl MockHttp {
+        pub fn with(mut self, method: &str, url: &str, response: MockResponse) -> Self {
+            self.responses
+                .insert(format!("{} {}", method, url), response);
+            self
+        }
+    }
+
+    impl HttpClient for MockHttp {
+        fn request(
+            &self,
+            method: &str,
+            url: &str,
+            _headers: &[(&str, &str)],
+        ) -> BoxFuture<'static, HelmResult<HttpResponse>> {
+            let response = self.responses.get(&format!("{} {}", method, url)).cloned();
+            let url = url.to_string();
+            async move {
+                let response =
+                    response.ok_or(HelmError::Network(format!("No mock for {}", url)))?;
+                let content_length = response
+                    .headers
+                    .iter()
+                    .find(|(name, _)| name == "content-length")
+                    .and_then(|(_, value)| value.parse().ok());
+                Ok(HttpResponse {
+                    status: response.status,
+                    content_length,
+                    headers: response.headers,
+                    body: futures::stream::iter(vec![Ok(response.body)]).boxed(),
+                })
+            }
+            .boxed()
+        }
+    }
+
+    #[derive(Default)]
+    pub struct MockFs {
+        pub paths: HashSet<PathBuf>,
+    }
+
+    impl MockFs {
+        pub fn with_path(mut self, path: impl Into<PathBuf>) -> Self {
+            self.paths.insert(path.into());
+            self
+        }
+    }
+
+    impl Fs for MockFs {
+        fn exists(&self, path: &Path) -> bool {
+            self.paths.contains(path)
+        }
+
+        // Directories exist as ancestors of listed paths
+        fn list_dir(&self, path: &Path) -> Vec<PathBuf> {
+            let mut entries: Vec<PathBuf> = self
+                .paths
+                .iter()
+                .filter_map(|listed| {
+                    let name = listed.strip_prefix(path).ok()?.components().next()?;
+                    Some(path.join(name))
+                })
+                .collect();
+            entries.sort();
+            entries.dedup();
+            entries
+        }
+    }
+
+    pub fn services(http: MockHttp, process: MockProcess, fs: MockFs) -> Services {
+        Services {
+            http: Arc::new(http),
+            process: Arc::new(process),
+            fs: Arc::new(fs),
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use log::info;
+use tauri::State;
+
+use crate::adopt::ExistingInstallation;
+use crate::app_state::AppState;
+use crate::audit::{audit_path, AuditAction};
+use crate::error::{HelmError, HelmResult};
+use crate::flasher::ResetStrategy;
+use crate::monitor::LogChannel;
+use crate::notifications::NotificationSettings;
+use crate::portable::{app_config_dir, portable_tools_dir};
+use crate::system_install::InstallScope;
+
+// User preferences persisted between application runs
+#[derive(Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    // Maximum download speed in KiB/s, None means unlimited
+    pub download_limit_kib: Option<u64>,
+    // External command is killed when it produces no output for this many seconds
+    pub command_inactivity_timeout_secs: Option<u64>,
+    // External command is killed when it runs longer than this many seconds
+    pub command_total_timeout_secs: Option<u64>,
+    // Install x86_64 toolchain on Apple Silicon, it runs via Rosetta
+    pub force_x86_64: bool,
+    // Installations made outside of esp-helm which user registered
+    pub adopted_installations: Vec<ExistingInstallation>,
+    // Record anonymized install results locally, disabled until user opts in
+    pub telemetry_enabled: bool,
+    // ESP_LOG passed to external commands, e.g. "info" or "wifi=debug"
+    pub esp_log: Option<String>,
+    // Directory with rustup, cargo and espressif subdirectories instead of home directory
+    pub install_root: Option<String>,
+    // Baud rates per board, keyed by USB VID:PID:serial, found by detection or set by user
+    pub flash_baud: HashMap<String, u32>,
+    pub monitor_baud: HashMap<String, u32>,
+    pub reset_strategy: HashMap<String, ResetStrategy>,
+    // Log channels in fallback order per project directory, UART when project is not listed
+    pub log_channels: HashMap<String, Vec<LogChannel>>,
+    // Personal access token for GitHub API, raises rate limit from 60 to 5000 requests per hour
+    pub github_token: Option<String>,
+    // Downloads running at once, others wait in queue
+    pub max_concurrent_downloads: Option<usize>,
+    // System scope keeps toolchains in shared root with exports in system profile
+    pub install_scope: InstallScope,
+    // Language of backend messages, English when not set or not supported
+    pub language: Option<String>,
+    pub notifications: NotificationSettings,
+    // Hours between background update checks, 24 when not set, 0 disables them
+    pub update_check_interval_hours: Option<u64>,
+    // Dated nightly used for RISC-V targets instead of moving "nightly", e.g. "nightly-2024-05-01"
+    pub nightly_pin: Option<String>,
+}
+
+// Shown instead of secrets when settings leave the backend
+pub const REDACTED: &str = "<redacted>";
+
+impl Settings {
+    // Copy for the frontend and diagnostics, GitHub token is only reported as set
+    pub fn redacted(&self) -> Settings {
+        let mut settings = self.clone();
+        settings.github_token = settings.github_token.map(|_| REDACTED.into());
+        settings
+    }
+}
+
+fn settings_path() -> Option<PathBuf> {
+    app_config_dir().map(|dir| dir.join("settings.json"))
+}
+
+pub fn load_settings() -> Settings {
+    let Some(path) = settings_path() else {
+        return Settings::default();
+    };
+
+    let mut settings = match std::fs::read_to_string(&path) {
+        Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+            info!("Unable to parse {}: {}", path.display(), e);
+            Settings::default()
+        }),
+        Err(_) => Settings::default(),
+    };
+    // Portable installation might have been moved since the settings were saved
+    if let Some(tools) = portable_tools_dir() {
+        settings.install_root = Some(tools.to_string_lossy().to_string());
+    }
+    settings
+}
+
+pub fn save_settings(settings: &Settings) -> HelmResult<()> {
+    let path = settings_path().ok_or(HelmError::NotFound("config directory".into()))?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let content = serde_json::to_string_pretty(settings)
+        .map_err(|e| HelmError::Other(format!("Failed to serialize settings: {}", e)))?;
+    std::fs::write(&path, content)?;
+    // Holds GitHub token, so only the user may read it
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))?;
+    }
+    audit_path(AuditAction::WriteFile, &path);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_settings(state_mutex: State<'_, Mutex<AppState>>) -> HelmResult<Settings> {
+    let state = state_mutex.lock().unwrap();
+    Ok(state.settings.redacted())
+}
+
+// GitHub token is kept, it is changed only by set_github_token
+#[tauri::command]
+pub async fn update_settings(
+    state_mutex: State<'_, Mutex<AppState>>,
+    mut settings: Settings,
+) -> HelmResult<String> {
+    let mut state = state_mutex.lock().unwrap();
+    settings.github_token = state.settings.github_token.clone();
+    save_settings(&settings)?;
+    state.settings = settings;
+    Ok("Settings saved".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redacted_settings_hide_github_token() {
+        let settings = Settings {
+            github_token: Some("ghp_secret".into()),
+            ..Default::default()
+        };
+        let json = serde_json::to_string(&settings.redacted()).unwrap();
+        assert!(!json.contains("ghp_secret"));
+        assert_eq!(settings.redacted().github_token.as_deref(), Some(REDACTED));
+        assert_eq!(Settings::default().redacted().github_token, None);
+    }
+}
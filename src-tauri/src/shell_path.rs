@@ -0,0 +1,70 @@
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use log::info;
+
+// Profile scripts may print banners, PATH is taken from between the markers
+const PATH_MARKER: &str = "__ESP_HELM_PATH__";
+// Slow or interactive shell configuration must not block startup
+const SHELL_TIMEOUT: Duration = Duration::from_secs(5);
+
+// PATH as seen in terminal, login and interactive shell reads both profile and rc files
+fn login_shell_path() -> Option<String> {
+    let shell = std::env::var("SHELL").unwrap_or_else(|_| "/bin/zsh".into());
+    let mut child = Command::new(&shell)
+        .args([
+            "-l",
+            "-i",
+            "-c",
+            &format!("printf '{0}%s{0}' \"$PATH\"", PATH_MARKER),
+        ])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+
+    let started = Instant::now();
+    loop {
+        match child.try_wait() {
+            Ok(Some(_)) => break,
+            Ok(None) if started.elapsed() < SHELL_TIMEOUT => {
+                std::thread::sleep(Duration::from_millis(50))
+            }
+            _ => {
+                info!("{} did not report PATH in time", shell);
+                let _ = child.kill();
+                return None;
+            }
+        }
+    }
+
+    let output = child.wait_with_output().ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let path = stdout.split(PATH_MARKER).nth(1)?;
+    (!path.is_empty()).then(|| path.to_string())
+}
+
+// Applications started from Finder get PATH of launchd without ~/.cargo/bin or Homebrew,
+// so PATH of login shell is merged in once at startup. Detection and child processes
+// use PATH of this process.
+pub fn apply_login_shell_path() {
+    if !cfg!(target_os = "macos") {
+        return;
+    }
+    let Some(shell_path) = login_shell_path() else {
+        return;
+    };
+    let current = std::env::var_os("PATH").unwrap_or_default();
+    let mut paths: Vec<PathBuf> = std::env::split_paths(&shell_path).collect();
+    for path in std::env::split_paths(&current) {
+        if !paths.contains(&path) {
+            paths.push(path);
+        }
+    }
+    if let Ok(path) = std::env::join_paths(paths) {
+        info!("Using PATH of login shell: {}", path.to_string_lossy());
+        std::env::set_var("PATH", path);
+    }
+}
@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use addr2line::object::{Object, ObjectSection, ObjectSymbol, SectionKind};
+use tauri::{AppHandle, Window};
+
+use crate::cargo_tools::cargo_bin;
+use crate::deploy::elf_path;
+use crate::error::{HelmError, HelmResult};
+use crate::external_command::run_external_command_in_dir;
+
+// Crates below this are summed up as "other"
+const MAX_CRATES: usize = 30;
+
+#[derive(serde::Serialize)]
+pub struct SectionSize {
+    name: String,
+    address: u64,
+    size: u64,
+    // Section occupies flash (code and initial values) and/or RAM at runtime
+    flash: bool,
+    ram: bool,
+}
+
+#[derive(Default, serde::Serialize)]
+pub struct CrateSize {
+    name: String,
+    flash: u64,
+    ram: u64,
+}
+
+#[derive(serde::Serialize)]
+pub struct SizeReport {
+    elf: String,
+    flash_total: u64,
+    ram_total: u64,
+    sections: Vec<SectionSize>,
+    crates: Vec<CrateSize>,
+}
+
+// Linker scripts of esp-hal and ESP-IDF name RAM sections after the memory they are placed in
+fn is_ram_section(name: &str) -> bool {
+    [
+        "data", "bss", "iram", "dram", "rwtext", "noinit", "rtc", "stack", "heap",
+    ]
+    .iter()
+    .any(|part| name.contains(part))
+}
+
+// Crate of demangled symbol, "<T as crate::Trait>::f" belongs to crate of T
+fn symbol_crate(symbol: &str) -> String {
+    let demangled = format!("{:#}", rustc_demangle::demangle(symbol));
+    let path = demangled.trim_start_matches('<').trim_start_matches('&');
+    let path = path.trim_start_matches("mut ").trim_start_matches("dyn ");
+    match path.split_once("::") {
+        Some((name, _)) if !name.contains(' ') => name.to_string(),
+        // C functions from ROM or ESP-IDF
+        _ => "[C]".to_string(),
+    }
+}
+
+fn analyze_elf(elf: &str) -> HelmResult<SizeReport> {
+    let data = std::fs::read(elf)?;
+    let file = addr2line::object::File::parse(data.as_slice())
+        .map_err(|e| HelmError::Validation(format!("Invalid ELF {}: {}", elf, e)))?;
+
+    let mut sections = vec![];
+    let mut section_regions = HashMap::new();
+    for section in file.sections() {
+        // Only sections with address are loaded, debug info is not
+        if section.address() == 0 || section.size() == 0 {
+            continue;
+        }
+        let name = section.name().unwrap_or_default().to_string();
+        let ram = is_ram_section(&name);
+        // Zero initialized data has no content in flash image
+        let flash = section.kind() != SectionKind::UninitializedData;
+        section_regions.insert(section.index(), (flash, ram));
+        sections.push(SectionSize {
+            name,
+            address: section.address(),
+            size: section.size(),
+            flash,
+            ram,
+        });
+    }
+
+    let mut crates: HashMap<String, CrateSize> = HashMap::new();
+    for symbol in file.symbols() {
+        let (Some(index), Ok(name)) = (symbol.section_index(), symbol.name()) else {
+            continue;
+        };
+        let Some((flash, ram)) = section_regions.get(&index) else {
+            continue;
+        };
+        let crate_name = symbol_crate(name);
+        let entry = crates
+            .entry(crate_name.clone())
+            .or_insert_with(|| CrateSize {
+                name: crate_name,
+                ..Default::default()
+            });
+        if *flash {
+            entry.flash += symbol.size();
+        }
+        if *ram {
+            entry.ram += symbol.size();
+        }
+    }
+
+    let mut crates: Vec<CrateSize> = crates.into_values().collect();
+    crates.sort_by(|a, b| (b.flash + b.ram).cmp(&(a.flash + a.ram)));
+    if crates.len() > MAX_CRATES {
+        let other = crates.split_off(MAX_CRATES).into_iter().fold(
+            CrateSize {
+                name: "other".into(),
+                ..Default::default()
+            },
+            |mut other, size| {
+                other.flash += size.flash;
+                other.ram += size.ram;
+                other
+            },
+        );
+        crates.push(other);
+    }
+
+    Ok(SizeReport {
+        elf: elf.to_string(),
+        flash_total: sections.iter().filter(|s| s.flash).map(|s| s.size).sum(),
+        ram_total: sections.iter().filter(|s| s.ram).map(|s| s.size).sum(),
+        sections,
+        crates,
+    })
+}
+
+// Command to build project in release mode and report flash and RAM usage per section and crate
+#[tauri::command]
+pub async fn analyze_binary_size(
+    window: Window,
+    app: AppHandle,
+    project: String,
+) -> HelmResult<SizeReport> {
+    let project = PathBuf::from(project);
+    let cargo = cargo_bin("cargo")?.to_string_lossy().to_string();
+    run_external_command_in_dir(
+        window,
+        app,
+        Some(&project),
+        &cargo,
+        &["build", "--release"],
+        "PROGRESS_EVENT",
+    )
+    .await?;
+
+    let elf = elf_path(&project, true)?;
+    analyze_elf(&elf.to_string_lossy())
+}
@@ -0,0 +1,232 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Mutex;
+
+use log::info;
+use tauri::{AppHandle, Manager, State};
+
+use crate::app_state::AppState;
+use crate::audit::{audit, AuditAction};
+use crate::error::{HelmError, HelmResult};
+use crate::install_dir::{set_install_root, InstallPaths};
+use crate::portable::portable_root;
+use crate::settings::save_settings;
+
+#[cfg(windows)]
+use std::os::windows::process::CommandExt;
+#[cfg(windows)]
+const CREATE_NO_WINDOW: u32 = 0x08000000; // Windows specific constant to hide console window
+
+// Exports read by login shells of all users
+#[cfg(unix)]
+const SYSTEM_PROFILE: &str = "/etc/profile.d/esp-helm.sh";
+
+#[derive(Clone, Copy, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InstallScope {
+    // Toolchains in home directory or custom install root, exports in user's dotfiles
+    #[default]
+    User,
+    // Shared toolchains for all users of the machine, e.g. lab computers
+    System,
+}
+
+// ProgramData on Windows, /opt elsewhere
+pub fn system_root() -> PathBuf {
+    #[cfg(windows)]
+    let base = std::env::var_os("ProgramData")
+        .map(PathBuf::from)
+        .unwrap_or(PathBuf::from("C:\\ProgramData"));
+    #[cfg(unix)]
+    let base = PathBuf::from("/opt");
+    base.join("esp-helm")
+}
+
+pub fn is_system_scope(app: &AppHandle) -> bool {
+    let state_mutex = app.state::<Mutex<AppState>>();
+    let state = state_mutex.lock().unwrap();
+    state.settings.install_scope == InstallScope::System
+}
+
+// Runs single command with administrator rights, user is asked by system dialog
+#[cfg(unix)]
+pub fn run_elevated(command: &str, args: &[&str]) -> HelmResult<()> {
+    info!("Elevated: {} {}", command, args.join(" "));
+    audit(
+        AuditAction::RunElevated,
+        &format!("{} {}", command, args.join(" ")),
+        None,
+    );
+    let status = Command::new("pkexec")
+        .arg(command)
+        .args(args)
+        .status()
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => HelmError::NotFound("pkexec".into()),
+            _ => e.into(),
+        })?;
+    match status.code() {
+        Some(0) => Ok(()),
+        // pkexec returns 126 when authorization dialog was dismissed
+        Some(126) | Some(127) => Err(HelmError::Permission(
+            "Administrator rights were not granted".into(),
+        )),
+        code => Err(HelmError::child_process_failed(code, "")),
+    }
+}
+
+#[cfg(windows)]
+pub fn run_elevated(command: &str, args: &[&str]) -> HelmResult<()> {
+    info!("Elevated: {} {}", command, args.join(" "));
+    audit(
+        AuditAction::RunElevated,
+        &format!("{} {}", command, args.join(" ")),
+        None,
+    );
+    let quote = |value: &str| format!("'{}'", value.replace('\'', "''"));
+    let argument_list: Vec<String> = args.iter().map(|arg| quote(arg)).collect();
+    let script = format!(
+        "$p = Start-Process {} -ArgumentList {} -Verb RunAs -Wait -PassThru \
+         -WindowStyle Hidden; exit $p.ExitCode",
+        quote(command),
+        argument_list.join(",")
+    );
+    let status = Command::new("powershell")
+        .args(["-NoProfile", "-Command", &script])
+        .creation_flags(CREATE_NO_WINDOW)
+        .status()?;
+    match status.code() {
+        Some(0) => Ok(()),
+        code => Err(HelmError::child_process_failed(code, "")),
+    }
+}
+
+fn current_user() -> HelmResult<String> {
+    #[cfg(unix)]
+    let variable = "USER";
+    #[cfg(windows)]
+    let variable = "USERNAME";
+    std::env::var(variable).map_err(|_| HelmError::NotFound("current user".into()))
+}
+
+// Root is owned by installing user so installers run without elevation,
+// other users can only read and execute the toolchains
+#[cfg(unix)]
+fn prepare_root(root: &Path) -> HelmResult<()> {
+    let root = root.to_string_lossy();
+    let script = format!(
+        "mkdir -p '{root}' && chown -R '{user}' '{root}' && chmod 755 '{root}'",
+        root = root,
+        user = current_user()?
+    );
+    run_elevated("sh", &["-c", &script])
+}
+
+#[cfg(windows)]
+fn prepare_root(root: &Path) -> HelmResult<()> {
+    let root = root.to_string_lossy();
+    let script = format!(
+        "New-Item -ItemType Directory -Force -Path '{root}' | Out-Null; \
+         icacls '{root}' /grant '*S-1-5-32-545:(OI)(CI)RX' '{user}:(OI)(CI)F' /T /Q",
+        root = root,
+        user = current_user()?
+    );
+    run_elevated("powershell", &["-NoProfile", "-Command", &script])?;
+    audit(
+        AuditAction::ModifyEnvironment,
+        "Machine environment",
+        Some("Shared toolchain exports".into()),
+    );
+    Ok(())
+}
+
+fn system_exports(root: &Path) -> Vec<(&'static str, String)> {
+    let path = |dir: &str| root.join(dir).to_string_lossy().to_string();
+    vec![
+        ("RUSTUP_HOME", path("rustup")),
+        ("CARGO_HOME", path("cargo")),
+        ("IDF_TOOLS_PATH", path("espressif")),
+    ]
+}
+
+#[cfg(unix)]
+fn write_system_exports(root: &Path) -> HelmResult<()> {
+    let mut content = String::from("# Added by esp-helm, shared toolchains\n");
+    for (name, value) in system_exports(root) {
+        content.push_str(&format!("export {}=\"{}\"\n", name, value));
+    }
+    content.push_str(&format!(
+        "export PATH=\"{}:$PATH\"\n",
+        root.join("cargo").join("bin").display()
+    ));
+    // Written as user first, install sets owner and mode of the system file
+    let temp = std::env::temp_dir().join("esp-helm-profile.sh");
+    std::fs::write(&temp, content)?;
+    let result = run_elevated(
+        "install",
+        &["-m", "644", &temp.to_string_lossy(), SYSTEM_PROFILE],
+    );
+    let _ = std::fs::remove_file(&temp);
+    if result.is_ok() {
+        audit(
+            AuditAction::ModifyEnvironment,
+            SYSTEM_PROFILE,
+            Some("Shared toolchain exports".into()),
+        );
+    }
+    result
+}
+
+#[cfg(windows)]
+fn write_system_exports(root: &Path) -> HelmResult<()> {
+    let mut script = String::new();
+    for (name, value) in system_exports(root) {
+        script.push_str(&format!(
+            "[Environment]::SetEnvironmentVariable('{}', '{}', 'Machine'); ",
+            name, value
+        ));
+    }
+    let bin = root.join("cargo").join("bin");
+    script.push_str(&format!(
+        "$bin = '{}'; $path = [Environment]::GetEnvironmentVariable('Path', 'Machine'); \
+         if (($path -split ';') -notcontains $bin) {{ \
+         [Environment]::SetEnvironmentVariable('Path', \"$path;$bin\", 'Machine') }}",
+        bin.display()
+    ));
+    run_elevated("powershell", &["-NoProfile", "-Command", &script])
+}
+
+// Command to switch between per-user and system-wide installation. System scope asks
+// for administrator rights once, switching back keeps shared toolchains for other users.
+#[tauri::command]
+pub async fn set_install_scope(
+    state_mutex: State<'_, Mutex<AppState>>,
+    scope: InstallScope,
+) -> HelmResult<InstallPaths> {
+    if portable_root().is_some() {
+        return Err(HelmError::Validation(
+            "Install scope cannot be changed in portable mode".into(),
+        ));
+    }
+    let paths = match scope {
+        InstallScope::System => {
+            let root = system_root();
+            info!("Preparing system-wide install root {}", root.display());
+            prepare_root(&root)?;
+            write_system_exports(&root)?;
+            set_install_root(
+                state_mutex.clone(),
+                Some(root.to_string_lossy().to_string()),
+            )
+            .await?
+        }
+        InstallScope::User => set_install_root(state_mutex.clone(), None).await?,
+    };
+
+    let mut state = state_mutex.lock().unwrap();
+    let mut settings = state.settings.clone();
+    settings.install_scope = scope;
+    save_settings(&settings)?;
+    state.settings = settings;
+    Ok(paths)
+}
@@ -0,0 +1,405 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use tauri::{AppHandle, Window};
+
+use crate::cargo_tools::cargo_bin;
+use crate::error::{HelmError, HelmResult};
+use crate::events::{emit_event, HelmEvent};
+use crate::external_command::run_external_command_lines;
+use crate::package_manager::find_in_path;
+use crate::verify::chip_target;
+
+// QEMU does not exit when tests finish, it is stopped after the summary or this timeout
+const QEMU_TIMEOUT: Duration = Duration::from_secs(120);
+
+#[derive(Clone, Copy, PartialEq, serde::Serialize, schemars::JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TestStatus {
+    Passed,
+    Failed,
+    Ignored,
+}
+
+#[derive(Clone, serde::Serialize, schemars::JsonSchema)]
+pub struct TestResult {
+    name: String,
+    status: TestStatus,
+}
+
+impl HelmEvent for TestResult {
+    const NAMES: &'static [&'static str] = &["test-result"];
+}
+
+// Output of build, image conversion and the tests themselves
+#[derive(Clone, serde::Serialize, schemars::JsonSchema)]
+pub struct TestOutput {
+    line: String,
+}
+
+impl HelmEvent for TestOutput {
+    const NAMES: &'static [&'static str] = &["test-output"];
+}
+
+fn emit_output(window: &Window, line: &str) {
+    let output = TestOutput {
+        line: line.to_string(),
+    };
+    emit_event(window, &output);
+}
+
+// Test names are split by "::" into modules, leaves have status
+#[derive(serde::Serialize)]
+pub struct TestNode {
+    name: String,
+    status: Option<TestStatus>,
+    output: Option<String>,
+    children: Vec<TestNode>,
+}
+
+#[derive(serde::Serialize)]
+pub struct TestReport {
+    passed: usize,
+    failed: usize,
+    ignored: usize,
+    tests: Vec<TestNode>,
+}
+
+// Parser of libtest output, embedded-test and custom harnesses use the same format
+#[derive(Default)]
+struct TestParser {
+    results: Vec<(String, TestStatus)>,
+    outputs: HashMap<String, String>,
+    // Test whose "---- name stdout ----" section is being read
+    failure: Option<String>,
+    finished: bool,
+}
+
+impl TestParser {
+    fn line(&mut self, line: &str) -> Option<TestResult> {
+        let line = line.trim_end();
+        if let Some(name) = line
+            .strip_prefix("---- ")
+            .and_then(|rest| rest.strip_suffix(" stdout ----"))
+        {
+            self.failure = Some(name.to_string());
+            return None;
+        }
+        if line == "failures:" || line.starts_with("test result:") {
+            self.failure = None;
+            self.finished |= line.starts_with("test result:");
+            return None;
+        }
+        if let Some(name) = &self.failure {
+            let output = self.outputs.entry(name.clone()).or_default();
+            output.push_str(line);
+            output.push('\n');
+            return None;
+        }
+
+        let (name, status) = line.strip_prefix("test ")?.rsplit_once(" ... ")?;
+        let status = match status {
+            "ok" => TestStatus::Passed,
+            "FAILED" => TestStatus::Failed,
+            status if status.starts_with("ignored") => TestStatus::Ignored,
+            _ => return None,
+        };
+        self.results.push((name.to_string(), status));
+        Some(TestResult {
+            name: name.to_string(),
+            status,
+        })
+    }
+
+    fn report(mut self) -> TestReport {
+        let count = |status| self.results.iter().filter(|(_, s)| *s == status).count();
+        let (passed, failed, ignored) = (
+            count(TestStatus::Passed),
+            count(TestStatus::Failed),
+            count(TestStatus::Ignored),
+        );
+
+        let mut tests: Vec<TestNode> = vec![];
+        for (name, status) in &self.results {
+            let path: Vec<&str> = name.split("::").collect();
+            let mut nodes = &mut tests;
+            for (depth, part) in path.iter().enumerate() {
+                let index = match nodes.iter().position(|node| node.name == *part) {
+                    Some(index) => index,
+                    None => {
+                        nodes.push(TestNode {
+                            name: part.to_string(),
+                            status: None,
+                            output: None,
+                            children: vec![],
+                        });
+                        nodes.len() - 1
+                    }
+                };
+                if depth == path.len() - 1 {
+                    nodes[index].status = Some(*status);
+                    nodes[index].output = self.outputs.remove(name);
+                }
+                nodes = &mut nodes[index].children;
+            }
+        }
+
+        TestReport {
+            passed,
+            failed,
+            ignored,
+            tests,
+        }
+    }
+}
+
+// cargo test with results parsed from output, runner from .cargo/config.toml can be overridden
+async fn cargo_test(
+    window: &Window,
+    app: &AppHandle,
+    project: &Path,
+    args: Vec<String>,
+) -> HelmResult<TestReport> {
+    let cargo = cargo_bin("cargo")?.to_string_lossy().to_string();
+    let mut parser = TestParser::default();
+    let success = run_external_command_lines(app, project, &cargo, &args, |line| {
+        emit_output(window, line);
+        if let Some(result) = parser.line(line) {
+            emit_event(window, &result);
+        }
+        false
+    })
+    .await?;
+    // Failing tests make cargo fail too, without any result the build itself failed
+    if !success && parser.results.is_empty() {
+        return Err(HelmError::Other("Tests could not be built".into()));
+    }
+    Ok(parser.report())
+}
+
+fn target_args(toolchain: &str, target: &str) -> Vec<String> {
+    let mut args = vec![
+        format!("+{}", toolchain),
+        "test".into(),
+        "--target".into(),
+        target.into(),
+    ];
+    if target.starts_with("xtensa") {
+        args.push("-Zbuild-std=core".into());
+    }
+    args
+}
+
+// Test binaries built by cargo test --no-run, read from JSON messages
+async fn build_test_binaries(
+    window: &Window,
+    app: &AppHandle,
+    project: &Path,
+    toolchain: &str,
+    target: &str,
+) -> HelmResult<Vec<PathBuf>> {
+    let cargo = cargo_bin("cargo")?.to_string_lossy().to_string();
+    let mut args = target_args(toolchain, target);
+    args.extend(["--no-run".into(), "--message-format=json".into()]);
+
+    let mut binaries = vec![];
+    let success = run_external_command_lines(app, project, &cargo, &args, |line| {
+        if let Ok(message) = serde_json::from_str::<serde_json::Value>(line) {
+            let is_test = message["profile"]["test"].as_bool().unwrap_or(false);
+            if let (true, Some(executable)) = (is_test, message["executable"].as_str()) {
+                binaries.push(PathBuf::from(executable));
+            }
+        } else {
+            // Compiler progress is printed to stderr as plain text
+            emit_output(window, line);
+        }
+        false
+    })
+    .await?;
+    if !success {
+        return Err(HelmError::Other("Test build failed".into()));
+    }
+    Ok(binaries)
+}
+
+// QEMU binary and machine, Espressif fork supports only these chips
+fn qemu_system(chip: &str) -> Option<(&'static str, &'static str)> {
+    match chip {
+        "esp32" => Some(("qemu-system-xtensa", "esp32")),
+        "esp32s3" => Some(("qemu-system-xtensa", "esp32s3")),
+        "esp32c3" => Some(("qemu-system-riscv32", "esp32c3")),
+        _ => None,
+    }
+}
+
+// Each test binary is merged to flash image with bootloader and booted in Espressif QEMU
+async fn qemu_test(
+    window: &Window,
+    app: &AppHandle,
+    project: &Path,
+    chip: &str,
+    toolchain: &str,
+    target: &str,
+) -> HelmResult<TestReport> {
+    let (qemu, machine) = qemu_system(chip).ok_or(HelmError::Validation(format!(
+        "QEMU does not support {}",
+        chip
+    )))?;
+    let qemu = find_in_path(qemu)
+        .ok_or(HelmError::NotFound(qemu.into()))?
+        .to_string_lossy()
+        .to_string();
+    let espflash = cargo_bin("espflash")?.to_string_lossy().to_string();
+
+    let mut parser = TestParser::default();
+    for binary in build_test_binaries(window, app, project, toolchain, target).await? {
+        let image = binary.with_extension("qemu.bin");
+        let args: Vec<String> = vec![
+            "save-image".into(),
+            "--chip".into(),
+            chip.into(),
+            "--merge".into(),
+            binary.to_string_lossy().to_string(),
+            image.to_string_lossy().to_string(),
+        ];
+        run_external_command_lines(app, project, &espflash, &args, |line| {
+            emit_output(window, line);
+            false
+        })
+        .await?;
+
+        let args: Vec<String> = vec![
+            "-nographic".into(),
+            "-machine".into(),
+            machine.into(),
+            "-drive".into(),
+            format!("file={},if=mtd,format=raw", image.display()),
+        ];
+        parser.finished = false;
+        let run = run_external_command_lines(app, project, &qemu, &args, |line| {
+            emit_output(window, line);
+            if let Some(result) = parser.line(line) {
+                emit_event(window, &result);
+            }
+            parser.finished
+        });
+        if tokio::time::timeout(QEMU_TIMEOUT, run).await.is_err() {
+            return Err(HelmError::Timeout(format!(
+                "{} did not finish in QEMU",
+                binary.display()
+            )));
+        }
+    }
+    Ok(parser.report())
+}
+
+// Command to run tests. Target "host" runs cargo test on this machine, chip name runs
+// embedded-test on hardware through probe-rs or in QEMU when port_or_qemu is "qemu".
+#[tauri::command]
+pub async fn run_tests(
+    window: Window,
+    app: AppHandle,
+    project: String,
+    target: String,
+    port_or_qemu: Option<String>,
+) -> HelmResult<TestReport> {
+    let project = PathBuf::from(project);
+    if target == "host" {
+        return cargo_test(&window, &app, &project, vec!["test".into()]).await;
+    }
+
+    let (toolchain, triple, _) =
+        chip_target(&target).ok_or(HelmError::Validation(format!("Unknown chip {}", target)))?;
+    match port_or_qemu.as_deref() {
+        Some("qemu") => qemu_test(&window, &app, &project, &target, toolchain, triple).await,
+        probe => {
+            let mut runner = format!("probe-rs run --chip {}", target);
+            if let Some(probe) = probe {
+                runner.push_str(&format!(" --probe {}", probe));
+            }
+            let mut args = target_args(toolchain, triple);
+            args.push("--config".into());
+            args.push(format!("target.{}.runner=\"{}\"", triple, runner));
+            cargo_test(&window, &app, &project, args).await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OUTPUT: &str = "\
+running 4 tests
+test gpio::toggles_pin ... ok
+test gpio::reads_input ... FAILED
+test timer::delay ... ignored, needs hardware
+test smoke ... ok
+
+failures:
+
+---- gpio::reads_input stdout ----
+thread 'gpio::reads_input' panicked at src/gpio.rs:10:5:
+assertion failed: pin.is_high()
+
+failures:
+    gpio::reads_input
+
+test result: FAILED. 2 passed; 1 failed; 1 ignored; 0 measured; 0 filtered out
+";
+
+    fn parse(output: &str) -> (TestParser, Vec<(String, TestStatus)>) {
+        let mut parser = TestParser::default();
+        let results = output
+            .lines()
+            .filter_map(|line| parser.line(line))
+            .map(|result| (result.name, result.status))
+            .collect();
+        (parser, results)
+    }
+
+    #[test]
+    fn parses_result_lines() {
+        let (parser, results) = parse(OUTPUT);
+        let statuses: Vec<(&str, TestStatus)> = results
+            .iter()
+            .map(|(name, status)| (name.as_str(), *status))
+            .collect();
+        assert!(
+            statuses
+                == [
+                    ("gpio::toggles_pin", TestStatus::Passed),
+                    ("gpio::reads_input", TestStatus::Failed),
+                    ("timer::delay", TestStatus::Ignored),
+                    ("smoke", TestStatus::Passed),
+                ]
+        );
+        assert!(parser.finished);
+        // Names listed under second "failures:" are not results
+        assert_eq!(parser.results.len(), 4);
+    }
+
+    #[test]
+    fn groups_tests_by_module() {
+        let (parser, _) = parse(OUTPUT);
+        let report = parser.report();
+        assert_eq!((report.passed, report.failed, report.ignored), (2, 1, 1));
+        let names: Vec<&str> = report.tests.iter().map(|node| node.name.as_str()).collect();
+        assert_eq!(names, vec!["gpio", "timer", "smoke"]);
+
+        let gpio = &report.tests[0];
+        assert!(gpio.status.is_none());
+        let failed = &gpio.children[1];
+        assert_eq!(failed.name, "reads_input");
+        assert!(failed.status == Some(TestStatus::Failed));
+        assert_eq!(
+            failed.output.as_deref(),
+            Some(
+                "thread 'gpio::reads_input' panicked at src/gpio.rs:10:5:\n\
+                 assertion failed: pin.is_high()\n\n"
+            )
+        );
+        assert!(gpio.children[0].output.is_none());
+    }
+}
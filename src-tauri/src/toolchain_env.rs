@@ -0,0 +1,106 @@
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+use crate::esp_clang::export_file_path;
+use crate::inventory::{cargo_home, espressif_home};
+
+// Variables of espup export file, e.g. PATH with GCC directories and LIBCLANG_PATH
+fn exported_variables() -> Vec<(String, String)> {
+    let Some(content) = export_file_path().and_then(|path| std::fs::read_to_string(path).ok())
+    else {
+        return vec![];
+    };
+    content
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            // export NAME="value" or $Env:NAME = "value", esp-helm marks its lines by comment
+            let line = line.split(" # ").next().unwrap_or(line);
+            let (name, value) = match line.strip_prefix("export ") {
+                Some(rest) => rest.split_once('=')?,
+                None => line.strip_prefix("$Env:")?.split_once(" = ")?,
+            };
+            Some((
+                name.trim().to_string(),
+                value.trim().trim_matches('"').to_string(),
+            ))
+        })
+        .collect()
+}
+
+// Replace reference to previous value of the variable, "$PATH" or "$Env:PATH"
+fn expand(name: &str, value: &str, previous: &str) -> String {
+    [
+        format!("$Env:{}", name),
+        format!("${{{}}}", name),
+        format!("${}", name),
+    ]
+    .iter()
+    .fold(value.to_string(), |value, pattern| {
+        value.replace(pattern, previous)
+    })
+}
+
+// Newest ESP-IDF downloaded to espressif directory, IDF_PATH of the user wins
+fn idf_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("IDF_PATH") {
+        return Some(PathBuf::from(path));
+    }
+    let mut versions: Vec<PathBuf> = std::fs::read_dir(espressif_home()?.join("esp-idf"))
+        .ok()?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.join("tools").join("idf.py").exists())
+        .collect();
+    versions.sort();
+    versions.pop()
+}
+
+fn prepend_path(path: &OsString, dir: PathBuf) -> OsString {
+    let mut paths: Vec<PathBuf> = std::env::split_paths(path).collect();
+    if paths.contains(&dir) {
+        return path.clone();
+    }
+    paths.insert(0, dir);
+    std::env::join_paths(paths).unwrap_or_else(|_| path.clone())
+}
+
+// Environment of sourced export-esp.sh, applied to every command spawned by esp-helm, so
+// builds do not depend on the shell esp-helm was started from. Install root variables are
+// already set for this process by apply_install_root.
+pub fn toolchain_env() -> BTreeMap<String, String> {
+    let mut env = BTreeMap::new();
+    let mut path = std::env::var_os("PATH").unwrap_or_default();
+    if let Some(cargo_home) = cargo_home() {
+        path = prepend_path(&path, cargo_home.join("bin"));
+    }
+    env.insert("PATH".to_string(), path.to_string_lossy().to_string());
+
+    for (name, value) in exported_variables() {
+        let previous = env
+            .get(&name)
+            .cloned()
+            .or_else(|| std::env::var(&name).ok())
+            .unwrap_or_default();
+        let value = expand(&name, &value, &previous);
+        env.insert(name, value);
+    }
+
+    if let Some(idf_path) = idf_path() {
+        let tools = idf_path.join("tools");
+        let path = OsString::from(&env["PATH"]);
+        env.insert(
+            "PATH".into(),
+            prepend_path(&path, tools).to_string_lossy().to_string(),
+        );
+        env.insert("IDF_PATH".into(), idf_path.to_string_lossy().to_string());
+    }
+    if let Some(espressif_home) = espressif_home() {
+        env.insert(
+            "IDF_TOOLS_PATH".into(),
+            espressif_home.to_string_lossy().to_string(),
+        );
+    }
+    env
+}
@@ -0,0 +1,147 @@
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use log::info;
+use tauri::{
+    AppHandle, CustomMenuItem, GlobalWindowEvent, Manager, SystemTray, SystemTrayEvent,
+    SystemTrayMenu, SystemTrayMenuItem, WindowEvent,
+};
+
+use crate::app_state::{AppState, JobStatus};
+use crate::deploy::flash_last_project;
+use crate::jobs::spawn_job;
+use crate::monitor::monitor_session;
+use crate::operations::OperationKind;
+
+const FLASH_ITEM: &str = "flash-last";
+const MONITOR_ITEM: &str = "open-monitor";
+const UPDATES_ITEM: &str = "check-updates";
+const SHOW_ITEM: &str = "show";
+const QUIT_ITEM: &str = "quit";
+
+pub fn system_tray() -> SystemTray {
+    let menu = SystemTrayMenu::new()
+        .add_item(CustomMenuItem::new(FLASH_ITEM, "Flash last project"))
+        .add_item(CustomMenuItem::new(MONITOR_ITEM, "Open monitor"))
+        .add_item(CustomMenuItem::new(UPDATES_ITEM, "Check for updates"))
+        .add_native_item(SystemTrayMenuItem::Separator)
+        .add_item(CustomMenuItem::new(SHOW_ITEM, "Show esp-helm"))
+        .add_item(CustomMenuItem::new(QUIT_ITEM, "Quit"));
+    SystemTray::new().with_menu(menu).with_tooltip("esp-helm")
+}
+
+fn show_window(app: &AppHandle) {
+    if let Some(window) = app.get_window("main") {
+        let _ = window.show();
+        let _ = window.unminimize();
+        let _ = window.set_focus();
+    }
+}
+
+// Monitor of the port used by last deploy, output goes to the main window
+fn open_monitor(app: &AppHandle) {
+    let (last, window) = {
+        let state_mutex = app.state::<Mutex<AppState>>();
+        let state = state_mutex.lock().unwrap();
+        (state.last_deploy.clone(), app.get_window("main"))
+    };
+    let (Some(last), Some(window)) = (last, window) else {
+        info!("No deployed project to monitor");
+        show_window(app);
+        return;
+    };
+    show_window(app);
+    let job_app = app.clone();
+    spawn_job(
+        app,
+        OperationKind::Monitor,
+        &format!("Monitor {}", last.port),
+        vec![],
+        async move {
+            monitor_session(
+                window,
+                job_app.clone(),
+                last.port,
+                None,
+                Some(PathBuf::from(last.project)),
+            )
+            .await
+            .map(|_| "Monitoring finished".to_string())
+        },
+    );
+}
+
+fn flash_last(app: &AppHandle) {
+    let Some(window) = app.get_window("main") else {
+        return;
+    };
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = flash_last_project(window, app).await {
+            info!("Flash of last project failed: {}", e);
+        }
+    });
+}
+
+pub fn handle_tray_event(app: &AppHandle, event: SystemTrayEvent) {
+    match event {
+        SystemTrayEvent::LeftClick { .. } => show_window(app),
+        SystemTrayEvent::MenuItemClick { id, .. } => match id.as_str() {
+            FLASH_ITEM => flash_last(app),
+            MONITOR_ITEM => open_monitor(app),
+            // Built-in updater checks and shows its dialog on this event
+            UPDATES_ITEM => app.trigger_global("tauri://update", None),
+            SHOW_ITEM => show_window(app),
+            QUIT_ITEM => app.exit(0),
+            _ => {}
+        },
+        _ => {}
+    }
+}
+
+// Closing the window hides it, so device watching and queued jobs keep running
+pub fn handle_window_event(event: GlobalWindowEvent) {
+    if let WindowEvent::CloseRequested { api, .. } = event.event() {
+        if event.window().label() == "main" {
+            let _ = event.window().hide();
+            api.prevent_close();
+        }
+    }
+}
+
+// Tooltip summarizes job queue, e.g. "esp-helm: 1 running, 2 queued"
+pub fn update_tray_tooltip(app: &AppHandle) {
+    let (running, queued, failed) = {
+        let state_mutex = app.state::<Mutex<AppState>>();
+        let state = state_mutex.lock().unwrap();
+        let count = |matches: fn(&JobStatus) -> bool| {
+            state
+                .scheduler
+                .jobs
+                .values()
+                .filter(|job| matches(&job.status))
+                .count()
+        };
+        (
+            count(|status| matches!(status, JobStatus::Running)),
+            count(|status| matches!(status, JobStatus::Queued)),
+            count(|status| matches!(status, JobStatus::Failed(_))),
+        )
+    };
+    let mut parts = vec![];
+    if running > 0 {
+        parts.push(format!("{} running", running));
+    }
+    if queued > 0 {
+        parts.push(format!("{} queued", queued));
+    }
+    if failed > 0 {
+        parts.push(format!("{} failed", failed));
+    }
+    let tooltip = if parts.is_empty() {
+        "esp-helm: idle".to_string()
+    } else {
+        format!("esp-helm: {}", parts.join(", "))
+    };
+    let _ = app.tray_handle().set_tooltip(&tooltip);
+}
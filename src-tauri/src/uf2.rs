@@ -0,0 +1,221 @@
+use std::path::{Path, PathBuf};
+
+use log::info;
+use sysinfo::{DiskExt, System, SystemExt};
+use tauri::{AppHandle, Window};
+
+use crate::cargo_tools::cargo_bin;
+use crate::error::{HelmError, HelmResult};
+use crate::external_command::run_external_command_with_progress;
+
+const UF2_MAGIC_START0: u32 = 0x0A32_4655;
+const UF2_MAGIC_START1: u32 = 0x9E5D_5157;
+const UF2_MAGIC_END: u32 = 0x0AB1_6F30;
+const UF2_FLAG_FAMILY_ID: u32 = 0x0000_2000;
+const UF2_BLOCK_SIZE: usize = 512;
+const UF2_PAYLOAD_SIZE: usize = 256;
+// UF2 bootloaders put this file to the root of their mass storage drive
+const UF2_INFO_FILE: &str = "INFO_UF2.TXT";
+
+#[derive(serde::Serialize)]
+pub struct Uf2Drive {
+    mount_point: String,
+    // Content of INFO_UF2.TXT, contains bootloader and board name
+    info: String,
+}
+
+// Family IDs registered in https://github.com/microsoft/uf2/blob/master/utils/uf2families.json
+fn family_id(chip: &str) -> Option<u32> {
+    match chip {
+        "esp32" => Some(0x1C5F_21B0),
+        "esp32s2" => Some(0xBFDD_4EEE),
+        "esp32s3" => Some(0xC47E_5767),
+        "esp32c2" => Some(0x2B88_D29C),
+        "esp32c3" => Some(0xD42B_A06C),
+        "esp32c6" => Some(0x540D_DF62),
+        "esp32h2" => Some(0x3327_26F6),
+        _ => None,
+    }
+}
+
+// Each 512 byte block carries 256 bytes of data, address is relative to app partition
+pub fn to_uf2(data: &[u8], family_id: u32) -> Vec<u8> {
+    let blocks = data.len().div_ceil(UF2_PAYLOAD_SIZE);
+    let mut uf2 = Vec::with_capacity(blocks * UF2_BLOCK_SIZE);
+    for (index, chunk) in data.chunks(UF2_PAYLOAD_SIZE).enumerate() {
+        let mut block = [0u8; UF2_BLOCK_SIZE];
+        let header = [
+            UF2_MAGIC_START0,
+            UF2_MAGIC_START1,
+            UF2_FLAG_FAMILY_ID,
+            (index * UF2_PAYLOAD_SIZE) as u32,
+            UF2_PAYLOAD_SIZE as u32,
+            index as u32,
+            blocks as u32,
+            family_id,
+        ];
+        for (position, word) in header.iter().enumerate() {
+            block[position * 4..position * 4 + 4].copy_from_slice(&word.to_le_bytes());
+        }
+        block[32..32 + chunk.len()].copy_from_slice(chunk);
+        block[UF2_BLOCK_SIZE - 4..].copy_from_slice(&UF2_MAGIC_END.to_le_bytes());
+        uf2.extend_from_slice(&block);
+    }
+    uf2
+}
+
+// ELF is converted to application image by espflash, binary is used as is
+async fn app_image(
+    window: Window,
+    app: AppHandle,
+    image: &Path,
+    chip: &str,
+) -> HelmResult<Vec<u8>> {
+    let data = std::fs::read(image)?;
+    if !data.starts_with(b"\x7fELF") {
+        return Ok(data);
+    }
+    let bin = image.with_extension("app.bin");
+    let espflash = cargo_bin("espflash")?.to_string_lossy().to_string();
+    run_external_command_with_progress(
+        window,
+        app,
+        &espflash,
+        &[
+            "save-image",
+            "--chip",
+            chip,
+            &image.to_string_lossy(),
+            &bin.to_string_lossy(),
+        ],
+        "PROGRESS_EVENT",
+    )
+    .await?;
+    Ok(std::fs::read(bin)?)
+}
+
+fn find_uf2_drives() -> Vec<Uf2Drive> {
+    let mut sys = System::new();
+    sys.refresh_disks_list();
+    sys.disks()
+        .iter()
+        .filter_map(|disk| {
+            let info = std::fs::read_to_string(disk.mount_point().join(UF2_INFO_FILE)).ok()?;
+            Some(Uf2Drive {
+                mount_point: disk.mount_point().to_string_lossy().to_string(),
+                info,
+            })
+        })
+        .collect()
+}
+
+// Command to list mass storage drives of boards in UF2 bootloader mode
+#[tauri::command]
+pub async fn list_uf2_drives() -> HelmResult<Vec<Uf2Drive>> {
+    Ok(find_uf2_drives())
+}
+
+// Command to convert ELF or application binary to UF2 file next to the image
+#[tauri::command]
+pub async fn convert_to_uf2(
+    window: Window,
+    app: AppHandle,
+    image: String,
+    chip: String,
+) -> HelmResult<String> {
+    let family_id =
+        family_id(&chip).ok_or(HelmError::Validation(format!("Unknown chip {}", chip)))?;
+    let image = PathBuf::from(image);
+    let data = app_image(window, app, &image, &chip).await?;
+    let output = image.with_extension("uf2");
+    std::fs::write(&output, to_uf2(&data, family_id))?;
+    Ok(output.to_string_lossy().to_string())
+}
+
+// Command to convert image and copy it to UF2 drive, board flashes and restarts by itself.
+// Without drive the only connected UF2 drive is used.
+#[tauri::command]
+pub async fn flash_uf2(
+    window: Window,
+    app: AppHandle,
+    image: String,
+    chip: String,
+    drive: Option<String>,
+) -> HelmResult<String> {
+    let drive = match drive {
+        Some(drive) => PathBuf::from(drive),
+        None => {
+            let drives = find_uf2_drives();
+            match drives.as_slice() {
+                [drive] => PathBuf::from(&drive.mount_point),
+                [] => return Err(HelmError::NotFound("UF2 drive".into())),
+                _ => {
+                    return Err(HelmError::Validation(
+                        "Multiple UF2 drives connected, select one".into(),
+                    ))
+                }
+            }
+        }
+    };
+    if !drive.join(UF2_INFO_FILE).exists() {
+        return Err(HelmError::Validation(format!(
+            "{} is not a UF2 drive",
+            drive.display()
+        )));
+    }
+
+    let uf2 = convert_to_uf2(window, app, image, chip).await?;
+    info!("Copying {} to {}", uf2, drive.display());
+    std::fs::copy(&uf2, drive.join("firmware.uf2"))?;
+    Ok(format!("Firmware copied to {}", drive.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(block: &[u8], index: usize) -> u32 {
+        u32::from_le_bytes(block[index * 4..index * 4 + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn splits_image_to_blocks() {
+        let data: Vec<u8> = (0..600u32).map(|byte| byte as u8).collect();
+        let uf2 = to_uf2(&data, 0xD42B_A06C);
+        assert_eq!(uf2.len(), 3 * UF2_BLOCK_SIZE);
+
+        for (index, block) in uf2.chunks(UF2_BLOCK_SIZE).enumerate() {
+            assert_eq!(word(block, 0), UF2_MAGIC_START0);
+            assert_eq!(word(block, 1), UF2_MAGIC_START1);
+            assert_eq!(word(block, 2), UF2_FLAG_FAMILY_ID);
+            assert_eq!(word(block, 3), (index * UF2_PAYLOAD_SIZE) as u32);
+            assert_eq!(word(block, 4), UF2_PAYLOAD_SIZE as u32);
+            assert_eq!(word(block, 5), index as u32);
+            assert_eq!(word(block, 6), 3);
+            assert_eq!(word(block, 7), 0xD42B_A06C);
+            assert_eq!(word(block, UF2_BLOCK_SIZE / 4 - 1), UF2_MAGIC_END);
+        }
+
+        // Last block is padded with zeros
+        let last = &uf2[2 * UF2_BLOCK_SIZE..];
+        assert_eq!(&last[32..32 + 88], &data[512..]);
+        assert!(last[32 + 88..UF2_BLOCK_SIZE - 4]
+            .iter()
+            .all(|byte| *byte == 0));
+        assert!(to_uf2(&[], 0).is_empty());
+    }
+
+    #[test]
+    fn family_ids_are_unique() {
+        let ids: Vec<u32> = crate::verify::CHIPS
+            .iter()
+            .filter_map(|chip| family_id(chip))
+            .collect();
+        let mut unique = ids.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(ids.len(), unique.len());
+        assert_eq!(family_id("esp32s3"), Some(0xC47E_5767));
+        assert_eq!(family_id("rp2040"), None);
+    }
+}
@@ -0,0 +1,251 @@
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use log::info;
+use tauri::{AppHandle, Manager, State};
+
+use crate::app_state::AppState;
+use crate::cleanup::compare_versions;
+use crate::error::{HelmError, HelmResult};
+use crate::events::{emit_event_all, UpdatesAvailable};
+use crate::inventory::collect_inventory;
+use crate::release_metadata::{tool_releases, ToolReleases, RELEASES_TTL, RELEASE_SOURCES};
+use crate::rust::{tool_version, tool_version_xtensa};
+use crate::services::services;
+use crate::settings::save_settings;
+
+pub const DEFAULT_CHECK_INTERVAL_HOURS: u64 = 24;
+// First check waits so it does not compete with startup work
+const STARTUP_DELAY: Duration = Duration::from_secs(2 * 60);
+// Scheduler wakes up this often and checks whether interval elapsed
+const SCHEDULER_TICK: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Clone, PartialEq, serde::Serialize, schemars::JsonSchema)]
+pub struct AvailableUpdate {
+    tool: String,
+    installed: String,
+    latest: String,
+    release_url: Option<String>,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn version_numbers(version: &str) -> Vec<u64> {
+    version
+        .split(|c: char| !c.is_ascii_digit())
+        .filter_map(|part| part.parse().ok())
+        .collect()
+}
+
+// Release candidates and betas are not offered as updates
+fn is_stable(tag: &str) -> bool {
+    !tag.contains('-')
+}
+
+// Newest stable release accepted by filter, releases are sorted newest first by GitHub
+// but tags of maintenance branches may be published later, so they are compared
+fn newest_release<'a>(
+    releases: &'a ToolReleases,
+    accept: impl Fn(&str) -> bool,
+) -> Option<(&'a str, Option<&'a str>)> {
+    releases
+        .releases()
+        .iter()
+        .filter(|release| is_stable(release.tag()) && accept(release.tag()))
+        .max_by(|a, b| compare_versions(a.tag(), b.tag()))
+        .map(|release| (release.tag(), release.html_url()))
+}
+
+fn update_for(tool: &str, installed: &str, releases: &ToolReleases) -> Option<AvailableUpdate> {
+    let (latest, url) = newest_release(releases, |_| true)?;
+    if compare_versions(latest, installed).is_le() {
+        return None;
+    }
+    Some(AvailableUpdate {
+        tool: tool.to_string(),
+        installed: installed.to_string(),
+        latest: latest.to_string(),
+        release_url: url.map(str::to_string),
+    })
+}
+
+// Only patch releases of the same major.minor, moving ESP-IDF to new minor is a migration
+fn idf_patch_update(installed: &str, releases: &ToolReleases) -> Option<AvailableUpdate> {
+    let series = version_numbers(installed);
+    if series.len() < 2 {
+        return None;
+    }
+    let (latest, url) = newest_release(releases, |tag| {
+        version_numbers(tag).get(..2) == Some(&series[..2])
+    })?;
+    if compare_versions(latest, installed).is_le() {
+        return None;
+    }
+    Some(AvailableUpdate {
+        tool: "esp-idf".to_string(),
+        installed: installed.to_string(),
+        latest: latest.to_string(),
+        release_url: url.map(str::to_string),
+    })
+}
+
+pub async fn check_updates(app: &AppHandle, max_age: Duration) -> Vec<AvailableUpdate> {
+    let adopted = {
+        let state_mutex = app.state::<Mutex<AppState>>();
+        let state = state_mutex.lock().unwrap();
+        state.settings.adopted_installations.clone()
+    };
+    let process = services(app).process;
+    let installed = tauri::async_runtime::spawn_blocking(move || {
+        let process = process.as_ref();
+        let idf_versions: Vec<String> = collect_inventory(&adopted)
+            .into_iter()
+            .filter(|item| item.kind == "esp-idf")
+            .filter_map(|item| item.version)
+            .collect();
+        (
+            tool_version(process, "espup", &["--version"], Some("espup")),
+            tool_version(process, "espflash", &["--version"], Some("espflash")),
+            tool_version_xtensa(process, "rustc", &["+esp", "--version"], Some("rustc")),
+            idf_versions,
+        )
+    })
+    .await;
+    let Ok((espup, espflash, toolchain, idf_versions)) = installed else {
+        return vec![];
+    };
+
+    let requests = RELEASE_SOURCES
+        .iter()
+        .filter(|(tool, _)| *tool != "esp-helm")
+        .map(|(tool, repo)| tool_releases(app, tool, repo, max_age));
+    let all_releases = futures::future::join_all(requests).await;
+
+    let mut updates = vec![];
+    for releases in &all_releases {
+        let update = match releases.tool() {
+            "espup" => espup
+                .as_deref()
+                .and_then(|installed| update_for("espup", installed, releases)),
+            "espflash" => espflash
+                .as_deref()
+                .and_then(|installed| update_for("espflash", installed, releases)),
+            "esp-toolchain" => toolchain
+                .as_deref()
+                .and_then(|installed| update_for("esp-toolchain", installed, releases)),
+            "esp-idf" => {
+                updates.extend(
+                    idf_versions
+                        .iter()
+                        .filter_map(|installed| idf_patch_update(installed, releases)),
+                );
+                None
+            }
+            _ => None,
+        };
+        updates.extend(update);
+    }
+    updates
+}
+
+// Stores result and emits updates-available when the set of updates changed,
+// the same summary is not repeated on every check
+async fn run_check(app: &AppHandle, max_age: Duration) -> UpdatesAvailable {
+    let updates = check_updates(app, max_age).await;
+    let summary = UpdatesAvailable {
+        updates,
+        checked_at: now(),
+    };
+    let changed = {
+        let state_mutex = app.state::<Mutex<AppState>>();
+        let mut state = state_mutex.lock().unwrap();
+        let changed = !matches!(
+            &state.available_updates,
+            Some(previous) if previous.updates == summary.updates
+        );
+        state.available_updates = Some(summary.clone());
+        changed
+    };
+    info!("Update check found {} updates", summary.updates.len());
+    if changed && !summary.updates.is_empty() {
+        emit_event_all(app, &summary);
+    }
+    summary
+}
+
+fn check_interval(app: &AppHandle) -> Option<Duration> {
+    let state_mutex = app.state::<Mutex<AppState>>();
+    let state = state_mutex.lock().unwrap();
+    match state
+        .settings
+        .update_check_interval_hours
+        .unwrap_or(DEFAULT_CHECK_INTERVAL_HOURS)
+    {
+        0 => None,
+        hours => Some(Duration::from_secs(hours * 60 * 60)),
+    }
+}
+
+// Checks for new releases in background for the whole session, nothing is installed
+pub fn start_update_scheduler(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(STARTUP_DELAY).await;
+        let mut last_check: Option<u64> = None;
+        loop {
+            if let Some(interval) = check_interval(&app) {
+                let due = match last_check {
+                    Some(last) => now().saturating_sub(last) >= interval.as_secs(),
+                    None => true,
+                };
+                if due {
+                    run_check(&app, RELEASES_TTL).await;
+                    last_check = Some(now());
+                }
+            }
+            tokio::time::sleep(SCHEDULER_TICK).await;
+        }
+    });
+}
+
+// Command to get result of the last background check, None before first check finished
+#[tauri::command]
+pub async fn get_available_updates(
+    state_mutex: State<'_, Mutex<AppState>>,
+) -> HelmResult<Option<UpdatesAvailable>> {
+    let state = state_mutex.lock().unwrap();
+    Ok(state.available_updates.clone())
+}
+
+// Command to check for updates immediately, release lists are fetched again
+#[tauri::command]
+pub async fn check_for_updates(app: AppHandle) -> HelmResult<UpdatesAvailable> {
+    Ok(run_check(&app, Duration::ZERO).await)
+}
+
+// Command to set hours between background checks, 0 disables them
+#[tauri::command]
+pub async fn set_update_check_interval(
+    state_mutex: State<'_, Mutex<AppState>>,
+    hours: u64,
+) -> HelmResult<String> {
+    if hours > 24 * 30 {
+        return Err(HelmError::Validation(
+            "Update check interval must be at most 30 days".into(),
+        ));
+    }
+    let mut state = state_mutex.lock().unwrap();
+    let mut settings = state.settings.clone();
+    settings.update_check_interval_hours = Some(hours);
+    save_settings(&settings)?;
+    state.settings = settings;
+    if hours == 0 {
+        Ok("Background update checks disabled".into())
+    } else {
+        Ok(format!("Updates are checked every {} hours", hours))
+    }
+}
@@ -0,0 +1,196 @@
+use std::path::Path;
+
+use log::info;
+use tauri::{AppHandle, Window};
+use tokio::fs;
+
+use crate::cargo_tools::cargo_bin;
+use crate::error::{HelmError, HelmResult};
+use crate::events::{emit_event, HelmEvent};
+use crate::external_command::run_external_command_with_progress;
+
+pub const ALL_CHIPS: [&str; 7] = [
+    "esp32", "esp32s2", "esp32s3", "esp32c2", "esp32c3", "esp32c6", "esp32h2",
+];
+
+#[derive(Clone, serde::Serialize, schemars::JsonSchema)]
+pub struct TargetVerification {
+    chip: String,
+    target: String,
+    success: bool,
+    error: Option<HelmError>,
+}
+
+#[derive(Clone, serde::Serialize, schemars::JsonSchema)]
+pub struct VerificationReport {
+    targets: Vec<TargetVerification>,
+}
+
+impl HelmEvent for VerificationReport {
+    const NAMES: &'static [&'static str] = &["verification-report"];
+}
+
+// Chips known to chip_target
+pub const CHIPS: [&str; 7] = [
+    "esp32", "esp32s2", "esp32s3", "esp32c2", "esp32c3", "esp32c6", "esp32h2",
+];
+
+// Toolchain, Rust target and address of GPIO_OUT_W1TS register used by blinky
+pub fn chip_target(chip: &str) -> Option<(&'static str, &'static str, u32)> {
+    match chip {
+        "esp32" => Some(("esp", "xtensa-esp32-none-elf", 0x3FF4_4008)),
+        "esp32s2" => Some(("esp", "xtensa-esp32s2-none-elf", 0x3F40_4008)),
+        "esp32s3" => Some(("esp", "xtensa-esp32s3-none-elf", 0x6000_4008)),
+        "esp32c2" | "esp32c3" => Some(("nightly", "riscv32imc-unknown-none-elf", 0x6000_4008)),
+        "esp32c6" | "esp32h2" => Some(("nightly", "riscv32imac-unknown-none-elf", 0x6009_1008)),
+        _ => None,
+    }
+}
+
+const CARGO_TOML: &str = r#"[package]
+name = "blinky"
+version = "0.1.0"
+edition = "2021"
+
+[lib]
+crate-type = ["staticlib"]
+path = "src/lib.rs"
+
+[profile.release]
+panic = "abort"
+"#;
+
+// Blinky without HAL, so verification does not need network access for dependencies
+fn blinky_source(gpio_out_w1ts: u32) -> String {
+    format!(
+        r#"#![no_std]
+
+const GPIO_OUT_W1TS: *mut u32 = 0x{:08X} as *mut u32;
+const GPIO_OUT_W1TC: *mut u32 = 0x{:08X} as *mut u32;
+
+#[no_mangle]
+pub extern "C" fn blink(pin: u32) -> ! {{
+    loop {{
+        unsafe {{ core::ptr::write_volatile(GPIO_OUT_W1TS, 1 << pin) }};
+        for _ in 0..1_000_000 {{
+            core::hint::spin_loop();
+        }}
+        unsafe {{ core::ptr::write_volatile(GPIO_OUT_W1TC, 1 << pin) }};
+        for _ in 0..1_000_000 {{
+            core::hint::spin_loop();
+        }}
+    }}
+}}
+
+#[panic_handler]
+fn panic(_info: &core::panic::PanicInfo) -> ! {{
+    loop {{}}
+}}
+"#,
+        gpio_out_w1ts,
+        gpio_out_w1ts + 4
+    )
+}
+
+async fn create_project(dir: &Path, gpio_out_w1ts: u32) -> HelmResult<()> {
+    fs::create_dir_all(dir.join("src")).await?;
+    fs::write(dir.join("Cargo.toml"), CARGO_TOML).await?;
+    fs::write(dir.join("src").join("lib.rs"), blinky_source(gpio_out_w1ts)).await?;
+    Ok(())
+}
+
+async fn verify_chip(window: Window, app: AppHandle, chip: &str) -> TargetVerification {
+    let Some((toolchain, target, gpio)) = chip_target(chip) else {
+        return TargetVerification {
+            chip: chip.to_string(),
+            target: String::new(),
+            success: false,
+            error: Some(HelmError::Validation(format!("Unknown chip {}", chip))),
+        };
+    };
+
+    info!("Verifying {} ({})", chip, target);
+    let result = async {
+        let dir = std::env::temp_dir().join("esp-helm-verify").join(chip);
+        create_project(&dir, gpio).await?;
+        let cargo = cargo_bin("cargo")?.to_string_lossy().to_string();
+        let manifest = dir.join("Cargo.toml").to_string_lossy().to_string();
+        let toolchain = format!("+{}", toolchain);
+        let mut args = vec![
+            toolchain.as_str(),
+            "build",
+            "--release",
+            "--manifest-path",
+            &manifest,
+            "--target",
+            target,
+        ];
+        // Xtensa targets have no prebuilt core library
+        if target.starts_with("xtensa") {
+            args.push("-Zbuild-std=core");
+        }
+        run_external_command_with_progress(window, app, &cargo, &args, "PROGRESS_EVENT").await
+    }
+    .await;
+
+    TargetVerification {
+        chip: chip.to_string(),
+        target: target.to_string(),
+        success: result.is_ok(),
+        error: result.err(),
+    }
+}
+
+pub async fn verify_targets(
+    window: Window,
+    app: AppHandle,
+    chips: Vec<String>,
+) -> Vec<TargetVerification> {
+    let chips = if chips.is_empty() {
+        ALL_CHIPS.iter().map(|chip| chip.to_string()).collect()
+    } else {
+        chips
+    };
+
+    let mut report = vec![];
+    for chip in &chips {
+        report.push(verify_chip(window.clone(), app.clone(), chip).await);
+    }
+    let event = VerificationReport {
+        targets: report.clone(),
+    };
+    emit_event(&window, &event);
+    report
+}
+
+// Verification step of installation, report is sent as event and failure is returned as error
+pub async fn verify_installation(
+    window: Window,
+    app: AppHandle,
+    chips: Vec<String>,
+) -> HelmResult<String> {
+    let report = verify_targets(window, app, chips).await;
+    let failed: Vec<&str> = report
+        .iter()
+        .filter(|result| !result.success)
+        .map(|result| result.chip.as_str())
+        .collect();
+    if failed.is_empty() {
+        Ok(format!("Verified {} targets", report.len()))
+    } else {
+        Err(HelmError::Validation(format!(
+            "Test build failed for {}",
+            failed.join(", ")
+        )))
+    }
+}
+
+// Command to build test project for each chip and report per-target result
+#[tauri::command]
+pub async fn verify_rust_installation(
+    window: Window,
+    app: AppHandle,
+    chips: Option<Vec<String>>,
+) -> HelmResult<Vec<TargetVerification>> {
+    Ok(verify_targets(window, app, chips.unwrap_or_default()).await)
+}
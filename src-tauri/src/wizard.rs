@@ -0,0 +1,128 @@
+use std::path::PathBuf;
+
+use log::info;
+
+use crate::error::{HelmError, HelmResult};
+use crate::portable::app_config_dir;
+
+// Steps of the first-run onboarding in the order they are executed
+#[derive(Clone, Copy, Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum WizardStep {
+    #[default]
+    Detect,
+    ChooseChips,
+    ChooseComponents,
+    Install,
+    Verify,
+    Done,
+}
+
+impl WizardStep {
+    fn next(self) -> Self {
+        match self {
+            WizardStep::Detect => WizardStep::ChooseChips,
+            WizardStep::ChooseChips => WizardStep::ChooseComponents,
+            WizardStep::ChooseComponents => WizardStep::Install,
+            WizardStep::Install => WizardStep::Verify,
+            WizardStep::Verify | WizardStep::Done => WizardStep::Done,
+        }
+    }
+}
+
+// Progress of onboarding persisted to disk, so it can be resumed after crash or restart
+#[derive(Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct WizardState {
+    // Step which is not completed yet
+    pub step: WizardStep,
+    pub chips: Vec<String>,
+    pub components: Vec<String>,
+}
+
+fn wizard_path() -> Option<PathBuf> {
+    app_config_dir().map(|dir| dir.join("wizard.json"))
+}
+
+pub fn load_wizard_state() -> WizardState {
+    let Some(path) = wizard_path() else {
+        return WizardState::default();
+    };
+
+    match std::fs::read_to_string(&path) {
+        Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+            info!("Unable to parse {}: {}", path.display(), e);
+            WizardState::default()
+        }),
+        Err(_) => WizardState::default(),
+    }
+}
+
+fn save_wizard_state(state: &WizardState) -> HelmResult<()> {
+    let path = wizard_path().ok_or(HelmError::NotFound("config directory".into()))?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let content = serde_json::to_string_pretty(state)
+        .map_err(|e| HelmError::Other(format!("Failed to serialize wizard state: {}", e)))?;
+    std::fs::write(&path, content)?;
+    Ok(())
+}
+
+// Command to get onboarding state, the frontend resumes from returned step
+#[tauri::command]
+pub async fn get_wizard_state() -> HelmResult<WizardState> {
+    Ok(load_wizard_state())
+}
+
+// Command to mark step as completed and move to the next one.
+// Selection of chips and components is stored with the step which chose them.
+#[tauri::command]
+pub async fn complete_wizard_step(
+    step: WizardStep,
+    chips: Option<Vec<String>>,
+    components: Option<Vec<String>>,
+) -> HelmResult<WizardState> {
+    let mut state = load_wizard_state();
+    if state.step != step {
+        return Err(HelmError::Validation(format!(
+            "Step {:?} can't be completed, current step is {:?}",
+            step, state.step
+        )));
+    }
+
+    match step {
+        WizardStep::ChooseChips => {
+            state.chips = chips
+                .filter(|chips| !chips.is_empty())
+                .ok_or(HelmError::Validation(
+                    "At least one chip must be selected".into(),
+                ))?;
+        }
+        WizardStep::ChooseComponents => {
+            state.components =
+                components
+                    .filter(|c| !c.is_empty())
+                    .ok_or(HelmError::Validation(
+                        "At least one component must be selected".into(),
+                    ))?;
+        }
+        _ => {}
+    }
+
+    state.step = step.next();
+    info!(
+        "Onboarding step {:?} completed, next {:?}",
+        step, state.step
+    );
+    save_wizard_state(&state)?;
+    Ok(state)
+}
+
+// Command to start onboarding from the beginning
+#[tauri::command]
+pub async fn reset_wizard() -> HelmResult<WizardState> {
+    let state = WizardState::default();
+    save_wizard_state(&state)?;
+    Ok(state)
+}
@@ -0,0 +1,185 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use log::info;
+use tauri::{AppHandle, Window};
+
+use crate::deploy::elf_path;
+use crate::error::{HelmError, HelmResult};
+use crate::external_command::run_external_command_in_dir;
+use crate::package_manager::find_in_path;
+
+#[cfg(windows)]
+use std::os::windows::process::CommandExt;
+#[cfg(windows)]
+const CREATE_NO_WINDOW: u32 = 0x08000000; // Windows specific constant to hide console window
+
+// wokwi-cli needs API token from https://wokwi.com/dashboard/ci
+const TOKEN_VARIABLE: &str = "WOKWI_CLI_TOKEN";
+
+#[derive(serde::Serialize)]
+pub struct WokwiStatus {
+    path: Option<String>,
+    version: Option<String>,
+    token_set: bool,
+}
+
+#[derive(serde::Serialize)]
+pub struct WokwiFiles {
+    config: String,
+    diagram: Option<String>,
+}
+
+// Install script puts the binary to ~/.wokwi/bin
+fn find_wokwi_cli() -> Option<PathBuf> {
+    find_in_path("wokwi-cli").or_else(|| {
+        let name = format!("wokwi-cli{}", std::env::consts::EXE_SUFFIX);
+        dirs::home_dir()
+            .map(|home| home.join(".wokwi").join("bin").join(name))
+            .filter(|path| path.exists())
+    })
+}
+
+fn wokwi_cli_version(path: &Path) -> Option<String> {
+    let mut cmd = Command::new(path);
+    cmd.arg("--version");
+
+    #[cfg(windows)]
+    cmd.creation_flags(CREATE_NO_WINDOW);
+
+    let output = cmd.output().ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout.split_whitespace().last().map(str::to_string)
+}
+
+pub fn get_wokwi_status() -> WokwiStatus {
+    let path = find_wokwi_cli();
+    WokwiStatus {
+        version: path.as_deref().and_then(wokwi_cli_version),
+        path: path.map(|path| path.to_string_lossy().to_string()),
+        token_set: std::env::var_os(TOKEN_VARIABLE).is_some(),
+    }
+}
+
+// Development board used in diagram, Wokwi has no ESP32-C2 board
+fn wokwi_board(chip: &str) -> Option<&'static str> {
+    match chip {
+        "esp32" => Some("board-esp32-devkit-c-v4"),
+        "esp32s2" => Some("board-esp32-s2-devkitm-1"),
+        "esp32s3" => Some("board-esp32-s3-devkitc-1"),
+        "esp32c3" => Some("board-esp32-c3-devkitm-1"),
+        "esp32c6" => Some("board-esp32-c6-devkitc-1"),
+        "esp32h2" => Some("board-esp32-h2-devkitm-1"),
+        _ => None,
+    }
+}
+
+fn diagram(board: &str) -> String {
+    let diagram = serde_json::json!({
+        "version": 1,
+        "author": "esp-helm",
+        "editor": "wokwi",
+        "parts": [
+            { "type": board, "id": "esp", "top": 0, "left": 0, "attrs": {} }
+        ],
+        "connections": [
+            ["esp:TX", "$serialMonitor:RX", "", []],
+            ["esp:RX", "$serialMonitor:TX", "", []]
+        ],
+        "serialMonitor": { "display": "terminal" }
+    });
+    serde_json::to_string_pretty(&diagram).unwrap() + "\n"
+}
+
+// Command to write wokwi.toml and diagram.json, existing diagram is kept as user may have edited it
+#[tauri::command]
+pub async fn generate_wokwi_config(
+    project_path: String,
+    chip: String,
+    release: Option<bool>,
+) -> HelmResult<WokwiFiles> {
+    let project = PathBuf::from(&project_path);
+    let board = wokwi_board(&chip).ok_or(HelmError::Validation(format!(
+        "{} is not supported by Wokwi",
+        chip
+    )))?;
+    let elf = elf_path(&project, release.unwrap_or(true))?;
+    let elf = elf.strip_prefix(&project).unwrap_or(&elf);
+    // wokwi.toml paths are relative to project and use forward slashes on all platforms
+    let elf = elf.to_string_lossy().replace('\\', "/");
+
+    let config = project.join("wokwi.toml");
+    std::fs::write(
+        &config,
+        format!(
+            "[wokwi]\nversion = 1\nelf = \"{}\"\nfirmware = \"{}\"\n",
+            elf, elf
+        ),
+    )?;
+
+    let diagram_path = project.join("diagram.json");
+    let diagram_written = if diagram_path.exists() {
+        false
+    } else {
+        std::fs::write(&diagram_path, diagram(board))?;
+        true
+    };
+    info!("Wokwi configuration written to {}", project_path);
+
+    Ok(WokwiFiles {
+        config: config.to_string_lossy().to_string(),
+        diagram: diagram_written.then(|| diagram_path.to_string_lossy().to_string()),
+    })
+}
+
+// Command to run simulation, "cli" runs wokwi-cli, "vscode" opens project in VS Code with Wokwi extension
+#[tauri::command]
+pub async fn launch_wokwi(
+    window: Window,
+    app: AppHandle,
+    project_path: String,
+    mode: String,
+    timeout_ms: Option<u64>,
+) -> HelmResult<String> {
+    let project = PathBuf::from(&project_path);
+    if !project.join("wokwi.toml").exists() {
+        return Err(HelmError::NotFound(format!(
+            "wokwi.toml in {}",
+            project_path
+        )));
+    }
+
+    match mode.as_str() {
+        "cli" => {
+            if std::env::var_os(TOKEN_VARIABLE).is_none() {
+                return Err(HelmError::Validation(format!(
+                    "{} is not set",
+                    TOKEN_VARIABLE
+                )));
+            }
+            let wokwi_cli = find_wokwi_cli().ok_or(HelmError::NotFound("wokwi-cli".into()))?;
+            let timeout = timeout_ms.unwrap_or(30_000).to_string();
+            run_external_command_in_dir(
+                window,
+                app,
+                Some(&project),
+                &wokwi_cli.to_string_lossy(),
+                &["--timeout", &timeout, "."],
+                "PROGRESS_EVENT",
+            )
+            .await
+        }
+        "vscode" => {
+            // VS Code on Windows is started by code.cmd
+            let code = find_in_path("code")
+                .or_else(|| find_in_path("code.cmd"))
+                .ok_or(HelmError::NotFound("VS Code".into()))?;
+            Command::new(code).arg(&project).spawn()?;
+            Ok("Run \"Wokwi: Start Simulator\" in VS Code".into())
+        }
+        _ => Err(HelmError::Validation(format!(
+            "Unknown simulation mode {}",
+            mode
+        ))),
+    }
+}
@@ -0,0 +1,201 @@
+use std::process::Command;
+
+use log::info;
+use tauri::{AppHandle, Window};
+
+use crate::error::{HelmError, HelmResult};
+use crate::external_command::run_external_command_with_progress;
+
+#[cfg(windows)]
+use std::os::windows::process::CommandExt;
+#[cfg(windows)]
+const CREATE_NO_WINDOW: u32 = 0x08000000; // Windows specific constant to hide console window
+
+#[derive(serde::Serialize)]
+pub struct WslDistro {
+    name: String,
+    state: String,
+    version: u8,
+    default: bool,
+}
+
+#[derive(serde::Serialize)]
+pub struct UsbDevice {
+    busid: String,
+    vid_pid: String,
+    description: String,
+    state: String,
+}
+
+// Linux part of installation executed inside of the distro
+const WSL_INSTALL_SCRIPT: &str = "set -e; \
+    if ! command -v rustup >/dev/null; then \
+    curl --proto '=https' --tlsv1.2 -sSf https://sh.rustup.rs | sh -s -- -y; fi; \
+    . \"$HOME/.cargo/env\"; \
+    cargo install espup; \
+    espup install";
+
+fn ensure_windows() -> HelmResult<()> {
+    if cfg!(windows) {
+        Ok(())
+    } else {
+        Err(HelmError::Validation(
+            "WSL is available only on Windows".into(),
+        ))
+    }
+}
+
+fn run_windows_tool(command: &str, args: &[&str]) -> HelmResult<Vec<u8>> {
+    let mut cmd = Command::new(command);
+    cmd.args(args);
+
+    #[cfg(windows)]
+    cmd.creation_flags(CREATE_NO_WINDOW);
+
+    let output = cmd.output().map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => HelmError::NotFound(command.to_string()),
+        _ => e.into(),
+    })?;
+    if !output.status.success() {
+        return Err(HelmError::child_process_failed(
+            output.status.code(),
+            &String::from_utf8_lossy(&output.stderr),
+        ));
+    }
+    Ok(output.stdout)
+}
+
+// wsl.exe writes UTF-16LE to redirected output
+fn decode_wsl_output(bytes: &[u8]) -> String {
+    let words: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .collect();
+    String::from_utf16_lossy(&words).replace('\0', "")
+}
+
+// Output of "wsl -l -v" has form:
+//   NAME      STATE           VERSION
+// * Ubuntu    Running         2
+fn parse_distros(output: &str) -> Vec<WslDistro> {
+    output
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let default = line.trim_start().starts_with('*');
+            let mut fields = line.trim_start_matches([' ', '*']).split_whitespace();
+            Some(WslDistro {
+                name: fields.next()?.to_string(),
+                state: fields.next()?.to_string(),
+                version: fields.next()?.parse().ok()?,
+                default,
+            })
+        })
+        .collect()
+}
+
+// Command to list WSL distributions
+#[tauri::command]
+pub async fn list_wsl_distros() -> HelmResult<Vec<WslDistro>> {
+    ensure_windows()?;
+    let output = run_windows_tool("wsl.exe", &["-l", "-v"])?;
+    Ok(parse_distros(&decode_wsl_output(&output)))
+}
+
+// Command to run Linux installation of Rust support inside of WSL distribution
+#[tauri::command]
+pub async fn install_rust_support_wsl(
+    window: Window,
+    app: AppHandle,
+    distro: String,
+) -> HelmResult<String> {
+    ensure_windows()?;
+    info!("Installing Rust support in WSL distro {}", distro);
+    run_external_command_with_progress(
+        window,
+        app,
+        "wsl.exe",
+        &["-d", &distro, "--", "bash", "-lc", WSL_INSTALL_SCRIPT],
+        "PROGRESS_EVENT",
+    )
+    .await?;
+    Ok(format!("Rust support installed in {}", distro))
+}
+
+// Output of "usbipd list" has form:
+// BUSID  VID:PID    DEVICE                                   STATE
+// 1-3    303a:1001  USB Serial Device (COM5), USB JTAG/se... Not shared
+fn parse_usb_devices(output: &str) -> Vec<UsbDevice> {
+    output
+        .lines()
+        .skip_while(|line| !line.starts_with("BUSID"))
+        .skip(1)
+        .take_while(|line| !line.trim().is_empty())
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let busid = fields.next()?.to_string();
+            let vid_pid = fields.next()?.to_string();
+            let rest: Vec<&str> = fields.collect();
+            // State is one of "Not shared", "Shared", "Attached"
+            let state_len = match rest.last() {
+                Some(&"shared") => 2,
+                _ => 1,
+            };
+            let split = rest.len().saturating_sub(state_len);
+            Some(UsbDevice {
+                busid,
+                vid_pid,
+                description: rest[..split].join(" "),
+                state: rest[split..].join(" "),
+            })
+        })
+        .collect()
+}
+
+fn usbipd(args: &[&str]) -> HelmResult<String> {
+    match run_windows_tool("usbipd", args) {
+        Ok(output) => Ok(String::from_utf8_lossy(&output).to_string()),
+        Err(HelmError::NotFound(_)) => Err(HelmError::NotFound(
+            "usbipd-win, install it by: winget install usbipd".into(),
+        )),
+        Err(e) => Err(e),
+    }
+}
+
+// Command to list USB devices which can be forwarded to WSL
+#[tauri::command]
+pub async fn list_usbipd_devices() -> HelmResult<Vec<UsbDevice>> {
+    ensure_windows()?;
+    Ok(parse_usb_devices(&usbipd(&["list"])?))
+}
+
+// Command to forward USB device to WSL, so it can be flashed from Linux.
+// Binding requires administrator rights, so it is requested via elevated PowerShell.
+#[tauri::command]
+pub async fn attach_usb_to_wsl(busid: String, distro: Option<String>) -> HelmResult<String> {
+    ensure_windows()?;
+
+    let devices = parse_usb_devices(&usbipd(&["list"])?);
+    let device = devices
+        .iter()
+        .find(|device| device.busid == busid)
+        .ok_or(HelmError::NotFound(format!("USB device {}", busid)))?;
+
+    if device.state == "Not shared" {
+        info!("Binding USB device {}", busid);
+        let bind = format!(
+            "Start-Process usbipd -ArgumentList 'bind','--busid','{}' -Verb RunAs -Wait",
+            busid
+        );
+        run_windows_tool("powershell", &["-NoProfile", "-Command", &bind])?;
+    }
+
+    info!("Attaching USB device {} to WSL", busid);
+    let mut args = vec!["attach", "--wsl", "--busid", busid.as_str()];
+    if let Some(distro) = &distro {
+        args.push("--distribution");
+        args.push(distro);
+    }
+    usbipd(&args)?;
+    Ok(format!("{} attached to WSL", busid))
+}
@@ -9,12 +9,12 @@ use walkdir::{DirEntry, WalkDir};
 use zip::result::ZipError;
 use zip::write::FileOptions;
 
-use std::sync::Mutex;
-use tauri::{Manager, Window};
+use tauri::Window;
 
 use log::info;
 
-use crate::app_state::{AppState, BuilderState};
+use crate::long_path::long_path;
+use crate::operations::is_aborted;
 
 #[derive(Clone, serde::Serialize)]
 struct Payload {
@@ -25,7 +25,6 @@ const PROGRESS_EVENT: &str = "progress";
 
 pub fn zip_dir(
     window: Window,
-    app: tauri::AppHandle,
     src_dir: &str,
     dst_file: &str,
     _method: zip::CompressionMethod,
@@ -43,7 +42,6 @@ pub fn zip_dir(
 
     zip_iter(
         window,
-        app,
         &mut src_it.filter_map(|e| e.ok()),
         src_dir,
         archive_file,
@@ -52,15 +50,8 @@ pub fn zip_dir(
     Ok(())
 }
 
-fn is_abort_state(app: tauri::AppHandle) -> bool {
-    let state_mutex = app.state::<Mutex<AppState>>();
-    let state = state_mutex.lock().unwrap();
-    matches!(state.builder, BuilderState::Abort)
-}
-
 fn zip_iter<T>(
     _window: Window,
-    app: tauri::AppHandle,
     it: &mut dyn Iterator<Item = DirEntry>,
     prefix: &str,
     writer: T,
@@ -76,7 +67,7 @@ where
 
     let mut buffer = Vec::new();
     for entry in it {
-        if is_abort_state(app.clone()) {
+        if is_aborted() {
             info!("Aborted");
             return Ok(());
         }
@@ -107,19 +98,14 @@ where
     Result::Ok(())
 }
 
-pub fn unzip(
-    _window: Window,
-    app: tauri::AppHandle,
-    file_path: String,
-    output_directory: String,
-) -> Result<(), ZipError> {
+pub fn unzip(_window: Window, file_path: String, output_directory: String) -> Result<(), ZipError> {
     let file_name = std::path::Path::new(&file_path);
     let file = fs::File::open(file_name).unwrap();
 
     let mut archive = zip::ZipArchive::new(file).unwrap();
 
     for i in 0..archive.len() {
-        if is_abort_state(app.clone()) {
+        if is_aborted() {
             info!("Aborted");
             return Ok(());
         }
@@ -135,9 +121,8 @@ pub fn unzip(
         // Skip the first component (the top level directory)
         components.next();
 
-        // Add path prefix to extract the file
-        let mut outpath = std::path::PathBuf::new();
-        outpath.push(&output_directory);
+        // Add path prefix to extract the file, ESP-IDF contains paths longer than MAX_PATH
+        let mut outpath = long_path(std::path::Path::new(&output_directory));
 
         // Append the rest of the components
         for component in components {